@@ -76,6 +76,7 @@ async fn main() -> std::io::Result<()> {
         Commands::Dkim(command) => command.exec(client).await,
         Commands::Queue(command) => command.exec(client).await,
         Commands::Report(command) => command.exec(client).await,
+        Commands::DeliveryHook(command) => command.exec(client).await,
     }
 
     Ok(())
@@ -4,11 +4,12 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use super::dkim::Algorithm;
+use super::{delivery_hook::HookSource, dkim::Algorithm};
 use clap::{Parser, Subcommand, ValueEnum};
 use jmap_client::client::Credentials;
 use mail_parser::DateTime;
 use serde::Deserialize;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[clap(version, about, long_about = None)]
@@ -72,6 +73,10 @@ pub enum Commands {
     /// Manage SMTP DMARC/TLS report queue
     #[clap(subcommand)]
     Report(ReportCommands),
+
+    /// Test delivery hooks and inspect their audit trail
+    #[clap(subcommand)]
+    DeliveryHook(DeliveryHookCommands),
 }
 
 pub struct Client {
@@ -375,6 +380,42 @@ pub enum DkimCommands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum DeliveryHookCommands {
+    /// Call the delivery hooks on a stored or local message without delivering it
+    Test {
+        /// Account name
+        account: String,
+        /// Id of a stored email to replay
+        #[clap(short, long)]
+        email_id: Option<String>,
+        /// Path to the message to test
+        #[clap(short, long)]
+        file: Option<PathBuf>,
+        /// Message source to simulate
+        #[clap(short, long)]
+        #[clap(value_enum)]
+        source: Option<HookSource>,
+        /// Hooks to call, defaults to the hooks enabled for the source
+        #[clap(long = "hook")]
+        hooks: Vec<String>,
+        /// Envelope sender
+        #[clap(long)]
+        from: Option<String>,
+        /// Envelope recipient
+        #[clap(long)]
+        to: Option<String>,
+    },
+
+    /// Show the delivery hooks called for a stored email
+    Audit {
+        /// Account name
+        account: String,
+        /// Email id
+        email_id: String,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum ImportCommands {
     /// Import messages and folders
@@ -0,0 +1,243 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{
+    UnwrapResult,
+    cli::{Client, DeliveryHookCommands},
+};
+use clap::ValueEnum;
+use mail_parser::DateTime;
+use prettytable::{Attr, Cell, Row, Table};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HookSource {
+    /// Local delivery of a message received over SMTP
+    #[default]
+    Smtp,
+    /// Local delivery of a message filed by a Sieve script
+    Sieve,
+    /// IMAP APPEND
+    Imap,
+    /// JMAP Email/import
+    Jmap,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TestRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    source: HookSource,
+    hooks: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    env_from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    env_rcpt_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DryRun {
+    action: String,
+    mailbox_ids: Vec<String>,
+    #[serde(default)]
+    would_create: Vec<String>,
+    flags: Vec<String>,
+    skip_inbox: bool,
+    preview_text: Option<String>,
+    redirects: Vec<String>,
+    expires_in: Option<u64>,
+    added_headers: Vec<(String, String)>,
+    removed_headers: Vec<(String, String)>,
+    body_changed: bool,
+    hooks: Vec<HookEntry>,
+    #[serde(default)]
+    skipped_hooks: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Audit {
+    mailbox_ids: Vec<String>,
+    runs: Vec<AuditRun>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuditRun {
+    source: String,
+    is_async: bool,
+    timestamp: u64,
+    hooks: Vec<HookEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HookEntry {
+    hook_id: String,
+    elapsed_ms: u64,
+    action: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    modifications: Vec<String>,
+    #[serde(default)]
+    mailbox_ids: Vec<String>,
+    #[serde(default)]
+    flags: Vec<String>,
+}
+
+impl DeliveryHookCommands {
+    pub async fn exec(self, client: Client) {
+        match self {
+            DeliveryHookCommands::Test {
+                account,
+                email_id,
+                file,
+                source,
+                hooks,
+                from,
+                to,
+            } => {
+                let message = match (&email_id, file) {
+                    (None, Some(file)) => Some(
+                        String::from_utf8_lossy(
+                            &std::fs::read(&file).unwrap_result("read message file"),
+                        )
+                        .into_owned(),
+                    ),
+                    (Some(_), None) => None,
+                    _ => {
+                        eprintln!("Either an email id or a message file is required.");
+                        std::process::exit(1);
+                    }
+                };
+
+                let result = client
+                    .http_request::<DryRun, _>(
+                        Method::POST,
+                        &format!("/api/delivery-hooks/{account}/test"),
+                        Some(TestRequest {
+                            email_id,
+                            message,
+                            source: source.unwrap_or_default(),
+                            hooks,
+                            env_from: from,
+                            env_rcpt_to: to,
+                        }),
+                    )
+                    .await;
+
+                let mut table = Table::new();
+                add_row(&mut table, "Action", &result.action);
+                add_row(&mut table, "Mailbox Ids", &result.mailbox_ids.join(", "));
+                if !result.would_create.is_empty() {
+                    add_row(&mut table, "Would Create", &result.would_create.join(", "));
+                }
+                add_row(&mut table, "Flags", &result.flags.join(", "));
+                add_row(&mut table, "Skip Inbox", &result.skip_inbox.to_string());
+                if let Some(preview_text) = &result.preview_text {
+                    add_row(&mut table, "Preview", preview_text);
+                }
+                if !result.redirects.is_empty() {
+                    add_row(&mut table, "Redirects", &result.redirects.join(", "));
+                }
+                if let Some(expires_in) = result.expires_in {
+                    add_row(&mut table, "Expires In", &format!("{expires_in}s"));
+                }
+                for (name, value) in &result.added_headers {
+                    add_row(&mut table, "+ Header", &format!("{name}: {value}"));
+                }
+                for (name, value) in &result.removed_headers {
+                    add_row(&mut table, "- Header", &format!("{name}: {value}"));
+                }
+                add_row(&mut table, "Body Changed", &result.body_changed.to_string());
+                if !result.skipped_hooks.is_empty() {
+                    add_row(
+                        &mut table,
+                        "Skipped Hooks",
+                        &result.skipped_hooks.join(", "),
+                    );
+                }
+
+                eprintln!();
+                table.printstd();
+                eprintln!();
+                hooks_table(&result.hooks).printstd();
+                eprintln!();
+            }
+            DeliveryHookCommands::Audit { account, email_id } => {
+                let audit = client
+                    .http_request::<Audit, String>(
+                        Method::GET,
+                        &format!("/api/delivery-hooks/{account}/{email_id}"),
+                        None,
+                    )
+                    .await;
+
+                eprintln!();
+                eprintln!("Mailbox Ids: {}", audit.mailbox_ids.join(", "));
+                if audit.runs.is_empty() {
+                    eprintln!("No delivery hooks were called for this message.");
+                }
+                for run in &audit.runs {
+                    eprintln!();
+                    eprintln!(
+                        "{} ({}{})",
+                        DateTime::from_timestamp(run.timestamp as i64).to_rfc822(),
+                        run.source,
+                        if run.is_async { ", async" } else { "" }
+                    );
+                    hooks_table(&run.hooks).printstd();
+                }
+                eprintln!();
+            }
+        }
+    }
+}
+
+fn add_row(table: &mut Table, name: &str, value: &str) {
+    table.add_row(Row::new(vec![
+        Cell::new(name).with_style(Attr::Bold),
+        Cell::new(value),
+    ]));
+}
+
+fn hooks_table(hooks: &[HookEntry]) -> Table {
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        [
+            "Hook",
+            "Action",
+            "Elapsed",
+            "Mailboxes",
+            "Flags",
+            "Modifications",
+        ]
+        .iter()
+        .map(|p| Cell::new(p).with_style(Attr::Bold))
+        .collect(),
+    ));
+    for hook in hooks {
+        table.add_row(Row::new(vec![
+            Cell::new(&hook.hook_id),
+            Cell::new(&match &hook.error {
+                Some(error) => format!("{} ({error})", hook.action),
+                None => hook.action.clone(),
+            }),
+            Cell::new(&format!("{}ms", hook.elapsed_ms)),
+            Cell::new(&hook.mailbox_ids.join("\n")),
+            Cell::new(&hook.flags.join("\n")),
+            Cell::new(&hook.modifications.join("\n")),
+        ]));
+    }
+    table
+}
@@ -49,6 +49,7 @@ impl Server {
         let mut tenant_id = None;
         let mut quota = None;
         let mut locale = None;
+        let mut undo_send_delay = None;
         let mut member_of = Vec::new();
         let mut emails = Vec::new();
         for data in principal.data {
@@ -84,6 +85,7 @@ impl Server {
                     emails.push(v);
                 }
                 PrincipalData::Locale(v) => locale = Some(v),
+                PrincipalData::UndoSendDelay(v) => undo_send_delay = Some(v),
                 _ => (),
             }
         }
@@ -156,6 +158,7 @@ impl Server {
             emails,
             quota: quota.unwrap_or_default(),
             locale,
+            undo_send_delay,
             permissions,
             object_quota,
             concurrent_imap_requests: self.core.imap.rate_concurrent.map(ConcurrencyLimiter::new),
@@ -23,6 +23,7 @@ pub mod oauth;
 pub mod rate_limit;
 pub mod roles;
 pub mod sasl;
+pub mod scram;
 
 #[derive(Debug, Default)]
 pub struct AccessToken {
@@ -32,6 +33,7 @@ pub struct AccessToken {
     pub name: String,
     pub description: Option<String>,
     pub locale: Option<String>,
+    pub undo_send_delay: Option<u64>,
     pub emails: Vec<String>,
     pub quota: u64,
     pub object_quota: [u32; Collection::MAX],
@@ -61,6 +63,7 @@ pub struct AuthRequest<'x> {
     credentials: Credentials<String>,
     session_id: u64,
     remote_ip: IpAddr,
+    peer_uid: Option<u32>,
     return_member_of: bool,
     allow_api_access: bool,
     directory: Option<&'x Directory>,
@@ -115,6 +118,9 @@ impl Server {
                     SpanId = req.session_id,
                 );
 
+                self.upgrade_scram_verifier(req, directory, &principal)
+                    .await;
+
                 return Ok(principal);
             }
             Ok(None) => Ok(()),
@@ -226,25 +232,34 @@ impl Server {
 
         if let Err(err) = result {
             Err(err)
-        } else if self.has_auth_fail2ban() {
-            let login = req.credentials.login();
-            if self.is_auth_fail2banned(req.remote_ip, login).await? {
-                Err(trc::SecurityEvent::AuthenticationBan
+        } else {
+            Err(self
+                .authentication_failed(req.remote_ip, req.peer_uid, req.credentials.login())
+                .await)
+        }
+    }
+
+    pub(crate) async fn authentication_failed(
+        &self,
+        remote_ip: IpAddr,
+        peer_uid: Option<u32>,
+        login: Option<&str>,
+    ) -> trc::Error {
+        if self.has_auth_fail2ban() {
+            match self.is_auth_fail2banned(remote_ip, peer_uid, login).await {
+                Ok(true) => trc::SecurityEvent::AuthenticationBan
                     .into_err()
-                    .ctx(trc::Key::RemoteIp, req.remote_ip)
-                    .ctx_opt(trc::Key::AccountName, login.map(|s| s.to_string())))
-            } else {
-                Err(trc::AuthEvent::Failed
-                    .ctx(trc::Key::RemoteIp, req.remote_ip)
-                    .ctx_opt(trc::Key::AccountName, login.map(|s| s.to_string())))
+                    .ctx(trc::Key::RemoteIp, remote_ip)
+                    .ctx_opt(trc::Key::AccountName, login.map(|s| s.to_string())),
+                Ok(false) => trc::AuthEvent::Failed
+                    .ctx(trc::Key::RemoteIp, remote_ip)
+                    .ctx_opt(trc::Key::AccountName, login.map(|s| s.to_string())),
+                Err(err) => err,
             }
         } else {
-            Err(trc::AuthEvent::Failed
-                .ctx(trc::Key::RemoteIp, req.remote_ip)
-                .ctx_opt(
-                    trc::Key::AccountName,
-                    req.credentials.login().map(|s| s.to_string()),
-                ))
+            trc::AuthEvent::Failed
+                .ctx(trc::Key::RemoteIp, remote_ip)
+                .ctx_opt(trc::Key::AccountName, login.map(|s| s.to_string()))
         }
     }
 }
@@ -259,6 +274,7 @@ impl<'x> AuthRequest<'x> {
             credentials,
             session_id,
             remote_ip,
+            peer_uid: None,
             return_member_of: true,
             directory: None,
             allow_api_access: false,
@@ -295,6 +311,11 @@ impl<'x> AuthRequest<'x> {
         self.allow_api_access = allow_api_access;
         self
     }
+
+    pub fn with_peer_uid(mut self, peer_uid: Option<u32>) -> Self {
+        self.peer_uid = peer_uid;
+        self
+    }
 }
 
 impl CacheItemWeight for AccessToken {
@@ -26,6 +26,9 @@ pub enum GrantType {
     LiveMetrics,
     Troubleshoot,
     Rsvp,
+    ListSubscribe,
+    ListUnsubscribe,
+    QuarantineRelease,
 }
 
 impl GrantType {
@@ -37,6 +40,9 @@ impl GrantType {
             GrantType::LiveMetrics => "live_metrics",
             GrantType::Troubleshoot => "troubleshoot",
             GrantType::Rsvp => "rsvp",
+            GrantType::ListSubscribe => "list_subscribe",
+            GrantType::ListUnsubscribe => "list_unsubscribe",
+            GrantType::QuarantineRelease => "quarantine_release",
         }
     }
 
@@ -48,6 +54,9 @@ impl GrantType {
             GrantType::LiveMetrics => 3,
             GrantType::Troubleshoot => 4,
             GrantType::Rsvp => 5,
+            GrantType::ListSubscribe => 6,
+            GrantType::ListUnsubscribe => 7,
+            GrantType::QuarantineRelease => 8,
         }
     }
 
@@ -59,7 +68,21 @@ impl GrantType {
             3 => Some(GrantType::LiveMetrics),
             4 => Some(GrantType::Troubleshoot),
             5 => Some(GrantType::Rsvp),
+            6 => Some(GrantType::ListSubscribe),
+            7 => Some(GrantType::ListUnsubscribe),
+            8 => Some(GrantType::QuarantineRelease),
             _ => None,
         }
     }
+
+    /// Tokens handed out to people without an account, which are not bound to a password
+    pub fn is_anonymous(&self) -> bool {
+        matches!(
+            self,
+            GrantType::Rsvp
+                | GrantType::ListSubscribe
+                | GrantType::ListUnsubscribe
+                | GrantType::QuarantineRelease
+        )
+    }
 }
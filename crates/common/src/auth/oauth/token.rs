@@ -5,13 +5,14 @@
  */
 
 use super::{CLIENT_ID_MAX_LEN, GrantType, RANDOM_CODE_LEN, crypto::SymmetricEncrypt};
-use crate::Server;
+use crate::{KV_TOKEN_REVOCATION, Server};
 use directory::{PrincipalData, QueryParams};
 use mail_builder::encoders::base64::base64_encode;
 use mail_parser::decoders::base64::base64_decode;
 use std::time::SystemTime;
 use store::{
     blake3,
+    dispatch::lookup::KeyValue,
     rand::{Rng, rng},
 };
 use trc::AddContext;
@@ -39,7 +40,7 @@ impl Server {
         // Build context
         let mut password_hash = String::new();
 
-        if !matches!(grant_type, GrantType::Rsvp) {
+        if !grant_type.is_anonymous() {
             if client_id.len() > CLIENT_ID_MAX_LEN {
                 return Err(trc::AuthEvent::Error
                     .into_err()
@@ -156,7 +157,7 @@ impl Server {
         }
 
         // Obtain password hash
-        let password_hash = if !matches!(grant_type, GrantType::Rsvp) && expiry - issued_at > 3600 {
+        let password_hash = if !grant_type.is_anonymous() && expiry - issued_at > 3600 {
             self.password_hash(account_id)
                 .await
                 .map_err(|err| trc::AuthEvent::Error.into_err().ctx(trc::Key::Details, err))?
@@ -204,6 +205,23 @@ impl Server {
                     .reason(err)
             })?;
 
+        // Reject tokens issued before the account was locked out
+        if self.core.smtp.sending_limits.enable
+            && self.core.smtp.sending_limits.revoke_tokens
+            && let Some(revoked_at) = self
+                .in_memory_store()
+                .key_get::<i64>(KeyValue::<()>::build_key(
+                    KV_TOKEN_REVOCATION,
+                    account_id.to_be_bytes(),
+                ))
+                .await?
+            && issued_at + OAUTH_EPOCH <= revoked_at as u64
+        {
+            return Err(trc::AuthEvent::TokenExpired
+                .into_err()
+                .details("Token has been revoked"));
+        }
+
         // Success
         Ok(TokenInfo {
             grant_type,
@@ -230,12 +248,11 @@ impl Server {
                 })?
                 .data
                 .into_iter()
-                .filter_map(|v| {
-                    if let PrincipalData::Password(secret) = v {
+                .filter_map(|v| match v {
+                    PrincipalData::Password(secret) | PrincipalData::ScramVerifier(secret) => {
                         Some(secret)
-                    } else {
-                        None
                     }
+                    _ => None,
                 })
                 .next()
                 .ok_or(
@@ -7,8 +7,9 @@
 use std::net::IpAddr;
 
 use crate::{
-    KV_RATE_LIMIT_HTTP_ANONYMOUS, KV_RATE_LIMIT_HTTP_AUTHENTICATED, Server, ip_to_bytes,
+    KV_RATE_LIMIT_HTTP_ANONYMOUS, KV_RATE_LIMIT_HTTP_AUTHENTICATED, Server,
     listener::limiter::{InFlight, LimiterResult},
+    peer_to_bytes,
 };
 use directory::Permission;
 use trc::AddContext;
@@ -56,7 +57,11 @@ impl Server {
         }
     }
 
-    pub async fn is_http_anonymous_request_allowed(&self, addr: &IpAddr) -> trc::Result<()> {
+    pub async fn is_http_anonymous_request_allowed(
+        &self,
+        addr: &IpAddr,
+        peer_uid: Option<u32>,
+    ) -> trc::Result<()> {
         if let Some(rate) = &self.core.jmap.rate_anonymous
             && !self.is_ip_allowed(addr)
             && self
@@ -65,7 +70,7 @@ impl Server {
                 .lookup
                 .is_rate_allowed(
                     KV_RATE_LIMIT_HTTP_ANONYMOUS,
-                    &ip_to_bytes(addr),
+                    &peer_to_bytes(addr, peer_uid),
                     rate,
                     false,
                 )
@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use base64::{Engine, engine::general_purpose::STANDARD};
 use mail_send::Credentials;
 
 pub fn sasl_decode_challenge_plain(challenge: &[u8]) -> Option<Credentials<String>> {
@@ -34,6 +35,10 @@ pub fn sasl_decode_challenge_oauth(challenge: &[u8]) -> Option<Credentials<Strin
     extract_oauth_bearer(challenge).map(|s| Credentials::OAuthBearer { token: s.into() })
 }
 
+pub fn sasl_encode_challenge(challenge: impl AsRef<[u8]>) -> String {
+    STANDARD.encode(challenge)
+}
+
 fn extract_oauth_bearer(bytes: &[u8]) -> Option<&str> {
     let mut start_pos = 0;
     let eof = bytes.len().saturating_sub(1);
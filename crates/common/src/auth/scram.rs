@@ -0,0 +1,415 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Server side of the SCRAM-SHA-256 and SCRAM-SHA-256-PLUS SASL mechanisms
+//! (RFC 5802, RFC 7677), with `tls-exporter` (RFC 9266) and `tls-server-end-point`
+//! (RFC 5929) channel bindings.
+
+use std::{
+    net::IpAddr,
+    sync::{Arc, LazyLock},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use directory::{
+    Directory, DirectoryInner, Principal, PrincipalData, QueryParams,
+    backend::internal::{
+        PrincipalField, PrincipalUpdate, PrincipalValue,
+        manage::{ManageDirectory, UpdatePrincipal},
+    },
+    core::scram::{ScramVerifier, hmac_sha256},
+};
+use mail_send::Credentials;
+use ring::digest;
+use x509_parser::parse_x509_certificate;
+
+use crate::{Server, listener::TlsChannelBinding};
+
+use super::{AccessToken, AuthRequest};
+
+static FAKE_SALT_KEY: LazyLock<[u8; 32]> = LazyLock::new(store::rand::random);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelBindingType {
+    TlsExporter,
+    TlsServerEndPoint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelBindingFlag {
+    /// `n`: the client does not support channel binding
+    None,
+    /// `y`: the client supports channel binding but thinks the server does not
+    Supported,
+    /// `p=`: the client requires channel binding
+    Required(ChannelBindingType),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScramClientFirst {
+    pub gs2_header: String,
+    pub channel_binding: ChannelBindingFlag,
+    pub username: String,
+    pub nonce: String,
+    pub message_bare: String,
+}
+
+/// Authentication exchange in progress, created after the client-first message
+#[derive(Debug, Clone)]
+pub struct ScramAuth {
+    client_first: ScramClientFirst,
+    nonce: String,
+    server_first: String,
+    verifier: ScramVerifier,
+    principal: Option<Principal>,
+}
+
+/// Progress of a SCRAM exchange after the client-first message
+#[derive(Debug)]
+pub enum ScramStep {
+    /// Waiting for the client-final message
+    ClientFinal(Box<ScramAuth>),
+    /// The server-final message was sent, waiting for the client to acknowledge it
+    Completed(Arc<AccessToken>),
+}
+
+impl ScramClientFirst {
+    pub fn parse(message: &[u8]) -> Option<Self> {
+        let message = std::str::from_utf8(message).ok()?;
+
+        // GS2 header
+        let (flag, rest) = message.split_once(',')?;
+        let channel_binding = match flag {
+            "n" => ChannelBindingFlag::None,
+            "y" => ChannelBindingFlag::Supported,
+            "p=tls-exporter" => ChannelBindingFlag::Required(ChannelBindingType::TlsExporter),
+            "p=tls-server-end-point" => {
+                ChannelBindingFlag::Required(ChannelBindingType::TlsServerEndPoint)
+            }
+            _ => return None,
+        };
+        let (authzid, message_bare) = rest.split_once(',')?;
+        let gs2_header = &message[..flag.len() + authzid.len() + 2];
+
+        // Client first message bare
+        let mut attributes = message_bare.split(',');
+        let username = decode_saslname(attributes.next()?.strip_prefix("n=")?)?;
+        let nonce = attributes.next()?.strip_prefix("r=")?;
+        if username.is_empty()
+            || nonce.is_empty()
+            || !nonce.bytes().all(|ch| ch.is_ascii_graphic())
+            || message_bare.contains(",m=")
+        {
+            return None;
+        }
+
+        // Authorizing as a different user is not supported
+        if !authzid.is_empty() && decode_saslname(authzid.strip_prefix("a=")?)? != username {
+            return None;
+        }
+
+        Some(Self {
+            gs2_header: gs2_header.to_string(),
+            channel_binding,
+            username,
+            nonce: nonce.to_string(),
+            message_bare: message_bare.to_string(),
+        })
+    }
+}
+
+impl ScramAuth {
+    pub fn new(
+        client_first: ScramClientFirst,
+        server_nonce: &str,
+        verifier: ScramVerifier,
+        principal: Option<Principal>,
+    ) -> Self {
+        let nonce = format!("{}{server_nonce}", client_first.nonce);
+        let server_first = format!(
+            "r={nonce},s={},i={}",
+            STANDARD.encode(&verifier.salt),
+            verifier.iterations
+        );
+
+        Self {
+            client_first,
+            nonce,
+            server_first,
+            verifier,
+            principal,
+        }
+    }
+
+    pub fn username(&self) -> &str {
+        &self.client_first.username
+    }
+
+    pub fn channel_binding(&self) -> ChannelBindingFlag {
+        self.client_first.channel_binding
+    }
+
+    pub fn server_first(&self) -> &str {
+        &self.server_first
+    }
+
+    /// Verify the client-final message and return the server-final message
+    pub fn verify(&self, client_final: &[u8], channel_binding: &[u8]) -> Option<String> {
+        let client_final = std::str::from_utf8(client_final).ok()?;
+        let (without_proof, proof) = client_final.rsplit_once(",p=")?;
+        let mut attributes = without_proof.split(',');
+
+        // Channel binding and nonce
+        let mut expected_binding = self.client_first.gs2_header.as_bytes().to_vec();
+        expected_binding.extend_from_slice(channel_binding);
+        if STANDARD
+            .decode(attributes.next()?.strip_prefix("c=")?)
+            .ok()?
+            != expected_binding
+            || attributes.next()?.strip_prefix("r=")? != self.nonce
+        {
+            return None;
+        }
+
+        let auth_message = format!(
+            "{},{},{without_proof}",
+            self.client_first.message_bare, self.server_first
+        );
+        if self
+            .verifier
+            .verify_proof(auth_message.as_bytes(), &STANDARD.decode(proof).ok()?)
+        {
+            Some(format!(
+                "v={}",
+                STANDARD.encode(self.verifier.server_signature(auth_message.as_bytes()))
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl Server {
+    /// Process the client-first message, `is_plus` is set when the client selected
+    /// SCRAM-SHA-256-PLUS and `plus_offered` when the mechanism was advertised
+    pub async fn scram_begin(
+        &self,
+        client_first: &[u8],
+        is_plus: bool,
+        plus_offered: bool,
+        directory: Option<&Directory>,
+    ) -> trc::Result<ScramAuth> {
+        let client_first = ScramClientFirst::parse(client_first).ok_or_else(|| {
+            trc::AuthEvent::Error
+                .into_err()
+                .details("Invalid SCRAM client-first message.")
+        })?;
+
+        match (client_first.channel_binding, is_plus) {
+            (ChannelBindingFlag::Required(_), true)
+            | (ChannelBindingFlag::None, false)
+            | (ChannelBindingFlag::Supported, false) => {}
+            _ => {
+                return Err(trc::AuthEvent::Error
+                    .into_err()
+                    .details("Invalid SCRAM channel binding flag."));
+            }
+        }
+        if client_first.channel_binding == ChannelBindingFlag::Supported && plus_offered {
+            // The client was led to believe channel binding is not supported
+            return Err(trc::AuthEvent::Error
+                .into_err()
+                .details("SCRAM channel binding downgrade detected."));
+        }
+
+        // Obtain the verifier, a fake one is used for unknown accounts to avoid
+        // disclosing which accounts exist
+        let directory = directory.unwrap_or(&self.core.storage.directory);
+        let principal = directory
+            .query(QueryParams::name(&client_first.username).with_return_member_of(true))
+            .await?;
+        let verifier = principal
+            .as_ref()
+            .filter(|principal| scram_allowed(principal))
+            .and_then(|principal| {
+                principal.data.iter().find_map(|data| match data {
+                    PrincipalData::ScramVerifier(verifier) => ScramVerifier::parse(verifier),
+                    _ => None,
+                })
+            });
+        let (verifier, principal) = match verifier {
+            Some(verifier) => (verifier, principal),
+            None => {
+                let salt = hmac_sha256(FAKE_SALT_KEY.as_slice(), client_first.username.as_bytes());
+                (
+                    ScramVerifier {
+                        iterations: self.core.jmap.scram_iterations,
+                        salt: salt[..16].to_vec(),
+                        stored_key: store::rand::random(),
+                        server_key: store::rand::random(),
+                    },
+                    None,
+                )
+            }
+        };
+
+        Ok(ScramAuth::new(
+            client_first,
+            &STANDARD.encode(store::rand::random::<[u8; 18]>()),
+            verifier,
+            principal,
+        ))
+    }
+
+    /// Verify the client-final message, returns the access token and the server-final message
+    pub async fn scram_finish(
+        &self,
+        auth: ScramAuth,
+        client_final: &[u8],
+        tls: Option<TlsChannelBinding>,
+        session_id: u64,
+        remote_ip: IpAddr,
+        peer_uid: Option<u32>,
+    ) -> trc::Result<(Arc<AccessToken>, String)> {
+        let channel_binding = match auth.channel_binding() {
+            ChannelBindingFlag::Required(typ) => tls
+                .and_then(|tls| self.channel_binding_data(tls, typ))
+                .ok_or_else(|| {
+                    trc::AuthEvent::Error
+                        .into_err()
+                        .details("SCRAM channel binding type not supported.")
+                })?,
+            _ => Vec::new(),
+        };
+
+        match (auth.verify(client_final, &channel_binding), auth.principal) {
+            (Some(server_final), Some(principal)) => {
+                trc::event!(
+                    Auth(trc::AuthEvent::Success),
+                    AccountName = principal.name().to_string(),
+                    AccountId = principal.id(),
+                    SpanId = session_id,
+                );
+
+                let access_token = self.get_access_token(principal).await?;
+                access_token.assert_has_permission(directory::Permission::Authenticate)?;
+
+                Ok((access_token, server_final))
+            }
+            _ => Err(self
+                .authentication_failed(remote_ip, peer_uid, Some(&auth.client_first.username))
+                .await),
+        }
+    }
+
+    pub fn channel_binding_data(
+        &self,
+        tls: TlsChannelBinding,
+        typ: ChannelBindingType,
+    ) -> Option<Vec<u8>> {
+        match typ {
+            ChannelBindingType::TlsExporter => tls.exporter,
+            ChannelBindingType::TlsServerEndPoint => {
+                tls_server_end_point(tls.certificate?.as_ref())
+            }
+        }
+    }
+
+    /// Store a SCRAM verifier for an account that successfully authenticated with a
+    /// cleartext password and does not have one yet
+    pub(crate) async fn upgrade_scram_verifier(
+        &self,
+        req: &AuthRequest<'_>,
+        directory: &Directory,
+        principal: &Principal,
+    ) {
+        let (Credentials::Plain { secret, .. }, DirectoryInner::Internal(store)) =
+            (&req.credentials, &directory.store)
+        else {
+            return;
+        };
+        // Skip accounts using app passwords or TOTP, as the secret might not be
+        // the account password
+        if !self.core.jmap.scram_upgrade
+            || !scram_allowed(principal)
+            || principal.data.iter().any(|data| {
+                matches!(
+                    data,
+                    PrincipalData::ScramVerifier(_) | PrincipalData::AppPassword(_)
+                )
+            })
+        {
+            return;
+        }
+
+        let verifier = ScramVerifier::generate(secret, self.core.jmap.scram_iterations);
+        match store
+            .update_principal(UpdatePrincipal::by_id(principal.id()).with_updates(vec![
+                PrincipalUpdate::add_item(
+                    PrincipalField::Secrets,
+                    PrincipalValue::String(verifier.to_string()),
+                ),
+            ]))
+            .await
+        {
+            Ok(changed_principals) => {
+                self.invalidate_principal_caches(changed_principals).await;
+            }
+            Err(err) => {
+                trc::error!(
+                    err.span_id(req.session_id)
+                        .details("Failed to store SCRAM verifier")
+                );
+            }
+        }
+    }
+}
+
+/// SCRAM cannot verify TOTP codes, accounts with TOTP enabled or without an account
+/// password or verifier must authenticate using a mechanism that goes through the
+/// secret checks
+fn scram_allowed(principal: &Principal) -> bool {
+    principal
+        .data
+        .iter()
+        .all(|data| !matches!(data, PrincipalData::OtpAuth(_)))
+        && principal.data.iter().any(|data| {
+            matches!(
+                data,
+                PrincipalData::Password(_) | PrincipalData::ScramVerifier(_)
+            )
+        })
+}
+
+/// Hash of the server certificate as defined in RFC 5929, SHA-256 is used unless the
+/// certificate is signed using SHA-384 or SHA-512
+fn tls_server_end_point(certificate: &[u8]) -> Option<Vec<u8>> {
+    let (_, parsed) = parse_x509_certificate(certificate).ok()?;
+    let algorithm = match parsed.signature_algorithm.algorithm.to_id_string().as_str() {
+        "1.2.840.113549.1.1.12" | "1.2.840.10045.4.3.3" => &digest::SHA384,
+        "1.2.840.113549.1.1.13" | "1.2.840.10045.4.3.4" => &digest::SHA512,
+        _ => &digest::SHA256,
+    };
+
+    Some(digest::digest(algorithm, certificate).as_ref().to_vec())
+}
+
+fn decode_saslname(name: &str) -> Option<String> {
+    let mut result = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '=' => match (chars.next(), chars.next()) {
+                (Some('2'), Some('C')) => result.push(','),
+                (Some('3'), Some('D')) => result.push('='),
+                _ => return None,
+            },
+            ',' => return None,
+            ch => result.push(ch),
+        }
+    }
+    Some(result)
+}
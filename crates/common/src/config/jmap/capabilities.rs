@@ -174,6 +174,7 @@ impl JmapConfig {
                     ("MT-PRIORITY".to_string(), vec!["MIXER".to_string()]),
                     ("REQUIRETLS".to_string(), vec![]),
                 ]),
+                undo_send_delay: None,
             }),
         );
 
@@ -187,6 +188,21 @@ impl JmapConfig {
             Capabilities::Empty(EmptyCapabilities::default()),
         );
 
+        // Add quarantine capabilities
+        if config
+            .property_or_default("quarantine.enable", "false")
+            .unwrap_or(false)
+        {
+            self.capabilities.session.append(
+                Capability::Quarantine,
+                Capabilities::Empty(EmptyCapabilities::default()),
+            );
+            self.capabilities.account.insert(
+                Capability::Quarantine,
+                Capabilities::Empty(EmptyCapabilities::default()),
+            );
+        }
+
         // Add Sieve capabilities
         let mut notification_methods = Vec::new();
 
@@ -6,6 +6,7 @@
 
 use crate::config::groupware::GroupwareConfig;
 use ahash::{AHashMap, AHashSet};
+use directory::core::scram::SCRAM_DEFAULT_ITERATIONS;
 use jmap_proto::request::capability::BaseCapabilities;
 use nlp::language::Language;
 use std::{str::FromStr, time::Duration};
@@ -71,12 +72,17 @@ pub struct JmapConfig {
 
     pub fallback_admin: Option<(String, String)>,
     pub master_user: Option<(String, String)>,
+    pub scram_iterations: u32,
+    pub scram_upgrade: bool,
 
     pub default_folders: Vec<DefaultFolder>,
     pub shared_folder: String,
 
     pub http_headers: Vec<(hyper::header::HeaderName, hyper::header::HeaderValue)>,
     pub http_use_forwarded: bool,
+    pub http2_enable: bool,
+    pub http2_h2c: bool,
+    pub http2_max_concurrent_streams: u32,
 
     pub encrypt: bool,
     pub encrypt_append: bool,
@@ -316,6 +322,15 @@ impl JmapConfig {
                 .property_or_default("email.encryption.append", "false")
                 .unwrap_or(false),
             http_use_forwarded: config.property("http.use-x-forwarded").unwrap_or(false),
+            http2_enable: config
+                .property_or_default("http.http2.enable", "true")
+                .unwrap_or(true),
+            http2_h2c: config
+                .property_or_default("http.http2.h2c", "false")
+                .unwrap_or(false),
+            http2_max_concurrent_streams: config
+                .property_or_default("http.http2.max-concurrent-streams", "100")
+                .unwrap_or(100),
             http_headers,
             push_attempt_interval: config
                 .property_or_default("jmap.push.attempts.interval", "1m")
@@ -350,6 +365,13 @@ impl JmapConfig {
                     .value("authentication.master.secret")
                     .map(|p| (u.to_string(), p.to_string()))
             }),
+            scram_iterations: config
+                .property_or_default::<u32>("authentication.scram.iterations", "4096")
+                .unwrap_or(SCRAM_DEFAULT_ITERATIONS)
+                .max(SCRAM_DEFAULT_ITERATIONS),
+            scram_upgrade: config
+                .property_or_default("authentication.scram.upgrade", "false")
+                .unwrap_or(false),
             contact_parse_max_items: config
                 .property("jmap.contact.parse.max-items")
                 .unwrap_or(10),
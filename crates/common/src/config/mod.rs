@@ -35,7 +35,7 @@ pub mod spamfilter;
 pub mod storage;
 pub mod telemetry;
 
-pub(crate) const CONNECTION_VARS: &[u32; 9] = &[
+pub(crate) const CONNECTION_VARS: &[u32; 12] = &[
     V_LISTENER,
     V_REMOTE_IP,
     V_REMOTE_PORT,
@@ -45,6 +45,9 @@ pub(crate) const CONNECTION_VARS: &[u32; 9] = &[
     V_TLS,
     V_ASN,
     V_COUNTRY,
+    V_PEER_UID,
+    V_PEER_GID,
+    V_PEER_PID,
 ];
 
 impl Core {
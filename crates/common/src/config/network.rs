@@ -45,6 +45,7 @@ pub struct ClusterRoles {
     pub spam_training: ClusterRole,
     pub imip_processing: ClusterRole,
     pub merge_threads: ClusterRole,
+    pub delivery_hooks: ClusterRole,
     pub calendar_alerts: ClusterRole,
     pub renew_acme: ClusterRole,
     pub calculate_metrics: ClusterRole,
@@ -90,7 +91,7 @@ pub struct FieldOrDefault {
     pub default: String,
 }
 
-pub(crate) const HTTP_VARS: &[u32; 11] = &[
+pub(crate) const HTTP_VARS: &[u32; 14] = &[
     V_LISTENER,
     V_REMOTE_IP,
     V_REMOTE_PORT,
@@ -102,6 +103,9 @@ pub(crate) const HTTP_VARS: &[u32; 11] = &[
     V_URL_PATH,
     V_HEADERS,
     V_METHOD,
+    V_PEER_UID,
+    V_PEER_GID,
+    V_PEER_PID,
 ];
 
 impl Default for Network {
@@ -261,6 +265,10 @@ impl Network {
                 &mut network.roles.merge_threads,
                 "cluster.roles.merge-threads",
             ),
+            (
+                &mut network.roles.delivery_hooks,
+                "cluster.roles.delivery-hooks",
+            ),
         ] {
             let shards = config
                 .properties::<NodeList>(key)
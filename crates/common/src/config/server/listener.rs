@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use rustls::{
     ALL_VERSIONS, ServerConfig, SupportedCipherSuite,
@@ -27,7 +27,7 @@ use crate::{
 };
 
 use super::{
-    Listener, Listeners, ServerProtocol, TcpListener,
+    Listener, Listeners, ServerProtocol, TcpListener, UnixListener,
     tls::{TLS12_VERSION, TLS13_VERSION},
 };
 
@@ -63,8 +63,24 @@ impl Listeners {
 
         // Build listeners
         let mut listeners = Vec::new();
-        for (_, addr) in config.properties::<SocketAddr>(("server.listener", id, "bind")) {
+        let mut unix_listeners = Vec::new();
+        let binds = config
+            .values(("server.listener", id, "bind"))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<Vec<_>>();
+        for (key, bind) in binds {
+            // Unix domain sockets are bound as "unix:<path>"
+            if let Some(path) = bind.strip_prefix("unix:") {
+                if let Some(listener) = parse_unix_listener(config, id, &key, path) {
+                    unix_listeners.push(listener);
+                }
+                continue;
+            }
+
             // Parse bind address and build socket
+            let Some(addr) = config.try_parse_value::<SocketAddr>(key.as_str(), &bind) else {
+                continue;
+            };
             let socket = match if addr.is_ipv4() {
                 TcpSocket::new_v4()
             } else {
@@ -169,7 +185,7 @@ impl Listeners {
             });
         }
 
-        if listeners.is_empty() {
+        if listeners.is_empty() && unix_listeners.is_empty() {
             config.new_build_error(
                 ("server.listener", id),
                 "No 'bind' directive found for listener",
@@ -204,6 +220,7 @@ impl Listeners {
             id: id_,
             protocol,
             listeners,
+            unix_listeners,
             proxy_networks,
             span_id_gen,
         });
@@ -294,6 +311,18 @@ impl Listeners {
                     )
                     .unwrap_or(true);
 
+                // Advertise HTTP/2 on HTTP listeners
+                if config
+                    .value(("server.listener", id, "protocol"))
+                    .and_then(|protocol| ServerProtocol::parse_value(protocol).ok())
+                    == Some(ServerProtocol::Http)
+                    && config
+                        .property_or_default("http.http2.enable", "true")
+                        .unwrap_or(true)
+                {
+                    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+                }
+
                 // Build acceptor
                 let default_config = Arc::new(server_config);
                 TcpAcceptor::Tls {
@@ -312,6 +341,57 @@ impl Listeners {
     }
 }
 
+fn parse_unix_listener(
+    config: &mut Config,
+    id: &str,
+    key: &str,
+    path: &str,
+) -> Option<UnixListener> {
+    if !cfg!(unix) {
+        config.new_build_error(
+            key,
+            "Unix domain sockets are not supported on this platform",
+        );
+        return None;
+    } else if path.is_empty() {
+        config.new_parse_error(key, "Missing Unix domain socket path");
+        return None;
+    }
+
+    let mode = if let Some(mode) = config.value(("server.listener", id, "unix.mode")) {
+        match u32::from_str_radix(mode, 8) {
+            Ok(mode) if mode <= 0o7777 => Some(mode),
+            _ => {
+                let err = format!("Invalid file mode {mode:?}, expected an octal value");
+                config.new_parse_error(("server.listener", id, "unix.mode"), err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    Some(UnixListener {
+        path: PathBuf::from(path),
+        backlog: config
+            .property_or_else::<Option<u32>>(
+                ("server.listener", id, "socket.backlog"),
+                "server.socket.backlog",
+                "1024",
+            )
+            .unwrap_or_default(),
+        #[cfg(unix)]
+        socket: None,
+        mode,
+        owner: config
+            .value(("server.listener", id, "unix.owner"))
+            .map(|owner| owner.to_string()),
+        group: config
+            .value(("server.listener", id, "unix.group"))
+            .map(|group| group.to_string()),
+    })
+}
+
 impl ParseValue for ServerProtocol {
     fn parse_value(value: &str) -> Result<Self, String> {
         if value.eq_ignore_ascii_case("smtp") {
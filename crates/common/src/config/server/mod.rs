@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{fmt::Display, net::SocketAddr, sync::Arc, time::Duration};
+use std::{fmt::Display, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use ahash::AHashMap;
 use serde::{Deserialize, Serialize};
@@ -28,6 +28,7 @@ pub struct Listener {
     pub id: String,
     pub protocol: ServerProtocol,
     pub listeners: Vec<TcpListener>,
+    pub unix_listeners: Vec<UnixListener>,
     pub proxy_networks: Vec<IpAddrMask>,
     pub max_connections: u64,
     pub span_id_gen: Arc<SnowflakeIdGenerator>,
@@ -45,6 +46,19 @@ pub struct TcpListener {
     pub nodelay: bool,
 }
 
+#[derive(Debug)]
+pub struct UnixListener {
+    pub path: PathBuf,
+    pub backlog: Option<u32>,
+    #[cfg(unix)]
+    pub socket: Option<tokio::net::UnixSocket>,
+
+    // File options
+    pub mode: Option<u32>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
 pub enum ServerProtocol {
     #[default]
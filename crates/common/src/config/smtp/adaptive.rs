@@ -0,0 +1,392 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Adaptive throttling of outbound deliveries
+//!
+//! Temporary failures that look like rate limiting (421 replies, enhanced status
+//! codes 4.4.5 and 4.7.x or replies containing one of `queue.adaptive.patterns`)
+//! lower the number of concurrent connections and the message rate allowed
+//! towards the destination, which are then raised step by step once the
+//! destination stops deferring messages. Destinations are grouped by the
+//! registrable domain of their MX host, so that all the MX hosts of a provider
+//! share the same limits. Limits are kept in memory and are not shared between
+//! cluster nodes.
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+use utils::config::{Config, Rate};
+
+#[derive(Debug, Clone)]
+pub struct AdaptiveThrottleConfig {
+    pub enable: bool,
+    pub concurrency_min: u32,
+    pub concurrency_max: u32,
+    /// Messages per second
+    pub rate_min: f64,
+    pub rate_max: f64,
+    /// Factor applied to the limits on every deferral
+    pub backoff: f64,
+    /// Minimum time between two consecutive decreases
+    pub cooldown: u64,
+    /// Time without deferrals before the limits are raised one step
+    pub recovery: u64,
+    /// Delay before retrying messages held back by the concurrency limit
+    pub retry: u64,
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct AdaptiveThrottle {
+    destinations: Mutex<AHashMap<String, Arc<ThrottledDestination>>>,
+}
+
+#[derive(Debug)]
+pub struct ThrottledDestination {
+    pub name: String,
+    in_flight: AtomicU32,
+    limits: Mutex<DestinationLimits>,
+}
+
+/// Limits in effect for a destination, `None` means not limited
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DestinationLimits {
+    pub concurrency: Option<u32>,
+    pub rate: Option<f64>,
+    pub deferrals: u64,
+    pub last_deferral: u64,
+    pub last_change: u64,
+    tokens: f64,
+    last_refill: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitChange {
+    Unchanged,
+    Decreased,
+    Increased,
+    Restored,
+}
+
+/// Accounts for a delivery attempt in progress, released on drop
+#[derive(Debug, Default)]
+pub struct ThrottlePermit {
+    destination: Option<Arc<ThrottledDestination>>,
+}
+
+const MAX_DESTINATIONS: usize = 4096;
+
+impl AdaptiveThrottleConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let rate_min = config
+            .property_or_default::<Rate>("queue.adaptive.rate.min", "1/1m")
+            .unwrap_or_default();
+        let rate_max = config
+            .property_or_default::<Rate>("queue.adaptive.rate.max", "60/1m")
+            .unwrap_or_default();
+        let concurrency_min = config
+            .property_or_default::<u32>("queue.adaptive.concurrency.min", "1")
+            .unwrap_or(1)
+            .max(1);
+        let mut patterns = config
+            .values("queue.adaptive.patterns")
+            .map(|(_, v)| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>();
+        if patterns.is_empty() {
+            patterns = [
+                "rate limit",
+                "too many",
+                "try again later",
+                "temporarily deferred",
+                "throttl",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        }
+
+        AdaptiveThrottleConfig {
+            enable: config
+                .property_or_default("queue.adaptive.enable", "false")
+                .unwrap_or(false),
+            concurrency_min,
+            concurrency_max: config
+                .property_or_default::<u32>("queue.adaptive.concurrency.max", "10")
+                .unwrap_or(10)
+                .max(concurrency_min),
+            rate_min: rate_per_second(&rate_min).unwrap_or(1.0 / 60.0),
+            rate_max: rate_per_second(&rate_max)
+                .unwrap_or(1.0)
+                .max(rate_per_second(&rate_min).unwrap_or(1.0 / 60.0)),
+            backoff: config
+                .property_or_default::<f64>("queue.adaptive.backoff", "0.5")
+                .unwrap_or(0.5)
+                .clamp(0.1, 0.9),
+            cooldown: config
+                .property_or_default::<Duration>("queue.adaptive.cooldown", "30s")
+                .unwrap_or(Duration::from_secs(30))
+                .as_millis() as u64,
+            recovery: config
+                .property_or_default::<Duration>("queue.adaptive.recovery", "5m")
+                .unwrap_or(Duration::from_secs(300))
+                .as_millis() as u64,
+            retry: config
+                .property_or_default::<Duration>("queue.adaptive.retry", "30s")
+                .unwrap_or(Duration::from_secs(30))
+                .as_secs(),
+            patterns,
+        }
+    }
+
+    /// Whether a temporary failure indicates that the destination is throttling us
+    pub fn is_deferral(&self, code: u16, esc: [u8; 3], message: &str) -> bool {
+        if code == 421 || (code / 100 == 4 && (esc == [4, 4, 5] || esc[..2] == [4, 7])) {
+            true
+        } else if code / 100 == 4 {
+            let message = message.to_lowercase();
+            self.patterns
+                .iter()
+                .any(|pattern| message.contains(pattern.as_str()))
+        } else {
+            false
+        }
+    }
+}
+
+impl Default for AdaptiveThrottleConfig {
+    fn default() -> Self {
+        AdaptiveThrottleConfig {
+            enable: false,
+            concurrency_min: 1,
+            concurrency_max: 10,
+            rate_min: 1.0 / 60.0,
+            rate_max: 1.0,
+            backoff: 0.5,
+            cooldown: 30_000,
+            recovery: 300_000,
+            retry: 30,
+            patterns: vec![],
+        }
+    }
+}
+
+fn rate_per_second(rate: &Rate) -> Option<f64> {
+    if rate.requests > 0 && !rate.period.is_zero() {
+        Some(rate.requests as f64 / rate.period.as_secs_f64())
+    } else {
+        None
+    }
+}
+
+impl AdaptiveThrottle {
+    /// Returns a permit for a delivery attempt or the number of milliseconds
+    /// to wait when the destination is over its limits
+    pub fn acquire(
+        &self,
+        config: &AdaptiveThrottleConfig,
+        destination: &str,
+        now: u64,
+    ) -> Result<ThrottlePermit, u64> {
+        let destination = {
+            let mut destinations = self.destinations.lock();
+            if destinations.len() >= MAX_DESTINATIONS && !destinations.contains_key(destination) {
+                destinations.retain(|_, d| Arc::strong_count(d) > 1 || d.limits().is_limited());
+            }
+            destinations
+                .entry(destination.to_string())
+                .or_insert_with(|| {
+                    Arc::new(ThrottledDestination {
+                        name: destination.to_string(),
+                        in_flight: AtomicU32::new(0),
+                        limits: Mutex::new(DestinationLimits::default()),
+                    })
+                })
+                .clone()
+        };
+
+        let in_flight = destination.in_flight.load(Ordering::Relaxed);
+        destination.limits.lock().check(config, in_flight, now)?;
+        destination.in_flight.fetch_add(1, Ordering::Relaxed);
+
+        Ok(ThrottlePermit {
+            destination: Some(destination),
+        })
+    }
+
+    pub fn limited_count(&self) -> usize {
+        self.destinations
+            .lock()
+            .values()
+            .filter(|destination| destination.limits().is_limited())
+            .count()
+    }
+
+    /// Destinations that currently have reduced limits
+    pub fn limited(&self) -> Vec<(Arc<ThrottledDestination>, DestinationLimits)> {
+        let mut limited = self
+            .destinations
+            .lock()
+            .values()
+            .filter_map(|destination| {
+                let limits = destination.limits();
+                limits.is_limited().then(|| (destination.clone(), limits))
+            })
+            .collect::<Vec<_>>();
+        limited.sort_unstable_by(|a, b| a.0.name.cmp(&b.0.name));
+        limited
+    }
+}
+
+impl ThrottledDestination {
+    pub fn limits(&self) -> DestinationLimits {
+        *self.limits.lock()
+    }
+
+    pub fn in_flight(&self) -> u32 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+}
+
+impl ThrottlePermit {
+    pub fn destination(&self) -> Option<&ThrottledDestination> {
+        self.destination.as_deref()
+    }
+
+    pub fn deferral(&self, config: &AdaptiveThrottleConfig, now: u64) -> LimitChange {
+        self.destination
+            .as_ref()
+            .map_or(LimitChange::Unchanged, |destination| {
+                let in_flight = destination.in_flight();
+                destination.limits.lock().deferral(config, in_flight, now)
+            })
+    }
+
+    pub fn success(&self, config: &AdaptiveThrottleConfig, now: u64) -> LimitChange {
+        self.destination
+            .as_ref()
+            .map_or(LimitChange::Unchanged, |destination| {
+                destination.limits.lock().recover(config, now)
+            })
+    }
+}
+
+impl Drop for ThrottlePermit {
+    fn drop(&mut self) {
+        if let Some(destination) = &self.destination {
+            destination.in_flight.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl DestinationLimits {
+    pub fn is_limited(&self) -> bool {
+        self.concurrency.is_some() || self.rate.is_some()
+    }
+
+    fn check(
+        &mut self,
+        config: &AdaptiveThrottleConfig,
+        in_flight: u32,
+        now: u64,
+    ) -> Result<(), u64> {
+        self.recover(config, now);
+
+        if let Some(concurrency) = self.concurrency
+            && in_flight >= concurrency
+        {
+            return Err(config.retry * 1000);
+        }
+
+        if let Some(rate) = self.rate {
+            let elapsed = now.saturating_sub(self.last_refill) as f64 / 1000.0;
+            self.tokens = (self.tokens + elapsed * rate).min(rate.max(1.0));
+            self.last_refill = now;
+
+            if self.tokens >= 1.0 {
+                self.tokens -= 1.0;
+            } else {
+                return Err((((1.0 - self.tokens) / rate) * 1000.0).ceil() as u64);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn deferral(
+        &mut self,
+        config: &AdaptiveThrottleConfig,
+        in_flight: u32,
+        now: u64,
+    ) -> LimitChange {
+        self.deferrals += 1;
+        self.last_deferral = now;
+
+        // Deferrals of the connections that were already open when the limits were
+        // lowered are not counted again
+        if self.is_limited() && now.saturating_sub(self.last_change) < config.cooldown {
+            return LimitChange::Unchanged;
+        }
+
+        let concurrency = match self.concurrency {
+            Some(concurrency) => (concurrency as f64 * config.backoff) as u32,
+            None => ((in_flight as f64 * config.backoff) as u32).min(config.concurrency_max),
+        }
+        .max(config.concurrency_min);
+        let rate = match self.rate {
+            Some(rate) => (rate * config.backoff).max(config.rate_min),
+            None => config.rate_max,
+        };
+        let change = if self.concurrency != Some(concurrency) || self.rate != Some(rate) {
+            LimitChange::Decreased
+        } else {
+            LimitChange::Unchanged
+        };
+
+        if self.rate.is_none() {
+            self.tokens = 1.0;
+            self.last_refill = now;
+        }
+        self.concurrency = Some(concurrency);
+        self.rate = Some(rate);
+        self.last_change = now;
+
+        change
+    }
+
+    fn recover(&mut self, config: &AdaptiveThrottleConfig, now: u64) -> LimitChange {
+        if !self.is_limited()
+            || now.saturating_sub(self.last_deferral) < config.recovery
+            || now.saturating_sub(self.last_change) < config.recovery
+        {
+            return LimitChange::Unchanged;
+        }
+
+        let concurrency = self.concurrency.map_or(config.concurrency_max, |c| {
+            (c + 1).min(config.concurrency_max)
+        });
+        let rate = self.rate.map_or(config.rate_max, |r| {
+            (r + (config.rate_max - config.rate_min) / 10.0).min(config.rate_max)
+        });
+        self.last_change = now;
+
+        if concurrency >= config.concurrency_max && rate >= config.rate_max {
+            self.concurrency = None;
+            self.rate = None;
+            LimitChange::Restored
+        } else {
+            self.concurrency = Some(concurrency);
+            self.rate = Some(rate);
+            LimitChange::Increased
+        }
+    }
+}
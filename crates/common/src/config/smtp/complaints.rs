@@ -0,0 +1,76 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Feedback loop complaint processing
+//!
+//! Messages submitted by authenticated accounts are remembered by Message-ID and
+//! queue id for `track-for`, so that abuse reports (RFC 5965) received from mailbox
+//! provider feedback loops can be traced back to the account that sent them.
+//! Complaints are counted per account and per sender domain over `period`, and
+//! accounts whose complaint rate goes above `suspend.rate` after sending at least
+//! `suspend.min-messages` messages can be suspended from sending.
+//!
+//! Reports are only trusted when received from one of the `sources` networks or
+//! when they carry a valid DKIM signature aligned with their `From` domain.
+
+use std::time::Duration;
+use utils::config::{Config, ipmask::IpAddrMask};
+
+#[derive(Debug, Clone, Default)]
+pub struct ComplaintConfig {
+    pub enable: bool,
+    pub track_for: u64,
+    pub period: u64,
+    pub sources: Vec<IpAddrMask>,
+    pub suspend: bool,
+    pub suspend_rate: f64,
+    pub suspend_min_messages: u64,
+}
+
+impl ComplaintConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        ComplaintConfig {
+            enable: config
+                .property_or_default("report.analysis.complaints.enable", "false")
+                .unwrap_or(false),
+            track_for: config
+                .property_or_default::<Duration>("report.analysis.complaints.track-for", "30d")
+                .unwrap_or(Duration::from_secs(30 * 86400))
+                .as_secs(),
+            period: config
+                .property_or_default::<Duration>("report.analysis.complaints.period", "7d")
+                .unwrap_or(Duration::from_secs(7 * 86400))
+                .as_secs()
+                .max(1),
+            sources: config
+                .properties::<IpAddrMask>("report.analysis.complaints.sources")
+                .into_iter()
+                .map(|(_, network)| network)
+                .collect(),
+            suspend: config
+                .property_or_default("report.analysis.complaints.suspend.enable", "false")
+                .unwrap_or(false),
+            suspend_rate: config
+                .property_or_default::<f64>("report.analysis.complaints.suspend.rate", "0.003")
+                .unwrap_or(0.003),
+            suspend_min_messages: config
+                .property_or_default::<u64>(
+                    "report.analysis.complaints.suspend.min-messages",
+                    "100",
+                )
+                .unwrap_or(100),
+        }
+    }
+
+    /// Whether an account with the given counts over the current period
+    /// should be suspended from sending
+    pub fn exceeds_rate(&self, sent: u64, complaints: u64) -> bool {
+        self.suspend
+            && sent >= self.suspend_min_messages
+            && sent > 0
+            && complaints as f64 / sent as f64 > self.suspend_rate
+    }
+}
@@ -9,11 +9,21 @@
 //! This module provides the configuration structure and parsing logic
 //! for delivery hooks, extending the base session configuration.
 
-use std::str::FromStr;
+use super::hook_auth::{HookSigner, parse_client_identity};
+use crate::expr::{if_block::IfBlock, tokenizer::TokenMap};
+use ahash::AHashMap;
 use base64::{Engine, engine::general_purpose::STANDARD};
 use hyper::{HeaderMap, header::{AUTHORIZATION, CONTENT_TYPE, HeaderName, HeaderValue}};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, AtomicU64, Ordering},
+};
+use std::time::Duration;
+use tokio::sync::{oneshot, watch};
 use utils::config::Config;
-use crate::expr::{if_block::IfBlock, tokenizer::TokenMap};
+use utils::config::utils::ParseValue;
 
 /// Configuration for a delivery hook
 #[derive(Clone)]
@@ -26,6 +36,297 @@ pub struct DeliveryHook {
     pub tls_allow_invalid_certs: bool,
     pub tempfail_on_error: bool,
     pub max_response_size: usize,
+    pub order: i32,
+    pub sources: Vec<DeliveryHookSource>,
+    pub include: Vec<DeliveryHookInclude>,
+    pub is_async: bool,
+    pub transport: DeliveryHookTransport,
+    pub signer: Option<HookSigner>,
+    pub client: reqwest::Client,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+/// How the enabled delivery hooks are executed for each recipient
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeliveryHookMode {
+    /// All hooks are called concurrently with the original message
+    #[default]
+    Parallel,
+    /// Hooks are called one after another in `order`, each one receiving the
+    /// message and filing decisions produced by the previous hooks. A hook filing
+    /// the message replaces the mailboxes chosen by the hooks before it.
+    Sequential,
+}
+
+impl ParseValue for DeliveryHookMode {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "parallel" => Ok(Self::Parallel),
+            "sequential" => Ok(Self::Sequential),
+            _ => Err(format!("Invalid delivery hook mode {value:?}")),
+        }
+    }
+}
+
+/// How requests are delivered to a hook, all transports share the same JSON contract
+#[derive(Clone)]
+pub enum DeliveryHookTransport {
+    /// HTTP POST to the hook URL
+    Http,
+    /// HTTP POST over a Unix domain socket
+    Unix { socket: String },
+    /// Request/reply over the configured PubSub store
+    PubSub(Arc<PubSubReplies>),
+}
+
+/// Requests published to a hook over the PubSub store that are waiting for a reply
+///
+/// Hook workers consume requests from `topic` and publish their responses, carrying
+/// the same `request_id`, to the `reply_to` topic included in each request.
+#[derive(Debug)]
+pub struct PubSubReplies {
+    pub topic: String,
+    pub reply_topic: String,
+    is_listening: tokio::sync::Mutex<bool>,
+    next_id: AtomicU64,
+    pending: parking_lot::Mutex<AHashMap<u64, oneshot::Sender<Vec<u8>>>>,
+    closed: watch::Sender<()>,
+}
+
+impl PubSubReplies {
+    pub fn new(topic: String, reply_topic: String) -> Self {
+        Self {
+            topic,
+            reply_topic,
+            is_listening: tokio::sync::Mutex::new(false),
+            next_id: AtomicU64::new(0),
+            pending: Default::default(),
+            closed: watch::Sender::new(()),
+        }
+    }
+
+    /// Returns a receiver that is notified once the hook is dropped by a configuration
+    /// reload, so the reply listener can unsubscribe
+    pub fn closed(&self) -> watch::Receiver<()> {
+        self.closed.subscribe()
+    }
+
+    /// Whether the reply topic is subscribed to, held while subscribing so that
+    /// no request is published before its reply can be received
+    pub async fn is_listening(&self) -> tokio::sync::MutexGuard<'_, bool> {
+        self.is_listening.lock().await
+    }
+
+    /// Registers a new request, returning its id and the receiver for its reply
+    pub fn register(&self) -> (u64, oneshot::Receiver<Vec<u8>>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id, tx);
+        (id, rx)
+    }
+
+    /// Removes a request that won't be waited on anymore
+    pub fn cancel(&self, id: u64) {
+        self.pending.lock().remove(&id);
+    }
+
+    /// Hands a reply to the waiting request, returns `false` if no request was waiting
+    pub fn complete(&self, id: u64, reply: Vec<u8>) -> bool {
+        self.pending
+            .lock()
+            .remove(&id)
+            .is_some_and(|tx| tx.send(reply).is_ok())
+    }
+}
+
+/// Where a message handed to the delivery hooks is coming from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryHookSource {
+    /// Local delivery of a message received over SMTP
+    #[default]
+    Smtp,
+    /// Local delivery of a message filed or rewritten by the recipient's Sieve script
+    Sieve,
+    /// IMAP APPEND
+    Imap,
+    /// JMAP Email/import, also used by the CLI import command
+    Jmap,
+}
+
+impl DeliveryHookSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Smtp => "smtp",
+            Self::Sieve => "sieve",
+            Self::Imap => "imap",
+            Self::Jmap => "jmap",
+        }
+    }
+}
+
+impl ParseValue for DeliveryHookSource {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "smtp" => Ok(Self::Smtp),
+            "sieve" => Ok(Self::Sieve),
+            "imap" => Ok(Self::Imap),
+            "jmap" => Ok(Self::Jmap),
+            _ => Err(format!("Invalid delivery hook source {value:?}")),
+        }
+    }
+}
+
+/// Optional context added to the requests sent to a hook
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryHookInclude {
+    /// Spam filter score and matched tags
+    Spam,
+    /// DKIM, SPF, DMARC and ARC results
+    AuthResults,
+    /// The recipient's mailboxes
+    Mailboxes,
+    /// Name of the recipient's active Sieve script
+    SieveScript,
+}
+
+impl ParseValue for DeliveryHookInclude {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "spam" => Ok(Self::Spam),
+            "auth-results" => Ok(Self::AuthResults),
+            "mailboxes" => Ok(Self::Mailboxes),
+            "sieve-script" => Ok(Self::SieveScript),
+            _ => Err(format!("Invalid delivery hook include {value:?}")),
+        }
+    }
+}
+
+/// Action taken for incoming messages while the circuit breaker of a hook is open
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CircuitBreakerFallback {
+    Accept,
+    #[default]
+    TempFail,
+    Quarantine,
+}
+
+impl ParseValue for CircuitBreakerFallback {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "accept" => Ok(Self::Accept),
+            "tempfail" => Ok(Self::TempFail),
+            "quarantine" => Ok(Self::Quarantine),
+            _ => Err(format!("Invalid circuit breaker fallback {value:?}")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    HalfOpen,
+    Open,
+}
+
+/// Circuit breaker settings of a delivery hook
+#[derive(Clone, Copy, Debug)]
+pub struct CircuitBreakerConfig {
+    pub threshold: u32,
+    pub cooldown: Duration,
+    pub fallback: CircuitBreakerFallback,
+}
+
+/// Circuit breakers of the delivery hooks, keyed by hook id so that their state
+/// survives configuration reloads
+#[derive(Debug, Default)]
+pub struct CircuitBreakers {
+    breakers: parking_lot::Mutex<AHashMap<String, Arc<CircuitBreaker>>>,
+}
+
+/// Stops calling a delivery hook after a number of consecutive failures
+///
+/// Once open, the hook is skipped until the cooldown period expires, after which a
+/// single probe request is let through (half-open). A successful probe closes the
+/// circuit while a failed one keeps it open for another cooldown period. A probe
+/// that does not complete within the probe timeout, for instance because the
+/// session that sent it was dropped, is replaced by a new one.
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    failures: AtomicU32,
+    opened_at: AtomicU64,
+    probe_started_at: AtomicU64,
+}
+
+impl CircuitBreakers {
+    pub fn get(&self, hook_id: &str) -> Arc<CircuitBreaker> {
+        let mut breakers = self.breakers.lock();
+        if let Some(breaker) = breakers.get(hook_id) {
+            breaker.clone()
+        } else {
+            let breaker = Arc::new(CircuitBreaker::default());
+            breakers.insert(hook_id.to_string(), breaker.clone());
+            breaker
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Returns whether a request may be sent at `now` (in seconds)
+    pub fn acquire(
+        &self,
+        config: &CircuitBreakerConfig,
+        probe_timeout: u64,
+        now: u64,
+    ) -> CircuitState {
+        let opened_at = self.opened_at.load(Ordering::Acquire);
+        if opened_at == 0 {
+            return CircuitState::Closed;
+        } else if now < opened_at.saturating_add(config.cooldown.as_secs()) {
+            return CircuitState::Open;
+        }
+
+        let probe_started_at = self.probe_started_at.load(Ordering::Acquire);
+        if (probe_started_at == 0 || now >= probe_started_at.saturating_add(probe_timeout))
+            && self
+                .probe_started_at
+                .compare_exchange(
+                    probe_started_at,
+                    now.max(1),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .is_ok()
+        {
+            CircuitState::HalfOpen
+        } else {
+            CircuitState::Open
+        }
+    }
+
+    /// Records a successful request, returns `true` if the circuit was closed
+    pub fn record_success(&self) -> bool {
+        self.failures.store(0, Ordering::Release);
+        self.probe_started_at.store(0, Ordering::Release);
+        self.opened_at.swap(0, Ordering::AcqRel) != 0
+    }
+
+    /// Records a failed request, returns `true` if the circuit was opened
+    pub fn record_failure(&self, config: &CircuitBreakerConfig, now: u64) -> bool {
+        let failures = self.failures.fetch_add(1, Ordering::AcqRel) + 1;
+        let now = now.max(1);
+        if self.probe_started_at.swap(0, Ordering::AcqRel) != 0 {
+            // The probe failed, wait for another cooldown period
+            self.opened_at.store(now, Ordering::Release);
+            true
+        } else {
+            failures >= config.threshold
+                && self
+                    .opened_at
+                    .compare_exchange(0, now, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+        }
+    }
 }
 
 /// Parse delivery hook configuration from TOML config
@@ -74,23 +375,138 @@ pub fn parse_delivery_hooks(config: &mut Config, id: &str, token_map: &TokenMap)
         );
     }
 
+    let timeout = config
+        .property_or_default(("session.delivery_hook", id, "timeout"), "30s")
+        .unwrap_or_else(|| std::time::Duration::from_secs(30));
+    let tls_allow_invalid_certs = config
+        .property_or_default(
+            ("session.delivery_hook", id, "allow-invalid-certs"),
+            "false",
+        )
+        .unwrap_or_default();
+
+    let transport = match config
+        .value(("session.delivery_hook", id, "transport"))
+        .unwrap_or("http")
+        .to_string()
+        .as_str()
+    {
+        "http" => DeliveryHookTransport::Http,
+        "unix" => DeliveryHookTransport::Unix {
+            socket: config
+                .value_require(("session.delivery_hook", id, "socket"))?
+                .to_string(),
+        },
+        "pubsub" => {
+            // Messages published over PubSub carry no headers to hold a signature
+            if config
+                .value(("session.delivery_hook", id, "signature.key"))
+                .is_some_and(|key| !key.is_empty())
+            {
+                config.new_parse_error(
+                    ("session.delivery_hook", id, "signature.key"),
+                    "Request signing is not supported by the PubSub transport",
+                );
+                return None;
+            }
+            let topic = config
+                .value(("session.delivery_hook", id, "topic"))
+                .map(|topic| topic.to_string())
+                .unwrap_or_else(|| format!("stalwart.delivery-hook.{id}"));
+            let reply_topic = format!("{topic}.reply.{:016x}", store::rand::random::<u64>());
+            DeliveryHookTransport::PubSub(Arc::new(PubSubReplies::new(topic, reply_topic)))
+        }
+        transport => {
+            let err = format!("Invalid delivery hook transport {transport:?}");
+            config.new_parse_error(("session.delivery_hook", id, "transport"), err);
+            return None;
+        }
+    };
+
+    // Build a single client per hook so connections are pooled across deliveries
+    let mut client_builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .danger_accept_invalid_certs(tls_allow_invalid_certs);
+    if let Some(identity) = parse_client_identity(config, "session.delivery_hook", id).ok()? {
+        client_builder = client_builder.identity(identity);
+    }
+    #[cfg(unix)]
+    if let DeliveryHookTransport::Unix { socket } = &transport {
+        client_builder = client_builder.unix_socket(socket.as_str());
+    }
+    let client = match client_builder.build() {
+        Ok(client) => client,
+        Err(err) => {
+            config.new_build_error(
+                ("session.delivery_hook", id, "url"),
+                format!("Failed to create HTTP client: {err}"),
+            );
+            return None;
+        }
+    };
+
+    let threshold = config
+        .property_or_default::<u32>(
+            ("session.delivery_hook", id, "circuit-breaker.threshold"),
+            "0",
+        )
+        .unwrap_or_default();
+    let circuit_breaker = (threshold > 0).then(|| CircuitBreakerConfig {
+        threshold,
+        cooldown: config
+            .property_or_default(
+                ("session.delivery_hook", id, "circuit-breaker.cooldown"),
+                "30s",
+            )
+            .unwrap_or_else(|| Duration::from_secs(30)),
+        fallback: config
+            .property_or_default(
+                ("session.delivery_hook", id, "circuit-breaker.fallback"),
+                "tempfail",
+            )
+            .unwrap_or_default(),
+    });
+
+    // Hooks only run on local deliveries unless other sources are listed
+    let mut sources = config
+        .properties::<DeliveryHookSource>(("session.delivery_hook", id, "sources"))
+        .into_iter()
+        .map(|(_, source)| source)
+        .collect::<Vec<_>>();
+    if sources.is_empty() {
+        sources = vec![DeliveryHookSource::Smtp, DeliveryHookSource::Sieve];
+    }
+
+    let include = config
+        .properties::<DeliveryHookInclude>(("session.delivery_hook", id, "include"))
+        .into_iter()
+        .map(|(_, include)| include)
+        .collect();
+
     Some(DeliveryHook {
         enable: IfBlock::try_parse(config, ("session.delivery_hook", id, "enable"), token_map)
             .unwrap_or_else(|| {
                 IfBlock::new::<()>(format!("delivery.hook.{id}.enable"), [], "false")
             }),
         id: id.to_string(),
-        url: config
-            .value_require(("session.delivery_hook", id, "url"))?
-            .to_string(),
-        timeout: config
-            .property_or_default(("session.delivery_hook", id, "timeout"), "30s")
-            .unwrap_or_else(|| std::time::Duration::from_secs(30)),
-        tls_allow_invalid_certs: config
-            .property_or_default(("session.delivery_hook", id, "allow-invalid-certs"), "false")
-            .unwrap_or_default(),
+        // Requests over a Unix socket still need a URL for the request path and Host header
+        url: match &transport {
+            DeliveryHookTransport::Http => config
+                .value_require(("session.delivery_hook", id, "url"))?
+                .to_string(),
+            DeliveryHookTransport::Unix { .. } => config
+                .value(("session.delivery_hook", id, "url"))
+                .unwrap_or("http://localhost/")
+                .to_string(),
+            DeliveryHookTransport::PubSub(_) => String::new(),
+        },
+        timeout,
+        tls_allow_invalid_certs,
         tempfail_on_error: config
-            .property_or_default(("session.delivery_hook", id, "options.tempfail-on-error"), "true")
+            .property_or_default(
+                ("session.delivery_hook", id, "options.tempfail-on-error"),
+                "true",
+            )
             .unwrap_or(true),
         max_response_size: config
             .property_or_default(
@@ -98,6 +514,18 @@ pub fn parse_delivery_hooks(config: &mut Config, id: &str, token_map: &TokenMap)
                 "52428800",
             )
             .unwrap_or(52428800),
+        order: config
+            .property_or_default(("session.delivery_hook", id, "order"), "0")
+            .unwrap_or_default(),
+        sources,
+        include,
+        is_async: config
+            .property_or_default(("session.delivery_hook", id, "async"), "false")
+            .unwrap_or_default(),
+        transport,
+        signer: HookSigner::parse(config, "session.delivery_hook", id),
+        client,
+        circuit_breaker,
         headers,
     })
 }
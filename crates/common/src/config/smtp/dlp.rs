@@ -0,0 +1,186 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Outbound content policies
+//!
+//! Messages submitted by authenticated accounts are checked against the policies
+//! listed under `session.dlp.policy.<id>` before they are queued. Each policy
+//! enables a set of detectors (credit card numbers, IBANs, keywords and attachment
+//! types, the latter two using dictionaries held in lookup stores) and triggers
+//! its action once the message contains at least `min-matches` matches. Messages
+//! can be rejected, held in the queue until an administrator approves them,
+//! delivered over TLS only or copied to a compliance address.
+
+use std::time::Duration;
+
+use smtp_proto::response::parser::ResponseReceiver;
+use utils::config::{Config, utils::ParseValue};
+
+use crate::expr::{if_block::IfBlock, tokenizer::TokenMap};
+
+use super::SMTP_RCPT_TO_VARS;
+
+#[derive(Debug, Clone, Default)]
+pub struct DlpConfig {
+    pub policies: Vec<DlpPolicy>,
+    pub hold_for: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DlpPolicy {
+    pub id: String,
+    pub enable: IfBlock,
+    pub detectors: Vec<DlpDetector>,
+    pub min_matches: usize,
+    pub action: DlpAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DlpDetector {
+    CreditCard,
+    Iban,
+    /// Words found in the lookup store with the given id
+    Keywords(String),
+    /// File extensions or content types found in the lookup store with the given id
+    Attachments(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DlpAction {
+    Reject(String),
+    Hold,
+    RequireTls,
+    Bcc(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DetectorType {
+    CreditCard,
+    Iban,
+    Keywords,
+    Attachments,
+}
+
+impl DlpConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let token_map = TokenMap::default().with_variables(SMTP_RCPT_TO_VARS);
+
+        DlpConfig {
+            policies: config
+                .sub_keys("session.dlp.policy", ".action")
+                .into_iter()
+                .filter_map(|id| parse_policy(config, &id, &token_map))
+                .collect(),
+            hold_for: config
+                .property_or_default::<Duration>("session.dlp.hold.expire", "7d")
+                .unwrap_or(Duration::from_secs(7 * 86400))
+                .as_secs(),
+        }
+    }
+}
+
+fn parse_policy(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<DlpPolicy> {
+    let mut detectors = Vec::new();
+    for (_, detector) in config.properties::<DetectorType>(("session.dlp.policy", id, "detect")) {
+        let detector = match detector {
+            DetectorType::CreditCard => DlpDetector::CreditCard,
+            DetectorType::Iban => DlpDetector::Iban,
+            DetectorType::Keywords => DlpDetector::Keywords(
+                config
+                    .value_require(("session.dlp.policy", id, "lookup.keywords"))?
+                    .to_string(),
+            ),
+            DetectorType::Attachments => DlpDetector::Attachments(
+                config
+                    .value_require(("session.dlp.policy", id, "lookup.attachments"))?
+                    .to_string(),
+            ),
+        };
+        if !detectors.contains(&detector) {
+            detectors.push(detector);
+        }
+    }
+    if detectors.is_empty() {
+        config.new_build_error(
+            ("session.dlp.policy", id, "detect"),
+            "At least one detector is required",
+        );
+        return None;
+    }
+
+    let action = config
+        .value_require(("session.dlp.policy", id, "action"))?
+        .to_string();
+    let action = match action.as_str() {
+        "reject" => {
+            let message = config
+                .value(("session.dlp.policy", id, "reject.message"))
+                .unwrap_or("550 5.7.1 Message rejected by content policy.")
+                .trim_end()
+                .to_string();
+            if !is_reject_reply(&message) {
+                config.new_parse_error(
+                    ("session.dlp.policy", id, "reject.message"),
+                    format!("Invalid SMTP reject reply {message:?}"),
+                );
+                return None;
+            }
+            DlpAction::Reject(message)
+        }
+        "hold" => DlpAction::Hold,
+        "require-tls" => DlpAction::RequireTls,
+        "bcc" => DlpAction::Bcc(
+            config
+                .value_require(("session.dlp.policy", id, "bcc"))?
+                .to_lowercase(),
+        ),
+        action => {
+            config.new_parse_error(
+                ("session.dlp.policy", id, "action"),
+                format!("Invalid content policy action {action:?}"),
+            );
+            return None;
+        }
+    };
+
+    Some(DlpPolicy {
+        id: id.to_string(),
+        enable: IfBlock::try_parse(config, ("session.dlp.policy", id, "enable"), token_map)
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(format!("session.dlp.policy.{id}.enable"), [], "true")
+            }),
+        detectors,
+        min_matches: config
+            .property_or_default::<usize>(("session.dlp.policy", id, "min-matches"), "1")
+            .unwrap_or(1)
+            .max(1),
+        action,
+    })
+}
+
+/// Whether the message is a single line 4xx or 5xx SMTP reply, with an optional
+/// enhanced status code of the same class
+fn is_reject_reply(message: &str) -> bool {
+    !message.contains(['\r', '\n'])
+        && ResponseReceiver::default()
+            .parse(&mut format!("{message}\r\n").as_bytes().iter())
+            .is_ok_and(|reply| {
+                matches!(reply.code, 400..=599)
+                    && (reply.esc[0] == 0 || u16::from(reply.esc[0]) == reply.code / 100)
+            })
+}
+
+impl ParseValue for DetectorType {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "credit-card" => Ok(Self::CreditCard),
+            "iban" => Ok(Self::Iban),
+            "keyword" | "keywords" => Ok(Self::Keywords),
+            "attachment" | "attachments" => Ok(Self::Attachments),
+            _ => Err(format!("Invalid content policy detector {value:?}")),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Request authentication shared by MTA hooks and delivery hooks
+//!
+//! Hook services can verify requests using an HMAC-SHA256 signature computed over
+//! `<timestamp>.<nonce>.<body>` and sent in the `X-Signature` header, together with
+//! the `X-Signature-Timestamp` and `X-Signature-Nonce` headers used to reject
+//! replayed requests. Hooks can additionally present a TLS client certificate.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use hyper::{
+    HeaderMap,
+    header::{HeaderName, HeaderValue},
+};
+use ring::hmac;
+use utils::config::Config;
+
+pub const SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-signature");
+pub const SIGNATURE_TIMESTAMP_HEADER: HeaderName = HeaderName::from_static("x-signature-timestamp");
+pub const SIGNATURE_NONCE_HEADER: HeaderName = HeaderName::from_static("x-signature-nonce");
+
+/// Signs the body of hook requests with a shared secret
+#[derive(Clone)]
+pub struct HookSigner {
+    key: hmac::Key,
+}
+
+impl HookSigner {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret),
+        }
+    }
+
+    pub fn parse(config: &mut Config, prefix: &str, id: &str) -> Option<Self> {
+        config
+            .value((prefix, id, "signature.key"))
+            .filter(|secret| !secret.is_empty())
+            .map(|secret| Self::new(secret.as_bytes()))
+    }
+
+    /// Returns the base64 encoded signature of a request body
+    pub fn sign(&self, timestamp: u64, nonce: &str, body: &[u8]) -> String {
+        let mut ctx = hmac::Context::with_key(&self.key);
+        ctx.update(timestamp.to_string().as_bytes());
+        ctx.update(b".");
+        ctx.update(nonce.as_bytes());
+        ctx.update(b".");
+        ctx.update(body);
+        STANDARD.encode(ctx.sign().as_ref())
+    }
+
+    /// Adds the signature headers for a request body sent at `timestamp` (in seconds)
+    pub fn sign_request(&self, headers: &mut HeaderMap, timestamp: u64, body: &[u8]) {
+        let nonce = format!("{:032x}", store::rand::random::<u128>());
+        let signature = self.sign(timestamp, &nonce, body);
+
+        headers.insert(SIGNATURE_HEADER, HeaderValue::from_str(&signature).unwrap());
+        headers.insert(SIGNATURE_TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+        headers.insert(
+            SIGNATURE_NONCE_HEADER,
+            HeaderValue::from_str(&nonce).unwrap(),
+        );
+    }
+}
+
+/// Parse the optional TLS client certificate presented to a hook, a certificate
+/// that is configured but can't be loaded is an error so that the hook is not
+/// enabled without it
+#[allow(clippy::result_unit_err)]
+pub fn parse_client_identity(
+    config: &mut Config,
+    prefix: &str,
+    id: &str,
+) -> Result<Option<reqwest::Identity>, ()> {
+    let Some(certificate) = config
+        .value((prefix, id, "tls.certificate"))
+        .map(|certificate| certificate.to_string())
+    else {
+        return Ok(None);
+    };
+    let private_key = config
+        .value_require((prefix, id, "tls.private-key"))
+        .ok_or(())?
+        .to_string();
+
+    match reqwest::Identity::from_pem(format!("{certificate}\n{private_key}").as_bytes()) {
+        Ok(identity) => Ok(Some(identity)),
+        Err(err) => {
+            config.new_build_error(
+                (prefix, id, "tls.certificate"),
+                format!("Failed to load client certificate: {err}"),
+            );
+            Err(())
+        }
+    }
+}
@@ -0,0 +1,220 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Configuration for the mailing list manager
+//!
+//! When enabled, messages addressed to `Type::List` principals of the internal
+//! directory are redistributed as list posts instead of being expanded to the list
+//! members at RCPT time. Settings under `mailing-list.default` apply to all lists and
+//! can be overridden for a single list under `mailing-list.list.<list-name>`.
+
+use ahash::AHashMap;
+use std::time::Duration;
+use utils::config::{Config, utils::ParseValue};
+
+#[derive(Debug, Clone, Default)]
+pub struct MailingListConfig {
+    pub enable: bool,
+    /// Base URL of the confirmation and one-click unsubscribe endpoints
+    pub url: String,
+    pub confirm_expiry: u64,
+    pub unsubscribe_expiry: u64,
+    pub moderation_expiry: u64,
+    pub default: ListPolicy,
+    pub lists: AHashMap<String, ListPolicy>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListPolicy {
+    pub posting: ListPosting,
+    pub non_member: ListNonMember,
+    pub reply_to: ListReplyTo,
+    pub from_rewrite: ListFromRewrite,
+    pub subject_prefix: Option<String>,
+    /// Addresses allowed to post to announce lists, notified of held posts
+    pub moderators: Vec<String>,
+    pub allow_subscribe: bool,
+}
+
+/// Who is allowed to post to the list without moderation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListPosting {
+    Open,
+    #[default]
+    Members,
+    Announce,
+}
+
+/// What happens to posts from senders that are not allowed to post
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListNonMember {
+    #[default]
+    Moderate,
+    Reject,
+    Discard,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListReplyTo {
+    #[default]
+    Sender,
+    List,
+}
+
+/// When the From header is replaced with the list address, so that list posts
+/// pass DMARC checks at the recipient
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListFromRewrite {
+    Never,
+    #[default]
+    Dmarc,
+    Always,
+}
+
+impl MailingListConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let default = ListPolicy::parse(config, "mailing-list.default", &ListPolicy::default());
+        let mut lists = AHashMap::new();
+        for name in config.sub_keys("mailing-list.list", "") {
+            let policy = ListPolicy::parse(config, &format!("mailing-list.list.{name}"), &default);
+            lists.insert(name, policy);
+        }
+
+        MailingListConfig {
+            enable: config
+                .property_or_default("mailing-list.enable", "false")
+                .unwrap_or(false),
+            url: config
+                .value("mailing-list.url")
+                .map(|v| v.trim().trim_end_matches('/'))
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| {
+                    format!(
+                        "https://{}/list",
+                        config.value("server.hostname").unwrap_or("localhost")
+                    )
+                }),
+            confirm_expiry: config
+                .property_or_default::<Duration>("mailing-list.expiry.confirm", "2d")
+                .unwrap_or(Duration::from_secs(2 * 86400))
+                .as_secs(),
+            unsubscribe_expiry: config
+                .property_or_default::<Duration>("mailing-list.expiry.unsubscribe", "365d")
+                .unwrap_or(Duration::from_secs(365 * 86400))
+                .as_secs(),
+            moderation_expiry: config
+                .property_or_default::<Duration>("mailing-list.expiry.moderation", "7d")
+                .unwrap_or(Duration::from_secs(7 * 86400))
+                .as_secs(),
+            default,
+            lists,
+        }
+    }
+
+    pub fn policy(&self, list_name: &str) -> &ListPolicy {
+        self.lists.get(list_name).unwrap_or(&self.default)
+    }
+}
+
+impl ListPolicy {
+    fn parse(config: &mut Config, prefix: &str, default: &Self) -> Self {
+        let moderators = config
+            .values((prefix, "moderators"))
+            .map(|(_, v)| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>();
+
+        ListPolicy {
+            posting: config
+                .property((prefix, "posting"))
+                .unwrap_or(default.posting),
+            non_member: config
+                .property((prefix, "non-member"))
+                .unwrap_or(default.non_member),
+            reply_to: config
+                .property((prefix, "reply-to"))
+                .unwrap_or(default.reply_to),
+            from_rewrite: config
+                .property((prefix, "from-rewrite"))
+                .unwrap_or(default.from_rewrite),
+            subject_prefix: config
+                .value((prefix, "subject-prefix"))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .or_else(|| default.subject_prefix.clone()),
+            moderators: if !moderators.is_empty() {
+                moderators
+            } else {
+                default.moderators.clone()
+            },
+            allow_subscribe: config
+                .property((prefix, "allow-subscribe"))
+                .unwrap_or(default.allow_subscribe),
+        }
+    }
+
+    pub fn is_moderator(&self, address: &str) -> bool {
+        self.moderators.iter().any(|m| m == address)
+    }
+}
+
+impl Default for ListPolicy {
+    fn default() -> Self {
+        ListPolicy {
+            posting: ListPosting::default(),
+            non_member: ListNonMember::default(),
+            reply_to: ListReplyTo::default(),
+            from_rewrite: ListFromRewrite::default(),
+            subject_prefix: None,
+            moderators: Vec::new(),
+            allow_subscribe: true,
+        }
+    }
+}
+
+impl ParseValue for ListPosting {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "open" => Ok(Self::Open),
+            "members" => Ok(Self::Members),
+            "announce" => Ok(Self::Announce),
+            _ => Err(format!("Invalid list posting policy {value:?}")),
+        }
+    }
+}
+
+impl ParseValue for ListNonMember {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "moderate" => Ok(Self::Moderate),
+            "reject" => Ok(Self::Reject),
+            "discard" => Ok(Self::Discard),
+            _ => Err(format!("Invalid list non-member action {value:?}")),
+        }
+    }
+}
+
+impl ParseValue for ListReplyTo {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "sender" => Ok(Self::Sender),
+            "list" => Ok(Self::List),
+            _ => Err(format!("Invalid list reply-to mode {value:?}")),
+        }
+    }
+}
+
+impl ParseValue for ListFromRewrite {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "never" => Ok(Self::Never),
+            "dmarc" => Ok(Self::Dmarc),
+            "always" => Ok(Self::Always),
+            _ => Err(format!("Invalid list from rewrite mode {value:?}")),
+        }
+    }
+}
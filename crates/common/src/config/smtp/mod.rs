@@ -6,19 +6,30 @@
 
 use utils::config::{Config, Rate};
 
+pub mod adaptive;
 pub mod auth;
+pub mod complaints;
 pub mod delivery_hooks;
+pub mod dlp;
+pub mod hook_auth;
+pub mod mailing_list;
+pub mod quarantine;
 pub mod queue;
 pub mod report;
 pub mod resolver;
+pub mod sending_limits;
 pub mod session;
+pub mod suppression;
 pub mod throttle;
+pub mod warmup;
 
 use crate::expr::{Expression, tokenizer::TokenMap};
 
 use self::{
-    auth::MailAuthConfig, queue::QueueConfig, report::ReportConfig, resolver::Resolvers,
-    session::SessionConfig,
+    adaptive::AdaptiveThrottleConfig, auth::MailAuthConfig, dlp::DlpConfig,
+    mailing_list::MailingListConfig, quarantine::QuarantineConfig, queue::QueueConfig,
+    report::ReportConfig, resolver::Resolvers, sending_limits::SendingLimitsConfig,
+    session::SessionConfig, suppression::SuppressionConfig,
 };
 
 use super::*;
@@ -30,6 +41,12 @@ pub struct SmtpConfig {
     pub resolvers: Resolvers,
     pub mail_auth: MailAuthConfig,
     pub report: ReportConfig,
+    pub mailing_list: MailingListConfig,
+    pub suppression: SuppressionConfig,
+    pub adaptive: AdaptiveThrottleConfig,
+    pub sending_limits: SendingLimitsConfig,
+    pub dlp: DlpConfig,
+    pub quarantine: QuarantineConfig,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -54,7 +71,7 @@ pub const THROTTLE_HELO_DOMAIN: u16 = 1 << 9;
 
 pub(crate) const RCPT_DOMAIN_VARS: &[u32; 1] = &[V_RECIPIENT_DOMAIN];
 
-pub(crate) const SMTP_EHLO_VARS: &[u32; 10] = &[
+pub(crate) const SMTP_EHLO_VARS: &[u32; 13] = &[
     V_LISTENER,
     V_REMOTE_IP,
     V_REMOTE_PORT,
@@ -65,8 +82,11 @@ pub(crate) const SMTP_EHLO_VARS: &[u32; 10] = &[
     V_HELO_DOMAIN,
     V_ASN,
     V_COUNTRY,
+    V_PEER_UID,
+    V_PEER_GID,
+    V_PEER_PID,
 ];
-pub(crate) const SMTP_MAIL_FROM_VARS: &[u32; 12] = &[
+pub(crate) const SMTP_MAIL_FROM_VARS: &[u32; 15] = &[
     V_LISTENER,
     V_REMOTE_IP,
     V_REMOTE_PORT,
@@ -79,8 +99,11 @@ pub(crate) const SMTP_MAIL_FROM_VARS: &[u32; 12] = &[
     V_AUTHENTICATED_AS,
     V_ASN,
     V_COUNTRY,
+    V_PEER_UID,
+    V_PEER_GID,
+    V_PEER_PID,
 ];
-pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 17] = &[
+pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 20] = &[
     V_SENDER,
     V_SENDER_DOMAIN,
     V_RECIPIENTS,
@@ -98,6 +121,9 @@ pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 17] = &[
     V_HELO_DOMAIN,
     V_ASN,
     V_COUNTRY,
+    V_PEER_UID,
+    V_PEER_GID,
+    V_PEER_PID,
 ];
 pub(crate) const SMTP_QUEUE_HOST_VARS: &[u32; 20] = &[
     V_SENDER,
@@ -159,6 +185,12 @@ impl SmtpConfig {
             resolvers: Resolvers::parse(config).await,
             mail_auth: MailAuthConfig::parse(config),
             report: ReportConfig::parse(config),
+            mailing_list: MailingListConfig::parse(config),
+            suppression: SuppressionConfig::parse(config),
+            adaptive: AdaptiveThrottleConfig::parse(config),
+            sending_limits: SendingLimitsConfig::parse(config),
+            dlp: DlpConfig::parse(config),
+            quarantine: QuarantineConfig::parse(config),
         }
     }
 }
@@ -0,0 +1,91 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Message quarantine
+//!
+//! When enabled, messages quarantined by a delivery hook, MTA hook, milter or by
+//! the SMTP Sieve script adding an `X-Quarantine` header are held server-side,
+//! outside of the recipient's mailboxes, for `retention`. `X-Quarantine` headers
+//! present in the delivered message itself are removed and never trusted. Setting
+//! `spam` also quarantines messages classified as spam instead of filing them into
+//! the Junk folder. Held messages can be released or deleted by administrators and
+//! by the recipients themselves, who can be sent a periodic digest with signed
+//! release links.
+
+use std::time::Duration;
+use utils::config::{Config, cron::SimpleCron, utils::ParseValue};
+
+#[derive(Debug, Clone, Default)]
+pub struct QuarantineConfig {
+    pub enable: bool,
+    pub retention: u64,
+    /// Quarantine messages classified as spam rather than filing them into Junk
+    pub spam: bool,
+    /// Base URL of the release link endpoint
+    pub url: String,
+    pub digest: Option<QuarantineDigest>,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuarantineDigest {
+    pub frequency: SimpleCron,
+    pub from_name: String,
+    pub from_address: String,
+    pub subject: String,
+}
+
+impl QuarantineConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let hostname = config
+            .value("server.hostname")
+            .unwrap_or("localhost")
+            .to_string();
+        let enable = config
+            .property_or_default("quarantine.enable", "false")
+            .unwrap_or(false);
+        let digest = (enable
+            && config
+                .property_or_default("quarantine.digest.enable", "false")
+                .unwrap_or(false))
+        .then(|| QuarantineDigest {
+            frequency: config
+                .property_or_default::<SimpleCron>("quarantine.digest.frequency", "0 8 *")
+                .unwrap_or_else(|| SimpleCron::parse_value("0 8 *").unwrap()),
+            from_name: config
+                .value("quarantine.digest.from-name")
+                .unwrap_or("Quarantine")
+                .to_string(),
+            from_address: config
+                .value("quarantine.digest.from-address")
+                .map(|v| v.trim().to_lowercase())
+                .unwrap_or_else(|| format!("postmaster@{hostname}")),
+            subject: config
+                .value("quarantine.digest.subject")
+                .unwrap_or("Quarantined messages")
+                .to_string(),
+        });
+
+        QuarantineConfig {
+            enable,
+            retention: config
+                .property_or_default::<Duration>("quarantine.retention", "30d")
+                .unwrap_or(Duration::from_secs(30 * 86400))
+                .as_secs()
+                .max(60),
+            spam: enable
+                && config
+                    .property_or_default("quarantine.spam", "false")
+                    .unwrap_or(false),
+            url: config
+                .value("quarantine.url")
+                .map(|v| v.trim().trim_end_matches('/'))
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| format!("https://{hostname}/quarantine")),
+            digest,
+        }
+    }
+}
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use self::throttle::parse_queue_rate_limiter;
+use self::{throttle::parse_queue_rate_limiter, warmup::WarmupSchedule};
 use super::*;
 use crate::{
     config::server::ServerProtocol,
@@ -17,6 +17,7 @@ use std::{
     fmt::Display,
     hash::{Hash, Hasher},
     net::IpAddr,
+    sync::Arc,
     time::Duration,
 };
 use throttle::parse_queue_rate_limiter_key;
@@ -143,6 +144,7 @@ pub struct ConnectionStrategy {
 pub struct IpAndHost {
     pub ip: IpAddr,
     pub host: Option<String>,
+    pub warmup: Option<Arc<WarmupSchedule>>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -524,6 +526,7 @@ fn parse_connection(config: &mut Config, id: &str) -> Option<ConnectionStrategy>
         let ip_and_host = IpAndHost {
             ip,
             host: config.property::<String>(("queue.source-ip", ip.to_string(), "ehlo-hostname")),
+            warmup: WarmupSchedule::parse(config, &ip).map(Arc::new),
         };
 
         if ip.is_ipv4() {
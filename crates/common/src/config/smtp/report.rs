@@ -10,7 +10,7 @@ use utils::config::{Config, utils::ParseValue};
 
 use crate::expr::{Constant, ConstantValue, Variable, if_block::IfBlock, tokenizer::TokenMap};
 
-use super::*;
+use super::{complaints::ComplaintConfig, *};
 
 #[derive(Clone)]
 pub struct ReportConfig {
@@ -29,6 +29,7 @@ pub struct ReportAnalysis {
     pub addresses: Vec<AddressMatch>,
     pub forward: bool,
     pub store: Option<Duration>,
+    pub complaints: ComplaintConfig,
 }
 
 #[derive(Clone)]
@@ -91,6 +92,7 @@ impl ReportConfig {
                 store: config
                     .property_or_default::<Option<Duration>>("report.analysis.store", "30d")
                     .unwrap_or_default(),
+                complaints: ComplaintConfig::parse(config),
             },
             dkim: Report::parse(config, "dkim", &rcpt_vars),
             spf: Report::parse(config, "spf", &sender_vars),
@@ -0,0 +1,93 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Per-account outbound sending limits
+//!
+//! Authenticated accounts may be limited in the number of recipients they send
+//! to per hour and per day, as well as in the number of distinct recipients per
+//! day. Recipients over a limit are temporarily rejected. In addition, sudden
+//! spikes in the hourly volume of an account and authentications from too many
+//! countries are taken as a sign of a compromised account, in which case the
+//! account is locked out from sending and its app passwords and OAuth tokens are
+//! revoked until an administrator lifts the lockout.
+
+use std::time::Duration;
+use utils::config::Config;
+
+#[derive(Debug, Clone, Default)]
+pub struct SendingLimitsConfig {
+    pub enable: bool,
+    pub hourly: Option<u64>,
+    pub daily: Option<u64>,
+    pub unique_daily: Option<u64>,
+    pub spike_factor: Option<f64>,
+    pub spike_min: u64,
+    pub max_countries: Option<u64>,
+    pub countries_period: u64,
+    pub lockout: bool,
+    pub revoke_app_passwords: bool,
+    pub revoke_tokens: bool,
+}
+
+impl SendingLimitsConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        SendingLimitsConfig {
+            enable: config
+                .property_or_default("session.sending-limits.enable", "false")
+                .unwrap_or(false),
+            hourly: config.property::<u64>("session.sending-limits.recipients.hourly"),
+            daily: config.property::<u64>("session.sending-limits.recipients.daily"),
+            unique_daily: config.property::<u64>("session.sending-limits.recipients.unique"),
+            spike_factor: config
+                .property_or_default::<Option<f64>>(
+                    "session.sending-limits.anomaly.spike.factor",
+                    "10",
+                )
+                .unwrap_or_default(),
+            spike_min: config
+                .property_or_default::<u64>(
+                    "session.sending-limits.anomaly.spike.min-recipients",
+                    "200",
+                )
+                .unwrap_or(200),
+            max_countries: config
+                .property_or_default::<Option<u64>>(
+                    "session.sending-limits.anomaly.countries.max",
+                    "false",
+                )
+                .unwrap_or_default(),
+            countries_period: config
+                .property_or_default::<Duration>(
+                    "session.sending-limits.anomaly.countries.period",
+                    "1d",
+                )
+                .unwrap_or(Duration::from_secs(86400))
+                .as_secs()
+                .max(1),
+            lockout: config
+                .property_or_default("session.sending-limits.lockout.enable", "true")
+                .unwrap_or(true),
+            revoke_app_passwords: config
+                .property_or_default(
+                    "session.sending-limits.lockout.revoke-app-passwords",
+                    "true",
+                )
+                .unwrap_or(true),
+            revoke_tokens: config
+                .property_or_default("session.sending-limits.lockout.revoke-tokens", "true")
+                .unwrap_or(true),
+        }
+    }
+
+    /// Whether the recipients sent to in the current hour are a spike compared
+    /// to those sent to in the previous hour and day
+    pub fn is_spike(&self, current_hour: u64, previous_hour: u64, previous_day: u64) -> bool {
+        self.spike_factor.is_some_and(|factor| {
+            current_hour >= self.spike_min
+                && current_hour as f64 > factor * previous_hour.max(previous_day / 24).max(1) as f64
+        })
+    }
+}
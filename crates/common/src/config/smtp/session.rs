@@ -21,7 +21,13 @@ use smtp_proto::*;
 use utils::config::{Config, utils::ParseValue};
 
 use crate::{
-    config::{CONNECTION_VARS, smtp::delivery_hooks::DeliveryHook},
+    config::{
+        CONNECTION_VARS,
+        smtp::{
+            delivery_hooks::{DeliveryHook, DeliveryHookMode},
+            hook_auth::{HookSigner, parse_client_identity},
+        },
+    },
     expr::{if_block::IfBlock, tokenizer::TokenMap, *},
 };
 
@@ -49,6 +55,8 @@ pub struct SessionConfig {
     pub milters: Vec<Milter>,
     pub hooks: Vec<MTAHook>,
     pub delivery_hooks: Vec<DeliveryHook>,
+    pub delivery_hooks_mode: DeliveryHookMode,
+    pub delivery_hooks_concurrency: usize,
 }
 
 #[derive(Clone)]
@@ -75,6 +83,7 @@ pub struct Extensions {
     pub expn: IfBlock,
     pub no_soliciting: IfBlock,
     pub future_release: IfBlock,
+    pub undo_delay: IfBlock,
     pub deliver_by: IfBlock,
     pub mt_priority: IfBlock,
 }
@@ -180,6 +189,8 @@ pub struct MTAHook {
     pub tempfail_on_error: bool,
     pub run_on_stage: AHashSet<Stage>,
     pub max_response_size: usize,
+    pub signer: Option<HookSigner>,
+    pub identity: Option<reqwest::Identity>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -215,10 +226,20 @@ impl SessionConfig {
             .filter_map(|id| parse_hooks(config, &id, &has_rcpt_vars))
             .collect();
         session.delivery_hooks = config
-            .sub_keys("session.delivery_hook", ".url")
+            .sub_keys_with_suffixes("session.delivery_hook", &[".url", ".transport"])
             .into_iter()
             .filter_map(|id| parse_delivery_hooks(config, &id, &has_rcpt_vars))
             .collect();
+        session
+            .delivery_hooks
+            .sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.id.cmp(&b.id)));
+        session.delivery_hooks_mode = config
+            .property_or_default("session.delivery_hook.mode", "parallel")
+            .unwrap_or_default();
+        session.delivery_hooks_concurrency = config
+            .property_or_default::<usize>("session.delivery_hook.async-concurrency", "8")
+            .unwrap_or(8)
+            .max(1);
         session.mta_sts_policy = Policy::try_parse(config);
 
         for (value, key, token_map) in [
@@ -284,6 +305,11 @@ impl SessionConfig {
                 "session.extensions.future-release",
                 &has_sender_vars,
             ),
+            (
+                &mut session.extensions.undo_delay,
+                "session.extensions.undo-delay",
+                &has_sender_vars,
+            ),
             (
                 &mut session.extensions.deliver_by,
                 "session.extensions.deliver-by",
@@ -593,6 +619,8 @@ fn parse_hooks(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<MT
                 "52428800",
             )
             .unwrap_or(52428800),
+        signer: HookSigner::parse(config, "session.hook", id),
+        identity: parse_client_identity(config, "session.hook", id).ok()?,
         headers,
     })
 }
@@ -789,6 +817,7 @@ impl Default for SessionConfig {
                     [("!is_empty(authenticated_as)", "7d")],
                     "false",
                 ),
+                undo_delay: IfBlock::new::<()>("session.extensions.undo-delay", [], "false"),
                 deliver_by: IfBlock::new::<()>(
                     "session.extensions.deliver-by",
                     [("!is_empty(authenticated_as)", "15d")],
@@ -804,6 +833,8 @@ impl Default for SessionConfig {
             milters: Default::default(),
             hooks: Default::default(),
             delivery_hooks: Default::default(),
+            delivery_hooks_mode: Default::default(),
+            delivery_hooks_concurrency: 8,
         }
     }
 }
@@ -818,9 +849,9 @@ impl ParseValue for Mechanism {
             "PLAIN" => AUTH_PLAIN,
             "XOAUTH2" => AUTH_XOAUTH2,
             "OAUTHBEARER" => AUTH_OAUTHBEARER,
-            /*"SCRAM-SHA-256-PLUS" => AUTH_SCRAM_SHA_256_PLUS,
+            "SCRAM-SHA-256-PLUS" => AUTH_SCRAM_SHA_256_PLUS,
             "SCRAM-SHA-256" => AUTH_SCRAM_SHA_256,
-            "SCRAM-SHA-1-PLUS" => AUTH_SCRAM_SHA_1_PLUS,
+            /*"SCRAM-SHA-1-PLUS" => AUTH_SCRAM_SHA_1_PLUS,
             "SCRAM-SHA-1" => AUTH_SCRAM_SHA_1,
             "XOAUTH" => AUTH_XOAUTH,
             "9798-M-DSA-SHA1" => AUTH_9798_M_DSA_SHA1,
@@ -896,7 +927,9 @@ impl ConstantValue for Mechanism {
             .add_constant("login", Mechanism(AUTH_LOGIN))
             .add_constant("plain", Mechanism(AUTH_PLAIN))
             .add_constant("xoauth2", Mechanism(AUTH_XOAUTH2))
-            .add_constant("oauthbearer", Mechanism(AUTH_OAUTHBEARER));
+            .add_constant("oauthbearer", Mechanism(AUTH_OAUTHBEARER))
+            .add_constant("scram_sha256", Mechanism(AUTH_SCRAM_SHA_256))
+            .add_constant("scram_sha256_plus", Mechanism(AUTH_SCRAM_SHA_256_PLUS));
     }
 }
 
@@ -0,0 +1,75 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Configuration for bounce processing and the recipient suppression list
+//!
+//! Permanent delivery failures reported by our own queue or by incoming delivery
+//! status notifications add the recipient to the suppression list of the sender's
+//! tenant. Transient failures only do so once more than `soft-bounce.limit`
+//! of them have been received for the same recipient. Incoming notifications are
+//! only trusted when they refer to a message sent during the last `track-for`.
+
+use std::time::Duration;
+use utils::config::{Config, Rate, utils::ParseValue};
+
+#[derive(Debug, Clone, Default)]
+pub struct SuppressionConfig {
+    pub enable: bool,
+    /// What happens when an authenticated sender adds a suppressed recipient
+    pub action: SuppressionAction,
+    pub hard_expiry: u64,
+    pub soft_expiry: u64,
+    pub soft_limit: Rate,
+    pub track_for: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SuppressionAction {
+    #[default]
+    Reject,
+    Warn,
+}
+
+impl SuppressionConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        SuppressionConfig {
+            enable: config
+                .property_or_default("queue.suppression.enable", "false")
+                .unwrap_or(false),
+            action: config
+                .property_or_default("queue.suppression.action", "reject")
+                .unwrap_or_default(),
+            hard_expiry: config
+                .property_or_default::<Duration>("queue.suppression.expiry.hard-bounce", "180d")
+                .unwrap_or(Duration::from_secs(180 * 86400))
+                .as_secs(),
+            soft_expiry: config
+                .property_or_default::<Duration>("queue.suppression.expiry.soft-bounce", "7d")
+                .unwrap_or(Duration::from_secs(7 * 86400))
+                .as_secs(),
+            soft_limit: config
+                .property_or_default::<Rate>("queue.suppression.soft-bounce.limit", "5/7d")
+                .unwrap_or(Rate {
+                    requests: 5,
+                    period: Duration::from_secs(7 * 86400),
+                }),
+            track_for: config
+                .property_or_default::<Duration>("queue.suppression.track-for", "7d")
+                .unwrap_or(Duration::from_secs(7 * 86400))
+                .as_secs(),
+        }
+    }
+}
+
+impl ParseValue for SuppressionAction {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "reject" => Ok(Self::Reject),
+            "warn" => Ok(Self::Warn),
+            _ => Err(format!("Invalid suppression action {value:?}")),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Warm-up schedules for outbound source addresses
+//!
+//! A source IP with a warm-up schedule may only be used for a limited number of
+//! messages per day. The first daily limit applies to the day the address is first
+//! used for delivery, the second one to the following day and so on, and once the
+//! schedule is over the address is warm and no longer limited. The schedule is
+//! either listed in `queue.source-ip.<ip>.warmup.schedule` or ramped up
+//! geometrically from `warmup.initial` to `warmup.target` over `warmup.days`.
+
+use std::net::IpAddr;
+use utils::config::{Config, utils::ParseValue};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarmupSchedule {
+    pub daily_limits: Vec<u64>,
+    /// Apply the daily limits to each destination provider separately
+    pub per_provider: bool,
+    pub overflow: WarmupOverflow,
+}
+
+/// What happens to messages once a warming address has reached its daily limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WarmupOverflow {
+    /// Deliver using another source address with capacity left
+    #[default]
+    Spill,
+    /// Retry on the next day
+    Defer,
+}
+
+impl WarmupSchedule {
+    pub fn parse(config: &mut Config, ip: &IpAddr) -> Option<Self> {
+        let ip = ip.to_string();
+        let mut daily_limits = config
+            .properties::<u64>(("queue.source-ip", ip.as_str(), "warmup.schedule"))
+            .into_iter()
+            .map(|(_, limit)| limit)
+            .collect::<Vec<_>>();
+
+        if daily_limits.is_empty() {
+            let days = config.property::<u64>(("queue.source-ip", ip.as_str(), "warmup.days"))?;
+            let initial = config
+                .property_or_default::<u64>(
+                    ("queue.source-ip", ip.as_str(), "warmup.initial"),
+                    "50",
+                )
+                .unwrap_or(50)
+                .max(1);
+            let target = config
+                .property_or_default::<u64>(
+                    ("queue.source-ip", ip.as_str(), "warmup.target"),
+                    "100000",
+                )
+                .unwrap_or(100_000)
+                .max(initial);
+            daily_limits = ramp(initial, target, days);
+        }
+
+        if daily_limits.is_empty() {
+            return None;
+        }
+
+        Some(WarmupSchedule {
+            daily_limits,
+            per_provider: config
+                .property_or_default(
+                    ("queue.source-ip", ip.as_str(), "warmup.per-provider"),
+                    "false",
+                )
+                .unwrap_or(false),
+            overflow: config
+                .property_or_default(("queue.source-ip", ip.as_str(), "warmup.overflow"), "spill")
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Returns the limit for the given day of the warm-up, or `None` once the
+    /// address is warm
+    pub fn daily_limit(&self, day: u64) -> Option<u64> {
+        self.daily_limits.get(day as usize).copied()
+    }
+
+    pub fn days(&self) -> u64 {
+        self.daily_limits.len() as u64
+    }
+}
+
+fn ramp(initial: u64, target: u64, days: u64) -> Vec<u64> {
+    match days {
+        0 => vec![],
+        1 => vec![initial],
+        _ => {
+            let factor = (target as f64 / initial as f64).powf(1.0 / (days - 1) as f64);
+            (0..days)
+                .map(|day| (initial as f64 * factor.powi(day as i32)).round() as u64)
+                .collect()
+        }
+    }
+}
+
+impl ParseValue for WarmupOverflow {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "spill" => Ok(Self::Spill),
+            "defer" => Ok(Self::Defer),
+            _ => Err(format!("Invalid warm-up overflow action {value:?}")),
+        }
+    }
+}
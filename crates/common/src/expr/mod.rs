@@ -48,6 +48,9 @@ pub const V_QUEUE_NAME: u32 = 29;
 pub const V_SOURCE: u32 = 30;
 pub const V_SIZE: u32 = 31;
 pub const V_QUEUE_AGE: u32 = 32;
+pub const V_PEER_UID: u32 = 33;
+pub const V_PEER_GID: u32 = 34;
+pub const V_PEER_PID: u32 = 35;
 
 pub const VARIABLES_MAP: &[(&str, u32)] = &[
     ("rcpt", V_RECIPIENT),
@@ -83,6 +86,9 @@ pub const VARIABLES_MAP: &[(&str, u32)] = &[
     ("source", V_SOURCE),
     ("size", V_SIZE),
     ("queue_age", V_QUEUE_AGE),
+    ("peer_uid", V_PEER_UID),
+    ("peer_gid", V_PEER_GID),
+    ("peer_pid", V_PEER_PID),
 ];
 
 pub mod eval;
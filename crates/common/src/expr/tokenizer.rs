@@ -375,6 +375,9 @@ impl TokenMap {
             V_RECEIVED_VIA_PORT,
             V_SOURCE,
             V_SIZE,
+            V_PEER_UID,
+            V_PEER_GID,
+            V_PEER_PID,
         ])
     }
 
@@ -18,6 +18,8 @@ use config::{
     scripts::Scripting,
     smtp::{
         SmtpConfig,
+        adaptive::AdaptiveThrottle,
+        delivery_hooks::CircuitBreakers,
         resolver::{Policy, Tlsa},
     },
     spamfilter::{IpResolver, SpamFilterConfig},
@@ -119,6 +121,21 @@ pub const KV_LOCK_TASK: u8 = 23;
 pub const KV_LOCK_HOUSEKEEPER: u8 = 24;
 pub const KV_LOCK_DAV: u8 = 25;
 pub const KV_SIEVE_ID: u8 = 26;
+pub const KV_LIST_MODERATION: u8 = 27;
+pub const KV_SUPPRESSION: u8 = 28;
+pub const KV_RATE_LIMIT_BOUNCE: u8 = 29;
+pub const KV_WARMUP: u8 = 30;
+pub const KV_WARMUP_COUNTER: u8 = 31;
+pub const KV_COMPLAINT_ORIGIN: u8 = 32;
+pub const KV_COMPLAINT_COUNTER: u8 = 33;
+pub const KV_COMPLAINT_SUSPENDED: u8 = 34;
+pub const KV_SENDING_COUNTER: u8 = 35;
+pub const KV_SENDING_RECIPIENT: u8 = 36;
+pub const KV_SENDING_COUNTRY: u8 = 37;
+pub const KV_SENDING_LOCKOUT: u8 = 38;
+pub const KV_TOKEN_REVOCATION: u8 = 39;
+pub const KV_QUARANTINE: u8 = 40;
+pub const KV_BOUNCE_ORIGIN: u8 = 41;
 
 #[derive(Clone)]
 pub struct Server {
@@ -147,6 +164,8 @@ pub struct Data {
     pub queue_id_gen: SnowflakeIdGenerator,
     pub span_id_gen: SnowflakeIdGenerator,
     pub queue_status: AtomicBool,
+    pub queue_throttle: AdaptiveThrottle,
+    pub delivery_hook_breakers: CircuitBreakers,
 
     pub webadmin: WebAdminManager,
     pub logos: Mutex<AHashMap<String, Option<Resource<Vec<u8>>>>>,
@@ -518,6 +537,15 @@ pub fn ip_to_bytes(ip: &IpAddr) -> Vec<u8> {
     }
 }
 
+/// Rate limiter key for a remote peer, Unix domain socket peers all share the
+/// unspecified address and are told apart by their uid instead
+pub fn peer_to_bytes(ip: &IpAddr, peer_uid: Option<u32>) -> Vec<u8> {
+    match peer_uid {
+        Some(uid) if ip.is_unspecified() => format!("uid:{uid}").into_bytes(),
+        _ => ip_to_bytes(ip),
+    }
+}
+
 pub fn ip_to_bytes_prefix(prefix: u8, ip: &IpAddr) -> Vec<u8> {
     match ip {
         IpAddr::V4(ip) => {
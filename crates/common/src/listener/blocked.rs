@@ -18,7 +18,7 @@ use utils::{
 
 use crate::{
     KV_RATE_LIMIT_AUTH, KV_RATE_LIMIT_LOITER, KV_RATE_LIMIT_RCPT, KV_RATE_LIMIT_SCAN, Server,
-    ip_to_bytes, ipc::BroadcastEvent, manager::config::MatchType,
+    ip_to_bytes, ipc::BroadcastEvent, manager::config::MatchType, peer_to_bytes,
 };
 
 #[derive(Debug, Clone)]
@@ -139,7 +139,7 @@ impl Security {
 impl Server {
     pub async fn is_rcpt_fail2banned(&self, ip: IpAddr, rcpt: &str) -> trc::Result<bool> {
         if let Some(rate) = &self.core.network.security.rcpt_fail_rate {
-            let is_allowed = self.is_ip_allowed(&ip)
+            let is_allowed = self.is_ip_ban_exempt(&ip)
                 || (self
                     .in_memory_store()
                     .is_rate_allowed(KV_RATE_LIMIT_RCPT, &ip_to_bytes(&ip), rate, false)
@@ -161,7 +161,7 @@ impl Server {
 
     pub async fn is_scanner_fail2banned(&self, ip: IpAddr) -> trc::Result<bool> {
         if let Some(rate) = &self.core.network.security.scanner_fail_rate {
-            let is_allowed = self.is_ip_allowed(&ip)
+            let is_allowed = self.is_ip_ban_exempt(&ip)
                 || self
                     .in_memory_store()
                     .is_rate_allowed(KV_RATE_LIMIT_SCAN, &ip_to_bytes(&ip), rate, false)
@@ -179,7 +179,8 @@ impl Server {
     pub async fn is_http_banned_path(&self, path: &str, ip: IpAddr) -> trc::Result<bool> {
         let paths = &self.core.network.security.http_banned_paths;
 
-        if !paths.is_empty() && paths.iter().any(|p| p.matches(path)) && !self.is_ip_allowed(&ip) {
+        if !paths.is_empty() && paths.iter().any(|p| p.matches(path)) && !self.is_ip_ban_exempt(&ip)
+        {
             self.block_ip(ip).await.map(|_| true)
         } else {
             Ok(false)
@@ -188,7 +189,7 @@ impl Server {
 
     pub async fn is_loiter_fail2banned(&self, ip: IpAddr) -> trc::Result<bool> {
         if let Some(rate) = &self.core.network.security.loiter_fail_rate {
-            let is_allowed = self.is_ip_allowed(&ip)
+            let is_allowed = self.is_ip_ban_exempt(&ip)
                 || self
                     .in_memory_store()
                     .is_rate_allowed(KV_RATE_LIMIT_LOITER, &ip_to_bytes(&ip), rate, false)
@@ -203,13 +204,23 @@ impl Server {
         Ok(false)
     }
 
-    pub async fn is_auth_fail2banned(&self, ip: IpAddr, login: Option<&str>) -> trc::Result<bool> {
+    pub async fn is_auth_fail2banned(
+        &self,
+        ip: IpAddr,
+        peer_uid: Option<u32>,
+        login: Option<&str>,
+    ) -> trc::Result<bool> {
         if let Some(rate) = &self.core.network.security.auth_fail_rate {
             let login = login.unwrap_or_default();
             let is_allowed = self.is_ip_allowed(&ip)
                 || (self
                     .in_memory_store()
-                    .is_rate_allowed(KV_RATE_LIMIT_AUTH, &ip_to_bytes(&ip), rate, false)
+                    .is_rate_allowed(
+                        KV_RATE_LIMIT_AUTH,
+                        &peer_to_bytes(&ip, peer_uid),
+                        rate,
+                        false,
+                    )
                     .await?
                     .is_none()
                     && (login.is_empty()
@@ -219,7 +230,11 @@ impl Server {
                             .await?
                             .is_none()));
             if !is_allowed {
-                return self.block_ip(ip).await.map(|_| true);
+                return if self.is_ip_ban_exempt(&ip) {
+                    Ok(true)
+                } else {
+                    self.block_ip(ip).await.map(|_| true)
+                };
             }
         }
 
@@ -255,15 +270,16 @@ impl Server {
     }
 
     pub fn is_ip_blocked(&self, ip: &IpAddr) -> bool {
-        self.inner.data.blocked_ips.read().contains(ip)
-            || (self.core.network.security.has_blocked_networks
-                && self
-                    .core
-                    .network
-                    .security
-                    .blocked_ip_networks
-                    .iter()
-                    .any(|network| network.matches(ip)))
+        !ip.is_unspecified()
+            && (self.inner.data.blocked_ips.read().contains(ip)
+                || (self.core.network.security.has_blocked_networks
+                    && self
+                        .core
+                        .network
+                        .security
+                        .blocked_ip_networks
+                        .iter()
+                        .any(|network| network.matches(ip))))
     }
 
     pub fn is_ip_allowed(&self, ip: &IpAddr) -> bool {
@@ -277,6 +293,12 @@ impl Server {
                     .iter()
                     .any(|network| network.matches(ip)))
     }
+
+    /// Unix domain socket peers share the unspecified address, banning it would
+    /// lock out every local client
+    pub fn is_ip_ban_exempt(&self, ip: &IpAddr) -> bool {
+        ip.is_unspecified() || self.is_ip_allowed(ip)
+    }
 }
 
 impl BlockedIps {
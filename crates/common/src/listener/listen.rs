@@ -5,7 +5,7 @@
  */
 
 use std::{
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     sync::Arc,
     time::Duration,
 };
@@ -19,7 +19,7 @@ use utils::{UnwrapFailure, config::Config};
 
 use crate::{
     Inner, Server,
-    config::server::{Listener, Listeners, ServerProtocol, TcpListener},
+    config::server::{Listener, Listeners, ServerProtocol, TcpListener, UnixListener},
     core::BuildServer,
 };
 
@@ -49,6 +49,28 @@ impl Listener {
         let is_tls = matches!(instance.acceptor, TcpAcceptor::Tls { implicit, .. } if implicit);
         let is_https = is_tls && self.protocol == ServerProtocol::Http;
         let has_proxies = !instance.proxy_networks.is_empty();
+        let (span_start, span_end) = match self.protocol {
+            ServerProtocol::Smtp | ServerProtocol::Lmtp => (
+                EventType::Smtp(SmtpEvent::ConnectionStart),
+                EventType::Smtp(SmtpEvent::ConnectionEnd),
+            ),
+            ServerProtocol::Imap => (
+                EventType::Imap(ImapEvent::ConnectionStart),
+                EventType::Imap(ImapEvent::ConnectionEnd),
+            ),
+            ServerProtocol::Pop3 => (
+                EventType::Pop3(Pop3Event::ConnectionStart),
+                EventType::Pop3(Pop3Event::ConnectionEnd),
+            ),
+            ServerProtocol::Http => (
+                EventType::Http(HttpEvent::ConnectionStart),
+                EventType::Http(HttpEvent::ConnectionEnd),
+            ),
+            ServerProtocol::ManageSieve => (
+                EventType::ManageSieve(ManageSieveEvent::ConnectionStart),
+                EventType::ManageSieve(ManageSieveEvent::ConnectionEnd),
+            ),
+        };
 
         // Spawn listeners
         for listener in self.listeners {
@@ -94,29 +116,6 @@ impl Listener {
             let instance = instance.clone();
             let inner = inner.clone();
             tokio::spawn(async move {
-                let (span_start, span_end) = match self.protocol {
-                    ServerProtocol::Smtp | ServerProtocol::Lmtp => (
-                        EventType::Smtp(SmtpEvent::ConnectionStart),
-                        EventType::Smtp(SmtpEvent::ConnectionEnd),
-                    ),
-                    ServerProtocol::Imap => (
-                        EventType::Imap(ImapEvent::ConnectionStart),
-                        EventType::Imap(ImapEvent::ConnectionEnd),
-                    ),
-                    ServerProtocol::Pop3 => (
-                        EventType::Pop3(Pop3Event::ConnectionStart),
-                        EventType::Pop3(Pop3Event::ConnectionEnd),
-                    ),
-                    ServerProtocol::Http => (
-                        EventType::Http(HttpEvent::ConnectionStart),
-                        EventType::Http(HttpEvent::ConnectionEnd),
-                    ),
-                    ServerProtocol::ManageSieve => (
-                        EventType::ManageSieve(ManageSieveEvent::ConnectionStart),
-                        EventType::ManageSieve(ManageSieveEvent::ConnectionEnd),
-                    ),
-                };
-
                 loop {
                     tokio::select! {
                         stream = listener.accept() => {
@@ -193,6 +192,85 @@ impl Listener {
                 }
             });
         }
+
+        // Spawn Unix domain socket listeners
+        #[cfg(unix)]
+        for listener in self.unix_listeners {
+            let path = listener.path.display().to_string();
+            let listener = match listener.listen() {
+                Ok(listener) => {
+                    trc::event!(
+                        Network(trc::NetworkEvent::ListenStart),
+                        ListenerId = instance.id.clone(),
+                        Path = path.clone(),
+                        Tls = is_tls,
+                    );
+
+                    listener
+                }
+                Err(err) => {
+                    trc::event!(
+                        Network(trc::NetworkEvent::ListenError),
+                        ListenerId = instance.id.clone(),
+                        Path = path,
+                        Tls = is_tls,
+                        Reason = err,
+                    );
+
+                    continue;
+                }
+            };
+
+            // Spawn listener
+            let mut shutdown_rx = instance.shutdown_rx.clone();
+            let manager = manager.clone();
+            let instance = instance.clone();
+            let inner = inner.clone();
+            tokio::spawn(async move {
+                // Unix domain sockets have no network addresses, peers are
+                // identified by their credentials instead. The unspecified address
+                // is used so that Unix peers never match loopback exemptions.
+                let local_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+                loop {
+                    tokio::select! {
+                        stream = listener.accept() => {
+                            match stream {
+                                Ok((stream, _)) => {
+                                    let server = inner.build_server();
+                                    let enable_acme = (is_https && server.has_acme_tls_providers()).then(|| server.clone());
+
+                                    if let Some(session) = instance.build_session(stream, local_addr, local_addr, &server) {
+                                        manager.spawn(session, is_tls, enable_acme, span_start, span_end);
+                                    }
+                                }
+                                Err(err) => {
+                                    trc::event!(
+                                        Network(trc::NetworkEvent::AcceptError),
+                                        ListenerId = instance.id.clone(),
+                                        Path = path.clone(),
+                                        Tls = is_tls,
+                                        Reason = err.to_string(),
+                                    );
+                                }
+                            }
+                        },
+                        _ = shutdown_rx.changed() => {
+                            trc::event!(
+                                Network(trc::NetworkEvent::ListenStop),
+                                ListenerId = instance.id.clone(),
+                                Path = path.clone(),
+                                Tls = is_tls,
+                            );
+
+                            let _ = std::fs::remove_file(&path);
+                            manager.shutdown().await;
+                            break;
+                        }
+                    };
+                }
+            });
+        }
     }
 }
 
@@ -301,9 +379,9 @@ impl SocketOpts {
 }
 
 impl Listeners {
-    pub fn bind_and_drop_priv(&self, config: &mut Config) {
+    pub fn bind_and_drop_priv(&mut self, config: &mut Config) {
         // Bind as root
-        for server in &self.servers {
+        for server in &mut self.servers {
             for listener in &server.listeners {
                 if let Err(err) = listener.socket.bind(listener.addr) {
                     config.new_build_error(
@@ -312,6 +390,16 @@ impl Listeners {
                     );
                 }
             }
+
+            #[cfg(unix)]
+            for listener in &mut server.unix_listeners {
+                if let Err(err) = listener.bind() {
+                    config.new_build_error(
+                        format!("server.listener.{}", server.id),
+                        format!("Failed to bind to {}: {}", listener.path.display(), err),
+                    );
+                }
+            }
         }
 
         // Drop privileges
@@ -357,6 +445,76 @@ impl TcpListener {
     }
 }
 
+#[cfg(unix)]
+impl UnixListener {
+    pub fn bind(&mut self) -> std::io::Result<()> {
+        use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+        // Remove stale sockets left behind by a previous instance
+        if std::fs::symlink_metadata(&self.path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            std::fs::remove_file(&self.path)?;
+        }
+
+        let socket = tokio::net::UnixSocket::new_stream()?;
+        socket.bind(&self.path)?;
+
+        if let Some(mode) = self.mode {
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(mode))?;
+        }
+        if self.owner.is_some() || self.group.is_some() {
+            let uid = self
+                .owner
+                .as_deref()
+                .map(|owner| {
+                    resolve_user(owner)
+                        .ok_or_else(|| std::io::Error::other(format!("Unknown user {owner:?}")))
+                })
+                .transpose()?;
+            let gid = self
+                .group
+                .as_deref()
+                .map(|group| {
+                    resolve_group(group)
+                        .ok_or_else(|| std::io::Error::other(format!("Unknown group {group:?}")))
+                })
+                .transpose()?;
+            std::os::unix::fs::chown(&self.path, uid, gid)?;
+        }
+
+        self.socket = Some(socket);
+        Ok(())
+    }
+
+    pub fn listen(self) -> Result<tokio::net::UnixListener, String> {
+        self.socket
+            .ok_or_else(|| format!("Socket {} is not bound", self.path.display()))?
+            .listen(self.backlog.unwrap_or(1024))
+            .map_err(|err| format!("Failed to listen on {}: {}", self.path.display(), err))
+    }
+}
+
+#[cfg(unix)]
+fn resolve_user(name: &str) -> Option<u32> {
+    if let Ok(uid) = name.parse() {
+        Some(uid)
+    } else {
+        let name = std::ffi::CString::new(name).ok()?;
+        let passwd = unsafe { libc::getpwnam(name.as_ptr()) };
+        (!passwd.is_null()).then(|| unsafe { (*passwd).pw_uid })
+    }
+}
+
+#[cfg(unix)]
+fn resolve_group(name: &str) -> Option<u32> {
+    if let Ok(gid) = name.parse() {
+        Some(gid)
+    } else {
+        let name = std::ffi::CString::new(name).ok()?;
+        let group = unsafe { libc::getgrnam(name.as_ptr()) };
+        (!group.is_null()).then(|| unsafe { (*group).gr_gid })
+    }
+}
+
 impl ServerInstance {
     pub async fn tls_accept<T: SessionStream>(
         &self,
@@ -7,7 +7,7 @@
 use std::{borrow::Cow, net::IpAddr, sync::Arc, time::Instant};
 
 use compact_str::ToCompactString;
-use rustls::ServerConfig;
+use rustls::{ServerConfig, pki_types::CertificateDer};
 use std::fmt::Debug;
 use tokio::{
     io::{AsyncRead, AsyncWrite},
@@ -79,6 +79,47 @@ pub struct SessionData<T: SessionStream> {
 pub trait SessionStream: AsyncRead + AsyncWrite + Unpin + 'static + Sync + Send {
     fn is_tls(&self) -> bool;
     fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>);
+
+    /// Channel binding data, only available when TLS is terminated by this server
+    fn tls_channel_binding(&self) -> Option<TlsChannelBinding> {
+        None
+    }
+
+    /// Application protocol negotiated using ALPN, if any
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Credentials of the connecting process, only available on Unix domain sockets
+    fn peer_credentials(&self) -> Option<PeerCredentials> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: Option<i32>,
+}
+
+impl PeerCredentials {
+    pub fn resolve_variable(&self, variable: u32) -> Variable<'static> {
+        match variable {
+            V_PEER_UID => (self.uid as i64).into(),
+            V_PEER_GID => (self.gid as i64).into(),
+            V_PEER_PID => self.pid.map(|pid| pid as i64).unwrap_or_default().into(),
+            _ => Variable::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TlsChannelBinding {
+    /// Keying material for the `tls-exporter` channel binding (RFC 9266), TLS 1.3 only
+    pub exporter: Option<Vec<u8>>,
+    /// Certificate presented during the handshake, used for `tls-server-end-point` (RFC 5929)
+    pub certificate: Option<CertificateDer<'static>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -86,6 +127,7 @@ pub enum SessionResult {
     Continue,
     Close,
     UpgradeTls,
+    UpgradeCompression,
 }
 
 pub trait SessionManager: Sync + Send + 'static + Clone {
@@ -99,7 +141,7 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
     ) {
         let manager = self.clone();
 
-        tokio::spawn(async move {
+        let future = async move {
             let start_time = Instant::now();
             let local_port = session.local_port;
             let session_id;
@@ -210,7 +252,10 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
                 ],
             )
             .send_with_metrics();
-        });
+        };
+
+        // Keep track of the certificate presented during TLS handshakes
+        tokio::spawn(tls::PRESENTED_CERTIFICATE.scope(Default::default(), future));
     }
 
     fn handle<T: SessionStream>(
@@ -231,6 +276,11 @@ impl<T: SessionStream> ResolveVariable for SessionData<T> {
             V_LISTENER => self.instance.id.as_str().into(),
             V_PROTOCOL => self.protocol.as_str().into(),
             V_TLS => self.stream.is_tls().into(),
+            V_PEER_UID | V_PEER_GID | V_PEER_PID => self
+                .stream
+                .peer_credentials()
+                .map(|peer| peer.resolve_variable(variable))
+                .unwrap_or_default(),
             _ => crate::expr::Variable::default(),
         }
     }
@@ -4,16 +4,22 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
 
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
 use proxy_header::io::ProxiedStream;
 use tokio::{
-    io::{AsyncRead, AsyncWrite},
+    io::{AsyncRead, AsyncWrite, ReadBuf},
     net::TcpStream,
 };
 use tokio_rustls::server::TlsStream;
 
-use super::SessionStream;
+use super::{PeerCredentials, SessionStream, TlsChannelBinding, tls::PRESENTED_CERTIFICATE};
 
 impl SessionStream for TcpStream {
     fn is_tls(&self) -> bool {
@@ -25,6 +31,25 @@ impl SessionStream for TcpStream {
     }
 }
 
+#[cfg(unix)]
+impl SessionStream for tokio::net::UnixStream {
+    fn is_tls(&self) -> bool {
+        false
+    }
+
+    fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>) {
+        (Cow::Borrowed(""), Cow::Borrowed(""))
+    }
+
+    fn peer_credentials(&self) -> Option<PeerCredentials> {
+        self.peer_cred().ok().map(|cred| PeerCredentials {
+            uid: cred.uid(),
+            gid: cred.gid(),
+            pid: cred.pid(),
+        })
+    }
+}
+
 impl<T: SessionStream> SessionStream for TlsStream<T> {
     fn is_tls(&self) -> bool {
         true
@@ -62,6 +87,33 @@ impl<T: SessionStream> SessionStream for TlsStream<T> {
             .into(),
         )
     }
+
+    fn tls_channel_binding(&self) -> Option<TlsChannelBinding> {
+        let (_, conn) = self.get_ref();
+
+        Some(TlsChannelBinding {
+            // Exported keying material is only unique per connection with TLS 1.3
+            exporter: (conn.protocol_version() == Some(rustls::ProtocolVersion::TLSv1_3))
+                .then(|| {
+                    conn.export_keying_material([0u8; 32], b"EXPORTER-Channel-Binding", None)
+                        .ok()
+                        .map(|material| material.to_vec())
+                })
+                .flatten(),
+            certificate: PRESENTED_CERTIFICATE
+                .try_with(|certificate| certificate.borrow().clone())
+                .ok()
+                .flatten(),
+        })
+    }
+
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.get_ref().1.alpn_protocol()
+    }
+
+    fn peer_credentials(&self) -> Option<PeerCredentials> {
+        self.get_ref().0.peer_credentials()
+    }
 }
 
 impl SessionStream for ProxiedStream<TcpStream> {
@@ -84,6 +136,169 @@ impl SessionStream for ProxiedStream<TcpStream> {
     }
 }
 
+/// Wraps a stream with raw DEFLATE compression in both directions (RFC 4978)
+pub struct DeflateStream<T> {
+    inner: T,
+    compress: Compress,
+    decompress: Decompress,
+    read_buf: Box<[u8]>,
+    read_pos: usize,
+    read_len: usize,
+    write_buf: Vec<u8>,
+    needs_flush: bool,
+}
+
+impl<T> DeflateStream<T> {
+    pub fn new(inner: T) -> Self {
+        DeflateStream {
+            inner,
+            compress: Compress::new(Compression::default(), false),
+            decompress: Decompress::new(false),
+            read_buf: vec![0; 8192].into_boxed_slice(),
+            read_pos: 0,
+            read_len: 0,
+            write_buf: Vec::with_capacity(8192),
+            needs_flush: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    fn deflate(&mut self, mut bytes: &[u8], flush: FlushCompress) -> io::Result<()> {
+        loop {
+            self.write_buf.reserve(bytes.len() + 128);
+            let total_in = self.compress.total_in();
+            self.compress
+                .compress_vec(bytes, &mut self.write_buf, flush)
+                .map_err(io::Error::other)?;
+            bytes = &bytes[(self.compress.total_in() - total_in) as usize..];
+
+            // Output space left over means the compressor has nothing else to emit
+            if bytes.is_empty() && self.write_buf.len() < self.write_buf.capacity() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> DeflateStream<T> {
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            let bytes_written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.write_buf))?;
+            if bytes_written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_buf.drain(..bytes_written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for DeflateStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            let output = buf.initialize_unfilled();
+            if output.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+
+            let total_in = this.decompress.total_in();
+            let total_out = this.decompress.total_out();
+            let status = this
+                .decompress
+                .decompress(
+                    &this.read_buf[this.read_pos..this.read_len],
+                    output,
+                    FlushDecompress::None,
+                )
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            let bytes_in = (this.decompress.total_in() - total_in) as usize;
+            let bytes_out = (this.decompress.total_out() - total_out) as usize;
+            this.read_pos += bytes_in;
+
+            if bytes_out > 0 {
+                buf.advance(bytes_out);
+                return Poll::Ready(Ok(()));
+            } else if status == Status::StreamEnd {
+                return Poll::Ready(Ok(()));
+            } else if this.read_pos == this.read_len {
+                let mut read_buf = ReadBuf::new(&mut this.read_buf);
+                ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read_buf))?;
+                let bytes_read = read_buf.filled().len();
+                if bytes_read == 0 {
+                    return Poll::Ready(Ok(()));
+                }
+                this.read_pos = 0;
+                this.read_len = bytes_read;
+            } else if bytes_in == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Decompressor made no progress",
+                )));
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for DeflateStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        this.deflate(buf, FlushCompress::None)?;
+        this.needs_flush = true;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.needs_flush {
+            this.deflate(&[], FlushCompress::Sync)?;
+            this.needs_flush = false;
+        }
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl<T: SessionStream> SessionStream for DeflateStream<T> {
+    fn is_tls(&self) -> bool {
+        self.inner.is_tls()
+    }
+
+    fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>) {
+        self.inner.tls_version_and_cipher()
+    }
+
+    fn tls_channel_binding(&self) -> Option<TlsChannelBinding> {
+        self.inner.tls_channel_binding()
+    }
+
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.inner.alpn_protocol()
+    }
+
+    fn peer_credentials(&self) -> Option<PeerCredentials> {
+        self.inner.peer_credentials()
+    }
+}
+
 #[derive(Default)]
 pub struct NullIo {
     pub tx_buf: Vec<u8>,
@@ -141,3 +356,42 @@ impl SessionStream for NullIo {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::DeflateStream;
+
+    #[tokio::test]
+    async fn deflate_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let mut client = DeflateStream::new(client);
+        let mut server = DeflateStream::new(server);
+
+        let request = b"A1 FETCH 1:* (FLAGS BODY.PEEK[HEADER])\r\n".repeat(512);
+        client.write_all(&request).await.unwrap();
+        client.flush().await.unwrap();
+
+        let mut buf = vec![0; request.len()];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, request);
+
+        // Each flush must deliver all pending data to the peer
+        for response in [&b"* OK Hello\r\n"[..], b"A1 OK Done\r\n"] {
+            server.write_all(response).await.unwrap();
+            server.flush().await.unwrap();
+
+            let mut buf = vec![0; response.len()];
+            client.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, response);
+        }
+    }
+}
@@ -5,6 +5,7 @@
  */
 
 use std::{
+    cell::RefCell,
     cmp::Ordering,
     fmt::{self, Formatter},
     sync::Arc,
@@ -13,6 +14,7 @@ use std::{
 use ahash::AHashMap;
 use rustls::{
     SupportedProtocolVersion,
+    pki_types::CertificateDer,
     server::{ClientHello, ResolvesServerCert},
     sign::CertifiedKey,
     version::{TLS12, TLS13},
@@ -49,9 +51,19 @@ impl CertificateResolver {
     }
 }
 
+tokio::task_local! {
+    /// Certificate presented by the last full TLS handshake of the current session,
+    /// used for the `tls-server-end-point` channel binding
+    pub(crate) static PRESENTED_CERTIFICATE: RefCell<Option<CertificateDer<'static>>>;
+}
+
 impl ResolvesServerCert for CertificateResolver {
     fn resolve(&self, hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
-        self.resolve_certificate(hello.server_name())
+        let key = self.resolve_certificate(hello.server_name());
+        let _ = PRESENTED_CERTIFICATE.try_with(|certificate| {
+            *certificate.borrow_mut() = key.as_ref().and_then(|key| key.cert.first().cloned());
+        });
+        key
     }
 }
 
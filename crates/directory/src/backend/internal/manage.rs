@@ -11,7 +11,11 @@ use super::{
 use crate::{
     ArchivedPrincipalData, FALLBACK_ADMIN_ID, MemberOf, Permission, PermissionGrant, Permissions,
     Principal, PrincipalData, QueryBy, QueryParams, ROLE_ADMIN, ROLE_TENANT_ADMIN, ROLE_USER, Type,
-    backend::RcptType, core::principal::build_search_index,
+    backend::RcptType,
+    core::{
+        principal::build_search_index,
+        scram::{SCRAM_DEFAULT_ITERATIONS, ScramVerifier},
+    },
 };
 use ahash::{AHashMap, AHashSet};
 use compact_str::CompactString;
@@ -398,11 +402,26 @@ impl ManageDirectory for Store {
                 create_principal
                     .data
                     .push(PrincipalData::AppPassword(secret));
+            } else if secret.is_scram_secret() {
+                create_principal
+                    .data
+                    .push(PrincipalData::ScramVerifier(secret));
             } else if !has_secret {
                 has_secret = true;
                 create_principal.data.push(PrincipalData::Password(secret));
             }
         }
+        if create_principal
+            .data
+            .iter()
+            .any(|v| matches!(v, PrincipalData::OtpAuth(_)))
+        {
+            // SCRAM cannot verify TOTP codes
+            create_principal
+                .data
+                .retain(|v| !matches!(v, PrincipalData::ScramVerifier(_)));
+        }
+        add_scram_verifier(&mut create_principal.data);
 
         if let Some(description) = principal_set.take_str(PrincipalField::Description) {
             create_principal
@@ -416,6 +435,11 @@ impl ManageDirectory for Store {
         if let Some(picture) = principal_set.take_str(PrincipalField::Locale) {
             create_principal.data.push(PrincipalData::Locale(picture));
         }
+        if let Some(delay) = principal_set.take_int(PrincipalField::UndoSendDelay) {
+            create_principal
+                .data
+                .push(PrincipalData::UndoSendDelay(delay));
+        }
         for url in principal_set
             .take_str_array(PrincipalField::Urls)
             .unwrap_or_default()
@@ -1175,6 +1199,7 @@ impl ManageDirectory for Store {
                             PrincipalData::Password(_)
                                 | PrincipalData::AppPassword(_)
                                 | PrincipalData::OtpAuth(_)
+                                | PrincipalData::ScramVerifier(_)
                         )
                     });
                     let mut has_secret = false;
@@ -1183,11 +1208,24 @@ impl ManageDirectory for Store {
                             principal.data.push(PrincipalData::OtpAuth(secret));
                         } else if secret.is_app_secret() {
                             principal.data.push(PrincipalData::AppPassword(secret));
+                        } else if secret.is_scram_secret() {
+                            principal.data.push(PrincipalData::ScramVerifier(secret));
                         } else if !has_secret {
                             has_secret = true;
                             principal.data.push(PrincipalData::Password(secret));
                         }
                     }
+                    if principal
+                        .data
+                        .iter()
+                        .any(|v| matches!(v, PrincipalData::OtpAuth(_)))
+                    {
+                        // SCRAM cannot verify TOTP codes
+                        principal
+                            .data
+                            .retain(|v| !matches!(v, PrincipalData::ScramVerifier(_)));
+                    }
+                    add_scram_verifier(&mut principal.data);
                 }
                 (
                     PrincipalAction::AddItem,
@@ -1197,21 +1235,42 @@ impl ManageDirectory for Store {
                     if !principal.data.iter().any(|v| match v {
                         PrincipalData::Password(v)
                         | PrincipalData::AppPassword(v)
-                        | PrincipalData::OtpAuth(v) => *v == secret,
+                        | PrincipalData::OtpAuth(v)
+                        | PrincipalData::ScramVerifier(v) => *v == secret,
                         _ => false,
                     }) {
                         if secret.is_app_secret() {
                             principal.data.push(PrincipalData::AppPassword(secret));
                         } else if secret.is_otp_secret() {
-                            principal
-                                .data
-                                .retain(|v| !matches!(v, PrincipalData::OtpAuth(_)));
+                            // SCRAM cannot verify TOTP codes, remove any stored verifier
+                            principal.data.retain(|v| {
+                                !matches!(
+                                    v,
+                                    PrincipalData::OtpAuth(_) | PrincipalData::ScramVerifier(_)
+                                )
+                            });
                             principal.data.push(PrincipalData::OtpAuth(secret));
-                        } else {
+                        } else if secret.is_scram_secret() {
                             principal
                                 .data
-                                .retain(|v| !matches!(v, PrincipalData::Password(_)));
+                                .retain(|v| !matches!(v, PrincipalData::ScramVerifier(_)));
+                            if !principal
+                                .data
+                                .iter()
+                                .any(|v| matches!(v, PrincipalData::OtpAuth(_)))
+                            {
+                                principal.data.push(PrincipalData::ScramVerifier(secret));
+                            }
+                        } else {
+                            // Verifiers derived from the previous password are no longer valid
+                            principal.data.retain(|v| {
+                                !matches!(
+                                    v,
+                                    PrincipalData::Password(_) | PrincipalData::ScramVerifier(_)
+                                )
+                            });
                             principal.data.push(PrincipalData::Password(secret));
+                            add_scram_verifier(&mut principal.data);
                         }
 
                         // Password changed, update changed principals
@@ -1235,7 +1294,9 @@ impl ManageDirectory for Store {
                         });
                     } else if !secret.is_empty() {
                         principal.data.retain(|v| match v {
-                            PrincipalData::Password(v) => *v != secret,
+                            PrincipalData::Password(v) | PrincipalData::ScramVerifier(v) => {
+                                *v != secret
+                            }
                             _ => true,
                         });
                     } else {
@@ -1272,6 +1333,27 @@ impl ManageDirectory for Store {
                         principal.data.push(PrincipalData::Locale(value));
                     }
                 }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::UndoSendDelay,
+                    PrincipalValue::Integer(delay),
+                ) => {
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                    principal
+                        .data
+                        .retain(|v| !matches!(v, PrincipalData::UndoSendDelay(_)));
+                    principal.data.push(PrincipalData::UndoSendDelay(delay));
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::UndoSendDelay,
+                    PrincipalValue::String(delay),
+                ) if delay.is_empty() => {
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                    principal
+                        .data
+                        .retain(|v| !matches!(v, PrincipalData::UndoSendDelay(_)));
+                }
                 (PrincipalAction::Set, PrincipalField::Quota, PrincipalValue::Integer(quota))
                     if matches!(
                         principal_type,
@@ -2389,7 +2471,8 @@ impl ManageDirectory for Store {
                 }
                 PrincipalData::Password(secret)
                 | PrincipalData::AppPassword(secret)
-                | PrincipalData::OtpAuth(secret) => {
+                | PrincipalData::OtpAuth(secret)
+                | PrincipalData::ScramVerifier(secret) => {
                     if fields.is_empty() || fields.contains(&PrincipalField::Secrets) {
                         result.append_str(PrincipalField::Secrets, secret);
                     }
@@ -2409,6 +2492,11 @@ impl ManageDirectory for Store {
                         result.set(PrincipalField::Locale, locale);
                     }
                 }
+                PrincipalData::UndoSendDelay(delay) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::UndoSendDelay) {
+                        result.set(PrincipalField::UndoSendDelay, delay);
+                    }
+                }
                 PrincipalData::ExternalMember(member) => {
                     if fields.is_empty() || fields.contains(&PrincipalField::ExternalMembers) {
                         result.append_str(PrincipalField::ExternalMembers, member);
@@ -2701,7 +2789,8 @@ impl ChangedPrincipals {
                     | PrincipalField::Tenant
                     | PrincipalField::Roles
                     | PrincipalField::EnabledPermissions
-                    | PrincipalField::DisabledPermissions,
+                    | PrincipalField::DisabledPermissions
+                    | PrincipalField::UndoSendDelay,
             ) | (
                 Type::Tenant | Type::Role | Type::ApiKey | Type::OauthClient,
                 PrincipalField::MemberOf
@@ -2820,6 +2909,28 @@ pub fn err_missing(field: impl Into<trc::Value>) -> trc::Error {
     trc::ManageEvent::MissingParameter.ctx(trc::Key::Key, field)
 }
 
+// Store a SCRAM verifier derived from the account password, unless one was provided
+// or TOTP is enabled, so that SCRAM works without waiting for a cleartext login
+fn add_scram_verifier(data: &mut Vec<PrincipalData>) {
+    if data.iter().any(|v| {
+        matches!(
+            v,
+            PrincipalData::OtpAuth(_) | PrincipalData::ScramVerifier(_)
+        )
+    }) {
+        return;
+    }
+
+    if let Some(verifier) = data.iter().find_map(|v| match v {
+        PrincipalData::Password(secret) => {
+            ScramVerifier::from_secret(secret, SCRAM_DEFAULT_ITERATIONS)
+        }
+        _ => None,
+    }) {
+        data.push(PrincipalData::ScramVerifier(verifier.to_string()));
+    }
+}
+
 pub fn err_exists(field: impl Into<trc::Value>, value: impl Into<trc::Value>) -> trc::Error {
     trc::ManageEvent::AlreadyExists
         .ctx(trc::Key::Key, field)
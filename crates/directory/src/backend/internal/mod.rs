@@ -7,7 +7,7 @@
 pub mod lookup;
 pub mod manage;
 
-use crate::Type;
+use crate::{Type, core::scram::SCRAM_SHA256_PREFIX};
 use ahash::AHashMap;
 
 use std::fmt::Display;
@@ -108,6 +108,7 @@ pub enum PrincipalField {
     Urls,
     ExternalMembers,
     Locale,
+    UndoSendDelay,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -196,6 +197,7 @@ impl PrincipalField {
             PrincipalField::Urls => 15,
             PrincipalField::ExternalMembers => 16,
             PrincipalField::Locale => 17,
+            PrincipalField::UndoSendDelay => 18,
         }
     }
 
@@ -219,6 +221,7 @@ impl PrincipalField {
             15 => Some(PrincipalField::Urls),
             16 => Some(PrincipalField::ExternalMembers),
             17 => Some(PrincipalField::Locale),
+            18 => Some(PrincipalField::UndoSendDelay),
             _ => None,
         }
     }
@@ -243,6 +246,7 @@ impl PrincipalField {
             PrincipalField::Urls => "urls",
             PrincipalField::ExternalMembers => "externalMembers",
             PrincipalField::Locale => "locale",
+            PrincipalField::UndoSendDelay => "undoSendDelay",
         }
     }
 
@@ -266,6 +270,7 @@ impl PrincipalField {
             "urls" => Some(PrincipalField::Urls),
             "externalMembers" => Some(PrincipalField::ExternalMembers),
             "locale" => Some(PrincipalField::Locale),
+            "undoSendDelay" => Some(PrincipalField::UndoSendDelay),
             _ => None,
         }
     }
@@ -274,6 +279,7 @@ impl PrincipalField {
 pub trait SpecialSecrets {
     fn is_otp_secret(&self) -> bool;
     fn is_app_secret(&self) -> bool;
+    fn is_scram_secret(&self) -> bool;
 }
 
 impl<T> SpecialSecrets for T
@@ -287,4 +293,8 @@ where
     fn is_app_secret(&self) -> bool {
         self.as_ref().starts_with("$app$")
     }
+
+    fn is_scram_secret(&self) -> bool {
+        self.as_ref().starts_with(SCRAM_SHA256_PREFIX)
+    }
 }
@@ -456,6 +456,8 @@ impl LdapMappings {
                         otp_secret = Some(item);
                     } else if item.is_app_secret() {
                         principal.data.push(PrincipalData::AppPassword(item));
+                    } else if item.is_scram_secret() {
+                        principal.data.push(PrincipalData::ScramVerifier(item));
                     } else if secret.is_none() {
                         secret = Some(item);
                     }
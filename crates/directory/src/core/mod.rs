@@ -10,6 +10,7 @@ pub mod cache;
 pub mod config;
 pub mod dispatch;
 pub mod principal;
+pub mod scram;
 pub mod secret;
 
 impl Permission {
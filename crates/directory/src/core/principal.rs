@@ -334,6 +334,7 @@ impl Principal {
                     external_data.insert(item);
                 }
                 PrincipalData::Password(_)
+                | PrincipalData::ScramVerifier(_)
                 | PrincipalData::Description(_)
                 | PrincipalData::PrimaryEmail(_)
                 | PrincipalData::EmailAlias(_) => {
@@ -351,6 +352,7 @@ impl Principal {
                 PrincipalData::Password(_)
                 | PrincipalData::AppPassword(_)
                 | PrincipalData::OtpAuth(_)
+                | PrincipalData::ScramVerifier(_)
                 | PrincipalData::Description(_)
                 | PrincipalData::PrimaryEmail(_)
                 | PrincipalData::EmailAlias(_)
@@ -374,6 +376,7 @@ impl Principal {
                         PrincipalData::Password(_)
                             | PrincipalData::AppPassword(_)
                             | PrincipalData::OtpAuth(_)
+                            | PrincipalData::ScramVerifier(_)
                             | PrincipalData::PrimaryEmail(_)
                             | PrincipalData::EmailAlias(_)
                     ) {
@@ -405,7 +408,8 @@ impl Principal {
                 }
                 PrincipalData::Password(value)
                 | PrincipalData::AppPassword(value)
-                | PrincipalData::OtpAuth(value) => {
+                | PrincipalData::OtpAuth(value)
+                | PrincipalData::ScramVerifier(value) => {
                     let item = PrincipalUpdate::add_item(
                         PrincipalField::Secrets,
                         PrincipalValue::String(value.to_string()),
@@ -445,7 +449,8 @@ impl Principal {
             match item {
                 PrincipalData::Password(value)
                 | PrincipalData::AppPassword(value)
-                | PrincipalData::OtpAuth(value) => {
+                | PrincipalData::OtpAuth(value)
+                | PrincipalData::ScramVerifier(value) => {
                     updates.push(PrincipalUpdate::remove_item(
                         PrincipalField::Secrets,
                         PrincipalValue::String(value),
@@ -497,10 +502,11 @@ impl PrincipalData {
         match self {
             PrincipalData::OtpAuth(_) => 0,
             PrincipalData::Password(_) => 1,
-            PrincipalData::AppPassword(_) => 2,
-            PrincipalData::PrimaryEmail(_) => 3,
-            PrincipalData::EmailAlias(_) => 4,
-            _ => 5,
+            PrincipalData::ScramVerifier(_) => 2,
+            PrincipalData::AppPassword(_) => 3,
+            PrincipalData::PrimaryEmail(_) => 4,
+            PrincipalData::EmailAlias(_) => 5,
+            _ => 6,
         }
     }
 
@@ -508,6 +514,7 @@ impl PrincipalData {
         match self {
             PrincipalData::OtpAuth(s)
             | PrincipalData::Password(s)
+            | PrincipalData::ScramVerifier(s)
             | PrincipalData::AppPassword(s)
             | PrincipalData::PrimaryEmail(s)
             | PrincipalData::EmailAlias(s) => Some(s),
@@ -540,6 +547,7 @@ impl PrincipalData {
             PrincipalData::Password(v)
             | PrincipalData::AppPassword(v)
             | PrincipalData::OtpAuth(v)
+            | PrincipalData::ScramVerifier(v)
             | PrincipalData::Description(v)
             | PrincipalData::PrimaryEmail(v)
             | PrincipalData::EmailAlias(v)
@@ -547,7 +555,7 @@ impl PrincipalData {
             | PrincipalData::ExternalMember(v)
             | PrincipalData::Url(v)
             | PrincipalData::Locale(v) => v.len(),
-            PrincipalData::DiskQuota(_) => U64_LEN,
+            PrincipalData::DiskQuota(_) | PrincipalData::UndoSendDelay(_) => U64_LEN,
             PrincipalData::Permission { .. } => U32_LEN + 1,
             PrincipalData::DirectoryQuota { .. } | PrincipalData::ObjectQuota { .. } => U64_LEN + 1,
             PrincipalData::Tenant(_)
@@ -1365,7 +1373,9 @@ impl<'de> serde::Deserialize<'de> for PrincipalSet {
                             })?;
                             continue;
                         }
-                        PrincipalField::Quota => map.next_value::<PrincipalValue>()?,
+                        PrincipalField::Quota | PrincipalField::UndoSendDelay => {
+                            map.next_value::<PrincipalValue>()?
+                        }
                         PrincipalField::Secrets
                         | PrincipalField::Emails
                         | PrincipalField::MemberOf
@@ -0,0 +1,145 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Salted SCRAM-SHA-256 verifiers (RFC 5802, RFC 7677)
+//!
+//! Verifiers are stored in the principal secrets using the same format as Dovecot,
+//! `{SCRAM-SHA-256}<iterations>,<salt>,<StoredKey>,<ServerKey>` with base64 encoded
+//! values, so they can be imported from and exported to other servers.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use constant_time_eq::constant_time_eq_32;
+use pbkdf2::hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+
+pub const SCRAM_SHA256_PREFIX: &str = "{SCRAM-SHA-256}";
+pub const SCRAM_DEFAULT_ITERATIONS: u32 = 4096;
+const SCRAM_SALT_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScramVerifier {
+    pub iterations: u32,
+    pub salt: Vec<u8>,
+    pub stored_key: [u8; 32],
+    pub server_key: [u8; 32],
+}
+
+impl ScramVerifier {
+    /// Derive a verifier from a cleartext password using a random salt
+    pub fn generate(password: &str, iterations: u32) -> Self {
+        let salt = store::rand::random::<[u8; SCRAM_SALT_LEN]>();
+        Self::derive(password, &salt, iterations)
+    }
+
+    pub fn derive(password: &str, salt: &[u8], iterations: u32) -> Self {
+        let salted_password = salted_password(password, salt, iterations);
+        Self {
+            iterations,
+            salt: salt.to_vec(),
+            stored_key: sha256(&hmac_sha256(&salted_password, b"Client Key")),
+            server_key: hmac_sha256(&salted_password, b"Server Key"),
+        }
+    }
+
+    /// Derive a verifier from a stored password, which is only possible for
+    /// passwords that are not hashed
+    pub fn from_secret(secret: &str, iterations: u32) -> Option<Self> {
+        let password = if let Some(secret) = secret.strip_prefix('{') {
+            match secret.split_once('}')? {
+                ("PLAIN" | "plain" | "CLEAR" | "clear", password) => password,
+                _ => return None,
+            }
+        } else if !secret.starts_with(['$', '_']) {
+            secret
+        } else {
+            return None;
+        };
+
+        (!password.is_empty()).then(|| Self::generate(password, iterations))
+    }
+
+    /// Parse a verifier, with or without the `{SCRAM-SHA-256}` prefix
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value
+            .strip_prefix(SCRAM_SHA256_PREFIX)
+            .unwrap_or(value)
+            .split(',');
+        let iterations = parts.next()?.parse::<u32>().ok().filter(|i| *i > 0)?;
+        let salt = STANDARD.decode(parts.next()?).ok()?;
+        let stored_key = STANDARD.decode(parts.next()?).ok()?.try_into().ok()?;
+        let server_key = STANDARD.decode(parts.next()?).ok()?.try_into().ok()?;
+
+        if parts.next().is_none() && !salt.is_empty() {
+            Some(Self {
+                iterations,
+                salt,
+                stored_key,
+                server_key,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Verify a cleartext password against this verifier
+    pub fn verify_password(&self, password: &str) -> bool {
+        let salted_password = salted_password(password, &self.salt, self.iterations);
+        constant_time_eq_32(
+            &sha256(&hmac_sha256(&salted_password, b"Client Key")),
+            &self.stored_key,
+        )
+    }
+
+    /// Verify the ClientProof sent by the client for an authentication message
+    pub fn verify_proof(&self, auth_message: &[u8], client_proof: &[u8]) -> bool {
+        if client_proof.len() != 32 {
+            return false;
+        }
+
+        // ClientKey = ClientProof XOR ClientSignature
+        let client_signature = hmac_sha256(&self.stored_key, auth_message);
+        let mut client_key = [0u8; 32];
+        for (i, byte) in client_key.iter_mut().enumerate() {
+            *byte = client_proof[i] ^ client_signature[i];
+        }
+
+        constant_time_eq_32(&sha256(&client_key), &self.stored_key)
+    }
+
+    pub fn server_signature(&self, auth_message: &[u8]) -> [u8; 32] {
+        hmac_sha256(&self.server_key, auth_message)
+    }
+}
+
+impl Display for ScramVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{SCRAM_SHA256_PREFIX}{},{},{},{}",
+            self.iterations,
+            STANDARD.encode(&self.salt),
+            STANDARD.encode(self.stored_key),
+            STANDARD.encode(self.server_key)
+        )
+    }
+}
+
+fn salted_password(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut output);
+    output
+}
+
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
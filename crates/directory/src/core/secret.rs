@@ -6,6 +6,7 @@
 
 use crate::Principal;
 use crate::PrincipalData;
+use crate::core::scram::ScramVerifier;
 use argon2::Argon2;
 use compact_str::ToCompactString;
 use mail_builder::encoders::base64::base64_encode;
@@ -46,6 +47,13 @@ impl Principal {
                     }
                     seen_password = true;
                 }
+                PrincipalData::ScramVerifier(secret) => {
+                    // Verifiers are only used for cleartext logins when no password hash is set
+                    if !only_app_pass && password.is_none() {
+                        password = Some(secret);
+                    }
+                    seen_password = true;
+                }
                 PrincipalData::AppPassword(secret) => {
                     // App passwords do not require TOTP
                     if let Some((_, app_secret)) =
@@ -246,6 +254,12 @@ pub async fn verify_secret_hash(hashed_secret: &str, secret: &str) -> trc::Resul
                     }
                 }
                 "PLAIN" | "plain" | "CLEAR" | "clear" => Ok(hashed_secret == secret),
+                "SCRAM-SHA-256" => match ScramVerifier::parse(hashed_secret) {
+                    Some(verifier) => Ok(verifier.verify_password(secret)),
+                    None => Err(trc::AuthEvent::Error
+                        .ctx(trc::Key::Reason, "Invalid SCRAM verifier")
+                        .details(hashed_secret.to_string())),
+                },
                 _ => Err(trc::AuthEvent::Error
                     .ctx(trc::Key::Reason, "Unsupported algorithm")
                     .details(hashed_secret.to_string())),
@@ -70,6 +70,10 @@ pub enum PrincipalData {
     // Secrets
     AppPassword(String),
     OtpAuth(String),
+    ScramVerifier(String),
+
+    // Settings
+    UndoSendDelay(u64),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
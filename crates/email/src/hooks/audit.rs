@@ -0,0 +1,168 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Per-message record of the delivery hooks that processed a stored message
+//!
+//! The audit trail is stored next to the message metadata and removed together
+//! with the message, it can be retrieved from the management API to find out why
+//! a message was filed, flagged or rewritten.
+
+use serde::{Serialize, Serializer};
+use std::time::Duration;
+use types::id::Id;
+
+use super::{Action, IngestSource, Modification};
+
+/// All the hook runs recorded for a message
+#[derive(
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    rkyv::Archive,
+    Serialize,
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+)]
+pub struct DeliveryHookAudit {
+    pub runs: Vec<HookAuditRun>,
+}
+
+/// Hooks called for a message at delivery time, or later by the task manager for
+/// asynchronous hooks
+#[derive(
+    rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Serialize, Debug, Clone, PartialEq, Eq,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct HookAuditRun {
+    pub source: String,
+    pub is_async: bool,
+    pub timestamp: u64,
+    pub hooks: Vec<HookAuditEntry>,
+}
+
+#[derive(
+    rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Serialize, Debug, Clone, PartialEq, Eq,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct HookAuditEntry {
+    pub hook_id: String,
+    pub elapsed_ms: u64,
+    pub action: HookAuditAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub modifications: Vec<String>,
+    /// Mailboxes the message was filed into by this hook
+    #[serde(serialize_with = "serialize_mailbox_ids")]
+    pub mailbox_ids: Vec<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+    pub stop: bool,
+}
+
+#[derive(
+    rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Serialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum HookAuditAction {
+    Accept,
+    Discard,
+    Quarantine,
+    Reject,
+    /// The hook could not be reached or returned an invalid response
+    Error,
+    /// The hook was not called because its circuit breaker is open
+    Skipped,
+}
+
+impl DeliveryHookAudit {
+    pub fn add_run(
+        &mut self,
+        source: IngestSource,
+        is_async: bool,
+        timestamp: u64,
+        hooks: Vec<HookAuditEntry>,
+    ) {
+        self.runs.push(HookAuditRun {
+            source: source.as_str().to_string(),
+            is_async,
+            timestamp,
+            hooks,
+        });
+    }
+}
+
+impl HookAuditEntry {
+    pub fn new(hook_id: &str, elapsed: Duration, action: HookAuditAction) -> Self {
+        Self {
+            hook_id: hook_id.to_string(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            action,
+            error: None,
+            modifications: Vec::new(),
+            mailbox_ids: Vec::new(),
+            flags: Vec::new(),
+            stop: false,
+        }
+    }
+
+    pub fn with_error(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+}
+
+impl From<&Action> for HookAuditAction {
+    fn from(action: &Action) -> Self {
+        match action {
+            Action::Accept => HookAuditAction::Accept,
+            Action::Discard => HookAuditAction::Discard,
+            Action::Reject => HookAuditAction::Reject,
+            Action::Quarantine => HookAuditAction::Quarantine,
+        }
+    }
+}
+
+/// Short description of a modification, message contents and header values are omitted
+pub fn describe_modification(modification: &Modification) -> String {
+    match modification {
+        Modification::FileInto {
+            folder,
+            mailbox_id,
+            special_use,
+            ..
+        } => {
+            let target = if !mailbox_id.is_empty() {
+                mailbox_id.as_str()
+            } else if let Some(special_use) = special_use {
+                special_use.as_str()
+            } else {
+                folder.as_str()
+            };
+            format!("fileInto {target}")
+        }
+        Modification::AddHeader { name, .. } => format!("addHeader {name}"),
+        Modification::ReplaceHeader { index, name, .. } => {
+            format!("replaceHeader {name}[{index}]")
+        }
+        Modification::RemoveHeader { name } => format!("removeHeader {name}"),
+        Modification::PrependBodyBanner { .. } => "prependBodyBanner".to_string(),
+        Modification::ReplaceMimePart { part_id, .. } => format!("replaceMimePart {part_id}"),
+        Modification::Redirect { address, keep } => {
+            format!("redirect {address}{}", if *keep { " (keep)" } else { "" })
+        }
+        Modification::SetExpiry { seconds } => format!("setExpiry {seconds}"),
+    }
+}
+
+pub(crate) fn serialize_mailbox_ids<S: Serializer>(
+    ids: &[u32],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(ids.iter().map(|id| Id::from(*id).as_string()))
+}
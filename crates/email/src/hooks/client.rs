@@ -4,23 +4,47 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::sync::{Arc, Weak};
+
+use common::{
+    Server,
+    config::smtp::delivery_hooks::{DeliveryHook, DeliveryHookTransport, PubSubReplies},
+};
+use serde::Deserialize;
+use store::{PubSubStore, write::now};
+use tokio::sync::watch;
 use utils::HttpLimitResponse;
-use common::config::smtp::delivery_hooks::DeliveryHook;
 
 use super::{Action, Request, Response};
 
-pub async fn send_delivery_hook_request(hook: &DeliveryHook, request: Request) -> Result<Response, String> {
-    let response = reqwest::Client::builder()
-        .timeout(hook.timeout)
-        .danger_accept_invalid_certs(hook.tls_allow_invalid_certs)
-        .build()
-        .map_err(|err| format!("Failed to create HTTP client: {}", err))?
+pub async fn send_delivery_hook_request(
+    server: &Server,
+    hook: &DeliveryHook,
+    request: Request,
+) -> Result<Response, String> {
+    match &hook.transport {
+        DeliveryHookTransport::Http | DeliveryHookTransport::Unix { .. } => {
+            send_http_request(hook, request).await
+        }
+        DeliveryHookTransport::PubSub(replies) => {
+            send_pubsub_request(&server.core.storage.pubsub, hook, replies, request).await
+        }
+    }
+}
+
+async fn send_http_request(hook: &DeliveryHook, request: Request) -> Result<Response, String> {
+    let body = serde_json::to_string(&request)
+        .map_err(|err| format!("Failed to serialize delivery hook request: {}", err))?;
+    let mut headers = hook.headers.clone();
+    if let Some(signer) = &hook.signer {
+        signer.sign_request(&mut headers, now(), body.as_bytes());
+    }
+
+    let response = hook
+        .client
         .post(&hook.url)
-        .headers(hook.headers.clone())
-        .body(
-            serde_json::to_string(&request)
-                .map_err(|err| format!("Failed to serialize delivery hook request: {}", err))?,
-        )
+        .headers(headers)
+        .body(body)
         .send()
         .await
         .map_err(|err| format!("Delivery hook request failed: {err}"))?;
@@ -31,24 +55,7 @@ pub async fn send_delivery_hook_request(hook: &DeliveryHook, request: Request) -
             .await
             .map_err(|err| format!("Failed to parse delivery hook response: {}", err))?
             .ok_or_else(|| "Delivery hook response too large".to_string())?;
-        let trimmed = AsRef::<[u8]>::as_ref(&bytes).trim_ascii();
-        match serde_json::from_slice(trimmed) {
-            Ok(parsed) => Ok(parsed),
-            Err(err) => {
-                trc::event!(
-                    Delivery(trc::DeliveryEvent::RawOutput),
-                    Reason = format!("Failed to parse delivery hook response: {err}"),
-                    Contents = String::from_utf8_lossy(trimmed).into_owned(),
-                );
-                Ok(Response {
-                    action: Action::Accept,
-                    modifications: Vec::new(),
-                    skip_inbox: false,
-                    flags: Vec::new(),
-                    preview_text: None,
-                })
-            }
-        }
+        Ok(parse_response(bytes.as_ref()))
     } else {
         Err(format!(
             "Delivery hook request failed with code {}: {}",
@@ -57,3 +64,129 @@ pub async fn send_delivery_hook_request(hook: &DeliveryHook, request: Request) -
         ))
     }
 }
+
+async fn send_pubsub_request(
+    pubsub: &PubSubStore,
+    hook: &DeliveryHook,
+    replies: &Arc<PubSubReplies>,
+    mut request: Request,
+) -> Result<Response, String> {
+    if pubsub.is_none() {
+        return Err(
+            "Delivery hook uses the PubSub transport but no PubSub store is configured".into(),
+        );
+    }
+
+    // Subscribe to the reply topic before publishing the first request
+    {
+        let mut is_listening = replies.is_listening().await;
+        if !*is_listening {
+            let stream = pubsub
+                .subscribe(&replies.reply_topic)
+                .await
+                .map_err(|err| format!("Failed to subscribe to delivery hook replies: {err}"))?;
+            tokio::spawn(receive_replies(
+                stream,
+                Arc::downgrade(replies),
+                replies.closed(),
+            ));
+            *is_listening = true;
+        }
+    }
+
+    let (request_id, reply) = replies.register();
+    request.request_id = Some(request_id.to_string());
+    request.reply_to = Some(replies.reply_topic.clone());
+    let payload = match serde_json::to_vec(&request) {
+        Ok(payload) => payload,
+        Err(err) => {
+            replies.cancel(request_id);
+            return Err(format!("Failed to serialize delivery hook request: {err}"));
+        }
+    };
+    if let Err(err) = pubsub.publish(&replies.topic, payload).await {
+        replies.cancel(request_id);
+        return Err(format!("Delivery hook request failed: {err}"));
+    }
+
+    match tokio::time::timeout(hook.timeout, reply).await {
+        Ok(Ok(bytes)) if bytes.len() <= hook.max_response_size => Ok(parse_response(&bytes)),
+        Ok(Ok(_)) => Err("Delivery hook response too large".to_string()),
+        Ok(Err(_)) => Err("Delivery hook reply was dropped".to_string()),
+        Err(_) => {
+            replies.cancel(request_id);
+            Err("Delivery hook request timed out".to_string())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReplyId {
+    request_id: String,
+}
+
+/// Route the replies published by the hook workers to the requests waiting for them,
+/// stops once the hook has been removed by a configuration reload
+async fn receive_replies(
+    mut stream: store::dispatch::pubsub::PubSubStream,
+    replies: Weak<PubSubReplies>,
+    mut closed: watch::Receiver<()>,
+) {
+    loop {
+        let msg = tokio::select! {
+            msg = stream.next() => msg,
+            // The hook was dropped, returning releases the subscription
+            _ = closed.changed() => return,
+        };
+        let Some(msg) = msg else {
+            break;
+        };
+        let Some(replies) = replies.upgrade() else {
+            return;
+        };
+        let payload = msg.payload();
+        match serde_json::from_slice::<ReplyId>(payload)
+            .ok()
+            .and_then(|reply| reply.request_id.parse::<u64>().ok())
+        {
+            Some(request_id) => {
+                // Replies that arrive after the request timed out are dropped
+                replies.complete(request_id, payload.to_vec());
+            }
+            None => {
+                trc::event!(
+                    DeliveryHook(trc::DeliveryHookEvent::Error),
+                    Details = "Received delivery hook reply without a valid request_id",
+                    Contents = String::from_utf8_lossy(payload).into_owned(),
+                );
+            }
+        }
+    }
+
+    // The subscription ended, the next request will subscribe again
+    if let Some(replies) = replies.upgrade() {
+        *replies.is_listening().await = false;
+    }
+}
+
+fn parse_response(bytes: &[u8]) -> Response {
+    let trimmed = bytes.trim_ascii();
+    match serde_json::from_slice(trimmed) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            trc::event!(
+                Delivery(trc::DeliveryEvent::RawOutput),
+                Reason = format!("Failed to parse delivery hook response: {err}"),
+                Contents = String::from_utf8_lossy(trimmed).into_owned(),
+            );
+            Response {
+                action: Action::Accept,
+                modifications: Vec::new(),
+                skip_inbox: false,
+                flags: Vec::new(),
+                preview_text: None,
+                stop: false,
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Parsing of the verdict headers added by this server during the SMTP session,
+//! used to enrich delivery hook requests.
+
+use super::{AuthResult, AuthResults, SpamTag, SpamVerdict};
+
+/// Builds the spam verdict from the topmost `X-Spam-Score` and `X-Spam-Result` headers
+pub fn spam_verdict<'x>(headers: impl Iterator<Item = (&'x str, &'x str)>) -> Option<SpamVerdict> {
+    let mut score_header = None;
+    let mut result_header = None;
+
+    for (name, value) in headers {
+        if score_header.is_none() && name.eq_ignore_ascii_case("X-Spam-Score") {
+            score_header = Some(value);
+        } else if result_header.is_none() && name.eq_ignore_ascii_case("X-Spam-Result") {
+            result_header = Some(value);
+        }
+    }
+
+    // Format is "<spam|ham>, score=<score>[, avg_confidence=<confidence>]"
+    let score_header = unfold(score_header?);
+    let mut parts = score_header.split(',').map(str::trim);
+    let is_spam = parts.next()?.eq_ignore_ascii_case("spam");
+    let score = parts
+        .filter_map(|part| part.strip_prefix("score="))
+        .find_map(|score| score.parse().ok());
+
+    // Format is "<TAG> (<score>), <TAG> (<score>)..."
+    let tags = result_header
+        .map(|value| {
+            unfold(value)
+                .split(',')
+                .filter_map(|tag| {
+                    let (name, score) = tag.trim().split_once(' ')?;
+                    Some(SpamTag {
+                        name: name.to_string(),
+                        score: score
+                            .trim()
+                            .trim_start_matches('(')
+                            .trim_end_matches(')')
+                            .parse()
+                            .ok()?,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(SpamVerdict {
+        is_spam,
+        score,
+        tags,
+    })
+}
+
+/// Parses the topmost `Authentication-Results` header, which is the one
+/// prepended by this server on reception
+pub fn auth_results<'x>(
+    mut headers: impl Iterator<Item = (&'x str, &'x str)>,
+) -> Option<AuthResults> {
+    let (_, value) =
+        headers.find(|(name, _)| name.eq_ignore_ascii_case("Authentication-Results"))?;
+    let value = strip_comments(&unfold(value));
+    let mut results = AuthResults::default();
+
+    // Skip the authserv-id
+    for resinfo in value.split(';').skip(1) {
+        let mut tokens = tokenize(resinfo).into_iter();
+        let Some((method, result)) = tokens.next().and_then(|token| {
+            token
+                .split_once('=')
+                .map(|(method, result)| (method.to_ascii_lowercase(), result.to_ascii_lowercase()))
+        }) else {
+            continue;
+        };
+        let result = AuthResult {
+            result,
+            properties: tokens
+                .filter_map(|token| {
+                    token.split_once('=').map(|(name, value)| {
+                        (name.to_string(), value.trim_matches('"').to_string())
+                    })
+                })
+                .collect(),
+        };
+
+        match method.as_str() {
+            "dkim" => results.dkim.push(result),
+            "spf" => results.spf = Some(result),
+            "dmarc" => results.dmarc = Some(result),
+            "arc" => results.arc = Some(result),
+            _ => {}
+        }
+    }
+
+    Some(results)
+}
+
+fn unfold(value: &str) -> String {
+    value
+        .split(['\r', '\n'])
+        .map(|line| line.trim_matches([' ', '\t']))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Removes RFC 5322 comments, keeping quoted strings untouched
+fn strip_comments(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut depth = 0u32;
+    let mut in_quote = false;
+
+    for ch in value.chars() {
+        match ch {
+            '"' if depth == 0 => {
+                in_quote = !in_quote;
+                result.push(ch);
+            }
+            '(' if !in_quote => depth += 1,
+            ')' if !in_quote && depth > 0 => depth -= 1,
+            _ if depth == 0 => result.push(ch),
+            _ => {}
+        }
+    }
+
+    result
+}
+
+// Splits on whitespace outside of quoted strings
+fn tokenize(value: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut in_quote = false;
+
+    for (pos, ch) in value.char_indices() {
+        match ch {
+            '"' => {
+                in_quote = !in_quote;
+                start.get_or_insert(pos);
+            }
+            ' ' | '\t' if !in_quote => {
+                if let Some(start) = start.take() {
+                    tokens.push(&value[start..pos]);
+                }
+            }
+            _ => {
+                start.get_or_insert(pos);
+            }
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&value[start..]);
+    }
+
+    tokens
+}
@@ -4,17 +4,21 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod audit;
 pub mod client;
+pub mod context;
 
 use serde::{Deserialize, Serialize};
 
+pub use common::config::smtp::delivery_hooks::DeliveryHookSource as IngestSource;
+
 // Types copied from smtp::inbound::hooks to avoid cyclic dependency
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Address {
     pub address: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Envelope {
     pub from: Address,
     pub to: Address,
@@ -35,10 +39,107 @@ pub struct Message {
 pub struct Request {
     pub user_id: String,
     pub principal_name: String,
+    #[serde(default)]
+    pub source: IngestSource,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub envelope: Option<Envelope>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub pipeline: Option<Pipeline>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub spam: Option<SpamVerdict>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub auth_results: Option<AuthResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub mailboxes: Option<Vec<MailboxInfo>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub sieve_script: Option<String>,
+    /// Correlation id for requests sent over a PubSub store, echoed back in the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// Topic the response has to be published to when using a PubSub store
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// Set when the request is a dry run, hooks should not perform any side effects
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Spam filter verdict taken from the headers added by this server
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SpamVerdict {
+    pub is_spam: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub score: Option<f64>,
+    #[serde(default)]
+    pub tags: Vec<SpamTag>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpamTag {
+    pub name: String,
+    pub score: f64,
+}
+
+/// Authentication results taken from the Authentication-Results header added by this server
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthResults {
+    #[serde(default)]
+    pub dkim: Vec<AuthResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub spf: Option<AuthResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub dmarc: Option<AuthResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub arc: Option<AuthResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthResult {
+    pub result: String,
+    /// Method properties such as `header.d` or `smtp.mailfrom`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub properties: Vec<(String, String)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MailboxInfo {
+    pub id: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+/// Decisions taken by the hooks that ran earlier in a sequential pipeline
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Pipeline {
+    pub position: u32,
+    #[serde(default)]
+    pub previous_hooks: Vec<String>,
+    #[serde(default)]
+    pub mailbox_ids: Vec<String>,
+    #[serde(default)]
+    pub flags: Vec<String>,
+    #[serde(default)]
+    pub skip_inbox: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub preview_text: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -52,6 +153,8 @@ pub struct Response {
     pub flags: Vec<String>,
     #[serde(default)]
     pub preview_text: Option<String>,
+    #[serde(default)]
+    pub stop: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -88,6 +191,30 @@ pub enum Modification {
         name: String,
         value: String,
     },
+    #[serde(rename = "removeHeader")]
+    RemoveHeader { name: String },
+    #[serde(rename = "prependBodyBanner")]
+    PrependBodyBanner {
+        #[serde(default)]
+        text: Option<String>,
+        #[serde(default)]
+        html: Option<String>,
+    },
+    #[serde(rename = "replaceMimePart")]
+    ReplaceMimePart {
+        part_id: u32,
+        #[serde(default = "default_content_type")]
+        content_type: String,
+        contents: String,
+    },
+    #[serde(rename = "redirect")]
+    Redirect {
+        address: String,
+        #[serde(default)]
+        keep: bool,
+    },
+    #[serde(rename = "setExpiry")]
+    SetExpiry { seconds: u64 },
 }
 
 pub enum ModificationOut {
@@ -97,6 +224,20 @@ pub enum ModificationOut {
         name: String,
         value: String,
     },
+    RemoveHeader { name: String },
+    PrependBodyBanner {
+        text: Option<String>,
+        html: Option<String>,
+    },
+    ReplaceMimePart {
+        part_id: u32,
+        content_type: String,
+        contents: String,
+    },
+}
+
+fn default_content_type() -> String {
+    "text/plain".to_string()
 }
 
 impl Request {
@@ -104,11 +245,25 @@ impl Request {
         Self {
             user_id,
             principal_name,
+            source: IngestSource::default(),
             envelope: None,
             message: None,
+            pipeline: None,
+            spam: None,
+            auth_results: None,
+            mailboxes: None,
+            sieve_script: None,
+            request_id: None,
+            reply_to: None,
+            dry_run: false,
         }
     }
 
+    pub fn with_source(mut self, source: IngestSource) -> Self {
+        self.source = source;
+        self
+    }
+
     pub fn with_envelope(mut self, envelope: Envelope) -> Self {
         self.envelope = Some(envelope);
         self
@@ -118,4 +273,14 @@ impl Request {
         self.message = Some(message);
         self
     }
+
+    pub fn with_pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.pipeline = Some(pipeline);
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
 }
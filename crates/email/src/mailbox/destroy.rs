@@ -7,6 +7,7 @@
 use super::*;
 use crate::{
     cache::{MessageCacheFetch, email::MessageCacheAccess},
+    mailbox::metadata::MailboxMetadata,
     message::metadata::MessageData,
 };
 use common::{
@@ -15,7 +16,7 @@ use common::{
 use store::{
     SerializeInfallible,
     roaring::RoaringBitmap,
-    write::{BatchBuilder, SearchIndex, TaskEpoch, TaskQueueClass, ValueClass},
+    write::{BatchBuilder, DirectoryClass, SearchIndex, TaskEpoch, TaskQueueClass, ValueClass},
 };
 use store::{
     ValueKey,
@@ -204,6 +205,35 @@ impl MailboxDestroy for Server {
                 .clear(MailboxField::UidCounter)
                 .custom(ObjectIndexBuilder::<_, ()>::new().with_current(mailbox))
                 .caused_by(trc::location!())?;
+
+            // Remove mailbox annotations and release their quota
+            if let Some(metadata) = self
+                .store()
+                .get_value::<Archive<AlignedBytes>>(ValueKey::property(
+                    account_id,
+                    Collection::Mailbox,
+                    document_id,
+                    MailboxField::Metadata,
+                ))
+                .await
+                .caused_by(trc::location!())?
+            {
+                let sizes = metadata
+                    .unarchive::<MailboxMetadata>()
+                    .caused_by(trc::location!())?
+                    .owner_sizes(account_id);
+                batch.clear(MailboxField::Metadata);
+                for (owner_id, size) in sizes {
+                    let resource_token = self
+                        .get_resource_token(access_token, owner_id)
+                        .await
+                        .caused_by(trc::location!())?;
+                    batch.add(DirectoryClass::UsedQuota(owner_id), -(size as i64));
+                    if let Some(tenant) = resource_token.tenant {
+                        batch.add(DirectoryClass::UsedQuota(tenant.id), -(size as i64));
+                    }
+                }
+            }
         } else {
             return Ok(Err(MailboxDestroyError::NotFound));
         };
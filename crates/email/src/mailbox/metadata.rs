@@ -0,0 +1,261 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::ResourceToken};
+use std::future::Future;
+use store::{
+    Serialize, ValueKey,
+    write::{AlignedBytes, Archive, Archiver, BatchBuilder, DirectoryClass},
+};
+use trc::AddContext;
+use types::{
+    collection::Collection,
+    field::{Field, MailboxField, PrincipalField},
+};
+
+#[derive(
+    rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Default, Debug, Clone, PartialEq, Eq,
+)]
+pub struct MailboxMetadata {
+    pub entries: Vec<MetadataEntry>,
+}
+
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MetadataEntry {
+    pub name: String,
+    pub owner_id: Option<u32>,
+    pub value: Vec<u8>,
+}
+
+pub trait MailboxMetadataFnc: Sync + Send {
+    fn mailbox_metadata(
+        &self,
+        account_id: u32,
+        mailbox_id: Option<u32>,
+    ) -> impl Future<Output = trc::Result<MailboxMetadata>> + Send;
+
+    /// Apply annotation changes made by the account of `writer_token` to a mailbox
+    /// of the account of `owner_token`. Private annotations are counted against the
+    /// limit and quota of the writer, shared annotations against the mailbox owner.
+    fn mailbox_metadata_set(
+        &self,
+        owner_token: &ResourceToken,
+        writer_token: &ResourceToken,
+        mailbox_id: Option<u32>,
+        changes: Vec<(String, Option<Vec<u8>>)>,
+        max_entries: usize,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl MailboxMetadataFnc for Server {
+    async fn mailbox_metadata(
+        &self,
+        account_id: u32,
+        mailbox_id: Option<u32>,
+    ) -> trc::Result<MailboxMetadata> {
+        if let Some(metadata) = self
+            .store()
+            .get_value::<Archive<AlignedBytes>>(metadata_key(account_id, mailbox_id))
+            .await
+            .caused_by(trc::location!())?
+        {
+            metadata
+                .deserialize::<MailboxMetadata>()
+                .caused_by(trc::location!())
+        } else {
+            Ok(MailboxMetadata::default())
+        }
+    }
+
+    async fn mailbox_metadata_set(
+        &self,
+        owner_token: &ResourceToken,
+        writer_token: &ResourceToken,
+        mailbox_id: Option<u32>,
+        changes: Vec<(String, Option<Vec<u8>>)>,
+        max_entries: usize,
+    ) -> trc::Result<bool> {
+        let account_id = owner_token.account_id;
+        let writer_id = writer_token.account_id;
+        let metadata_archive = self
+            .store()
+            .get_value::<Archive<AlignedBytes>>(metadata_key(account_id, mailbox_id))
+            .await
+            .caused_by(trc::location!())?;
+        let mut metadata = if let Some(metadata) = &metadata_archive {
+            metadata
+                .deserialize::<MailboxMetadata>()
+                .caused_by(trc::location!())?
+        } else {
+            MailboxMetadata::default()
+        };
+
+        // Apply changes
+        let old_sizes = [
+            metadata.owner_size(account_id, account_id),
+            metadata.owner_size(account_id, writer_id),
+        ];
+        let mut has_shared = false;
+        let mut has_private = false;
+        for (name, value) in changes {
+            let owner_id = if name.starts_with("/private/") {
+                has_private = true;
+                Some(writer_id)
+            } else {
+                has_shared = true;
+                None
+            };
+            let pos = metadata
+                .entries
+                .iter()
+                .position(|entry| entry.name == name && entry.owner_id == owner_id);
+            match (pos, value) {
+                (Some(pos), Some(value)) => {
+                    metadata.entries[pos].value = value;
+                }
+                (None, Some(value)) => {
+                    metadata.entries.push(MetadataEntry {
+                        name,
+                        owner_id,
+                        value,
+                    });
+                }
+                (Some(pos), None) => {
+                    metadata.entries.swap_remove(pos);
+                }
+                (None, None) => {}
+            }
+        }
+        if (has_shared && metadata.owner_count(account_id, account_id) > max_entries)
+            || (has_private && metadata.owner_count(account_id, writer_id) > max_entries)
+        {
+            return Ok(false);
+        }
+
+        // Validate quota
+        let new_sizes = [
+            metadata.owner_size(account_id, account_id),
+            metadata.owner_size(account_id, writer_id),
+        ];
+        let tokens = if writer_id != account_id {
+            &[owner_token, writer_token][..]
+        } else {
+            &[owner_token][..]
+        };
+        for (resource_token, (old_size, new_size)) in
+            tokens.iter().zip(old_sizes.into_iter().zip(new_sizes))
+        {
+            if new_size > old_size {
+                self.has_available_quota(resource_token, new_size - old_size)
+                    .await?;
+            }
+        }
+
+        let (collection, document_id, field) = metadata_field(mailbox_id);
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(collection)
+            .with_document(document_id);
+        if let Some(metadata_archive) = metadata_archive {
+            batch.assert_value(field, metadata_archive);
+        }
+        if !metadata.entries.is_empty() {
+            batch.set(
+                field,
+                Archiver::new(metadata)
+                    .serialize()
+                    .caused_by(trc::location!())?,
+            );
+        } else {
+            batch.clear(field);
+        }
+
+        // Update used quota
+        for (resource_token, (old_size, new_size)) in
+            tokens.iter().zip(old_sizes.into_iter().zip(new_sizes))
+        {
+            if new_size != old_size {
+                let delta = new_size as i64 - old_size as i64;
+                batch.add(DirectoryClass::UsedQuota(resource_token.account_id), delta);
+                if let Some(tenant) = &resource_token.tenant {
+                    batch.add(DirectoryClass::UsedQuota(tenant.id), delta);
+                }
+            }
+        }
+
+        self.commit_batch(batch)
+            .await
+            .caused_by(trc::location!())
+            .map(|_| true)
+    }
+}
+
+impl MailboxMetadata {
+    /// Size of the annotations charged to `owner_id`, shared annotations belong to
+    /// the account holding the mailbox
+    pub fn owner_size(&self, account_id: u32, owner_id: u32) -> u64 {
+        self.entries
+            .iter()
+            .filter(|entry| entry.owner_id.unwrap_or(account_id) == owner_id)
+            .map(|entry| (entry.name.len() + entry.value.len()) as u64)
+            .sum()
+    }
+
+    pub fn owner_count(&self, account_id: u32, owner_id: u32) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.owner_id.unwrap_or(account_id) == owner_id)
+            .count()
+    }
+
+    pub fn visible_entries(&self, account_id: u32) -> impl Iterator<Item = &MetadataEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.owner_id.is_none_or(|owner_id| owner_id == account_id))
+    }
+}
+
+impl ArchivedMailboxMetadata {
+    /// Annotation sizes grouped by the account they are charged to
+    pub fn owner_sizes(&self, account_id: u32) -> Vec<(u32, u64)> {
+        let mut sizes: Vec<(u32, u64)> = Vec::new();
+        for entry in self.entries.iter() {
+            let owner_id = entry
+                .owner_id
+                .as_ref()
+                .map(|owner_id| owner_id.to_native())
+                .unwrap_or(account_id);
+            let size = (entry.name.len() + entry.value.len()) as u64;
+            if let Some((_, total)) = sizes.iter_mut().find(|(id, _)| *id == owner_id) {
+                *total += size;
+            } else {
+                sizes.push((owner_id, size));
+            }
+        }
+        sizes
+    }
+}
+
+fn metadata_field(mailbox_id: Option<u32>) -> (Collection, u32, Field) {
+    match mailbox_id {
+        Some(mailbox_id) => (
+            Collection::Mailbox,
+            mailbox_id,
+            MailboxField::Metadata.into(),
+        ),
+        None => (
+            Collection::Principal,
+            0,
+            PrincipalField::MailboxMetadata.into(),
+        ),
+    }
+}
+
+fn metadata_key(account_id: u32, mailbox_id: Option<u32>) -> ValueKey<store::write::ValueClass> {
+    let (collection, document_id, field) = metadata_field(mailbox_id);
+    ValueKey::property(account_id, collection, document_id, field)
+}
@@ -5,6 +5,7 @@
  */
 
 use super::ingest::{EmailIngest, IngestEmail, IngestSource};
+use super::quarantine::{EmailQuarantine, QuarantineReason};
 use crate::{mailbox::{DRAFTS_ID, INBOX_ID, JUNK_ID, TRASH_ID}, sieve::ingest::SieveScriptIngest};
 use common::{
     Server,
@@ -12,7 +13,12 @@ use common::{
 };
 use directory::Permission;
 use types::{keyword::Keyword};
-use mail_parser::MessageParser;
+use mail_builder::encoders::{
+    base64::base64_encode_mime,
+    encode::{EncodingType, get_encoding_type},
+    quoted_printable::quoted_printable_encode,
+};
+use mail_parser::{HeaderName, MessageParser, MessagePart, PartType};
 use std::{borrow::Cow, future::Future};
 use store::ahash::AHashMap;
 use types::blob_hash::BlobHash;
@@ -22,8 +28,10 @@ use crate::{
     sieve::ingest::SieveOutputMessage,
 };
 
-use super::delivery_hooks::try_delivery_hook;
-use crate::hooks::ModificationOut as HookModification;
+use super::delivery_hooks::{schedule_hook_tasks, try_delivery_hook};
+use crate::hooks::{
+    self, IngestSource as HookSource, ModificationOut as HookModification, audit::HookAuditEntry,
+};
 
 // Prepend AddHeader modifications to a raw RFC 5322 message
 fn apply_add_header_modifications(
@@ -206,6 +214,262 @@ fn apply_replace_header_modifications(
     Some(new_message)
 }
 
+// Apply delivery hook header modifications to a raw RFC 5322 message,
+// returns None when the message was left unchanged
+pub(crate) fn apply_hook_modifications(
+    modifications: Vec<HookModification>,
+    original_raw: &[u8],
+    session_id: u64,
+) -> Option<Vec<u8>> {
+    // Separate modifications by type
+    let mut add_headers: Vec<(String, String)> = Vec::new();
+    let mut replace_headers: Vec<(u32, String, String)> = Vec::new();
+    let mut remove_headers: Vec<String> = Vec::new();
+    let mut banners = BodyBanners::default();
+    let mut replace_parts: Vec<(u32, String, String)> = Vec::new();
+
+    for m in modifications {
+        match m {
+            HookModification::AddHeader { name, value } => {
+                add_headers.push((name, value));
+            }
+            HookModification::ReplaceHeader { index, name, value } => {
+                replace_headers.push((index, name, value));
+            }
+            HookModification::RemoveHeader { name } => {
+                remove_headers.push(name);
+            }
+            HookModification::PrependBodyBanner { text, html } => {
+                banners.add(text, html);
+            }
+            HookModification::ReplaceMimePart {
+                part_id,
+                content_type,
+                contents,
+            } => {
+                replace_parts.push((part_id, content_type, contents));
+            }
+        }
+    }
+
+    // Apply AddHeader modifications first
+    let mut new_raw = None;
+    if !add_headers.is_empty() {
+        new_raw = Some(apply_add_header_modifications(&add_headers, original_raw));
+    }
+
+    // Apply ReplaceHeader modifications
+    if !replace_headers.is_empty()
+        && let Some(modified) = apply_replace_header_modifications(
+            &replace_headers,
+            new_raw.as_deref().unwrap_or(original_raw),
+            session_id,
+        )
+    {
+        new_raw = Some(modified);
+    }
+
+    // Apply RemoveHeader modifications
+    if !remove_headers.is_empty()
+        && let Some(modified) = apply_remove_header_modifications(
+            &remove_headers,
+            new_raw.as_deref().unwrap_or(original_raw),
+        )
+    {
+        new_raw = Some(modified);
+    }
+
+    // Apply body modifications last, as they require parsing the message
+    if (!banners.is_empty() || !replace_parts.is_empty())
+        && let Some(modified) = apply_body_modifications(
+            &banners,
+            &replace_parts,
+            new_raw.as_deref().unwrap_or(original_raw),
+            session_id,
+        )
+    {
+        new_raw = Some(modified);
+    }
+
+    new_raw
+}
+
+// Remove all occurrences of the named headers from a raw RFC 5322 message
+fn apply_remove_header_modifications(names: &[String], original_raw: &[u8]) -> Option<Vec<u8>> {
+    let message = MessageParser::new().parse_headers(original_raw)?;
+    let root = message.root_part();
+    let mut new_message = Vec::with_capacity(original_raw.len());
+    let mut last_offset = 0;
+
+    for header in &root.headers {
+        if names
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(header.name.as_str()))
+        {
+            new_message.extend_from_slice(&original_raw[last_offset..header.offset_field as usize]);
+            last_offset = header.offset_end as usize;
+        }
+    }
+
+    if last_offset == 0 {
+        return None;
+    }
+
+    new_message.extend_from_slice(&original_raw[last_offset..]);
+    Some(new_message)
+}
+
+#[derive(Default)]
+struct BodyBanners {
+    text: Vec<String>,
+    html: Vec<String>,
+}
+
+impl BodyBanners {
+    fn add(&mut self, text: Option<String>, html: Option<String>) {
+        if let Some(text) = text.filter(|text| !text.is_empty()) {
+            self.text.push(text);
+        }
+        if let Some(html) = html.filter(|html| !html.is_empty()) {
+            self.html.push(html);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.html.is_empty()
+    }
+}
+
+// Rewrite the body parts of a raw RFC 5322 message, prepending banners to the
+// text and HTML bodies and replacing whole MIME parts by their part id
+fn apply_body_modifications(
+    banners: &BodyBanners,
+    replace_parts: &[(u32, String, String)],
+    original_raw: &[u8],
+    session_id: u64,
+) -> Option<Vec<u8>> {
+    let message = MessageParser::new().parse(original_raw)?;
+    let mut rewrites: Vec<(&MessagePart<'_>, Cow<'_, str>, String)> = Vec::new();
+    let mut rewritten_ids = Vec::new();
+
+    for (part_id, content_type, contents) in replace_parts {
+        match message.parts.get(*part_id as usize) {
+            Some(part) if !matches!(part.body, PartType::Multipart(_)) => {
+                if !rewritten_ids.contains(part_id) {
+                    rewritten_ids.push(*part_id);
+                    rewrites.push((part, content_type.as_str().into(), contents.clone()));
+                }
+            }
+            _ => {
+                trc::event!(
+                    MessageIngest(trc::MessageIngestEvent::Error),
+                    Details =
+                        format!("ReplaceMimePart: part {part_id} not found or not a leaf part"),
+                    SpanId = session_id
+                );
+            }
+        }
+    }
+
+    for part_id in message.text_body.iter().chain(message.html_body.iter()) {
+        if rewritten_ids.contains(part_id) {
+            continue;
+        }
+        let Some(part) = message.parts.get(*part_id as usize) else {
+            continue;
+        };
+        let contents = match &part.body {
+            PartType::Text(text) if !banners.text.is_empty() => {
+                let mut contents = String::with_capacity(text.len() + 128);
+                for banner in &banners.text {
+                    contents.push_str(banner);
+                    contents.push_str("\r\n\r\n");
+                }
+                contents.push_str(text);
+                ("text/plain", contents)
+            }
+            PartType::Html(html) if !banners.html.is_empty() => {
+                ("text/html", prepend_html_banners(html, &banners.html))
+            }
+            _ => continue,
+        };
+        rewritten_ids.push(*part_id);
+        rewrites.push((part, contents.0.into(), contents.1));
+    }
+
+    if rewrites.is_empty() {
+        return None;
+    }
+
+    // Rebuild the message, replacing each part while keeping its non-content headers
+    rewrites.sort_unstable_by_key(|(part, _, _)| part.offset_header);
+    let mut new_message = Vec::with_capacity(original_raw.len() + 512);
+    let mut last_offset = 0;
+    for (part, content_type, contents) in rewrites {
+        new_message.extend_from_slice(&original_raw[last_offset..part.offset_header as usize]);
+        for header in &part.headers {
+            if !matches!(
+                header.name,
+                HeaderName::ContentType | HeaderName::ContentTransferEncoding
+            ) {
+                new_message.extend_from_slice(
+                    &original_raw[header.offset_field as usize..header.offset_end as usize],
+                );
+            }
+        }
+
+        new_message.extend_from_slice(b"Content-Type: ");
+        new_message.extend_from_slice(content_type.as_bytes());
+        if content_type.starts_with("text/") && !content_type.contains(';') {
+            new_message.extend_from_slice(b"; charset=\"utf-8\"");
+        }
+        new_message.extend_from_slice(b"\r\nContent-Transfer-Encoding: ");
+        let mut body = Vec::with_capacity(contents.len());
+        let encoding = match get_encoding_type(contents.as_bytes(), false, true) {
+            EncodingType::None => {
+                body.extend_from_slice(contents.as_bytes());
+                "7bit"
+            }
+            EncodingType::QuotedPrintable(_) => {
+                let _ = quoted_printable_encode(contents.as_bytes(), &mut body, true);
+                "quoted-printable"
+            }
+            EncodingType::Base64 => {
+                let _ = base64_encode_mime(contents.as_bytes(), &mut body, false);
+                "base64"
+            }
+        };
+        new_message.extend_from_slice(encoding.as_bytes());
+        new_message.extend_from_slice(b"\r\n\r\n");
+        new_message.extend_from_slice(&body);
+        if !body.ends_with(b"\n") {
+            new_message.extend_from_slice(b"\r\n");
+        }
+        last_offset = part.offset_end as usize;
+    }
+    new_message.extend_from_slice(&original_raw[last_offset..]);
+
+    Some(new_message)
+}
+
+// Insert HTML banners right after the opening body tag, or at the start if there is none
+fn prepend_html_banners(html: &str, banners: &[String]) -> String {
+    let insert_at = html
+        .as_bytes()
+        .windows(5)
+        .position(|w| w.eq_ignore_ascii_case(b"<body"))
+        .and_then(|pos| html[pos..].find('>').map(|end| pos + end + 1))
+        .unwrap_or(0);
+    let mut contents =
+        String::with_capacity(html.len() + banners.iter().map(|b| b.len()).sum::<usize>());
+    contents.push_str(&html[..insert_at]);
+    for banner in banners {
+        contents.push_str(banner);
+    }
+    contents.push_str(&html[insert_at..]);
+    contents
+}
+
 #[derive(Debug)]
 pub struct IngestMessage {
     pub sender_address: String,
@@ -220,6 +484,8 @@ pub struct IngestMessage {
 pub struct IngestRecipient {
     pub address: String,
     pub is_spam: bool,
+    /// Quarantined by a milter, MTA hook or Sieve script before queueing
+    pub is_quarantined: bool,
 }
 
 #[cfg(test)]
@@ -855,14 +1121,40 @@ async fn deliver_to_recipient(
         let mut keywords: Vec<Keyword> = output_message.keywords;
 
         // Apply delivery hooks (mailboxes/flags/skip_inbox + per-recipient modifications)
-        let mut owned_new_raw: Option<Vec<u8>> = None;
+        let owned_new_raw: Option<Vec<u8>>;
         let mut use_modified = false;
         let mut parsed_for_ingest = parsed_output_message.clone();
-        let mut hook_preview_text: Option<String> = None;
-        match try_delivery_hook(server, uid, &sender, &rcpt.address, &parsed_output_message).await
+        let hook_preview_text: Option<String>;
+        let hook_redirects: Vec<(String, bool)>;
+        let hook_expires_in: Option<u64>;
+        let hook_audit: Vec<HookAuditEntry>;
+        let hook_quarantine: bool;
+        // Messages filed or rewritten by the recipient's Sieve script are hooked as such
+        let hook_source = if output_message.changed || output_message.did_file_into {
+            HookSource::Sieve
+        } else {
+            HookSource::Smtp
+        };
+        let hook_envelope = hooks::Envelope {
+            from: hooks::Address {
+                address: sender.to_string(),
+            },
+            to: hooks::Address {
+                address: rcpt.address.to_string(),
+            },
+        };
+        match try_delivery_hook(
+            server,
+            uid,
+            hook_source,
+            Some(hook_envelope.clone()),
+            &parsed_output_message,
+            session_id,
+        )
+        .await
         {
             Ok(result) => {
-                let (hook_mailboxes, hook_flags, skip_inbox, hook_modifications, preview_text) = match result {
+                let outcome = match result {
                     Some(v) => v,
                     None => {
                         // Discard without error
@@ -877,13 +1169,13 @@ async fn deliver_to_recipient(
                     }
                 };
 
-                for id in hook_mailboxes {
+                for id in outcome.mailbox_ids {
                     if !mailbox_ids.contains(&id) {
                         mailbox_ids.push(id);
                     }
                 }
 
-                for k in hook_flags
+                for k in outcome.flags
                     .into_iter()
                     .map(types::keyword::Keyword::from)
                 {
@@ -892,7 +1184,7 @@ async fn deliver_to_recipient(
                     }
                 }
 
-                if skip_inbox {
+                if outcome.skip_inbox {
                     mailbox_ids.retain(|&id| id != INBOX_ID);
                 }
 
@@ -925,41 +1217,24 @@ async fn deliver_to_recipient(
                     }
                 }
 
-                hook_preview_text = preview_text;
-
-                // Separate modifications by type
-                let mut add_headers: Vec<(String, String)> = Vec::new();
-                let mut replace_headers: Vec<(u32, String, String)> = Vec::new();
-
-                for m in hook_modifications {
-                    match m {
-                        HookModification::AddHeader { name, value } => {
-                            add_headers.push((name, value));
-                        }
-                        HookModification::ReplaceHeader { index, name, value } => {
-                            replace_headers.push((index, name, value));
-                        }
+                hook_preview_text = outcome.preview_text;
+                hook_redirects = outcome.redirects;
+                hook_expires_in = outcome.expires_in;
+                hook_audit = outcome.audit;
+                hook_quarantine = outcome.quarantine;
+
+                // Apply header modifications on top of the message produced by the hooks
+                owned_new_raw = match outcome.raw_message {
+                    Some(hook_raw) => {
+                        apply_hook_modifications(outcome.modifications, &hook_raw, session_id)
+                            .or(Some(hook_raw))
                     }
-                }
-
-                // Apply AddHeader modifications first
-                let mut current_raw = &output_message.raw[..];
-                if !add_headers.is_empty() {
-                    owned_new_raw = Some(apply_add_header_modifications(
-                        &add_headers,
-                        current_raw,
-                    ));
-                    current_raw = owned_new_raw.as_deref().unwrap();
-                }
-
-                // Apply ReplaceHeader modifications
-                if !replace_headers.is_empty() {
-                    if let Some(modified) =
-                        apply_replace_header_modifications(&replace_headers, current_raw, session_id)
-                    {
-                        owned_new_raw = Some(modified);
-                    }
-                }
+                    None => apply_hook_modifications(
+                        outcome.modifications,
+                        &output_message.raw,
+                        session_id,
+                    ),
+                };
 
                 // Try to re-parse the modified message; rollback on failure
                 if owned_new_raw.is_some() {
@@ -990,7 +1265,7 @@ async fn deliver_to_recipient(
         }
 
         // Use modified raw bytes if present
-        let raw_for_ingest: &[u8] = if use_modified {
+        let mut raw_for_ingest: &[u8] = if use_modified {
             owned_new_raw
                 .as_deref()
                 .expect("modified bytes must exist when flagged")
@@ -998,6 +1273,115 @@ async fn deliver_to_recipient(
             &output_message.raw
         };
 
+        // Quarantine headers are set by the sender and must never be trusted
+        let stripped_raw = if server.core.smtp.quarantine.enable {
+            apply_remove_header_modifications(&["X-Quarantine".into()], raw_for_ingest)
+        } else {
+            None
+        };
+        if let Some(new_parsed) = stripped_raw
+            .as_deref()
+            .and_then(|bytes| MessageParser::new().parse(bytes))
+        {
+            parsed_for_ingest = new_parsed;
+            raw_for_ingest = stripped_raw.as_deref().unwrap();
+        }
+
+        // Hold quarantined messages outside of the recipient's mailboxes,
+        // they are not redirected either
+        let quarantine = &server.core.smtp.quarantine;
+        if quarantine.enable {
+            let reason = if hook_quarantine || rcpt.is_quarantined {
+                Some(QuarantineReason::Hook)
+            } else if rcpt.is_spam && quarantine.spam {
+                Some(QuarantineReason::Spam)
+            } else {
+                None
+            };
+
+            if let Some(reason) = reason {
+                match server
+                    .quarantine_hold(
+                        uid,
+                        sender,
+                        &rcpt.address,
+                        raw_for_ingest,
+                        reason,
+                        session_id,
+                    )
+                    .await
+                {
+                    Ok(_) => {
+                        has_delivered = true;
+                    }
+                    Err(err) => {
+                        last_temp_error = err.into();
+                    }
+                }
+                continue;
+            }
+        }
+
+        // Redirect the message through the SMTP queue
+        if !hook_redirects.is_empty() {
+            // Messages carrying this recipient in an X-Loop header were already redirected
+            // by it, deliver them locally instead of sending them around again
+            let is_loop = parsed_for_ingest.headers_raw().any(|(name, value)| {
+                name.eq_ignore_ascii_case("X-Loop")
+                    && value.trim().eq_ignore_ascii_case(&rcpt.address)
+            });
+            let mut keep = hook_redirects.iter().all(|(_, keep)| *keep);
+            if is_loop {
+                trc::event!(
+                    DeliveryHook(trc::DeliveryHookEvent::Error),
+                    AccountId = uid,
+                    Details = "Redirect loop detected, delivering locally.",
+                    SpanId = session_id
+                );
+                keep = true;
+            } else if raw_for_ingest.len() <= server.core.jmap.mail_max_size {
+                let recipients = hook_redirects
+                    .into_iter()
+                    .map(|(address, _)| address)
+                    .collect::<Vec<_>>();
+
+                trc::event!(
+                    DeliveryHook(trc::DeliveryHookEvent::ActionRedirect),
+                    AccountId = uid,
+                    To = recipients
+                        .iter()
+                        .map(|address| trc::Value::from(address.clone()))
+                        .collect::<Vec<_>>(),
+                    SpanId = session_id
+                );
+
+                let mut message =
+                    Vec::with_capacity(raw_for_ingest.len() + rcpt.address.len() + 10);
+                message.extend_from_slice(b"X-Loop: ");
+                message.extend_from_slice(rcpt.address.as_bytes());
+                message.extend_from_slice(b"\r\n");
+                message.extend_from_slice(raw_for_ingest);
+                autogenerated.push(AutogeneratedMessage {
+                    sender_address: rcpt.address.to_string(),
+                    recipients,
+                    message,
+                });
+            } else {
+                trc::event!(
+                    MessageIngest(trc::MessageIngestEvent::Error),
+                    Details = "Message too large to be redirected.",
+                    AccountId = uid,
+                    Size = raw_for_ingest.len(),
+                    SpanId = session_id
+                );
+                keep = true;
+            }
+
+            if !keep {
+                continue;
+            }
+        }
+
         match server
             .email_ingest(IngestEmail {
                 raw_message: raw_for_ingest,
@@ -1020,6 +1404,18 @@ async fn deliver_to_recipient(
             .await
         {
             Ok(ingested_message) => {
+                schedule_hook_tasks(
+                    server,
+                    uid,
+                    &ingested_message,
+                    hook_source,
+                    Some(hook_envelope),
+                    hook_expires_in,
+                    hook_audit,
+                    session_id,
+                )
+                .await;
+
                 has_delivered = true;
                 final_ingested_message = ingested_message;
             }
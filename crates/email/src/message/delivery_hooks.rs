@@ -9,20 +9,50 @@
 //! This module provides functionality to call external webhooks during email delivery,
 //! allowing for custom routing, filtering, and message modification logic.
 
-use common::{Server, expr::functions::ResolveVariable};
+use common::{
+    MessageStoreCache, Server,
+    config::smtp::delivery_hooks::{
+        CircuitBreakerFallback, CircuitState, DeliveryHook, DeliveryHookInclude, DeliveryHookMode,
+    },
+    expr::functions::ResolveVariable,
+};
 use futures::future::join_all;
-use std::{collections::HashSet, str::FromStr, time::Instant};
+use mail_parser::MessageParser;
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use store::{
+    Serialize,
+    write::{Archiver, BatchBuilder, TaskEpoch, TaskQueueClass, ValueClass, now},
+};
 use trc::AddContext;
 
-use types::{id::Id, special_use::SpecialUse};
+use types::{
+    blob_hash::{BLOB_HASH_LEN, BlobHash},
+    collection::Collection,
+    field::EmailField,
+    id::Id,
+    keyword::Keyword,
+    special_use::SpecialUse,
+};
 
 use crate::{
     cache::{MessageCacheFetch, mailbox::MailboxCacheAccess},
     hooks::{
-        self, Action as HookAction, Modification, ModificationOut,
+        self, Action as HookAction, IngestSource, Modification, ModificationOut,
+        audit::{
+            DeliveryHookAudit, HookAuditAction, HookAuditEntry, describe_modification,
+            serialize_mailbox_ids,
+        },
         client::send_delivery_hook_request,
+        context,
     },
     mailbox::{INBOX_ID, TRASH_ID, manage::MailboxFnc},
+    message::{delivery::apply_hook_modifications, ingest::IngestedEmail},
+    sieve::ingest::SieveScriptIngest,
 };
 
 pub struct DeliveryResolver;
@@ -39,33 +69,321 @@ impl ResolveVariable for DeliveryResolver {
     }
 }
 
-/// Try to call the delivery hook to determine mailbox filing
+/// Combined result of the delivery hooks executed for a recipient
+#[derive(Default)]
+pub struct DeliveryHookOutcome {
+    pub mailbox_ids: HashSet<u32>,
+    pub flags: HashSet<String>,
+    pub skip_inbox: bool,
+    pub modifications: Vec<ModificationOut>,
+    pub preview_text: Option<String>,
+    /// Message rewritten by a sequential pipeline, replaces the original message when set
+    pub raw_message: Option<Vec<u8>>,
+    /// Addresses to redirect the message to, and whether to also keep a local copy
+    pub redirects: Vec<(String, bool)>,
+    /// Seconds after which the delivered message is destroyed
+    pub expires_in: Option<u64>,
+    /// Record of the responses returned by each hook
+    pub audit: Vec<HookAuditEntry>,
+    /// Hold the message in quarantine rather than delivering it
+    pub quarantine: bool,
+    /// Mailbox paths a dry run would create to file the message into
+    pub would_create: Vec<String>,
+}
+
+/// What to do after processing the response of a single hook
+#[derive(Debug, PartialEq, Eq)]
+enum HookVerdict {
+    Continue,
+    Stop,
+    Discard,
+    TempFail,
+    PermFail,
+}
+
+/// Try to call the delivery hooks to determine mailbox filing
 /// Returns:
-/// - (mailbox_ids, flags, skip_inbox, modifications)
+/// - the combined outcome of all enabled hooks
 /// - none: discard message, but don't return an error
 pub async fn try_delivery_hook(
     server: &Server,
     user_id: u32,
-    sender: &str,
-    recipient: &str,
+    source: IngestSource,
+    envelope: Option<hooks::Envelope>,
     parsed_message: &mail_parser::Message<'_>,
-) -> trc::Result<Option<(HashSet<u32>, HashSet<String>, bool, Vec<ModificationOut>, Option<String>)>>
-{
-    let default_response = Some((HashSet::new(), HashSet::new(), false, Vec::new(), None));
+    session_id: u64,
+) -> trc::Result<Option<DeliveryHookOutcome>> {
+    run_delivery_hooks(
+        server,
+        user_id,
+        source,
+        envelope,
+        parsed_message,
+        session_id,
+        false,
+    )
+    .await
+}
 
-    let envelope = hooks::Envelope {
-        from: hooks::Address {
-            address: sender.to_string(),
-        },
-        to: hooks::Address {
-            address: recipient.to_string(),
+/// Call the asynchronous delivery hooks for a message that has already been stored,
+/// the outcome is applied to the stored message by the task manager
+pub async fn try_async_delivery_hook(
+    server: &Server,
+    user_id: u32,
+    source: IngestSource,
+    envelope: Option<hooks::Envelope>,
+    parsed_message: &mail_parser::Message<'_>,
+    session_id: u64,
+) -> trc::Result<Option<DeliveryHookOutcome>> {
+    run_delivery_hooks(
+        server,
+        user_id,
+        source,
+        envelope,
+        parsed_message,
+        session_id,
+        true,
+    )
+    .await
+}
+
+/// Returns whether any asynchronous delivery hook is configured for `source`
+pub fn has_async_hooks(server: &Server, source: IngestSource) -> bool {
+    server
+        .core
+        .smtp
+        .session
+        .delivery_hooks
+        .iter()
+        .any(|hook| hook.is_async && hook.sources.contains(&source))
+}
+
+/// Final decision reached by the hooks called in a dry run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DryRunAction {
+    Accept,
+    Discard,
+    TempFail,
+    PermFail,
+}
+
+/// What the delivery hooks would do to a message, without applying it
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryHookDryRun {
+    pub action: DryRunAction,
+    #[serde(serialize_with = "serialize_mailbox_ids")]
+    pub mailbox_ids: Vec<u32>,
+    pub would_create: Vec<String>,
+    pub flags: Vec<String>,
+    pub skip_inbox: bool,
+    pub preview_text: Option<String>,
+    pub redirects: Vec<String>,
+    pub expires_in: Option<u64>,
+    pub quarantine: bool,
+    pub added_headers: Vec<(String, String)>,
+    pub removed_headers: Vec<(String, String)>,
+    pub body_changed: bool,
+    pub hooks: Vec<HookAuditEntry>,
+    pub skipped_hooks: Vec<String>,
+}
+
+/// Call the hooks configured for `source` exactly as a delivery would, and report the
+/// resulting mailboxes, flags and header changes without modifying the account.
+/// When `hook_ids` is not empty only the listed hooks are called, regardless of their
+/// sources and `enable` expression. Asynchronous hooks act on messages that were already
+/// delivered, so they are skipped unless listed explicitly.
+pub async fn dry_run_delivery_hooks(
+    server: &Server,
+    user_id: u32,
+    source: IngestSource,
+    envelope: Option<hooks::Envelope>,
+    parsed_message: &mail_parser::Message<'_>,
+    hook_ids: &[String],
+    session_id: u64,
+) -> trc::Result<DeliveryHookDryRun> {
+    let resolver = DeliveryResolver;
+    let mut enabled_hooks = Vec::new();
+    let mut skipped_hooks = Vec::new();
+    for hook in &server.core.smtp.session.delivery_hooks {
+        let is_enabled = if hook_ids.is_empty() {
+            hook.sources.contains(&source)
+                && server
+                    .eval_if(&hook.enable, &resolver, 0)
+                    .await
+                    .unwrap_or(false)
+        } else {
+            hook_ids.contains(&hook.id)
+        };
+        if !is_enabled {
+            continue;
+        }
+        if hook.is_async && hook_ids.is_empty() {
+            skipped_hooks.push(hook.id.clone());
+        } else {
+            enabled_hooks.push(hook);
+        }
+    }
+
+    let (verdict, outcome) = call_hooks(
+        server,
+        user_id,
+        source,
+        envelope,
+        parsed_message,
+        session_id,
+        enabled_hooks,
+        true,
+    )
+    .await?;
+
+    // Compare the original message with the one that would be stored
+    let original_raw = parsed_message.raw_message.as_ref();
+    let current_raw = outcome.raw_message.as_deref().unwrap_or(original_raw);
+    let modified_raw = apply_hook_modifications(outcome.modifications, current_raw, session_id)
+        .or(outcome.raw_message);
+    let (added_headers, removed_headers, body_changed) = match modified_raw
+        .as_deref()
+        .and_then(|raw| MessageParser::new().parse(raw))
+    {
+        Some(modified) => diff_messages(parsed_message, &modified),
+        None => (Vec::new(), Vec::new(), false),
+    };
+
+    let mut mailbox_ids = outcome.mailbox_ids.into_iter().collect::<Vec<_>>();
+    mailbox_ids.sort_unstable();
+    let mut flags = outcome.flags.into_iter().collect::<Vec<_>>();
+    flags.sort_unstable();
+
+    Ok(DeliveryHookDryRun {
+        action: match verdict {
+            HookVerdict::Continue | HookVerdict::Stop => DryRunAction::Accept,
+            HookVerdict::Discard => DryRunAction::Discard,
+            HookVerdict::TempFail => DryRunAction::TempFail,
+            HookVerdict::PermFail => DryRunAction::PermFail,
         },
+        mailbox_ids,
+        would_create: outcome.would_create,
+        flags,
+        skip_inbox: outcome.skip_inbox,
+        preview_text: outcome.preview_text,
+        redirects: outcome
+            .redirects
+            .into_iter()
+            .map(|(address, _)| address)
+            .collect(),
+        expires_in: outcome.expires_in,
+        quarantine: outcome.quarantine,
+        added_headers,
+        removed_headers,
+        body_changed,
+        hooks: outcome.audit,
+        skipped_hooks,
+    })
+}
+
+/// Headers added and removed between two versions of a message, and whether its body changed
+#[allow(clippy::type_complexity)]
+fn diff_messages(
+    original: &mail_parser::Message<'_>,
+    modified: &mail_parser::Message<'_>,
+) -> (Vec<(String, String)>, Vec<(String, String)>, bool) {
+    let headers = |message: &mail_parser::Message<'_>| {
+        message
+            .headers_raw()
+            .map(|(name, value)| (name.to_string(), value.trim().to_string()))
+            .collect::<Vec<_>>()
+    };
+    let mut added = headers(modified);
+    let mut removed = Vec::new();
+    for header in headers(original) {
+        if let Some(pos) = added.iter().position(|h| h == &header) {
+            added.remove(pos);
+        } else {
+            removed.push(header);
+        }
+    }
+
+    let body = |message: &mail_parser::Message<'_>| {
+        message
+            .raw_message
+            .get(message.root_part().raw_body_offset() as usize..)
+            .unwrap_or_default()
+            .to_vec()
     };
 
-    let headers = parsed_message
-        .headers_raw()
-        .map(|(k, v)| (k.to_string(), v.to_string()))
-        .collect();
+    (added, removed, body(original) != body(modified))
+}
+
+async fn run_delivery_hooks(
+    server: &Server,
+    user_id: u32,
+    source: IngestSource,
+    envelope: Option<hooks::Envelope>,
+    parsed_message: &mail_parser::Message<'_>,
+    session_id: u64,
+    is_async: bool,
+) -> trc::Result<Option<DeliveryHookOutcome>> {
+    // Get configured delivery hooks
+    let delivery_hooks = &server.core.smtp.session.delivery_hooks;
+
+    // If no hooks configured, return default to continue normal flow
+    if delivery_hooks.is_empty() {
+        return Ok(Some(DeliveryHookOutcome::default()));
+    }
+
+    // Filter enabled hooks
+    let resolver = DeliveryResolver;
+    let mut enabled_hooks = Vec::new();
+    for hook in delivery_hooks {
+        if hook.is_async == is_async
+            && hook.sources.contains(&source)
+            && server
+                .eval_if(&hook.enable, &resolver, 0)
+                .await
+                .unwrap_or(false)
+        {
+            enabled_hooks.push(hook);
+        }
+    }
+
+    match call_hooks(
+        server,
+        user_id,
+        source,
+        envelope,
+        parsed_message,
+        session_id,
+        enabled_hooks,
+        false,
+    )
+    .await?
+    {
+        (HookVerdict::Continue | HookVerdict::Stop, outcome) => Ok(Some(outcome)),
+        (HookVerdict::Discard, _) => Ok(None),
+        (HookVerdict::TempFail, _) => Err(tempfail_error()),
+        (HookVerdict::PermFail, _) => Err(permfail_error()),
+    }
+}
+
+/// Build the requests for the enabled hooks and call them, returns the final verdict
+/// together with the combined outcome. During a dry run requests are flagged with
+/// `dry_run`, mailboxes are not created and circuit breakers are bypassed.
+#[allow(clippy::too_many_arguments)]
+async fn call_hooks(
+    server: &Server,
+    user_id: u32,
+    source: IngestSource,
+    envelope: Option<hooks::Envelope>,
+    parsed_message: &mail_parser::Message<'_>,
+    session_id: u64,
+    enabled_hooks: Vec<&DeliveryHook>,
+    dry_run: bool,
+) -> trc::Result<(HookVerdict, DeliveryHookOutcome)> {
+    if enabled_hooks.is_empty() {
+        return Ok((HookVerdict::Continue, DeliveryHookOutcome::default()));
+    }
 
     let principal = match server
         .directory()
@@ -86,50 +404,395 @@ pub async fn try_delivery_hook(
         Err(err) => return Err(err),
     };
 
-    let request = hooks::Request::new(
-        Id::from(user_id).as_string(),
-        principal.name,
+    let mut request = hooks::Request::new(Id::from(user_id).as_string(), principal.name)
+        .with_source(source)
+        .with_dry_run(dry_run);
+    if let Some(envelope) = envelope {
+        request = request.with_envelope(envelope);
+    }
+
+    // Get mailbox cache for resolving mailbox names and special use folders
+    let mut cache = server
+        .get_cached_messages(user_id)
+        .await
+        .caused_by(trc::location!())?;
+
+    // Add the optional fields each hook opted into
+    let context = RequestContext::build(
+        server,
+        user_id,
+        source,
+        &enabled_hooks,
+        parsed_message,
+        &cache,
     )
-    .with_envelope(envelope)
-    .with_message(hooks::Message {
-        headers,
-        server_headers: vec![],
-        contents: String::from_utf8_lossy(&parsed_message.raw_message).into_owned(),
-        size: parsed_message.raw_message.len(),
+    .await?;
+    let enabled_hooks = enabled_hooks
+        .into_iter()
+        .map(|hook| (hook, context.request_for(hook, &request)))
+        .collect::<Vec<_>>();
+
+    match server.core.smtp.session.delivery_hooks_mode {
+        DeliveryHookMode::Parallel => {
+            run_parallel(
+                server,
+                user_id,
+                enabled_hooks,
+                build_message(parsed_message),
+                &mut cache,
+                dry_run,
+            )
+            .await
+        }
+        DeliveryHookMode::Sequential => {
+            run_sequential(
+                server,
+                user_id,
+                enabled_hooks,
+                parsed_message,
+                session_id,
+                &mut cache,
+                dry_run,
+            )
+            .await
+        }
+    }
+}
+
+/// Optional request fields, computed once and only sent to the hooks that opted into them
+#[derive(Default)]
+struct RequestContext {
+    spam: Option<hooks::SpamVerdict>,
+    auth_results: Option<hooks::AuthResults>,
+    mailboxes: Option<Vec<hooks::MailboxInfo>>,
+    sieve_script: Option<String>,
+}
+
+impl RequestContext {
+    async fn build(
+        server: &Server,
+        user_id: u32,
+        source: IngestSource,
+        enabled_hooks: &[&DeliveryHook],
+        parsed_message: &mail_parser::Message<'_>,
+        cache: &MessageStoreCache,
+    ) -> trc::Result<Self> {
+        let mut context = RequestContext::default();
+        let wants = |include| {
+            enabled_hooks
+                .iter()
+                .any(|hook| hook.include.contains(&include))
+        };
+
+        // Verdict headers can only be trusted when added by this server during the SMTP session
+        let is_local_delivery = matches!(source, IngestSource::Smtp | IngestSource::Sieve);
+        if is_local_delivery && wants(DeliveryHookInclude::Spam) {
+            context.spam = context::spam_verdict(parsed_message.headers_raw());
+        }
+        if is_local_delivery && wants(DeliveryHookInclude::AuthResults) {
+            context.auth_results = context::auth_results(parsed_message.headers_raw());
+        }
+
+        if wants(DeliveryHookInclude::Mailboxes) {
+            context.mailboxes = cache
+                .mailboxes
+                .items
+                .iter()
+                .map(|mailbox| hooks::MailboxInfo {
+                    id: Id::from(mailbox.document_id).as_string(),
+                    path: mailbox.path.clone(),
+                    role: mailbox.role.as_str().map(String::from),
+                })
+                .collect::<Vec<_>>()
+                .into();
+        }
+
+        if wants(DeliveryHookInclude::SieveScript) {
+            context.sieve_script = server
+                .sieve_script_get_active(user_id)
+                .await
+                .caused_by(trc::location!())?
+                .map(|script| script.script_name);
+        }
+
+        Ok(context)
+    }
+
+    fn request_for(&self, hook: &DeliveryHook, request: &hooks::Request) -> hooks::Request {
+        let mut request = request.clone();
+        for include in &hook.include {
+            match include {
+                DeliveryHookInclude::Spam => request.spam = self.spam.clone(),
+                DeliveryHookInclude::AuthResults => {
+                    request.auth_results = self.auth_results.clone()
+                }
+                DeliveryHookInclude::Mailboxes => request.mailboxes = self.mailboxes.clone(),
+                DeliveryHookInclude::SieveScript => {
+                    request.sieve_script = self.sieve_script.clone()
+                }
+            }
+        }
+        request
+    }
+}
+
+/// Changes requested by the delivery hooks for a message added by a client
+#[derive(Default)]
+pub struct IngestHookOutcome {
+    /// Rewritten message, replaces the original message when set
+    pub raw_message: Option<Vec<u8>>,
+    pub preview_text: Option<String>,
+    pub expires_in: Option<u64>,
+    pub audit: Vec<HookAuditEntry>,
+}
+
+/// Run the delivery hooks configured for `source` on a message added through
+/// IMAP APPEND or JMAP Email/import, updating the target mailboxes and keywords.
+/// Returns `None` when a hook discarded the message.
+pub async fn try_ingest_hook(
+    server: &Server,
+    account_id: u32,
+    source: IngestSource,
+    raw_message: &[u8],
+    mailbox_ids: &mut Vec<u32>,
+    keywords: &mut Vec<Keyword>,
+    session_id: u64,
+) -> trc::Result<Option<IngestHookOutcome>> {
+    // Avoid parsing the message when no hook handles this source
+    if !server
+        .core
+        .smtp
+        .session
+        .delivery_hooks
+        .iter()
+        .any(|hook| !hook.is_async && hook.sources.contains(&source))
+    {
+        return Ok(Some(IngestHookOutcome::default()));
+    }
+    let Some(parsed_message) = MessageParser::new().parse(raw_message) else {
+        return Ok(Some(IngestHookOutcome::default()));
+    };
+
+    let Some(outcome) = try_delivery_hook(
+        server,
+        account_id,
+        source,
+        None,
+        &parsed_message,
+        session_id,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    // Mailboxes requested by the client are kept first so their UIDs can be reported back
+    for mailbox_id in outcome.mailbox_ids {
+        if !mailbox_ids.contains(&mailbox_id) {
+            mailbox_ids.push(mailbox_id);
+        }
+    }
+    if outcome.skip_inbox && mailbox_ids.len() > 1 {
+        mailbox_ids.retain(|&id| id != INBOX_ID);
+    }
+    for keyword in outcome.flags.into_iter().map(Keyword::from) {
+        if !keywords.contains(&keyword) {
+            keywords.push(keyword);
+        }
+    }
+
+    if !outcome.redirects.is_empty() {
+        trc::event!(
+            DeliveryHook(trc::DeliveryHookEvent::Error),
+            AccountId = account_id,
+            Details = "Redirects are only supported for local deliveries, ignoring.",
+            SpanId = session_id
+        );
+    }
+
+    // Apply modifications, rolling back if the result can't be parsed
+    let raw_message = match outcome.raw_message {
+        Some(hook_raw) => apply_hook_modifications(outcome.modifications, &hook_raw, session_id)
+            .or(Some(hook_raw)),
+        None => apply_hook_modifications(outcome.modifications, raw_message, session_id),
+    }
+    .filter(|raw_message| {
+        let is_valid = MessageParser::new().parse(raw_message).is_some();
+        if !is_valid {
+            trc::event!(
+                MessageIngest(trc::MessageIngestEvent::Error),
+                Details = "Failed to parse message after header modifications.",
+                SpanId = session_id
+            );
+        }
+        is_valid
     });
 
-    // Get configured delivery hooks
-    let delivery_hooks = &server.core.smtp.session.delivery_hooks;
+    Ok(Some(IngestHookOutcome {
+        raw_message,
+        preview_text: outcome.preview_text,
+        expires_in: outcome.expires_in,
+        audit: outcome.audit,
+    }))
+}
 
-    // If no hooks configured, return default to continue normal flow
-    if delivery_hooks.is_empty() {
-        return Ok(default_response);
+/// Asynchronous delivery hooks pending to be called for a stored message
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AsyncHookTask {
+    /// Hash of the message the task was scheduled for, guards against reused document ids
+    pub blob_hash: BlobHash,
+    pub source: IngestSource,
+    pub envelope: Option<hooks::Envelope>,
+}
+
+impl AsyncHookTask {
+    pub fn serialize(&self) -> Vec<u8> {
+        let envelope_len = self
+            .envelope
+            .as_ref()
+            .map_or(0, |e| e.from.address.len() + e.to.address.len() + 1);
+        let mut buf = Vec::with_capacity(BLOB_HASH_LEN + 1 + envelope_len);
+        buf.extend_from_slice(self.blob_hash.as_slice());
+        buf.push(match self.source {
+            IngestSource::Smtp => 0,
+            IngestSource::Sieve => 1,
+            IngestSource::Imap => 2,
+            IngestSource::Jmap => 3,
+        });
+        if let Some(envelope) = &self.envelope {
+            buf.extend_from_slice(envelope.from.address.as_bytes());
+            buf.push(0);
+            buf.extend_from_slice(envelope.to.address.as_bytes());
+        }
+        buf
     }
 
-    // Filter enabled hooks
-    let resolver = DeliveryResolver;
-    let mut enabled_hooks = Vec::new();
-    for hook in delivery_hooks {
-        if server
-            .eval_if(&hook.enable, &resolver, 0)
-            .await
-            .unwrap_or(false)
-        {
-            enabled_hooks.push(hook);
+    pub fn deserialize(bytes: &[u8]) -> Option<Self> {
+        let blob_hash = BlobHash::try_from_hash_slice(bytes.get(..BLOB_HASH_LEN)?).ok()?;
+        let source = match bytes.get(BLOB_HASH_LEN)? {
+            0 => IngestSource::Smtp,
+            1 => IngestSource::Sieve,
+            2 => IngestSource::Imap,
+            3 => IngestSource::Jmap,
+            _ => return None,
+        };
+        let envelope = match bytes.get(BLOB_HASH_LEN + 1..) {
+            Some(envelope) if !envelope.is_empty() => {
+                let (from, to) = std::str::from_utf8(envelope).ok()?.split_once('\0')?;
+                Some(hooks::Envelope {
+                    from: hooks::Address {
+                        address: from.to_string(),
+                    },
+                    to: hooks::Address {
+                        address: to.to_string(),
+                    },
+                })
+            }
+            _ => None,
+        };
+
+        Some(Self {
+            blob_hash,
+            source,
+            envelope,
+        })
+    }
+}
+
+/// Queue the tasks requested for an ingested message: its deletion once the expiry
+/// set by a hook is due, and the asynchronous hooks configured for `source`.
+/// The responses of the hooks that processed the message are stored as its audit trail.
+#[allow(clippy::too_many_arguments)]
+pub async fn schedule_hook_tasks(
+    server: &Server,
+    account_id: u32,
+    ingested_message: &IngestedEmail,
+    source: IngestSource,
+    envelope: Option<hooks::Envelope>,
+    expires_in: Option<u64>,
+    audit: Vec<HookAuditEntry>,
+    session_id: u64,
+) {
+    // Duplicate messages are not stored
+    if ingested_message.change_id == u64::MAX {
+        return;
+    }
+
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(Collection::Email)
+        .with_document(ingested_message.document_id);
+    if let Some(expires_in) = expires_in {
+        batch.set(
+            ValueClass::TaskQueue(TaskQueueClass::ExpireEmail {
+                due: TaskEpoch::new(now().saturating_add(expires_in)),
+            }),
+            ingested_message.blob_id.hash.as_slice().to_vec(),
+        );
+    }
+    if has_async_hooks(server, source) {
+        batch.set(
+            ValueClass::TaskQueue(TaskQueueClass::DeliveryHook {
+                due: TaskEpoch::now(),
+            }),
+            AsyncHookTask {
+                blob_hash: ingested_message.blob_id.hash.clone(),
+                source,
+                envelope,
+            }
+            .serialize(),
+        );
+    }
+    if !audit.is_empty() {
+        let mut hook_audit = DeliveryHookAudit::default();
+        hook_audit.add_run(source, false, now(), audit);
+        match Archiver::new(hook_audit).serialize() {
+            Ok(value) => {
+                batch.set(EmailField::HookAudit, value);
+            }
+            Err(err) => {
+                trc::error!(
+                    err.account_id(account_id)
+                        .document_id(ingested_message.document_id)
+                        .span_id(session_id)
+                        .details("Failed to serialize delivery hook audit")
+                );
+            }
         }
     }
+    if batch.is_empty() {
+        return;
+    }
 
-    if enabled_hooks.is_empty() {
-        return Ok(default_response);
+    match server.store().write(batch.build_all()).await {
+        Ok(_) => server.notify_task_queue(),
+        Err(err) => {
+            trc::error!(
+                err.account_id(account_id)
+                    .document_id(ingested_message.document_id)
+                    .span_id(session_id)
+                    .details("Failed to schedule delivery hook tasks")
+            );
+        }
     }
+}
 
-    // Run all enabled hooks in parallel
+/// Run all enabled hooks concurrently and merge their responses
+async fn run_parallel(
+    server: &Server,
+    user_id: u32,
+    enabled_hooks: Vec<(&DeliveryHook, hooks::Request)>,
+    message: hooks::Message,
+    cache: &mut Arc<MessageStoreCache>,
+    dry_run: bool,
+) -> trc::Result<(HookVerdict, DeliveryHookOutcome)> {
     let mut hook_futures = Vec::new();
-    for hook in enabled_hooks {
-        let hook_request = request.clone();
+    for (hook, request) in enabled_hooks {
+        let hook_request = request.with_message(message.clone());
         let time = Instant::now();
         hook_futures.push(async move {
-            let result = send_delivery_hook_request(hook, hook_request).await;
+            let result = send_request(server, hook, hook_request, user_id, dry_run).await;
             (hook, result, time.elapsed())
         });
     }
@@ -137,192 +800,486 @@ pub async fn try_delivery_hook(
     let hook_results = join_all(hook_futures).await;
 
     // Process all hook results
-    let mut mailbox_ids = HashSet::new();
-    let mut flags = HashSet::new();
-    let mut skip_inbox = false;
-    let mut modifications_out: Vec<ModificationOut> = Vec::new();
-    let mut preview_text: Option<String> = None;
+    let mut outcome = DeliveryHookOutcome::default();
     let mut should_tempfail = false;
     let mut should_permfail = false;
 
-    // Get mailbox cache for resolving mailbox names and special use folders
-    let mut cache = server
-        .get_cached_messages(user_id)
-        .await
-        .caused_by(trc::location!())?;
-
     for (hook, result, elapsed) in hook_results {
-        match result {
-            Ok(response) => {
-                if response.skip_inbox {
-                    skip_inbox = true;
-                }
+        let verdict = match result {
+            Some(result) => {
+                process_hook_result(
+                    server,
+                    user_id,
+                    hook,
+                    result,
+                    elapsed,
+                    cache,
+                    &mut outcome,
+                    false,
+                    dry_run,
+                )
+                .await?
+            }
+            None => circuit_fallback(hook, user_id, &mut outcome),
+        };
 
-                if preview_text.is_none() {
-                    preview_text = response.preview_text;
-                }
+        match verdict {
+            HookVerdict::Continue | HookVerdict::Stop => {}
+            // Discard means we stop processing further hooks and do not deliver
+            HookVerdict::Discard => return Ok((HookVerdict::Discard, outcome)),
+            HookVerdict::TempFail => should_tempfail = true,
+            HookVerdict::PermFail => should_permfail = true,
+        }
+    }
+
+    // Check for failures - tempfail takes precedence over permfail for retry behavior
+    let verdict = if should_tempfail {
+        HookVerdict::TempFail
+    } else if should_permfail {
+        HookVerdict::PermFail
+    } else {
+        HookVerdict::Continue
+    };
+
+    Ok((verdict, outcome))
+}
+
+/// Run the enabled hooks one after another, feeding each hook the message and
+/// filing decisions produced by the previous ones
+async fn run_sequential(
+    server: &Server,
+    user_id: u32,
+    enabled_hooks: Vec<(&DeliveryHook, hooks::Request)>,
+    parsed_message: &mail_parser::Message<'_>,
+    session_id: u64,
+    cache: &mut Arc<MessageStoreCache>,
+    dry_run: bool,
+) -> trc::Result<(HookVerdict, DeliveryHookOutcome)> {
+    let mut outcome = DeliveryHookOutcome::default();
+    let mut previous_hooks = Vec::with_capacity(enabled_hooks.len());
+    let mut message = build_message(parsed_message);
 
-                for flag in response.flags {
-                    flags.insert(flag);
+    for (position, (hook, request)) in enabled_hooks.into_iter().enumerate() {
+        let hook_request = request
+            .with_message(message.clone())
+            .with_pipeline(hooks::Pipeline {
+                position: position as u32 + 1,
+                previous_hooks: previous_hooks.clone(),
+                mailbox_ids: outcome
+                    .mailbox_ids
+                    .iter()
+                    .map(|id| Id::from(*id).as_string())
+                    .collect(),
+                flags: outcome.flags.iter().cloned().collect(),
+                skip_inbox: outcome.skip_inbox,
+                preview_text: outcome.preview_text.clone(),
+            });
+
+        let time = Instant::now();
+        let verdict = match send_request(server, hook, hook_request, user_id, dry_run).await {
+            Some(result) => {
+                process_hook_result(
+                    server,
+                    user_id,
+                    hook,
+                    result,
+                    time.elapsed(),
+                    cache,
+                    &mut outcome,
+                    true,
+                    dry_run,
+                )
+                .await?
+            }
+            None => circuit_fallback(hook, user_id, &mut outcome),
+        };
+        previous_hooks.push(hook.id.clone());
+
+        match verdict {
+            HookVerdict::Continue | HookVerdict::Stop => {}
+            HookVerdict::Discard | HookVerdict::TempFail | HookVerdict::PermFail => {
+                return Ok((verdict, outcome));
+            }
+        }
+
+        // Apply the modifications so the next hook sees the rewritten message
+        if !outcome.modifications.is_empty() {
+            let current_raw = outcome
+                .raw_message
+                .as_deref()
+                .unwrap_or(parsed_message.raw_message.as_ref());
+            if let Some(new_raw) = apply_hook_modifications(
+                std::mem::take(&mut outcome.modifications),
+                current_raw,
+                session_id,
+            ) {
+                if let Some(new_parsed) = MessageParser::new().parse(&new_raw) {
+                    message = build_message(&new_parsed);
+                    outcome.raw_message = Some(new_raw);
+                } else {
+                    trc::event!(
+                        MessageIngest(trc::MessageIngestEvent::Error),
+                        Details = format!(
+                            "Failed to parse message after header modifications from hook '{}'.",
+                            hook.id
+                        ),
+                        SpanId = session_id
+                    );
                 }
+            }
+        }
 
-                for modification in response.modifications {
-                    match modification {
-                        Modification::FileInto {
-                            folder: mailbox,
-                            mailbox_id,
-                            special_use,
-                            create,
-                        } => {
-                            let mut target_id = u32::MAX;
-
-                            // Find mailbox by Id first (similar to sieve ingest logic)
-                            if !mailbox_id.is_empty() {
-                                if let Some(id) = Id::from_str(&mailbox_id).ok() {
-                                    let document_id = id.document_id();
-                                    if cache.has_mailbox_id(&document_id) {
-                                        target_id = document_id;
-                                    }
-                                }
-                            }
-
-                            // Find mailbox by special_use role if ID not found
-                            if let Some(special_use_role) = &special_use
-                                && target_id == u32::MAX
-                            {
-                                if special_use_role.eq_ignore_ascii_case("inbox") {
-                                    target_id = INBOX_ID;
-                                } else if special_use_role.eq_ignore_ascii_case("trash") {
-                                    target_id = TRASH_ID;
-                                } else if let Some(role) = SpecialUse::parse(special_use_role)
-                                    && let Some(item) = cache.mailbox_by_role(&role)
-                                {
-                                    target_id = item.document_id;
-                                }
-                            }
-
-                            // Find mailbox by name
-                            if target_id == u32::MAX {
-                                if !create {
-                                    if let Some(m) = cache.mailbox_by_path(&mailbox) {
-                                        target_id = m.document_id;
-                                    }
-                                } else if let Some(document_id) = server
-                                    .mailbox_create_path(user_id, &mailbox)
-                                    .await
-                                    .caused_by(trc::location!())?
-                                {
-                                    // Refresh cache after creating mailbox
-                                    cache = server
-                                        .get_cached_messages(user_id)
-                                        .await
-                                        .caused_by(trc::location!())?;
-                                    target_id = document_id;
-                                }
-                            }
-
-                            // Don't file into invalid mailboxes
-                            if target_id != u32::MAX {
-                                mailbox_ids.insert(target_id);
-                            }
-                        }
-                        Modification::AddHeader { name, value } => {
-                            modifications_out.push(ModificationOut::AddHeader { name, value });
-                        }
-                        Modification::ReplaceHeader { index, name, value } => {
-                            modifications_out.push(ModificationOut::ReplaceHeader {
-                                index,
-                                name,
-                                value,
-                            });
-                        }
+        if verdict == HookVerdict::Stop {
+            break;
+        }
+    }
+
+    Ok((HookVerdict::Continue, outcome))
+}
+
+/// Send a request to the hook unless its circuit breaker is open,
+/// returns `None` when the hook was skipped
+async fn send_request(
+    server: &Server,
+    hook: &DeliveryHook,
+    request: hooks::Request,
+    user_id: u32,
+    dry_run: bool,
+) -> Option<Result<hooks::Response, String>> {
+    let Some(config) = hook.circuit_breaker.as_ref().filter(|_| !dry_run) else {
+        return Some(send_delivery_hook_request(server, hook, request).await);
+    };
+
+    // A probe that has not completed within the hook timeout is abandoned
+    let breaker = server.inner.data.delivery_hook_breakers.get(&hook.id);
+    match breaker.acquire(config, hook.timeout.as_secs().max(1), now()) {
+        CircuitState::Closed => {}
+        CircuitState::HalfOpen => {
+            trc::event!(
+                DeliveryHook(trc::DeliveryHookEvent::CircuitBreakerHalfOpen),
+                AccountId = user_id,
+                Details = format!(
+                    "Hook '{}' circuit breaker half-open, sending probe",
+                    hook.id
+                ),
+            );
+        }
+        CircuitState::Open => return None,
+    }
+
+    let result = send_delivery_hook_request(server, hook, request).await;
+    if result.is_ok() {
+        if breaker.record_success() {
+            trc::event!(
+                DeliveryHook(trc::DeliveryHookEvent::CircuitBreakerClosed),
+                AccountId = user_id,
+                Details = format!("Hook '{}' circuit breaker closed", hook.id),
+            );
+        }
+    } else if breaker.record_failure(config, now()) {
+        trc::event!(
+            DeliveryHook(trc::DeliveryHookEvent::CircuitBreakerOpen),
+            AccountId = user_id,
+            Details = format!("Hook '{}' circuit breaker opened", hook.id),
+            Total = config.threshold,
+        );
+    }
+
+    Some(result)
+}
+
+/// Apply the configured fallback action for a hook skipped by its circuit breaker
+fn circuit_fallback(
+    hook: &DeliveryHook,
+    user_id: u32,
+    outcome: &mut DeliveryHookOutcome,
+) -> HookVerdict {
+    let fallback = hook
+        .circuit_breaker
+        .as_ref()
+        .map(|breaker| breaker.fallback)
+        .unwrap_or_default();
+
+    trc::event!(
+        DeliveryHook(trc::DeliveryHookEvent::CircuitBreakerFallback),
+        AccountId = user_id,
+        Details = format!("Hook '{}' skipped, circuit breaker is open", hook.id),
+    );
+    outcome.audit.push(
+        HookAuditEntry::new(&hook.id, Duration::ZERO, HookAuditAction::Skipped)
+            .with_error("Circuit breaker is open"),
+    );
+
+    match fallback {
+        CircuitBreakerFallback::Accept => HookVerdict::Continue,
+        CircuitBreakerFallback::TempFail => HookVerdict::TempFail,
+        CircuitBreakerFallback::Quarantine => {
+            outcome.quarantine = true;
+            HookVerdict::Continue
+        }
+    }
+}
+
+/// Merge a single hook response into the outcome
+#[allow(clippy::too_many_arguments)]
+async fn process_hook_result(
+    server: &Server,
+    user_id: u32,
+    hook: &DeliveryHook,
+    result: Result<hooks::Response, String>,
+    elapsed: Duration,
+    cache: &mut Arc<MessageStoreCache>,
+    outcome: &mut DeliveryHookOutcome,
+    sequential: bool,
+    dry_run: bool,
+) -> trc::Result<HookVerdict> {
+    let response = match result {
+        Ok(response) => response,
+        Err(err) => {
+            // Hook error - log and potentially fail
+            trc::event!(
+                DeliveryHook(trc::DeliveryHookEvent::Error),
+                AccountId = user_id,
+                Details = format!("Hook '{}': {}", hook.id, err),
+                Elapsed = elapsed,
+            );
+            outcome.audit.push(
+                HookAuditEntry::new(&hook.id, elapsed, HookAuditAction::Error).with_error(err),
+            );
+
+            // If tempfail_on_error is set, hook errors should cause tempfail
+            return Ok(if hook.tempfail_on_error && !hook.is_async {
+                HookVerdict::TempFail
+            } else {
+                HookVerdict::Continue
+            });
+        }
+    };
+
+    let mut audit = HookAuditEntry::new(&hook.id, elapsed, (&response.action).into());
+    audit.flags = response.flags.clone();
+    audit.stop = response.stop;
+
+    if response.skip_inbox {
+        outcome.skip_inbox = true;
+    }
+
+    // In a pipeline later hooks may override the preview text set by earlier ones
+    if response.preview_text.is_some() && (sequential || outcome.preview_text.is_none()) {
+        outcome.preview_text = response.preview_text;
+    }
+
+    for flag in response.flags {
+        outcome.flags.insert(flag);
+    }
+
+    // In a pipeline the mailboxes chosen by a hook override those chosen by earlier ones
+    if sequential
+        && response
+            .modifications
+            .iter()
+            .any(|modification| matches!(modification, Modification::FileInto { .. }))
+    {
+        outcome.mailbox_ids.clear();
+        outcome.would_create.clear();
+    }
+
+    for modification in response.modifications {
+        audit
+            .modifications
+            .push(describe_modification(&modification));
+        match modification {
+            Modification::FileInto {
+                folder: mailbox,
+                mailbox_id,
+                special_use,
+                create,
+            } => {
+                let mut target_id = u32::MAX;
+
+                // Find mailbox by Id first (similar to sieve ingest logic)
+                if !mailbox_id.is_empty()
+                    && let Ok(id) = Id::from_str(&mailbox_id)
+                {
+                    let document_id = id.document_id();
+                    if cache.has_mailbox_id(&document_id) {
+                        target_id = document_id;
                     }
                 }
 
-                match response.action {
-                    HookAction::Accept => {
-                        trc::event!(
-                            DeliveryHook(trc::DeliveryHookEvent::ActionAccept),
-                            AccountId = user_id,
-                            Details = format!("Hook '{}' accepted", hook.id),
-                            Elapsed = elapsed,
-                        );
-                    }
-                    HookAction::Discard => {
-                        trc::event!(
-                            DeliveryHook(trc::DeliveryHookEvent::ActionDiscard),
-                            AccountId = user_id,
-                            Details = format!("Hook '{}' discarded", hook.id),
-                            Elapsed = elapsed,
-                        );
-                        // Discard means we stop processing further hooks and do not deliver
-                        return Ok(None);
-                    }
-                    HookAction::Quarantine => {
-                        trc::event!(
-                            DeliveryHook(trc::DeliveryHookEvent::ActionQuarantine),
-                            AccountId = user_id,
-                            Details = format!("Hook '{}' quarantined", hook.id),
-                            Elapsed = elapsed,
-                        );
-                        modifications_out.push(ModificationOut::AddHeader {
-                            name: "X-Quarantine".into(),
-                            value: "true".into(),
-                        });
+                // Find mailbox by special_use role if ID not found
+                if let Some(special_use_role) = &special_use
+                    && target_id == u32::MAX
+                {
+                    if special_use_role.eq_ignore_ascii_case("inbox") {
+                        target_id = INBOX_ID;
+                    } else if special_use_role.eq_ignore_ascii_case("trash") {
+                        target_id = TRASH_ID;
+                    } else if let Some(role) = SpecialUse::parse(special_use_role)
+                        && let Some(item) = cache.mailbox_by_role(&role)
+                    {
+                        target_id = item.document_id;
                     }
-                    HookAction::Reject => {
-                        trc::event!(
-                            DeliveryHook(trc::DeliveryHookEvent::ActionReject),
-                            AccountId = user_id,
-                            Details = format!("Hook '{}' rejected", hook.id),
-                            Elapsed = elapsed,
-                        );
-
-                        // Check if this rejection should be a tempfail or permfail
-                        if hook.tempfail_on_error {
-                            should_tempfail = true;
-                        } else {
-                            should_permfail = true;
+                }
+
+                // Find mailbox by name
+                if target_id == u32::MAX {
+                    if !create || dry_run {
+                        if let Some(m) = cache.mailbox_by_path(&mailbox) {
+                            target_id = m.document_id;
+                        } else if create
+                            && !mailbox.trim().is_empty()
+                            && !outcome.would_create.contains(&mailbox)
+                        {
+                            // Report the mailbox a real delivery would create
+                            outcome.would_create.push(mailbox);
                         }
+                    } else if let Some(document_id) = server
+                        .mailbox_create_path(user_id, &mailbox)
+                        .await
+                        .caused_by(trc::location!())?
+                    {
+                        // Refresh cache after creating mailbox
+                        *cache = server
+                            .get_cached_messages(user_id)
+                            .await
+                            .caused_by(trc::location!())?;
+                        target_id = document_id;
                     }
                 }
-            }
-            Err(err) => {
-                // Hook error - log and potentially fail
-                trc::event!(
-                    DeliveryHook(trc::DeliveryHookEvent::Error),
-                    AccountId = user_id,
-                    Details = format!("Hook '{}': {}", hook.id, err),
-                    Elapsed = elapsed,
-                );
 
-                // If tempfail_on_error is set, hook errors should cause tempfail
-                if hook.tempfail_on_error {
-                    should_tempfail = true;
+                // Don't file into invalid mailboxes
+                if target_id != u32::MAX {
+                    outcome.mailbox_ids.insert(target_id);
+                    audit.mailbox_ids.push(target_id);
+                }
+            }
+            Modification::AddHeader { name, value } => {
+                outcome
+                    .modifications
+                    .push(ModificationOut::AddHeader { name, value });
+            }
+            Modification::ReplaceHeader { index, name, value } => {
+                outcome
+                    .modifications
+                    .push(ModificationOut::ReplaceHeader { index, name, value });
+            }
+            Modification::RemoveHeader { name } => {
+                outcome
+                    .modifications
+                    .push(ModificationOut::RemoveHeader { name });
+            }
+            Modification::PrependBodyBanner { text, html } => {
+                outcome
+                    .modifications
+                    .push(ModificationOut::PrependBodyBanner { text, html });
+            }
+            Modification::ReplaceMimePart {
+                part_id,
+                content_type,
+                contents,
+            } => {
+                outcome
+                    .modifications
+                    .push(ModificationOut::ReplaceMimePart {
+                        part_id,
+                        content_type,
+                        contents,
+                    });
+            }
+            Modification::Redirect { address, keep } => {
+                if !address.is_empty() {
+                    outcome.redirects.push((address, keep));
                 }
             }
+            Modification::SetExpiry { seconds } => {
+                // The earliest expiry requested by any hook wins
+                outcome.expires_in = Some(
+                    outcome
+                        .expires_in
+                        .map_or(seconds, |expires_in| expires_in.min(seconds)),
+                );
+            }
         }
     }
 
-    // Check for failures - tempfail takes precedence over permfail for retry behavior
-    if should_tempfail {
-        return Err(
-            trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
-                .ctx(
-                    trc::Key::Reason,
-                    "Message temporarily rejected by delivery hook",
-                )
-                .ctx(trc::Key::Code, 451),
-        );
+    outcome.audit.push(audit);
+
+    match response.action {
+        HookAction::Accept => {
+            trc::event!(
+                DeliveryHook(trc::DeliveryHookEvent::ActionAccept),
+                AccountId = user_id,
+                Details = format!("Hook '{}' accepted", hook.id),
+                Elapsed = elapsed,
+            );
+        }
+        HookAction::Discard => {
+            trc::event!(
+                DeliveryHook(trc::DeliveryHookEvent::ActionDiscard),
+                AccountId = user_id,
+                Details = format!("Hook '{}' discarded", hook.id),
+                Elapsed = elapsed,
+            );
+            return Ok(HookVerdict::Discard);
+        }
+        HookAction::Quarantine => {
+            trc::event!(
+                DeliveryHook(trc::DeliveryHookEvent::ActionQuarantine),
+                AccountId = user_id,
+                Details = format!("Hook '{}' quarantined", hook.id),
+                Elapsed = elapsed,
+            );
+            outcome.quarantine = true;
+        }
+        HookAction::Reject => {
+            trc::event!(
+                DeliveryHook(trc::DeliveryHookEvent::ActionReject),
+                AccountId = user_id,
+                Details = format!("Hook '{}' rejected", hook.id),
+                Elapsed = elapsed,
+            );
+
+            // Check if this rejection should be a tempfail or permfail, asynchronous
+            // hooks run after delivery so retrying a rejection would not change anything
+            return Ok(if hook.tempfail_on_error && !hook.is_async {
+                HookVerdict::TempFail
+            } else {
+                HookVerdict::PermFail
+            });
+        }
     }
 
-    if should_permfail {
-        return Err(
-            trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
-                .ctx(trc::Key::Reason, "Message rejected by delivery hook")
-                .ctx(trc::Key::Code, 550),
-        );
+    Ok(if response.stop {
+        HookVerdict::Stop
+    } else {
+        HookVerdict::Continue
+    })
+}
+
+fn build_message(parsed_message: &mail_parser::Message<'_>) -> hooks::Message {
+    hooks::Message {
+        headers: parsed_message
+            .headers_raw()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        server_headers: vec![],
+        contents: String::from_utf8_lossy(&parsed_message.raw_message).into_owned(),
+        size: parsed_message.raw_message.len(),
     }
+}
+
+fn tempfail_error() -> trc::Error {
+    trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
+        .ctx(
+            trc::Key::Reason,
+            "Message temporarily rejected by delivery hook",
+        )
+        .ctx(trc::Key::Code, 451)
+}
 
-    Ok(Some((mailbox_ids, flags, skip_inbox, modifications_out, preview_text)))
+fn permfail_error() -> trc::Error {
+    trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
+        .ctx(trc::Key::Reason, "Message rejected by delivery hook")
+        .ctx(trc::Key::Code, 550)
 }
@@ -86,6 +86,7 @@ impl ArchivedMessageMetadata {
 
         batch
             .clear(EmailField::Metadata)
+            .clear(EmailField::HookAudit)
             .clear(ValueClass::IndexProperty(IndexPropertyClass::Hash {
                 property: EmailField::Threading.into(),
                 hash: CheekyHash::new(if !thread_name.is_empty() {
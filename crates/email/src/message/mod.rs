@@ -12,3 +12,4 @@ pub mod delivery_hooks;
 pub mod index;
 pub mod ingest;
 pub mod metadata;
+pub mod quarantine;
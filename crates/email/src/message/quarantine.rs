@@ -0,0 +1,395 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{
+    index::PREVIEW_LENGTH,
+    ingest::{EmailIngest, IngestEmail, IngestSource},
+};
+use crate::mailbox::INBOX_ID;
+use common::{KV_QUARANTINE, Server};
+use mail_parser::MessageParser;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use store::{
+    IterateParams, U32_LEN, U64_LEN, ValueKey,
+    write::{
+        BatchBuilder, InMemoryClass, Operation, ValueClass, ValueOp,
+        key::{DeserializeBigEndian, KeySerializer},
+        now,
+    },
+};
+use trc::{AddContext, QuarantineEvent};
+use types::blob_hash::BlobHash;
+
+/// A message held server-side instead of being delivered to the recipient's mailboxes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedMessage {
+    pub id: u64,
+    pub account_id: u32,
+    pub sender: String,
+    pub recipient: String,
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    pub preview: Option<String>,
+    pub size: usize,
+    pub received: u64,
+    pub expires: u64,
+    pub reason: QuarantineReason,
+    /// Whether the message was already listed in a digest sent to the recipient
+    #[serde(default)]
+    pub notified: bool,
+    #[serde(skip)]
+    pub blob_hash: BlobHash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuarantineReason {
+    /// Quarantined by a delivery hook, MTA hook, milter or Sieve script
+    Hook,
+    /// Classified as spam by the spam filter
+    Spam,
+}
+
+pub trait EmailQuarantine: Sync + Send {
+    fn quarantine_hold(
+        &self,
+        account_id: u32,
+        sender: &str,
+        recipient: &str,
+        raw_message: &[u8],
+        reason: QuarantineReason,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<u64>> + Send;
+
+    /// Lists the messages quarantined for an account, or for all accounts when `None`
+    fn quarantine_list(
+        &self,
+        account_id: Option<u32>,
+    ) -> impl Future<Output = trc::Result<Vec<QuarantinedMessage>>> + Send;
+
+    fn quarantine_get(
+        &self,
+        account_id: u32,
+        id: u64,
+    ) -> impl Future<Output = trc::Result<Option<QuarantinedMessage>>> + Send;
+
+    fn quarantine_fetch(
+        &self,
+        message: &QuarantinedMessage,
+    ) -> impl Future<Output = trc::Result<Vec<u8>>> + Send;
+
+    /// Delivers a quarantined message to the recipient's Inbox
+    fn quarantine_release(
+        &self,
+        account_id: u32,
+        id: u64,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn quarantine_delete(
+        &self,
+        account_id: u32,
+        id: u64,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn quarantine_set_notified(
+        &self,
+        messages: &[QuarantinedMessage],
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl EmailQuarantine for Server {
+    async fn quarantine_hold(
+        &self,
+        account_id: u32,
+        sender: &str,
+        recipient: &str,
+        raw_message: &[u8],
+        reason: QuarantineReason,
+        session_id: u64,
+    ) -> trc::Result<u64> {
+        let retention = self.core.smtp.quarantine.retention;
+        let (blob_hash, _) = self
+            .put_temporary_blob(account_id, raw_message, retention)
+            .await
+            .caused_by(trc::location!())?;
+        let message = MessageParser::new().parse(raw_message);
+        let received = now();
+        let item = QuarantinedMessage {
+            id: self.inner.data.queue_id_gen.generate(),
+            account_id,
+            sender: sender.to_string(),
+            recipient: recipient.to_string(),
+            from: message.as_ref().and_then(|message| {
+                message
+                    .from()
+                    .and_then(|from| from.first())
+                    .and_then(|from| from.address())
+                    .map(|from| from.to_string())
+            }),
+            subject: message
+                .as_ref()
+                .and_then(|message| message.subject())
+                .map(|subject| subject.to_string()),
+            preview: message
+                .as_ref()
+                .and_then(|message| message.body_preview(PREVIEW_LENGTH))
+                .map(|preview| preview.replace('\r', "")),
+            size: raw_message.len(),
+            received,
+            expires: received + retention,
+            reason,
+            notified: false,
+            blob_hash,
+        };
+
+        let mut batch = BatchBuilder::new();
+        batch.any_op(Operation::Value {
+            class: quarantine_key(account_id, item.id),
+            op: ValueOp::Set(item.serialize()),
+        });
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            Quarantine(QuarantineEvent::Held),
+            SpanId = session_id,
+            AccountId = account_id,
+            From = item.sender.clone(),
+            To = item.recipient.clone(),
+            Id = item.id,
+            Reason = match reason {
+                QuarantineReason::Hook => "hook",
+                QuarantineReason::Spam => "spam",
+            },
+        );
+
+        Ok(item.id)
+    }
+
+    async fn quarantine_list(
+        &self,
+        account_id: Option<u32>,
+    ) -> trc::Result<Vec<QuarantinedMessage>> {
+        let mut messages = Vec::new();
+        let current_time = now();
+        let (from_key, to_key) = match account_id {
+            Some(account_id) => (
+                quarantine_key(account_id, 0),
+                quarantine_key(account_id, u64::MAX),
+            ),
+            None => (quarantine_key(0, 0), quarantine_key(u32::MAX, u64::MAX)),
+        };
+
+        self.store()
+            .iterate(
+                IterateParams::new(ValueKey::from(from_key), ValueKey::from(to_key)).ascending(),
+                |key, value| {
+                    let account_id = key.deserialize_be_u32(key.len() - U64_LEN - U32_LEN)?;
+                    let id = key.deserialize_be_u64(key.len() - U64_LEN)?;
+                    let message = QuarantinedMessage::deserialize(account_id, id, value)?;
+                    if message.expires > current_time {
+                        messages.push(message);
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(messages)
+    }
+
+    async fn quarantine_get(
+        &self,
+        account_id: u32,
+        id: u64,
+    ) -> trc::Result<Option<QuarantinedMessage>> {
+        self.store()
+            .get_value::<QuarantineValue>(ValueKey::from(quarantine_key(account_id, id)))
+            .await
+            .caused_by(trc::location!())?
+            .map(|value| QuarantinedMessage::deserialize(account_id, id, &value.0))
+            .transpose()
+            .map(|message| message.filter(|message| message.expires > now()))
+    }
+
+    async fn quarantine_fetch(&self, message: &QuarantinedMessage) -> trc::Result<Vec<u8>> {
+        self.blob_store()
+            .get_blob(message.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| {
+                trc::EventType::Quarantine(QuarantineEvent::Error)
+                    .into_err()
+                    .details("Quarantined message blob not found")
+                    .ctx(trc::Key::Id, message.id)
+                    .caused_by(trc::location!())
+            })
+    }
+
+    async fn quarantine_release(&self, account_id: u32, id: u64) -> trc::Result<bool> {
+        let Some(message) = self.quarantine_get(account_id, id).await? else {
+            return Ok(false);
+        };
+        let raw_message = self.quarantine_fetch(&message).await?;
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+
+        self.email_ingest(IngestEmail {
+            raw_message: &raw_message,
+            blob_hash: Some(&message.blob_hash),
+            message: MessageParser::new().parse(&raw_message),
+            access_token: &access_token,
+            mailbox_ids: vec![INBOX_ID],
+            keywords: vec![],
+            received_at: message.received.into(),
+            source: IngestSource::Jmap {
+                train_classifier: false,
+            },
+            session_id: 0,
+            preview_text: None,
+        })
+        .await
+        .caused_by(trc::location!())?;
+
+        let mut batch = BatchBuilder::new();
+        batch.any_op(Operation::Value {
+            class: quarantine_key(account_id, id),
+            op: ValueOp::Clear,
+        });
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            Quarantine(QuarantineEvent::Released),
+            AccountId = account_id,
+            From = message.sender,
+            To = message.recipient,
+            Id = id,
+        );
+
+        Ok(true)
+    }
+
+    async fn quarantine_delete(&self, account_id: u32, id: u64) -> trc::Result<bool> {
+        let Some(message) = self.quarantine_get(account_id, id).await? else {
+            return Ok(false);
+        };
+
+        // The message blob is temporary and is purged once the retention period is over
+        let mut batch = BatchBuilder::new();
+        batch.any_op(Operation::Value {
+            class: quarantine_key(account_id, id),
+            op: ValueOp::Clear,
+        });
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            Quarantine(QuarantineEvent::Deleted),
+            AccountId = account_id,
+            From = message.sender,
+            To = message.recipient,
+            Id = id,
+        );
+
+        Ok(true)
+    }
+
+    async fn quarantine_set_notified(&self, messages: &[QuarantinedMessage]) -> trc::Result<()> {
+        let mut batch = BatchBuilder::new();
+        for message in messages {
+            batch.any_op(Operation::Value {
+                class: quarantine_key(message.account_id, message.id),
+                op: ValueOp::Set(
+                    QuarantinedMessage {
+                        notified: true,
+                        ..message.clone()
+                    }
+                    .serialize(),
+                ),
+            });
+            if batch.is_large_batch() {
+                self.store()
+                    .write(batch.build_all())
+                    .await
+                    .caused_by(trc::location!())?;
+                batch = BatchBuilder::new();
+            }
+        }
+        if !batch.is_empty() {
+            self.store()
+                .write(batch.build_all())
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn quarantine_key(account_id: u32, id: u64) -> ValueClass {
+    ValueClass::InMemory(InMemoryClass::Key(
+        KeySerializer::new(1 + U32_LEN + U64_LEN)
+            .write(KV_QUARANTINE)
+            .write(account_id)
+            .write(id)
+            .finalize(),
+    ))
+}
+
+// Quarantined messages use the same layout as in-memory keys stored in the data
+// store, so they are purged by the housekeeper once the retention period is over
+impl QuarantinedMessage {
+    fn serialize(&self) -> Vec<u8> {
+        let value = serde_json::to_vec(self).unwrap_or_default();
+        KeySerializer::new(U64_LEN + self.blob_hash.as_slice().len() + value.len())
+            .write(self.expires)
+            .write(self.blob_hash.as_slice())
+            .write(value.as_slice())
+            .finalize()
+    }
+
+    fn deserialize(account_id: u32, id: u64, bytes: &[u8]) -> trc::Result<Self> {
+        let hash_end = U64_LEN + types::blob_hash::BLOB_HASH_LEN;
+        bytes
+            .get(U64_LEN..hash_end)
+            .and_then(|hash| BlobHash::try_from_hash_slice(hash).ok())
+            .and_then(|blob_hash| {
+                serde_json::from_slice::<QuarantinedMessage>(bytes.get(hash_end..)?)
+                    .ok()
+                    .map(|message| QuarantinedMessage {
+                        id,
+                        account_id,
+                        blob_hash,
+                        ..message
+                    })
+            })
+            .ok_or_else(|| {
+                trc::StoreEvent::DataCorruption
+                    .caused_by(trc::location!())
+                    .ctx(trc::Key::Id, id)
+            })
+    }
+}
+
+struct QuarantineValue(Vec<u8>);
+
+impl store::Deserialize for QuarantineValue {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        Ok(QuarantineValue(bytes.to_vec()))
+    }
+}
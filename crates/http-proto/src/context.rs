@@ -58,6 +58,11 @@ impl ResolveVariable for HttpContext<'_> {
             V_TLS => self.session.is_tls.into(),
             V_PROTOCOL => if self.session.is_tls { "https" } else { "http" }.into(),
             V_LISTENER => self.session.instance.id.as_str().into(),
+            V_PEER_UID | V_PEER_GID | V_PEER_PID => self
+                .session
+                .peer
+                .map(|peer| peer.resolve_variable(variable))
+                .unwrap_or_default(),
             V_URL => self.req.uri().to_compact_string().into(),
             V_URL_PATH => self.req.uri().path().into(),
             V_METHOD => self.req.method().as_str().into(),
@@ -12,7 +12,7 @@ pub use form_urlencoded;
 
 use std::{net::IpAddr, sync::Arc};
 
-use common::listener::ServerInstance;
+use common::listener::{PeerCredentials, ServerInstance};
 use hyper::StatusCode;
 
 pub type HttpRequest = hyper::Request<hyper::body::Incoming>;
@@ -54,6 +54,7 @@ pub struct HttpSessionData {
     pub remote_ip: IpAddr,
     pub remote_port: u16,
     pub is_tls: bool,
+    pub peer: Option<PeerCredentials>,
     pub session_id: u64,
 }
 
@@ -60,8 +60,11 @@ impl Authenticator for Server {
                 })?
             } else if mechanism.eq_ignore_ascii_case("bearer") {
                 // Enforce anonymous rate limit
-                self.is_http_anonymous_request_allowed(&session.remote_ip)
-                    .await?;
+                self.is_http_anonymous_request_allowed(
+                    &session.remote_ip,
+                    session.peer.map(|peer| peer.uid),
+                )
+                .await?;
 
                 decode_bearer_token(token, allow_api_access).ok_or_else(|| {
                     trc::AuthEvent::Error
@@ -72,8 +75,11 @@ impl Authenticator for Server {
                 })?
             } else {
                 // Enforce anonymous rate limit
-                self.is_http_anonymous_request_allowed(&session.remote_ip)
-                    .await?;
+                self.is_http_anonymous_request_allowed(
+                    &session.remote_ip,
+                    session.peer.map(|peer| peer.uid),
+                )
+                .await?;
 
                 return Err(trc::AuthEvent::Error
                     .into_err()
@@ -90,7 +96,8 @@ impl Authenticator for Server {
                         session.session_id,
                         session.remote_ip,
                     )
-                    .with_api_access(allow_api_access),
+                    .with_api_access(allow_api_access)
+                    .with_peer_uid(session.peer.map(|peer| peer.uid)),
                 )
                 .await?;
 
@@ -111,8 +118,11 @@ impl Authenticator for Server {
                 .map(|in_flight| (in_flight, access_token))
         } else {
             // Enforce anonymous rate limit
-            self.is_http_anonymous_request_allowed(&session.remote_ip)
-                .await?;
+            self.is_http_anonymous_request_allowed(
+                &session.remote_ip,
+                session.peer.map(|peer| peer.uid),
+            )
+            .await?;
 
             Err(trc::AuthEvent::Failed
                 .into_err()
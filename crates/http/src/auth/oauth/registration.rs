@@ -52,8 +52,11 @@ impl ClientRegistrationHandler for Server {
             // Validate permissions
             access_token.assert_has_permission(Permission::OauthClientRegistration)?;
         } else {
-            self.is_http_anonymous_request_allowed(&session.remote_ip)
-                .await?;
+            self.is_http_anonymous_request_allowed(
+                &session.remote_ip,
+                session.peer.map(|peer| peer.uid),
+            )
+            .await?;
         }
 
         // Parse request
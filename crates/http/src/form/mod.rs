@@ -185,6 +185,7 @@ impl FormHandler for Server {
                         .map(|address| IngestRecipient {
                             address: address.clone(),
                             is_spam: false,
+                            is_quarantined: false,
                         })
                         .collect(),
                     message_blob,
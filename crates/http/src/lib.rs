@@ -7,7 +7,9 @@
 pub mod auth;
 pub mod autoconfig;
 pub mod form;
+pub mod list;
 pub mod management;
+pub mod quarantine;
 pub mod request;
 
 use std::sync::Arc;
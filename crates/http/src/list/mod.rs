@@ -0,0 +1,144 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use http_proto::*;
+use hyper::{Method, StatusCode};
+use smtp::lists::{ListCommand, MailingList};
+use std::{fmt::Write, future::Future};
+use utils::url_params::UrlParams;
+
+pub trait ListHandler: Sync + Send {
+    fn handle_list_request(
+        &self,
+        req: &HttpRequest,
+        action: &str,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ListHandler for Server {
+    // Links sent in confirmation messages and in the List-Unsubscribe header.
+    // GET only renders a form so that link scanners cannot act on the token,
+    // the change is applied on POST, which is also how RFC 8058 one-click
+    // unsubscribe requests are sent.
+    async fn handle_list_request(
+        &self,
+        req: &HttpRequest,
+        action: &str,
+    ) -> trc::Result<HttpResponse> {
+        let expected_command = match action {
+            "confirm" => ListCommand::Subscribe,
+            "unsubscribe" => ListCommand::Unsubscribe,
+            _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+        };
+        let params = UrlParams::new(req.uri().query());
+        let result = match params.get("t") {
+            Some(token) => self
+                .list_confirm(token)
+                .await
+                .ok()
+                .flatten()
+                .filter(|(_, command, _)| *command == expected_command),
+            None => None,
+        };
+        let Some((list, command, address)) = result else {
+            return Ok(HtmlResponse::with_status(
+                StatusCode::NOT_FOUND,
+                render_page("Invalid link", "This link is invalid or has expired.", None),
+            )
+            .into_http_response()
+            .with_no_store());
+        };
+        let is_subscribe = command == ListCommand::Subscribe;
+
+        let html = if req.method() == Method::POST {
+            if is_subscribe {
+                self.list_subscribe(&list, &address).await?;
+            } else {
+                self.list_unsubscribe(&list, &address).await?;
+            }
+
+            render_page(
+                if is_subscribe {
+                    "Subscribed"
+                } else {
+                    "Unsubscribed"
+                },
+                &format!(
+                    "{} has been {} the list {}.",
+                    escape_html(&address),
+                    if is_subscribe {
+                        "subscribed to"
+                    } else {
+                        "unsubscribed from"
+                    },
+                    escape_html(&list.address)
+                ),
+                None,
+            )
+        } else {
+            render_page(
+                if is_subscribe {
+                    "Confirm subscription"
+                } else {
+                    "Confirm unsubscription"
+                },
+                &format!(
+                    "Please confirm that you want to {} {} {} the list {}.",
+                    if is_subscribe {
+                        "subscribe"
+                    } else {
+                        "unsubscribe"
+                    },
+                    escape_html(&address),
+                    if is_subscribe { "to" } else { "from" },
+                    escape_html(&list.address)
+                ),
+                Some(if is_subscribe {
+                    "Subscribe"
+                } else {
+                    "Unsubscribe"
+                }),
+            )
+        };
+
+        Ok(HtmlResponse::new(html).into_http_response().with_no_store())
+    }
+}
+
+pub(crate) fn render_page(title: &str, message: &str, button: Option<&str>) -> String {
+    let mut html = format!(
+        concat!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\">",
+            "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">",
+            "<title>{}</title></head><body><h1>{}</h1><p>{}</p>"
+        ),
+        title, title, message
+    );
+    if let Some(button) = button {
+        let _ = write!(
+            html,
+            "<form method=\"post\"><button type=\"submit\">{button}</button></form>"
+        );
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,93 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::{
+    Permission,
+    backend::internal::manage::{self, ManageDirectory},
+};
+use http_proto::{request::decode_path_element, *};
+use hyper::Method;
+use serde_json::json;
+use smtp::reporting::complaints::Complaints;
+use std::future::Future;
+
+pub trait ManageComplaints: Sync + Send {
+    fn handle_manage_complaints(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageComplaints for Server {
+    async fn handle_manage_complaints(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        match (
+            path.get(2).copied(),
+            path.get(3).copied().map(decode_path_element),
+            req.method(),
+        ) {
+            (Some("account"), Some(name), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::IncomingReportGet)?;
+
+                let account_id = resolve_account(self, &name, access_token).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.complaint_stats_account(account_id).await?,
+                }))
+                .into_http_response())
+            }
+            (Some("account"), Some(name), &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::IncomingReportDelete)?;
+
+                let account_id = resolve_account(self, &name, access_token).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.sending_release(account_id).await?,
+                }))
+                .into_http_response())
+            }
+            (Some("domain"), Some(domain), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::IncomingReportGet)?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.complaint_stats_domain(&domain).await?,
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+pub(crate) async fn resolve_account(
+    server: &Server,
+    name: &str,
+    access_token: &AccessToken,
+) -> trc::Result<u32> {
+    let principal = server
+        .store()
+        .get_principal_info(name)
+        .await?
+        .ok_or_else(|| manage::not_found(name.to_string()))?;
+    if access_token.tenant.is_some() && principal.tenant != access_token.tenant_id() {
+        return Err(manage::error(
+            "Account does not belong to this tenant.",
+            None::<u64>,
+        ));
+    }
+
+    Ok(principal.id)
+}
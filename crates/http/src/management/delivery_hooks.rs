@@ -0,0 +1,241 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::{
+    Permission,
+    backend::internal::manage::{self, ManageDirectory},
+};
+use email::{
+    hooks::{self, IngestSource, audit::DeliveryHookAudit},
+    message::{
+        delivery_hooks::dry_run_delivery_hooks,
+        metadata::{MessageData, MessageMetadata},
+    },
+};
+use http_proto::{request::decode_path_element, *};
+use hyper::Method;
+use mail_parser::MessageParser;
+use serde::Deserialize;
+use serde_json::json;
+use std::{future::Future, str::FromStr};
+use store::{
+    ValueKey,
+    write::{AlignedBytes, Archive},
+};
+use trc::AddContext;
+use types::{blob_hash::BlobHash, collection::Collection, field::EmailField, id::Id};
+
+pub trait ManageDeliveryHooks: Sync + Send {
+    fn handle_manage_delivery_hooks(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryHookTestRequest {
+    /// Stored message to replay
+    #[serde(default)]
+    pub email_id: Option<String>,
+    /// Raw message to test, used when no email id is provided
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub source: IngestSource,
+    /// Hooks to call, defaults to the hooks enabled for the source
+    #[serde(default)]
+    pub hooks: Vec<String>,
+    #[serde(default)]
+    pub env_from: Option<String>,
+    #[serde(default)]
+    pub env_rcpt_to: Option<String>,
+}
+
+impl ManageDeliveryHooks for Server {
+    async fn handle_manage_delivery_hooks(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        match (
+            path.get(1).copied().filter(|a| !a.is_empty()),
+            path.get(2).copied(),
+            req.method(),
+        ) {
+            (Some(account), Some("test"), &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::Troubleshoot)?;
+
+                let account_id = self.resolve_hook_account(account, access_token).await?;
+                let request = serde_json::from_slice::<DeliveryHookTestRequest>(
+                    body.as_deref().unwrap_or_default(),
+                )
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+
+                // Obtain the message to test
+                let raw_message = match (&request.email_id, request.message) {
+                    (Some(email_id), _) => {
+                        let document_id = parse_email_id(email_id)?;
+                        let metadata = self
+                            .store()
+                            .get_value::<Archive<AlignedBytes>>(ValueKey::property(
+                                account_id,
+                                Collection::Email,
+                                document_id,
+                                EmailField::Metadata,
+                            ))
+                            .await?
+                            .ok_or_else(|| manage::not_found(email_id.to_string()))?;
+                        let blob_hash = BlobHash::from(
+                            &metadata
+                                .unarchive::<MessageMetadata>()
+                                .caused_by(trc::location!())?
+                                .blob_hash,
+                        );
+                        self.blob_store()
+                            .get_blob(blob_hash.as_slice(), 0..usize::MAX)
+                            .await?
+                            .ok_or_else(|| manage::not_found(email_id.to_string()))?
+                    }
+                    (None, Some(message)) => message.into_bytes(),
+                    (None, None) => {
+                        return Err(manage::error(
+                            "Either an email id or a message is required.",
+                            None::<u64>,
+                        ));
+                    }
+                };
+                let message = MessageParser::new()
+                    .parse(&raw_message)
+                    .ok_or_else(|| manage::error("Failed to parse message.", None::<u64>))?;
+
+                let envelope =
+                    (request.env_from.is_some() || request.env_rcpt_to.is_some()).then(|| {
+                        hooks::Envelope {
+                            from: hooks::Address {
+                                address: request.env_from.unwrap_or_default(),
+                            },
+                            to: hooks::Address {
+                                address: request.env_rcpt_to.unwrap_or_default(),
+                            },
+                        }
+                    });
+
+                let result = dry_run_delivery_hooks(
+                    self,
+                    account_id,
+                    request.source,
+                    envelope,
+                    &message,
+                    &request.hooks,
+                    session.session_id,
+                )
+                .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": result,
+                }))
+                .into_http_response())
+            }
+            (Some(account), Some(email_id), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::Troubleshoot)?;
+
+                let account_id = self.resolve_hook_account(account, access_token).await?;
+                let document_id = parse_email_id(email_id)?;
+
+                // Obtain the current mailboxes of the message
+                let data = self
+                    .store()
+                    .get_value::<Archive<AlignedBytes>>(ValueKey::archive(
+                        account_id,
+                        Collection::Email,
+                        document_id,
+                    ))
+                    .await?
+                    .ok_or_else(|| manage::not_found(email_id.to_string()))?;
+                let mailbox_ids = data
+                    .unarchive::<MessageData>()
+                    .caused_by(trc::location!())?
+                    .mailboxes
+                    .iter()
+                    .map(|m| Id::from(m.mailbox_id.to_native()).to_string())
+                    .collect::<Vec<_>>();
+
+                let audit = self
+                    .store()
+                    .get_value::<Archive<AlignedBytes>>(ValueKey::property(
+                        account_id,
+                        Collection::Email,
+                        document_id,
+                        EmailField::HookAudit,
+                    ))
+                    .await?
+                    .map(|archive| archive.deserialize::<DeliveryHookAudit>())
+                    .transpose()
+                    .caused_by(trc::location!())?
+                    .unwrap_or_default();
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "id": email_id,
+                        "mailboxIds": mailbox_ids,
+                        "runs": audit.runs,
+                    },
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+trait ResolveHookAccount {
+    fn resolve_hook_account(
+        &self,
+        account: &str,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<u32>> + Send;
+}
+
+impl ResolveHookAccount for Server {
+    async fn resolve_hook_account(
+        &self,
+        account: &str,
+        access_token: &AccessToken,
+    ) -> trc::Result<u32> {
+        let principal = self
+            .store()
+            .get_principal_info(decode_path_element(account).as_ref())
+            .await?
+            .ok_or_else(|| manage::not_found(account.to_string()))?;
+        if access_token.tenant.is_some() && principal.tenant != access_token.tenant_id() {
+            return Err(manage::error(
+                "Account does not belong to this tenant.",
+                None::<u64>,
+            ));
+        }
+
+        Ok(principal.id)
+    }
+}
+
+fn parse_email_id(email_id: &str) -> trc::Result<u32> {
+    Id::from_str(email_id)
+        .map(|id| id.document_id())
+        .map_err(|_| manage::error("Invalid email id.", Some(email_id.to_string())))
+}
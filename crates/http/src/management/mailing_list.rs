@@ -0,0 +1,170 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::{
+    Permission, Type,
+    backend::internal::manage::{self, ManageDirectory},
+};
+use http_proto::{request::decode_path_element, *};
+use hyper::Method;
+use serde::Deserialize;
+use serde_json::json;
+use smtp::lists::{ListInfo, MailingList, moderation::ListModeration};
+use std::future::Future;
+
+pub trait ManageMailingLists: Sync + Send {
+    fn handle_manage_mailing_list(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubscribeRequest {
+    pub address: String,
+}
+
+impl ManageMailingLists for Server {
+    async fn handle_manage_mailing_list(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let (Some(name), Some(section)) = (
+            path.get(1).copied().filter(|name| !name.is_empty()),
+            path.get(2).copied(),
+        ) else {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        };
+
+        match (section, path.get(3).copied(), req.method()) {
+            ("subscribers", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MailingListGet)?;
+
+                let list = self.resolve_mailing_list(name, access_token).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "address": list.address,
+                        "members": list.members,
+                    },
+                }))
+                .into_http_response())
+            }
+            ("subscribers", None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MailingListUpdate)?;
+
+                let list = self.resolve_mailing_list(name, access_token).await?;
+                let request =
+                    serde_json::from_slice::<SubscribeRequest>(body.as_deref().unwrap_or_default())
+                        .map_err(|err| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .from_json_error(err)
+                        })?;
+                let added = self
+                    .list_subscribe(&list, &request.address.trim().to_lowercase())
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": added,
+                }))
+                .into_http_response())
+            }
+            ("subscribers", Some(address), &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MailingListUpdate)?;
+
+                let list = self.resolve_mailing_list(name, access_token).await?;
+                let address = decode_path_element(address).to_lowercase();
+                if !self.list_unsubscribe(&list, &address).await? {
+                    return Err(manage::not_found(address));
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            ("moderation", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MailingListGet)?;
+
+                let list = self.resolve_mailing_list(name, access_token).await?;
+                let posts = self.list_held_posts(list.id).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "items": posts,
+                        "total": posts.len(),
+                    },
+                }))
+                .into_http_response())
+            }
+            ("moderation", Some(id), method @ (&Method::POST | &Method::DELETE)) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MailingListUpdate)?;
+
+                let list = self.resolve_mailing_list(name, access_token).await?;
+                let id = id
+                    .parse::<u64>()
+                    .map_err(|_| manage::error("Invalid message id.", Some(id.to_string())))?;
+                if !self
+                    .list_moderate(&list, id, method == Method::POST)
+                    .await?
+                {
+                    return Err(manage::not_found(id.to_string()));
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+trait ResolveMailingList {
+    fn resolve_mailing_list(
+        &self,
+        name: &str,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<ListInfo>> + Send;
+}
+
+impl ResolveMailingList for Server {
+    async fn resolve_mailing_list(
+        &self,
+        name: &str,
+        access_token: &AccessToken,
+    ) -> trc::Result<ListInfo> {
+        let principal = self
+            .store()
+            .get_principal_info(decode_path_element(name).as_ref())
+            .await?
+            .filter(|principal| principal.typ == Type::List)
+            .ok_or_else(|| manage::not_found(name.to_string()))?;
+        if access_token.tenant.is_some() && principal.tenant != access_token.tenant_id() {
+            return Err(manage::error(
+                "List does not belong to this tenant.",
+                None::<u64>,
+            ));
+        }
+
+        self.list_by_id(principal.id)
+            .await?
+            .ok_or_else(|| manage::not_found(name.to_string()))
+    }
+}
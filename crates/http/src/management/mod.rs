@@ -4,18 +4,25 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod complaints;
 pub mod crypto;
+pub mod delivery_hooks;
 pub mod dkim;
 pub mod dns;
 pub mod log;
+pub mod mailing_list;
 pub mod principal;
+pub mod quarantine;
 pub mod queue;
 pub mod reload;
 pub mod report;
+pub mod sending_limits;
 pub mod settings;
 pub mod spam;
 pub mod stores;
+pub mod suppression;
 pub mod troubleshoot;
+pub mod warmup;
 
 // SPDX-SnippetBegin
 // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
@@ -29,7 +36,9 @@ use enterprise::telemetry::TelemetryApi;
 
 use crate::auth::oauth::auth::OAuthApiHandler;
 use common::{Server, auth::AccessToken};
+use complaints::ManageComplaints;
 use crypto::CryptoHandler;
+use delivery_hooks::ManageDeliveryHooks;
 use directory::{Permission, backend::internal::manage};
 use dkim::DkimManagement;
 use dns::DnsManagement;
@@ -39,10 +48,13 @@ use jmap::api::{ToJmapHttpResponse, ToRequestError};
 use jmap_proto::error::request::RequestError;
 use log::LogManagement;
 use mail_parser::DateTime;
+use mailing_list::ManageMailingLists;
 use principal::PrincipalManager;
+use quarantine::ManageQuarantine;
 use queue::QueueManagement;
 use reload::ManageReload;
 use report::ManageReports;
+use sending_limits::ManageSendingLimits;
 use serde::Serialize;
 use settings::ManageSettings;
 use spam::ManageSpamHandler;
@@ -50,7 +62,9 @@ use std::future::Future;
 use std::{str::FromStr, sync::Arc};
 use store::write::now;
 use stores::ManageStore;
+use suppression::ManageSuppressions;
 use troubleshoot::TroubleshootApi;
+use warmup::ManageWarmup;
 
 #[derive(Serialize)]
 #[serde(tag = "error")]
@@ -97,11 +111,30 @@ impl ManagementApi for Server {
         let path = req.uri().path().split('/').skip(2).collect::<Vec<_>>();
 
         match path.first().copied().unwrap_or_default() {
+            "queue" if path.get(1).copied() == Some("suppressions") => {
+                self.handle_manage_suppressions(req, path, body, &access_token)
+                    .await
+            }
+            "queue" if path.get(1).copied() == Some("warmup") => {
+                self.handle_manage_warmup(req, path, &access_token).await
+            }
+            "queue" if path.get(1).copied() == Some("senders") => {
+                self.handle_manage_sending_limits(req, path, &access_token)
+                    .await
+            }
             "queue" => self.handle_manage_queue(req, path, &access_token).await,
+            "quarantine" => {
+                self.handle_manage_quarantine(req, path, &access_token)
+                    .await
+            }
             "settings" => {
                 self.handle_manage_settings(req, path, body, &access_token)
                     .await
             }
+            "reports" if path.get(1).copied() == Some("complaints") => {
+                self.handle_manage_complaints(req, path, &access_token)
+                    .await
+            }
             "reports" => self.handle_manage_reports(req, path, &access_token).await,
             "principal" => {
                 self.handle_manage_principal(req, path, body, &access_token)
@@ -125,6 +158,14 @@ impl ManagementApi for Server {
                 self.handle_manage_spam(req, path, body, session, &access_token)
                     .await
             }
+            "delivery-hooks" => {
+                self.handle_manage_delivery_hooks(req, path, body, session, &access_token)
+                    .await
+            }
+            "list" => {
+                self.handle_manage_mailing_list(req, path, body, &access_token)
+                    .await
+            }
             "restart" if req.method() == Method::GET => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::Restart)?;
@@ -575,7 +575,8 @@ impl PrincipalManager for Server {
                                 | PrincipalField::Lists
                                 | PrincipalField::Urls
                                 | PrincipalField::ExternalMembers
-                                | PrincipalField::Locale => (),
+                                | PrincipalField::Locale
+                                | PrincipalField::UndoSendDelay => (),
                                 PrincipalField::Picture => {
                                     invalidate_logo_cache |=
                                         matches!(typ, Type::Domain | Type::Tenant);
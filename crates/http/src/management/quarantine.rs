@@ -0,0 +1,133 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::complaints::resolve_account;
+use common::{Server, auth::AccessToken};
+use directory::{Permission, backend::internal::manage};
+use email::message::quarantine::EmailQuarantine;
+use http_proto::{request::decode_path_element, *};
+use hyper::{Method, StatusCode};
+use serde_json::json;
+use std::future::Future;
+use utils::url_params::UrlParams;
+
+pub trait ManageQuarantine: Sync + Send {
+    fn handle_manage_quarantine(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageQuarantine for Server {
+    async fn handle_manage_quarantine(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let account = path
+            .get(1)
+            .copied()
+            .filter(|name| !name.is_empty())
+            .map(decode_path_element);
+        let id = path
+            .get(2)
+            .map(|id| {
+                id.parse::<u64>()
+                    .map_err(|_| manage::error("Invalid message id.", Some(id.to_string())))
+            })
+            .transpose()?;
+
+        match (account, id, path.get(3).copied(), req.method()) {
+            (account, None, None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let account_id = match account {
+                    Some(name) => Some(resolve_account(self, &name, access_token).await?),
+                    None if access_token.tenant.is_some() => {
+                        return Err(manage::error("An account name is required.", None::<u64>));
+                    }
+                    None => None,
+                };
+                let params = UrlParams::new(req.uri().query());
+                let page = params.parse::<usize>("page").unwrap_or_default();
+                let limit = params.parse::<usize>("limit").unwrap_or_default();
+                let messages = self.quarantine_list(account_id).await?;
+                let total = messages.len();
+                let items = if limit > 0 {
+                    messages
+                        .into_iter()
+                        .skip(page.saturating_sub(1) * limit)
+                        .take(limit)
+                        .collect::<Vec<_>>()
+                } else {
+                    messages
+                };
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "items": items,
+                        "total": total,
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some(name), Some(id), section, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let account_id = resolve_account(self, &name, access_token).await?;
+                let message = self
+                    .quarantine_get(account_id, id)
+                    .await?
+                    .ok_or_else(|| manage::not_found(id.to_string()))?;
+
+                match section {
+                    None => Ok(JsonResponse::new(json!({
+                        "data": message,
+                    }))
+                    .into_http_response()),
+                    Some("raw") => Ok(HttpResponse::new(StatusCode::OK)
+                        .with_content_type("message/rfc822")
+                        .with_binary_body(self.quarantine_fetch(&message).await?)),
+                    Some(_) => Err(trc::ResourceEvent::NotFound.into_err()),
+                }
+            }
+            (Some(name), Some(id), None, &Method::PATCH | &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let account_id = resolve_account(self, &name, access_token).await?;
+                if !self.quarantine_release(account_id, id).await? {
+                    return Err(manage::not_found(id.to_string()));
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": true,
+                }))
+                .into_http_response())
+            }
+            (Some(name), Some(id), None, &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueDelete)?;
+
+                let account_id = resolve_account(self, &name, access_token).await?;
+                if !self.quarantine_delete(account_id, id).await? {
+                    return Err(manage::not_found(id.to_string()));
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": true,
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
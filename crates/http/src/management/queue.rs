@@ -24,8 +24,10 @@ use mail_parser::DateTime;
 use serde::{Deserializer, Serializer};
 use serde_json::json;
 use smtp::{
+    inbound::dlp::HeldMessages,
     queue::{
-        self, ArchivedMessage, ArchivedStatus, ErrorDetails, QueueId, Status, spool::SmtpSpool,
+        self, ArchivedMessage, ArchivedStatus, ErrorDetails, HELD_FOR_APPROVAL, QueueId, Status,
+        spool::SmtpSpool,
     },
     reporting::{dmarc::DmarcReporting, tls::TlsReporting},
 };
@@ -61,6 +63,10 @@ pub struct Message {
     pub env_id: Option<String>,
 
     pub blob_hash: String,
+
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub held: bool,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
@@ -320,6 +326,27 @@ impl QueueManagement for Server {
                     Err(trc::ResourceEvent::NotFound.into_err())
                 }
             }
+            ("held", Some(queue_id), &Method::PATCH) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let queue_id = queue_id.parse().unwrap_or_default();
+                if let Some(message_) = self.read_message_archive(queue_id).await? {
+                    let message = message_.unarchive::<queue::Message>()?;
+                    if message.is_tenant_domain(&tenant_domains) {
+                        let found = self.release_held(queue_id).await.unwrap_or_default();
+                        if found {
+                            let _ = self.inner.ipc.queue_tx.send(QueueEvent::Refresh).await;
+                        }
+
+                        return Ok(JsonResponse::new(json!({
+                                "data": found,
+                        }))
+                        .into_http_response());
+                    }
+                }
+                Err(trc::ResourceEvent::NotFound.into_err())
+            }
             ("messages", None, &Method::DELETE) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::MessageQueueDelete)?;
@@ -542,6 +569,39 @@ impl QueueManagement for Server {
                     Err(trc::ResourceEvent::NotFound.into_err())
                 }
             }
+            ("status", Some(action), &Method::GET) if action == "throttle" => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let items = self
+                    .inner
+                    .data
+                    .queue_throttle
+                    .limited()
+                    .into_iter()
+                    .map(|(destination, limits)| {
+                        json!({
+                            "destination": destination.name,
+                            "concurrency": limits.concurrency,
+                            "inFlight": destination.in_flight(),
+                            "ratePerMinute": limits.rate.map(|rate| rate * 60.0),
+                            "deferrals": limits.deferrals,
+                            "lastDeferral": DateTime::from_timestamp(
+                                (limits.last_deferral / 1000) as i64
+                            )
+                            .to_rfc3339(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "enabled": self.core.smtp.adaptive.enable,
+                            "items": items,
+                        },
+                }))
+                .into_http_response())
+            }
             ("status", None, &Method::GET) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::MessageQueueGet)?;
@@ -620,6 +680,7 @@ impl Message {
                 .collect(),
 
             blob_hash: URL_SAFE_NO_PAD.encode::<&[u8]>(message.blob_hash.0.as_slice()),
+            held: u64::from(message.flags) & HELD_FOR_APPROVAL != 0,
         }
     }
 }
@@ -648,6 +709,7 @@ async fn fetch_queued_messages(
     let page = params.parse::<usize>("page").unwrap_or_default();
     let limit = params.parse::<usize>("limit").unwrap_or_default();
     let values = params.has_key("values");
+    let held = params.has_key("held");
 
     let range_start = params.parse::<u64>("range-start").unwrap_or_default();
     let range_end = params.parse::<u64>("range-end").unwrap_or(u64::MAX);
@@ -665,7 +727,8 @@ async fn fetch_queued_messages(
         || to.is_some()
         || before.is_some()
         || after.is_some()
-        || queue.is_some();
+        || queue.is_some()
+        || held;
     let mut offset = page.saturating_sub(1) * limit;
     let mut total_returned = 0;
 
@@ -713,7 +776,8 @@ async fn fetch_queued_messages(
                             })
                             && queue
                                 .as_ref()
-                                .is_none_or(|q| message.recipients.iter().any(|r| &r.queue == q))));
+                                .is_none_or(|q| message.recipients.iter().any(|r| &r.queue == q))
+                            && (!held || u64::from(message.flags) & HELD_FOR_APPROVAL != 0)));
 
                 if matches {
                     if offset == 0 {
@@ -938,6 +1002,10 @@ fn is_zero(num: &i16) -> bool {
     *num == 0
 }
 
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 trait IsTenantDomain {
     fn is_tenant_domain(&self, tenant_domains: &Option<Vec<String>>) -> bool;
 }
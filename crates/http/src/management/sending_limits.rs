@@ -0,0 +1,58 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::complaints::resolve_account;
+use common::{Server, auth::AccessToken};
+use directory::Permission;
+use http_proto::{request::decode_path_element, *};
+use hyper::Method;
+use serde_json::json;
+use smtp::core::sending_limits::SendingLimits;
+use std::future::Future;
+
+pub trait ManageSendingLimits: Sync + Send {
+    fn handle_manage_sending_limits(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageSendingLimits for Server {
+    async fn handle_manage_sending_limits(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        match (path.get(2).copied().map(decode_path_element), req.method()) {
+            (Some(name), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let account_id = resolve_account(self, &name, access_token).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.sending_status(account_id).await?,
+                }))
+                .into_http_response())
+            }
+            (Some(name), &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let account_id = resolve_account(self, &name, access_token).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.sending_unlock(account_id).await?,
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::{Permission, backend::internal::manage};
+#[cfg(feature = "enterprise")]
+use directory::{Type, backend::internal::manage::ManageDirectory};
+use http_proto::{request::decode_path_element, *};
+use hyper::Method;
+use serde::Deserialize;
+use serde_json::json;
+use smtp::queue::suppression::{Suppression, SuppressionList, SuppressionReason};
+use std::{future::Future, time::Duration};
+use store::write::now;
+use utils::{config::utils::ParseValue, url_params::UrlParams};
+
+pub trait ManageSuppressions: Sync + Send {
+    fn handle_manage_suppressions(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuppressionRequest {
+    pub address: String,
+    /// Optional lifetime of the entry such as "30d", entries never expire by default
+    #[serde(default)]
+    pub expires: Option<String>,
+}
+
+impl ManageSuppressions for Server {
+    async fn handle_manage_suppressions(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let params = UrlParams::new(req.uri().query());
+        #[allow(unused_mut)]
+        let mut tenant_id = access_token.tenant_id();
+
+        // SPDX-SnippetBegin
+        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+        // SPDX-License-Identifier: LicenseRef-SEL
+        #[cfg(feature = "enterprise")]
+        if self.core.is_enterprise_edition()
+            && tenant_id.is_none()
+            && let Some(tenant_name) = params.get("tenant")
+        {
+            // System administrators can manage the suppression list of any tenant
+            tenant_id = self
+                .core
+                .storage
+                .data
+                .get_principal_info(tenant_name)
+                .await?
+                .filter(|p| p.typ == Type::Tenant)
+                .map(|p| p.id)
+                .ok_or_else(|| manage::not_found(tenant_name.to_string()))?
+                .into();
+        }
+        // SPDX-SnippetEnd
+
+        match (path.get(2).copied().map(decode_path_element), req.method()) {
+            (None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueList)?;
+
+                let filter = params.get("filter").map(|filter| filter.to_lowercase());
+                let page = params.parse::<usize>("page").unwrap_or_default();
+                let limit = params.parse::<usize>("limit").unwrap_or_default();
+
+                let entries = self
+                    .suppression_list(tenant_id)
+                    .await?
+                    .into_iter()
+                    .filter(|entry| {
+                        filter
+                            .as_ref()
+                            .is_none_or(|filter| entry.address.contains(filter.as_str()))
+                    })
+                    .collect::<Vec<_>>();
+                let total = entries.len();
+                let items = if limit > 0 {
+                    entries
+                        .into_iter()
+                        .skip(page.saturating_sub(1) * limit)
+                        .take(limit)
+                        .collect::<Vec<_>>()
+                } else {
+                    entries
+                };
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "items": items,
+                        "total": total,
+                    },
+                }))
+                .into_http_response())
+            }
+            (None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let request = serde_json::from_slice::<SuppressionRequest>(
+                    body.as_deref().unwrap_or_default(),
+                )
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+                let address = request.address.trim().to_lowercase();
+                if !address.contains('@') {
+                    return Err(manage::error("Invalid address.", Some(address)));
+                }
+                let created = now();
+                let expires = request
+                    .expires
+                    .map(|expires| {
+                        Duration::parse_value(&expires)
+                            .map(|expires| created + expires.as_secs())
+                            .map_err(|err| manage::error("Invalid expiration.", Some(err)))
+                    })
+                    .transpose()?;
+
+                self.suppression_add(
+                    tenant_id,
+                    Suppression {
+                        address,
+                        reason: SuppressionReason::Manual,
+                        status: None,
+                        diagnostic: None,
+                        created,
+                        expires,
+                    },
+                )
+                .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (Some(address), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let address = address.to_lowercase();
+                let entry = self
+                    .suppression_get(tenant_id, &address)
+                    .await?
+                    .ok_or_else(|| manage::not_found(address))?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": entry,
+                }))
+                .into_http_response())
+            }
+            (Some(address), &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueDelete)?;
+
+                let address = address.to_lowercase();
+                if !self.suppression_remove(tenant_id, &address).await? {
+                    return Err(manage::not_found(address));
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken, config::smtp::warmup::WarmupSchedule};
+use directory::{Permission, backend::internal::manage};
+use http_proto::{request::decode_path_element, *};
+use hyper::Method;
+use serde_json::json;
+use smtp::outbound::warmup::IpWarmup;
+use std::{future::Future, net::IpAddr};
+
+pub trait ManageWarmup: Sync + Send {
+    fn handle_manage_warmup(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageWarmup for Server {
+    async fn handle_manage_warmup(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        match (path.get(2).copied().map(decode_path_element), req.method()) {
+            (None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueList)?;
+
+                let mut items = Vec::new();
+                for (ip, schedule) in self.warmup_schedules() {
+                    items.push(self.warmup_status(ip, schedule).await?);
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "items": items,
+                        "total": items.len(),
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some(ip), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let (ip, schedule) = find_schedule(self, &ip)?;
+                let status = self.warmup_status(ip, schedule).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "status": status,
+                        "schedule": schedule.daily_limits,
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some(ip), &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let (ip, _) = find_schedule(self, &ip)?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.warmup_reset(ip).await?,
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+fn find_schedule<'x>(server: &'x Server, ip: &str) -> trc::Result<(IpAddr, &'x WarmupSchedule)> {
+    let addr = ip
+        .parse::<IpAddr>()
+        .map_err(|_| manage::error("Invalid IP address.", Some(ip.to_string())))?;
+    server
+        .warmup_schedules()
+        .into_iter()
+        .find(|(ip, _)| *ip == addr)
+        .ok_or_else(|| manage::not_found(ip.to_string()))
+}
@@ -0,0 +1,90 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::list::{escape_html, render_page};
+use common::{Server, auth::oauth::GrantType};
+use email::message::quarantine::EmailQuarantine;
+use http_proto::*;
+use hyper::{Method, StatusCode};
+use std::future::Future;
+use utils::url_params::UrlParams;
+
+pub trait QuarantineHandler: Sync + Send {
+    fn handle_quarantine_request(
+        &self,
+        req: &HttpRequest,
+        action: &str,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl QuarantineHandler for Server {
+    // Release links sent in quarantine digests. As with list confirmations,
+    // GET only renders a form so that link scanners cannot release messages.
+    async fn handle_quarantine_request(
+        &self,
+        req: &HttpRequest,
+        action: &str,
+    ) -> trc::Result<HttpResponse> {
+        if action != "release" {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+        let params = UrlParams::new(req.uri().query());
+        let token = match params.get("t") {
+            Some(token) => self
+                .validate_access_token(GrantType::QuarantineRelease.into(), token)
+                .await
+                .ok(),
+            None => None,
+        };
+        let message = match token
+            .and_then(|token| Some((token.account_id, token.client_id.parse::<u64>().ok()?)))
+        {
+            Some((account_id, id)) => self.quarantine_get(account_id, id).await?,
+            None => None,
+        };
+        let Some(message) = message else {
+            return Ok(HtmlResponse::with_status(
+                StatusCode::NOT_FOUND,
+                render_page(
+                    "Invalid link",
+                    "This link is invalid, has expired or the message was already released.",
+                    None,
+                ),
+            )
+            .into_http_response()
+            .with_no_store());
+        };
+
+        let html = if req.method() == Method::POST {
+            self.quarantine_release(message.account_id, message.id)
+                .await?;
+
+            render_page(
+                "Message released",
+                &format!(
+                    "The message from {} has been delivered to your Inbox.",
+                    escape_html(message.from.as_deref().unwrap_or(&message.sender))
+                ),
+                None,
+            )
+        } else {
+            render_page(
+                "Release message",
+                &format!(
+                    concat!(
+                        "Please confirm that you want to deliver the message from {} ",
+                        "with subject \"{}\" to your Inbox."
+                    ),
+                    escape_html(message.from.as_deref().unwrap_or(&message.sender)),
+                    escape_html(message.subject.as_deref().unwrap_or_default())
+                ),
+                Some("Release"),
+            )
+        };
+
+        Ok(HtmlResponse::new(html).into_http_response().with_no_store())
+    }
+}
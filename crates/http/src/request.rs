@@ -15,9 +15,11 @@ use crate::{
     },
     autoconfig::Autoconfig,
     form::FormHandler,
+    list::ListHandler,
     management::{
         ManagementApi, ToManageHttpResponse, UnauthorizedResponse, troubleshoot::TroubleshootApi,
     },
+    quarantine::QuarantineHandler,
 };
 use common::{
     Inner, KV_ACME, Server,
@@ -40,7 +42,10 @@ use hyper::{
     server::conn::http1,
     service::service_fn,
 };
-use hyper_util::rt::TokioIo;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
 use jmap::{
     api::{
         ToJmapHttpResponse, event_source::EventSourceHandler, request::RequestHandler,
@@ -187,7 +192,7 @@ impl ParseHttp for Server {
 
                         return self.handle_event_source(req, access_token).await;
                     }
-                    ("ws", &Method::GET) => {
+                    ("ws", &Method::GET | &Method::CONNECT) => {
                         // Authenticate request
                         let (_in_flight, access_token) =
                             self.authenticate_headers(&req, &session, false).await?;
@@ -274,15 +279,21 @@ impl ParseHttp for Server {
                 }
                 ("oauth-authorization-server", &Method::GET) => {
                     // Limit anonymous requests
-                    self.is_http_anonymous_request_allowed(&session.remote_ip)
-                        .await?;
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
 
                     return self.handle_oauth_metadata(req, session).await;
                 }
                 ("openid-configuration", &Method::GET) => {
                     // Limit anonymous requests
-                    self.is_http_anonymous_request_allowed(&session.remote_ip)
-                        .await?;
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
 
                     return self.handle_oidc_metadata(req, session).await;
                 }
@@ -303,8 +314,11 @@ impl ParseHttp for Server {
                 }
                 ("mta-sts.txt", &Method::GET) => {
                     // Limit anonymous requests
-                    self.is_http_anonymous_request_allowed(&session.remote_ip)
-                        .await?;
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
 
                     return if let Some(policy) = self.build_mta_sts_policy() {
                         Ok(Resource::new("text/plain", policy.to_string().into_bytes())
@@ -315,8 +329,11 @@ impl ParseHttp for Server {
                 }
                 ("mail-v1.xml", &Method::GET) => {
                     // Limit anonymous requests
-                    self.is_http_anonymous_request_allowed(&session.remote_ip)
-                        .await?;
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
 
                     return self.handle_autoconfig_request(&req).await;
                 }
@@ -325,8 +342,11 @@ impl ParseHttp for Server {
                         && path.next().unwrap_or_default() == "config-v1.1.xml"
                     {
                         // Limit anonymous requests
-                        self.is_http_anonymous_request_allowed(&session.remote_ip)
-                            .await?;
+                        self.is_http_anonymous_request_allowed(
+                            &session.remote_ip,
+                            session.peer.map(|peer| peer.uid),
+                        )
+                        .await?;
 
                         return self.handle_autoconfig_request(&req).await;
                     }
@@ -338,14 +358,20 @@ impl ParseHttp for Server {
             },
             "auth" => match (path.next().unwrap_or_default(), req.method()) {
                 ("device", &Method::POST) => {
-                    self.is_http_anonymous_request_allowed(&session.remote_ip)
-                        .await?;
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
 
                     return self.handle_device_auth(&mut req, session).await;
                 }
                 ("token", &Method::POST) => {
-                    self.is_http_anonymous_request_allowed(&session.remote_ip)
-                        .await?;
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
 
                     return self.handle_token_request(&mut req, session).await;
                 }
@@ -372,8 +398,11 @@ impl ParseHttp for Server {
                 }
                 ("jwks.json", &Method::GET) => {
                     // Limit anonymous requests
-                    self.is_http_anonymous_request_allowed(&session.remote_ip)
-                        .await?;
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
 
                     return Ok(self.core.oauth.oidc_jwks.clone().into_http_response());
                 }
@@ -469,16 +498,22 @@ impl ParseHttp for Server {
                     && path.next().unwrap_or_default() == "config-v1.1.xml"
                 {
                     // Limit anonymous requests
-                    self.is_http_anonymous_request_allowed(&session.remote_ip)
-                        .await?;
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
 
                     return self.handle_autoconfig_request(&req).await;
                 }
             }
             "calendar" => {
                 // Limit anonymous requests
-                self.is_http_anonymous_request_allowed(&session.remote_ip)
-                    .await?;
+                self.is_http_anonymous_request_allowed(
+                    &session.remote_ip,
+                    session.peer.map(|peer| peer.uid),
+                )
+                .await?;
 
                 if self.core.groupware.itip_http_rsvp_url.is_some()
                     && req.method() == Method::GET
@@ -504,6 +539,38 @@ impl ParseHttp for Server {
                         });
                 }
             }
+            "list" => {
+                if self.core.smtp.mailing_list.enable
+                    && matches!(*req.method(), Method::GET | Method::POST)
+                {
+                    // Limit anonymous requests
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
+
+                    return self
+                        .handle_list_request(&req, path.next().unwrap_or_default())
+                        .await;
+                }
+            }
+            "quarantine" => {
+                if self.core.smtp.quarantine.enable
+                    && matches!(*req.method(), Method::GET | Method::POST)
+                {
+                    // Limit anonymous requests
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
+
+                    return self
+                        .handle_quarantine_request(&req, path.next().unwrap_or_default())
+                        .await;
+                }
+            }
             "autodiscover" | "Autodiscover" => {
                 if req.method() == Method::POST
                     && path
@@ -512,8 +579,11 @@ impl ParseHttp for Server {
                         .eq_ignore_ascii_case("autodiscover.xml")
                 {
                     // Limit anonymous requests
-                    self.is_http_anonymous_request_allowed(&session.remote_ip)
-                        .await?;
+                    self.is_http_anonymous_request_allowed(
+                        &session.remote_ip,
+                        session.peer.map(|peer| peer.uid),
+                    )
+                    .await?;
 
                     return self
                         .handle_autodiscover_request(
@@ -524,8 +594,11 @@ impl ParseHttp for Server {
             }
             "robots.txt" => {
                 // Limit anonymous requests
-                self.is_http_anonymous_request_allowed(&session.remote_ip)
-                    .await?;
+                self.is_http_anonymous_request_allowed(
+                    &session.remote_ip,
+                    session.peer.map(|peer| peer.uid),
+                )
+                .await?;
 
                 return Ok(
                     Resource::new("text/plain", b"User-agent: *\nDisallow: /\n".to_vec())
@@ -534,8 +607,11 @@ impl ParseHttp for Server {
             }
             "healthz" => {
                 // Limit anonymous requests
-                self.is_http_anonymous_request_allowed(&session.remote_ip)
-                    .await?;
+                self.is_http_anonymous_request_allowed(
+                    &session.remote_ip,
+                    session.peer.map(|peer| peer.uid),
+                )
+                .await?;
 
                 match path.next().unwrap_or_default() {
                     "live" => {
@@ -590,6 +666,7 @@ impl ParseHttp for Server {
                         req.headers()
                             .get(header::HOST)
                             .and_then(|h| h.to_str().ok())
+                            .or_else(|| req.uri().host())
                             .map(|h| h.rsplit_once(':').map_or(h, |(h, _)| h))
                             .unwrap_or_default(),
                     )
@@ -615,8 +692,11 @@ impl ParseHttp for Server {
                 if let Some(form) = &self.core.network.contact_form {
                     match *req.method() {
                         Method::POST => {
-                            self.is_http_anonymous_request_allowed(&session.remote_ip)
-                                .await?;
+                            self.is_http_anonymous_request_allowed(
+                                &session.remote_ip,
+                                session.peer.map(|peer| peer.uid),
+                            )
+                            .await?;
 
                             let form_data =
                                 FormData::from_request(&mut req, form.max_size, session.session_id)
@@ -666,154 +746,185 @@ impl ParseHttp for Server {
 async fn handle_session<T: SessionStream>(inner: Arc<Inner>, session: SessionData<T>) {
     let _in_flight = session.in_flight;
     let is_tls = session.stream.is_tls();
+    let peer = session.stream.peer_credentials();
+
+    // HTTP/2 is negotiated using ALPN on TLS listeners, while plain text
+    // connections may use it with prior knowledge (h2c) if enabled.
+    let (is_http2, max_concurrent_streams) = {
+        let core = inner.shared_core.load();
+        (
+            match session.stream.alpn_protocol() {
+                Some(b"h2") => core.jmap.http2_enable,
+                None => core.jmap.http2_enable && core.jmap.http2_h2c,
+                _ => false,
+            },
+            core.jmap.http2_max_concurrent_streams,
+        )
+    };
 
-    if let Err(http_err) = http1::Builder::new()
-        .keep_alive(true)
-        .serve_connection(
-            TokioIo::new(session.stream),
-            service_fn(|req: hyper::Request<body::Incoming>| {
-                let instance = session.instance.clone();
-                let inner = inner.clone();
+    let io = TokioIo::new(session.stream);
+    let service = service_fn(|req: hyper::Request<body::Incoming>| {
+        let instance = session.instance.clone();
+        let inner = inner.clone();
 
-                async move {
-                    let server = inner.build_server();
+        async move {
+            let server = inner.build_server();
 
-                    // Obtain remote IP
-                    let remote_ip = if !server.core.jmap.http_use_forwarded {
-                        trc::event!(
-                            Http(trc::HttpEvent::RequestUrl),
-                            SpanId = session.session_id,
-                            Url = req.uri().to_string(),
-                        );
+            // Obtain remote IP
+            let remote_ip = if !server.core.jmap.http_use_forwarded {
+                trc::event!(
+                    Http(trc::HttpEvent::RequestUrl),
+                    SpanId = session.session_id,
+                    Url = req.uri().to_string(),
+                );
 
-                        session.remote_ip
-                    } else if let Some(forwarded_for) = req
-                        .headers()
-                        .get(header::FORWARDED)
-                        .and_then(|h| h.to_str().ok())
-                        .and_then(|h| {
-                            let h = h.to_ascii_lowercase();
-                            h.split_once("for=").and_then(|(_, rest)| {
-                                let mut start_ip = usize::MAX;
-                                let mut end_ip = usize::MAX;
-
-                                for (pos, ch) in rest.char_indices() {
-                                    match ch {
-                                        '0'..='9' | 'a'..='f' | ':' | '.' => {
-                                            if start_ip == usize::MAX {
-                                                start_ip = pos;
-                                            }
-                                            end_ip = pos;
-                                        }
-                                        '"' | '[' | ' ' if start_ip == usize::MAX => {}
-                                        _ => {
-                                            break;
-                                        }
+                session.remote_ip
+            } else if let Some(forwarded_for) = req
+                .headers()
+                .get(header::FORWARDED)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| {
+                    let h = h.to_ascii_lowercase();
+                    h.split_once("for=").and_then(|(_, rest)| {
+                        let mut start_ip = usize::MAX;
+                        let mut end_ip = usize::MAX;
+
+                        for (pos, ch) in rest.char_indices() {
+                            match ch {
+                                '0'..='9' | 'a'..='f' | ':' | '.' => {
+                                    if start_ip == usize::MAX {
+                                        start_ip = pos;
                                     }
+                                    end_ip = pos;
                                 }
-
-                                rest.get(start_ip..=end_ip)
-                                    .and_then(|h| h.parse::<IpAddr>().ok())
-                            })
-                        })
-                        .or_else(|| {
-                            req.headers()
-                                .get("X-Forwarded-For")
-                                .and_then(|h| h.to_str().ok())
-                                .map(|h| h.split_once(',').map_or(h, |(ip, _)| ip).trim())
-                                .and_then(|h| h.parse::<IpAddr>().ok())
-                        })
-                    {
-                        // Check if the forwarded IP has been blocked
-                        if server.is_ip_blocked(&forwarded_for) {
-                            trc::event!(
-                                Security(trc::SecurityEvent::IpBlocked),
-                                ListenerId = instance.id.clone(),
-                                RemoteIp = forwarded_for,
-                                SpanId = session.session_id,
-                            );
-
-                            return Ok::<_, hyper::Error>(
-                                JsonProblemResponse(StatusCode::FORBIDDEN)
-                                    .into_http_response()
-                                    .build(),
-                            );
-                        }
-
-                        trc::event!(
-                            Http(trc::HttpEvent::RequestUrl),
-                            SpanId = session.session_id,
-                            RemoteIp = forwarded_for,
-                            Url = req.uri().to_string(),
-                        );
-
-                        forwarded_for
-                    } else {
-                        trc::event!(
-                            Http(trc::HttpEvent::XForwardedMissing),
-                            SpanId = session.session_id,
-                        );
-                        session.remote_ip
-                    };
-
-                    // Parse HTTP request
-                    let response = match Box::pin(server.parse_http_request(
-                        req,
-                        HttpSessionData {
-                            instance,
-                            local_ip: session.local_ip,
-                            local_port: session.local_port,
-                            remote_ip,
-                            remote_port: session.remote_port,
-                            is_tls,
-                            session_id: session.session_id,
-                        },
-                    ))
-                    .await
-                    {
-                        Ok(response) => response,
-                        Err(err) => {
-                            let response = err.into_http_response();
-                            trc::error!(err.span_id(session.session_id));
-                            response
+                                '"' | '[' | ' ' if start_ip == usize::MAX => {}
+                                _ => {
+                                    break;
+                                }
+                            }
                         }
-                    };
 
+                        rest.get(start_ip..=end_ip)
+                            .and_then(|h| h.parse::<IpAddr>().ok())
+                    })
+                })
+                .or_else(|| {
+                    req.headers()
+                        .get("X-Forwarded-For")
+                        .and_then(|h| h.to_str().ok())
+                        .map(|h| h.split_once(',').map_or(h, |(ip, _)| ip).trim())
+                        .and_then(|h| h.parse::<IpAddr>().ok())
+                })
+                // The unspecified address identifies Unix domain socket peers
+                .filter(|ip| !ip.is_unspecified())
+            {
+                // Check if the forwarded IP has been blocked
+                if server.is_ip_blocked(&forwarded_for) {
                     trc::event!(
-                        Http(trc::HttpEvent::ResponseBody),
+                        Security(trc::SecurityEvent::IpBlocked),
+                        ListenerId = instance.id.clone(),
+                        RemoteIp = forwarded_for,
                         SpanId = session.session_id,
-                        Contents = match response.body() {
-                            HttpResponseBody::Text(value) =>
-                                trc::Value::String(value.as_str().into()),
-                            HttpResponseBody::Binary(_) =>
-                                trc::Value::String("[binary data]".into()),
-                            HttpResponseBody::Stream(_) => trc::Value::String("[stream]".into()),
-                            _ => trc::Value::None,
-                        },
-                        Code = response.status().as_u16(),
-                        Size = response.size(),
                     );
 
-                    // Build response
-                    let mut response = response.build();
+                    return Ok::<_, hyper::Error>(
+                        JsonProblemResponse(StatusCode::FORBIDDEN)
+                            .into_http_response()
+                            .build(),
+                    );
+                }
+
+                trc::event!(
+                    Http(trc::HttpEvent::RequestUrl),
+                    SpanId = session.session_id,
+                    RemoteIp = forwarded_for,
+                    Url = req.uri().to_string(),
+                );
 
-                    // Add custom headers
-                    if !server.core.jmap.http_headers.is_empty() {
-                        let headers = response.headers_mut();
+                forwarded_for
+            } else {
+                trc::event!(
+                    Http(trc::HttpEvent::XForwardedMissing),
+                    SpanId = session.session_id,
+                );
+                session.remote_ip
+            };
+
+            // Parse HTTP request
+            let response = match Box::pin(server.parse_http_request(
+                req,
+                HttpSessionData {
+                    instance,
+                    local_ip: session.local_ip,
+                    local_port: session.local_port,
+                    remote_ip,
+                    remote_port: session.remote_port,
+                    is_tls,
+                    peer,
+                    session_id: session.session_id,
+                },
+            ))
+            .await
+            {
+                Ok(response) => response,
+                Err(err) => {
+                    let response = err.into_http_response();
+                    trc::error!(err.span_id(session.session_id));
+                    response
+                }
+            };
 
-                        for (header, value) in &server.core.jmap.http_headers {
-                            headers.insert(header.clone(), value.clone());
-                        }
-                    }
+            trc::event!(
+                Http(trc::HttpEvent::ResponseBody),
+                SpanId = session.session_id,
+                Contents = match response.body() {
+                    HttpResponseBody::Text(value) => trc::Value::String(value.as_str().into()),
+                    HttpResponseBody::Binary(_) => trc::Value::String("[binary data]".into()),
+                    HttpResponseBody::Stream(_) => trc::Value::String("[stream]".into()),
+                    _ => trc::Value::None,
+                },
+                Code = response.status().as_u16(),
+                Size = response.size(),
+            );
 
-                    Ok::<_, hyper::Error>(response)
+            // Build response
+            let mut response = response.build();
+
+            // Add custom headers
+            if !server.core.jmap.http_headers.is_empty() {
+                let headers = response.headers_mut();
+
+                for (header, value) in &server.core.jmap.http_headers {
+                    headers.insert(header.clone(), value.clone());
                 }
-            }),
-        )
-        .with_upgrades()
-        .await
-    {
-        if http_err.is_parse() {
+            }
+
+            Ok::<_, hyper::Error>(response)
+        }
+    });
+
+    let result: Result<(), Box<dyn std::error::Error + Send + Sync>> = if is_http2 {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        builder.http1().keep_alive(true);
+        builder
+            .http2()
+            .max_concurrent_streams(max_concurrent_streams)
+            .enable_connect_protocol();
+        builder.serve_connection_with_upgrades(io, service).await
+    } else {
+        http1::Builder::new()
+            .keep_alive(true)
+            .serve_connection(io, service)
+            .with_upgrades()
+            .await
+            .map_err(Into::into)
+    };
+
+    if let Err(http_err) = result {
+        if http_err
+            .downcast_ref::<hyper::Error>()
+            .is_some_and(|err| err.is_parse())
+        {
             let server = inner.build_server();
             if !server.core.jmap.http_use_forwarded {
                 match server.is_scanner_fail2banned(session.remote_ip).await {
@@ -74,6 +74,16 @@ pub enum Command {
     // RFC 9208
     GetQuota,
     GetQuotaRoot,
+
+    // RFC 5465
+    Notify,
+
+    // RFC 5464
+    GetMetadata,
+    SetMetadata,
+
+    // RFC 4978
+    Compress,
 }
 
 impl Command {
@@ -152,6 +162,20 @@ pub enum ResponseCode {
 
     // USEATTR
     UseAttr,
+
+    // NOTIFY
+    BadEvent {
+        events: Vec<protocol::notify::Event>,
+    },
+    NotificationOverflow,
+
+    // METADATA
+    Metadata {
+        code: protocol::metadata::MetadataCode,
+    },
+
+    // COMPRESS
+    CompressionActive,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -0,0 +1,73 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use compact_str::ToCompactString;
+
+use crate::{
+    Command,
+    protocol::compress::{self, Algorithm},
+    receiver::{Request, bad},
+};
+
+impl Request<Command> {
+    pub fn parse_compress(self) -> trc::Result<compress::Arguments> {
+        if self.tokens.len() == 1 {
+            let algorithm = self.tokens.into_iter().next().unwrap().unwrap_bytes();
+            if algorithm.eq_ignore_ascii_case(b"DEFLATE") {
+                Ok(compress::Arguments {
+                    tag: self.tag,
+                    algorithm: Algorithm::Deflate,
+                })
+            } else {
+                Err(bad(
+                    self.tag.to_compact_string(),
+                    format!(
+                        "Unsupported compression algorithm '{}'.",
+                        String::from_utf8_lossy(&algorithm)
+                    ),
+                ))
+            }
+        } else {
+            Err(self.into_error("Expected a single compression algorithm."))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::compress::{self, Algorithm},
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_compress() {
+        let mut receiver = Receiver::new();
+
+        assert_eq!(
+            receiver
+                .parse(&mut "a1 COMPRESS deflate\r\n".as_bytes().iter())
+                .unwrap()
+                .parse_compress()
+                .unwrap(),
+            compress::Arguments {
+                tag: "a1".into(),
+                algorithm: Algorithm::Deflate,
+            }
+        );
+
+        for command in ["a2 COMPRESS gzip\r\n", "a3 COMPRESS\r\n"] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_compress()
+                    .is_err(),
+                "{command}"
+            );
+        }
+    }
+}
@@ -16,6 +16,8 @@ use crate::{
     utf7::utf7_maybe_decode,
 };
 
+use super::metadata::parse_list_metadata;
+
 impl Request<Command> {
     #[allow(clippy::while_let_on_iterator)]
     pub fn parse_list(self, is_utf8: bool) -> trc::Result<list::Arguments> {
@@ -153,6 +155,12 @@ impl Request<Command> {
                                         }
                                     }
                                 }
+                                if let ReturnOption::Metadata { entries, max_size } =
+                                    &mut return_option
+                                {
+                                    (*entries, *max_size) = parse_list_metadata(&mut tokens)
+                                        .map_err(|v| bad(self.tag.to_compact_string(), v))?;
+                                }
                                 return_options.push(return_option);
                             }
                             _ => {
@@ -202,6 +210,7 @@ impl ReturnOption {
             "CHILDREN" => Self::Children,
             "STATUS" => Self::Status(Vec::with_capacity(2)),
             "SPECIAL-USE" => Self::SpecialUse,
+            "METADATA" => Self::Metadata { entries: vec![], max_size: None },
         )
         .ok_or_else(|| format!("Invalid return option {:?}", String::from_utf8_lossy(value)).into())
     }
@@ -368,6 +377,22 @@ mod tests {
                     ],
                 },
             ),
+            (
+                concat!(
+                    "A03 LIST \"\" % RETURN (METADATA ",
+                    "(MAXSIZE 100 /shared/comment /private/Comment))\r\n"
+                ),
+                list::Arguments::Extended {
+                    tag: "A03".into(),
+                    reference_name: "".into(),
+                    mailbox_name: vec!["%".into()],
+                    selection_options: vec![],
+                    return_options: vec![ReturnOption::Metadata {
+                        entries: vec!["/shared/comment".into(), "/private/comment".into()],
+                        max_size: Some(100),
+                    }],
+                },
+            ),
         ] {
             assert_eq!(
                 receiver
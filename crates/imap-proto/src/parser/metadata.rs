@@ -0,0 +1,308 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use compact_str::ToCompactString;
+
+use crate::Command;
+use crate::protocol::metadata::{self, Depth};
+use crate::receiver::{Request, Token, bad};
+use crate::utf7::utf7_maybe_decode;
+
+use super::parse_number;
+
+impl Request<Command> {
+    #[allow(clippy::while_let_on_iterator)]
+    pub fn parse_get_metadata(self, is_utf8: bool) -> trc::Result<metadata::GetArguments> {
+        if self.tokens.len() < 2 {
+            return Err(self.into_error("Missing arguments."));
+        }
+
+        let mut tokens = self.tokens.into_iter();
+        let mut max_size = None;
+        let mut depth = Depth::Zero;
+
+        let mut token = tokens.next().unwrap();
+        if token.is_parenthesis_open() {
+            while let Some(token) = tokens.next() {
+                match token {
+                    Token::ParenthesisClose => break,
+                    Token::Argument(value) if value.eq_ignore_ascii_case(b"MAXSIZE") => {
+                        max_size = parse_number::<u32>(
+                            &tokens
+                                .next()
+                                .ok_or_else(|| {
+                                    bad(self.tag.to_compact_string(), "Missing MAXSIZE value.")
+                                })?
+                                .unwrap_bytes(),
+                        )
+                        .map_err(|v| bad(self.tag.to_compact_string(), v))?
+                        .into();
+                    }
+                    Token::Argument(value) if value.eq_ignore_ascii_case(b"DEPTH") => {
+                        let value = tokens.next().map(|token| token.unwrap_bytes());
+                        depth = value
+                            .and_then(|value| {
+                                hashify::tiny_map_ignore_case!(value.as_slice(),
+                                    "0" => Depth::Zero,
+                                    "1" => Depth::One,
+                                    "infinity" => Depth::Infinity,
+                                )
+                            })
+                            .ok_or_else(|| {
+                                bad(self.tag.to_compact_string(), "Invalid DEPTH value.")
+                            })?;
+                    }
+                    _ => {
+                        return Err(bad(
+                            self.tag.to_compact_string(),
+                            "Invalid GETMETADATA option.",
+                        ));
+                    }
+                }
+            }
+            token = tokens
+                .next()
+                .ok_or_else(|| bad(self.tag.to_compact_string(), "Missing mailbox name."))?;
+        }
+
+        let mailbox_name = utf7_maybe_decode(
+            token
+                .unwrap_string()
+                .map_err(|v| bad(self.tag.to_compact_string(), v))?,
+            is_utf8,
+        );
+
+        let mut entries = Vec::new();
+        match tokens.next() {
+            Some(Token::ParenthesisOpen) => {
+                while let Some(token) = tokens.next() {
+                    match token {
+                        Token::ParenthesisClose => break,
+                        token => entries.push(
+                            parse_entry(token).map_err(|v| bad(self.tag.to_compact_string(), v))?,
+                        ),
+                    }
+                }
+            }
+            Some(token) => {
+                entries.push(parse_entry(token).map_err(|v| bad(self.tag.to_compact_string(), v))?);
+            }
+            None => {}
+        }
+
+        if !entries.is_empty() {
+            Ok(metadata::GetArguments {
+                tag: self.tag,
+                mailbox_name,
+                entries,
+                max_size,
+                depth,
+            })
+        } else {
+            Err(bad(
+                self.tag.to_compact_string(),
+                "At least one entry name is required.",
+            ))
+        }
+    }
+
+    pub fn parse_set_metadata(self, is_utf8: bool) -> trc::Result<metadata::SetArguments> {
+        if self.tokens.len() < 4 {
+            return Err(self.into_error("Missing arguments."));
+        }
+
+        let mut tokens = self.tokens.into_iter();
+        let mailbox_name = utf7_maybe_decode(
+            tokens
+                .next()
+                .unwrap()
+                .unwrap_string()
+                .map_err(|v| bad(self.tag.to_compact_string(), v))?,
+            is_utf8,
+        );
+        if tokens
+            .next()
+            .is_none_or(|token| !token.is_parenthesis_open())
+        {
+            return Err(bad(
+                self.tag.to_compact_string(),
+                "Expected parenthesis after mailbox name.",
+            ));
+        }
+
+        let mut entries = Vec::new();
+        loop {
+            match tokens.next() {
+                Some(Token::ParenthesisClose) => break,
+                Some(token) => {
+                    let entry =
+                        parse_entry(token).map_err(|v| bad(self.tag.to_compact_string(), v))?;
+                    let value = match tokens.next() {
+                        Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"NIL") => None,
+                        Some(Token::Argument(value)) => Some(value),
+                        Some(Token::Nil) => Some(vec![]),
+                        _ => {
+                            return Err(bad(self.tag.to_compact_string(), "Expected entry value."));
+                        }
+                    };
+                    entries.push((entry, value));
+                }
+                None => {
+                    return Err(bad(
+                        self.tag.to_compact_string(),
+                        "Expected parenthesis after entry list.",
+                    ));
+                }
+            }
+        }
+
+        Ok(metadata::SetArguments {
+            tag: self.tag,
+            mailbox_name,
+            entries,
+        })
+    }
+}
+
+// Parses the METADATA option of LIST RETURN as defined in RFC 9590
+pub(crate) fn parse_list_metadata(
+    tokens: &mut impl Iterator<Item = Token>,
+) -> super::Result<(Vec<String>, Option<u32>)> {
+    if !tokens
+        .next()
+        .is_some_and(|token| token.is_parenthesis_open())
+    {
+        return Err("Expected parenthesis after METADATA.".into());
+    }
+
+    let mut entries = Vec::new();
+    let mut max_size = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::ParenthesisClose => break,
+            Token::Argument(value) if value.eq_ignore_ascii_case(b"MAXSIZE") => {
+                max_size = parse_number::<u32>(
+                    &tokens
+                        .next()
+                        .ok_or("Missing MAXSIZE value.")?
+                        .unwrap_bytes(),
+                )?
+                .into();
+            }
+            token => entries.push(parse_entry(token)?),
+        }
+    }
+
+    if !entries.is_empty() {
+        Ok((entries, max_size))
+    } else {
+        Err("At least one entry name is required.".into())
+    }
+}
+
+fn parse_entry(token: Token) -> super::Result<String> {
+    let entry = token.unwrap_string()?.to_ascii_lowercase();
+    if (entry.starts_with("/private/") || entry.starts_with("/shared/"))
+        && !entry.ends_with('/')
+        && !entry.contains("//")
+        && entry
+            .bytes()
+            .all(|ch| ch.is_ascii_graphic() && ch != b'*' && ch != b'%')
+    {
+        Ok(entry)
+    } else {
+        Err(format!("Invalid entry name '{entry}'.").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::metadata::{self, Depth},
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_get_metadata() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A01 GETMETADATA \"\" /private/vendor/vendor.dovecot/webmail-user\r\n",
+                metadata::GetArguments {
+                    tag: "A01".into(),
+                    mailbox_name: "".into(),
+                    entries: vec!["/private/vendor/vendor.dovecot/webmail-user".into()],
+                    max_size: None,
+                    depth: Depth::Zero,
+                },
+            ),
+            (
+                "A02 GETMETADATA (MAXSIZE 1024 DEPTH infinity) INBOX (/shared/Comment /private/comment)\r\n",
+                metadata::GetArguments {
+                    tag: "A02".into(),
+                    mailbox_name: "INBOX".into(),
+                    entries: vec!["/shared/comment".into(), "/private/comment".into()],
+                    max_size: Some(1024),
+                    depth: Depth::Infinity,
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_get_metadata(true)
+                    .unwrap(),
+                arguments,
+                "{command}"
+            );
+        }
+
+        for command in [
+            "A03 GETMETADATA INBOX /comment\r\n",
+            "A04 GETMETADATA INBOX /shared/comment/\r\n",
+            "A05 GETMETADATA INBOX /shared/*\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_get_metadata(true)
+                    .is_err(),
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_set_metadata() {
+        let mut receiver = Receiver::new();
+
+        assert_eq!(
+            receiver
+                .parse(
+                    &mut concat!(
+                        "A01 SETMETADATA INBOX (/private/comment {12+}\r\n",
+                        "My new\r\nnote /shared/comment NIL)\r\n"
+                    )
+                    .as_bytes()
+                    .iter()
+                )
+                .unwrap()
+                .parse_set_metadata(true)
+                .unwrap(),
+            metadata::SetArguments {
+                tag: "A01".into(),
+                mailbox_name: "INBOX".into(),
+                entries: vec![
+                    ("/private/comment".into(), Some(b"My new\r\nnote".to_vec())),
+                    ("/shared/comment".into(), None),
+                ],
+            }
+        );
+    }
+}
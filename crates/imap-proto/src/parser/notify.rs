@@ -0,0 +1,269 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use compact_str::ToCompactString;
+
+use crate::Command;
+use crate::protocol::notify::{self, Event, EventGroup, Filter};
+use crate::receiver::{Request, Token, bad};
+use crate::utf7::utf7_maybe_decode;
+
+impl Request<Command> {
+    pub fn parse_notify(self, is_utf8: bool) -> trc::Result<notify::Arguments> {
+        if self.tokens.is_empty() {
+            return Err(self.into_error("Missing arguments."));
+        }
+
+        let mut tokens = self.tokens.into_iter().peekable();
+        let token = tokens.next().unwrap();
+        if token.eq_ignore_ascii_case(b"NONE") {
+            return if tokens.next().is_none() {
+                Ok(notify::Arguments {
+                    tag: self.tag,
+                    status: false,
+                    groups: vec![],
+                })
+            } else {
+                Err(bad(self.tag.to_compact_string(), "Too many arguments."))
+            };
+        } else if !token.eq_ignore_ascii_case(b"SET") {
+            return Err(bad(self.tag.to_compact_string(), "Expected SET or NONE."));
+        }
+
+        let status = if tokens
+            .peek()
+            .is_some_and(|token| token.eq_ignore_ascii_case(b"STATUS"))
+        {
+            tokens.next();
+            true
+        } else {
+            false
+        };
+
+        let mut groups = Vec::new();
+        while let Some(token) = tokens.next() {
+            if token.is_parenthesis_open() {
+                groups.push(
+                    EventGroup::parse(&mut tokens, is_utf8)
+                        .map_err(|v| bad(self.tag.to_compact_string(), v))?,
+                );
+            } else {
+                return Err(bad(
+                    self.tag.to_compact_string(),
+                    "Expected parenthesis before event group.",
+                ));
+            }
+        }
+
+        if !groups.is_empty() {
+            Ok(notify::Arguments {
+                tag: self.tag,
+                status,
+                groups,
+            })
+        } else {
+            Err(bad(
+                self.tag.to_compact_string(),
+                "At least one event group is required.",
+            ))
+        }
+    }
+}
+
+impl EventGroup {
+    fn parse(tokens: &mut Peekable<IntoIter<Token>>, is_utf8: bool) -> super::Result<Self> {
+        let filter = match tokens.next() {
+            Some(Token::Argument(value)) => hashify::tiny_map_ignore_case!(value.as_slice(),
+                "SELECTED" => Filter::Selected,
+                "SELECTED-DELAYED" => Filter::SelectedDelayed,
+                "PERSONAL" => Filter::Personal,
+                "INBOXES" => Filter::Inboxes,
+                "SUBSCRIBED" => Filter::Subscribed,
+                "SUBTREE" => Filter::Subtree(vec![]),
+                "MAILBOXES" => Filter::Mailboxes(vec![]),
+            )
+            .ok_or_else(|| format!("Invalid filter '{}'.", String::from_utf8_lossy(&value)))?,
+            _ => return Err("Expected filter name.".into()),
+        };
+        let filter = match filter {
+            Filter::Subtree(_) => Filter::Subtree(parse_mailboxes(tokens, is_utf8)?),
+            Filter::Mailboxes(_) => Filter::Mailboxes(parse_mailboxes(tokens, is_utf8)?),
+            filter => filter,
+        };
+
+        let mut events = Vec::new();
+        match tokens.next() {
+            Some(Token::ParenthesisOpen) => loop {
+                match tokens.next() {
+                    Some(Token::ParenthesisClose) => break,
+                    Some(Token::Argument(value)) => {
+                        let event = Event::parse(&value)?;
+                        if event == Event::MessageNew
+                            && tokens
+                                .peek()
+                                .is_some_and(|token| token.is_parenthesis_open())
+                        {
+                            return Err("Fetch attributes for MessageNew are not supported.".into());
+                        }
+                        if !events.contains(&event) {
+                            events.push(event);
+                        }
+                    }
+                    _ => return Err("Invalid event list.".into()),
+                }
+            },
+            Some(token) if token.eq_ignore_ascii_case(b"NONE") => {}
+            _ => return Err("Expected event list or NONE.".into()),
+        }
+
+        if !tokens
+            .next()
+            .is_some_and(|token| token.is_parenthesis_close())
+        {
+            return Err("Expected parenthesis after event list.".into());
+        }
+
+        let has_new = events.contains(&Event::MessageNew);
+        if has_new != events.contains(&Event::MessageExpunge) {
+            return Err("MessageNew and MessageExpunge must be requested together.".into());
+        } else if !has_new && events.contains(&Event::FlagChange) {
+            return Err("FlagChange requires MessageNew and MessageExpunge.".into());
+        }
+
+        Ok(EventGroup { filter, events })
+    }
+}
+
+impl Event {
+    pub fn parse(value: &[u8]) -> super::Result<Self> {
+        hashify::tiny_map_ignore_case!(value,
+            "MessageNew" => Self::MessageNew,
+            "MessageExpunge" => Self::MessageExpunge,
+            "FlagChange" => Self::FlagChange,
+            "AnnotationChange" => Self::AnnotationChange,
+            "MailboxName" => Self::MailboxName,
+            "SubscriptionChange" => Self::SubscriptionChange,
+            "MailboxMetadataChange" => Self::MailboxMetadataChange,
+            "ServerMetadataChange" => Self::ServerMetadataChange
+        )
+        .ok_or_else(|| format!("Invalid event '{}'.", String::from_utf8_lossy(value)).into())
+    }
+}
+
+fn parse_mailboxes(
+    tokens: &mut Peekable<IntoIter<Token>>,
+    is_utf8: bool,
+) -> super::Result<Vec<String>> {
+    let mut mailboxes = Vec::new();
+    match tokens.next() {
+        Some(Token::ParenthesisOpen) => loop {
+            match tokens.next() {
+                Some(Token::ParenthesisClose) => break,
+                Some(token @ Token::Argument(_)) => {
+                    mailboxes.push(utf7_maybe_decode(token.unwrap_string()?, is_utf8));
+                }
+                _ => return Err("Invalid mailbox list.".into()),
+            }
+        },
+        Some(token @ Token::Argument(_)) => {
+            mailboxes.push(utf7_maybe_decode(token.unwrap_string()?, is_utf8));
+        }
+        _ => return Err("Expected mailbox name.".into()),
+    }
+
+    if !mailboxes.is_empty() {
+        Ok(mailboxes)
+    } else {
+        Err("At least one mailbox name is required.".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::notify::{self, Event, EventGroup, Filter},
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_notify() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A01 NOTIFY NONE\r\n",
+                notify::Arguments {
+                    tag: "A01".into(),
+                    status: false,
+                    groups: vec![],
+                },
+            ),
+            (
+                concat!(
+                    "A02 NOTIFY SET STATUS (selected (MessageNew MessageExpunge FlagChange)) ",
+                    "(subtree (Lists \"Other Lists\") (MessageNew MessageExpunge)) ",
+                    "(personal (MailboxName SubscriptionChange)) (mailboxes Drafts NONE)\r\n"
+                ),
+                notify::Arguments {
+                    tag: "A02".into(),
+                    status: true,
+                    groups: vec![
+                        EventGroup {
+                            filter: Filter::Selected,
+                            events: vec![
+                                Event::MessageNew,
+                                Event::MessageExpunge,
+                                Event::FlagChange,
+                            ],
+                        },
+                        EventGroup {
+                            filter: Filter::Subtree(vec!["Lists".into(), "Other Lists".into()]),
+                            events: vec![Event::MessageNew, Event::MessageExpunge],
+                        },
+                        EventGroup {
+                            filter: Filter::Personal,
+                            events: vec![Event::MailboxName, Event::SubscriptionChange],
+                        },
+                        EventGroup {
+                            filter: Filter::Mailboxes(vec!["Drafts".into()]),
+                            events: vec![],
+                        },
+                    ],
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_notify(true)
+                    .unwrap(),
+                arguments,
+                "{command}"
+            );
+        }
+
+        for command in [
+            "A03 NOTIFY SET (inboxes (MessageNew))\r\n",
+            "A04 NOTIFY SET (personal (FlagChange))\r\n",
+            "A05 NOTIFY SET (unknown (MailboxName))\r\n",
+            "A06 NOTIFY SET (selected (MessageNew (UID) MessageExpunge))\r\n",
+            "A07 NOTIFY SET\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_notify(true)
+                    .is_err(),
+                "{command}"
+            );
+        }
+    }
+}
@@ -53,6 +53,10 @@ pub enum Capability {
     QuotaResource(QuotaResourceName),
     QuotaSet,
     JmapAccess,
+    Notify,
+    Metadata,
+    ListMetadata,    //LIST-METADATA
+    CompressDeflate, //COMPRESS=DEFLATE
 }
 
 /*
@@ -128,6 +132,10 @@ impl Capability {
             }
             Capability::QuotaSet => b"QUOTA=SET",
             Capability::JmapAccess => b"JMAPACCESS",
+            Capability::Notify => b"NOTIFY",
+            Capability::Metadata => b"METADATA",
+            Capability::ListMetadata => b"LIST-METADATA",
+            Capability::CompressDeflate => b"COMPRESS=DEFLATE",
         });
     }
 
@@ -173,9 +181,14 @@ impl Capability {
                 Capability::Preview,
                 Capability::Quota,
                 Capability::QuotaResource(QuotaResourceName::Storage),
+                Capability::Notify,
+                Capability::Metadata,
+                Capability::ListMetadata,
+                Capability::CompressDeflate,
             ]);
         } else {
             capabilities.extend([
+                Capability::Auth(Mechanism::ScramSha256),
                 Capability::Auth(Mechanism::Plain),
                 Capability::Auth(Mechanism::OAuthBearer),
                 Capability::Auth(Mechanism::XOauth2),
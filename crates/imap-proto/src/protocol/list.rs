@@ -7,7 +7,9 @@
 use crate::utf7::utf7_encode;
 
 use super::{
-    ImapResponse, quoted_string,
+    ImapResponse,
+    metadata::MetadataItem,
+    quoted_string,
     status::{Status, StatusItem},
 };
 
@@ -34,6 +36,7 @@ pub struct Response {
     pub is_lsub: bool,
     pub list_items: Vec<ListItem>,
     pub status_items: Vec<StatusItem>,
+    pub metadata_items: Vec<MetadataItem>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -50,6 +53,10 @@ pub enum ReturnOption {
     Children,
     Status(Vec<Status>),
     SpecialUse,
+    Metadata {
+        entries: Vec<String>,
+        max_size: Option<u32>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -256,6 +263,18 @@ impl ListItem {
     }
 }
 
+impl Response {
+    fn serialize_metadata(&self, buf: &mut Vec<u8>, list_item: &ListItem) {
+        if let Some(metadata_item) = self
+            .metadata_items
+            .iter()
+            .find(|item| item.mailbox_name == list_item.mailbox_name)
+        {
+            metadata_item.serialize(buf, self.is_utf8);
+        }
+    }
+}
+
 impl ImapResponse for Response {
     fn serialize(self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(100);
@@ -266,11 +285,13 @@ impl ImapResponse for Response {
                 {
                     list_item.serialize(&mut buf, self.is_rev2, self.is_utf8, self.is_lsub);
                     status_item.serialize(&mut buf, self.is_rev2);
+                    self.serialize_metadata(&mut buf, list_item);
                 }
             }
             (false, true) => {
                 for list_item in &self.list_items {
                     list_item.serialize(&mut buf, self.is_rev2, self.is_utf8, self.is_lsub);
+                    self.serialize_metadata(&mut buf, list_item);
                 }
             }
             (true, false) => {
@@ -290,6 +311,7 @@ mod tests {
 
     use crate::protocol::{
         ImapResponse,
+        metadata::MetadataItem,
         status::{Status, StatusItem, StatusItemType},
     };
 
@@ -386,6 +408,10 @@ mod tests {
                     ],
                 },
             ],
+            metadata_items: vec![MetadataItem {
+                mailbox_name: "foo".into(),
+                entries: vec![("/shared/comment".into(), Some(b"Rock".to_vec()))],
+            }],
             is_lsub: false,
             is_rev2: true,
             is_utf8: true,
@@ -395,6 +421,7 @@ mod tests {
             "* STATUS \"INBOX\" (MESSAGES 17)\r\n",
             "* LIST () \"/\" \"foo\" (\"CHILDINFO\" (\"SUBSCRIBED\"))\r\n",
             "* STATUS \"foo\" (MESSAGES 30 UNSEEN 29)\r\n",
+            "* METADATA \"foo\" (/shared/comment \"Rock\")\r\n",
         );
         let expected_v1 = concat!(
             "* LSUB (\\Subscribed) \"/\" \"INBOX\"\r\n",
@@ -406,6 +433,7 @@ mod tests {
         response.is_utf8 = false;
         response.is_lsub = true;
         response.status_items.clear();
+        response.metadata_items.clear();
         let response_v1 = String::from_utf8(response.serialize()).unwrap();
 
         assert_eq!(response_v2, expected_v2);
@@ -0,0 +1,156 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::utf7::utf7_encode;
+
+use super::{literal_string, quoted_string};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetArguments {
+    pub tag: String,
+    pub mailbox_name: String,
+    pub entries: Vec<String>,
+    pub max_size: Option<u32>,
+    pub depth: Depth,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetArguments {
+    pub tag: String,
+    pub mailbox_name: String,
+    pub entries: Vec<(String, Option<Vec<u8>>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Depth {
+    #[default]
+    Zero,
+    One,
+    Infinity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataItem {
+    pub mailbox_name: String,
+    pub entries: Vec<(String, Option<Vec<u8>>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataCode {
+    LongEntries(u32),
+    MaxSize(u32),
+    TooMany,
+    NoPrivate,
+}
+
+impl Depth {
+    pub fn matches(&self, entry: &str, name: &str) -> bool {
+        match name.strip_prefix(entry) {
+            Some("") => true,
+            Some(child) if child.starts_with('/') => match self {
+                Depth::Zero => false,
+                Depth::One => !child[1..].contains('/'),
+                Depth::Infinity => true,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl MetadataItem {
+    pub fn serialize(&self, buf: &mut Vec<u8>, is_utf8: bool) {
+        buf.extend_from_slice(b"* METADATA ");
+        if is_utf8 {
+            quoted_string(buf, &self.mailbox_name);
+        } else {
+            quoted_string(buf, &utf7_encode(&self.mailbox_name));
+        }
+        buf.extend_from_slice(b" (");
+        for (pos, (name, value)) in self.entries.iter().enumerate() {
+            if pos > 0 {
+                buf.push(b' ');
+            }
+            buf.extend_from_slice(name.as_bytes());
+            buf.push(b' ');
+            match value {
+                Some(value)
+                    if value.iter().any(|ch| {
+                        [b'\\', b'"', b'\r', b'\n', 0].contains(ch) || !ch.is_ascii()
+                    }) =>
+                {
+                    literal_string(buf, value);
+                }
+                Some(value) => {
+                    buf.push(b'"');
+                    buf.extend_from_slice(value);
+                    buf.push(b'"');
+                }
+                None => buf.extend_from_slice(b"NIL"),
+            }
+        }
+        buf.extend_from_slice(b")\r\n");
+    }
+}
+
+impl MetadataCode {
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(b"METADATA ");
+        match self {
+            MetadataCode::LongEntries(size) => {
+                buf.extend_from_slice(b"LONGENTRIES ");
+                buf.extend_from_slice(size.to_string().as_bytes());
+            }
+            MetadataCode::MaxSize(size) => {
+                buf.extend_from_slice(b"MAXSIZE ");
+                buf.extend_from_slice(size.to_string().as_bytes());
+            }
+            MetadataCode::TooMany => buf.extend_from_slice(b"TOOMANY"),
+            MetadataCode::NoPrivate => buf.extend_from_slice(b"NOPRIVATE"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Depth, MetadataItem};
+
+    #[test]
+    fn serialize_metadata() {
+        let mut buf = Vec::new();
+        MetadataItem {
+            mailbox_name: "INBOX".into(),
+            entries: vec![
+                ("/private/comment".into(), Some(b"My comment".to_vec())),
+                ("/shared/comment".into(), Some(b"Line 1\r\nLine 2".to_vec())),
+                ("/shared/vendor/acme/color".into(), None),
+            ],
+        }
+        .serialize(&mut buf, true);
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            concat!(
+                "* METADATA \"INBOX\" (/private/comment \"My comment\" ",
+                "/shared/comment {14}\r\nLine 1\r\nLine 2 ",
+                "/shared/vendor/acme/color NIL)\r\n"
+            )
+        );
+    }
+
+    #[test]
+    fn depth_matches() {
+        for (depth, name, expected) in [
+            (Depth::Zero, "/shared/comment", true),
+            (Depth::Zero, "/shared/comment/a", false),
+            (Depth::One, "/shared/comment/a", true),
+            (Depth::One, "/shared/comment/a/b", false),
+            (Depth::Infinity, "/shared/comment/a/b", true),
+            (Depth::Infinity, "/shared/commentary", false),
+        ] {
+            assert_eq!(depth.matches("/shared/comment", name), expected, "{name}");
+        }
+    }
+}
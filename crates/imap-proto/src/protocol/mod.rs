@@ -16,6 +16,7 @@ pub mod acl;
 pub mod append;
 pub mod authenticate;
 pub mod capability;
+pub mod compress;
 pub mod copy_move;
 pub mod create;
 pub mod delete;
@@ -24,7 +25,9 @@ pub mod expunge;
 pub mod fetch;
 pub mod list;
 pub mod login;
+pub mod metadata;
 pub mod namespace;
+pub mod notify;
 pub mod quota;
 pub mod rename;
 pub mod search;
@@ -492,6 +495,23 @@ impl ResponseCode {
                 return;
             }
             ResponseCode::UseAttr => b"USEATTR",
+            ResponseCode::BadEvent { events } => {
+                buf.extend_from_slice(b"BADEVENT (");
+                for (pos, event) in events.iter().enumerate() {
+                    if pos > 0 {
+                        buf.push(b' ');
+                    }
+                    event.serialize(buf);
+                }
+                buf.push(b')');
+                return;
+            }
+            ResponseCode::NotificationOverflow => b"NOTIFICATIONOVERFLOW",
+            ResponseCode::Metadata { code } => {
+                code.serialize(buf);
+                return;
+            }
+            ResponseCode::CompressionActive => b"COMPRESSIONACTIVE",
         });
     }
 
@@ -535,6 +555,10 @@ impl ResponseCode {
             ResponseCode::MailboxId { .. } => "MAILBOXID",
             ResponseCode::HighestModseq { .. } => "HIGHESTMODSEQ",
             ResponseCode::UseAttr => "USEATTR",
+            ResponseCode::BadEvent { .. } => "BADEVENT",
+            ResponseCode::NotificationOverflow => "NOTIFICATIONOVERFLOW",
+            ResponseCode::Metadata { .. } => "METADATA",
+            ResponseCode::CompressionActive => "COMPRESSIONACTIVE",
         }
     }
 }
@@ -726,6 +750,10 @@ impl Display for Command {
             Command::Id => write!(f, "ID"),
             Command::GetQuota => write!(f, "GETQUOTA"),
             Command::GetQuotaRoot => write!(f, "GETQUOTAROOT"),
+            Command::Notify => write!(f, "NOTIFY"),
+            Command::GetMetadata => write!(f, "GETMETADATA"),
+            Command::SetMetadata => write!(f, "SETMETADATA"),
+            Command::Compress => write!(f, "COMPRESS"),
         }
     }
 }
@@ -247,6 +247,19 @@ impl<T: SessionStream> Session<T> {
                     .handle_id(request)
                     .await
                     .map(|_| SessionResult::Continue),
+                Command::Notify => self
+                    .handle_notify(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::GetMetadata => self
+                    .handle_get_metadata(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::SetMetadata => self
+                    .handle_set_metadata(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::Compress => self.handle_compress(request).await,
             };
 
             match result {
@@ -315,7 +328,12 @@ impl<T: SessionStream> Session<T> {
         match &request.command {
             Command::Capability | Command::Noop | Command::Logout | Command::Id => Ok(request),
             Command::StartTls => {
-                if !self.is_tls {
+                if self.is_compressed {
+                    Err(trc::ImapEvent::Error
+                        .into_err()
+                        .details("TLS cannot be started after compression.")
+                        .id(request.tag))
+                } else if !self.is_tls {
                     if self.instance.acceptor.is_tls() {
                         Ok(request)
                     } else {
@@ -379,7 +397,11 @@ impl<T: SessionStream> Session<T> {
             | Command::MyRights
             | Command::Unauthenticate
             | Command::GetQuota
-            | Command::GetQuotaRoot => {
+            | Command::GetQuotaRoot
+            | Command::Notify
+            | Command::GetMetadata
+            | Command::SetMetadata
+            | Command::Compress => {
                 if let State::Authenticated { .. } | State::Selected { .. } = state {
                     Ok(request)
                 } else {
@@ -318,23 +318,31 @@ impl<T: SessionStream> SessionData<T> {
                         let old_account = &mailboxes[pos];
                         let new_account = &changed_account;
 
-                        // Add new mailboxes
+                        // Add new and renamed mailboxes
                         for (mailbox_name, mailbox_id) in new_account.mailbox_names.iter() {
-                            if let Some(old_mailbox) = old_account.mailbox_state.get(mailbox_id) {
-                                if let Some(mailbox) = new_account.mailbox_state.get(mailbox_id)
-                                    && (mailbox.total_messages != old_mailbox.total_messages
-                                        || mailbox.total_unseen != old_mailbox.total_unseen)
-                                {
-                                    changes.changed.push(mailbox_name.clone());
+                            if let Some(old_mailbox) =
+                                old_account.mailbox_state.get(mailbox_id).filter(|_| {
+                                    old_account.mailbox_names.get(mailbox_name) == Some(mailbox_id)
+                                })
+                            {
+                                if let Some(mailbox) = new_account.mailbox_state.get(mailbox_id) {
+                                    if mailbox.total_messages != old_mailbox.total_messages
+                                        || mailbox.total_unseen != old_mailbox.total_unseen
+                                    {
+                                        changes.changed.push(mailbox_name.clone());
+                                    }
+                                    if mailbox.is_subscribed != old_mailbox.is_subscribed {
+                                        changes.subscribed.push(mailbox_name.clone());
+                                    }
                                 }
                             } else {
                                 changes.added.push(mailbox_name.clone());
                             }
                         }
 
-                        // Add deleted mailboxes
+                        // Add deleted and renamed mailboxes
                         for (mailbox_name, mailbox_id) in &old_account.mailbox_names {
-                            if !new_account.mailbox_state.contains_key(mailbox_id) {
+                            if new_account.mailbox_names.get(mailbox_name) != Some(mailbox_id) {
                                 changes.deleted.push(mailbox_name.clone());
                             }
                         }
@@ -13,18 +13,19 @@ use std::{
 use ahash::AHashMap;
 use common::{
     Inner, Server,
-    auth::AccessToken,
-    listener::{ServerInstance, SessionStream, limiter::InFlight},
+    auth::{AccessToken, scram::ScramStep},
+    ipc::PushNotification,
+    listener::{ServerInstance, SessionStream, TlsChannelBinding, limiter::InFlight},
 };
 
 use imap_proto::{
     Command,
-    protocol::{ProtocolVersion, list::Attribute},
+    protocol::{ProtocolVersion, list::Attribute, notify::EventGroup},
     receiver::Receiver,
 };
 use tokio::{
     io::{ReadHalf, WriteHalf},
-    sync::watch,
+    sync::{mpsc, watch},
 };
 use trc::AddContext;
 
@@ -51,6 +52,7 @@ pub struct Session<T: SessionStream> {
     pub version: ProtocolVersion,
     pub state: State<T>,
     pub is_tls: bool,
+    pub is_compressed: bool,
     pub is_condstore: bool,
     pub is_qresync: bool,
     pub is_utf8: bool,
@@ -58,7 +60,16 @@ pub struct Session<T: SessionStream> {
     pub stream_tx: Arc<tokio::sync::Mutex<WriteHalf<T>>>,
     pub in_flight: InFlight,
     pub remote_addr: IpAddr,
+    pub peer_uid: Option<u32>,
     pub session_id: u64,
+    pub channel_binding: Option<TlsChannelBinding>,
+    pub scram: Option<ScramStep>,
+    pub notify: Option<NotifyState>,
+}
+
+pub struct NotifyState {
+    pub groups: Vec<EventGroup>,
+    pub push_rx: mpsc::Receiver<PushNotification>,
 }
 
 pub struct SessionData<T: SessionStream> {
@@ -142,6 +153,7 @@ pub struct MailboxSync {
     pub added: Vec<String>,
     pub changed: Vec<String>,
     pub deleted: Vec<String>,
+    pub subscribed: Vec<String>,
 }
 
 pub enum SavedSearch {
@@ -8,7 +8,10 @@ use std::sync::Arc;
 
 use common::{
     core::BuildServer,
-    listener::{SessionData, SessionManager, SessionResult, SessionStream, stream::NullIo},
+    listener::{
+        SessionData, SessionManager, SessionResult, SessionStream,
+        stream::{DeflateStream, NullIo},
+    },
 };
 use imap_proto::{
     protocol::{ProtocolVersion, SerializeResponse},
@@ -17,7 +20,7 @@ use imap_proto::{
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio_rustls::server::TlsStream;
 
-use crate::{GREETING_WITH_TLS, GREETING_WITHOUT_TLS};
+use crate::{GREETING_WITH_TLS, GREETING_WITHOUT_TLS, op::notify::next_notification};
 
 use super::{ImapSessionManager, Session, State};
 
@@ -28,12 +31,23 @@ impl SessionManager for ImapSessionManager {
         session: SessionData<T>,
     ) -> impl std::future::Future<Output = ()> + Send {
         async move {
-            if let Ok(mut session) = Session::new(session, self).await
-                && session.handle_conn().await
-                && session.instance.acceptor.is_tls()
-                && let Ok(mut session) = session.into_tls().await
-            {
-                session.handle_conn().await;
+            if let Ok(mut session) = Session::new(session, self).await {
+                match session.handle_conn().await {
+                    SessionResult::UpgradeTls if session.instance.acceptor.is_tls() => {
+                        if let Ok(mut session) = session.into_tls().await
+                            && session.handle_conn().await == SessionResult::UpgradeCompression
+                            && let Ok(mut session) = session.into_compressed().await
+                        {
+                            session.handle_conn().await;
+                        }
+                    }
+                    SessionResult::UpgradeCompression => {
+                        if let Ok(mut session) = session.into_compressed().await {
+                            session.handle_conn().await;
+                        }
+                    }
+                    _ => (),
+                }
             }
         }
     }
@@ -45,7 +59,7 @@ impl SessionManager for ImapSessionManager {
 }
 
 impl<T: SessionStream> Session<T> {
-    pub async fn handle_conn(&mut self) -> bool {
+    pub async fn handle_conn(&mut self) -> SessionResult {
         let mut buf = vec![0; 8192];
         let mut shutdown_rx = self.instance.shutdown_rx.clone();
 
@@ -63,8 +77,9 @@ impl<T: SessionStream> Session<T> {
                             if bytes_read > 0 {
                                 match self.ingest(&buf[..bytes_read]).await {
                                     SessionResult::Continue => (),
-                                    SessionResult::UpgradeTls => {
-                                        return true;
+                                    result @ (SessionResult::UpgradeTls
+                                    | SessionResult::UpgradeCompression) => {
+                                        return result;
                                     }
                                     SessionResult::Close => {
                                         break;
@@ -99,6 +114,13 @@ impl<T: SessionStream> Session<T> {
                         }
                     }
                 },
+                push_notification = next_notification(&mut self.notify) => {
+                    if let Err(err) = self.write_notifications(push_notification).await
+                        && !self.write_error(err).await
+                    {
+                        break;
+                    }
+                },
                 _ = shutdown_rx.changed() => {
                     trc::event!(
                         Network(trc::NetworkEvent::Closed),
@@ -112,7 +134,7 @@ impl<T: SessionStream> Session<T> {
             };
         }
 
-        false
+        SessionResult::Close
     }
 
     pub async fn new(
@@ -139,6 +161,8 @@ impl<T: SessionStream> Session<T> {
         let _ = session.stream.flush().await;
 
         // Split stream into read and write halves
+        let channel_binding = session.stream.tls_channel_binding();
+        let peer_uid = session.stream.peer_credentials().map(|peer| peer.uid);
         let (stream_rx, stream_tx) = tokio::io::split(session.stream);
         let server = manager.inner.build_server();
 
@@ -147,6 +171,7 @@ impl<T: SessionStream> Session<T> {
             version: ProtocolVersion::Rev1,
             state: State::NotAuthenticated { auth_failures: 0 },
             is_tls,
+            is_compressed: false,
             is_condstore: false,
             is_qresync: false,
             is_utf8: false,
@@ -155,12 +180,36 @@ impl<T: SessionStream> Session<T> {
             session_id: session.session_id,
             in_flight: session.in_flight,
             remote_addr: session.remote_ip,
+            peer_uid,
+            channel_binding,
+            scram: None,
+            notify: None,
             stream_rx,
             stream_tx: Arc::new(tokio::sync::Mutex::new(stream_tx)),
         })
     }
 
     pub async fn into_tls(self) -> Result<Session<TlsStream<T>>, ()> {
+        let instance = self.instance.clone();
+        let session_id = self.session_id;
+
+        self.into_stream(|stream| async move { instance.tls_accept(stream, session_id).await })
+            .await
+    }
+
+    pub async fn into_compressed(self) -> Result<Session<DeflateStream<T>>, ()> {
+        let mut session = self
+            .into_stream(|stream| async { Ok(DeflateStream::new(stream)) })
+            .await?;
+        session.is_compressed = true;
+
+        Ok(session)
+    }
+
+    async fn into_stream<U: SessionStream, F: Future<Output = Result<U, ()>>>(
+        self,
+        upgrade: impl FnOnce(T) -> F,
+    ) -> Result<Session<U>, ()> {
         // Drop references to write half from state
         let state = if let Some(state) =
             self.state
@@ -192,9 +241,11 @@ impl<T: SessionStream> Session<T> {
             return Err(());
         };
 
-        // Upgrade to TLS
-        let (stream_rx, stream_tx) =
-            tokio::io::split(self.instance.tls_accept(stream, self.session_id).await?);
+        // Wrap the stream with TLS or compression
+        let stream = upgrade(stream).await?;
+        let is_tls = stream.is_tls();
+        let channel_binding = stream.tls_channel_binding();
+        let (stream_rx, stream_tx) = tokio::io::split(stream);
         let stream_tx = Arc::new(tokio::sync::Mutex::new(stream_tx));
 
         Ok(Session {
@@ -203,13 +254,18 @@ impl<T: SessionStream> Session<T> {
             receiver: self.receiver,
             version: self.version,
             state: state.try_replace_stream_tx(stream_tx.clone()).unwrap(),
-            is_tls: true,
+            is_tls,
+            is_compressed: self.is_compressed,
             is_condstore: self.is_condstore,
             is_qresync: self.is_qresync,
             is_utf8: self.is_utf8,
             session_id: self.session_id,
             in_flight: self.in_flight,
             remote_addr: self.remote_addr,
+            peer_uid: self.peer_uid,
+            channel_binding,
+            scram: None,
+            notify: self.notify,
             stream_rx,
             stream_tx,
         })
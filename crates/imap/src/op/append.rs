@@ -11,7 +11,13 @@ use crate::{
 };
 use common::{ipc::PushNotification, listener::SessionStream};
 use directory::Permission;
-use email::message::ingest::{EmailIngest, IngestEmail, IngestSource};
+use email::{
+    hooks::IngestSource as HookSource,
+    message::{
+        delivery_hooks::{schedule_hook_tasks, try_ingest_hook},
+        ingest::{EmailIngest, IngestEmail, IngestSource},
+    },
+};
 use imap_proto::{
     Command, ResponseCode, StatusResponse,
     protocol::{append::Arguments, select::HighestModSeq},
@@ -100,29 +106,81 @@ impl<T: SessionStream> SessionData<T> {
         let mut created_ids = Vec::with_capacity(arguments.messages.len());
         let mut last_change_id = None;
         for message in arguments.messages {
+            // Run the delivery hooks configured for IMAP
+            let mut mailbox_ids = vec![mailbox_id];
+            let mut keywords = message
+                .flags
+                .into_iter()
+                .map(Keyword::from)
+                .collect::<Vec<_>>();
+            let Some(hook_outcome) = try_ingest_hook(
+                &self.server,
+                account_id,
+                HookSource::Imap,
+                &message.message,
+                &mut mailbox_ids,
+                &mut keywords,
+                self.session_id,
+            )
+            .await
+            .map_err(|err| {
+                if err.matches(trc::EventType::MessageIngest(
+                    trc::MessageIngestEvent::Error,
+                )) {
+                    err.details("Message rejected by delivery hook.")
+                } else {
+                    err
+                }
+                .id(arguments.tag.clone())
+            })?
+            else {
+                // Discarded by a hook
+                continue;
+            };
+            let raw_message = hook_outcome
+                .raw_message
+                .as_deref()
+                .unwrap_or(&message.message);
+            // Hooks may have moved the message out of the target mailbox
+            let is_in_mailbox = mailbox_ids.first() == Some(&mailbox_id);
+
             match self
                 .server
                 .email_ingest(IngestEmail {
-                    raw_message: &message.message,
-                    message: MessageParser::new().parse(&message.message),
+                    raw_message,
+                    message: MessageParser::new().parse(raw_message),
                     blob_hash: None,
                     access_token: &access_token,
-                    mailbox_ids: vec![mailbox_id],
-                    keywords: message.flags.into_iter().map(Keyword::from).collect(),
+                    mailbox_ids,
+                    keywords,
                     received_at: message.received_at.map(|d| d as u64),
                     source: IngestSource::Imap {
                         train_classifier: true,
                     },
                     session_id: self.session_id,
-                    preview_text: None,
+                    preview_text: hook_outcome.preview_text,
                 })
                 .await
             {
                 Ok(email) => {
-                    created_ids.push(ImapUidToId {
-                        uid: email.imap_uids[0],
-                        id: email.document_id,
-                    });
+                    schedule_hook_tasks(
+                        &self.server,
+                        account_id,
+                        &email,
+                        HookSource::Imap,
+                        None,
+                        hook_outcome.expires_in,
+                        hook_outcome.audit,
+                        self.session_id,
+                    )
+                    .await;
+
+                    if is_in_mailbox {
+                        created_ids.push(ImapUidToId {
+                            uid: email.imap_uids[0],
+                            id: email.document_id,
+                        });
+                    }
                     last_change_id = Some(email.change_id);
                 }
                 Err(err) => {
@@ -6,8 +6,9 @@
 
 use common::{
     auth::{
-        AuthRequest,
-        sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain},
+        AccessToken, AuthRequest,
+        sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain, sasl_encode_challenge},
+        scram::ScramStep,
     },
     listener::{SessionStream, limiter::LimiterResult},
 };
@@ -15,7 +16,10 @@ use common::{
 use directory::Permission;
 use imap_proto::{
     Command, ResponseCode, StatusResponse,
-    protocol::{authenticate::Mechanism, capability::Capability},
+    protocol::{
+        authenticate::{self, Mechanism},
+        capability::Capability,
+    },
     receiver::{self, Request},
 };
 use mail_parser::decoders::base64::base64_decode;
@@ -63,6 +67,71 @@ impl<T: SessionStream> Session<T> {
                     self.write_bytes(b"+ \r\n".to_vec()).await
                 }
             }
+            Mechanism::ScramSha256 | Mechanism::ScramSha256Plus => {
+                let response = match args.params.pop() {
+                    Some(param) => Some(base64_decode(param.as_bytes()).ok_or_else(|| {
+                        self.scram = None;
+                        trc::AuthEvent::Error
+                            .into_err()
+                            .details("Failed to decode challenge.")
+                            .id(args.tag.clone())
+                            .code(ResponseCode::Parse)
+                    })?),
+                    None => None,
+                };
+
+                match (self.scram.take(), response) {
+                    (None, Some(response)) if !response.is_empty() => {
+                        let auth = self
+                            .server
+                            .scram_begin(
+                                &response,
+                                args.mechanism == Mechanism::ScramSha256Plus,
+                                self.channel_binding.is_some(),
+                                None,
+                            )
+                            .await
+                            .map_err(|err| err.id(args.tag.clone()))?;
+                        let challenge = sasl_encode_challenge(auth.server_first());
+                        self.scram = Some(ScramStep::ClientFinal(Box::new(auth)));
+                        self.continue_authenticate(args, challenge).await
+                    }
+                    (None, _) => self.continue_authenticate(args, String::new()).await,
+                    (Some(ScramStep::ClientFinal(auth)), Some(response)) => {
+                        match self
+                            .server
+                            .scram_finish(
+                                *auth,
+                                &response,
+                                self.channel_binding.clone(),
+                                self.session_id,
+                                self.remote_addr,
+                                self.peer_uid,
+                            )
+                            .await
+                        {
+                            Ok((access_token, server_final)) => {
+                                self.scram = Some(ScramStep::Completed(access_token));
+                                self.continue_authenticate(
+                                    args,
+                                    sasl_encode_challenge(server_final),
+                                )
+                                .await
+                            }
+                            Err(err) => self.handle_auth_result(Err(err), args.tag).await,
+                        }
+                    }
+                    (Some(ScramStep::Completed(access_token)), response)
+                        if response.as_ref().is_none_or(|response| response.is_empty()) =>
+                    {
+                        self.handle_auth_result(Ok(access_token), args.tag).await
+                    }
+                    _ => Err(trc::AuthEvent::Error
+                        .into_err()
+                        .details("Invalid SASL challenge.")
+                        .id(args.tag)),
+                }
+            }
             _ => Err(trc::AuthEvent::Error
                 .into_err()
                 .details("Authentication mechanism not supported.")
@@ -71,20 +140,44 @@ impl<T: SessionStream> Session<T> {
         }
     }
 
+    async fn continue_authenticate(
+        &mut self,
+        args: authenticate::Arguments,
+        challenge: String,
+    ) -> trc::Result<()> {
+        self.receiver.request = receiver::Request {
+            tag: args.tag,
+            command: Command::Authenticate,
+            tokens: vec![receiver::Token::Argument(args.mechanism.into_bytes())],
+        };
+        self.receiver.state = receiver::State::Argument { last_ch: b' ' };
+        self.write_bytes(format!("+ {challenge}\r\n").into_bytes())
+            .await
+    }
+
     pub async fn authenticate(
         &mut self,
         credentials: Credentials<String>,
         tag: String,
     ) -> trc::Result<()> {
         // Authenticate
-        let access_token = self
+        let result = self
             .server
-            .authenticate(&AuthRequest::from_credentials(
-                credentials,
-                self.session_id,
-                self.remote_addr,
-            ))
-            .await
+            .authenticate(
+                &AuthRequest::from_credentials(credentials, self.session_id, self.remote_addr)
+                    .with_peer_uid(self.peer_uid),
+            )
+            .await;
+
+        self.handle_auth_result(result, tag).await
+    }
+
+    async fn handle_auth_result(
+        &mut self,
+        result: trc::Result<Arc<AccessToken>>,
+        tag: String,
+    ) -> trc::Result<()> {
+        let access_token = result
             .map_err(|err| {
                 if err.matches(trc::EventType::Auth(trc::AuthEvent::Failed)) {
                     let auth_failures = self.state.auth_failures();
@@ -140,6 +233,7 @@ impl<T: SessionStream> Session<T> {
 
     pub async fn handle_unauthenticate(&mut self, request: Request<Command>) -> trc::Result<()> {
         self.state = State::NotAuthenticated { auth_failures: 0 };
+        self.notify = None;
 
         self.write_bytes(
             StatusResponse::completed(Command::Unauthenticate)
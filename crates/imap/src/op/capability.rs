@@ -13,6 +13,7 @@ use imap_proto::{
     Command, StatusResponse,
     protocol::{
         ImapResponse,
+        authenticate::Mechanism,
         capability::{Capability, Response},
     },
     receiver::Request,
@@ -32,18 +33,21 @@ impl<T: SessionStream> Session<T> {
             Elapsed = op_start.elapsed()
         );
 
+        let mut capabilities = Capability::all_capabilities(
+            self.state.is_authenticated(),
+            !self.is_tls && !self.is_compressed && self.instance.acceptor.is_tls(),
+        );
+        if self.is_compressed {
+            capabilities.retain(|capability| *capability != Capability::CompressDeflate);
+        }
+        if !self.state.is_authenticated() && self.channel_binding.is_some() {
+            capabilities.insert(0, Capability::Auth(Mechanism::ScramSha256Plus));
+        }
+
         self.write_bytes(
             StatusResponse::completed(Command::Capability)
                 .with_tag(request.tag)
-                .serialize(
-                    Response {
-                        capabilities: Capability::all_capabilities(
-                            self.state.is_authenticated(),
-                            !self.is_tls && self.instance.acceptor.is_tls(),
-                        ),
-                    }
-                    .serialize(),
-                ),
+                .serialize(Response { capabilities }.serialize()),
         )
         .await
     }
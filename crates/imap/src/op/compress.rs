@@ -0,0 +1,53 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{sync::Arc, time::Instant};
+
+use crate::core::{Session, State};
+use common::listener::{SessionResult, SessionStream};
+use imap_proto::{Command, ResponseCode, StatusResponse, receiver::Request};
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_compress(
+        &mut self,
+        request: Request<Command>,
+    ) -> trc::Result<SessionResult> {
+        let op_start = Instant::now();
+        let arguments = request.parse_compress()?;
+
+        if self.is_compressed {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Compression is already active.")
+                .code(ResponseCode::CompressionActive)
+                .id(arguments.tag));
+        }
+
+        // The write half can only be replaced once no other command holds it
+        if let State::Authenticated { data } | State::Selected { data, .. } = &self.state
+            && Arc::strong_count(data) > 1
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Wait for pending commands to complete before enabling compression.")
+                .id(arguments.tag));
+        }
+
+        trc::event!(
+            Imap(trc::ImapEvent::Compress),
+            SpanId = self.session_id,
+            Elapsed = op_start.elapsed()
+        );
+
+        self.write_bytes(
+            StatusResponse::ok("DEFLATE active")
+                .with_tag(arguments.tag)
+                .into_bytes(),
+        )
+        .await
+        .map(|_| SessionResult::UpgradeCompression)
+    }
+}
@@ -20,6 +20,7 @@ use imap_proto::{
         list::{
             self, Arguments, Attribute, ChildInfo, ListItem, ReturnOption, SelectionOption, Tag,
         },
+        metadata::Depth,
     },
     receiver::Request,
 };
@@ -69,6 +70,7 @@ impl<T: SessionStream> Session<T> {
                                 tags: vec![],
                             }],
                             status_items: Vec::new(),
+                            metadata_items: Vec::new(),
                         }
                         .serialize(),
                     ),
@@ -128,6 +130,7 @@ impl<T: SessionStream> SessionData<T> {
         let mut include_subscribed = false;
         let mut include_children = false;
         let mut include_status = None;
+        let mut include_metadata = None;
         for selection_option in &selection_options {
             match selection_option {
                 SelectionOption::Subscribed => {
@@ -158,6 +161,9 @@ impl<T: SessionStream> SessionData<T> {
                 ReturnOption::SpecialUse => {
                     include_special_use = true;
                 }
+                ReturnOption::Metadata { entries, max_size } => {
+                    include_metadata = (entries, *max_size).into();
+                }
             }
         }
         if recursive_match && !filter_subscribed {
@@ -291,6 +297,25 @@ impl<T: SessionStream> SessionData<T> {
             }
         }
 
+        // Add metadata response
+        let mut metadata_items = Vec::new();
+        if let Some((entries, max_size)) = include_metadata {
+            for list_item in &list_items {
+                if let Ok((metadata_item, _)) = self
+                    .metadata(
+                        list_item.mailbox_name.clone(),
+                        entries,
+                        Depth::Zero,
+                        max_size,
+                    )
+                    .await
+                    && !metadata_item.entries.is_empty()
+                {
+                    metadata_items.push(metadata_item);
+                }
+            }
+        }
+
         trc::event!(
             Imap(if !is_lsub {
                 trc::ImapEvent::List
@@ -320,6 +345,7 @@ impl<T: SessionStream> SessionData<T> {
                     is_lsub,
                     list_items,
                     status_items,
+                    metadata_items,
                 }
                 .serialize(),
             ),
@@ -0,0 +1,264 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use crate::{
+    core::{MailboxId, Session, SessionData},
+    op::ImapContext,
+    spawn_op,
+};
+use common::listener::SessionStream;
+use directory::Permission;
+use email::mailbox::metadata::MailboxMetadataFnc;
+use imap_proto::{
+    Command, ResponseCode, StatusResponse,
+    protocol::metadata::{Depth, MetadataCode, MetadataItem, SetArguments},
+    receiver::Request,
+};
+use trc::AddContext;
+use types::acl::Acl;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_get_metadata(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapStatus)?;
+
+        let op_start = Instant::now();
+        let arguments = request.parse_get_metadata(self.is_utf8)?;
+        let data = self.state.session_data();
+        let is_utf8 = self.is_utf8;
+
+        spawn_op!(data, {
+            // Refresh mailboxes
+            data.synchronize_mailboxes(false)
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?;
+
+            let (metadata_item, long_entries) = data
+                .metadata(
+                    arguments.mailbox_name,
+                    &arguments.entries,
+                    arguments.depth,
+                    arguments.max_size,
+                )
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?;
+
+            trc::event!(
+                Imap(trc::ImapEvent::GetMetadata),
+                SpanId = data.session_id,
+                MailboxName = metadata_item.mailbox_name.clone(),
+                Total = metadata_item.entries.len(),
+                Elapsed = op_start.elapsed()
+            );
+
+            let mut buf = Vec::with_capacity(64);
+            if !metadata_item.entries.is_empty() {
+                metadata_item.serialize(&mut buf, is_utf8);
+            }
+            let mut response = StatusResponse::completed(Command::GetMetadata);
+            if let Some(long_entries) = long_entries {
+                response = response.with_code(ResponseCode::Metadata {
+                    code: MetadataCode::LongEntries(long_entries),
+                });
+            }
+
+            data.write_bytes(response.with_tag(arguments.tag).serialize(buf))
+                .await
+        })
+    }
+
+    pub async fn handle_set_metadata(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapStatus)?;
+
+        let op_start = Instant::now();
+        let arguments = request.parse_set_metadata(self.is_utf8)?;
+        let data = self.state.session_data();
+
+        spawn_op!(data, {
+            let response = data.set_metadata(arguments, op_start).await?;
+
+            data.write_bytes(response.into_bytes()).await
+        })
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    pub async fn metadata(
+        &self,
+        mailbox_name: String,
+        entries: &[String],
+        depth: Depth,
+        max_size: Option<u32>,
+    ) -> trc::Result<(MetadataItem, Option<u32>)> {
+        // Server annotations are stored in the user's own account
+        let (account_id, mailbox_id) = if !mailbox_name.is_empty() {
+            let mailbox = self
+                .mailbox_by_name(&mailbox_name, &[Acl::Read, Acl::ReadItems])
+                .await?;
+            (mailbox.account_id, Some(mailbox.mailbox_id))
+        } else {
+            (self.account_id, None)
+        };
+
+        let metadata = self
+            .server
+            .mailbox_metadata(account_id, mailbox_id)
+            .await
+            .caused_by(trc::location!())?;
+
+        let mut result = Vec::new();
+        let mut long_entries = None;
+        for entry in metadata.visible_entries(self.account_id) {
+            if entries.iter().any(|name| depth.matches(name, &entry.name)) {
+                let size = entry.value.len() as u32;
+                if max_size.is_some_and(|max_size| size > max_size) {
+                    long_entries = long_entries.max(Some(size));
+                } else {
+                    result.push((entry.name.clone(), Some(entry.value.clone())));
+                }
+            }
+        }
+        result.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        Ok((
+            MetadataItem {
+                mailbox_name,
+                entries: result,
+            },
+            long_entries,
+        ))
+    }
+
+    async fn set_metadata(
+        &self,
+        arguments: SetArguments,
+        op_start: Instant,
+    ) -> trc::Result<StatusResponse> {
+        // Validate value sizes
+        let max_size = self.server.core.imap.metadata_max_size;
+        if arguments
+            .entries
+            .iter()
+            .any(|(_, value)| value.as_ref().is_some_and(|value| value.len() > max_size))
+        {
+            return Ok(StatusResponse::no("Annotation value is too large.")
+                .with_tag(arguments.tag)
+                .with_code(ResponseCode::Metadata {
+                    code: MetadataCode::MaxSize(max_size as u32),
+                }));
+        }
+
+        // Refresh mailboxes
+        self.synchronize_mailboxes(false)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+
+        // Private annotations require lookup and read rights on the mailbox,
+        // shared annotations also require the right to modify it
+        let (account_id, mailbox_id) = if !arguments.mailbox_name.is_empty() {
+            let acls: &[Acl] = if arguments
+                .entries
+                .iter()
+                .any(|(name, _)| name.starts_with("/shared/"))
+            {
+                &[Acl::Read, Acl::ReadItems, Acl::Modify]
+            } else {
+                &[Acl::Read, Acl::ReadItems]
+            };
+            let mailbox = self
+                .mailbox_by_name(&arguments.mailbox_name, acls)
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?;
+            (mailbox.account_id, Some(mailbox.mailbox_id))
+        } else {
+            (self.account_id, None)
+        };
+
+        // Private annotations are charged to the user setting them
+        let access_token = self
+            .get_access_token()
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+        let owner_token = self
+            .server
+            .get_resource_token(&access_token, account_id)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+        let writer_token = self
+            .server
+            .get_resource_token(&access_token, self.account_id)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+        let total = arguments.entries.len();
+        let result = self
+            .server
+            .mailbox_metadata_set(
+                &owner_token,
+                &writer_token,
+                mailbox_id,
+                arguments.entries,
+                self.server.core.imap.metadata_max_entries,
+            )
+            .await;
+
+        trc::event!(
+            Imap(trc::ImapEvent::SetMetadata),
+            SpanId = self.session_id,
+            AccountId = account_id,
+            MailboxName = arguments.mailbox_name,
+            Total = total,
+            Elapsed = op_start.elapsed()
+        );
+
+        match result {
+            Ok(true) => Ok(StatusResponse::completed(Command::SetMetadata).with_tag(arguments.tag)),
+            Ok(false) => Ok(StatusResponse::no("Too many annotations.")
+                .with_tag(arguments.tag)
+                .with_code(ResponseCode::Metadata {
+                    code: MetadataCode::TooMany,
+                })),
+            Err(err) => Err(
+                if err.matches(trc::EventType::Limit(trc::LimitEvent::Quota)) {
+                    err.details("Disk quota exceeded.")
+                        .code(ResponseCode::OverQuota)
+                } else if err.matches(trc::EventType::Limit(trc::LimitEvent::TenantQuota)) {
+                    err.details("Organization disk quota exceeded.")
+                        .code(ResponseCode::OverQuota)
+                } else {
+                    err
+                }
+                .id(arguments.tag),
+            ),
+        }
+    }
+
+    async fn mailbox_by_name(&self, mailbox_name: &str, acls: &[Acl]) -> trc::Result<MailboxId> {
+        let mailbox = self.get_mailbox_by_name(mailbox_name).ok_or_else(|| {
+            trc::ImapEvent::Error
+                .into_err()
+                .details("Mailbox does not exist.")
+                .code(ResponseCode::NonExistent)
+        })?;
+
+        for acl in acls {
+            if !self
+                .check_mailbox_acl(mailbox.account_id, mailbox.mailbox_id, *acl)
+                .await
+                .caused_by(trc::location!())?
+            {
+                return Err(trc::ImapEvent::Error
+                    .into_err()
+                    .details("You do not have the required permissions to access this mailbox.")
+                    .code(ResponseCode::NoPerm));
+            }
+        }
+
+        Ok(mailbox)
+    }
+}
@@ -0,0 +1,447 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use crate::{
+    core::{MailboxId, NotifyState, SelectedMailbox, Session, SessionData, State},
+    op::ImapContext,
+};
+use ahash::AHashSet;
+use common::{ipc::PushNotification, listener::SessionStream};
+use directory::Permission;
+use email::cache::MessageCacheFetch;
+use imap_proto::{
+    Command, ResponseCode, StatusResponse,
+    protocol::{
+        list::{Attribute, ListItem},
+        notify::{Event, Filter},
+        status::Status,
+    },
+    receiver::Request,
+};
+use trc::AddContext;
+use types::type_state::DataType;
+use utils::map::bitmap::Bitmap;
+
+const SUPPORTED_EVENTS: [Event; 5] = [
+    Event::MessageNew,
+    Event::MessageExpunge,
+    Event::FlagChange,
+    Event::MailboxName,
+    Event::SubscriptionChange,
+];
+
+#[derive(Debug, Default, Clone, Copy)]
+struct NotifyMailbox {
+    is_personal: bool,
+    is_subscribed: bool,
+    is_selected: bool,
+}
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_notify(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapIdle)?;
+
+        let op_start = Instant::now();
+        let arguments = request.parse_notify(self.is_utf8)?;
+
+        if arguments
+            .groups
+            .iter()
+            .flat_map(|group| group.events.iter())
+            .any(|event| !SUPPORTED_EVENTS.contains(event))
+        {
+            return self
+                .write_bytes(
+                    StatusResponse::no("Unsupported notification event.")
+                        .with_tag(arguments.tag)
+                        .with_code(ResponseCode::BadEvent {
+                            events: SUPPORTED_EVENTS.to_vec(),
+                        })
+                        .into_bytes(),
+                )
+                .await;
+        }
+
+        let total_groups = arguments.groups.len();
+        if !arguments.groups.is_empty() {
+            let data = self.state.session_data();
+
+            // Register with push manager
+            let push_rx = self
+                .server
+                .subscribe_push_manager(
+                    &data.access_token,
+                    Bitmap::from_iter([
+                        DataType::Email,
+                        DataType::Mailbox,
+                        DataType::EmailDelivery,
+                    ]),
+                )
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?;
+
+            // Refresh mailboxes so that only subsequent changes are reported
+            data.synchronize_mailboxes(false)
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?;
+
+            let notify = NotifyState {
+                groups: arguments.groups,
+                push_rx,
+            };
+
+            // Send the initial status of all monitored mailboxes
+            if arguments.status {
+                let selected_id = match &self.state {
+                    State::Selected { mailbox, .. } => Some(mailbox.id),
+                    _ => None,
+                };
+                let mailbox_names = data
+                    .mailboxes
+                    .lock()
+                    .iter()
+                    .flat_map(|account| {
+                        account
+                            .mailbox_names
+                            .iter()
+                            .map(|(mailbox_name, mailbox_id)| {
+                                let mailbox_id = MailboxId {
+                                    account_id: account.account_id,
+                                    mailbox_id: *mailbox_id,
+                                };
+                                (
+                                    mailbox_name.clone(),
+                                    NotifyMailbox {
+                                        is_personal: account.prefix.is_none(),
+                                        is_subscribed: account
+                                            .mailbox_state
+                                            .get(&mailbox_id.mailbox_id)
+                                            .is_some_and(|m| m.is_subscribed),
+                                        is_selected: selected_id == Some(mailbox_id),
+                                    },
+                                )
+                            })
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut buf = Vec::with_capacity(64);
+                for (mailbox_name, mailbox) in mailbox_names {
+                    if !mailbox.is_selected
+                        && notify
+                            .events(&mailbox_name, mailbox)
+                            .iter()
+                            .any(|event| event.is_message_event())
+                        && let Ok(status) = data
+                            .status(mailbox_name, &notify_status_items(self.is_condstore))
+                            .await
+                    {
+                        status.serialize(&mut buf, self.is_utf8);
+                    }
+                }
+                if !buf.is_empty() {
+                    self.write_bytes(buf).await?;
+                }
+            }
+
+            self.notify = Some(notify);
+        } else {
+            self.notify = None;
+        }
+
+        trc::event!(
+            Imap(trc::ImapEvent::Notify),
+            SpanId = self.session_id,
+            Total = total_groups,
+            Elapsed = op_start.elapsed()
+        );
+
+        self.write_bytes(
+            StatusResponse::completed(Command::Notify)
+                .with_tag(arguments.tag)
+                .into_bytes(),
+        )
+        .await
+    }
+
+    pub async fn write_notifications(
+        &mut self,
+        push_notification: Option<PushNotification>,
+    ) -> trc::Result<()> {
+        let (data, mailbox) = match (&self.state, push_notification.is_some()) {
+            (State::Authenticated { data }, true) => (data.clone(), None),
+            (State::Selected { data, mailbox }, true) => (data.clone(), Some(mailbox.clone())),
+            _ => {
+                // Push channel closed or session no longer authenticated
+                self.notify = None;
+                return Ok(());
+            }
+        };
+        let notify = if let Some(notify) = &self.notify {
+            notify
+        } else {
+            return Ok(());
+        };
+
+        let mut has_mailbox_changes = false;
+        let mut has_email_changes = false;
+        match push_notification.unwrap() {
+            PushNotification::StateChange(state_change) => {
+                for type_state in state_change.types {
+                    match type_state {
+                        DataType::Email | DataType::EmailDelivery => {
+                            has_email_changes = true;
+                        }
+                        DataType::Mailbox => {
+                            has_mailbox_changes = true;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            PushNotification::EmailPush(_) => {
+                has_email_changes = true;
+                has_mailbox_changes = true;
+            }
+            PushNotification::CalendarAlert(_) => (),
+        }
+
+        // Report changes in other mailboxes
+        if has_mailbox_changes {
+            let changes = data
+                .synchronize_mailboxes(true)
+                .await
+                .caused_by(trc::location!())?
+                .unwrap();
+            let selected_id = mailbox.as_ref().map(|mailbox| mailbox.id);
+            let mut buf = Vec::with_capacity(64);
+
+            for mailbox_name in changes.deleted {
+                let mailbox = data.notify_mailbox(&mailbox_name, selected_id);
+                if notify
+                    .events(&mailbox_name, mailbox)
+                    .contains(&Event::MailboxName)
+                {
+                    ListItem {
+                        mailbox_name,
+                        attributes: vec![Attribute::NonExistent],
+                        tags: vec![],
+                    }
+                    .serialize(
+                        &mut buf,
+                        self.version.is_rev2(),
+                        self.is_utf8,
+                        false,
+                    );
+                }
+            }
+
+            for mailbox_name in changes.added {
+                let mailbox = data.notify_mailbox(&mailbox_name, selected_id);
+                if notify
+                    .events(&mailbox_name, mailbox)
+                    .contains(&Event::MailboxName)
+                {
+                    ListItem {
+                        mailbox_name,
+                        attributes: if mailbox.is_subscribed {
+                            vec![Attribute::Subscribed]
+                        } else {
+                            vec![]
+                        },
+                        tags: vec![],
+                    }
+                    .serialize(
+                        &mut buf,
+                        self.version.is_rev2(),
+                        self.is_utf8,
+                        false,
+                    );
+                }
+            }
+
+            for mailbox_name in changes.subscribed {
+                let mailbox = data.notify_mailbox(&mailbox_name, selected_id);
+                if notify
+                    .events(&mailbox_name, mailbox)
+                    .contains(&Event::SubscriptionChange)
+                {
+                    ListItem {
+                        mailbox_name,
+                        attributes: if mailbox.is_subscribed {
+                            vec![Attribute::Subscribed]
+                        } else {
+                            vec![]
+                        },
+                        tags: vec![],
+                    }
+                    .serialize(
+                        &mut buf,
+                        self.version.is_rev2(),
+                        self.is_utf8,
+                        false,
+                    );
+                }
+            }
+
+            for mailbox_name in changes.changed {
+                let mailbox = data.notify_mailbox(&mailbox_name, selected_id);
+                if !mailbox.is_selected
+                    && notify
+                        .events(&mailbox_name, mailbox)
+                        .iter()
+                        .any(|event| event.is_message_event())
+                    && let Ok(status) = data
+                        .status(mailbox_name, &notify_status_items(self.is_condstore))
+                        .await
+                {
+                    status.serialize(&mut buf, self.is_utf8);
+                }
+            }
+
+            if !buf.is_empty() {
+                data.write_bytes(buf).await?;
+            }
+        }
+
+        // Report changes in the selected mailbox, expunges are deferred
+        // to the next command when SELECTED-DELAYED is requested
+        if has_email_changes
+            && let Some(selected) = &mailbox
+            && let Some(group) = notify.groups.iter().find(|group| {
+                group.filter.is_selected()
+                    && group.events.iter().any(|event| event.is_message_event())
+            })
+        {
+            // Sequence numbers can only be reported consistently once the pending
+            // expunges are sent, so the whole update waits for the next command
+            let is_delayed = group.filter == Filter::SelectedDelayed
+                && data
+                    .has_pending_expunges(selected)
+                    .await
+                    .caused_by(trc::location!())?;
+
+            if !is_delayed {
+                data.write_changes(
+                    &mailbox,
+                    false,
+                    true,
+                    self.is_qresync,
+                    self.version.is_rev2(),
+                    self.is_utf8,
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    /// Whether messages were removed from the selected mailbox since the last
+    /// EXPUNGE responses were sent, without updating the mailbox state
+    async fn has_pending_expunges(&self, mailbox: &SelectedMailbox) -> trc::Result<bool> {
+        let cached_messages = self
+            .server
+            .get_cached_messages(mailbox.id.account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let uids = cached_messages
+            .emails
+            .items
+            .iter()
+            .filter_map(|item| {
+                item.mailboxes
+                    .iter()
+                    .find(|m| m.mailbox_id == mailbox.id.mailbox_id)
+                    .map(|m| m.uid)
+            })
+            .collect::<AHashSet<_>>();
+
+        let state = mailbox.state.lock();
+        Ok(state
+            .next_state
+            .as_ref()
+            .is_some_and(|next_state| !next_state.deletions.is_empty())
+            || state.id_to_imap.values().any(|id| !uids.contains(&id.uid)))
+    }
+
+    fn notify_mailbox(&self, mailbox_name: &str, selected_id: Option<MailboxId>) -> NotifyMailbox {
+        for account in self.mailboxes.lock().iter() {
+            if let Some(mailbox_id) = account.mailbox_names.get(mailbox_name) {
+                return NotifyMailbox {
+                    is_personal: account.prefix.is_none(),
+                    is_subscribed: account
+                        .mailbox_state
+                        .get(mailbox_id)
+                        .is_some_and(|m| m.is_subscribed),
+                    is_selected: selected_id
+                        == Some(MailboxId {
+                            account_id: account.account_id,
+                            mailbox_id: *mailbox_id,
+                        }),
+                };
+            }
+        }
+
+        // Mailbox no longer exists
+        NotifyMailbox {
+            is_personal: !mailbox_name.starts_with(&self.server.core.jmap.shared_folder),
+            ..Default::default()
+        }
+    }
+}
+
+impl NotifyState {
+    fn events(&self, mailbox_name: &str, mailbox: NotifyMailbox) -> &[Event] {
+        // The selected mailbox filter takes precedence over all others
+        if mailbox.is_selected
+            && let Some(group) = self.groups.iter().find(|group| group.filter.is_selected())
+        {
+            return &group.events;
+        }
+
+        self.groups
+            .iter()
+            .find(|group| match &group.filter {
+                Filter::Selected | Filter::SelectedDelayed => false,
+                Filter::Personal => mailbox.is_personal,
+                Filter::Inboxes => mailbox.is_personal && mailbox_name == "INBOX",
+                Filter::Subscribed => mailbox.is_subscribed,
+                Filter::Subtree(roots) => roots.iter().any(|root| {
+                    mailbox_name
+                        .strip_prefix(root.as_str())
+                        .is_some_and(|path| path.is_empty() || path.starts_with('/'))
+                }),
+                Filter::Mailboxes(names) => names.iter().any(|name| name == mailbox_name),
+            })
+            .map(|group| group.events.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+pub async fn next_notification(notify: &mut Option<NotifyState>) -> Option<PushNotification> {
+    match notify {
+        Some(notify) => notify.push_rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn notify_status_items(is_condstore: bool) -> Vec<Status> {
+    let mut items = vec![
+        Status::Messages,
+        Status::UidNext,
+        Status::UidValidity,
+        Status::Unseen,
+    ];
+    if is_condstore {
+        items.push(Status::HighestModSeq);
+    }
+    items
+}
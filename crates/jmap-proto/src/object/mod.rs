@@ -24,6 +24,7 @@ pub mod mailbox;
 pub mod participant_identity;
 pub mod principal;
 pub mod push_subscription;
+pub mod quarantined_email;
 pub mod quota;
 pub mod search_snippet;
 pub mod share_notification;
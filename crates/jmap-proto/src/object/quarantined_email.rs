@@ -0,0 +1,190 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    object::{AnyId, JmapObject, JmapObjectId},
+    types::date::UTCDate,
+};
+use jmap_tools::{Element, Key, Property};
+use std::{borrow::Cow, str::FromStr};
+use types::id::Id;
+
+#[derive(Debug, Clone, Default)]
+pub struct QuarantinedEmail;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QuarantinedEmailProperty {
+    Id,
+    Sender,
+    Recipient,
+    From,
+    Subject,
+    Preview,
+    Size,
+    ReceivedAt,
+    ExpiresAt,
+    Reason,
+    Released,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QuarantinedEmailValue {
+    Id(Id),
+    Date(UTCDate),
+}
+
+impl Property for QuarantinedEmailProperty {
+    fn try_parse(_: Option<&Key<'_, Self>>, value: &str) -> Option<Self> {
+        QuarantinedEmailProperty::parse(value)
+    }
+
+    fn to_cow(&self) -> Cow<'static, str> {
+        match self {
+            QuarantinedEmailProperty::Id => "id",
+            QuarantinedEmailProperty::Sender => "sender",
+            QuarantinedEmailProperty::Recipient => "recipient",
+            QuarantinedEmailProperty::From => "from",
+            QuarantinedEmailProperty::Subject => "subject",
+            QuarantinedEmailProperty::Preview => "preview",
+            QuarantinedEmailProperty::Size => "size",
+            QuarantinedEmailProperty::ReceivedAt => "receivedAt",
+            QuarantinedEmailProperty::ExpiresAt => "expiresAt",
+            QuarantinedEmailProperty::Reason => "reason",
+            QuarantinedEmailProperty::Released => "released",
+        }
+        .into()
+    }
+}
+
+impl Element for QuarantinedEmailValue {
+    type Property = QuarantinedEmailProperty;
+
+    fn try_parse<P>(key: &Key<'_, Self::Property>, value: &str) -> Option<Self> {
+        if let Key::Property(prop) = key {
+            match prop {
+                QuarantinedEmailProperty::Id => {
+                    Id::from_str(value).ok().map(QuarantinedEmailValue::Id)
+                }
+                QuarantinedEmailProperty::ReceivedAt | QuarantinedEmailProperty::ExpiresAt => {
+                    UTCDate::from_str(value)
+                        .ok()
+                        .map(QuarantinedEmailValue::Date)
+                }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    fn to_cow(&self) -> Cow<'static, str> {
+        match self {
+            QuarantinedEmailValue::Id(id) => id.to_string().into(),
+            QuarantinedEmailValue::Date(utcdate) => utcdate.to_string().into(),
+        }
+    }
+}
+
+impl QuarantinedEmailProperty {
+    fn parse(value: &str) -> Option<Self> {
+        hashify::tiny_map!(value.as_bytes(),
+            b"id" => QuarantinedEmailProperty::Id,
+            b"sender" => QuarantinedEmailProperty::Sender,
+            b"recipient" => QuarantinedEmailProperty::Recipient,
+            b"from" => QuarantinedEmailProperty::From,
+            b"subject" => QuarantinedEmailProperty::Subject,
+            b"preview" => QuarantinedEmailProperty::Preview,
+            b"size" => QuarantinedEmailProperty::Size,
+            b"receivedAt" => QuarantinedEmailProperty::ReceivedAt,
+            b"expiresAt" => QuarantinedEmailProperty::ExpiresAt,
+            b"reason" => QuarantinedEmailProperty::Reason,
+            b"released" => QuarantinedEmailProperty::Released,
+        )
+    }
+}
+
+impl FromStr for QuarantinedEmailProperty {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        QuarantinedEmailProperty::parse(s).ok_or(())
+    }
+}
+
+impl JmapObject for QuarantinedEmail {
+    type Property = QuarantinedEmailProperty;
+
+    type Element = QuarantinedEmailValue;
+
+    type Id = Id;
+
+    type Filter = ();
+
+    type Comparator = ();
+
+    type GetArguments = ();
+
+    type SetArguments<'de> = ();
+
+    type QueryArguments = ();
+
+    type CopyArguments = ();
+
+    type ParseArguments = ();
+
+    const ID_PROPERTY: Self::Property = QuarantinedEmailProperty::Id;
+}
+
+impl From<Id> for QuarantinedEmailValue {
+    fn from(id: Id) -> Self {
+        QuarantinedEmailValue::Id(id)
+    }
+}
+
+impl JmapObjectId for QuarantinedEmailValue {
+    fn as_id(&self) -> Option<Id> {
+        if let QuarantinedEmailValue::Id(id) = self {
+            Some(*id)
+        } else {
+            None
+        }
+    }
+
+    fn as_any_id(&self) -> Option<AnyId> {
+        self.as_id().map(AnyId::Id)
+    }
+
+    fn as_id_ref(&self) -> Option<&str> {
+        None
+    }
+
+    fn try_set_id(&mut self, new_id: AnyId) -> bool {
+        if let AnyId::Id(id) = new_id {
+            *self = QuarantinedEmailValue::Id(id);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl JmapObjectId for QuarantinedEmailProperty {
+    fn as_id(&self) -> Option<Id> {
+        None
+    }
+
+    fn as_any_id(&self) -> Option<AnyId> {
+        None
+    }
+
+    fn as_id_ref(&self) -> Option<&str> {
+        None
+    }
+
+    fn try_set_id(&mut self, _: AnyId) -> bool {
+        false
+    }
+}
@@ -87,6 +87,8 @@ pub enum Capability {
     PrincipalsAvailability = 1 << 14,
     #[serde(rename(serialize = "urn:ietf:params:jmap:filenode"))]
     FileNode = 1 << 15,
+    #[serde(rename(serialize = "https://stalw.art/jmap/quarantine"))]
+    Quarantine = 1 << 16,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -186,6 +188,11 @@ pub struct SubmissionCapabilities {
     pub max_delayed_send: usize,
     #[serde(rename(serialize = "submissionExtensions"))]
     pub submission_extensions: VecMap<String, Vec<String>>,
+    #[serde(
+        rename(serialize = "undoSendDelay"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub undo_send_delay: Option<u64>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -297,6 +304,7 @@ impl Capability {
             Capability::PrincipalsOwner => "urn:ietf:params:jmap:principals:owner",
             Capability::PrincipalsAvailability => "urn:ietf:params:jmap:principals:availability",
             Capability::FileNode => "urn:ietf:params:jmap:filenode",
+            Capability::Quarantine => "https://stalw.art/jmap/quarantine",
         }
     }
 
@@ -317,6 +325,7 @@ impl Capability {
             Capability::Principals,
             Capability::PrincipalsAvailability,
             Capability::FileNode,
+            Capability::Quarantine,
         ]
     }
 }
@@ -414,6 +423,13 @@ impl Capabilities {
             _ => self.clone(),
         }
     }
+
+    pub fn with_undo_send_delay(mut self, undo_send_delay: Option<u64>) -> Capabilities {
+        if let Capabilities::Submission(submission) = &mut self {
+            submission.undo_send_delay = undo_send_delay;
+        }
+        self
+    }
 }
 
 impl Capability {
@@ -435,6 +451,7 @@ impl Capability {
             "urn:ietf:params:jmap:principals:availability" => Capability::PrincipalsAvailability,
             "urn:ietf:params:jmap:contacts:parse" => Capability::ContactsParse,
             "urn:ietf:params:jmap:calendars:parse" => Capability::CalendarsParse,
+            "https://stalw.art/jmap/quarantine" => Capability::Quarantine,
         )
     }
 }
@@ -25,6 +25,7 @@ pub enum MethodObject {
     EmailSubmission,
     VacationResponse,
     SieveScript,
+    QuarantinedEmail,
     Principal,
     Quota,
     Calendar,
@@ -112,6 +113,9 @@ impl MethodName {
             (MethodFunction::Get, MethodObject::VacationResponse) => "VacationResponse/get",
             (MethodFunction::Set, MethodObject::VacationResponse) => "VacationResponse/set",
 
+            (MethodFunction::Get, MethodObject::QuarantinedEmail) => "QuarantinedEmail/get",
+            (MethodFunction::Set, MethodObject::QuarantinedEmail) => "QuarantinedEmail/set",
+
             (MethodFunction::Get, MethodObject::SieveScript) => "SieveScript/get",
             (MethodFunction::Set, MethodObject::SieveScript) => "SieveScript/set",
             (MethodFunction::Query, MethodObject::SieveScript) => "SieveScript/query",
@@ -223,6 +227,9 @@ impl MethodName {
             "VacationResponse/get" => (MethodObject::VacationResponse, MethodFunction::Get),
             "VacationResponse/set" => (MethodObject::VacationResponse, MethodFunction::Set),
 
+            "QuarantinedEmail/get" => (MethodObject::QuarantinedEmail, MethodFunction::Get),
+            "QuarantinedEmail/set" => (MethodObject::QuarantinedEmail, MethodFunction::Set),
+
             "SieveScript/get" => (MethodObject::SieveScript, MethodFunction::Get),
             "SieveScript/set" => (MethodObject::SieveScript, MethodFunction::Set),
             "SieveScript/query" => (MethodObject::SieveScript, MethodFunction::Query),
@@ -306,6 +313,7 @@ impl Display for MethodObject {
             MethodObject::SearchSnippet => "SearchSnippet",
             MethodObject::Identity => "Identity",
             MethodObject::VacationResponse => "VacationResponse",
+            MethodObject::QuarantinedEmail => "QuarantinedEmail",
             MethodObject::PushSubscription => "PushSubscription",
             MethodObject::SieveScript => "SieveScript",
             MethodObject::Principal => "Principal",
@@ -33,9 +33,9 @@ use crate::{
         calendar_event::CalendarEvent, calendar_event_notification::CalendarEventNotification,
         contact::ContactCard, email::Email, email_submission::EmailSubmission, file_node::FileNode,
         identity::Identity, mailbox::Mailbox, participant_identity::ParticipantIdentity,
-        principal::Principal, push_subscription::PushSubscription, quota::Quota,
-        share_notification::ShareNotification, sieve::Sieve, thread::Thread,
-        vacation_response::VacationResponse,
+        principal::Principal, push_subscription::PushSubscription,
+        quarantined_email::QuarantinedEmail, quota::Quota, share_notification::ShareNotification,
+        sieve::Sieve, thread::Thread, vacation_response::VacationResponse,
     },
     request::{capability::CapabilityIds, reference::MaybeIdReference},
 };
@@ -85,6 +85,7 @@ pub enum GetRequestMethod {
     PushSubscription(GetRequest<PushSubscription>),
     Sieve(GetRequest<Sieve>),
     VacationResponse(GetRequest<VacationResponse>),
+    QuarantinedEmail(GetRequest<QuarantinedEmail>),
     Principal(GetRequest<Principal>),
     PrincipalAvailability(GetAvailabilityRequest),
     Quota(GetRequest<Quota>),
@@ -108,6 +109,7 @@ pub enum SetRequestMethod<'x> {
     PushSubscription(SetRequest<'x, PushSubscription>),
     Sieve(SetRequest<'x, Sieve>),
     VacationResponse(SetRequest<'x, VacationResponse>),
+    QuarantinedEmail(SetRequest<'x, QuarantinedEmail>),
     AddressBook(SetRequest<'x, AddressBook>),
     ContactCard(SetRequest<'x, ContactCard>),
     FileNode(SetRequest<'x, FileNode>),
@@ -43,6 +43,7 @@ use crate::{
         participant_identity::ParticipantIdentity,
         principal::Principal,
         push_subscription::PushSubscription,
+        quarantined_email::QuarantinedEmail,
         quota::Quota,
         share_notification::ShareNotification,
         sieve::Sieve,
@@ -84,6 +85,7 @@ pub enum GetResponseMethod {
     PushSubscription(GetResponse<PushSubscription>),
     Sieve(GetResponse<Sieve>),
     VacationResponse(GetResponse<VacationResponse>),
+    QuarantinedEmail(GetResponse<QuarantinedEmail>),
     Principal(GetResponse<Principal>),
     PrincipalAvailability(GetAvailabilityResponse),
     Quota(GetResponse<Quota>),
@@ -108,6 +110,7 @@ pub enum SetResponseMethod {
     PushSubscription(SetResponse<PushSubscription>),
     Sieve(SetResponse<Sieve>),
     VacationResponse(SetResponse<VacationResponse>),
+    QuarantinedEmail(SetResponse<QuarantinedEmail>),
     AddressBook(SetResponse<AddressBook>),
     ContactCard(SetResponse<ContactCard>),
     FileNode(SetResponse<FileNode>),
@@ -265,6 +268,12 @@ impl<'x> From<GetResponse<VacationResponse>> for ResponseMethod<'x> {
     }
 }
 
+impl<'x> From<GetResponse<QuarantinedEmail>> for ResponseMethod<'x> {
+    fn from(value: GetResponse<QuarantinedEmail>) -> Self {
+        ResponseMethod::Get(GetResponseMethod::QuarantinedEmail(value))
+    }
+}
+
 impl<'x> From<GetResponse<Principal>> for ResponseMethod<'x> {
     fn from(value: GetResponse<Principal>) -> Self {
         ResponseMethod::Get(GetResponseMethod::Principal(value))
@@ -337,6 +346,12 @@ impl<'x> From<SetResponse<VacationResponse>> for ResponseMethod<'x> {
     }
 }
 
+impl<'x> From<SetResponse<QuarantinedEmail>> for ResponseMethod<'x> {
+    fn from(value: SetResponse<QuarantinedEmail>) -> Self {
+        ResponseMethod::Set(SetResponseMethod::QuarantinedEmail(value))
+    }
+}
+
 impl<'x> From<SetResponse<AddressBook>> for ResponseMethod<'x> {
     fn from(value: SetResponse<AddressBook>) -> Self {
         ResponseMethod::Set(SetResponseMethod::AddressBook(value))
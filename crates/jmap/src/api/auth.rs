@@ -64,6 +64,7 @@ impl JmapAuthorization for AccessToken {
                 GetRequestMethod::PushSubscription(_) => Permission::JmapPushSubscriptionGet,
                 GetRequestMethod::Sieve(_) => Permission::JmapSieveScriptGet,
                 GetRequestMethod::VacationResponse(_) => Permission::JmapVacationResponseGet,
+                GetRequestMethod::QuarantinedEmail(_) => Permission::JmapEmailGet,
                 GetRequestMethod::Principal(_) => Permission::JmapPrincipalGet,
                 GetRequestMethod::Quota(_) => Permission::JmapQuotaGet,
                 GetRequestMethod::Blob(_) => Permission::JmapBlobGet,
@@ -89,6 +90,7 @@ impl JmapAuthorization for AccessToken {
                 SetRequestMethod::PushSubscription(_) => Permission::JmapPushSubscriptionSet,
                 SetRequestMethod::Sieve(_) => Permission::JmapSieveScriptSet,
                 SetRequestMethod::VacationResponse(_) => Permission::JmapVacationResponseSet,
+                SetRequestMethod::QuarantinedEmail(_) => Permission::JmapEmailSet,
                 SetRequestMethod::AddressBook(_) => Permission::JmapAddressBookSet,
                 SetRequestMethod::ContactCard(_) => Permission::JmapContactCardSet,
                 SetRequestMethod::FileNode(_) => Permission::JmapFileNodeSet,
@@ -122,6 +124,7 @@ impl JmapAuthorization for AccessToken {
                 | MethodObject::PushSubscription
                 | MethodObject::SearchSnippet
                 | MethodObject::VacationResponse
+                | MethodObject::QuarantinedEmail
                 | MethodObject::SieveScript
                 | MethodObject::AddressBook => Permission::JmapEmailChanges,
             },
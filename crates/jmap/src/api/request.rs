@@ -32,6 +32,7 @@ use crate::{
     participant_identity::{get::ParticipantIdentityGet, set::ParticipantIdentitySet},
     principal::{availability::PrincipalGetAvailability, get::PrincipalGet, query::PrincipalQuery},
     push::{get::PushSubscriptionFetch, set::PushSubscriptionSet},
+    quarantine::{get::QuarantinedEmailGet, set::QuarantinedEmailSet},
     quota::{get::QuotaGet, query::QuotaQuery},
     share_notification::{
         get::ShareNotificationGet, query::ShareNotificationQuery, set::ShareNotificationSet,
@@ -143,6 +144,9 @@ impl RequestHandler for Server {
                                     SetResponseMethod::VacationResponse(set_response) => {
                                         set_response.update_created_ids(&mut response);
                                     }
+                                    SetResponseMethod::QuarantinedEmail(set_response) => {
+                                        set_response.update_created_ids(&mut response);
+                                    }
                                     SetResponseMethod::AddressBook(set_response) => {
                                         set_response.update_created_ids(&mut response);
                                     }
@@ -274,6 +278,12 @@ impl RequestHandler for Server {
 
                     self.vacation_response_get(req).await?.into()
                 }
+                GetRequestMethod::QuarantinedEmail(mut req) => {
+                    set_account_id_if_missing(&mut req.account_id, access_token);
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.quarantined_email_get(req).await?.into()
+                }
                 GetRequestMethod::Principal(req) => {
                     self.principal_get(req, access_token).await?.into()
                 }
@@ -457,6 +467,12 @@ impl RequestHandler for Server {
 
                     self.vacation_response_set(req, access_token).await?.into()
                 }
+                SetRequestMethod::QuarantinedEmail(mut req) => {
+                    set_account_id_if_missing(&mut req.account_id, access_token);
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.quarantined_email_set(req).await?.into()
+                }
                 SetRequestMethod::AddressBook(mut req) => {
                     set_account_id_if_missing(&mut req.account_id, access_token);
                     access_token.assert_has_access(req.account_id, Collection::AddressBook)?;
@@ -11,6 +11,7 @@ use jmap_proto::request::capability::{
 };
 use std::future::Future;
 use std::sync::Arc;
+use store::ahash::AHashMap;
 use types::id::Id;
 use utils::map::vec_map::VecMap;
 
@@ -41,13 +42,16 @@ impl SessionHandler for Server {
             is_read_only: false,
             account_capabilities: VecMap::with_capacity(account_capabilities.len()),
         };
-        for capability in access_token.account_capabilities() {
+        for capability in access_token.account_capabilities(account_capabilities) {
             session.primary_accounts.append(capability, account_id);
             account.account_capabilities.append(
                 capability,
                 account_capabilities
                     .get(&capability)
-                    .map(|v| v.to_account_capabilities(account_id.into(), true))
+                    .map(|v| {
+                        v.to_account_capabilities(account_id.into(), true)
+                            .with_undo_send_delay(access_token.undo_send_delay)
+                    })
                     .unwrap_or_else(|| Capabilities::Empty(EmptyCapabilities::default())),
             );
         }
@@ -74,12 +78,15 @@ impl SessionHandler for Server {
                 is_read_only: false,
                 account_capabilities: VecMap::with_capacity(account_capabilities.len()),
             };
-            for capability in access_token.account_capabilities() {
+            for capability in access_token.account_capabilities(account_capabilities) {
                 account.account_capabilities.append(
                     capability,
                     account_capabilities
                         .get(&capability)
-                        .map(|v| v.to_account_capabilities(account_id.into(), is_owner))
+                        .map(|v| {
+                            v.to_account_capabilities(account_id.into(), is_owner)
+                                .with_undo_send_delay(access_token.undo_send_delay)
+                        })
                         .unwrap_or_else(|| Capabilities::Empty(EmptyCapabilities::default())),
                 );
             }
@@ -91,11 +98,17 @@ impl SessionHandler for Server {
 }
 
 trait AccountCapabilities {
-    fn account_capabilities(&self) -> impl Iterator<Item = Capability>;
+    fn account_capabilities<'x>(
+        &'x self,
+        enabled: &'x AHashMap<Capability, Capabilities>,
+    ) -> impl Iterator<Item = Capability> + 'x;
 }
 
 impl AccountCapabilities for AccessToken {
-    fn account_capabilities(&self) -> impl Iterator<Item = Capability> {
+    fn account_capabilities<'x>(
+        &'x self,
+        enabled: &'x AHashMap<Capability, Capabilities>,
+    ) -> impl Iterator<Item = Capability> + 'x {
         Capability::all_capabilities()
             .iter()
             .filter(move |capability| {
@@ -111,10 +124,15 @@ impl AccountCapabilities for AccessToken {
                     Capability::Blob => Permission::JmapBlobGet,
                     Capability::Quota => Permission::JmapQuotaGet,
                     Capability::FileNode => Permission::JmapFileNodeGet,
+                    Capability::Quarantine if enabled.contains_key(capability) => {
+                        Permission::JmapEmailGet
+                    }
                     Capability::WebSocket
                     | Capability::Principals
                     | Capability::PrincipalsAvailability => return true,
-                    Capability::Core | Capability::PrincipalsOwner => return false,
+                    Capability::Core | Capability::PrincipalsOwner | Capability::Quarantine => {
+                        return false;
+                    }
                 };
                 self.has_permission(permission)
             })
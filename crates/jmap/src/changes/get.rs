@@ -289,6 +289,7 @@ impl IntermediateChangesResponse {
             | MethodObject::PushSubscription
             | MethodObject::SearchSnippet
             | MethodObject::VacationResponse
+            | MethodObject::QuarantinedEmail
             | MethodObject::SieveScript
             | MethodObject::Principal
             | MethodObject::Quota => unreachable!(),
@@ -10,8 +10,12 @@ use crate::{
 use common::{Server, auth::AccessToken};
 use email::{
     cache::{MessageCacheFetch, mailbox::MailboxCacheAccess},
+    hooks::IngestSource as HookSource,
     mailbox::JUNK_ID,
-    message::ingest::{EmailIngest, IngestEmail, IngestSource},
+    message::{
+        delivery_hooks::{schedule_hook_tasks, try_ingest_hook},
+        ingest::{EmailIngest, IngestEmail, IngestSource},
+    },
 };
 use http_proto::HttpSessionData;
 use jmap_proto::{
@@ -143,29 +147,84 @@ impl EmailImport for Server {
                 }
             };
 
+            // Run the delivery hooks configured for JMAP
+            let train_classifier = email
+                .keywords
+                .iter()
+                .any(|k| matches!(k, Keyword::Junk | Keyword::NotJunk))
+                || mailbox_ids.contains(&JUNK_ID);
+            let mut mailbox_ids = mailbox_ids;
+            let mut keywords = email.keywords;
+            let hook_outcome = match try_ingest_hook(
+                self,
+                account_id,
+                HookSource::Jmap,
+                &raw_message,
+                &mut mailbox_ids,
+                &mut keywords,
+                session.session_id,
+            )
+            .await
+            {
+                Ok(Some(hook_outcome)) => hook_outcome,
+                Ok(None) => {
+                    response.not_created.append(
+                        id,
+                        SetError::forbidden()
+                            .with_description("Message discarded by delivery hook."),
+                    );
+                    continue;
+                }
+                Err(mut err)
+                    if err.matches(trc::EventType::MessageIngest(
+                        trc::MessageIngestEvent::Error,
+                    )) =>
+                {
+                    response.not_created.append(
+                        id,
+                        SetError::forbidden().with_description(
+                            err.take_value(trc::Key::Reason)
+                                .and_then(|v| v.into_string())
+                                .unwrap_or_else(|| "Message rejected by delivery hook.".into()),
+                        ),
+                    );
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            let (raw_message, blob_hash) = match &hook_outcome.raw_message {
+                Some(raw_message) => (raw_message.as_slice(), None),
+                None => (raw_message.as_slice(), Some(&blob_id.hash)),
+            };
+
             // Import message
             match self
                 .email_ingest(IngestEmail {
-                    raw_message: &raw_message,
-                    message: MessageParser::new().parse(&raw_message),
-                    blob_hash: Some(&blob_id.hash),
+                    raw_message,
+                    message: MessageParser::new().parse(raw_message),
+                    blob_hash,
                     access_token: import_access_token.as_deref().unwrap_or(access_token),
-                    source: IngestSource::Jmap {
-                        train_classifier: email
-                            .keywords
-                            .iter()
-                            .any(|k| matches!(k, Keyword::Junk | Keyword::NotJunk))
-                            || mailbox_ids.contains(&JUNK_ID),
-                    },
+                    source: IngestSource::Jmap { train_classifier },
                     mailbox_ids,
-                    keywords: email.keywords,
+                    keywords,
                     received_at: email.received_at.map(|r| r.into()),
                     session_id: session.session_id,
-                    preview_text: None,
+                    preview_text: hook_outcome.preview_text,
                 })
                 .await
             {
                 Ok(email) => {
+                    schedule_hook_tasks(
+                        self,
+                        account_id,
+                        &email,
+                        HookSource::Jmap,
+                        None,
+                        hook_outcome.expires_in,
+                        hook_outcome.audit,
+                        session.session_id,
+                    )
+                    .await;
                     response
                         .created
                         .append(id, ingested_into_object(email).into());
@@ -21,6 +21,7 @@ pub mod mailbox;
 pub mod participant_identity;
 pub mod principal;
 pub mod push;
+pub mod quarantine;
 pub mod quota;
 pub mod share_notification;
 pub mod sieve;
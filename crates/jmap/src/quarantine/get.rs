@@ -0,0 +1,109 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use email::message::quarantine::{EmailQuarantine, QuarantineReason};
+use jmap_proto::{
+    method::get::{GetRequest, GetResponse},
+    object::quarantined_email::{
+        QuarantinedEmail, QuarantinedEmailProperty, QuarantinedEmailValue,
+    },
+    types::{date::UTCDate, state::State},
+};
+use jmap_tools::{Map, Value};
+use std::future::Future;
+use trc::AddContext;
+use types::id::Id;
+
+pub trait QuarantinedEmailGet: Sync + Send {
+    fn quarantined_email_get(
+        &self,
+        request: GetRequest<QuarantinedEmail>,
+    ) -> impl Future<Output = trc::Result<GetResponse<QuarantinedEmail>>> + Send;
+}
+
+impl QuarantinedEmailGet for Server {
+    async fn quarantined_email_get(
+        &self,
+        mut request: GetRequest<QuarantinedEmail>,
+    ) -> trc::Result<GetResponse<QuarantinedEmail>> {
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            QuarantinedEmailProperty::Id,
+            QuarantinedEmailProperty::Sender,
+            QuarantinedEmailProperty::Recipient,
+            QuarantinedEmailProperty::From,
+            QuarantinedEmailProperty::Subject,
+            QuarantinedEmailProperty::Preview,
+            QuarantinedEmailProperty::Size,
+            QuarantinedEmailProperty::ReceivedAt,
+            QuarantinedEmailProperty::ExpiresAt,
+            QuarantinedEmailProperty::Reason,
+            QuarantinedEmailProperty::Released,
+        ]);
+        let account_id = request.account_id.document_id();
+        let messages = self
+            .quarantine_list(Some(account_id))
+            .await
+            .caused_by(trc::location!())?;
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            messages
+                .iter()
+                .take(self.core.jmap.get_max_objects)
+                .map(|message| Id::new(message.id))
+                .collect()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: State::Initial.into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            let Some(message) = messages.iter().find(|message| message.id == id.id()) else {
+                response.not_found.push(id);
+                continue;
+            };
+
+            let mut result = Map::with_capacity(properties.len());
+            for property in &properties {
+                let value = match property {
+                    QuarantinedEmailProperty::Id => Value::Element(id.into()),
+                    QuarantinedEmailProperty::Sender => message.sender.clone().into(),
+                    QuarantinedEmailProperty::Recipient => message.recipient.clone().into(),
+                    QuarantinedEmailProperty::From => message.from.clone().into(),
+                    QuarantinedEmailProperty::Subject => message.subject.clone().into(),
+                    QuarantinedEmailProperty::Preview => message.preview.clone().into(),
+                    QuarantinedEmailProperty::Size => (message.size as u64).into(),
+                    QuarantinedEmailProperty::ReceivedAt => {
+                        Value::Element(QuarantinedEmailValue::Date(UTCDate::from_timestamp(
+                            message.received as i64,
+                        )))
+                    }
+                    QuarantinedEmailProperty::ExpiresAt => {
+                        Value::Element(QuarantinedEmailValue::Date(UTCDate::from_timestamp(
+                            message.expires as i64,
+                        )))
+                    }
+                    QuarantinedEmailProperty::Reason => match message.reason {
+                        QuarantineReason::Hook => "hook",
+                        QuarantineReason::Spam => "spam",
+                    }
+                    .to_string()
+                    .into(),
+                    QuarantinedEmailProperty::Released => Value::Bool(false),
+                };
+                result.insert_unchecked(property.clone(), value);
+            }
+            response.list.push(result.into());
+        }
+
+        Ok(response)
+    }
+}
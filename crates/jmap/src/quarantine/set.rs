@@ -0,0 +1,95 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use email::message::quarantine::EmailQuarantine;
+use jmap_proto::{
+    error::set::SetError,
+    method::set::{SetRequest, SetResponse},
+    object::quarantined_email::{QuarantinedEmail, QuarantinedEmailProperty},
+    request::IntoValid,
+};
+use jmap_tools::{Key, Value};
+use std::future::Future;
+use trc::AddContext;
+
+pub trait QuarantinedEmailSet: Sync + Send {
+    fn quarantined_email_set(
+        &self,
+        request: SetRequest<'_, QuarantinedEmail>,
+    ) -> impl Future<Output = trc::Result<SetResponse<QuarantinedEmail>>> + Send;
+}
+
+impl QuarantinedEmailSet for Server {
+    async fn quarantined_email_set(
+        &self,
+        mut request: SetRequest<'_, QuarantinedEmail>,
+    ) -> trc::Result<SetResponse<QuarantinedEmail>> {
+        let account_id = request.account_id.document_id();
+        let mut response = SetResponse::from_request(&request, self.core.jmap.set_max_objects)?;
+        let will_destroy = request.unwrap_destroy().into_valid().collect::<Vec<_>>();
+
+        // Quarantined messages are only created by the delivery pipeline
+        for (id, _) in request.unwrap_create() {
+            response.not_created.append(
+                id,
+                SetError::forbidden().with_description("Quarantined messages cannot be created."),
+            );
+        }
+
+        // Releasing a message delivers it to the Inbox
+        'update: for (id, object) in request.unwrap_update().into_valid() {
+            if will_destroy.contains(&id) {
+                response.not_updated.append(id, SetError::will_destroy());
+                continue 'update;
+            }
+
+            let mut release = false;
+            for (property, value) in object.into_expanded_object() {
+                match (&property, value) {
+                    (Key::Property(QuarantinedEmailProperty::Released), Value::Bool(value)) => {
+                        release = value;
+                    }
+                    _ => {
+                        response.not_updated.append(
+                            id,
+                            SetError::invalid_properties()
+                                .with_property(property.to_owned())
+                                .with_description("Field could not be set."),
+                        );
+                        continue 'update;
+                    }
+                }
+            }
+
+            if !release
+                || self
+                    .quarantine_release(account_id, id.id())
+                    .await
+                    .caused_by(trc::location!())?
+            {
+                response.updated.append(id, None);
+            } else {
+                response.not_updated.append(id, SetError::not_found());
+            }
+        }
+
+        // Process deletions
+        for id in will_destroy {
+            if self
+                .quarantine_delete(account_id, id.id())
+                .await
+                .caused_by(trc::location!())?
+            {
+                response.destroyed.push(id);
+            } else {
+                response.not_destroyed.append(id, SetError::not_found());
+            }
+        }
+
+        Ok(response)
+    }
+}
@@ -170,7 +170,8 @@ impl EmailSubmissionGet for Server {
                             displayed: false,
                         };
                 }
-                is_pending = true;
+                is_pending = matches!(submission.undo_status, ArchivedUndoStatus::Pending)
+                    && u64::from(submission.send_at) > now();
             }
 
             let mut result = Map::with_capacity(properties.len());
@@ -219,10 +220,14 @@ impl EmailSubmissionGet for Server {
                             email_submission::UndoStatus::Pending
                         } else {
                             match submission.undo_status {
-                                ArchivedUndoStatus::Pending => {
+                                ArchivedUndoStatus::Pending
+                                    if u64::from(submission.send_at) > now() =>
+                                {
                                     email_submission::UndoStatus::Pending
                                 }
-                                ArchivedUndoStatus::Final => email_submission::UndoStatus::Final,
+                                ArchivedUndoStatus::Pending | ArchivedUndoStatus::Final => {
+                                    email_submission::UndoStatus::Final
+                                }
                                 ArchivedUndoStatus::Canceled => {
                                     email_submission::UndoStatus::Canceled
                                 }
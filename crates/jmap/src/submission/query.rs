@@ -51,6 +51,7 @@ impl EmailSubmissionQuery for Server {
 
         let mut submissions = Vec::with_capacity(16);
         let mut document_ids = RoaringBitmap::new();
+        let current_time = now();
 
         self.store()
             .iterate(
@@ -61,7 +62,7 @@ impl EmailSubmissionQuery for Server {
                         document_id: 0,
                         class: ValueClass::IndexProperty(IndexPropertyClass::Integer {
                             property: EmailSubmissionField::Metadata.into(),
-                            value: now() - (3 * 86400),
+                            value: current_time - (3 * 86400),
                         }),
                     },
                     ValueKey {
@@ -77,14 +78,21 @@ impl EmailSubmissionQuery for Server {
                 .ascending(),
                 |key, value| {
                     let document_id = key.deserialize_be_u32(key.len() - U32_LEN)?;
+                    let send_at = key.deserialize_be_u64(key.len() - U32_LEN - U64_LEN)?;
+
+                    // Held submissions become final once released
+                    let mut undo_status = value.last().copied().unwrap();
+                    if undo_status == UndoStatus::Pending.as_index() && send_at <= current_time {
+                        undo_status = UndoStatus::Final.as_index();
+                    }
 
                     submissions.push(Submission {
                         document_id,
-                        send_at: key.deserialize_be_u64(key.len() - U32_LEN - U64_LEN)?,
+                        send_at,
                         email_id: value.deserialize_be_u32(0)?,
                         thread_id: value.deserialize_be_u32(U32_LEN)?,
                         identity_id: value.deserialize_be_u32(U32_LEN + U32_LEN)?,
-                        undo_status: value.last().copied().unwrap(),
+                        undo_status,
                     });
 
                     document_ids.insert(document_id);
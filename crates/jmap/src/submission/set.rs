@@ -166,7 +166,17 @@ impl EmailSubmissionSet for Server {
 
             match undo_status {
                 Some(email_submission::UndoStatus::Canceled) => {
-                    if let Some(queue_message) =
+                    // Submissions can only be cancelled until they are released
+                    if submission.inner.undo_status != UndoStatus::Pending
+                        || submission.inner.send_at <= now()
+                    {
+                        response.not_updated.append(
+                            id,
+                            SetError::new(SetErrorType::CannotUnsend).with_description(
+                                "The submission has already been released for delivery.",
+                            ),
+                        );
+                    } else if let Some(queue_message) =
                         self.read_message(queue_id, QueueName::default()).await
                     {
                         // Delete message from queue
@@ -332,6 +342,7 @@ impl EmailSubmissionSet for Server {
         };
         let mut mail_from: Option<MailFrom<Cow<'_, str>>> = None;
         let mut rcpt_to: Vec<RcptTo<Cow<'_, str>>> = Vec::new();
+        let mut send_at = None;
 
         for (property, mut value) in object.into_expanded_object() {
             if let Err(err) = response.resolve_self_references(&mut value) {
@@ -443,6 +454,15 @@ impl EmailSubmissionSet for Server {
                 (Key::Property(EmailSubmissionProperty::Envelope), Value::Null) => {
                     continue;
                 }
+                (
+                    Key::Property(EmailSubmissionProperty::SendAt),
+                    Value::Element(EmailSubmissionValue::Date(value)),
+                ) => {
+                    send_at = Some(value.timestamp().max(0) as u64);
+                }
+                (Key::Property(EmailSubmissionProperty::SendAt), Value::Null) => {
+                    continue;
+                }
                 (Key::Property(EmailSubmissionProperty::UndoStatus), Value::Element(_)) => {
                     continue;
                 }
@@ -488,7 +508,7 @@ impl EmailSubmissionSet for Server {
         };
 
         // Make sure the envelope address matches the identity email address
-        let mail_from = if let Some(mail_from) = mail_from {
+        let mut mail_from = if let Some(mail_from) = mail_from {
             if !mail_from.address.eq_ignore_ascii_case(&identity_mail_from) {
                 return Ok(Err(SetError::new(SetErrorType::ForbiddenFrom)
                     .with_description(
@@ -507,6 +527,19 @@ impl EmailSubmissionSet for Server {
             }
         };
 
+        // Hold the message in the queue until sendAt
+        if let Some(send_at) = send_at {
+            if mail_from.hold_for != 0 || mail_from.hold_until != 0 {
+                return Ok(Err(SetError::invalid_properties()
+                    .with_property(EmailSubmissionProperty::SendAt)
+                    .with_description(
+                        "sendAt cannot be combined with FUTURERELEASE parameters.",
+                    )));
+            }
+            mail_from.hold_until = send_at;
+        }
+        let hold_until = mail_from.hold_until;
+
         // Obtain message metadata
         let metadata_ = if let Some(metadata) = self
             .store()
@@ -600,15 +633,6 @@ impl EmailSubmissionSet for Server {
                 .find(|header| matches!(header.name, ArchivedMetadataHeaderName::Bcc));
         }
 
-        // Update sendAt
-        submission.send_at = if mail_from.hold_until > 0 {
-            mail_from.hold_until
-        } else if mail_from.hold_for > 0 {
-            mail_from.hold_for + now()
-        } else {
-            now()
-        };
-
         // Obtain raw message
         let mut message = if let Some(message) = self
             .blob_store()
@@ -663,6 +687,29 @@ impl EmailSubmissionSet for Server {
                     .with_description(format!("Server rejected MAIL-FROM: {}", error.trim())));
             }
 
+            // Hold the message for the account's undo-send delay, or the server default
+            if session.data.future_release == 0 {
+                let account_delay = session
+                    .data
+                    .authenticated_as
+                    .as_ref()
+                    .and_then(|access_token| access_token.undo_send_delay);
+                session.data.future_release = if let Some(undo_delay) = account_delay {
+                    undo_delay
+                } else {
+                    session
+                        .server
+                        .eval_if::<Duration, _>(
+                            &session.server.core.smtp.session.extensions.undo_delay,
+                            &session,
+                            session.data.session_id,
+                        )
+                        .await
+                        .map_or(0, |undo_delay| undo_delay.as_secs())
+                };
+            }
+            let future_release = session.data.future_release;
+
             // RCPT TO
             let mut responses = Vec::new();
             let mut has_success = false;
@@ -682,7 +729,7 @@ impl EmailSubmissionSet for Server {
                 session.data.message = message;
                 let response = session.queue_message().await;
                 if let smtp::core::State::Accepted(queue_id) = session.state {
-                    Ok((true, responses, Some(queue_id)))
+                    Ok((true, responses, Some(queue_id), future_release))
                 } else {
                     Err(
                         SetError::new(SetErrorType::ForbiddenToSend).with_description(format!(
@@ -692,22 +739,29 @@ impl EmailSubmissionSet for Server {
                     )
                 }
             } else {
-                Ok((false, responses, None))
+                Ok((false, responses, None, future_release))
             }
         });
 
         match handle.await {
-            Ok(Ok((has_success, responses, queue_id))) => {
+            Ok(Ok((has_success, responses, queue_id, future_release))) => {
                 // Set queue ID
                 if let Some(queue_id) = queue_id {
                     submission.queue_id = Some(queue_id);
                 }
 
-                // Set responses
-                submission.undo_status = if has_success {
-                    UndoStatus::Final
+                // Update sendAt
+                submission.send_at = if hold_until > 0 && future_release > 0 {
+                    hold_until
                 } else {
+                    now() + future_release
+                };
+
+                // Set responses, held messages can be canceled until released
+                submission.undo_status = if has_success && submission.send_at > now() {
                     UndoStatus::Pending
+                } else {
+                    UndoStatus::Final
                 };
                 submission.delivery_status = responses
                     .into_iter()
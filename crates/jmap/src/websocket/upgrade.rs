@@ -35,7 +35,28 @@ impl WebSocketUpgrade for Server {
         session: HttpSessionData,
     ) -> trc::Result<HttpResponse> {
         let headers = req.headers();
-        if headers
+        let response = if req.method() == hyper::Method::CONNECT {
+            // HTTP/2 extended CONNECT (RFC 8441)
+            if req
+                .extensions()
+                .get::<hyper::ext::Protocol>()
+                .is_none_or(|protocol| protocol.as_str() != "websocket")
+                || headers
+                    .get("Sec-WebSocket-Version")
+                    .and_then(|h| h.to_str().ok())
+                    != Some("13")
+            {
+                return Err(trc::ResourceEvent::BadParameters
+                    .into_err()
+                    .details("WebSocket upgrade failed")
+                    .ctx(
+                        trc::Key::Reason,
+                        "Missing or Invalid :protocol or Sec-WebSocket-Version headers.",
+                    ));
+            }
+
+            HttpResponse::new(StatusCode::OK).with_header("Sec-WebSocket-Protocol", "jmap")
+        } else if headers
             .get(hyper::header::CONNECTION)
             .and_then(|h| h.to_str().ok())
             != Some("Upgrade")
@@ -51,24 +72,26 @@ impl WebSocketUpgrade for Server {
                     trc::Key::Reason,
                     "Missing or Invalid Connection or Upgrade headers.",
                 ));
-        }
-        let derived_key = match (
-            headers
-                .get("Sec-WebSocket-Key")
-                .and_then(|h| h.to_str().ok()),
-            headers
-                .get("Sec-WebSocket-Version")
-                .and_then(|h| h.to_str().ok()),
-        ) {
-            (Some(key), Some("13")) => derive_accept_key(key.as_bytes()),
-            _ => {
-                return Err(trc::ResourceEvent::BadParameters
-                    .into_err()
-                    .details("WebSocket upgrade failed")
-                    .ctx(
-                        trc::Key::Reason,
-                        "Missing or Invalid Sec-WebSocket-Key headers.",
-                    ));
+        } else {
+            match (
+                headers
+                    .get("Sec-WebSocket-Key")
+                    .and_then(|h| h.to_str().ok()),
+                headers
+                    .get("Sec-WebSocket-Version")
+                    .and_then(|h| h.to_str().ok()),
+            ) {
+                (Some(key), Some("13")) => HttpResponse::new(StatusCode::SWITCHING_PROTOCOLS)
+                    .with_websocket_upgrade(derive_accept_key(key.as_bytes())),
+                _ => {
+                    return Err(trc::ResourceEvent::BadParameters
+                        .into_err()
+                        .details("WebSocket upgrade failed")
+                        .ctx(
+                            trc::Key::Reason,
+                            "Missing or Invalid Sec-WebSocket-Key headers.",
+                        ));
+                }
             }
         };
 
@@ -104,6 +127,6 @@ impl WebSocketUpgrade for Server {
             }
         });
 
-        Ok(HttpResponse::new(StatusCode::SWITCHING_PROTOCOLS).with_websocket_upgrade(derived_key))
+        Ok(response)
     }
 }
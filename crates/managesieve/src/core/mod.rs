@@ -11,7 +11,7 @@ use std::{borrow::Cow, net::IpAddr, sync::Arc};
 
 use common::{
     Inner, Server,
-    auth::AccessToken,
+    auth::{AccessToken, scram::ScramStep},
     listener::{ServerInstance, limiter::InFlight},
 };
 
@@ -28,6 +28,7 @@ pub struct Session<T: AsyncRead + AsyncWrite> {
     pub stream: T,
     pub session_id: u64,
     pub in_flight: InFlight,
+    pub scram: Option<ScramStep>,
 }
 
 pub enum State {
@@ -4,10 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::sync::Arc;
+
 use common::{
     auth::{
-        AuthRequest,
-        sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain},
+        AccessToken, AuthRequest,
+        sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain, sasl_encode_challenge},
+        scram::ScramStep,
     },
     listener::{SessionStream, limiter::LimiterResult},
 };
@@ -62,6 +65,9 @@ impl<T: SessionStream> Session<T> {
                     return Ok(b"{0}\r\n".to_vec());
                 }
             }
+            Mechanism::ScramSha256 | Mechanism::ScramSha256Plus => {
+                return self.handle_scram(mechanism, params.pop()).await;
+            }
             _ => {
                 return Err(trc::AuthEvent::Error
                     .into_err()
@@ -70,14 +76,95 @@ impl<T: SessionStream> Session<T> {
         };
 
         // Authenticate
-        let access_token = self
+        let result = self
             .server
-            .authenticate(&AuthRequest::from_credentials(
-                credentials,
-                self.session_id,
-                self.remote_addr,
-            ))
-            .await
+            .authenticate(
+                &AuthRequest::from_credentials(credentials, self.session_id, self.remote_addr)
+                    .with_peer_uid(self.stream.peer_credentials().map(|peer| peer.uid)),
+            )
+            .await;
+
+        self.handle_auth_result(result).await
+    }
+
+    async fn handle_scram(
+        &mut self,
+        mechanism: Mechanism,
+        response: Option<String>,
+    ) -> trc::Result<Vec<u8>> {
+        let response = match response {
+            Some(response) => Some(base64_decode(response.as_bytes()).ok_or_else(|| {
+                self.scram = None;
+                trc::AuthEvent::Error
+                    .into_err()
+                    .details("Failed to decode challenge.")
+            })?),
+            None => None,
+        };
+
+        match (self.scram.take(), response) {
+            (None, Some(response)) if !response.is_empty() => {
+                let auth = self
+                    .server
+                    .scram_begin(
+                        &response,
+                        mechanism == Mechanism::ScramSha256Plus,
+                        self.stream.tls_channel_binding().is_some(),
+                        None,
+                    )
+                    .await?;
+                let challenge = sasl_encode_challenge(auth.server_first());
+                self.scram = Some(ScramStep::ClientFinal(Box::new(auth)));
+                Ok(self.continue_authenticate(mechanism, &challenge))
+            }
+            (None, _) => Ok(self.continue_authenticate(mechanism, "")),
+            (Some(ScramStep::ClientFinal(auth)), Some(response)) => {
+                match self
+                    .server
+                    .scram_finish(
+                        *auth,
+                        &response,
+                        self.stream.tls_channel_binding(),
+                        self.session_id,
+                        self.remote_addr,
+                        self.stream.peer_credentials().map(|peer| peer.uid),
+                    )
+                    .await
+                {
+                    Ok((access_token, server_final)) => {
+                        self.scram = Some(ScramStep::Completed(access_token));
+                        Ok(self
+                            .continue_authenticate(mechanism, &sasl_encode_challenge(server_final)))
+                    }
+                    Err(err) => self.handle_auth_result(Err(err)).await,
+                }
+            }
+            (Some(ScramStep::Completed(access_token)), response)
+                if response.as_ref().is_none_or(|response| response.is_empty()) =>
+            {
+                self.handle_auth_result(Ok(access_token)).await
+            }
+            _ => Err(trc::AuthEvent::Error
+                .into_err()
+                .details("Invalid SASL challenge.")),
+        }
+    }
+
+    fn continue_authenticate(&mut self, mechanism: Mechanism, challenge: &str) -> Vec<u8> {
+        self.receiver.request = receiver::Request {
+            tag: "".into(),
+            command: Command::Authenticate,
+            tokens: vec![receiver::Token::Argument(mechanism.into_bytes())],
+        };
+        self.receiver.state = receiver::State::Argument { last_ch: b' ' };
+        format!("\"{challenge}\"\r\n").into_bytes()
+    }
+
+    async fn handle_auth_result(
+        &mut self,
+        result: trc::Result<Arc<AccessToken>>,
+    ) -> trc::Result<Vec<u8>> {
+        let access_token = result
             .map_err(|err| {
                 if err.matches(trc::EventType::Auth(trc::AuthEvent::Failed)) {
                     match &self.state {
@@ -19,10 +19,14 @@ impl<T: SessionStream> Session<T> {
         if !self.stream.is_tls() {
             response.extend_from_slice(b"\"STARTTLS\"\r\n");
         }
+        response.extend_from_slice(b"\"SASL\" \"");
+        if self.stream.tls_channel_binding().is_some() {
+            response.extend_from_slice(b"SCRAM-SHA-256-PLUS ");
+        }
         if self.stream.is_tls() || self.server.core.imap.allow_plain_auth {
-            response.extend_from_slice(b"\"SASL\" \"PLAIN OAUTHBEARER XOAUTH2\"\r\n");
+            response.extend_from_slice(b"SCRAM-SHA-256 PLAIN OAUTHBEARER XOAUTH2\"\r\n");
         } else {
-            response.extend_from_slice(b"\"SASL\" \"OAUTHBEARER XOAUTH2\"\r\n");
+            response.extend_from_slice(b"SCRAM-SHA-256 OAUTHBEARER XOAUTH2\"\r\n");
         };
         if let Some(sieve) =
             self.server
@@ -8,7 +8,7 @@ use std::{net::IpAddr, sync::Arc};
 
 use common::{
     Inner, Server,
-    auth::AccessToken,
+    auth::{AccessToken, scram::ScramStep},
     listener::{ServerInstance, SessionStream, limiter::InFlight},
 };
 use mailbox::Mailbox;
@@ -42,6 +42,7 @@ pub struct Session<T: SessionStream> {
     pub in_flight: InFlight,
     pub remote_addr: IpAddr,
     pub session_id: u64,
+    pub scram: Option<ScramStep>,
 }
 
 pub enum State {
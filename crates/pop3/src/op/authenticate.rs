@@ -4,10 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::sync::Arc;
+
 use common::{
     auth::{
-        AuthRequest,
-        sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain},
+        AccessToken, AuthRequest,
+        sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain, sasl_encode_challenge},
+        scram::ScramStep,
     },
     listener::{SessionStream, limiter::LimiterResult},
 };
@@ -58,22 +61,100 @@ impl<T: SessionStream> Session<T> {
                     self.write_bytes("+\r\n").await
                 }
             }
+            Mechanism::ScramSha256 | Mechanism::ScramSha256Plus => {
+                let response = match params.pop() {
+                    Some(param) => Some(base64_decode(param.as_bytes()).ok_or_else(|| {
+                        self.scram = None;
+                        trc::AuthEvent::Error
+                            .into_err()
+                            .details("Invalid SASL challenge")
+                    })?),
+                    None => None,
+                };
+
+                match (self.scram.take(), response) {
+                    (None, Some(response)) => {
+                        let auth = self
+                            .server
+                            .scram_begin(
+                                &response,
+                                mechanism == Mechanism::ScramSha256Plus,
+                                self.stream.tls_channel_binding().is_some(),
+                                None,
+                            )
+                            .await?;
+                        let challenge = sasl_encode_challenge(auth.server_first());
+                        self.scram = Some(ScramStep::ClientFinal(Box::new(auth)));
+                        self.continue_sasl(mechanism, &challenge).await
+                    }
+                    (None, None) => self.continue_sasl(mechanism, "").await,
+                    (Some(ScramStep::ClientFinal(auth)), Some(response)) => {
+                        match self
+                            .server
+                            .scram_finish(
+                                *auth,
+                                &response,
+                                self.stream.tls_channel_binding(),
+                                self.session_id,
+                                self.remote_addr,
+                                self.stream.peer_credentials().map(|peer| peer.uid),
+                            )
+                            .await
+                        {
+                            Ok((access_token, server_final)) => {
+                                self.scram = Some(ScramStep::Completed(access_token));
+                                self.continue_sasl(mechanism, &sasl_encode_challenge(server_final))
+                                    .await
+                            }
+                            Err(err) => self.handle_auth_result(Err(err)).await,
+                        }
+                    }
+                    (Some(ScramStep::Completed(access_token)), None) => {
+                        self.handle_auth_result(Ok(access_token)).await
+                    }
+                    _ => Err(trc::AuthEvent::Error
+                        .into_err()
+                        .details("Invalid SASL challenge")),
+                }
+            }
             _ => Err(trc::AuthEvent::Error
                 .into_err()
                 .details("Authentication mechanism not supported.")),
         }
     }
 
+    async fn continue_sasl(&mut self, mechanism: Mechanism, challenge: &str) -> trc::Result<()> {
+        self.receiver.state = request::State::Argument {
+            request: Command::Auth {
+                mechanism: mechanism.as_str().as_bytes().to_vec(),
+                params: vec![],
+            },
+            num: 1,
+            last_is_space: true,
+        };
+
+        self.write_bytes(format!("+ {challenge}\r\n").into_bytes())
+            .await
+    }
+
     pub async fn handle_auth(&mut self, credentials: Credentials<String>) -> trc::Result<()> {
         // Authenticate
-        let access_token = self
+        let result = self
             .server
-            .authenticate(&AuthRequest::from_credentials(
-                credentials,
-                self.session_id,
-                self.remote_addr,
-            ))
-            .await
+            .authenticate(
+                &AuthRequest::from_credentials(credentials, self.session_id, self.remote_addr)
+                    .with_peer_uid(self.stream.peer_credentials().map(|peer| peer.uid)),
+            )
+            .await;
+
+        self.handle_auth_result(result).await
+    }
+
+    async fn handle_auth_result(
+        &mut self,
+        result: trc::Result<Arc<AccessToken>>,
+    ) -> trc::Result<()> {
+        let access_token = result
             .map_err(|err| {
                 if err.matches(trc::EventType::Auth(trc::AuthEvent::Failed)) {
                     match &self.state {
@@ -18,11 +18,16 @@ pub mod list;
 
 impl<T: SessionStream> Session<T> {
     pub async fn handle_capa(&mut self) -> trc::Result<()> {
-        let mechanisms = if self.stream.is_tls() || self.server.core.imap.allow_plain_auth {
-            vec![Mechanism::Plain, Mechanism::OAuthBearer, Mechanism::XOauth2]
+        let mut mechanisms = if self.stream.tls_channel_binding().is_some() {
+            vec![Mechanism::ScramSha256Plus, Mechanism::ScramSha256]
         } else {
-            vec![Mechanism::OAuthBearer, Mechanism::XOauth2]
+            vec![Mechanism::ScramSha256]
         };
+        if self.stream.is_tls() || self.server.core.imap.allow_plain_auth {
+            mechanisms.extend([Mechanism::Plain, Mechanism::OAuthBearer, Mechanism::XOauth2]);
+        } else {
+            mechanisms.extend([Mechanism::OAuthBearer, Mechanism::XOauth2]);
+        }
 
         trc::event!(
             Pop3(trc::Pop3Event::Capabilities),
@@ -64,6 +64,7 @@ pub enum Mechanism {
     DigestMd5,
     ScramSha1,
     ScramSha256,
+    ScramSha256Plus,
     Apop,
     Ntlm,
     Gssapi,
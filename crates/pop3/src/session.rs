@@ -41,6 +41,7 @@ impl SessionManager for Pop3SessionManager {
                 in_flight: session.in_flight,
                 remote_addr: session.remote_ip,
                 session_id: session.session_id,
+                scram: None,
             };
 
             if session
@@ -84,7 +85,7 @@ impl<T: SessionStream> Session<T> {
                                     SessionResult::UpgradeTls => {
                                         return true;
                                     }
-                                    SessionResult::Close => {
+                                    SessionResult::Close | SessionResult::UpgradeCompression => {
                                         break;
                                     }
                                 }
@@ -148,6 +149,7 @@ impl<T: SessionStream> Session<T> {
             session_id: self.session_id,
             in_flight: self.in_flight,
             remote_addr: self.remote_addr,
+            scram: None,
         })
     }
 }
@@ -11,7 +11,7 @@ use common::{
     ipc::{BroadcastEvent, HousekeeperEvent, PurgeType},
 };
 use email::message::delete::EmailDeletion;
-use smtp::reporting::SmtpReporting;
+use smtp::reporting::{SmtpReporting, quarantine::QuarantineReporting};
 use spam_filter::modules::classifier::SpamClassifier;
 use std::{
     collections::BinaryHeap,
@@ -57,6 +57,7 @@ enum ActionClass {
     RenewLicense,
     // SPDX-SnippetEnd
     TrainSpamClassifier,
+    QuarantineDigest,
 }
 
 #[derive(Default)]
@@ -125,6 +126,16 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                 );
             }
 
+            // Quarantine digests
+            if roles.purge_accounts.is_enabled_or_sharded()
+                && let Some(digest) = &server.core.smtp.quarantine.digest
+            {
+                queue.schedule(
+                    Instant::now() + digest.frequency.time_to_next(),
+                    ActionClass::QuarantineDigest,
+                );
+            }
+
             // OTEL Push Metrics
             if roles.push_metrics.is_enabled_or_sharded()
                 && let Some(otel) = &server.core.metrics.otel
@@ -215,6 +226,22 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                 _ => {}
                             }
 
+                            // Reload quarantine digests
+                            if server
+                                .core
+                                .network
+                                .roles
+                                .purge_accounts
+                                .is_enabled_or_sharded()
+                                && let Some(digest) = &server.core.smtp.quarantine.digest
+                                && !queue.has_action(&ActionClass::QuarantineDigest)
+                            {
+                                queue.schedule(
+                                    Instant::now() + digest.frequency.time_to_next(),
+                                    ActionClass::QuarantineDigest,
+                                );
+                            }
+
                             // SPDX-SnippetBegin
                             // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
                             // SPDX-License-Identifier: LicenseRef-SEL
@@ -600,6 +627,24 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                     });
                                 }
                             }
+                            ActionClass::QuarantineDigest => {
+                                if let Some(digest) = &server.core.smtp.quarantine.digest {
+                                    trc::event!(
+                                        Housekeeper(trc::HousekeeperEvent::Run),
+                                        Type = "quarantine_digest"
+                                    );
+
+                                    queue.schedule(
+                                        Instant::now() + digest.frequency.time_to_next(),
+                                        ActionClass::QuarantineDigest,
+                                    );
+
+                                    let server = server.clone();
+                                    tokio::spawn(async move {
+                                        server.quarantine_digest().await;
+                                    });
+                                }
+                            }
 
                             // SPDX-SnippetBegin
                             // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
@@ -715,6 +760,49 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
     });
 }
 
+trait QuarantineDigest: Sync + Send {
+    fn quarantine_digest(&self) -> impl Future<Output = ()> + Send;
+}
+
+impl QuarantineDigest for Server {
+    async fn quarantine_digest(&self) {
+        // Digests are sent by a single node, items are flagged once they were notified
+        let lock_name = b"quarantine-digest";
+        match self
+            .in_memory_store()
+            .try_lock(KV_LOCK_HOUSEKEEPER, lock_name, 3600)
+            .await
+        {
+            Ok(true) => (),
+            Ok(false) => {
+                return;
+            }
+            Err(err) => {
+                trc::error!(
+                    err.details("Failed to lock task.")
+                        .details("quarantine-digest")
+                );
+                return;
+            }
+        }
+
+        if let Err(err) = self.send_quarantine_digests().await {
+            trc::error!(err.details("Failed to send quarantine digests"));
+        }
+
+        if let Err(err) = self
+            .in_memory_store()
+            .remove_lock(KV_LOCK_HOUSEKEEPER, lock_name)
+            .await
+        {
+            trc::error!(
+                err.details("Failed to delete task lock.")
+                    .details("quarantine-digest")
+            );
+        }
+    }
+}
+
 pub trait Purge: Sync + Send {
     fn purge(&self, purge: PurgeType, store_idx: u32) -> impl Future<Output = ()> + Send;
 }
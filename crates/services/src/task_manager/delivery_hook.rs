@@ -0,0 +1,333 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, storage::index::ObjectIndexBuilder};
+use directory::backend::internal::manage::ManageDirectory;
+use email::{
+    cache::{MessageCacheFetch, mailbox::MailboxCacheAccess},
+    hooks::audit::DeliveryHookAudit,
+    mailbox::{INBOX_ID, UidMailbox},
+    message::{
+        delete::EmailDeletion,
+        delivery_hooks::{AsyncHookTask, try_async_delivery_hook},
+        ingest::EmailIngest,
+        metadata::{MessageData, MessageMetadata},
+        quarantine::{EmailQuarantine, QuarantineReason},
+    },
+};
+use mail_parser::MessageParser;
+use store::{
+    Serialize, ValueKey,
+    roaring::RoaringBitmap,
+    write::{
+        AlignedBytes, Archive, Archiver, BatchBuilder, TaskEpoch, TaskQueueClass, ValueClass, now,
+    },
+};
+use trc::AddContext;
+use types::{
+    blob_hash::BlobHash,
+    collection::{Collection, VanishedCollection},
+    field::EmailField,
+    keyword::Keyword,
+};
+
+pub trait DeliveryHookTask: Sync + Send {
+    fn run_delivery_hooks(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        task: &AsyncHookTask,
+    ) -> impl Future<Output = bool> + Send;
+}
+
+impl DeliveryHookTask for Server {
+    async fn run_delivery_hooks(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        task: &AsyncHookTask,
+    ) -> bool {
+        match run_delivery_hooks(self, account_id, document_id, task).await {
+            Ok(_) => true,
+            Err(err) => {
+                // Permanent failures are reported but not retried, the message was already delivered
+                let is_tempfail = err.value_as_uint(trc::Key::Code) != Some(550);
+                trc::error!(
+                    err.account_id(account_id)
+                        .document_id(document_id)
+                        .details("Failed to run asynchronous delivery hooks")
+                );
+                !is_tempfail
+            }
+        }
+    }
+}
+
+async fn run_delivery_hooks(
+    server: &Server,
+    account_id: u32,
+    document_id: u32,
+    task: &AsyncHookTask,
+) -> trc::Result<()> {
+    // Make sure the document id still refers to the same message
+    let Some(metadata_archive) = server
+        .store()
+        .get_value::<Archive<AlignedBytes>>(ValueKey::property(
+            account_id,
+            Collection::Email,
+            document_id,
+            EmailField::Metadata,
+        ))
+        .await
+        .caused_by(trc::location!())?
+    else {
+        return Ok(());
+    };
+    let metadata = metadata_archive
+        .to_unarchived::<MessageMetadata>()
+        .caused_by(trc::location!())?;
+    if BlobHash::from(&metadata.inner.blob_hash) != task.blob_hash {
+        return Ok(());
+    }
+
+    let Some(raw_message) = server
+        .blob_store()
+        .get_blob(task.blob_hash.as_slice(), 0..usize::MAX)
+        .await
+        .caused_by(trc::location!())?
+    else {
+        return Ok(());
+    };
+    let Some(parsed_message) = MessageParser::new().parse(&raw_message) else {
+        return Ok(());
+    };
+
+    let session_id = server.inner.data.span_id_gen.generate();
+    let Some(outcome) = try_async_delivery_hook(
+        server,
+        account_id,
+        task.source,
+        task.envelope.clone(),
+        &parsed_message,
+        session_id,
+    )
+    .await?
+    else {
+        // Discarded by a hook, delete the stored message
+        return delete_message(server, account_id, document_id).await;
+    };
+
+    // Move the message to quarantine, the same as a synchronous hook would
+    if outcome.quarantine && server.core.smtp.quarantine.enable {
+        let (sender, recipient) = task
+            .envelope
+            .as_ref()
+            .map(|envelope| (envelope.from.address.as_str(), envelope.to.address.as_str()))
+            .unwrap_or_default();
+        server
+            .quarantine_hold(
+                account_id,
+                sender,
+                recipient,
+                &raw_message,
+                QuarantineReason::Hook,
+                session_id,
+            )
+            .await
+            .caused_by(trc::location!())?;
+        return delete_message(server, account_id, document_id).await;
+    }
+
+    if !outcome.modifications.is_empty() || !outcome.redirects.is_empty() {
+        trc::event!(
+            DeliveryHook(trc::DeliveryHookEvent::Error),
+            AccountId = account_id,
+            DocumentId = document_id,
+            Details = "Message modifications and redirects are ignored by asynchronous hooks.",
+            SpanId = session_id
+        );
+    }
+
+    // Apply the changes to the stored message
+    let Some(data_archive) = server
+        .store()
+        .get_value::<Archive<AlignedBytes>>(ValueKey::archive(
+            account_id,
+            Collection::Email,
+            document_id,
+        ))
+        .await
+        .caused_by(trc::location!())?
+    else {
+        return Ok(());
+    };
+    let data = data_archive
+        .to_unarchived::<MessageData>()
+        .caused_by(trc::location!())?;
+    let mut new_data = data.inner.to_builder();
+
+    let cache = server
+        .get_cached_messages(account_id)
+        .await
+        .caused_by(trc::location!())?;
+    for mailbox_id in &outcome.mailbox_ids {
+        if cache.has_mailbox_id(mailbox_id)
+            && !new_data
+                .mailboxes
+                .iter()
+                .any(|m| m.mailbox_id == *mailbox_id)
+        {
+            new_data.add_mailbox(UidMailbox::new_unassigned(*mailbox_id));
+        }
+    }
+    if outcome.skip_inbox && new_data.mailboxes.len() > 1 {
+        new_data.remove_mailbox(INBOX_ID);
+    }
+    for flag in outcome.flags {
+        new_data.add_keyword(Keyword::from(flag));
+    }
+
+    let has_data_changes =
+        new_data.has_keyword_changes(data.inner) || new_data.has_mailbox_changes(data.inner);
+    let preview_text = outcome
+        .preview_text
+        .map(|text| text.replace('\r', ""))
+        .filter(|text| metadata.inner.preview.as_ref() != text);
+
+    if has_data_changes || preview_text.is_some() {
+        // Obtain IMAP UIDs for added mailboxes
+        let ids = server
+            .assign_email_ids(
+                account_id,
+                new_data
+                    .mailboxes
+                    .iter()
+                    .filter(|m| m.uid == 0)
+                    .map(|m| m.mailbox_id),
+                false,
+            )
+            .await
+            .caused_by(trc::location!())?;
+        for (uid_mailbox, uid) in new_data
+            .mailboxes
+            .iter_mut()
+            .filter(|m| m.uid == 0)
+            .zip(ids)
+        {
+            uid_mailbox.uid = uid;
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Email)
+            .with_document(document_id);
+        for mailbox in new_data.removed_mailboxes(data.inner) {
+            batch.log_vanished_item(
+                VanishedCollection::Email,
+                (mailbox.mailbox_id.to_native(), mailbox.uid.to_native()),
+            );
+        }
+        if let Some(preview_text) = preview_text {
+            let mut metadata = metadata_archive
+                .deserialize::<MessageMetadata>()
+                .caused_by(trc::location!())?;
+            metadata.preview = preview_text.into_boxed_str();
+            batch.set(
+                EmailField::Metadata,
+                Archiver::new(metadata)
+                    .serialize()
+                    .caused_by(trc::location!())?,
+            );
+        }
+
+        // The message data is always rewritten so that clients see the change
+        batch
+            .custom(
+                ObjectIndexBuilder::new()
+                    .with_current(data)
+                    .with_changes(new_data.seal()),
+            )
+            .caused_by(trc::location!())?;
+        server
+            .commit_batch(batch)
+            .await
+            .caused_by(trc::location!())?;
+    }
+
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(Collection::Email)
+        .with_document(document_id);
+    if !outcome.audit.is_empty() {
+        // Append this run to the audit trail recorded at delivery time
+        let mut audit = server
+            .store()
+            .get_value::<Archive<AlignedBytes>>(ValueKey::property(
+                account_id,
+                Collection::Email,
+                document_id,
+                EmailField::HookAudit,
+            ))
+            .await
+            .caused_by(trc::location!())?
+            .map(|archive| archive.deserialize::<DeliveryHookAudit>())
+            .transpose()
+            .caused_by(trc::location!())?
+            .unwrap_or_default();
+        audit.add_run(task.source, true, now(), outcome.audit);
+        batch.set(
+            EmailField::HookAudit,
+            Archiver::new(audit)
+                .serialize()
+                .caused_by(trc::location!())?,
+        );
+    }
+    if let Some(expires_in) = outcome.expires_in {
+        batch.set(
+            ValueClass::TaskQueue(TaskQueueClass::ExpireEmail {
+                due: TaskEpoch::new(now().saturating_add(expires_in)),
+            }),
+            task.blob_hash.as_slice().to_vec(),
+        );
+    }
+    if !batch.is_empty() {
+        server
+            .store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+        if outcome.expires_in.is_some() {
+            server.notify_task_queue();
+        }
+    }
+
+    Ok(())
+}
+
+async fn delete_message(server: &Server, account_id: u32, document_id: u32) -> trc::Result<()> {
+    let mut batch = BatchBuilder::new();
+    let tenant_id = server
+        .store()
+        .get_principal(account_id)
+        .await
+        .caused_by(trc::location!())?
+        .and_then(|p| p.tenant());
+    server
+        .emails_delete(
+            account_id,
+            tenant_id,
+            &mut batch,
+            RoaringBitmap::from_iter([document_id]),
+        )
+        .await?;
+    if !batch.is_empty() {
+        server.commit_batch(batch).await?;
+        server.notify_task_queue();
+    }
+    Ok(())
+}
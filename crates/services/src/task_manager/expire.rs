@@ -0,0 +1,93 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use directory::backend::internal::manage::ManageDirectory;
+use email::message::{delete::EmailDeletion, metadata::MessageMetadata};
+use store::{
+    ValueKey,
+    roaring::RoaringBitmap,
+    write::{AlignedBytes, Archive, BatchBuilder},
+};
+use trc::AddContext;
+use types::{blob_hash::BlobHash, collection::Collection, field::EmailField};
+
+pub trait ExpireEmailTask: Sync + Send {
+    fn expire_email(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        blob_hash: &BlobHash,
+    ) -> impl Future<Output = bool> + Send;
+}
+
+impl ExpireEmailTask for Server {
+    async fn expire_email(&self, account_id: u32, document_id: u32, blob_hash: &BlobHash) -> bool {
+        match expire_email(self, account_id, document_id, blob_hash).await {
+            Ok(_) => true,
+            Err(err) => {
+                trc::error!(
+                    err.account_id(account_id)
+                        .document_id(document_id)
+                        .details("Failed to expire e-mail")
+                );
+                false
+            }
+        }
+    }
+}
+
+async fn expire_email(
+    server: &Server,
+    account_id: u32,
+    document_id: u32,
+    blob_hash: &BlobHash,
+) -> trc::Result<()> {
+    // Make sure the document id still refers to the same message, it might have
+    // been deleted and reassigned before the expiry was due
+    let Some(archive) = server
+        .store()
+        .get_value::<Archive<AlignedBytes>>(ValueKey::property(
+            account_id,
+            Collection::Email,
+            document_id,
+            EmailField::Metadata,
+        ))
+        .await
+        .caused_by(trc::location!())?
+    else {
+        return Ok(());
+    };
+    let metadata = archive
+        .to_unarchived::<MessageMetadata>()
+        .caused_by(trc::location!())?;
+    if BlobHash::from(&metadata.inner.blob_hash) != *blob_hash {
+        return Ok(());
+    }
+
+    // Delete message
+    let mut batch = BatchBuilder::new();
+    let tenant_id = server
+        .store()
+        .get_principal(account_id)
+        .await
+        .caused_by(trc::location!())?
+        .and_then(|p| p.tenant());
+    server
+        .emails_delete(
+            account_id,
+            tenant_id,
+            &mut batch,
+            RoaringBitmap::from_iter([document_id]),
+        )
+        .await?;
+    if !batch.is_empty() {
+        server.commit_batch(batch).await?;
+        server.notify_task_queue();
+    }
+
+    Ok(())
+}
@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use crate::task_manager::delivery_hook::DeliveryHookTask;
+use crate::task_manager::expire::ExpireEmailTask;
 use crate::task_manager::imip::SendImipTask;
 use crate::task_manager::index::SearchIndexTask;
 use crate::task_manager::lock::{TaskLock, TaskLockManager};
@@ -14,6 +16,7 @@ use common::config::server::ServerProtocol;
 use common::listener::limiter::ConcurrencyLimiter;
 use common::listener::{ServerInstance, TcpAcceptor};
 use common::{Inner, KV_LOCK_TASK, Server, core::BuildServer};
+use email::message::delivery_hooks::AsyncHookTask;
 use email::message::ingest::MergeThreadIds;
 use groupware::calendar::alarm::{CalendarAlarm, CalendarAlarmType};
 use std::collections::hash_map::Entry;
@@ -33,11 +36,14 @@ use store::{
         now,
     },
 };
-use tokio::sync::{mpsc, watch};
+use tokio::sync::{Semaphore, mpsc, watch};
 use trc::TaskQueueEvent;
+use types::blob_hash::BlobHash;
 use utils::snowflake::SnowflakeIdGenerator;
 
 pub mod alarm;
+pub mod delivery_hook;
+pub mod expire;
 pub mod imip;
 pub mod index;
 pub mod lock;
@@ -57,6 +63,8 @@ pub enum TaskAction {
     SendAlarm(CalendarAlarm),
     SendImip,
     MergeThreads(MergeThreadIds<AHashSet<u32>>),
+    ExpireEmail(ExpireEmailAction),
+    DeliveryHook(AsyncHookTask),
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -68,15 +76,23 @@ pub struct IndexAction {
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub(crate) struct ImipAction;
 
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct ExpireEmailAction {
+    pub blob_hash: BlobHash,
+}
+
 const INDEX_EXPIRY: u64 = 60 * 5; // 5 minutes
 const ALARM_EXPIRY: u64 = 60 * 2; // 2 minutes
 const QUEUE_REFRESH_INTERVAL: u64 = 60 * 5; // 5 minutes
+const DELIVERY_HOOK_MAX_AGE: u64 = 60 * 60 * 24; // 1 day
 
 pub(crate) struct TaskManagerIpc {
     tx_fts: mpsc::Sender<Task<IndexAction>>,
     tx_alarm: mpsc::Sender<Task<CalendarAlarm>>,
     tx_imip: mpsc::Sender<Task<ImipAction>>,
     tx_threads: mpsc::Sender<Task<MergeThreadIds<AHashSet<u32>>>>,
+    tx_expire: mpsc::Sender<Task<ExpireEmailAction>>,
+    tx_hook: mpsc::Sender<Task<AsyncHookTask>>,
     locked: AHashMap<Vec<u8>, Locked>,
     revision: u64,
 }
@@ -93,6 +109,8 @@ pub fn spawn_task_manager(inner: Arc<Inner>) {
     let (tx_index_3, mut rx_index_3) = mpsc::channel::<Task<ImipAction>>(IPC_CHANNEL_BUFFER);
     let (tx_index_4, mut rx_index_4) =
         mpsc::channel::<Task<MergeThreadIds<AHashSet<u32>>>>(IPC_CHANNEL_BUFFER);
+    let (tx_index_5, mut rx_index_5) = mpsc::channel::<Task<ExpireEmailAction>>(IPC_CHANNEL_BUFFER);
+    let (tx_index_6, mut rx_index_6) = mpsc::channel::<Task<AsyncHookTask>>(IPC_CHANNEL_BUFFER);
 
     // Create dummy server instance for alarms
     let server_instance = Arc::new(ServerInstance {
@@ -294,12 +312,120 @@ pub fn spawn_task_manager(inner: Arc<Inner>) {
         });
     }
 
+    // Expire e-mail worker
+    {
+        let inner = inner.clone();
+        tokio::spawn(async move {
+            while let Some(task) = rx_index_5.recv().await {
+                let server = inner.build_server();
+
+                // Lock task
+                if server
+                    .try_lock_task(
+                        task.account_id,
+                        task.document_id,
+                        task.lock_key(),
+                        task.lock_expiry(),
+                    )
+                    .await
+                {
+                    let success = server
+                        .expire_email(task.account_id, task.document_id, &task.action.blob_hash)
+                        .await;
+
+                    // Remove entry from queue
+                    if success {
+                        delete_tasks(&server, &[task]).await;
+                    } else {
+                        trc::event!(
+                            TaskQueue(TaskQueueEvent::TaskFailed),
+                            AccountId = task.account_id,
+                            DocumentId = task.document_id,
+                            Details = "Expiring e-mail task failed",
+                        );
+                    }
+                }
+            }
+        });
+    }
+
+    // Asynchronous delivery hook worker
+    {
+        let inner = inner.clone();
+        let mut max_concurrent = inner
+            .build_server()
+            .core
+            .smtp
+            .session
+            .delivery_hooks_concurrency;
+        let permits = Arc::new(Semaphore::new(max_concurrent));
+        tokio::spawn(async move {
+            while let Some(task) = rx_index_6.recv().await {
+                let server = inner.build_server();
+
+                // Resize the worker pool when the limit was changed by a reload,
+                // excess permits are retired once the hooks holding them finish
+                let concurrency = server.core.smtp.session.delivery_hooks_concurrency;
+                if concurrency > max_concurrent {
+                    permits.add_permits(concurrency - max_concurrent);
+                } else if concurrency < max_concurrent {
+                    let permits = permits.clone();
+                    let excess = (max_concurrent - concurrency) as u32;
+                    tokio::spawn(async move {
+                        if let Ok(permit) = permits.acquire_many_owned(excess).await {
+                            permit.forget();
+                        }
+                    });
+                }
+                max_concurrent = concurrency;
+
+                let Ok(permit) = permits.clone().acquire_owned().await else {
+                    break;
+                };
+
+                // Hooks are called concurrently, up to the configured limit
+                tokio::spawn(async move {
+                    // Lock task
+                    if server
+                        .try_lock_task(
+                            task.account_id,
+                            task.document_id,
+                            task.lock_key(),
+                            task.lock_expiry(),
+                        )
+                        .await
+                    {
+                        let success = server
+                            .run_delivery_hooks(task.account_id, task.document_id, &task.action)
+                            .await;
+
+                        // Remove entry from queue, giving up on hooks that keep failing
+                        if success || task.due.due() + DELIVERY_HOOK_MAX_AGE <= now() {
+                            delete_tasks(&server, &[task]).await;
+                        } else {
+                            trc::event!(
+                                TaskQueue(TaskQueueEvent::TaskFailed),
+                                AccountId = task.account_id,
+                                DocumentId = task.document_id,
+                                Details = "Delivery hook task failed",
+                            );
+                        }
+                    }
+
+                    drop(permit);
+                });
+            }
+        });
+    }
+
     tokio::spawn(async move {
         let mut ipc = TaskManagerIpc {
             tx_fts: tx_index_1,
             tx_alarm: tx_index_2,
             tx_imip: tx_index_3,
             tx_threads: tx_index_4,
+            tx_expire: tx_index_5,
+            tx_hook: tx_index_6,
             locked: Default::default(),
             revision: 0,
         };
@@ -492,6 +618,48 @@ impl TaskQueueManager for Server {
                         );
                     }
                 }
+                TaskAction::ExpireEmail(action)
+                    if roles.purge_accounts.is_enabled_for_hash(&event) =>
+                {
+                    if ipc
+                        .tx_expire
+                        .send(Task {
+                            account_id: event.account_id,
+                            document_id: event.document_id,
+                            due: event.due,
+                            action,
+                        })
+                        .await
+                        .is_err()
+                    {
+                        trc::event!(
+                            Server(trc::ServerEvent::ThreadError),
+                            Details = "Error sending task.",
+                            CausedBy = trc::location!()
+                        );
+                    }
+                }
+                TaskAction::DeliveryHook(action)
+                    if roles.delivery_hooks.is_enabled_for_hash(&event) =>
+                {
+                    if ipc
+                        .tx_hook
+                        .send(Task {
+                            account_id: event.account_id,
+                            document_id: event.document_id,
+                            due: event.due,
+                            action,
+                        })
+                        .await
+                        .is_err()
+                    {
+                        trc::event!(
+                            Server(trc::ServerEvent::ThreadError),
+                            Details = "Error sending task.",
+                            CausedBy = trc::location!()
+                        );
+                    }
+                }
                 _ => {
                     trc::event!(
                         TaskQueue(TaskQueueEvent::TaskIgnored),
@@ -544,6 +712,8 @@ impl TaskAction {
             TaskAction::SendAlarm(_) => "SendAlarm",
             TaskAction::SendImip => "SendImip",
             TaskAction::MergeThreads(_) => "MergeThreads",
+            TaskAction::ExpireEmail(_) => "ExpireEmail",
+            TaskAction::DeliveryHook(_) => "DeliveryHook",
         }
     }
 }
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use crate::{inbound::auth::SaslToken, queue::QueueId};
+use crate::{inbound::auth::SaslToken, lists::ListRecipient, queue::QueueId};
 use common::{
     Inner, Server,
     auth::AccessToken,
@@ -26,6 +26,7 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use utils::DomainPart;
 
 pub mod params;
+pub mod sending_limits;
 pub mod throttle;
 
 #[derive(Clone)]
@@ -73,6 +74,7 @@ pub struct SessionData {
 
     pub mail_from: Option<SessionAddress>,
     pub rcpt_to: Vec<SessionAddress>,
+    pub list_rcpts: Vec<ListRecipient>,
     pub rcpt_errors: usize,
     pub rcpt_oks: usize,
     pub message: Vec<u8>,
@@ -154,6 +156,7 @@ impl SessionData {
             helo_domain: String::new(),
             mail_from: None,
             rcpt_to: Vec::new(),
+            list_rcpts: Vec::new(),
             authenticated_as: None,
             priority: 0,
             valid_until: Instant::now(),
@@ -278,6 +281,7 @@ impl SessionData {
             helo_domain: "localhost".into(),
             mail_from,
             rcpt_to,
+            list_rcpts: Vec::new(),
             rcpt_errors: 0,
             rcpt_oks: 0,
             message,
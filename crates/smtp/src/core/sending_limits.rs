@@ -0,0 +1,478 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{
+    KV_SENDING_COUNTER, KV_SENDING_COUNTRY, KV_SENDING_LOCKOUT, KV_SENDING_RECIPIENT,
+    KV_TOKEN_REVOCATION, Server,
+};
+use directory::{
+    Type,
+    backend::internal::{
+        PrincipalAction, PrincipalField, PrincipalUpdate, PrincipalValue,
+        manage::{ChangedPrincipals, ManageDirectory, UpdatePrincipal},
+    },
+};
+use serde::Serialize;
+use std::{future::Future, net::IpAddr};
+use store::{
+    Serialize as _,
+    dispatch::lookup::KeyValue,
+    write::{AlignedBytes, Archive, Archiver, key::KeySerializer, now},
+};
+use trc::{AddContext, SendingLimitEvent};
+
+const HOUR: u64 = 3600;
+const DAY: u64 = 86400;
+
+#[derive(
+    rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Serialize, Debug, Clone, PartialEq, Eq,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct SendingLockout {
+    pub locked_at: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendingStatus {
+    pub recipients_hour: u64,
+    pub recipients_day: u64,
+    pub unique_recipients_day: u64,
+    pub countries: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockout: Option<SendingLockout>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RcptVerdict {
+    Allow,
+    RateExceeded,
+    UniqueExceeded,
+    LockedOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Counter {
+    Hourly = 0,
+    Daily = 1,
+    Unique = 2,
+    Countries = 3,
+}
+
+pub trait SendingLimits: Sync + Send {
+    fn sending_rcpt(
+        &self,
+        account_id: u32,
+        rcpt: &str,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<RcptVerdict>> + Send;
+
+    fn sending_auth(
+        &self,
+        account_id: u32,
+        remote_ip: IpAddr,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn sending_lockout(
+        &self,
+        account_id: u32,
+        reason: SendingLimitEvent,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn is_sending_locked(&self, account_id: u32) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn sending_unlock(&self, account_id: u32) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn sending_status(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<SendingStatus>> + Send;
+}
+
+impl SendingLimits for Server {
+    /// Accounts a new recipient for an authenticated account, returning whether
+    /// it can be accepted
+    async fn sending_rcpt(
+        &self,
+        account_id: u32,
+        rcpt: &str,
+        session_id: u64,
+    ) -> trc::Result<RcptVerdict> {
+        let config = &self.core.smtp.sending_limits;
+        let store = self.in_memory_store();
+        let now = now();
+        let (hour, day) = (now / HOUR, now / DAY);
+
+        // Enforce recipient limits
+        for (limit, counter, bucket) in [
+            (config.hourly, Counter::Hourly, hour),
+            (config.daily, Counter::Daily, day),
+        ] {
+            if let Some(limit) = limit
+                && self.sending_counter(counter, account_id, bucket).await? >= limit
+            {
+                trc::event!(
+                    SendingLimit(SendingLimitEvent::RateExceeded),
+                    SpanId = session_id,
+                    AccountId = account_id,
+                    To = rcpt.to_string(),
+                    Limit = limit,
+                );
+
+                return Ok(RcptVerdict::RateExceeded);
+            }
+        }
+
+        // Enforce unique recipient limits
+        if let Some(limit) = config.unique_daily {
+            let key = KeyValue::<()>::build_key(
+                KV_SENDING_RECIPIENT,
+                KeySerializer::new(
+                    std::mem::size_of::<u32>() + std::mem::size_of::<u64>() + rcpt.len(),
+                )
+                .write(account_id)
+                .write(day)
+                .write(rcpt.as_bytes())
+                .finalize(),
+            );
+            if !store.key_exists(key.clone()).await? {
+                if self
+                    .sending_counter(Counter::Unique, account_id, day)
+                    .await?
+                    >= limit
+                {
+                    trc::event!(
+                        SendingLimit(SendingLimitEvent::UniqueExceeded),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        To = rcpt.to_string(),
+                        Limit = limit,
+                    );
+
+                    return Ok(RcptVerdict::UniqueExceeded);
+                }
+
+                store
+                    .key_set(KeyValue::new(key, vec![]).expires(DAY))
+                    .await?;
+                store
+                    .counter_incr(
+                        KeyValue::with_prefix(
+                            KV_SENDING_COUNTER,
+                            counter_key(Counter::Unique, account_id, day),
+                            1,
+                        )
+                        .expires(DAY),
+                        false,
+                    )
+                    .await?;
+            }
+        }
+
+        // Update counters
+        let mut current_hour = 0;
+        for (counter, bucket, expires) in [
+            (Counter::Hourly, hour, 2 * HOUR),
+            (Counter::Daily, day, 2 * DAY),
+        ] {
+            let value = store
+                .counter_incr(
+                    KeyValue::with_prefix(
+                        KV_SENDING_COUNTER,
+                        counter_key(counter, account_id, bucket),
+                        1,
+                    )
+                    .expires(expires),
+                    true,
+                )
+                .await?;
+            if counter == Counter::Hourly {
+                current_hour = value.max(0) as u64;
+            }
+        }
+
+        // Detect sudden increases in volume
+        if config.spike_factor.is_some() && current_hour >= config.spike_min {
+            let previous_hour = self
+                .sending_counter(Counter::Hourly, account_id, hour - 1)
+                .await?;
+            let previous_day = self
+                .sending_counter(Counter::Daily, account_id, day - 1)
+                .await?;
+
+            if config.is_spike(current_hour, previous_hour, previous_day) {
+                trc::event!(
+                    SendingLimit(SendingLimitEvent::VolumeSpike),
+                    SpanId = session_id,
+                    AccountId = account_id,
+                    Total = current_hour,
+                    Details = vec![
+                        trc::Value::from(previous_hour),
+                        trc::Value::from(previous_day)
+                    ],
+                );
+
+                if self
+                    .sending_lockout(account_id, SendingLimitEvent::VolumeSpike, session_id)
+                    .await?
+                {
+                    return Ok(RcptVerdict::LockedOut);
+                }
+            }
+        }
+
+        Ok(RcptVerdict::Allow)
+    }
+
+    /// Keeps track of the countries an account authenticates from
+    async fn sending_auth(
+        &self,
+        account_id: u32,
+        remote_ip: IpAddr,
+        session_id: u64,
+    ) -> trc::Result<()> {
+        let config = &self.core.smtp.sending_limits;
+        let Some(max_countries) = config.max_countries else {
+            return Ok(());
+        };
+        let Some(country) = self.lookup_asn_country(remote_ip).await.country else {
+            return Ok(());
+        };
+        let store = self.in_memory_store();
+        let bucket = now() / config.countries_period;
+
+        let key = KeyValue::<()>::build_key(
+            KV_SENDING_COUNTRY,
+            KeySerializer::new(
+                std::mem::size_of::<u32>() + std::mem::size_of::<u64>() + country.len(),
+            )
+            .write(account_id)
+            .write(bucket)
+            .write(country.as_bytes())
+            .finalize(),
+        );
+        if store.key_exists(key.clone()).await? {
+            return Ok(());
+        }
+        store
+            .key_set(KeyValue::new(key, vec![]).expires(config.countries_period))
+            .await?;
+        let countries = store
+            .counter_incr(
+                KeyValue::with_prefix(
+                    KV_SENDING_COUNTER,
+                    counter_key(Counter::Countries, account_id, bucket),
+                    1,
+                )
+                .expires(config.countries_period),
+                true,
+            )
+            .await?
+            .max(0) as u64;
+
+        if countries > max_countries {
+            trc::event!(
+                SendingLimit(SendingLimitEvent::CountryAnomaly),
+                SpanId = session_id,
+                AccountId = account_id,
+                RemoteIp = remote_ip,
+                Details = country.to_string(),
+                Total = countries,
+            );
+
+            self.sending_lockout(account_id, SendingLimitEvent::CountryAnomaly, session_id)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Locks an account out from sending and revokes its credentials, returns
+    /// whether the account was locked out
+    async fn sending_lockout(
+        &self,
+        account_id: u32,
+        reason: SendingLimitEvent,
+        session_id: u64,
+    ) -> trc::Result<bool> {
+        let config = &self.core.smtp.sending_limits;
+        if !config.lockout {
+            return Ok(false);
+        } else if self.is_sending_locked(account_id).await? {
+            return Ok(true);
+        }
+
+        let locked_at = now();
+        self.in_memory_store()
+            .key_set(KeyValue::with_prefix(
+                KV_SENDING_LOCKOUT,
+                account_id.to_be_bytes(),
+                Archiver::new(SendingLockout {
+                    locked_at,
+                    reason: reason.name().to_string(),
+                })
+                .untrusted()
+                .serialize()
+                .caused_by(trc::location!())?,
+            ))
+            .await?;
+
+        // Revoke app passwords
+        let mut changed_principals =
+            ChangedPrincipals::from_change(account_id, Type::Individual, PrincipalField::Secrets);
+        if config.revoke_app_passwords {
+            match self
+                .core
+                .storage
+                .data
+                .update_principal(UpdatePrincipal::by_id(account_id).with_updates(vec![
+                    PrincipalUpdate {
+                        action: PrincipalAction::RemoveItem,
+                        field: PrincipalField::Secrets,
+                        value: PrincipalValue::String("$app$".to_string()),
+                    },
+                ]))
+                .await
+            {
+                Ok(changes) => {
+                    changed_principals = changes;
+                }
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(session_id)
+                            .account_id(account_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to revoke app passwords.")
+                    );
+                }
+            }
+        }
+
+        // Revoke OAuth tokens
+        if config.revoke_tokens {
+            self.in_memory_store()
+                .key_set(
+                    KeyValue::with_prefix(
+                        KV_TOKEN_REVOCATION,
+                        account_id.to_be_bytes(),
+                        locked_at.to_be_bytes().to_vec(),
+                    )
+                    .expires(self.core.oauth.oauth_expiry_refresh_token),
+                )
+                .await?;
+        }
+        self.invalidate_principal_caches(changed_principals).await;
+
+        trc::event!(
+            SendingLimit(SendingLimitEvent::AccountLocked),
+            SpanId = session_id,
+            AccountId = account_id,
+            Reason = reason.name(),
+        );
+
+        Ok(true)
+    }
+
+    async fn is_sending_locked(&self, account_id: u32) -> trc::Result<bool> {
+        self.in_memory_store()
+            .key_exists(KeyValue::<()>::build_key(
+                KV_SENDING_LOCKOUT,
+                account_id.to_be_bytes(),
+            ))
+            .await
+    }
+
+    async fn sending_unlock(&self, account_id: u32) -> trc::Result<bool> {
+        if !self.is_sending_locked(account_id).await? {
+            return Ok(false);
+        }
+
+        self.in_memory_store()
+            .key_delete(KeyValue::<()>::build_key(
+                KV_SENDING_LOCKOUT,
+                account_id.to_be_bytes(),
+            ))
+            .await?;
+
+        trc::event!(
+            SendingLimit(SendingLimitEvent::AccountUnlocked),
+            AccountId = account_id,
+        );
+
+        Ok(true)
+    }
+
+    async fn sending_status(&self, account_id: u32) -> trc::Result<SendingStatus> {
+        let now = now();
+        let day = now / DAY;
+
+        Ok(SendingStatus {
+            recipients_hour: self
+                .sending_counter(Counter::Hourly, account_id, now / HOUR)
+                .await?,
+            recipients_day: self
+                .sending_counter(Counter::Daily, account_id, day)
+                .await?,
+            unique_recipients_day: self
+                .sending_counter(Counter::Unique, account_id, day)
+                .await?,
+            countries: self
+                .sending_counter(
+                    Counter::Countries,
+                    account_id,
+                    now / self.core.smtp.sending_limits.countries_period,
+                )
+                .await?,
+            lockout: self
+                .in_memory_store()
+                .key_get::<Archive<AlignedBytes>>(KeyValue::<()>::build_key(
+                    KV_SENDING_LOCKOUT,
+                    account_id.to_be_bytes(),
+                ))
+                .await?
+                .map(|value| value.deserialize::<SendingLockout>())
+                .transpose()
+                .caused_by(trc::location!())?,
+        })
+    }
+}
+
+trait SendingCounter {
+    fn sending_counter(
+        &self,
+        counter: Counter,
+        account_id: u32,
+        bucket: u64,
+    ) -> impl Future<Output = trc::Result<u64>> + Send;
+}
+
+impl SendingCounter for Server {
+    async fn sending_counter(
+        &self,
+        counter: Counter,
+        account_id: u32,
+        bucket: u64,
+    ) -> trc::Result<u64> {
+        self.in_memory_store()
+            .counter_get(KeyValue::<()>::build_key(
+                KV_SENDING_COUNTER,
+                counter_key(counter, account_id, bucket),
+            ))
+            .await
+            .map(|value| value.max(0) as u64)
+    }
+}
+
+fn counter_key(counter: Counter, account_id: u32, bucket: u64) -> Vec<u8> {
+    KeySerializer::new(1 + std::mem::size_of::<u32>() + std::mem::size_of::<u64>())
+        .write(counter as u8)
+        .write(account_id)
+        .write(bucket)
+        .finalize()
+}
@@ -125,6 +125,8 @@ impl NewKey for QueueRateLimiter {
         }
         if (self.keys & THROTTLE_REMOTE_IP) != 0 {
             hasher.update(e.resolve_variable(V_REMOTE_IP).to_string().as_bytes());
+            // Unix domain socket peers share an address and are told apart by their uid
+            hasher.update(e.resolve_variable(V_PEER_UID).to_string().as_bytes());
         }
         if (self.keys & THROTTLE_LOCAL_IP) != 0 {
             hasher.update(e.resolve_variable(V_LOCAL_IP).to_string().as_bytes());
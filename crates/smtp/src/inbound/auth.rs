@@ -4,10 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::sync::Arc;
+
 use common::{
     auth::{
-        AuthRequest,
-        sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain},
+        AccessToken, AuthRequest,
+        sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain, sasl_encode_challenge},
+        scram::ScramStep,
     },
     listener::SessionStream,
 };
@@ -15,25 +18,30 @@ use common::{
 use directory::Permission;
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
-use smtp_proto::{AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_XOAUTH2, IntoString};
+use smtp_proto::{
+    AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_SCRAM_SHA_256, AUTH_SCRAM_SHA_256_PLUS,
+    AUTH_XOAUTH2, IntoString,
+};
 use trc::{AuthEvent, SmtpEvent};
 
-use crate::core::Session;
+use crate::core::{Session, sending_limits::SendingLimits};
 
 pub struct SaslToken {
     mechanism: u64,
     credentials: Credentials<String>,
+    scram: Option<ScramStep>,
 }
 
 impl SaslToken {
     pub fn from_mechanism(mechanism: u64) -> Option<SaslToken> {
         match mechanism {
-            AUTH_PLAIN | AUTH_LOGIN => SaslToken {
+            AUTH_PLAIN | AUTH_LOGIN | AUTH_SCRAM_SHA_256 | AUTH_SCRAM_SHA_256_PLUS => SaslToken {
                 mechanism,
                 credentials: Credentials::Plain {
                     username: String::new(),
                     secret: String::new(),
                 },
+                scram: None,
             }
             .into(),
             AUTH_OAUTHBEARER | AUTH_XOAUTH2 => SaslToken {
@@ -41,6 +49,7 @@ impl SaslToken {
                 credentials: Credentials::OAuthBearer {
                     token: String::new(),
                 },
+                scram: None,
             }
             .into(),
             _ => None,
@@ -56,6 +65,21 @@ impl<T: SessionStream> Session<T> {
     ) -> Result<bool, ()> {
         if response.is_empty() {
             match (token.mechanism, &token.credentials) {
+                (AUTH_SCRAM_SHA_256 | AUTH_SCRAM_SHA_256_PLUS, _) => {
+                    return match token.scram.take() {
+                        None => {
+                            self.write(b"334 \r\n").await?;
+                            Ok(true)
+                        }
+                        Some(ScramStep::Completed(access_token)) => {
+                            // Client acknowledged the server-final message
+                            self.handle_auth_result(Ok(access_token)).await
+                        }
+                        Some(ScramStep::ClientFinal(_)) => {
+                            self.auth_error(b"500 5.5.6 Invalid challenge.\r\n").await
+                        }
+                    };
+                }
                 (AUTH_PLAIN | AUTH_XOAUTH2 | AUTH_OAUTHBEARER, _) => {
                     self.write(b"334 Go ahead.\r\n").await?;
                     return Ok(true);
@@ -91,6 +115,9 @@ impl<T: SessionStream> Session<T> {
                         return self.authenticate(credentials).await;
                     }
                 }
+                (AUTH_SCRAM_SHA_256 | AUTH_SCRAM_SHA_256_PLUS, _) => {
+                    return self.handle_scram_response(token, &response).await;
+                }
                 _ => (),
             }
         }
@@ -98,6 +125,68 @@ impl<T: SessionStream> Session<T> {
         self.auth_error(b"500 5.5.6 Invalid challenge.\r\n").await
     }
 
+    async fn handle_scram_response(
+        &mut self,
+        token: &mut SaslToken,
+        response: &[u8],
+    ) -> Result<bool, ()> {
+        match token.scram.take() {
+            None => {
+                match self
+                    .server
+                    .scram_begin(
+                        response,
+                        token.mechanism == AUTH_SCRAM_SHA_256_PLUS,
+                        self.stream.tls_channel_binding().is_some(),
+                        self.params.auth_directory.as_deref(),
+                    )
+                    .await
+                {
+                    Ok(auth) => {
+                        self.write(
+                            format!("334 {}\r\n", sasl_encode_challenge(auth.server_first()))
+                                .as_bytes(),
+                        )
+                        .await?;
+                        token.scram = Some(ScramStep::ClientFinal(Box::new(auth)));
+                        Ok(true)
+                    }
+                    Err(err) => {
+                        trc::error!(err.span_id(self.data.session_id));
+                        self.auth_error(b"500 5.5.6 Invalid challenge.\r\n").await
+                    }
+                }
+            }
+            Some(ScramStep::ClientFinal(auth)) => {
+                match self
+                    .server
+                    .scram_finish(
+                        *auth,
+                        response,
+                        self.stream.tls_channel_binding(),
+                        self.data.session_id,
+                        self.data.remote_ip,
+                        self.stream.peer_credentials().map(|peer| peer.uid),
+                    )
+                    .await
+                {
+                    Ok((access_token, server_final)) => {
+                        self.write(
+                            format!("334 {}\r\n", sasl_encode_challenge(server_final)).as_bytes(),
+                        )
+                        .await?;
+                        token.scram = Some(ScramStep::Completed(access_token));
+                        Ok(true)
+                    }
+                    Err(err) => self.handle_auth_result(Err(err)).await,
+                }
+            }
+            Some(ScramStep::Completed(_)) => {
+                self.auth_error(b"500 5.5.6 Invalid challenge.\r\n").await
+            }
+        }
+    }
+
     pub async fn authenticate(&mut self, credentials: Credentials<String>) -> Result<bool, ()> {
         if let Some(directory) = &self.params.auth_directory {
             // Authenticate
@@ -109,74 +198,112 @@ impl<T: SessionStream> Session<T> {
                         self.data.session_id,
                         self.data.remote_ip,
                     )
-                    .with_directory(directory),
+                    .with_directory(directory)
+                    .with_peer_uid(self.stream.peer_credentials().map(|peer| peer.uid)),
                 )
-                .await
-                .and_then(|access_token| {
-                    access_token
-                        .assert_has_permission(Permission::EmailSend)
-                        .map(|_| access_token)
-                });
-
-            match result {
-                Ok(access_token) => {
-                    self.data.authenticated_as = access_token.into();
-                    self.eval_post_auth_params().await;
-                    self.write(b"235 2.7.0 Authentication succeeded.\r\n")
-                        .await?;
-                    return Ok(false);
+                .await;
+
+            self.handle_auth_result(result).await
+        } else {
+            trc::event!(
+                Smtp(SmtpEvent::MissingAuthDirectory),
+                SpanId = self.data.session_id,
+            );
+            self.write(b"454 4.7.0 Temporary authentication failure\r\n")
+                .await?;
+
+            Ok(false)
+        }
+    }
+
+    async fn handle_auth_result(
+        &mut self,
+        result: trc::Result<Arc<AccessToken>>,
+    ) -> Result<bool, ()> {
+        match result.and_then(|access_token| {
+            access_token
+                .assert_has_permission(Permission::EmailSend)
+                .map(|_| access_token)
+        }) {
+            Ok(access_token) => {
+                if self.server.core.smtp.sending_limits.enable
+                    && let Err(err) = self
+                        .server
+                        .sending_auth(
+                            access_token.primary_id(),
+                            self.data.remote_ip,
+                            self.data.session_id,
+                        )
+                        .await
+                {
+                    trc::error!(
+                        err.span_id(self.data.session_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to track authentication source.")
+                    );
                 }
-                Err(err) => {
-                    let reason = *err.as_ref();
 
-                    trc::error!(err.span_id(self.data.session_id));
+                self.data.authenticated_as = access_token.into();
+                self.eval_post_auth_params().await;
+                self.write(b"235 2.7.0 Authentication succeeded.\r\n")
+                    .await?;
+                return Ok(false);
+            }
+            Err(err) => {
+                let reason = *err.as_ref();
 
-                    match reason {
-                        trc::EventType::Auth(trc::AuthEvent::Failed) => {
-                            return self
-                                .auth_error(b"535 5.7.8 Authentication credentials invalid.\r\n")
-                                .await;
-                        }
-                        trc::EventType::Auth(trc::AuthEvent::TokenExpired) => {
-                            return self.auth_error(b"535 5.7.8 OAuth token expired.\r\n").await;
-                        }
-                        trc::EventType::Auth(trc::AuthEvent::MissingTotp) => {
-                            return self
+                trc::error!(err.span_id(self.data.session_id));
+
+                match reason {
+                    trc::EventType::Auth(trc::AuthEvent::Failed) => {
+                        return self
+                            .auth_error(b"535 5.7.8 Authentication credentials invalid.\r\n")
+                            .await;
+                    }
+                    trc::EventType::Auth(trc::AuthEvent::TokenExpired) => {
+                        return self.auth_error(b"535 5.7.8 OAuth token expired.\r\n").await;
+                    }
+                    trc::EventType::Auth(trc::AuthEvent::MissingTotp) => {
+                        return self
                             .auth_error(
                                 b"334 5.7.8 Missing TOTP token, try with 'secret$totp_code'.\r\n",
                             )
                             .await;
-                        }
-                        trc::EventType::Security(trc::SecurityEvent::Unauthorized) => {
-                            self.write(
-                                concat!(
-                                    "550 5.7.1 Your account is not authorized ",
-                                    "to use this service.\r\n"
-                                )
-                                .as_bytes(),
+                    }
+                    trc::EventType::Security(trc::SecurityEvent::Unauthorized) => {
+                        self.write(
+                            concat!(
+                                "550 5.7.1 Your account is not authorized ",
+                                "to use this service.\r\n"
                             )
-                            .await?;
-                            return Ok(false);
-                        }
-                        trc::EventType::Security(_) => {
-                            return Err(());
-                        }
-                        _ => (),
+                            .as_bytes(),
+                        )
+                        .await?;
+                        return Ok(false);
+                    }
+                    trc::EventType::Security(_) => {
+                        return Err(());
                     }
+                    _ => (),
                 }
             }
-        } else {
-            trc::event!(
-                Smtp(SmtpEvent::MissingAuthDirectory),
-                SpanId = self.data.session_id,
-            );
         }
+
         self.write(b"454 4.7.0 Temporary authentication failure\r\n")
             .await?;
 
         Ok(false)
     }
 
+    /// SCRAM-SHA-256-PLUS is only available when TLS is terminated by this server
+    pub fn available_mechanisms(&self, mechanisms: u64) -> u64 {
+        if self.stream.tls_channel_binding().is_some() {
+            mechanisms
+        } else {
+            mechanisms & !AUTH_SCRAM_SHA_256_PLUS
+        }
+    }
+
     pub async fn auth_error(&mut self, response: &[u8]) -> Result<bool, ()> {
         tokio::time::sleep(self.params.auth_errors_wait).await;
         self.data.auth_errors += 1;
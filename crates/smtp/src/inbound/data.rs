@@ -7,12 +7,18 @@
 use super::{ArcSeal, AuthResult, DkimSign};
 use crate::{
     core::{Session, SessionAddress, State},
-    inbound::milter::Modification,
+    inbound::{dlp::DlpVerdict, milter::Modification},
     queue::{
-        self, Message, MessageSource, MessageWrapper, QueueEnvelope, RCPT_SPAM_PAYLOAD,
+        self, Message, MessageSource, MessageWrapper, QueueEnvelope, RCPT_QUARANTINE,
+        RCPT_SPAM_PAYLOAD,
+        bounce::parse_dsn_message,
         quota::HasQueueQuota,
+        suppression::{BounceOrigin, SuppressionList},
+    },
+    reporting::{
+        analysis::AnalyzeReport,
+        complaints::{ComplaintOrigin, Complaints, origin_keys},
     },
-    reporting::analysis::AnalyzeReport,
     scripts::ScriptResult,
 };
 use common::{
@@ -43,7 +49,7 @@ use std::{
     borrow::Cow,
     time::{Instant, SystemTime},
 };
-use trc::SmtpEvent;
+use trc::{DlpEvent, SmtpEvent};
 use utils::{DomainPart, config::Rate};
 
 impl<T: SessionStream> Session<T> {
@@ -72,6 +78,14 @@ impl<T: SessionStream> Session<T> {
         );
         let has_date_header = auth_message.has_date_header();
         let has_message_id_header = auth_message.has_message_id_header();
+        let mut origin_message_id = parsed_message
+            .message_id()
+            .filter(|_| {
+                self.is_authenticated()
+                    && (self.server.core.smtp.report.analysis.complaints.enable
+                        || self.server.core.smtp.suppression.enable)
+            })
+            .map(|id| id.to_string());
 
         // Loop detection
         let dc = &self.server.core.smtp.session.data;
@@ -328,8 +342,52 @@ impl<T: SessionStream> Session<T> {
             _ => (None, None),
         };
 
+        // Update the suppression list of the bounced sender, only notifications
+        // about messages sent by this server are trusted
+        if self.server.core.smtp.suppression.enable
+            && self
+                .data
+                .mail_from
+                .as_ref()
+                .is_some_and(|mail_from| mail_from.address.is_empty())
+            && let Some(rcpt) = self.data.rcpt_to.first()
+        {
+            let bounces = parse_dsn_message(&parsed_message);
+            if !bounces.is_empty() {
+                let server = self.server.clone();
+                let sender = rcpt.address_lcase.clone();
+                let origin_keys = origin_keys(&parsed_message);
+                let session_id = self.data.session_id;
+                tokio::spawn(async move {
+                    server
+                        .process_dsn_bounces(&sender, origin_keys, bounces, session_id)
+                        .await;
+                });
+            }
+        }
+
         // Analyze reports
         if is_report {
+            // Abuse reports are trusted when received from a feedback loop source
+            // or when signed by the domain of their author
+            let from_domain = auth_message.from().domain_part().to_lowercase();
+            let is_trusted_source = rc
+                .analysis
+                .complaints
+                .sources
+                .iter()
+                .any(|network| network.matches(&self.data.remote_ip))
+                || dkim_output.iter().any(|output| {
+                    matches!(output.result(), DkimResult::Pass)
+                        && output.signature().is_some_and(|signature| {
+                            let domain = signature.domain().to_lowercase();
+                            from_domain == domain
+                                || from_domain
+                                    .strip_suffix(&domain)
+                                    .is_some_and(|prefix| prefix.ends_with('.'))
+                        })
+                });
+
             if !rc.analysis.forward {
                 self.server.analyze_report(
                     mail_parser::Message {
@@ -343,6 +401,7 @@ impl<T: SessionStream> Session<T> {
                             .collect(),
                         raw_message: b"".into(),
                     },
+                    is_trusted_source,
                     self.data.session_id,
                 );
                 self.data.messages_sent += 1;
@@ -360,6 +419,7 @@ impl<T: SessionStream> Session<T> {
                             .collect(),
                         raw_message: b"".into(),
                     },
+                    is_trusted_source,
                     self.data.session_id,
                 );
             }
@@ -500,6 +560,11 @@ impl<T: SessionStream> Session<T> {
         };
 
         // Apply modifications
+        let quarantine_enabled = self.server.core.smtp.quarantine.enable;
+        let mut quarantine = quarantine_enabled
+            && modifications
+                .iter()
+                .any(|m| matches!(m, Modification::Quarantine { .. }));
         let mut edited_message = if !modifications.is_empty() {
             self.data
                 .apply_milter_modifications(modifications, &auth_message)
@@ -587,6 +652,11 @@ impl<T: SessionStream> Session<T> {
             // Apply modifications
             for modification in modifications {
                 match modification {
+                    ScriptModification::AddHeader { name, .. }
+                        if quarantine_enabled && name.eq_ignore_ascii_case("X-Quarantine") =>
+                    {
+                        quarantine = true;
+                    }
                     ScriptModification::AddHeader { name, value } => {
                         headers.extend_from_slice(name.as_bytes());
                         headers.extend_from_slice(b": ");
@@ -602,12 +672,111 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Quarantine verdicts are kept in the envelope, quarantine headers
+        // are removed before local delivery
+        if quarantine {
+            for rcpt in self.data.rcpt_to.iter_mut() {
+                rcpt.flags |= RCPT_QUARANTINE;
+            }
+        }
+
+        let raw_message = edited_message.as_deref().unwrap_or(raw_message.as_slice());
+
+        // Outbound content policies
+        let dlp = if self.is_authenticated() && !self.server.core.smtp.dlp.policies.is_empty() {
+            let verdict = self.dlp_verdict(raw_message).await;
+            if let Some(reason) = verdict.reject {
+                trc::event!(
+                    Dlp(DlpEvent::MessageRejected),
+                    SpanId = self.data.session_id,
+                    From = self.data.mail_from.as_ref().unwrap().address_lcase.clone(),
+                    Code = reason.trim_end().to_string(),
+                );
+                return reason.into_bytes().into();
+            }
+            for address in &verdict.bcc {
+                if !self
+                    .data
+                    .rcpt_to
+                    .iter()
+                    .any(|rcpt| &rcpt.address_lcase == address)
+                {
+                    trc::event!(
+                        Dlp(DlpEvent::ComplianceCopy),
+                        SpanId = self.data.session_id,
+                        To = address.clone(),
+                    );
+                    self.data.rcpt_to.push(SessionAddress {
+                        domain: address.domain_part().into(),
+                        address_lcase: address.clone(),
+                        address: address.clone(),
+                        flags: RCPT_NOTIFY_NEVER,
+                        dsn_info: None,
+                    });
+                }
+            }
+            verdict
+        } else {
+            DlpVerdict::default()
+        };
+
+        // Mailing lists
+        if !self.data.list_rcpts.is_empty() {
+            // List addresses are removed from the envelope, only regular
+            // recipients are queued
+            let list_rcpts = std::mem::take(&mut self.data.list_rcpts);
+            self.data.rcpt_to.retain(|rcpt| {
+                !list_rcpts
+                    .iter()
+                    .any(|list_rcpt| list_rcpt.address == rcpt.address_lcase)
+            });
+
+            if let Err(response) = self
+                .process_list_rcpts(
+                    list_rcpts,
+                    &headers,
+                    raw_message,
+                    dmarc_result
+                        .as_ref()
+                        .is_some_and(|result| result == &DmarcResult::Pass),
+                    dmarc_policy.as_ref(),
+                    dlp.hold,
+                )
+                .await
+            {
+                return response;
+            }
+
+            if self.data.rcpt_to.is_empty() {
+                self.data.messages_sent += 1;
+                return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
+            }
+        }
+
         // Build message
         let mail_from = self.data.mail_from.clone().unwrap();
         let rcpt_to = std::mem::take(&mut self.data.rcpt_to);
         let mut message = self
             .build_message(mail_from, rcpt_to, message_id, self.data.session_id)
             .await;
+        if dlp.require_tls {
+            trc::event!(
+                Dlp(DlpEvent::TlsRequired),
+                SpanId = self.data.session_id,
+                QueueId = message.queue_id,
+            );
+            message.require_tls();
+        }
+        if dlp.hold {
+            let hold_for = self.server.core.smtp.dlp.hold_for;
+            trc::event!(
+                Dlp(DlpEvent::MessageHeld),
+                SpanId = self.data.session_id,
+                QueueId = message.queue_id,
+                Expires = trc::Value::Timestamp(message.message.created + hold_for),
+            );
+            message.hold_for_approval(hold_for);
+        }
 
         // Add Return-Path
         if self
@@ -641,12 +810,15 @@ impl<T: SessionStream> Session<T> {
                 .unwrap_or(true)
         {
             headers.extend_from_slice(b"Message-ID: ");
+            let message_id_start = headers.len();
             let _ = generate_message_id_header(&mut headers, &self.hostname);
+            origin_message_id = std::str::from_utf8(&headers[message_id_start..])
+                .ok()
+                .map(|id| id.to_string());
             headers.extend_from_slice(b"\r\n");
         }
 
         // DKIM sign
-        let raw_message = edited_message.as_deref().unwrap_or(raw_message.as_slice());
         for signer in self
             .server
             .eval_if::<Vec<String>, _>(&ac.dkim.sign, self, self.data.session_id)
@@ -699,6 +871,35 @@ impl<T: SessionStream> Session<T> {
             } else {
                 MessageSource::Authenticated
             };
+
+            // Remember the sending account for matching feedback loop complaints
+            let complaint_origin = self
+                .data
+                .authenticated_as
+                .as_ref()
+                .filter(|_| self.server.core.smtp.report.analysis.complaints.enable)
+                .map(|access_token| ComplaintOrigin {
+                    account_id: access_token.primary_id(),
+                    queue_id,
+                    sender: message.message.return_path.to_string(),
+                });
+
+            // Remember the recipients for matching delivery status notifications
+            let bounce_origin = self
+                .data
+                .authenticated_as
+                .as_ref()
+                .filter(|_| self.server.core.smtp.suppression.enable)
+                .map(|_| BounceOrigin {
+                    sender: message.message.return_path.to_lowercase(),
+                    recipients: message
+                        .message
+                        .recipients
+                        .iter()
+                        .map(|rcpt| rcpt.address.to_lowercase())
+                        .collect(),
+                });
+
             if message
                 .queue(
                     Some(&headers),
@@ -709,6 +910,22 @@ impl<T: SessionStream> Session<T> {
                 )
                 .await
             {
+                if let Some(origin) = complaint_origin {
+                    self.server
+                        .complaint_track(origin, origin_message_id.as_deref(), self.data.session_id)
+                        .await;
+                }
+                if let Some(origin) = bounce_origin {
+                    self.server
+                        .bounce_track(
+                            origin,
+                            queue_id,
+                            origin_message_id.as_deref(),
+                            self.data.session_id,
+                        )
+                        .await;
+                }
+
                 self.state = State::Accepted(queue_id);
                 self.data.messages_sent += 1;
                 format!("250 2.0.0 Message queued with id {queue_id:x}.\r\n")
@@ -0,0 +1,452 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use ahash::AHashSet;
+use common::{
+    Server,
+    config::smtp::{
+        dlp::{DlpAction, DlpDetector},
+        queue::{QueueExpiry, QueueName},
+    },
+    listener::SessionStream,
+};
+use mail_parser::{Message, MessageParser, MimeHeaders};
+use smtp_proto::MAIL_REQUIRETLS;
+use store::write::now;
+use trc::DlpEvent;
+
+use crate::{
+    core::Session,
+    queue::{
+        HELD_FOR_APPROVAL, MessageWrapper, QueueEnvelope, QueueId, Schedule, Status,
+        spool::SmtpSpool,
+    },
+};
+
+// Maximum number of dictionary lookups per detector and message
+const MAX_LOOKUPS: usize = 1024;
+
+#[derive(Debug, Default)]
+pub struct DlpVerdict {
+    pub reject: Option<String>,
+    pub hold: bool,
+    pub require_tls: bool,
+    pub bcc: Vec<String>,
+}
+
+impl<T: SessionStream> Session<T> {
+    pub async fn dlp_verdict(&self, raw_message: &[u8]) -> DlpVerdict {
+        let mut verdict = DlpVerdict::default();
+        let Some(message) = MessageParser::new().parse(raw_message) else {
+            return verdict;
+        };
+        let mut content = MessageContent::new(&message);
+
+        for policy in &self.server.core.smtp.dlp.policies {
+            if !self
+                .server
+                .eval_if(&policy.enable, self, self.data.session_id)
+                .await
+                .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let mut matches = 0;
+            for detector in &policy.detectors {
+                matches += match detector {
+                    DlpDetector::CreditCard => content.credit_cards(),
+                    DlpDetector::Iban => content.ibans(),
+                    DlpDetector::Keywords(store) => {
+                        let words = content.words().to_vec();
+                        self.dlp_lookup(store, &words).await
+                    }
+                    DlpDetector::Attachments(store) => {
+                        let types = content.attachment_types().to_vec();
+                        self.dlp_lookup(store, &types).await
+                    }
+                };
+                if matches >= policy.min_matches {
+                    break;
+                }
+            }
+            if matches < policy.min_matches {
+                continue;
+            }
+
+            trc::event!(
+                Dlp(DlpEvent::PolicyMatched),
+                SpanId = self.data.session_id,
+                Id = policy.id.clone(),
+                Total = matches,
+            );
+
+            match &policy.action {
+                DlpAction::Reject(reason) => {
+                    verdict.reject = Some(format!("{}\r\n", reason.trim_end()));
+                    break;
+                }
+                DlpAction::Hold => {
+                    verdict.hold = true;
+                }
+                DlpAction::RequireTls => {
+                    verdict.require_tls = true;
+                }
+                DlpAction::Bcc(address) => {
+                    if !verdict.bcc.contains(address) {
+                        verdict.bcc.push(address.clone());
+                    }
+                }
+            }
+        }
+
+        verdict
+    }
+
+    async fn dlp_lookup(&self, store_id: &str, values: &[String]) -> usize {
+        let Some(store) = self.server.get_in_memory_store(store_id) else {
+            trc::event!(
+                Eval(trc::EvalEvent::StoreNotFound),
+                Id = store_id.to_string(),
+                SpanId = self.data.session_id,
+            );
+            return 0;
+        };
+
+        let mut matches = 0;
+        for value in values.iter().take(MAX_LOOKUPS) {
+            match store.key_exists(value.as_str()).await {
+                Ok(true) => {
+                    matches += 1;
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(self.data.session_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to lookup content policy dictionary")
+                    );
+                    break;
+                }
+            }
+        }
+
+        matches
+    }
+}
+
+impl MessageWrapper {
+    /// Holds all recipients until the message is released or `hold_for` elapses,
+    /// in which case the message expires without any delivery attempts
+    pub fn hold_for_approval(&mut self, hold_for: u64) {
+        self.message.flags |= HELD_FOR_APPROVAL;
+        for rcpt in &mut self.message.recipients {
+            rcpt.retry = Schedule::later(hold_for);
+            rcpt.notify = Schedule::later(hold_for);
+            rcpt.expires = QueueExpiry::Ttl(hold_for);
+        }
+    }
+
+    pub fn require_tls(&mut self) {
+        self.message.flags |= MAIL_REQUIRETLS;
+    }
+}
+
+pub trait HeldMessages: Sync + Send {
+    fn release_held(&self, queue_id: QueueId) -> impl Future<Output = Option<bool>> + Send;
+}
+
+impl HeldMessages for Server {
+    async fn release_held(&self, queue_id: QueueId) -> Option<bool> {
+        let mut message = self.read_message(queue_id, QueueName::default()).await?;
+        if message.message.flags & HELD_FOR_APPROVAL == 0 {
+            return Some(false);
+        }
+        message.message.flags &= !HELD_FOR_APPROVAL;
+
+        // Schedule delivery as if the message had been queued now
+        let now = now();
+        let held_for = now.saturating_sub(message.message.created);
+        for idx in 0..message.message.recipients.len() {
+            if !matches!(
+                message.message.recipients[idx].status,
+                Status::Scheduled | Status::TemporaryFailure(_)
+            ) {
+                continue;
+            }
+
+            let queue = self.get_queue_or_default(
+                &self
+                    .eval_if::<String, _>(
+                        &self.core.smtp.queue.queue,
+                        &QueueEnvelope::new(&message.message, &message.message.recipients[idx]),
+                        message.span_id,
+                    )
+                    .await
+                    .unwrap_or_else(|| "default".to_string()),
+                message.span_id,
+            );
+            let rcpt = &mut message.message.recipients[idx];
+            rcpt.retry = Schedule::now();
+            rcpt.notify = Schedule::later(queue.notify.first().copied().unwrap_or(86400));
+            rcpt.expires = match queue.expiry {
+                QueueExpiry::Ttl(time) => QueueExpiry::Ttl(held_for + time),
+                QueueExpiry::Attempts(count) => QueueExpiry::Attempts(count),
+            };
+        }
+
+        trc::event!(
+            Dlp(DlpEvent::MessageReleased),
+            QueueId = queue_id,
+            From = message.message.return_path.to_string(),
+        );
+
+        Some(message.save_changes(self, None).await)
+    }
+}
+
+struct MessageContent<'x> {
+    message: &'x Message<'x>,
+    text: Option<String>,
+    credit_cards: Option<usize>,
+    ibans: Option<usize>,
+    words: Option<Vec<String>>,
+    attachment_types: Option<Vec<String>>,
+}
+
+impl<'x> MessageContent<'x> {
+    fn new(message: &'x Message<'x>) -> Self {
+        MessageContent {
+            message,
+            text: None,
+            credit_cards: None,
+            ibans: None,
+            words: None,
+            attachment_types: None,
+        }
+    }
+
+    fn text(&mut self) -> &str {
+        self.text.get_or_insert_with(|| {
+            let mut text = self.message.subject().unwrap_or_default().to_string();
+            for idx in 0..self.message.text_body.len() {
+                if let Some(body) = self.message.body_text(idx) {
+                    text.push('\n');
+                    text.push_str(body.as_ref());
+                }
+            }
+            text
+        })
+    }
+
+    fn credit_cards(&mut self) -> usize {
+        if let Some(count) = self.credit_cards {
+            return count;
+        }
+        let count = find_credit_cards(self.text());
+        self.credit_cards = Some(count);
+        count
+    }
+
+    fn ibans(&mut self) -> usize {
+        if let Some(count) = self.ibans {
+            return count;
+        }
+        let count = find_ibans(self.text());
+        self.ibans = Some(count);
+        count
+    }
+
+    fn words(&mut self) -> &[String] {
+        if self.words.is_none() {
+            let mut seen = AHashSet::new();
+            let words = self
+                .text()
+                .split(|ch: char| !ch.is_alphanumeric())
+                .filter(|word| word.chars().nth(1).is_some())
+                .map(|word| word.to_lowercase())
+                .filter(|word| seen.insert(word.clone()))
+                .collect();
+            self.words = Some(words);
+        }
+        self.words.as_deref().unwrap_or_default()
+    }
+
+    fn attachment_types(&mut self) -> &[String] {
+        self.attachment_types.get_or_insert_with(|| {
+            let mut types = Vec::new();
+            for part in self.message.attachments() {
+                if let Some((_, ext)) = part
+                    .attachment_name()
+                    .and_then(|name| name.rsplit_once('.'))
+                {
+                    types.push(ext.to_lowercase());
+                }
+                if let Some(ct) = part.content_type() {
+                    types.push(format!(
+                        "{}/{}",
+                        ct.ctype(),
+                        ct.subtype().unwrap_or_default()
+                    ));
+                }
+            }
+            types.iter_mut().for_each(|t| t.make_ascii_lowercase());
+            types.sort_unstable();
+            types.dedup();
+            types
+        })
+    }
+}
+
+/// Counts the distinct card numbers in the text, written as 13 to 19 digits
+/// optionally separated by single spaces or dashes, that pass the Luhn check
+pub fn find_credit_cards(text: &str) -> usize {
+    let mut found = AHashSet::new();
+    let mut groups: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch.is_ascii_digit() {
+            match groups.last_mut() {
+                Some(group) if !group.is_empty() => group.push(ch),
+                _ => groups.push(ch.to_string()),
+            }
+            if chars.peek().is_some_and(|ch| *ch == ' ' || *ch == '-') {
+                chars.next();
+                if chars.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                    groups.push(String::new());
+                } else {
+                    find_card_numbers(&mut groups, &mut found);
+                }
+            }
+        } else {
+            find_card_numbers(&mut groups, &mut found);
+        }
+    }
+    find_card_numbers(&mut groups, &mut found);
+
+    found.len()
+}
+
+/// Looks for card numbers spanning consecutive digit groups
+fn find_card_numbers(groups: &mut Vec<String>, found: &mut AHashSet<String>) {
+    let mut start = 0;
+    while start < groups.len() {
+        let mut digits = String::new();
+        let mut card = None;
+        for (pos, group) in groups[start..].iter().enumerate() {
+            digits.push_str(group);
+            if digits.len() > 19 {
+                break;
+            } else if is_card_number(&digits) {
+                card = Some((pos, digits.clone()));
+            }
+        }
+
+        if let Some((pos, card)) = card {
+            found.insert(card);
+            start += pos + 1;
+        } else {
+            start += 1;
+        }
+    }
+    groups.clear();
+}
+
+fn is_card_number(digits: &str) -> bool {
+    (13..=19).contains(&digits.len())
+        && digits.bytes().any(|ch| ch != digits.as_bytes()[0])
+        && luhn_check(digits)
+}
+
+fn luhn_check(digits: &str) -> bool {
+    digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(pos, ch)| {
+            let digit = (ch - b'0') as u32;
+            if pos % 2 == 1 {
+                let double = digit * 2;
+                if double > 9 { double - 9 } else { double }
+            } else {
+                digit
+            }
+        })
+        .sum::<u32>()
+        % 10
+        == 0
+}
+
+/// Counts the distinct IBANs in the text, written either compact or in groups
+/// separated by single spaces, that pass the ISO 7064 mod 97 check
+pub fn find_ibans(text: &str) -> usize {
+    let mut found = AHashSet::new();
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+
+    while pos + 4 <= bytes.len() {
+        if !(bytes[pos].is_ascii_uppercase()
+            && bytes[pos + 1].is_ascii_uppercase()
+            && bytes[pos + 2].is_ascii_digit()
+            && bytes[pos + 3].is_ascii_digit()
+            && (pos == 0 || !bytes[pos - 1].is_ascii_alphanumeric()))
+        {
+            pos += 1;
+            continue;
+        }
+
+        // Collect space separated groups
+        let mut iban = String::new();
+        let mut group_ends = Vec::new();
+        let mut end = pos;
+        while end < bytes.len() && iban.len() <= 34 {
+            let ch = bytes[end];
+            if ch.is_ascii_uppercase() || ch.is_ascii_digit() {
+                iban.push(ch as char);
+            } else if ch == b' '
+                && bytes
+                    .get(end + 1)
+                    .is_some_and(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit())
+            {
+                group_ends.push((iban.len(), end));
+            } else {
+                break;
+            }
+            end += 1;
+        }
+        group_ends.push((iban.len(), end));
+
+        // Trailing groups might not be part of the IBAN
+        if let Some((len, end)) = group_ends
+            .into_iter()
+            .rev()
+            .find(|&(len, _)| (15..=34).contains(&len) && iban_check(&iban[..len]))
+        {
+            iban.truncate(len);
+            found.insert(iban);
+            pos = end;
+        } else {
+            pos += 1;
+        }
+    }
+
+    found.len()
+}
+
+fn iban_check(iban: &str) -> bool {
+    let mut remainder = 0u32;
+    for ch in iban[4..].bytes().chain(iban[..4].bytes()) {
+        remainder = if ch.is_ascii_digit() {
+            (remainder * 10 + (ch - b'0') as u32) % 97
+        } else {
+            (remainder * 100 + (ch - b'A' + 10) as u32) % 97
+        };
+    }
+    remainder == 1
+}
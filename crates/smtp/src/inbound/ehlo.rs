@@ -214,12 +214,13 @@ impl<T: SessionStream> Session<T> {
 
         // Authentication
         if !self.is_authenticated() {
-            response.auth_mechanisms = self
-                .server
-                .eval_if::<Mechanism, _>(&ac.mechanisms, self, self.data.session_id)
-                .await
-                .unwrap_or_default()
-                .into();
+            response.auth_mechanisms = self.available_mechanisms(
+                self.server
+                    .eval_if::<Mechanism, _>(&ac.mechanisms, self, self.data.session_id)
+                    .await
+                    .unwrap_or_default()
+                    .into(),
+            );
             if response.auth_mechanisms != 0 {
                 response.capabilities |= EXT_AUTH;
             }
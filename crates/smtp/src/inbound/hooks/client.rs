@@ -5,6 +5,7 @@
  */
 
 use common::config::smtp::session::MTAHook;
+use store::write::now;
 use utils::HttpLimitResponse;
 
 use super::{Request, Response};
@@ -13,17 +14,26 @@ pub(super) async fn send_mta_hook_request(
     mta_hook: &MTAHook,
     request: Request,
 ) -> Result<Response, String> {
-    let response = reqwest::Client::builder()
+    let body = serde_json::to_string(&request)
+        .map_err(|err| format!("Failed to serialize Hook request: {}", err))?;
+    let mut headers = mta_hook.headers.clone();
+    if let Some(signer) = &mta_hook.signer {
+        signer.sign_request(&mut headers, now(), body.as_bytes());
+    }
+
+    let mut client = reqwest::Client::builder()
         .timeout(mta_hook.timeout)
-        .danger_accept_invalid_certs(mta_hook.tls_allow_invalid_certs)
+        .danger_accept_invalid_certs(mta_hook.tls_allow_invalid_certs);
+    if let Some(identity) = &mta_hook.identity {
+        client = client.identity(identity.clone());
+    }
+
+    let response = client
         .build()
         .map_err(|err| format!("Failed to create HTTP client: {}", err))?
         .post(&mta_hook.url)
-        .headers(mta_hook.headers.clone())
-        .body(
-            serde_json::to_string(&request)
-                .map_err(|err| format!("Failed to serialize Hook request: {}", err))?,
-        )
+        .headers(headers)
+        .body(body)
         .send()
         .await
         .map_err(|err| format!("Hook request failed: {err}"))?;
@@ -5,7 +5,8 @@
  */
 
 use crate::{
-    core::{Session, SessionAddress},
+    core::{Session, SessionAddress, sending_limits::SendingLimits},
+    reporting::complaints::Complaints,
     scripts::ScriptResult,
 };
 use common::{config::smtp::session::Stage, listener::SessionStream, scripts::ScriptModification};
@@ -15,7 +16,7 @@ use std::{
     borrow::Cow,
     time::{Duration, Instant, SystemTime},
 };
-use trc::SmtpEvent;
+use trc::{ComplaintEvent, SendingLimitEvent, SmtpEvent};
 use utils::{DomainPart, config::Rate};
 
 impl<T: SessionStream> Session<T> {
@@ -271,6 +272,48 @@ impl<T: SessionStream> Session<T> {
             _ => (),
         }
 
+        // Reject accounts suspended due to feedback loop complaints
+        if self.server.core.smtp.report.analysis.complaints.suspend
+            && let Some(access_token) = &self.data.authenticated_as
+            && self
+                .server
+                .is_sending_suspended(access_token.primary_id())
+                .await
+                .unwrap_or_default()
+        {
+            trc::event!(
+                Complaint(ComplaintEvent::SendingRejected),
+                SpanId = self.data.session_id,
+                AccountId = access_token.primary_id(),
+                From = self.data.mail_from.as_ref().unwrap().address_lcase.clone(),
+            );
+            self.data.mail_from = None;
+            return self
+                .write(b"550 5.7.1 Your account has been suspended from sending.\r\n")
+                .await;
+        }
+
+        // Reject accounts locked out due to suspicious activity
+        if self.server.core.smtp.sending_limits.enable
+            && let Some(access_token) = &self.data.authenticated_as
+            && self
+                .server
+                .is_sending_locked(access_token.primary_id())
+                .await
+                .unwrap_or_default()
+        {
+            trc::event!(
+                SendingLimit(SendingLimitEvent::SendingRejected),
+                SpanId = self.data.session_id,
+                AccountId = access_token.primary_id(),
+                From = self.data.mail_from.as_ref().unwrap().address_lcase.clone(),
+            );
+            self.data.mail_from = None;
+            return self
+                .write(b"550 5.7.1 Your account has been locked out from sending.\r\n")
+                .await;
+        }
+
         // Validate parameters
         let config = &self.server.core.smtp.session.extensions;
         let config_data = &self.server.core.smtp.session.data;
@@ -14,6 +14,7 @@ use mail_auth::{
 
 pub mod auth;
 pub mod data;
+pub mod dlp;
 pub mod ehlo;
 pub mod hooks;
 pub mod mail;
@@ -5,11 +5,18 @@
  */
 
 use crate::{
-    core::{Session, SessionAddress},
+    core::{
+        Session, SessionAddress,
+        sending_limits::{RcptVerdict, SendingLimits},
+    },
+    queue::suppression::SuppressionList,
     scripts::ScriptResult,
 };
 use common::{
-    KV_GREYLIST, config::smtp::session::Stage, listener::SessionStream, scripts::ScriptModification,
+    KV_GREYLIST,
+    config::smtp::{session::Stage, suppression::SuppressionAction},
+    listener::SessionStream,
+    scripts::ScriptModification,
 };
 use directory::backend::RcptType;
 use smtp_proto::{
@@ -17,7 +24,7 @@ use smtp_proto::{
 };
 use std::borrow::Cow;
 use store::dispatch::lookup::KeyValue;
-use trc::{SecurityEvent, SmtpEvent};
+use trc::{SecurityEvent, SmtpEvent, SuppressionEvent};
 use utils::DomainPart;
 
 impl<T: SessionStream> Session<T> {
@@ -30,6 +37,10 @@ impl<T: SessionStream> Session<T> {
                 || to.address.contains("delay@")
             {
                 return self.write(b"451 4.5.3 Try again later.\r\n").await;
+            } else if to.address.contains("throttle@") {
+                return self
+                    .write(b"421 4.7.0 Too many messages, try again later.\r\n")
+                    .await;
             } else if to.address.contains("slow@") {
                 tokio::time::sleep(std::time::Duration::from_secs(
                     rand::random::<u64>() % 5 + 5,
@@ -191,6 +202,7 @@ impl<T: SessionStream> Session<T> {
         // Verify address
         let rcpt = self.data.rcpt_to.last().unwrap();
         let mut rcpt_members = None;
+        let mut list_rcpt = None;
         if let Some(directory) = self
             .server
             .eval_if::<String, _>(&rcpt_config.directory, self, self.data.session_id)
@@ -206,7 +218,22 @@ impl<T: SessionStream> Session<T> {
                     {
                         Ok(RcptType::Mailbox) => {}
                         Ok(RcptType::List(members)) => {
-                            rcpt_members = Some(members);
+                            if self.server.core.smtp.mailing_list.enable {
+                                match self.list_rcpt(&rcpt.address_lcase).await {
+                                    Ok(Some(list)) => {
+                                        list_rcpt = Some(list);
+                                    }
+                                    Ok(None) => {
+                                        rcpt_members = Some(members);
+                                    }
+                                    Err(response) => {
+                                        self.data.rcpt_to.pop();
+                                        return self.write(response).await;
+                                    }
+                                }
+                            } else {
+                                rcpt_members = Some(members);
+                            }
                         }
                         Ok(RcptType::Invalid) => {
                             trc::event!(
@@ -284,6 +311,86 @@ impl<T: SessionStream> Session<T> {
                 .await;
         }
 
+        // Suppression list
+        if self.server.core.smtp.suppression.enable
+            && let Some(access_token) = &self.data.authenticated_as
+        {
+            let rcpt = self.data.rcpt_to.last().unwrap();
+            match self
+                .server
+                .suppression_get(access_token.tenant_id(), &rcpt.address_lcase)
+                .await
+            {
+                Ok(Some(entry)) => {
+                    if self.server.core.smtp.suppression.action == SuppressionAction::Reject {
+                        trc::event!(
+                            Suppression(SuppressionEvent::RcptRejected),
+                            SpanId = self.data.session_id,
+                            To = entry.address,
+                            Reason = entry.reason.as_str(),
+                        );
+
+                        self.data.rcpt_to.pop();
+                        return self
+                            .write(b"550 5.7.1 Recipient address is on the suppression list.\r\n")
+                            .await;
+                    } else {
+                        trc::event!(
+                            Suppression(SuppressionEvent::RcptWarning),
+                            SpanId = self.data.session_id,
+                            To = entry.address,
+                            Reason = entry.reason.as_str(),
+                        );
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(self.data.session_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to check suppression list.")
+                    );
+                }
+            }
+        }
+
+        // Per-account sending limits
+        if self.server.core.smtp.sending_limits.enable
+            && let Some(access_token) = &self.data.authenticated_as
+        {
+            let rcpt = self.data.rcpt_to.last().unwrap();
+            match self
+                .server
+                .sending_rcpt(
+                    access_token.primary_id(),
+                    &rcpt.address_lcase,
+                    self.data.session_id,
+                )
+                .await
+            {
+                Ok(RcptVerdict::Allow) => {}
+                Ok(RcptVerdict::RateExceeded | RcptVerdict::UniqueExceeded) => {
+                    self.data.rcpt_to.pop();
+                    return self
+                        .write(b"451 4.7.1 Sending limit exceeded, try again later.\r\n")
+                        .await;
+                }
+                Ok(RcptVerdict::LockedOut) => {
+                    self.data.rcpt_to.pop();
+                    return self
+                        .write(b"550 5.7.1 Your account has been locked out from sending.\r\n")
+                        .await;
+                }
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(self.data.session_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to check sending limits.")
+                    );
+                }
+            }
+        }
+
         if self.is_allowed().await {
             // Greylist
             if let Some(greylist_duration) = self
@@ -371,6 +478,12 @@ impl<T: SessionStream> Session<T> {
                 .await;
         }
 
+        // Lists handled by the list manager are kept in the envelope and
+        // processed once the message is received
+        if let Some(list_rcpt) = list_rcpt {
+            self.data.list_rcpts.push(list_rcpt);
+        }
+
         // Expand list
         if let Some(members) = rcpt_members {
             let list_addr = self.data.rcpt_to.pop().unwrap();
@@ -85,16 +85,17 @@ impl<T: SessionStream> Session<T> {
                                 mechanism,
                                 initial_response,
                             } => {
-                                let auth: u64 = self
-                                    .server
-                                    .eval_if::<Mechanism, _>(
-                                        &self.server.core.smtp.session.auth.mechanisms,
-                                        self,
-                                        self.data.session_id,
-                                    )
-                                    .await
-                                    .unwrap_or_default()
-                                    .into();
+                                let auth = self.available_mechanisms(
+                                    self.server
+                                        .eval_if::<Mechanism, _>(
+                                            &self.server.core.smtp.session.auth.mechanisms,
+                                            self,
+                                            self.data.session_id,
+                                        )
+                                        .await
+                                        .unwrap_or_default()
+                                        .into(),
+                                );
                                 if auth == 0 || self.params.auth_directory.is_none() {
                                     trc::event!(
                                         Smtp(SmtpEvent::AuthNotAllowed),
@@ -469,6 +470,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
         self.data.mail_from = None;
         self.data.spf_mail_from = None;
         self.data.rcpt_to.clear();
+        self.data.list_rcpts.clear();
         self.data.message = Vec::with_capacity(0);
         self.data.priority = 0;
         self.data.delivery_by = 0;
@@ -584,6 +586,11 @@ impl<T: SessionStream> ResolveVariable for Session<T> {
             V_LOCAL_IP => self.data.local_ip_str.as_str().into(),
             V_LOCAL_PORT => self.data.local_port.into(),
             V_TLS => self.stream.is_tls().into(),
+            V_PEER_UID | V_PEER_GID | V_PEER_PID => self
+                .stream
+                .peer_credentials()
+                .map(|peer| peer.resolve_variable(variable))
+                .unwrap_or_default(),
             V_PRIORITY => self.data.priority.to_compact_string().into(),
             V_PROTOCOL => self.instance.protocol.as_str().into(),
             V_ASN => self
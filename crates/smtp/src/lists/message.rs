@@ -0,0 +1,194 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::ListInfo;
+use common::config::smtp::mailing_list::{ListPolicy, ListPosting, ListReplyTo};
+use mail_builder::headers::{Header, address::Address};
+use mail_parser::{HeaderName, MessageParser};
+
+// Headers owned by the list manager, any copies set by the sender or by an
+// upstream list are dropped before adding our own
+static LIST_HEADERS: &[&str] = &[
+    "List-Id",
+    "List-Post",
+    "List-Help",
+    "List-Subscribe",
+    "List-Unsubscribe",
+    "List-Unsubscribe-Post",
+    "List-Owner",
+    "List-Archive",
+    "Precedence",
+    "X-Loop",
+];
+
+/// A list post with its headers rewritten, ready to be sent to each member
+pub struct ListPost {
+    headers: Vec<u8>,
+    body: Vec<u8>,
+    list_address: String,
+}
+
+impl ListPost {
+    pub fn new(
+        raw_message: &[u8],
+        list: &ListInfo,
+        policy: &ListPolicy,
+        rewrite_from: bool,
+    ) -> Option<Self> {
+        let message = MessageParser::new().parse_headers(raw_message)?;
+        let root = message.root_part();
+        let body_offset = root.headers.last()?.offset_end as usize;
+        let (local_part, domain) = list.address.rsplit_once('@')?;
+        let mut headers = Vec::with_capacity(body_offset + 512);
+        let mut has_reply_to = false;
+        let mut original_from = None;
+
+        for header in &root.headers {
+            let raw_header = &raw_message[header.offset_field as usize..header.offset_end as usize];
+            let name = header.name.as_str();
+
+            if LIST_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+                continue;
+            }
+
+            match &header.name {
+                HeaderName::Subject => {
+                    if let Some(prefix) = &policy.subject_prefix {
+                        let value =
+                            &raw_message[header.offset_start as usize..header.offset_end as usize];
+                        let value = std::str::from_utf8(value).unwrap_or_default().trim();
+                        headers.extend_from_slice(b"Subject: ");
+                        if !value.contains(prefix.as_str()) {
+                            headers.extend_from_slice(prefix.as_bytes());
+                            headers.push(b' ');
+                        }
+                        headers.extend_from_slice(value.as_bytes());
+                        headers.extend_from_slice(b"\r\n");
+                        continue;
+                    }
+                }
+                HeaderName::From if rewrite_from => {
+                    original_from = Some(raw_header);
+                    continue;
+                }
+                HeaderName::ReplyTo => {
+                    if policy.reply_to == ListReplyTo::List {
+                        continue;
+                    }
+                    has_reply_to = true;
+                }
+                _ => {}
+            }
+
+            headers.extend_from_slice(raw_header);
+        }
+
+        // Replace the From header with the list address so that DMARC alignment
+        // is preserved, keeping the original author in X-Original-From
+        if let Some(original_from) = original_from {
+            let author = message.from().and_then(|addr| addr.first());
+            let author_name = author
+                .and_then(|addr| addr.name())
+                .or_else(|| author.and_then(|addr| addr.address()))
+                .unwrap_or("Unknown");
+            let list_name = list.description.as_deref().unwrap_or(list.name.as_str());
+
+            headers.extend_from_slice(b"From: ");
+            let _ = Address::new_address(
+                format!("{author_name} via {list_name}").into(),
+                list.address.as_str(),
+            )
+            .write_header(&mut headers, 6);
+            headers.extend_from_slice(b"X-Original-");
+            headers.extend_from_slice(original_from);
+
+            if !has_reply_to
+                && policy.reply_to == ListReplyTo::Sender
+                && let Some(address) = author.and_then(|addr| addr.address())
+            {
+                headers.extend_from_slice(b"Reply-To: ");
+                let _ = Address::new_address(author.and_then(|addr| addr.name()), address)
+                    .write_header(&mut headers, 10);
+            }
+        }
+
+        if policy.reply_to == ListReplyTo::List {
+            headers.extend_from_slice(b"Reply-To: <");
+            headers.extend_from_slice(list.address.as_bytes());
+            headers.extend_from_slice(b">\r\n");
+        }
+
+        // RFC 2919 and RFC 2369 headers
+        headers.extend_from_slice(b"List-Id: ");
+        if let Some(description) = &list.description {
+            let _ = Address::new_address(
+                description.as_str().into(),
+                format!("{local_part}.{domain}"),
+            )
+            .write_header(&mut headers, 9);
+        } else {
+            headers.extend_from_slice(format!("<{local_part}.{domain}>\r\n").as_bytes());
+        }
+        if policy.posting == ListPosting::Announce {
+            headers.extend_from_slice(b"List-Post: NO\r\n");
+        } else {
+            headers
+                .extend_from_slice(format!("List-Post: <mailto:{}>\r\n", list.address).as_bytes());
+        }
+        if policy.allow_subscribe {
+            headers.extend_from_slice(
+                format!("List-Subscribe: <mailto:{local_part}+subscribe@{domain}>\r\n").as_bytes(),
+            );
+        }
+        headers.extend_from_slice(
+            format!("List-Owner: <mailto:{local_part}+owner@{domain}>\r\n").as_bytes(),
+        );
+        headers.extend_from_slice(b"Precedence: list\r\n");
+        headers.extend_from_slice(format!("X-Loop: {}\r\n", list.address).as_bytes());
+
+        Some(ListPost {
+            headers,
+            body: raw_message[body_offset..].to_vec(),
+            list_address: list.address.clone(),
+        })
+    }
+
+    /// Build the copy sent to a member, including its RFC 8058 one-click
+    /// unsubscribe URL
+    pub fn build(&self, unsubscribe_url: Option<&str>) -> Vec<u8> {
+        let mut message = Vec::with_capacity(self.headers.len() + self.body.len() + 256);
+        message.extend_from_slice(&self.headers);
+        if let Some((local_part, domain)) = self.list_address.rsplit_once('@') {
+            message.extend_from_slice(b"List-Unsubscribe: ");
+            if let Some(url) = unsubscribe_url {
+                message.extend_from_slice(format!("<{url}>,\r\n\t").as_bytes());
+            }
+            message.extend_from_slice(
+                format!("<mailto:{local_part}+unsubscribe@{domain}>\r\n").as_bytes(),
+            );
+            if unsubscribe_url.is_some() {
+                message.extend_from_slice(b"List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n");
+            }
+        }
+        message.extend_from_slice(&self.body);
+        message
+    }
+}
+
+/// Returns true when the message was already distributed by this list
+pub fn is_list_loop(raw_message: &[u8], list_address: &str) -> bool {
+    MessageParser::new()
+        .parse_headers(raw_message)
+        .is_some_and(|message| {
+            message.root_part().headers.iter().any(|header| {
+                header.name.as_str().eq_ignore_ascii_case("X-Loop")
+                    && std::str::from_utf8(
+                        &raw_message[header.offset_start as usize..header.offset_end as usize],
+                    )
+                    .is_ok_and(|value| value.trim().eq_ignore_ascii_case(list_address))
+            })
+        })
+}
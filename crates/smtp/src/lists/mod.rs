@@ -0,0 +1,485 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::reporting::SmtpReporting;
+use common::{
+    Server,
+    auth::oauth::GrantType,
+    config::smtp::mailing_list::{ListFromRewrite, ListNonMember, ListPolicy, ListPosting},
+};
+use directory::{
+    Type,
+    backend::internal::{
+        PrincipalField, PrincipalUpdate, PrincipalValue,
+        lookup::DirectoryStore,
+        manage::{ManageDirectory, UpdatePrincipal},
+    },
+};
+use mail_auth::dmarc::Policy;
+use mail_builder::{MessageBuilder, headers::HeaderType};
+use message::ListPost;
+use std::future::Future;
+use trc::{AddContext, MailingListEvent};
+
+pub mod message;
+pub mod moderation;
+pub mod session;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListInfo {
+    pub id: u32,
+    pub name: String,
+    pub address: String,
+    pub description: Option<String>,
+    pub members: Vec<String>,
+}
+
+/// Request addressed to one of the list's `+command` addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListCommand {
+    Post,
+    Subscribe,
+    Unsubscribe,
+    Confirm,
+    Owner,
+    Bounces,
+}
+
+/// What happens to a post sent to the list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostAction {
+    Distribute,
+    Moderate,
+    Discard,
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListRecipient {
+    pub address: String,
+    pub list: ListInfo,
+    pub command: ListCommand,
+    pub action: PostAction,
+}
+
+impl ListCommand {
+    pub fn parse(detail: &str) -> Option<Self> {
+        match detail.to_ascii_lowercase().as_str() {
+            "subscribe" | "join" => Some(ListCommand::Subscribe),
+            "unsubscribe" | "leave" => Some(ListCommand::Unsubscribe),
+            "confirm" => Some(ListCommand::Confirm),
+            "owner" | "request" => Some(ListCommand::Owner),
+            "bounces" => Some(ListCommand::Bounces),
+            _ => None,
+        }
+    }
+}
+
+impl ListInfo {
+    pub fn is_member(&self, address: &str) -> bool {
+        self.members.iter().any(|member| member == address)
+    }
+
+    /// Decide whether a post from `sender` is distributed, held or refused,
+    /// members and moderators are only recognized when `is_verified` is set
+    pub fn post_action(&self, policy: &ListPolicy, sender: &str, is_verified: bool) -> PostAction {
+        let is_allowed = match policy.posting {
+            ListPosting::Open => true,
+            ListPosting::Members => {
+                is_verified && (self.is_member(sender) || policy.is_moderator(sender))
+            }
+            ListPosting::Announce => is_verified && policy.is_moderator(sender),
+        };
+
+        if is_allowed {
+            PostAction::Distribute
+        } else {
+            match policy.non_member {
+                ListNonMember::Moderate => PostAction::Moderate,
+                ListNonMember::Reject => PostAction::Reject,
+                ListNonMember::Discard => PostAction::Discard,
+            }
+        }
+    }
+
+    pub fn command_address(&self, command: &str) -> String {
+        match self.address.rsplit_once('@') {
+            Some((local_part, domain)) => format!("{local_part}+{command}@{domain}"),
+            None => self.address.clone(),
+        }
+    }
+}
+
+/// Returns true when the From header has to be replaced with the list address
+pub fn rewrite_from(policy: &ListPolicy, dmarc_policy: Option<&Policy>) -> bool {
+    match policy.from_rewrite {
+        ListFromRewrite::Never => false,
+        ListFromRewrite::Always => true,
+        ListFromRewrite::Dmarc => {
+            matches!(dmarc_policy, Some(Policy::Reject | Policy::Quarantine))
+        }
+    }
+}
+
+pub trait MailingList: Sync + Send {
+    fn list_by_address(
+        &self,
+        address: &str,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<Option<(ListInfo, ListCommand)>>> + Send;
+
+    fn list_by_id(
+        &self,
+        list_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<ListInfo>>> + Send;
+
+    fn list_distribute(
+        &self,
+        list: &ListInfo,
+        sender: &str,
+        raw_message: &[u8],
+        rewrite_from: bool,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<usize>> + Send;
+
+    fn list_subscribe(
+        &self,
+        list: &ListInfo,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn list_unsubscribe(
+        &self,
+        list: &ListInfo,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn list_send_confirmation(
+        &self,
+        list: &ListInfo,
+        address: &str,
+        command: ListCommand,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_confirm(
+        &self,
+        token: &str,
+    ) -> impl Future<Output = trc::Result<Option<(ListInfo, ListCommand, String)>>> + Send;
+
+    fn list_token(
+        &self,
+        command: ListCommand,
+        list_id: u32,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<String>> + Send;
+}
+
+impl MailingList for Server {
+    async fn list_by_address(
+        &self,
+        address: &str,
+        session_id: u64,
+    ) -> trc::Result<Option<(ListInfo, ListCommand)>> {
+        let store = self.store();
+        if let Some(list_id) = store.email_to_id(address).await? {
+            return self
+                .list_by_id(list_id)
+                .await
+                .map(|list| list.map(|list| (list, ListCommand::Post)));
+        }
+
+        // Command addresses such as list+subscribe@domain
+        if let Some((local_part, domain)) = address.rsplit_once('@')
+            && let Some((local_part, detail)) = local_part.rsplit_once('+')
+            && let Some(command) = ListCommand::parse(detail)
+            && let Some(list_id) = store.email_to_id(&format!("{local_part}@{domain}")).await?
+        {
+            return self
+                .list_by_id(list_id)
+                .await
+                .map(|list| list.map(|list| (list, command)));
+        }
+
+        // Any other subaddress is a post to the list
+        let subaddress = self
+            .core
+            .smtp
+            .session
+            .rcpt
+            .subaddressing
+            .to_subaddress(self, address, session_id)
+            .await;
+        if subaddress != address
+            && let Some(list_id) = store.email_to_id(subaddress.as_ref()).await?
+        {
+            return self
+                .list_by_id(list_id)
+                .await
+                .map(|list| list.map(|list| (list, ListCommand::Post)));
+        }
+
+        Ok(None)
+    }
+
+    async fn list_by_id(&self, list_id: u32) -> trc::Result<Option<ListInfo>> {
+        let store = self.store();
+        if let Some(principal) = store
+            .get_principal(list_id)
+            .await
+            .caused_by(trc::location!())?
+            .filter(|p| p.typ() == Type::List)
+        {
+            let Some(address) = principal.primary_email().map(|e| e.to_lowercase()) else {
+                return Ok(None);
+            };
+            let mut members = store
+                .expn_by_id(list_id)
+                .await
+                .caused_by(trc::location!())?
+                .into_iter()
+                .map(|member| member.to_lowercase())
+                .filter(|member| member != &address)
+                .collect::<Vec<_>>();
+            members.sort_unstable();
+            members.dedup();
+
+            Ok(Some(ListInfo {
+                id: list_id,
+                name: principal.name().to_string(),
+                description: principal.description().map(|d| d.to_string()),
+                address,
+                members,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn list_distribute(
+        &self,
+        list: &ListInfo,
+        sender: &str,
+        raw_message: &[u8],
+        rewrite_from: bool,
+        session_id: u64,
+    ) -> trc::Result<usize> {
+        let config = &self.core.smtp.mailing_list;
+        let policy = config.policy(&list.name);
+        let post = ListPost::new(raw_message, list, policy, rewrite_from).ok_or_else(|| {
+            trc::EventType::MailingList(MailingListEvent::Error)
+                .into_err()
+                .details("Failed to parse list post")
+                .caused_by(trc::location!())
+        })?;
+        let return_path = list.command_address("bounces");
+        let total = list.members.len();
+
+        // Each member receives its own copy with a personal unsubscribe URL,
+        // copies are generated in the background so large lists do not hold
+        // up the session that delivered the post
+        let server = self.clone();
+        let list = list.clone();
+        let sender = sender.to_string();
+        tokio::spawn(async move {
+            let config = &server.core.smtp.mailing_list;
+            for member in &list.members {
+                let unsubscribe_url = match server
+                    .list_token(ListCommand::Unsubscribe, list.id, member)
+                    .await
+                {
+                    Ok(token) => Some(format!(
+                        "{}/unsubscribe?t={}",
+                        config.url,
+                        form_urlencoded::byte_serialize(token.as_bytes()).collect::<String>()
+                    )),
+                    Err(err) => {
+                        trc::error!(err.span_id(session_id).caused_by(trc::location!()));
+                        None
+                    }
+                };
+
+                server
+                    .send_autogenerated(
+                        return_path.as_str(),
+                        [member.as_str()].into_iter(),
+                        post.build(unsubscribe_url.as_deref()),
+                        Some(&server.core.smtp.mail_auth.dkim.sign),
+                        session_id,
+                    )
+                    .await;
+            }
+
+            trc::event!(
+                MailingList(MailingListEvent::Posted),
+                SpanId = session_id,
+                To = list.address,
+                From = sender,
+                Total = list.members.len(),
+            );
+        });
+
+        Ok(total)
+    }
+
+    async fn list_subscribe(&self, list: &ListInfo, address: &str) -> trc::Result<bool> {
+        if list.is_member(address) {
+            return Ok(false);
+        }
+
+        let changed_principals = self
+            .store()
+            .update_principal(UpdatePrincipal::by_id(list.id).with_updates(vec![
+                PrincipalUpdate::add_item(
+                    PrincipalField::ExternalMembers,
+                    PrincipalValue::String(address.to_string()),
+                ),
+            ]))
+            .await
+            .caused_by(trc::location!())?;
+        self.invalidate_principal_caches(changed_principals).await;
+
+        trc::event!(
+            MailingList(MailingListEvent::Subscribed),
+            To = list.address.clone(),
+            From = address.to_string(),
+        );
+
+        Ok(true)
+    }
+
+    async fn list_unsubscribe(&self, list: &ListInfo, address: &str) -> trc::Result<bool> {
+        if !list.is_member(address) {
+            return Ok(false);
+        }
+
+        // Local accounts are members of the list, everyone else is stored as
+        // an external member
+        let store = self.store();
+        let mut updates = vec![PrincipalUpdate::remove_item(
+            PrincipalField::ExternalMembers,
+            PrincipalValue::String(address.to_string()),
+        )];
+        if let Some(member_id) = store.email_to_id(address).await?
+            && let Some(member_name) = store.get_principal_name(member_id).await?
+        {
+            updates.push(PrincipalUpdate::remove_item(
+                PrincipalField::Members,
+                PrincipalValue::String(member_name),
+            ));
+        }
+
+        let changed_principals = store
+            .update_principal(UpdatePrincipal::by_id(list.id).with_updates(updates))
+            .await
+            .caused_by(trc::location!())?;
+        self.invalidate_principal_caches(changed_principals).await;
+
+        trc::event!(
+            MailingList(MailingListEvent::Unsubscribed),
+            To = list.address.clone(),
+            From = address.to_string(),
+        );
+
+        Ok(true)
+    }
+
+    async fn list_send_confirmation(
+        &self,
+        list: &ListInfo,
+        address: &str,
+        command: ListCommand,
+        session_id: u64,
+    ) -> trc::Result<()> {
+        let config = &self.core.smtp.mailing_list;
+        let token = self.list_token(command, list.id, address).await?;
+        let (action, path) = if command == ListCommand::Subscribe {
+            ("subscribe to", "confirm")
+        } else {
+            ("unsubscribe from", "unsubscribe")
+        };
+        let url = format!(
+            "{}/{path}?t={}",
+            config.url,
+            form_urlencoded::byte_serialize(token.as_bytes()).collect::<String>()
+        );
+        let body = format!(
+            concat!(
+                "We received a request to {} the list {}.\r\n\r\n",
+                "To confirm, visit the link below or reply to this message ",
+                "without changing its subject:\r\n\r\n{}\r\n\r\n",
+                "If you did not make this request, you can ignore this message.\r\n"
+            ),
+            action, list.address, url
+        );
+        let confirm_address = list.command_address("confirm");
+        let message = MessageBuilder::new()
+            .from(confirm_address.as_str())
+            .to(address)
+            .subject(format!("Confirm: {token}"))
+            .header("Auto-Submitted", HeaderType::Text("auto-replied".into()))
+            .header("X-Loop", HeaderType::Text(list.address.as_str().into()))
+            .text_body(body)
+            .write_to_vec()
+            .unwrap_or_default();
+
+        self.send_autogenerated(
+            list.command_address("bounces"),
+            [address].into_iter(),
+            message,
+            Some(&self.core.smtp.mail_auth.dkim.sign),
+            session_id,
+        )
+        .await;
+
+        trc::event!(
+            MailingList(if command == ListCommand::Subscribe {
+                MailingListEvent::SubscribeRequested
+            } else {
+                MailingListEvent::UnsubscribeRequested
+            }),
+            SpanId = session_id,
+            To = list.address.clone(),
+            From = address.to_string(),
+        );
+
+        Ok(())
+    }
+
+    async fn list_confirm(
+        &self,
+        token: &str,
+    ) -> trc::Result<Option<(ListInfo, ListCommand, String)>> {
+        let token = self.validate_access_token(None, token).await?;
+        let command = match token.grant_type {
+            GrantType::ListSubscribe => ListCommand::Subscribe,
+            GrantType::ListUnsubscribe => ListCommand::Unsubscribe,
+            _ => return Ok(None),
+        };
+
+        Ok(self
+            .list_by_id(token.account_id)
+            .await?
+            .map(|list| (list, command, token.client_id)))
+    }
+
+    async fn list_token(
+        &self,
+        command: ListCommand,
+        list_id: u32,
+        address: &str,
+    ) -> trc::Result<String> {
+        let config = &self.core.smtp.mailing_list;
+        let (grant_type, expiry) = if command == ListCommand::Subscribe {
+            (GrantType::ListSubscribe, config.confirm_expiry)
+        } else {
+            (GrantType::ListUnsubscribe, config.unsubscribe_expiry)
+        };
+
+        self.encode_access_token(grant_type, list_id, address, expiry)
+            .await
+    }
+}
@@ -0,0 +1,301 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{ListInfo, MailingList};
+use crate::reporting::SmtpReporting;
+use common::{KV_LIST_MODERATION, Server};
+use mail_builder::{MessageBuilder, headers::HeaderType};
+use mail_parser::MessageParser;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use store::{
+    IterateParams, U64_LEN, ValueKey,
+    dispatch::lookup::{serialize_with_expiry, value_without_expiry},
+    write::{
+        BatchBuilder, InMemoryClass, Operation, ValueClass, ValueOp,
+        key::{DeserializeBigEndian, KeySerializer},
+        now,
+    },
+};
+use trc::{AddContext, MailingListEvent};
+use types::blob_hash::BlobHash;
+
+/// A post from a sender that is not allowed to post, waiting for a moderator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeldPost {
+    pub id: u64,
+    pub list_id: u32,
+    pub sender: String,
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    pub size: usize,
+    pub received: u64,
+    pub expires: u64,
+    pub rewrite_from: bool,
+    #[serde(skip)]
+    pub blob_hash: BlobHash,
+}
+
+pub trait ListModeration: Sync + Send {
+    fn list_hold(
+        &self,
+        list: &ListInfo,
+        sender: &str,
+        raw_message: &[u8],
+        rewrite_from: bool,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<u64>> + Send;
+
+    fn list_held_posts(
+        &self,
+        list_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<HeldPost>>> + Send;
+
+    fn list_held_post(
+        &self,
+        list_id: u32,
+        id: u64,
+    ) -> impl Future<Output = trc::Result<Option<HeldPost>>> + Send;
+
+    fn list_moderate(
+        &self,
+        list: &ListInfo,
+        id: u64,
+        approve: bool,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl ListModeration for Server {
+    async fn list_hold(
+        &self,
+        list: &ListInfo,
+        sender: &str,
+        raw_message: &[u8],
+        rewrite_from: bool,
+        session_id: u64,
+    ) -> trc::Result<u64> {
+        let config = &self.core.smtp.mailing_list;
+        let (blob_hash, _) = self
+            .put_temporary_blob(list.id, raw_message, config.moderation_expiry)
+            .await
+            .caused_by(trc::location!())?;
+        let headers = MessageParser::new().parse_headers(raw_message);
+        let received = now();
+        let post = HeldPost {
+            id: self.inner.data.queue_id_gen.generate(),
+            list_id: list.id,
+            sender: sender.to_string(),
+            from: headers.as_ref().and_then(|message| {
+                message
+                    .from()
+                    .and_then(|from| from.first())
+                    .and_then(|from| from.address())
+                    .map(|from| from.to_string())
+            }),
+            subject: headers
+                .as_ref()
+                .and_then(|message| message.subject())
+                .map(|subject| subject.to_string()),
+            size: raw_message.len(),
+            received,
+            expires: received + config.moderation_expiry,
+            rewrite_from,
+            blob_hash,
+        };
+
+        let mut batch = BatchBuilder::new();
+        batch.any_op(Operation::Value {
+            class: held_post_key(list.id, post.id),
+            op: ValueOp::Set(post.serialize()),
+        });
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            MailingList(MailingListEvent::Held),
+            SpanId = session_id,
+            To = list.address.clone(),
+            From = sender.to_string(),
+            Id = post.id,
+        );
+
+        // Let the moderators know there is a post waiting for them
+        let moderators = &config.policy(&list.name).moderators;
+        if !moderators.is_empty() {
+            let body = format!(
+                concat!(
+                    "A message sent to the list {} is awaiting moderation.\r\n\r\n",
+                    "Sender: {}\r\nSubject: {}\r\nId: {}\r\n\r\n",
+                    "The message will be discarded if it is not approved within {} days.\r\n"
+                ),
+                list.address,
+                sender,
+                post.subject.as_deref().unwrap_or_default(),
+                post.id,
+                config.moderation_expiry / 86400
+            );
+            let owner_address = list.command_address("owner");
+            let message = MessageBuilder::new()
+                .from(owner_address.as_str())
+                .to(moderators
+                    .iter()
+                    .map(|moderator| moderator.as_str())
+                    .collect::<Vec<_>>())
+                .subject(format!("Post to {} awaiting moderation", list.address))
+                .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+                .header("X-Loop", HeaderType::Text(list.address.as_str().into()))
+                .text_body(body)
+                .write_to_vec()
+                .unwrap_or_default();
+
+            self.send_autogenerated(
+                list.command_address("bounces"),
+                moderators.iter(),
+                message,
+                Some(&self.core.smtp.mail_auth.dkim.sign),
+                session_id,
+            )
+            .await;
+        }
+
+        Ok(post.id)
+    }
+
+    async fn list_held_posts(&self, list_id: u32) -> trc::Result<Vec<HeldPost>> {
+        let mut posts = Vec::new();
+        let current_time = now();
+
+        self.store()
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(held_post_key(list_id, 0)),
+                    ValueKey::from(held_post_key(list_id, u64::MAX)),
+                )
+                .ascending(),
+                |key, value| {
+                    let id = key.deserialize_be_u64(key.len() - U64_LEN)?;
+                    let post = HeldPost::deserialize(list_id, id, value)?;
+                    if post.expires > current_time {
+                        posts.push(post);
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(posts)
+    }
+
+    async fn list_held_post(&self, list_id: u32, id: u64) -> trc::Result<Option<HeldPost>> {
+        self.store()
+            .get_value::<HeldPostValue>(ValueKey::from(held_post_key(list_id, id)))
+            .await
+            .caused_by(trc::location!())?
+            .map(|value| HeldPost::deserialize(list_id, id, &value.0))
+            .transpose()
+            .map(|post| post.filter(|post| post.expires > now()))
+    }
+
+    async fn list_moderate(&self, list: &ListInfo, id: u64, approve: bool) -> trc::Result<bool> {
+        let Some(post) = self.list_held_post(list.id, id).await? else {
+            return Ok(false);
+        };
+
+        let mut batch = BatchBuilder::new();
+        batch.any_op(Operation::Value {
+            class: held_post_key(list.id, id),
+            op: ValueOp::Clear,
+        });
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+
+        if approve {
+            let raw_message = self
+                .blob_store()
+                .get_blob(post.blob_hash.as_slice(), 0..usize::MAX)
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| {
+                    trc::EventType::MailingList(MailingListEvent::Error)
+                        .into_err()
+                        .details("Held message blob not found")
+                        .caused_by(trc::location!())
+                })?;
+
+            trc::event!(
+                MailingList(MailingListEvent::Approved),
+                To = list.address.clone(),
+                From = post.sender.clone(),
+                Id = id,
+            );
+
+            self.list_distribute(list, &post.sender, &raw_message, post.rewrite_from, 0)
+                .await?;
+        } else {
+            trc::event!(
+                MailingList(MailingListEvent::Rejected),
+                To = list.address.clone(),
+                From = post.sender.clone(),
+                Id = id,
+            );
+        }
+
+        Ok(true)
+    }
+}
+
+fn held_post_key(list_id: u32, id: u64) -> ValueClass {
+    ValueClass::InMemory(InMemoryClass::Key(
+        KeySerializer::new(1 + std::mem::size_of::<u32>() + U64_LEN)
+            .write(KV_LIST_MODERATION)
+            .write(list_id)
+            .write(id)
+            .finalize(),
+    ))
+}
+
+impl HeldPost {
+    fn serialize(&self) -> Vec<u8> {
+        let mut value = self.blob_hash.as_slice().to_vec();
+        value.extend_from_slice(&serde_json::to_vec(self).unwrap_or_default());
+        serialize_with_expiry(self.expires, &value)
+    }
+
+    fn deserialize(list_id: u32, id: u64, bytes: &[u8]) -> trc::Result<Self> {
+        value_without_expiry(bytes)
+            .and_then(|value| {
+                let (hash, value) = value.split_at_checked(types::blob_hash::BLOB_HASH_LEN)?;
+                let blob_hash = BlobHash::try_from_hash_slice(hash).ok()?;
+                serde_json::from_slice::<HeldPost>(value)
+                    .ok()
+                    .map(|post| HeldPost {
+                        id,
+                        list_id,
+                        blob_hash,
+                        ..post
+                    })
+            })
+            .ok_or_else(|| {
+                trc::StoreEvent::DataCorruption
+                    .caused_by(trc::location!())
+                    .ctx(trc::Key::Id, id)
+            })
+    }
+}
+
+struct HeldPostValue(Vec<u8>);
+
+impl store::Deserialize for HeldPostValue {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        Ok(HeldPostValue(bytes.to_vec()))
+    }
+}
@@ -0,0 +1,311 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{
+    ListCommand, ListInfo, ListRecipient, MailingList, PostAction, message::is_list_loop,
+    moderation::ListModeration, rewrite_from,
+};
+use crate::{core::Session, reporting::SmtpReporting};
+use common::{config::smtp::mailing_list::ListPosting, listener::SessionStream};
+use mail_auth::dmarc::Policy;
+use mail_parser::MessageParser;
+use std::borrow::Cow;
+use trc::MailingListEvent;
+
+impl<T: SessionStream> Session<T> {
+    /// Resolve a recipient that expanded to a list, returns `None` when the
+    /// address is not managed by the list manager
+    pub async fn list_rcpt(&self, rcpt: &str) -> Result<Option<ListRecipient>, &'static [u8]> {
+        let (list, command) = match self
+            .server
+            .list_by_address(rcpt, self.data.session_id)
+            .await
+        {
+            Ok(Some(result)) => result,
+            Ok(None) => return Ok(None),
+            Err(err) => {
+                trc::error!(
+                    err.span_id(self.data.session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to obtain list.")
+                );
+                return Err(b"451 4.4.3 Unable to verify address at this time.\r\n");
+            }
+        };
+        let policy = self.server.core.smtp.mailing_list.policy(&list.name);
+        let sender = self
+            .data
+            .mail_from
+            .as_ref()
+            .map(|addr| addr.address_lcase.as_str())
+            .unwrap_or_default();
+        let mut action = PostAction::Distribute;
+
+        match command {
+            ListCommand::Bounces => {}
+            _ if sender.is_empty() => {
+                return Err(b"550 5.7.1 Null senders cannot post to lists.\r\n");
+            }
+            ListCommand::Post => {
+                // Unauthenticated members are verified once the message is
+                // received, using the DMARC result of its From header
+                action = list.post_action(policy, sender, true);
+                if action == PostAction::Reject {
+                    trc::event!(
+                        MailingList(MailingListEvent::Rejected),
+                        SpanId = self.data.session_id,
+                        To = list.address,
+                        From = sender.to_string(),
+                    );
+                    return Err(b"550 5.7.1 You are not allowed to post to this list.\r\n");
+                }
+            }
+            ListCommand::Subscribe if !policy.allow_subscribe => {
+                return Err(b"550 5.7.1 This list does not accept subscription requests.\r\n");
+            }
+            _ => {}
+        }
+
+        Ok(Some(ListRecipient {
+            address: rcpt.to_string(),
+            list,
+            command,
+            action,
+        }))
+    }
+
+    /// Distribute, hold or act on a message addressed to one or more lists
+    pub async fn process_list_rcpts(
+        &self,
+        list_rcpts: Vec<ListRecipient>,
+        headers: &[u8],
+        raw_message: &[u8],
+        dmarc_pass: bool,
+        dmarc_policy: Option<&Policy>,
+        is_held: bool,
+    ) -> Result<(), Cow<'static, [u8]>> {
+        let mut list_rcpts = list_rcpts;
+        let sender = self
+            .data
+            .mail_from
+            .as_ref()
+            .map(|addr| addr.address_lcase.clone())
+            .unwrap_or_default();
+        let session_id = self.data.session_id;
+
+        // Members and moderators can only post without moderation when they
+        // authenticated as the sender or the From header passed DMARC
+        if !self.is_list_sender_verified(&sender) {
+            let mut from = None;
+            for list_rcpt in list_rcpts.iter_mut().filter(|list_rcpt| {
+                list_rcpt.command == ListCommand::Post && list_rcpt.action == PostAction::Distribute
+            }) {
+                let policy = self
+                    .server
+                    .core
+                    .smtp
+                    .mailing_list
+                    .policy(&list_rcpt.list.name);
+                if policy.posting == ListPosting::Open {
+                    continue;
+                }
+                let from = from.get_or_insert_with(|| {
+                    MessageParser::new()
+                        .parse_headers(raw_message)
+                        .and_then(|message| {
+                            message
+                                .from()
+                                .and_then(|from| from.first())
+                                .and_then(|from| from.address())
+                                .map(|from| from.to_lowercase())
+                        })
+                        .unwrap_or_default()
+                });
+                list_rcpt.action = list_rcpt.list.post_action(policy, from, dmarc_pass);
+                if list_rcpt.action == PostAction::Reject {
+                    trc::event!(
+                        MailingList(MailingListEvent::Rejected),
+                        SpanId = session_id,
+                        To = list_rcpt.list.address.clone(),
+                        From = sender.clone(),
+                    );
+                    return Err(
+                        (&b"550 5.7.1 You are not allowed to post to this list.\r\n"[..]).into(),
+                    );
+                }
+            }
+        }
+
+        // Posts held by a content policy wait for a moderator
+        if is_held {
+            for list_rcpt in list_rcpts.iter_mut().filter(|list_rcpt| {
+                list_rcpt.command == ListCommand::Post && list_rcpt.action == PostAction::Distribute
+            }) {
+                list_rcpt.action = PostAction::Moderate;
+            }
+        }
+
+        let mut message = Vec::with_capacity(headers.len() + raw_message.len());
+        message.extend_from_slice(headers);
+        message.extend_from_slice(raw_message);
+
+        for ListRecipient {
+            list,
+            command,
+            action,
+            ..
+        } in list_rcpts
+        {
+            let result = match command {
+                ListCommand::Post if is_list_loop(raw_message, &list.address) => {
+                    trc::event!(
+                        MailingList(MailingListEvent::Discarded),
+                        SpanId = session_id,
+                        To = list.address,
+                        From = sender.clone(),
+                        Reason = "Mail loop detected",
+                    );
+                    Ok(())
+                }
+                ListCommand::Post => {
+                    let policy = self.server.core.smtp.mailing_list.policy(&list.name);
+                    let rewrite_from = rewrite_from(policy, dmarc_policy);
+                    match action {
+                        PostAction::Distribute => self
+                            .server
+                            .list_distribute(&list, &sender, &message, rewrite_from, session_id)
+                            .await
+                            .map(|_| ()),
+                        PostAction::Moderate => self
+                            .server
+                            .list_hold(&list, &sender, &message, rewrite_from, session_id)
+                            .await
+                            .map(|_| ()),
+                        PostAction::Discard | PostAction::Reject => {
+                            trc::event!(
+                                MailingList(MailingListEvent::Discarded),
+                                SpanId = session_id,
+                                To = list.address,
+                                From = sender.clone(),
+                                Reason = "Sender not allowed to post",
+                            );
+                            Ok(())
+                        }
+                    }
+                }
+                ListCommand::Subscribe | ListCommand::Unsubscribe => {
+                    if list.is_member(&sender) == (command == ListCommand::Subscribe) {
+                        Ok(())
+                    } else {
+                        self.server
+                            .list_send_confirmation(&list, &sender, command, session_id)
+                            .await
+                    }
+                }
+                ListCommand::Confirm => self.list_confirm_reply(&list, raw_message).await,
+                ListCommand::Owner => {
+                    let moderators = &self
+                        .server
+                        .core
+                        .smtp
+                        .mailing_list
+                        .policy(&list.name)
+                        .moderators;
+                    if !moderators.is_empty() {
+                        self.server
+                            .send_autogenerated(
+                                list.command_address("bounces"),
+                                moderators.iter(),
+                                message.clone(),
+                                None,
+                                session_id,
+                            )
+                            .await;
+                    } else {
+                        trc::event!(
+                            MailingList(MailingListEvent::Discarded),
+                            SpanId = session_id,
+                            To = list.address,
+                            From = sender.clone(),
+                            Reason = "List has no moderators",
+                        );
+                    }
+                    Ok(())
+                }
+                ListCommand::Bounces => {
+                    trc::event!(
+                        MailingList(MailingListEvent::BounceReceived),
+                        SpanId = session_id,
+                        To = list.address,
+                        From = sender.clone(),
+                        Size = message.len(),
+                    );
+                    Ok(())
+                }
+            };
+
+            if let Err(err) = result {
+                trc::error!(
+                    err.span_id(session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to process list message.")
+                );
+                return Err(
+                    (&b"451 4.3.5 Unable to process list message at this time.\r\n"[..]).into(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_list_sender_verified(&self, sender: &str) -> bool {
+        self.authenticated_as() == Some(sender)
+            || self
+                .authenticated_emails()
+                .iter()
+                .any(|email| email == sender)
+    }
+
+    // Replies to confirmation messages carry the token in their subject
+    async fn list_confirm_reply(&self, list: &ListInfo, raw_message: &[u8]) -> trc::Result<()> {
+        let subject = MessageParser::new()
+            .parse_headers(raw_message)
+            .and_then(|message| message.subject().map(|subject| subject.to_string()))
+            .unwrap_or_default();
+
+        for word in subject.split_whitespace() {
+            let word = word.trim_matches(|c: char| matches!(c, '[' | ']' | '(' | ')' | '<' | '>'));
+            if word.len() < 32 {
+                continue;
+            }
+            if let Ok(Some((token_list, command, address))) = self.server.list_confirm(word).await
+                && token_list.id == list.id
+            {
+                return if command == ListCommand::Subscribe {
+                    self.server.list_subscribe(&token_list, &address).await
+                } else {
+                    self.server.list_unsubscribe(&token_list, &address).await
+                }
+                .map(|_| ());
+            }
+        }
+
+        trc::event!(
+            MailingList(MailingListEvent::ConfirmFailed),
+            SpanId = self.data.session_id,
+            To = list.address.clone(),
+            From = self
+                .data
+                .mail_from
+                .as_ref()
+                .map(|addr| addr.address_lcase.clone())
+                .unwrap_or_default(),
+        );
+
+        Ok(())
+    }
+}
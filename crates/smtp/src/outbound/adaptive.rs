@@ -0,0 +1,146 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::queue::{Error, ErrorDetails, HostResponse, Status};
+use common::{
+    Server,
+    config::smtp::adaptive::{AdaptiveThrottleConfig, LimitChange, ThrottlePermit},
+    psl,
+};
+use std::time::{Duration, SystemTime};
+use store::write::now;
+use trc::{AdaptiveThrottleEvent, Collector, MetricType};
+
+pub trait AdaptiveThrottling {
+    fn throttle_acquire(&self, mx: &str, span_id: u64) -> Result<ThrottlePermit, u64>;
+
+    fn throttle_report<'x>(
+        &self,
+        permit: &ThrottlePermit,
+        statuses: impl IntoIterator<Item = &'x Status<HostResponse<Box<str>>, ErrorDetails>>,
+        span_id: u64,
+    );
+}
+
+impl AdaptiveThrottling for Server {
+    /// Returns a permit to deliver to the MX host or the time at which the
+    /// delivery should be retried
+    fn throttle_acquire(&self, mx: &str, span_id: u64) -> Result<ThrottlePermit, u64> {
+        let config = &self.core.smtp.adaptive;
+        if !config.enable {
+            return Ok(ThrottlePermit::default());
+        }
+
+        let destination = throttle_destination(mx);
+        match self
+            .inner
+            .data
+            .queue_throttle
+            .acquire(config, destination, now_ms())
+        {
+            Ok(permit) => Ok(permit),
+            Err(wait) => {
+                let retry_at = now() + Duration::from_millis(wait).as_secs().max(1);
+
+                trc::event!(
+                    AdaptiveThrottle(AdaptiveThrottleEvent::Throttled),
+                    SpanId = span_id,
+                    Hostname = mx.to_string(),
+                    Domain = destination.to_string(),
+                    NextRetry = trc::Value::Timestamp(retry_at),
+                );
+
+                Err(retry_at)
+            }
+        }
+    }
+
+    /// Adjusts the destination limits based on the outcome of a delivery attempt
+    fn throttle_report<'x>(
+        &self,
+        permit: &ThrottlePermit,
+        statuses: impl IntoIterator<Item = &'x Status<HostResponse<Box<str>>, ErrorDetails>>,
+        span_id: u64,
+    ) {
+        let Some(destination) = permit.destination() else {
+            return;
+        };
+        let config = &self.core.smtp.adaptive;
+
+        let mut deferral = None;
+        let mut is_success = false;
+        for status in statuses {
+            match status {
+                Status::Completed(_) => is_success = true,
+                Status::TemporaryFailure(err) if is_deferral(config, err) => {
+                    deferral = Some(err);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let change = if let Some(err) = deferral {
+            trc::event!(
+                AdaptiveThrottle(AdaptiveThrottleEvent::Deferral),
+                SpanId = span_id,
+                Domain = destination.name.clone(),
+                Hostname = err.entity.to_string(),
+                Details = err.details.to_string(),
+            );
+
+            permit.deferral(config, now_ms())
+        } else if is_success {
+            permit.success(config, now_ms())
+        } else {
+            LimitChange::Unchanged
+        };
+
+        if change != LimitChange::Unchanged {
+            let limits = destination.limits();
+
+            trc::event!(
+                AdaptiveThrottle(match change {
+                    LimitChange::Decreased => AdaptiveThrottleEvent::LimitDecreased,
+                    LimitChange::Increased => AdaptiveThrottleEvent::LimitIncreased,
+                    _ => AdaptiveThrottleEvent::LimitRestored,
+                }),
+                SpanId = span_id,
+                Domain = destination.name.clone(),
+                Limit = limits.concurrency,
+                Total = limits.rate.map(|rate| (rate * 60.0).round() as u64),
+            );
+
+            Collector::update_gauge(
+                MetricType::DeliveryThrottledDestinations,
+                self.inner.data.queue_throttle.limited_count() as u64,
+            );
+        }
+    }
+}
+
+fn is_deferral(config: &AdaptiveThrottleConfig, err: &ErrorDetails) -> bool {
+    match &err.details {
+        Error::UnexpectedResponse(response) => config.is_deferral(
+            response.response.code,
+            response.response.esc,
+            &response.response.message,
+        ),
+        _ => false,
+    }
+}
+
+/// MX hosts of the same provider share their limits
+pub fn throttle_destination(mx: &str) -> &str {
+    let mx = mx.trim_end_matches('.');
+    psl::domain_str(mx).unwrap_or(mx)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::UNIX_EPOCH
+        .elapsed()
+        .map_or(0, |time| time.as_millis() as u64)
+}
@@ -4,27 +4,31 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use super::{NextHop, lookup::ToNextHop, mta_sts, session::SessionParams};
+use super::{
+    NextHop, adaptive::AdaptiveThrottling, lookup::ToNextHop, mta_sts, session::SessionParams,
+};
 use crate::outbound::DeliveryResult;
 use crate::outbound::client::{
     SmtpClient, from_error_details, from_error_status, from_mail_send_error,
 };
 use crate::outbound::dane::dnssec::TlsaLookup;
-use crate::outbound::lookup::{DnsLookup, SourceIp};
+use crate::outbound::lookup::DnsLookup;
 use crate::outbound::mta_sts::lookup::MtaStsLookup;
 use crate::outbound::mta_sts::verify::VerifyPolicy;
+use crate::outbound::warmup::IpWarmup;
 use crate::outbound::{client::StartTlsResult, dane::verify::TlsaVerify};
 use crate::queue::dsn::SendDsn;
 use crate::queue::spool::SmtpSpool;
 use crate::queue::throttle::IsAllowed;
 use crate::queue::{
-    Error, FROM_REPORT, HostResponse, MessageWrapper, QueueEnvelope, QueuedMessage, Status,
+    Error, FROM_REPORT, HELD_FOR_APPROVAL, HostResponse, MessageWrapper, QueueEnvelope,
+    QueuedMessage, Status,
 };
 use crate::reporting::SmtpReporting;
 use crate::{queue::ErrorDetails, reporting::tls::TlsRptOptions};
 use ahash::AHashMap;
 use common::Server;
-use common::config::smtp::queue::RoutingStrategy;
+use common::config::smtp::queue::{QueueExpiry, RoutingStrategy};
 use common::config::{server::ServerProtocol, smtp::report::AggregateFrequency};
 use common::ipc::{PolicyType, QueueEvent, QueueEventStatus, TlsEvent};
 use compact_str::ToCompactString;
@@ -39,7 +43,7 @@ use std::{
     time::Instant,
 };
 use store::write::{BatchBuilder, QueueClass, ValueClass, now};
-use trc::{DaneEvent, DeliveryEvent, MtaStsEvent, ServerEvent, TlsRptEvent};
+use trc::{DaneEvent, DeliveryEvent, DlpEvent, MtaStsEvent, ServerEvent, TlsRptEvent};
 
 impl QueuedMessage {
     pub fn try_deliver(self, server: Server) {
@@ -179,6 +183,35 @@ impl QueuedMessage {
             }
         }
 
+        // Messages held for approval are not delivered until released, recipients
+        // rescheduled before then are held again until the hold expires
+        if message.message.flags & HELD_FOR_APPROVAL != 0 {
+            let now = now();
+            let hold_for = server.core.smtp.dlp.hold_for;
+            let expires = message.message.created + hold_for;
+            for rcpt in message.message.recipients.iter_mut() {
+                if matches!(
+                    &rcpt.status,
+                    Status::Scheduled | Status::TemporaryFailure(_)
+                ) && rcpt.retry.due <= now
+                    && rcpt.queue == message.queue_name
+                {
+                    rcpt.retry.due = expires;
+                    rcpt.expires = QueueExpiry::Ttl(hold_for);
+                }
+            }
+
+            trc::event!(
+                Dlp(DlpEvent::MessageHeld),
+                SpanId = span_id,
+                QueueId = message.queue_id,
+                Expires = trc::Value::Timestamp(expires),
+            );
+
+            message.save_changes(&server, self.due.into()).await;
+            return QueueEventStatus::Deferred;
+        }
+
         // Throttle sender
         for throttle in &server.core.smtp.queue.outbound_limiters.sender {
             if let Err(retry_at) = server
@@ -853,6 +886,18 @@ impl QueuedMessage {
                         }
                     }
 
+                    // Adaptive throttling
+                    let throttle_permit =
+                        match server.throttle_acquire(envelope.mx, message.span_id) {
+                            Ok(permit) => permit,
+                            Err(retry_at) => {
+                                delivery_results
+                                    .push(DeliveryResult::rate_limited(rcpt_idxs, retry_at));
+                                continue 'next_route;
+                            }
+                        };
+                    let results_start = delivery_results.len();
+
                     // Obtain connection parameters
                     let conn_strategy = server.get_connection_or_default(
                         &server
@@ -867,7 +912,22 @@ impl QueuedMessage {
                     );
 
                     // Set source IP, if any
-                    let ip_host = conn_strategy.source_ip(remote_ip.is_ipv4());
+                    let ip_host = match server
+                        .select_source_ip(
+                            conn_strategy,
+                            remote_ip.is_ipv4(),
+                            envelope.mx,
+                            message.span_id,
+                        )
+                        .await
+                    {
+                        Ok(ip_host) => ip_host,
+                        Err(retry_at) => {
+                            delivery_results
+                                .push(DeliveryResult::rate_limited(rcpt_idxs, retry_at));
+                            continue 'next_route;
+                        }
+                    };
 
                     // Connect
                     let time = Instant::now();
@@ -964,6 +1024,7 @@ impl QueuedMessage {
                                 Details = status.to_string(),
                             );
 
+                            server.throttle_report(&throttle_permit, [&status], message.span_id);
                             last_status = status;
                             continue 'next_host;
                         }
@@ -1227,6 +1288,7 @@ impl QueuedMessage {
                                 Details = from_error_status(&status),
                             );
 
+                            server.throttle_report(&throttle_permit, [&status], message.span_id);
                             last_status = status;
                             continue 'next_host;
                         }
@@ -1237,6 +1299,14 @@ impl QueuedMessage {
                             .await
                     }
 
+                    server.throttle_report(
+                        &throttle_permit,
+                        delivery_results[results_start..]
+                            .iter()
+                            .filter_map(|result| result.status()),
+                        message.span_id,
+                    );
+
                     // Continue with the next domain/route
                     continue 'next_route;
                 }
@@ -8,8 +8,8 @@ use crate::{
     outbound::DeliveryResult,
     queue::{
         Error, ErrorDetails, FROM_AUTHENTICATED, FROM_UNAUTHENTICATED_DMARC, HostResponse,
-        MessageSource, MessageWrapper, RCPT_SPAM_PAYLOAD, Status, UnexpectedResponse,
-        quota::HasQueueQuota, spool::SmtpSpool,
+        MessageSource, MessageWrapper, RCPT_QUARANTINE, RCPT_SPAM_PAYLOAD, Status,
+        UnexpectedResponse, quota::HasQueueQuota, spool::SmtpSpool,
     },
     reporting::SmtpReporting,
 };
@@ -34,6 +34,7 @@ impl MessageWrapper {
             recipients.push(IngestRecipient {
                 address: rcpt_addr.to_lowercase(),
                 is_spam: rcpt.flags & RCPT_SPAM_PAYLOAD != 0,
+                is_quarantined: rcpt.flags & RCPT_QUARANTINE != 0,
             });
             pending_recipients.push((rcpt_idx, rcpt_addr));
         }
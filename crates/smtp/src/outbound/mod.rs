@@ -17,6 +17,7 @@ use mail_send::Credentials;
 use smtp_proto::{Response, Severity};
 use std::borrow::Cow;
 
+pub mod adaptive;
 pub mod client;
 pub mod dane;
 pub mod delivery;
@@ -24,6 +25,7 @@ pub mod local;
 pub mod lookup;
 pub mod mta_sts;
 pub mod session;
+pub mod warmup;
 
 pub(super) enum DeliveryResult {
     Domain {
@@ -347,4 +349,13 @@ impl DeliveryResult {
     pub fn account(status: Status<HostResponse<Box<str>>, ErrorDetails>, rcpt_idx: usize) -> Self {
         DeliveryResult::Account { status, rcpt_idx }
     }
+
+    pub fn status(&self) -> Option<&Status<HostResponse<Box<str>>, ErrorDetails>> {
+        match self {
+            DeliveryResult::Domain { status, .. } | DeliveryResult::Account { status, .. } => {
+                Some(status)
+            }
+            DeliveryResult::RateLimited { .. } => None,
+        }
+    }
 }
@@ -0,0 +1,375 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{adaptive::throttle_destination, lookup::SourceIp};
+use ahash::AHashSet;
+use common::{
+    KV_WARMUP, KV_WARMUP_COUNTER, Server,
+    config::smtp::{
+        queue::{ConnectionStrategy, IpAndHost},
+        warmup::{WarmupOverflow, WarmupSchedule},
+    },
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{future::Future, net::IpAddr};
+use store::{
+    U64_LEN, ValueKey,
+    dispatch::lookup::KeyValue,
+    write::{BatchBuilder, InMemoryClass, Operation, ValueClass, ValueOp, key::KeySerializer, now},
+};
+use trc::{AddContext, WarmupEvent};
+
+const DAY: u64 = 86400;
+
+/// Persisted warm-up progress of a source IP
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupProgress {
+    pub ip: IpAddr,
+    /// Day, counted from the epoch, on which the address was first used
+    pub started: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupStatus {
+    pub ip: IpAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started: Option<u64>,
+    /// Day of the schedule, starting at zero
+    pub day: u64,
+    pub days: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_limit: Option<u64>,
+    pub sent_today: u64,
+    pub per_provider: bool,
+    pub is_warm: bool,
+}
+
+pub trait IpWarmup: Sync + Send {
+    fn select_source_ip<'x>(
+        &self,
+        strategy: &'x ConnectionStrategy,
+        is_v4: bool,
+        mx: &str,
+        span_id: u64,
+    ) -> impl Future<Output = Result<Option<&'x IpAndHost>, u64>> + Send;
+
+    fn warmup_status(
+        &self,
+        ip: IpAddr,
+        schedule: &WarmupSchedule,
+    ) -> impl Future<Output = trc::Result<WarmupStatus>> + Send;
+
+    fn warmup_reset(&self, ip: IpAddr) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn warmup_schedules(&self) -> Vec<(IpAddr, &WarmupSchedule)>;
+
+    fn warmup_reserve(
+        &self,
+        ip: &IpAndHost,
+        provider: &str,
+        today: u64,
+        span_id: u64,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn warmup_progress(
+        &self,
+        ip: IpAddr,
+    ) -> impl Future<Output = trc::Result<Option<WarmupProgress>>> + Send;
+
+    fn warmup_start(&self, ip: IpAddr, today: u64) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl IpWarmup for Server {
+    /// Picks the source IP for a delivery attempt honouring the warm-up schedules,
+    /// or returns the time at which the delivery should be retried
+    async fn select_source_ip<'x>(
+        &self,
+        strategy: &'x ConnectionStrategy,
+        is_v4: bool,
+        mx: &str,
+        span_id: u64,
+    ) -> Result<Option<&'x IpAndHost>, u64> {
+        let ips = if is_v4 {
+            &strategy.source_ipv4
+        } else {
+            &strategy.source_ipv6
+        };
+        if ips.iter().all(|ip| ip.warmup.is_none()) {
+            return Ok(strategy.source_ip(is_v4));
+        }
+
+        // Try the randomly selected address first, then spill over to warm
+        // addresses before trying other warming ones
+        let first = rand::rng().random_range(0..ips.len());
+        let mut candidates = (0..ips.len())
+            .filter(|&pos| pos != first)
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|&pos| ips[pos].warmup.is_some());
+        candidates.insert(0, first);
+
+        let today = now() / DAY;
+        let provider = throttle_destination(mx);
+        for (attempt, pos) in candidates.into_iter().enumerate() {
+            let ip = &ips[pos];
+            match self.warmup_reserve(ip, provider, today, span_id).await {
+                Ok(true) => {
+                    if attempt > 0 {
+                        trc::event!(
+                            Warmup(WarmupEvent::Spilled),
+                            SpanId = span_id,
+                            LocalIp = ip.ip,
+                            Hostname = mx.to_string(),
+                        );
+                    }
+                    return Ok(Some(ip));
+                }
+                Ok(false) => {
+                    if ip
+                        .warmup
+                        .as_ref()
+                        .is_some_and(|schedule| schedule.overflow == WarmupOverflow::Defer)
+                    {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(span_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to obtain IP warm-up progress.")
+                    );
+                    return Ok(Some(ip));
+                }
+            }
+        }
+
+        let retry_at = (today + 1) * DAY;
+        trc::event!(
+            Warmup(WarmupEvent::Deferred),
+            SpanId = span_id,
+            Hostname = mx.to_string(),
+            NextRetry = trc::Value::Timestamp(retry_at),
+        );
+
+        Err(retry_at)
+    }
+
+    async fn warmup_status(
+        &self,
+        ip: IpAddr,
+        schedule: &WarmupSchedule,
+    ) -> trc::Result<WarmupStatus> {
+        let today = now() / DAY;
+        let started = self
+            .warmup_progress(ip)
+            .await?
+            .map(|progress| progress.started);
+        let day = started.map_or(0, |started| today.saturating_sub(started));
+        let daily_limit = schedule.daily_limit(day);
+        let sent_today = self
+            .in_memory_store()
+            .counter_get(KeyValue::<()>::build_key(
+                KV_WARMUP_COUNTER,
+                counter_key(ip, today, ""),
+            ))
+            .await
+            .caused_by(trc::location!())?
+            .max(0) as u64;
+
+        Ok(WarmupStatus {
+            ip,
+            started: started.map(|started| started * DAY),
+            day,
+            days: schedule.days(),
+            daily_limit,
+            sent_today,
+            per_provider: schedule.per_provider,
+            is_warm: started.is_some() && daily_limit.is_none(),
+        })
+    }
+
+    async fn warmup_reset(&self, ip: IpAddr) -> trc::Result<bool> {
+        if self.warmup_progress(ip).await?.is_none() {
+            return Ok(false);
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch.any_op(Operation::Value {
+            class: progress_key(ip),
+            op: ValueOp::Clear,
+        });
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(Warmup(WarmupEvent::Reset), LocalIp = ip);
+
+        Ok(true)
+    }
+
+    fn warmup_schedules(&self) -> Vec<(IpAddr, &WarmupSchedule)> {
+        let mut seen = AHashSet::new();
+        let mut schedules = self
+            .core
+            .smtp
+            .queue
+            .connection_strategy
+            .values()
+            .flat_map(|strategy| strategy.source_ipv4.iter().chain(&strategy.source_ipv6))
+            .filter_map(|ip| {
+                ip.warmup
+                    .as_deref()
+                    .filter(|_| seen.insert(ip.ip))
+                    .map(|schedule| (ip.ip, schedule))
+            })
+            .collect::<Vec<_>>();
+        schedules.sort_unstable_by_key(|(ip, _)| *ip);
+        schedules
+    }
+
+    /// Counts a delivery attempt against the daily limit of a warming address,
+    /// returns `false` when the limit has been reached
+    async fn warmup_reserve(
+        &self,
+        ip: &IpAndHost,
+        provider: &str,
+        today: u64,
+        span_id: u64,
+    ) -> trc::Result<bool> {
+        let Some(schedule) = &ip.warmup else {
+            return Ok(true);
+        };
+
+        let started = match self.warmup_progress(ip.ip).await? {
+            Some(progress) => progress.started,
+            None => {
+                self.warmup_start(ip.ip, today).await?;
+
+                trc::event!(
+                    Warmup(WarmupEvent::Started),
+                    SpanId = span_id,
+                    LocalIp = ip.ip,
+                    Total = schedule.days(),
+                );
+
+                today
+            }
+        };
+        let Some(limit) = schedule.daily_limit(today.saturating_sub(started)) else {
+            return Ok(true);
+        };
+
+        let store = self.in_memory_store();
+        let total_key = counter_key(ip.ip, today, "");
+        let limit_key = if schedule.per_provider {
+            counter_key(ip.ip, today, provider)
+        } else {
+            total_key.clone()
+        };
+        let sent = store
+            .counter_get(KeyValue::<()>::build_key(KV_WARMUP_COUNTER, &limit_key))
+            .await?;
+        if sent >= limit as i64 {
+            trc::event!(
+                Warmup(WarmupEvent::LimitReached),
+                SpanId = span_id,
+                LocalIp = ip.ip,
+                Domain = schedule.per_provider.then(|| provider.to_string()),
+                Limit = limit,
+            );
+
+            return Ok(false);
+        }
+
+        for key in [Some(total_key), schedule.per_provider.then_some(limit_key)]
+            .into_iter()
+            .flatten()
+        {
+            store
+                .counter_incr(
+                    KeyValue::with_prefix(KV_WARMUP_COUNTER, key, 1).expires(2 * DAY),
+                    false,
+                )
+                .await?;
+        }
+
+        Ok(true)
+    }
+
+    async fn warmup_progress(&self, ip: IpAddr) -> trc::Result<Option<WarmupProgress>> {
+        self.store()
+            .get_value::<WarmupValue>(ValueKey::from(progress_key(ip)))
+            .await
+            .caused_by(trc::location!())?
+            .map(|value| WarmupProgress::deserialize(&value.0))
+            .transpose()
+    }
+
+    async fn warmup_start(&self, ip: IpAddr, today: u64) -> trc::Result<()> {
+        let mut batch = BatchBuilder::new();
+        batch.any_op(Operation::Value {
+            class: progress_key(ip),
+            op: ValueOp::Set(WarmupProgress { ip, started: today }.serialize()),
+        });
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+}
+
+fn progress_key(ip: IpAddr) -> ValueClass {
+    ValueClass::InMemory(InMemoryClass::Key(KeyValue::<()>::build_key(
+        KV_WARMUP,
+        ip.to_string(),
+    )))
+}
+
+fn counter_key(ip: IpAddr, day: u64, provider: &str) -> Vec<u8> {
+    let ip = ip.to_string();
+    KeySerializer::new(ip.len() + 1 + std::mem::size_of::<u32>() + provider.len())
+        .write(ip.as_bytes())
+        .write(0u8)
+        .write(day as u32)
+        .write(provider.as_bytes())
+        .finalize()
+}
+
+// Progress entries never expire and use the same layout as in-memory keys
+// stored in the data store
+impl WarmupProgress {
+    fn serialize(&self) -> Vec<u8> {
+        let value = serde_json::to_vec(self).unwrap_or_default();
+        KeySerializer::new(U64_LEN + value.len())
+            .write(u64::MAX)
+            .write(value.as_slice())
+            .finalize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        bytes
+            .get(U64_LEN..)
+            .and_then(|value| serde_json::from_slice::<WarmupProgress>(value).ok())
+            .ok_or_else(|| {
+                trc::StoreEvent::DataCorruption
+                    .caused_by(trc::location!())
+                    .ctx(trc::Key::Value, bytes)
+            })
+    }
+}
+
+struct WarmupValue(Vec<u8>);
+
+impl store::Deserialize for WarmupValue {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        Ok(WarmupValue(bytes.to_vec()))
+    }
+}
@@ -0,0 +1,192 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{Error, ErrorDetails, Recipient, Status};
+use mail_parser::{Message, MimeHeaders};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BounceKind {
+    Hard,
+    Soft,
+}
+
+/// A failed recipient reported by a delivery status notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bounce {
+    pub address: String,
+    pub kind: BounceKind,
+    pub status: String,
+    pub diagnostic: Option<String>,
+}
+
+impl BounceKind {
+    /// Classifies a DSN recipient by its action and status code (RFC 3464, RFC 3463).
+    /// Only failures that say something about the address itself are hard bounces,
+    /// policy and capacity errors are treated as transient regardless of their class.
+    pub fn classify(action: &str, status: &str) -> Option<Self> {
+        if action.eq_ignore_ascii_case("delayed") {
+            return Some(BounceKind::Soft);
+        } else if !action.eq_ignore_ascii_case("failed") {
+            return None;
+        }
+
+        let mut code = status.trim().splitn(3, '.').map(|v| v.parse::<u16>().ok());
+        match (
+            code.next().flatten(),
+            code.next().flatten(),
+            code.next().flatten(),
+        ) {
+            (Some(5), Some(1), Some(_))
+            | (Some(5), Some(2), Some(1))
+            | (Some(5), Some(0), Some(0)) => Some(BounceKind::Hard),
+            _ => Some(BounceKind::Soft),
+        }
+    }
+}
+
+/// Extracts the failed recipients from a `multipart/report` message with
+/// a `message/delivery-status` part
+pub fn parse_dsn_message(message: &Message<'_>) -> Vec<Bounce> {
+    message
+        .parts
+        .iter()
+        .find(|part| part.is_content_type("message", "delivery-status"))
+        .map(|part| parse_delivery_status(part.contents()))
+        .unwrap_or_default()
+}
+
+pub fn parse_delivery_status(report: &[u8]) -> Vec<Bounce> {
+    let report = String::from_utf8_lossy(report);
+    let mut bounces = Vec::new();
+
+    // The first block holds the per-message fields, each following block
+    // describes one recipient
+    for block in report.replace("\r\n", "\n").split("\n\n").skip(1) {
+        let mut original_recipient = None;
+        let mut final_recipient = None;
+        let mut action = None;
+        let mut status = None;
+        let mut diagnostic = None;
+
+        for (name, value) in unfold_fields(block) {
+            if name.eq_ignore_ascii_case("Original-Recipient") {
+                original_recipient = parse_address_field(&value);
+            } else if name.eq_ignore_ascii_case("Final-Recipient") {
+                final_recipient = parse_address_field(&value);
+            } else if name.eq_ignore_ascii_case("Action") {
+                action = Some(value);
+            } else if name.eq_ignore_ascii_case("Status") {
+                status = Some(value);
+            } else if name.eq_ignore_ascii_case("Diagnostic-Code") {
+                diagnostic = Some(
+                    value
+                        .split_once(';')
+                        .map_or(value.as_str(), |(_, text)| text)
+                        .trim()
+                        .to_string(),
+                );
+            }
+        }
+
+        if let (Some(address), Some(action)) = (original_recipient.or(final_recipient), action) {
+            let status = status.unwrap_or_default();
+            if let Some(kind) = BounceKind::classify(&action, &status) {
+                bounces.push(Bounce {
+                    address,
+                    kind,
+                    status,
+                    diagnostic,
+                });
+            }
+        }
+    }
+
+    bounces
+}
+
+fn unfold_fields(block: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for line in block.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            fields.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    fields
+}
+
+fn parse_address_field(value: &str) -> Option<String> {
+    let (addr_type, address) = value.split_once(';')?;
+    let address = address.trim().trim_start_matches('<').trim_end_matches('>');
+    if addr_type.trim().eq_ignore_ascii_case("rfc822") && address.contains('@') {
+        Some(address.to_lowercase())
+    } else {
+        None
+    }
+}
+
+impl Recipient {
+    /// Returns the bounce for a recipient that failed permanently in our own queue
+    pub fn bounce(&self) -> Option<Bounce> {
+        let Status::PermanentFailure(err) = &self.status else {
+            return None;
+        };
+        let (status, diagnostic) = err.bounce_status();
+
+        BounceKind::classify("failed", &status).map(|kind| Bounce {
+            address: self.address.to_lowercase(),
+            kind,
+            status,
+            diagnostic,
+        })
+    }
+}
+
+impl ErrorDetails {
+    fn bounce_status(&self) -> (String, Option<String>) {
+        let mut status = String::with_capacity(5);
+        match &self.details {
+            Error::UnexpectedResponse(response) => {
+                let response = &response.response;
+                if response.esc[0] > 0 {
+                    let _ = write!(
+                        status,
+                        "{}.{}.{}",
+                        response.esc[0], response.esc[1], response.esc[2]
+                    );
+                } else {
+                    let _ = write!(
+                        status,
+                        "{}.{}.{}",
+                        response.code / 100,
+                        (response.code / 10) % 10,
+                        response.code % 10
+                    );
+                }
+
+                (
+                    status,
+                    format!("{} {}", response.code, response.message).into(),
+                )
+            }
+            // The recipient domain does not exist or does not accept mail
+            Error::DnsError(details) => ("5.1.2".to_string(), details.to_string().into()),
+            Error::ConnectionError(details)
+            | Error::TlsError(details)
+            | Error::DaneError(details)
+            | Error::MtaStsError(details)
+            | Error::Io(details) => ("4.4.0".to_string(), details.to_string().into()),
+            Error::RateLimited | Error::ConcurrencyLimited => ("4.4.5".to_string(), None),
+        }
+    }
+}
@@ -5,6 +5,7 @@
  */
 
 use super::spool::SmtpSpool;
+use super::suppression::SuppressionList;
 use super::{
     Error, ErrorDetails, HostResponse, Message, MessageSource, QueueEnvelope, RCPT_DSN_SENT,
     Recipient, Status,
@@ -35,6 +36,19 @@ impl SendDsn for Server {
         self.log_dsn(message).await;
 
         if !message.message.return_path.is_empty() {
+            // Update the sender's suppression list
+            if self.core.smtp.suppression.enable {
+                let bounces = message
+                    .message
+                    .recipients
+                    .iter()
+                    .filter(|rcpt| !rcpt.has_flag(RCPT_DSN_SENT))
+                    .filter_map(|rcpt| rcpt.bounce())
+                    .collect::<Vec<_>>();
+                self.process_bounces(&message.message.return_path, bounces, message.span_id)
+                    .await;
+            }
+
             // Build DSN
             if let Some(dsn) = message.build_dsn(self).await {
                 let mut dsn_message = self.new_message("", message.span_id);
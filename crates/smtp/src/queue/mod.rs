@@ -19,10 +19,12 @@ use store::write::now;
 use types::blob_hash::BlobHash;
 use utils::DomainPart;
 
+pub mod bounce;
 pub mod dsn;
 pub mod manager;
 pub mod quota;
 pub mod spool;
+pub mod suppression;
 pub mod throttle;
 
 pub type QueueId = u64;
@@ -124,10 +126,12 @@ pub const FROM_UNAUTHENTICATED_DMARC: u64 = 1 << 34;
 pub const FROM_DSN: u64 = 1 << 35;
 pub const FROM_REPORT: u64 = 1 << 36;
 pub const FROM_AUTOGENERATED: u64 = 1 << 37;
+pub const HELD_FOR_APPROVAL: u64 = 1 << 38;
 
 pub const RCPT_DSN_SENT: u64 = 1 << 32;
 //pub const RCPT_STATUS_CHANGED: u64 = 1 << 33;
 pub const RCPT_SPAM_PAYLOAD: u64 = 1 << 34;
+pub const RCPT_QUARANTINE: u64 = 1 << 35;
 
 #[derive(
     Debug,
@@ -0,0 +1,461 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::bounce::{Bounce, BounceKind};
+use crate::reporting::complaints::{origin_message_key, origin_queue_key};
+use common::{KV_BOUNCE_ORIGIN, KV_RATE_LIMIT_BOUNCE, KV_SUPPRESSION, Server};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use store::{
+    IterateParams, Serialize as _, ValueKey,
+    dispatch::lookup::{KeyValue, serialize_with_expiry, value_without_expiry},
+    write::{
+        AlignedBytes, Archive, Archiver, BatchBuilder, InMemoryClass, Operation, ValueClass,
+        ValueOp, key::KeySerializer, now,
+    },
+};
+use trc::{AddContext, SuppressionEvent};
+
+/// Suppression lists of senders without a tenant
+pub const NO_TENANT: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SuppressionReason {
+    HardBounce,
+    SoftBounce,
+    Manual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suppression {
+    pub address: String,
+    pub reason: SuppressionReason,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostic: Option<String>,
+    pub created: u64,
+    /// `None` when the entry was added manually and never expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+}
+
+/// Message sent by an authenticated user, remembered for matching incoming
+/// delivery status notifications
+#[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq)]
+pub struct BounceOrigin {
+    pub sender: String,
+    pub recipients: Vec<String>,
+}
+
+pub trait SuppressionList: Sync + Send {
+    fn suppression_get(
+        &self,
+        tenant_id: Option<u32>,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<Option<Suppression>>> + Send;
+
+    fn suppression_add(
+        &self,
+        tenant_id: Option<u32>,
+        entry: Suppression,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn suppression_remove(
+        &self,
+        tenant_id: Option<u32>,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn suppression_list(
+        &self,
+        tenant_id: Option<u32>,
+    ) -> impl Future<Output = trc::Result<Vec<Suppression>>> + Send;
+
+    fn suppression_tenant(
+        &self,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<Option<u32>>> + Send;
+
+    fn process_bounces(
+        &self,
+        sender: &str,
+        bounces: Vec<Bounce>,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn bounce_track(
+        &self,
+        origin: BounceOrigin,
+        queue_id: u64,
+        message_id: Option<&str>,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn process_dsn_bounces(
+        &self,
+        sender: &str,
+        origin_keys: Vec<Vec<u8>>,
+        bounces: Vec<Bounce>,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl SuppressionList for Server {
+    async fn suppression_get(
+        &self,
+        tenant_id: Option<u32>,
+        address: &str,
+    ) -> trc::Result<Option<Suppression>> {
+        self.store()
+            .get_value::<SuppressionValue>(ValueKey::from(suppression_key(tenant_id, address)))
+            .await
+            .caused_by(trc::location!())?
+            .map(|value| Suppression::deserialize(&value.0))
+            .transpose()
+            .map(|entry| entry.filter(|entry| entry.expires.is_none_or(|expires| expires > now())))
+    }
+
+    async fn suppression_add(&self, tenant_id: Option<u32>, entry: Suppression) -> trc::Result<()> {
+        let mut batch = BatchBuilder::new();
+        batch.any_op(Operation::Value {
+            class: suppression_key(tenant_id, &entry.address),
+            op: ValueOp::Set(entry.serialize()),
+        });
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            Suppression(SuppressionEvent::Added),
+            To = entry.address,
+            Reason = entry.reason.as_str(),
+            Expires = entry.expires.map(trc::Value::Timestamp),
+        );
+
+        Ok(())
+    }
+
+    async fn suppression_remove(&self, tenant_id: Option<u32>, address: &str) -> trc::Result<bool> {
+        if self.suppression_get(tenant_id, address).await?.is_none() {
+            return Ok(false);
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch.any_op(Operation::Value {
+            class: suppression_key(tenant_id, address),
+            op: ValueOp::Clear,
+        });
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            Suppression(SuppressionEvent::Removed),
+            To = address.to_string(),
+        );
+
+        Ok(true)
+    }
+
+    async fn suppression_list(&self, tenant_id: Option<u32>) -> trc::Result<Vec<Suppression>> {
+        let mut entries = Vec::new();
+        let current_time = now();
+        let tenant_id = tenant_id.unwrap_or(NO_TENANT);
+
+        self.store()
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::InMemory(InMemoryClass::Key(
+                        suppression_prefix(tenant_id, &[]),
+                    ))),
+                    ValueKey::from(ValueClass::InMemory(InMemoryClass::Key(
+                        suppression_prefix(tenant_id, &[u8::MAX; 10]),
+                    ))),
+                )
+                .ascending(),
+                |_, value| {
+                    let entry = Suppression::deserialize(value)?;
+                    if entry.expires.is_none_or(|expires| expires > current_time) {
+                        entries.push(entry);
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(entries)
+    }
+
+    async fn suppression_tenant(&self, address: &str) -> trc::Result<Option<u32>> {
+        match self
+            .core
+            .storage
+            .directory
+            .email_to_id(address)
+            .await
+            .caused_by(trc::location!())?
+        {
+            Some(account_id) => self
+                .get_access_token(account_id)
+                .await
+                .map(|access_token| access_token.tenant_id()),
+            None => Ok(None),
+        }
+    }
+
+    async fn process_bounces(&self, sender: &str, bounces: Vec<Bounce>, session_id: u64) {
+        let config = &self.core.smtp.suppression;
+        if bounces.is_empty() {
+            return;
+        }
+
+        let tenant_id = match self.suppression_tenant(sender).await {
+            Ok(tenant_id) => tenant_id,
+            Err(err) => {
+                trc::error!(
+                    err.span_id(session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to obtain sender tenant.")
+                );
+                return;
+            }
+        };
+
+        for bounce in bounces {
+            let result = match bounce.kind {
+                BounceKind::Hard => {
+                    trc::event!(
+                        Suppression(SuppressionEvent::HardBounce),
+                        SpanId = session_id,
+                        From = sender.to_string(),
+                        To = bounce.address.clone(),
+                        Code = bounce.status.clone(),
+                        Details = bounce.diagnostic.clone(),
+                    );
+
+                    Ok(Some(config.hard_expiry))
+                }
+                BounceKind::Soft => {
+                    trc::event!(
+                        Suppression(SuppressionEvent::SoftBounce),
+                        SpanId = session_id,
+                        From = sender.to_string(),
+                        To = bounce.address.clone(),
+                        Code = bounce.status.clone(),
+                        Details = bounce.diagnostic.clone(),
+                    );
+
+                    // Suppress only once the soft bounce limit is exceeded
+                    let key =
+                        suppression_key_bytes(tenant_id.unwrap_or(NO_TENANT), &bounce.address);
+                    self.in_memory_store()
+                        .is_rate_allowed(KV_RATE_LIMIT_BOUNCE, &key, &config.soft_limit, false)
+                        .await
+                        .map(|exceeded| exceeded.map(|_| config.soft_expiry))
+                }
+            };
+
+            let expiry = match result {
+                Ok(Some(expiry)) => expiry,
+                Ok(None) => continue,
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(session_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to process bounce.")
+                    );
+                    continue;
+                }
+            };
+
+            let created = now();
+            let entry = Suppression {
+                address: bounce.address,
+                reason: match bounce.kind {
+                    BounceKind::Hard => SuppressionReason::HardBounce,
+                    BounceKind::Soft => SuppressionReason::SoftBounce,
+                },
+                status: Some(bounce.status),
+                diagnostic: bounce.diagnostic,
+                created,
+                expires: Some(created + expiry),
+            };
+            if let Err(err) = self.suppression_add(tenant_id, entry).await {
+                trc::error!(
+                    err.span_id(session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to add address to suppression list.")
+                );
+            }
+        }
+    }
+
+    async fn bounce_track(
+        &self,
+        origin: BounceOrigin,
+        queue_id: u64,
+        message_id: Option<&str>,
+        session_id: u64,
+    ) {
+        let track_for = self.core.smtp.suppression.track_for;
+        let store = self.in_memory_store();
+
+        let result = async {
+            let value = Archiver::new(origin)
+                .untrusted()
+                .serialize()
+                .caused_by(trc::location!())?;
+            if let Some(message_id) = message_id.and_then(origin_message_key) {
+                store
+                    .key_set(
+                        KeyValue::with_prefix(KV_BOUNCE_ORIGIN, message_id, value.clone())
+                            .expires(track_for),
+                    )
+                    .await?;
+            }
+            store
+                .key_set(
+                    KeyValue::with_prefix(KV_BOUNCE_ORIGIN, origin_queue_key(queue_id), value)
+                        .expires(track_for),
+                )
+                .await
+        }
+        .await;
+
+        if let Err(err) = result {
+            trc::error!(
+                err.span_id(session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to track message for bounces.")
+            );
+        }
+    }
+
+    async fn process_dsn_bounces(
+        &self,
+        sender: &str,
+        origin_keys: Vec<Vec<u8>>,
+        bounces: Vec<Bounce>,
+        session_id: u64,
+    ) {
+        // Only trust notifications about messages we sent to the bounced addresses
+        let mut origin = None;
+        for key in origin_keys {
+            match self
+                .in_memory_store()
+                .key_get::<Archive<AlignedBytes>>(KeyValue::<()>::build_key(KV_BOUNCE_ORIGIN, key))
+                .await
+                .and_then(|value| {
+                    value
+                        .map(|value| value.deserialize::<BounceOrigin>())
+                        .transpose()
+                }) {
+                Ok(Some(value)) => {
+                    origin = Some(value);
+                    break;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(session_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to obtain bounce origin.")
+                    );
+                    return;
+                }
+            }
+        }
+
+        match origin.filter(|origin| origin.sender.eq_ignore_ascii_case(sender)) {
+            Some(origin) => {
+                let bounces = bounces
+                    .into_iter()
+                    .filter(|bounce| {
+                        origin
+                            .recipients
+                            .iter()
+                            .any(|rcpt| rcpt.eq_ignore_ascii_case(&bounce.address))
+                    })
+                    .collect::<Vec<_>>();
+                self.process_bounces(&origin.sender, bounces, session_id)
+                    .await;
+            }
+            None => {
+                trc::event!(
+                    Suppression(SuppressionEvent::BounceUnmatched),
+                    SpanId = session_id,
+                    To = sender.to_string(),
+                    Total = bounces.len(),
+                );
+            }
+        }
+    }
+}
+
+impl SuppressionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionReason::HardBounce => "hard-bounce",
+            SuppressionReason::SoftBounce => "soft-bounce",
+            SuppressionReason::Manual => "manual",
+        }
+    }
+}
+
+fn suppression_key(tenant_id: Option<u32>, address: &str) -> ValueClass {
+    ValueClass::InMemory(InMemoryClass::Key(suppression_prefix(
+        tenant_id.unwrap_or(NO_TENANT),
+        address.as_bytes(),
+    )))
+}
+
+fn suppression_prefix(tenant_id: u32, address: &[u8]) -> Vec<u8> {
+    KeySerializer::new(1 + std::mem::size_of::<u32>() + address.len())
+        .write(KV_SUPPRESSION)
+        .write(tenant_id)
+        .write(address)
+        .finalize()
+}
+
+fn suppression_key_bytes(tenant_id: u32, address: &str) -> Vec<u8> {
+    KeySerializer::new(std::mem::size_of::<u32>() + address.len())
+        .write(tenant_id)
+        .write(address.as_bytes())
+        .finalize()
+}
+
+impl Suppression {
+    fn serialize(&self) -> Vec<u8> {
+        serialize_with_expiry(
+            self.expires.unwrap_or(u64::MAX),
+            &serde_json::to_vec(self).unwrap_or_default(),
+        )
+    }
+
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        value_without_expiry(bytes)
+            .and_then(|value| serde_json::from_slice::<Suppression>(value).ok())
+            .ok_or_else(|| {
+                trc::StoreEvent::DataCorruption
+                    .caused_by(trc::location!())
+                    .ctx(trc::Key::Value, bytes)
+            })
+    }
+}
+
+struct SuppressionValue(Vec<u8>);
+
+impl store::Deserialize for SuppressionValue {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        Ok(SuppressionValue(bytes.to_vec()))
+    }
+}
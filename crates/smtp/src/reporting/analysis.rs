@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use super::complaints::Complaints;
 use ahash::AHashMap;
 use common::Server;
 use mail_auth::{
@@ -52,11 +53,11 @@ pub struct IncomingReport<T> {
 }
 
 pub trait AnalyzeReport: Sync + Send {
-    fn analyze_report(&self, message: Message<'static>, session_id: u64);
+    fn analyze_report(&self, message: Message<'static>, is_trusted_source: bool, session_id: u64);
 }
 
 impl AnalyzeReport for Server {
-    fn analyze_report(&self, message: Message<'static>, session_id: u64) {
+    fn analyze_report(&self, message: Message<'static>, is_trusted_source: bool, session_id: u64) {
         let core = self.clone();
         tokio::spawn(async move {
             let from: String = message
@@ -256,6 +257,20 @@ impl AnalyzeReport for Server {
                         Some(report) => {
                             // Log
                             report.log();
+
+                            // Trace complaints back to the sending account
+                            if core.core.smtp.report.analysis.complaints.enable {
+                                if is_trusted_source {
+                                    core.process_complaint(&message, &report, session_id).await;
+                                } else {
+                                    trc::event!(
+                                        Complaint(trc::ComplaintEvent::Untrusted),
+                                        SpanId = session_id,
+                                        From = from.to_string(),
+                                    );
+                                }
+                            }
+
                             Format::Arf(report.into_owned())
                         }
                         None => {
@@ -0,0 +1,445 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{KV_COMPLAINT_COUNTER, KV_COMPLAINT_ORIGIN, KV_COMPLAINT_SUSPENDED, Server};
+use mail_auth::report::{Feedback, FeedbackType};
+use mail_parser::{HeaderName, Message, MessageParser, MimeHeaders, PartType};
+use serde::Serialize;
+use std::future::Future;
+use store::{
+    Serialize as _,
+    dispatch::lookup::KeyValue,
+    write::{AlignedBytes, Archive, Archiver, key::KeySerializer, now},
+};
+use trc::{AddContext, ComplaintEvent};
+
+/// Account that sent a message, remembered for matching feedback loop reports
+#[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug, Clone, PartialEq, Eq)]
+pub struct ComplaintOrigin {
+    pub account_id: u32,
+    pub queue_id: u64,
+    pub sender: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComplaintStats {
+    pub sent: u64,
+    pub complaints: u64,
+    pub rate: f64,
+    pub period_start: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suspended: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Counter {
+    AccountSent = 0,
+    AccountComplaints = 1,
+    DomainSent = 2,
+    DomainComplaints = 3,
+}
+
+pub trait Complaints: Sync + Send {
+    fn complaint_track(
+        &self,
+        origin: ComplaintOrigin,
+        message_id: Option<&str>,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn process_complaint(
+        &self,
+        message: &Message<'_>,
+        feedback: &Feedback<'_>,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn complaint_stats_account(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<ComplaintStats>> + Send;
+
+    fn complaint_stats_domain(
+        &self,
+        domain: &str,
+    ) -> impl Future<Output = trc::Result<ComplaintStats>> + Send;
+
+    fn is_sending_suspended(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn sending_release(&self, account_id: u32) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl Complaints for Server {
+    async fn complaint_track(
+        &self,
+        origin: ComplaintOrigin,
+        message_id: Option<&str>,
+        session_id: u64,
+    ) {
+        let config = &self.core.smtp.report.analysis.complaints;
+        let store = self.in_memory_store();
+        let bucket = now() / config.period;
+        let domain = sender_domain(&origin.sender).to_string();
+        let account_id = origin.account_id;
+        let queue_key = origin_queue_key(origin.queue_id);
+
+        let result = async {
+            let value = Archiver::new(origin)
+                .untrusted()
+                .serialize()
+                .caused_by(trc::location!())?;
+            if let Some(message_id) = message_id.and_then(origin_message_key) {
+                store
+                    .key_set(
+                        KeyValue::with_prefix(KV_COMPLAINT_ORIGIN, message_id, value.clone())
+                            .expires(config.track_for),
+                    )
+                    .await?;
+            }
+            store
+                .key_set(
+                    KeyValue::with_prefix(KV_COMPLAINT_ORIGIN, queue_key, value)
+                        .expires(config.track_for),
+                )
+                .await?;
+
+            for key in [
+                counter_key(Counter::AccountSent, bucket, &account_id.to_be_bytes()),
+                counter_key(Counter::DomainSent, bucket, domain.as_bytes()),
+            ] {
+                store
+                    .counter_incr(
+                        KeyValue::with_prefix(KV_COMPLAINT_COUNTER, key, 1)
+                            .expires(config.period * 2),
+                        false,
+                    )
+                    .await?;
+            }
+
+            trc::Result::Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            trc::error!(
+                err.span_id(session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to track message for complaints.")
+            );
+        }
+    }
+
+    async fn process_complaint(
+        &self,
+        message: &Message<'_>,
+        feedback: &Feedback<'_>,
+        session_id: u64,
+    ) {
+        if feedback.feedback_type() != FeedbackType::Abuse {
+            return;
+        }
+
+        let result = async {
+            let Some(origin) = self.complaint_origin(message).await? else {
+                trc::event!(
+                    Complaint(ComplaintEvent::Unmatched),
+                    SpanId = session_id,
+                    From = feedback.original_mail_from().map(|from| from.to_string()),
+                    To = feedback.original_rcpt_to().map(|to| to.to_string()),
+                );
+
+                return Ok(());
+            };
+
+            let config = &self.core.smtp.report.analysis.complaints;
+            let store = self.in_memory_store();
+            let bucket = now() / config.period;
+            let domain = sender_domain(&origin.sender);
+
+            trc::event!(
+                Complaint(ComplaintEvent::Received),
+                SpanId = session_id,
+                AccountId = origin.account_id,
+                QueueId = origin.queue_id,
+                From = origin.sender.clone(),
+                To = feedback.original_rcpt_to().map(|to| to.to_string()),
+                Domain = feedback.reporting_mta().map(|mta| mta.to_string()),
+            );
+
+            // Each report is a single complaint, the incident count is set by the reporter
+            let mut complaints = 0;
+            for (counter, key) in [
+                (
+                    Counter::AccountComplaints,
+                    &origin.account_id.to_be_bytes()[..],
+                ),
+                (Counter::DomainComplaints, domain.as_bytes()),
+            ] {
+                let value = store
+                    .counter_incr(
+                        KeyValue::with_prefix(
+                            KV_COMPLAINT_COUNTER,
+                            counter_key(counter, bucket, key),
+                            1,
+                        )
+                        .expires(config.period * 2),
+                        true,
+                    )
+                    .await?;
+                if counter == Counter::AccountComplaints {
+                    complaints = value.max(0) as u64;
+                }
+            }
+
+            // Suspend accounts over the complaint rate threshold
+            if config.suspend && !self.is_sending_suspended(origin.account_id).await? {
+                let sent = store
+                    .counter_get(KeyValue::<()>::build_key(
+                        KV_COMPLAINT_COUNTER,
+                        counter_key(
+                            Counter::AccountSent,
+                            bucket,
+                            &origin.account_id.to_be_bytes(),
+                        ),
+                    ))
+                    .await?
+                    .max(0) as u64;
+
+                if config.exceeds_rate(sent, complaints) {
+                    store
+                        .key_set(KeyValue::with_prefix(
+                            KV_COMPLAINT_SUSPENDED,
+                            origin.account_id.to_be_bytes(),
+                            now().to_be_bytes().to_vec(),
+                        ))
+                        .await?;
+
+                    trc::event!(
+                        Complaint(ComplaintEvent::AccountSuspended),
+                        SpanId = session_id,
+                        AccountId = origin.account_id,
+                        Total = sent,
+                        Details = complaints,
+                    );
+                }
+            }
+
+            trc::Result::Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            trc::error!(
+                err.span_id(session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to process complaint.")
+            );
+        }
+    }
+
+    async fn complaint_stats_account(&self, account_id: u32) -> trc::Result<ComplaintStats> {
+        let mut stats = self
+            .complaint_stats(
+                Counter::AccountSent,
+                Counter::AccountComplaints,
+                &account_id.to_be_bytes(),
+            )
+            .await?;
+        stats.suspended = Some(self.is_sending_suspended(account_id).await?);
+        Ok(stats)
+    }
+
+    async fn complaint_stats_domain(&self, domain: &str) -> trc::Result<ComplaintStats> {
+        self.complaint_stats(
+            Counter::DomainSent,
+            Counter::DomainComplaints,
+            domain.to_lowercase().as_bytes(),
+        )
+        .await
+    }
+
+    async fn is_sending_suspended(&self, account_id: u32) -> trc::Result<bool> {
+        self.in_memory_store()
+            .key_exists(KeyValue::<()>::build_key(
+                KV_COMPLAINT_SUSPENDED,
+                account_id.to_be_bytes(),
+            ))
+            .await
+    }
+
+    async fn sending_release(&self, account_id: u32) -> trc::Result<bool> {
+        if !self.is_sending_suspended(account_id).await? {
+            return Ok(false);
+        }
+
+        self.in_memory_store()
+            .key_delete(KeyValue::<()>::build_key(
+                KV_COMPLAINT_SUSPENDED,
+                account_id.to_be_bytes(),
+            ))
+            .await?;
+
+        trc::event!(
+            Complaint(ComplaintEvent::AccountReleased),
+            AccountId = account_id,
+        );
+
+        Ok(true)
+    }
+}
+
+trait ComplaintLookup {
+    fn complaint_origin(
+        &self,
+        message: &Message<'_>,
+    ) -> impl Future<Output = trc::Result<Option<ComplaintOrigin>>> + Send;
+
+    fn complaint_stats(
+        &self,
+        sent: Counter,
+        complaints: Counter,
+        key: &[u8],
+    ) -> impl Future<Output = trc::Result<ComplaintStats>> + Send;
+}
+
+impl ComplaintLookup for Server {
+    async fn complaint_origin(
+        &self,
+        message: &Message<'_>,
+    ) -> trc::Result<Option<ComplaintOrigin>> {
+        for key in origin_keys(message) {
+            if let Some(value) = self
+                .in_memory_store()
+                .key_get::<Archive<AlignedBytes>>(KeyValue::<()>::build_key(
+                    KV_COMPLAINT_ORIGIN,
+                    key,
+                ))
+                .await?
+            {
+                return value
+                    .deserialize::<ComplaintOrigin>()
+                    .caused_by(trc::location!())
+                    .map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn complaint_stats(
+        &self,
+        sent: Counter,
+        complaints: Counter,
+        key: &[u8],
+    ) -> trc::Result<ComplaintStats> {
+        let period = self.core.smtp.report.analysis.complaints.period;
+        let bucket = now() / period;
+        let store = self.in_memory_store();
+        let sent = store
+            .counter_get(KeyValue::<()>::build_key(
+                KV_COMPLAINT_COUNTER,
+                counter_key(sent, bucket, key),
+            ))
+            .await?
+            .max(0) as u64;
+        let complaints = store
+            .counter_get(KeyValue::<()>::build_key(
+                KV_COMPLAINT_COUNTER,
+                counter_key(complaints, bucket, key),
+            ))
+            .await?
+            .max(0) as u64;
+
+        Ok(ComplaintStats {
+            sent,
+            complaints,
+            rate: if sent > 0 {
+                complaints as f64 / sent as f64
+            } else {
+                0.0
+            },
+            period_start: bucket * period,
+            suspended: None,
+        })
+    }
+}
+
+/// Keys of the messages an abuse report refers to, obtained from the Message-ID
+/// and from the queue ids in our Received headers of the included message
+pub(crate) fn origin_keys(message: &Message<'_>) -> Vec<Vec<u8>> {
+    let mut keys = Vec::new();
+
+    for part in &message.parts {
+        let included = match &part.body {
+            PartType::Message(included) => Some(included.clone()),
+            PartType::Text(headers) if part.is_content_type("text", "rfc822-headers") => {
+                MessageParser::new().parse_headers(headers.as_bytes())
+            }
+            _ => None,
+        };
+
+        if let Some(included) = included {
+            if let Some(key) = included.message_id().and_then(origin_message_key) {
+                keys.push(key);
+            }
+            for received in included
+                .header_values(HeaderName::Received)
+                .filter_map(|value| value.as_received())
+            {
+                if let Some(queue_id) = received
+                    .id()
+                    .and_then(|id| u64::from_str_radix(id.trim(), 16).ok())
+                {
+                    keys.push(origin_queue_key(queue_id));
+                }
+            }
+            break;
+        }
+    }
+
+    keys
+}
+
+pub(crate) fn origin_message_key(message_id: &str) -> Option<Vec<u8>> {
+    let message_id = message_id
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_lowercase();
+    if !message_id.is_empty() {
+        Some(
+            KeySerializer::new(message_id.len() + 1)
+                .write(b'm')
+                .write(message_id.as_bytes())
+                .finalize(),
+        )
+    } else {
+        None
+    }
+}
+
+pub(crate) fn origin_queue_key(queue_id: u64) -> Vec<u8> {
+    KeySerializer::new(std::mem::size_of::<u64>() + 1)
+        .write(b'q')
+        .write(queue_id)
+        .finalize()
+}
+
+fn counter_key(counter: Counter, bucket: u64, key: &[u8]) -> Vec<u8> {
+    KeySerializer::new(1 + std::mem::size_of::<u64>() + key.len())
+        .write(counter as u8)
+        .write(bucket)
+        .write(key)
+        .finalize()
+}
+
+fn sender_domain(sender: &str) -> &str {
+    sender.rsplit_once('@').map_or(sender, |(_, domain)| domain)
+}
@@ -25,8 +25,10 @@ use store::write::{ReportEvent, key::KeySerializer};
 use tokio::io::{AsyncRead, AsyncWrite};
 
 pub mod analysis;
+pub mod complaints;
 pub mod dkim;
 pub mod dmarc;
+pub mod quarantine;
 pub mod scheduler;
 pub mod spf;
 pub mod tls;
@@ -0,0 +1,127 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::SmtpReporting;
+use common::{Server, auth::oauth::GrantType};
+use email::message::quarantine::{EmailQuarantine, QuarantineReason, QuarantinedMessage};
+use mail_builder::{MessageBuilder, headers::HeaderType};
+use mail_parser::DateTime;
+use std::{collections::BTreeMap, fmt::Write, future::Future};
+use store::write::now;
+use trc::{AddContext, QuarantineEvent};
+
+pub trait QuarantineReporting: Sync + Send {
+    /// Sends each account a digest listing the messages quarantined since the last one
+    fn send_quarantine_digests(&self) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn quarantine_release_url(
+        &self,
+        message: &QuarantinedMessage,
+    ) -> impl Future<Output = trc::Result<String>> + Send;
+}
+
+impl QuarantineReporting for Server {
+    async fn send_quarantine_digests(&self) -> trc::Result<()> {
+        let Some(digest) = &self.core.smtp.quarantine.digest else {
+            return Ok(());
+        };
+
+        let mut pending: BTreeMap<u32, Vec<QuarantinedMessage>> = BTreeMap::new();
+        for message in self.quarantine_list(None).await? {
+            if !message.notified {
+                pending.entry(message.account_id).or_default().push(message);
+            }
+        }
+
+        for (account_id, messages) in pending {
+            let access_token = match self.get_access_token(account_id).await {
+                Ok(access_token) => access_token,
+                Err(err) => {
+                    trc::error!(err.account_id(account_id).caused_by(trc::location!()));
+                    continue;
+                }
+            };
+            let Some(address) = access_token.emails.first() else {
+                continue;
+            };
+
+            let mut body = format!(
+                concat!(
+                    "The following {} message(s) addressed to you were quarantined ",
+                    "and have not been delivered to your mailbox.\r\n",
+                    "Messages that are not released are deleted once they expire.\r\n"
+                ),
+                messages.len()
+            );
+            for message in &messages {
+                let url = self.quarantine_release_url(message).await?;
+                let _ = write!(
+                    &mut body,
+                    concat!(
+                        "\r\nFrom: {}\r\nSubject: {}\r\nReceived: {}\r\nReason: {}\r\n",
+                        "Expires: {}\r\nRelease: {}\r\n"
+                    ),
+                    message.from.as_deref().unwrap_or(message.sender.as_str()),
+                    message.subject.as_deref().unwrap_or_default(),
+                    DateTime::from_timestamp(message.received as i64).to_rfc822(),
+                    match message.reason {
+                        QuarantineReason::Hook => "Content filter",
+                        QuarantineReason::Spam => "Spam",
+                    },
+                    DateTime::from_timestamp(message.expires as i64).to_rfc822(),
+                    url
+                );
+            }
+
+            let raw_message = MessageBuilder::new()
+                .from((digest.from_name.as_str(), digest.from_address.as_str()))
+                .to(address.as_str())
+                .subject(digest.subject.as_str())
+                .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+                .text_body(body)
+                .write_to_vec()
+                .unwrap_or_default();
+
+            self.send_autogenerated(
+                digest.from_address.as_str(),
+                [address.as_str()].into_iter(),
+                raw_message,
+                Some(&self.core.smtp.mail_auth.dkim.sign),
+                0,
+            )
+            .await;
+            self.quarantine_set_notified(&messages)
+                .await
+                .caused_by(trc::location!())?;
+
+            trc::event!(
+                Quarantine(QuarantineEvent::DigestSent),
+                AccountId = account_id,
+                To = address.clone(),
+                Total = messages.len(),
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn quarantine_release_url(&self, message: &QuarantinedMessage) -> trc::Result<String> {
+        let token = self
+            .encode_access_token(
+                GrantType::QuarantineRelease,
+                message.account_id,
+                &message.id.to_string(),
+                message.expires.saturating_sub(now()).max(1),
+            )
+            .await?;
+
+        Ok(format!(
+            "{}/release?t={}",
+            self.core.smtp.quarantine.url,
+            form_urlencoded::byte_serialize(token.as_bytes()).collect::<String>()
+        ))
+    }
+}
@@ -14,16 +14,16 @@ pub struct NatsPubSubStream {
 }
 
 impl NatsPubSub {
-    pub async fn publish(&self, topic: &'static str, message: Vec<u8>) -> trc::Result<()> {
+    pub async fn publish(&self, topic: &str, message: Vec<u8>) -> trc::Result<()> {
         self.client
-            .publish(topic, message.into())
+            .publish(topic.to_string(), message.into())
             .await
             .map_err(|err| Error::new(EventType::Cluster(ClusterEvent::PublisherError)).reason(err))
     }
 
-    pub async fn subscribe(&self, topic: &'static str) -> trc::Result<PubSubStream> {
+    pub async fn subscribe(&self, topic: &str) -> trc::Result<PubSubStream> {
         self.client
-            .subscribe(topic)
+            .subscribe(topic.to_string())
             .await
             .map(|subs| PubSubStream::Nats(NatsPubSubStream { subs }))
             .map_err(|err| {
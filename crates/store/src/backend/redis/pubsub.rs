@@ -20,7 +20,7 @@ pub struct RedisClusterPubSubStream {
 }
 
 impl RedisStore {
-    pub async fn publish(&self, topic: &'static str, message: Vec<u8>) -> trc::Result<()> {
+    pub async fn publish(&self, topic: &str, message: Vec<u8>) -> trc::Result<()> {
         match &self.pool {
             RedisPool::Single(pool) => pool
                 .get()
@@ -41,7 +41,7 @@ impl RedisStore {
         }
     }
 
-    pub async fn subscribe(&self, topic: &'static str) -> trc::Result<PubSubStream> {
+    pub async fn subscribe(&self, topic: &str) -> trc::Result<PubSubStream> {
         match &self.pool {
             RedisPool::Single(pool) => {
                 let mut pubsub = pool
@@ -13,7 +13,7 @@ pub struct ZenohPubSubStream {
 }
 
 impl ZenohPubSub {
-    pub async fn publish(&self, topic: &'static str, message: Vec<u8>) -> trc::Result<()> {
+    pub async fn publish(&self, topic: &str, message: Vec<u8>) -> trc::Result<()> {
         self.session
             .declare_publisher(topic)
             .await
@@ -25,7 +25,7 @@ impl ZenohPubSub {
             .map_err(|err| Error::new(EventType::Cluster(ClusterEvent::PublisherError)).reason(err))
     }
 
-    pub async fn subscribe(&self, topic: &'static str) -> trc::Result<PubSubStream> {
+    pub async fn subscribe(&self, topic: &str) -> trc::Result<PubSubStream> {
         self.session
             .declare_subscriber(topic)
             .await
@@ -37,12 +37,10 @@ impl InMemoryStore {
                 let mut batch = BatchBuilder::new();
                 batch.any_op(Operation::Value {
                     class: ValueClass::InMemory(InMemoryClass::Key(kv.key)),
-                    op: ValueOp::Set(
-                        KeySerializer::new(kv.value.len() + U64_LEN)
-                            .write(kv.expires.map_or(u64::MAX, |expires| now() + expires))
-                            .write(kv.value.as_slice())
-                            .finalize(),
-                    ),
+                    op: ValueOp::Set(serialize_with_expiry(
+                        kv.expires.map_or(u64::MAX, |expires| now() + expires),
+                        &kv.value,
+                    )),
                 });
                 store.write(batch.build_all()).await.map(|_| ())
             }
@@ -617,6 +615,21 @@ impl<T> KeyValue<T> {
     }
 }
 
+/// Serializes a value using the layout of in-memory keys kept in the data store,
+/// an expiration timestamp followed by the value. Entries written with this layout
+/// are purged along with any other expired in-memory key.
+pub fn serialize_with_expiry(expires: u64, value: &[u8]) -> Vec<u8> {
+    KeySerializer::new(U64_LEN + value.len())
+        .write(expires)
+        .write(value)
+        .finalize()
+}
+
+/// Returns the value of an entry written by [`serialize_with_expiry`]
+pub fn value_without_expiry(bytes: &[u8]) -> Option<&[u8]> {
+    bytes.get(U64_LEN..)
+}
+
 enum LookupValue<T> {
     Value(T),
     None,
@@ -627,7 +640,7 @@ impl<T: Deserialize> Deserialize for LookupValue<T> {
         bytes.deserialize_be_u64(0).and_then(|expires| {
             Ok(if expires > now() {
                 LookupValue::Value(
-                    T::deserialize(bytes.get(U64_LEN..).unwrap_or_default())
+                    T::deserialize(value_without_expiry(bytes).unwrap_or_default())
                         .caused_by(trc::location!())?,
                 )
             } else {
@@ -337,6 +337,16 @@ impl ValueClass {
                     .write(account_id)
                     .write(9u8)
                     .write(document_id),
+                TaskQueueClass::ExpireEmail { due } => serializer
+                    .write(due.inner())
+                    .write(account_id)
+                    .write(10u8)
+                    .write(document_id),
+                TaskQueueClass::DeliveryHook { due } => serializer
+                    .write(due.inner())
+                    .write(account_id)
+                    .write(11u8)
+                    .write(document_id),
             },
             ValueClass::Blob(op) => match op {
                 BlobOp::Commit { hash } => serializer.write::<&[u8]>(hash.as_ref()),
@@ -605,6 +615,9 @@ impl ValueClass {
                 TaskQueueClass::SendAlarm { .. } | TaskQueueClass::MergeThreads { .. } => {
                     U64_LEN + (U32_LEN * 3) + 1
                 }
+                TaskQueueClass::ExpireEmail { .. } | TaskQueueClass::DeliveryHook { .. } => {
+                    U64_LEN + (U32_LEN * 2) + 1
+                }
                 TaskQueueClass::SendImip { is_payload, .. } => {
                     if *is_payload {
                         (U64_LEN * 2) + (U32_LEN * 2) + 1
@@ -239,6 +239,12 @@ pub enum TaskQueueClass {
     MergeThreads {
         due: TaskEpoch,
     },
+    ExpireEmail {
+        due: TaskEpoch,
+    },
+    DeliveryHook {
+        due: TaskEpoch,
+    },
 }
 
 #[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
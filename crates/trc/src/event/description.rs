@@ -43,6 +43,14 @@ impl EventType {
             EventType::Milter(event) => event.description(),
             EventType::MtaHook(event) => event.description(),
             EventType::DeliveryHook(event) => event.description(),
+            EventType::MailingList(event) => event.description(),
+            EventType::Suppression(event) => event.description(),
+            EventType::AdaptiveThrottle(event) => event.description(),
+            EventType::Warmup(event) => event.description(),
+            EventType::Complaint(event) => event.description(),
+            EventType::SendingLimit(event) => event.description(),
+            EventType::Dlp(event) => event.description(),
+            EventType::Quarantine(event) => event.description(),
             EventType::Delivery(event) => event.description(),
             EventType::Queue(event) => event.description(),
             EventType::TlsRpt(event) => event.description(),
@@ -94,6 +102,14 @@ impl EventType {
             EventType::Milter(event) => event.explain(),
             EventType::MtaHook(event) => event.explain(),
             EventType::DeliveryHook(event) => event.explain(),
+            EventType::MailingList(event) => event.explain(),
+            EventType::Suppression(event) => event.explain(),
+            EventType::AdaptiveThrottle(event) => event.explain(),
+            EventType::Warmup(event) => event.explain(),
+            EventType::Complaint(event) => event.explain(),
+            EventType::SendingLimit(event) => event.explain(),
+            EventType::Dlp(event) => event.explain(),
+            EventType::Quarantine(event) => event.explain(),
             EventType::Delivery(event) => event.explain(),
             EventType::Queue(event) => event.explain(),
             EventType::TlsRpt(event) => event.explain(),
@@ -254,6 +270,10 @@ impl ImapEvent {
             ImapEvent::ConnectionStart => "IMAP connection started",
             ImapEvent::ConnectionEnd => "IMAP connection ended",
             ImapEvent::GetQuota => "IMAP GETQUOTA command",
+            ImapEvent::Notify => "IMAP NOTIFY command",
+            ImapEvent::GetMetadata => "IMAP GETMETADATA command",
+            ImapEvent::SetMetadata => "IMAP SETMETADATA command",
+            ImapEvent::Compress => "IMAP COMPRESS command",
         }
     }
 
@@ -296,6 +316,10 @@ impl ImapEvent {
             ImapEvent::ConnectionStart => "IMAP connection started",
             ImapEvent::ConnectionEnd => "IMAP connection ended",
             ImapEvent::GetQuota => "Client requested mailbox quota",
+            ImapEvent::Notify => "Client changed mailbox notification settings",
+            ImapEvent::GetMetadata => "Client requested mailbox or server annotations",
+            ImapEvent::SetMetadata => "Client changed mailbox or server annotations",
+            ImapEvent::Compress => "Client enabled stream compression",
         }
     }
 }
@@ -1000,6 +1024,11 @@ impl DeliveryHookEvent {
             DeliveryHookEvent::ActionQuarantine => "Delivery hook action: Quarantine",
             DeliveryHookEvent::ActionDiscard => "Delivery hook action: Discard",
             DeliveryHookEvent::Error => "Delivery hook error",
+            DeliveryHookEvent::CircuitBreakerOpen => "Delivery hook circuit breaker opened",
+            DeliveryHookEvent::CircuitBreakerHalfOpen => "Delivery hook circuit breaker half-open",
+            DeliveryHookEvent::CircuitBreakerClosed => "Delivery hook circuit breaker closed",
+            DeliveryHookEvent::CircuitBreakerFallback => "Delivery hook skipped by circuit breaker",
+            DeliveryHookEvent::ActionRedirect => "Delivery hook action: Redirect",
         }
     }
 
@@ -1010,6 +1039,309 @@ impl DeliveryHookEvent {
             DeliveryHookEvent::ActionQuarantine => "The delivery hook quarantined the message",
             DeliveryHookEvent::ActionDiscard => "The delivery hook discarded the message",
             DeliveryHookEvent::Error => "An error occurred with the delivery hook",
+            DeliveryHookEvent::CircuitBreakerOpen => {
+                "The delivery hook failed too many times in a row and will not be called until the cooldown period expires"
+            }
+            DeliveryHookEvent::CircuitBreakerHalfOpen => {
+                "The delivery hook cooldown period expired and a probe request is being sent"
+            }
+            DeliveryHookEvent::CircuitBreakerClosed => {
+                "The delivery hook recovered and is being called again"
+            }
+            DeliveryHookEvent::CircuitBreakerFallback => {
+                "The delivery hook circuit breaker is open and the fallback action was applied"
+            }
+            DeliveryHookEvent::ActionRedirect => {
+                "The delivery hook redirected the message to another address"
+            }
+        }
+    }
+}
+
+impl MailingListEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            MailingListEvent::Posted => "Mailing list post distributed",
+            MailingListEvent::Held => "Mailing list post held for moderation",
+            MailingListEvent::Approved => "Mailing list post approved",
+            MailingListEvent::Rejected => "Mailing list post rejected",
+            MailingListEvent::Discarded => "Mailing list post discarded",
+            MailingListEvent::SubscribeRequested => "Mailing list subscription requested",
+            MailingListEvent::UnsubscribeRequested => "Mailing list unsubscription requested",
+            MailingListEvent::Subscribed => "Mailing list subscriber added",
+            MailingListEvent::Unsubscribed => "Mailing list subscriber removed",
+            MailingListEvent::ConfirmFailed => "Mailing list confirmation failed",
+            MailingListEvent::BounceReceived => "Mailing list bounce received",
+            MailingListEvent::Error => "Mailing list error",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            MailingListEvent::Posted => "A message was sent to all the members of a mailing list",
+            MailingListEvent::Held => {
+                "A message sent to a mailing list requires approval from a moderator"
+            }
+            MailingListEvent::Approved => "A moderator approved a message held for moderation",
+            MailingListEvent::Rejected => "A moderator rejected a message held for moderation",
+            MailingListEvent::Discarded => {
+                "A message sent to a mailing list was discarded by the list policy"
+            }
+            MailingListEvent::SubscribeRequested => {
+                "A subscription confirmation request was sent to the subscriber"
+            }
+            MailingListEvent::UnsubscribeRequested => {
+                "An unsubscription confirmation request was sent to the subscriber"
+            }
+            MailingListEvent::Subscribed => "An address was subscribed to a mailing list",
+            MailingListEvent::Unsubscribed => "An address was unsubscribed from a mailing list",
+            MailingListEvent::ConfirmFailed => {
+                "A subscription or unsubscription request could not be confirmed"
+            }
+            MailingListEvent::BounceReceived => {
+                "A delivery status notification was received for a mailing list post"
+            }
+            MailingListEvent::Error => "An error occurred while processing a mailing list message",
+        }
+    }
+}
+
+impl SuppressionEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SuppressionEvent::HardBounce => "Hard bounce received",
+            SuppressionEvent::SoftBounce => "Soft bounce received",
+            SuppressionEvent::Added => "Address added to suppression list",
+            SuppressionEvent::Removed => "Address removed from suppression list",
+            SuppressionEvent::RcptRejected => "Suppressed recipient rejected",
+            SuppressionEvent::RcptWarning => "Suppressed recipient accepted",
+            SuppressionEvent::BounceUnmatched => "Bounce could not be matched",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            SuppressionEvent::HardBounce => {
+                "A delivery status notification reported a permanent failure for a recipient"
+            }
+            SuppressionEvent::SoftBounce => {
+                "A delivery status notification reported a transient failure for a recipient"
+            }
+            SuppressionEvent::Added => "A recipient address was added to the suppression list",
+            SuppressionEvent::Removed => {
+                "A recipient address was removed from the suppression list"
+            }
+            SuppressionEvent::RcptRejected => {
+                "An authenticated sender tried to send a message to a suppressed address"
+            }
+            SuppressionEvent::RcptWarning => {
+                "A message to a suppressed address was accepted because suppression is in warn mode"
+            }
+            SuppressionEvent::BounceUnmatched => {
+                "A delivery status notification did not refer to a message sent by this server and was ignored"
+            }
+        }
+    }
+}
+
+impl AdaptiveThrottleEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            AdaptiveThrottleEvent::Deferral => "Destination deferred delivery",
+            AdaptiveThrottleEvent::LimitDecreased => "Destination limits decreased",
+            AdaptiveThrottleEvent::LimitIncreased => "Destination limits increased",
+            AdaptiveThrottleEvent::LimitRestored => "Destination limits restored",
+            AdaptiveThrottleEvent::Throttled => "Delivery throttled",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            AdaptiveThrottleEvent::Deferral => {
+                "The remote server replied with a temporary failure that indicates rate limiting"
+            }
+            AdaptiveThrottleEvent::LimitDecreased => {
+                "The concurrency and message rate allowed for the destination were lowered"
+            }
+            AdaptiveThrottleEvent::LimitIncreased => {
+                "The concurrency and message rate allowed for the destination were raised after a period without deferrals"
+            }
+            AdaptiveThrottleEvent::LimitRestored => {
+                "The destination is no longer throttled after a period without deferrals"
+            }
+            AdaptiveThrottleEvent::Throttled => {
+                "Delivery was postponed because the destination is over its adaptive limits"
+            }
+        }
+    }
+}
+
+impl WarmupEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            WarmupEvent::Started => "IP warm-up started",
+            WarmupEvent::LimitReached => "IP warm-up daily limit reached",
+            WarmupEvent::Spilled => "Delivery moved to another source IP",
+            WarmupEvent::Deferred => "Delivery deferred by IP warm-up",
+            WarmupEvent::Reset => "IP warm-up restarted",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            WarmupEvent::Started => {
+                "A source IP with a warm-up schedule was used for delivery for the first time"
+            }
+            WarmupEvent::LimitReached => {
+                "A warming source IP has delivered as many messages as its schedule allows for today"
+            }
+            WarmupEvent::Spilled => {
+                "The message is delivered from another source IP because the selected one reached its warm-up limit"
+            }
+            WarmupEvent::Deferred => {
+                "The message was deferred to the next day because no source IP has warm-up capacity left"
+            }
+            WarmupEvent::Reset => "The warm-up progress of a source IP was reset",
+        }
+    }
+}
+
+impl ComplaintEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ComplaintEvent::Received => "Abuse complaint received",
+            ComplaintEvent::Unmatched => "Abuse complaint could not be matched",
+            ComplaintEvent::AccountSuspended => "Account suspended from sending",
+            ComplaintEvent::AccountReleased => "Account sending suspension lifted",
+            ComplaintEvent::SendingRejected => "Message from suspended account rejected",
+            ComplaintEvent::Untrusted => "Abuse complaint from untrusted source ignored",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            ComplaintEvent::Received => {
+                "A feedback loop abuse report was matched to a message sent by a local account"
+            }
+            ComplaintEvent::Unmatched => {
+                "A feedback loop abuse report does not refer to any message sent by a local account"
+            }
+            ComplaintEvent::AccountSuspended => {
+                "An account was suspended from sending because its complaint rate exceeded the threshold"
+            }
+            ComplaintEvent::AccountReleased => {
+                "The sending suspension of an account was lifted by an administrator"
+            }
+            ComplaintEvent::SendingRejected => {
+                "An account suspended because of complaints tried to send a message"
+            }
+            ComplaintEvent::Untrusted => {
+                "A feedback loop abuse report was not received from a configured source and is not DKIM authenticated"
+            }
+        }
+    }
+}
+
+impl SendingLimitEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SendingLimitEvent::RateExceeded => "Account sending limit exceeded",
+            SendingLimitEvent::UniqueExceeded => "Account unique recipient limit exceeded",
+            SendingLimitEvent::VolumeSpike => "Sudden increase in account sending volume",
+            SendingLimitEvent::CountryAnomaly => "Account authenticated from too many countries",
+            SendingLimitEvent::AccountLocked => "Account locked out from sending",
+            SendingLimitEvent::AccountUnlocked => "Account sending lockout lifted",
+            SendingLimitEvent::SendingRejected => "Message from locked out account rejected",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            SendingLimitEvent::RateExceeded => {
+                "An account reached its hourly or daily recipient limit"
+            }
+            SendingLimitEvent::UniqueExceeded => {
+                "An account reached its daily limit of distinct recipients"
+            }
+            SendingLimitEvent::VolumeSpike => {
+                "The number of recipients sent to by an account grew well above its usual volume"
+            }
+            SendingLimitEvent::CountryAnomaly => {
+                "An account authenticated from more countries than allowed over the detection period"
+            }
+            SendingLimitEvent::AccountLocked => {
+                "An account was locked out from sending and its credentials were revoked because it appears to be compromised"
+            }
+            SendingLimitEvent::AccountUnlocked => {
+                "The sending lockout of an account was lifted by an administrator"
+            }
+            SendingLimitEvent::SendingRejected => {
+                "An account locked out from sending tried to send a message"
+            }
+        }
+    }
+}
+
+impl DlpEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            DlpEvent::PolicyMatched => "Content policy matched",
+            DlpEvent::MessageRejected => "Message rejected by content policy",
+            DlpEvent::MessageHeld => "Message held for approval",
+            DlpEvent::MessageReleased => "Held message released",
+            DlpEvent::TlsRequired => "TLS delivery required by content policy",
+            DlpEvent::ComplianceCopy => "Compliance copy added by content policy",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            DlpEvent::PolicyMatched => {
+                "A message submitted by an authenticated account matched a content policy"
+            }
+            DlpEvent::MessageRejected => {
+                "A message was rejected because it matched a content policy"
+            }
+            DlpEvent::MessageHeld => {
+                "A message matching a content policy was held in the queue until approved by an administrator"
+            }
+            DlpEvent::MessageReleased => {
+                "A message held by a content policy was approved for delivery"
+            }
+            DlpEvent::TlsRequired => {
+                "A message matching a content policy will only be delivered over TLS"
+            }
+            DlpEvent::ComplianceCopy => {
+                "A copy of a message matching a content policy was sent to a compliance address"
+            }
+        }
+    }
+}
+
+impl QuarantineEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            QuarantineEvent::Held => "Message quarantined",
+            QuarantineEvent::Released => "Quarantined message released",
+            QuarantineEvent::Deleted => "Quarantined message deleted",
+            QuarantineEvent::DigestSent => "Quarantine digest sent",
+            QuarantineEvent::Error => "Quarantine error",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            QuarantineEvent::Held => {
+                "A message was held in the quarantine instead of being delivered to the recipient's mailboxes"
+            }
+            QuarantineEvent::Released => {
+                "A quarantined message was released and delivered to the recipient's Inbox"
+            }
+            QuarantineEvent::Deleted => "A quarantined message was deleted without being delivered",
+            QuarantineEvent::DigestSent => {
+                "A digest listing the messages held in quarantine was sent to an account"
+            }
+            QuarantineEvent::Error => "An error occurred while processing a quarantined message",
         }
     }
 }
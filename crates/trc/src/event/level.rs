@@ -84,7 +84,11 @@ impl EventType {
                 | ImapEvent::Error
                 | ImapEvent::IdleStart
                 | ImapEvent::IdleStop
-                | ImapEvent::GetQuota => Level::Debug,
+                | ImapEvent::GetQuota
+                | ImapEvent::Notify
+                | ImapEvent::GetMetadata
+                | ImapEvent::SetMetadata
+                | ImapEvent::Compress => Level::Debug,
                 ImapEvent::RawInput | ImapEvent::RawOutput => Level::Trace,
             },
             EventType::ManageSieve(event) => match event {
@@ -418,8 +422,74 @@ impl EventType {
                 DeliveryHookEvent::ActionAccept
                 | DeliveryHookEvent::ActionDiscard
                 | DeliveryHookEvent::ActionReject
-                | DeliveryHookEvent::ActionQuarantine => Level::Info,
-                DeliveryHookEvent::Error => Level::Warn,
+                | DeliveryHookEvent::ActionQuarantine
+                | DeliveryHookEvent::CircuitBreakerHalfOpen
+                | DeliveryHookEvent::CircuitBreakerClosed
+                | DeliveryHookEvent::CircuitBreakerFallback
+                | DeliveryHookEvent::ActionRedirect => Level::Info,
+                DeliveryHookEvent::Error | DeliveryHookEvent::CircuitBreakerOpen => Level::Warn,
+            },
+            EventType::MailingList(event) => match event {
+                MailingListEvent::Posted
+                | MailingListEvent::Held
+                | MailingListEvent::Approved
+                | MailingListEvent::Rejected
+                | MailingListEvent::Subscribed
+                | MailingListEvent::Unsubscribed => Level::Info,
+                MailingListEvent::Discarded
+                | MailingListEvent::SubscribeRequested
+                | MailingListEvent::UnsubscribeRequested
+                | MailingListEvent::ConfirmFailed
+                | MailingListEvent::BounceReceived => Level::Debug,
+                MailingListEvent::Error => Level::Warn,
+            },
+            EventType::Suppression(event) => match event {
+                SuppressionEvent::HardBounce
+                | SuppressionEvent::Added
+                | SuppressionEvent::Removed
+                | SuppressionEvent::RcptRejected
+                | SuppressionEvent::RcptWarning => Level::Info,
+                SuppressionEvent::SoftBounce | SuppressionEvent::BounceUnmatched => Level::Debug,
+            },
+            EventType::AdaptiveThrottle(event) => match event {
+                AdaptiveThrottleEvent::LimitDecreased
+                | AdaptiveThrottleEvent::LimitIncreased
+                | AdaptiveThrottleEvent::LimitRestored => Level::Info,
+                AdaptiveThrottleEvent::Deferral | AdaptiveThrottleEvent::Throttled => Level::Debug,
+            },
+            EventType::Warmup(event) => match event {
+                WarmupEvent::Started | WarmupEvent::LimitReached | WarmupEvent::Reset => {
+                    Level::Info
+                }
+                WarmupEvent::Spilled | WarmupEvent::Deferred => Level::Debug,
+            },
+            EventType::Complaint(event) => match event {
+                ComplaintEvent::Received
+                | ComplaintEvent::AccountReleased
+                | ComplaintEvent::SendingRejected => Level::Info,
+                ComplaintEvent::Unmatched | ComplaintEvent::Untrusted => Level::Debug,
+                ComplaintEvent::AccountSuspended => Level::Warn,
+            },
+            EventType::SendingLimit(event) => match event {
+                SendingLimitEvent::RateExceeded
+                | SendingLimitEvent::UniqueExceeded
+                | SendingLimitEvent::AccountUnlocked
+                | SendingLimitEvent::SendingRejected => Level::Info,
+                SendingLimitEvent::VolumeSpike | SendingLimitEvent::CountryAnomaly => Level::Warn,
+                SendingLimitEvent::AccountLocked => Level::Error,
+            },
+            EventType::Dlp(event) => match event {
+                DlpEvent::PolicyMatched
+                | DlpEvent::TlsRequired
+                | DlpEvent::ComplianceCopy
+                | DlpEvent::MessageReleased => Level::Info,
+                DlpEvent::MessageRejected | DlpEvent::MessageHeld => Level::Warn,
+            },
+            EventType::Quarantine(event) => match event {
+                QuarantineEvent::Released
+                | QuarantineEvent::Deleted
+                | QuarantineEvent::DigestSent => Level::Info,
+                QuarantineEvent::Held | QuarantineEvent::Error => Level::Warn,
             },
             EventType::Dane(event) => match event {
                 DaneEvent::AuthenticationSuccess
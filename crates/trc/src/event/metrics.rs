@@ -36,6 +36,7 @@ impl MetricType {
             Self::QueueCount => "queue.count",
             Self::UserCount => "user.count",
             Self::DomainCount => "domain.count",
+            Self::DeliveryThrottledDestinations => "delivery.throttled-destinations",
         }
     }
 
@@ -68,6 +69,7 @@ impl MetricType {
             Self::QueueCount => "Total number of messages in the queue",
             Self::UserCount => "Total number of users",
             Self::DomainCount => "Total number of domains",
+            Self::DeliveryThrottledDestinations => "Destinations with reduced delivery limits",
         }
     }
 
@@ -100,6 +102,7 @@ impl MetricType {
             Self::QueueCount => "messages",
             Self::UserCount => "users",
             Self::DomainCount => "domains",
+            Self::DeliveryThrottledDestinations => "destinations",
         }
     }
 
@@ -132,6 +135,7 @@ impl MetricType {
             Self::QueueCount => 24,
             Self::UserCount => 25,
             Self::DomainCount => 26,
+            Self::DeliveryThrottledDestinations => 27,
         }
     }
 
@@ -164,6 +168,7 @@ impl MetricType {
             24 => Some(Self::QueueCount),
             25 => Some(Self::UserCount),
             26 => Some(Self::DomainCount),
+            27 => Some(Self::DeliveryThrottledDestinations),
             _ => None,
         }
     }
@@ -197,6 +202,7 @@ impl MetricType {
             "queue.count" => Some(Self::QueueCount),
             "user.count" => Some(Self::UserCount),
             "domain.count" => Some(Self::DomainCount),
+            "delivery.throttled-destinations" => Some(Self::DeliveryThrottledDestinations),
             _ => None,
         }
     }
@@ -230,6 +236,7 @@ impl MetricType {
             Self::QueueCount,
             Self::UserCount,
             Self::DomainCount,
+            Self::DeliveryThrottledDestinations,
         ]
     }
 }
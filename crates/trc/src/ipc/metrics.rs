@@ -49,6 +49,8 @@ static SERVER_MEMORY: AtomicGauge = AtomicGauge::new(MetricType::ServerMemory);
 static QUEUE_COUNT: AtomicGauge = AtomicGauge::new(MetricType::QueueCount);
 static USER_COUNT: AtomicGauge = AtomicGauge::new(MetricType::UserCount);
 static DOMAIN_COUNT: AtomicGauge = AtomicGauge::new(MetricType::DomainCount);
+static THROTTLED_DESTINATIONS: AtomicGauge =
+    AtomicGauge::new(MetricType::DeliveryThrottledDestinations);
 
 const CONN_SMTP_IN: usize = 0;
 const CONN_SMTP_OUT: usize = 1;
@@ -226,9 +228,19 @@ impl Collector {
     }
 
     pub fn collect_gauges(is_enterprise: bool) -> impl Iterator<Item = &'static AtomicGauge> {
-        static E_GAUGES: &[&AtomicGauge] =
-            &[&SERVER_MEMORY, &QUEUE_COUNT, &USER_COUNT, &DOMAIN_COUNT];
-        static C_GAUGES: &[&AtomicGauge] = &[&SERVER_MEMORY, &USER_COUNT, &DOMAIN_COUNT];
+        static E_GAUGES: &[&AtomicGauge] = &[
+            &SERVER_MEMORY,
+            &QUEUE_COUNT,
+            &USER_COUNT,
+            &DOMAIN_COUNT,
+            &THROTTLED_DESTINATIONS,
+        ];
+        static C_GAUGES: &[&AtomicGauge] = &[
+            &SERVER_MEMORY,
+            &USER_COUNT,
+            &DOMAIN_COUNT,
+            &THROTTLED_DESTINATIONS,
+        ];
 
         if is_enterprise { E_GAUGES } else { C_GAUGES }
             .iter()
@@ -315,6 +327,7 @@ impl Collector {
             MetricType::SieveRequestTime => CONNECTION_METRICS[CONN_SIEVE].elapsed.average(),
             MetricType::UserCount => USER_COUNT.get() as f64,
             MetricType::DomainCount => DOMAIN_COUNT.get() as f64,
+            MetricType::DeliveryThrottledDestinations => THROTTLED_DESTINATIONS.get() as f64,
         }
     }
 
@@ -324,6 +337,7 @@ impl Collector {
             MetricType::QueueCount => QUEUE_COUNT.set(value),
             MetricType::UserCount => USER_COUNT.set(value),
             MetricType::DomainCount => DOMAIN_COUNT.set(value),
+            MetricType::DeliveryThrottledDestinations => THROTTLED_DESTINATIONS.set(value),
             _ => {}
         }
     }
@@ -604,6 +618,15 @@ impl EventType {
                 | MilterEvent::ActionShutdown,
             ) => true,
             EventType::MtaHook(_) => true,
+            EventType::DeliveryHook(_) => true,
+            EventType::MailingList(_) => true,
+            EventType::Suppression(_) => true,
+            EventType::AdaptiveThrottle(_) => true,
+            EventType::Warmup(_) => true,
+            EventType::Complaint(_) => true,
+            EventType::SendingLimit(_) => true,
+            EventType::Dlp(_) => true,
+            EventType::Quarantine(_) => true,
             EventType::Delivery(
                 DeliveryEvent::AttemptStart
                 | DeliveryEvent::Completed
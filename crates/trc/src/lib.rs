@@ -180,6 +180,14 @@ pub enum EventType {
     Milter(MilterEvent),
     MtaHook(MtaHookEvent),
     DeliveryHook(DeliveryHookEvent),
+    MailingList(MailingListEvent),
+    Suppression(SuppressionEvent),
+    AdaptiveThrottle(AdaptiveThrottleEvent),
+    Warmup(WarmupEvent),
+    Complaint(ComplaintEvent),
+    SendingLimit(SendingLimitEvent),
+    Dlp(DlpEvent),
+    Quarantine(QuarantineEvent),
     Delivery(DeliveryEvent),
     Queue(QueueEvent),
     TlsRpt(TlsRptEvent),
@@ -284,6 +292,10 @@ pub enum ImapEvent {
     Unsubscribe,
     Thread,
     GetQuota,
+    Notify,
+    GetMetadata,
+    SetMetadata,
+    Compress,
 
     // Errors
     Error,
@@ -600,6 +612,96 @@ pub enum DeliveryHookEvent {
     ActionReject,
     ActionQuarantine,
     Error,
+    CircuitBreakerOpen,
+    CircuitBreakerHalfOpen,
+    CircuitBreakerClosed,
+    CircuitBreakerFallback,
+    ActionRedirect,
+}
+
+#[event_type]
+pub enum MailingListEvent {
+    Posted,
+    Held,
+    Approved,
+    Rejected,
+    Discarded,
+    SubscribeRequested,
+    UnsubscribeRequested,
+    Subscribed,
+    Unsubscribed,
+    ConfirmFailed,
+    BounceReceived,
+    Error,
+}
+
+#[event_type]
+pub enum SuppressionEvent {
+    HardBounce,
+    SoftBounce,
+    Added,
+    Removed,
+    RcptRejected,
+    RcptWarning,
+    BounceUnmatched,
+}
+
+#[event_type]
+pub enum AdaptiveThrottleEvent {
+    Deferral,
+    LimitDecreased,
+    LimitIncreased,
+    LimitRestored,
+    Throttled,
+}
+
+#[event_type]
+pub enum WarmupEvent {
+    Started,
+    LimitReached,
+    Spilled,
+    Deferred,
+    Reset,
+}
+
+#[event_type]
+pub enum ComplaintEvent {
+    Received,
+    Unmatched,
+    AccountSuspended,
+    AccountReleased,
+    SendingRejected,
+    Untrusted,
+}
+
+#[event_type]
+pub enum SendingLimitEvent {
+    RateExceeded,
+    UniqueExceeded,
+    VolumeSpike,
+    CountryAnomaly,
+    AccountLocked,
+    AccountUnlocked,
+    SendingRejected,
+}
+
+#[event_type]
+pub enum DlpEvent {
+    PolicyMatched,
+    MessageRejected,
+    MessageHeld,
+    MessageReleased,
+    TlsRequired,
+    ComplianceCopy,
+}
+
+#[event_type]
+pub enum QuarantineEvent {
+    Held,
+    Released,
+    Deleted,
+    DigestSent,
+    Error,
 }
 
 #[event_type]
@@ -1019,6 +1121,7 @@ pub enum MetricType {
     DeliveryTotalTime,
     DeliveryTime,
     DeliveryActiveConnections,
+    DeliveryThrottledDestinations,
     QueueCount,
     ReportOutgoingSize,
     StoreReadTime,
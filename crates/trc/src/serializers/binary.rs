@@ -614,6 +614,68 @@ impl EventType {
             EventType::DeliveryHook(DeliveryHookEvent::ActionDiscard) => 588,
             EventType::DeliveryHook(DeliveryHookEvent::ActionQuarantine) => 589,
             EventType::DeliveryHook(DeliveryHookEvent::Error) => 590,
+            EventType::DeliveryHook(DeliveryHookEvent::CircuitBreakerOpen) => 591,
+            EventType::DeliveryHook(DeliveryHookEvent::CircuitBreakerHalfOpen) => 592,
+            EventType::DeliveryHook(DeliveryHookEvent::CircuitBreakerClosed) => 593,
+            EventType::DeliveryHook(DeliveryHookEvent::CircuitBreakerFallback) => 594,
+            EventType::DeliveryHook(DeliveryHookEvent::ActionRedirect) => 595,
+            EventType::MailingList(MailingListEvent::Posted) => 596,
+            EventType::MailingList(MailingListEvent::Held) => 597,
+            EventType::MailingList(MailingListEvent::Approved) => 598,
+            EventType::MailingList(MailingListEvent::Rejected) => 599,
+            EventType::MailingList(MailingListEvent::Discarded) => 600,
+            EventType::MailingList(MailingListEvent::SubscribeRequested) => 601,
+            EventType::MailingList(MailingListEvent::UnsubscribeRequested) => 602,
+            EventType::MailingList(MailingListEvent::Subscribed) => 603,
+            EventType::MailingList(MailingListEvent::Unsubscribed) => 604,
+            EventType::MailingList(MailingListEvent::ConfirmFailed) => 605,
+            EventType::MailingList(MailingListEvent::BounceReceived) => 606,
+            EventType::MailingList(MailingListEvent::Error) => 607,
+            EventType::Suppression(SuppressionEvent::HardBounce) => 608,
+            EventType::Suppression(SuppressionEvent::SoftBounce) => 609,
+            EventType::Suppression(SuppressionEvent::Added) => 610,
+            EventType::Suppression(SuppressionEvent::Removed) => 611,
+            EventType::Suppression(SuppressionEvent::RcptRejected) => 612,
+            EventType::Suppression(SuppressionEvent::RcptWarning) => 613,
+            EventType::AdaptiveThrottle(AdaptiveThrottleEvent::Deferral) => 614,
+            EventType::AdaptiveThrottle(AdaptiveThrottleEvent::LimitDecreased) => 615,
+            EventType::AdaptiveThrottle(AdaptiveThrottleEvent::LimitIncreased) => 616,
+            EventType::AdaptiveThrottle(AdaptiveThrottleEvent::LimitRestored) => 617,
+            EventType::AdaptiveThrottle(AdaptiveThrottleEvent::Throttled) => 618,
+            EventType::Warmup(WarmupEvent::Started) => 619,
+            EventType::Warmup(WarmupEvent::LimitReached) => 620,
+            EventType::Warmup(WarmupEvent::Spilled) => 621,
+            EventType::Warmup(WarmupEvent::Deferred) => 622,
+            EventType::Warmup(WarmupEvent::Reset) => 623,
+            EventType::Complaint(ComplaintEvent::Received) => 624,
+            EventType::Complaint(ComplaintEvent::Unmatched) => 625,
+            EventType::Complaint(ComplaintEvent::AccountSuspended) => 626,
+            EventType::Complaint(ComplaintEvent::AccountReleased) => 627,
+            EventType::Complaint(ComplaintEvent::SendingRejected) => 628,
+            EventType::Complaint(ComplaintEvent::Untrusted) => 652,
+            EventType::SendingLimit(SendingLimitEvent::RateExceeded) => 629,
+            EventType::SendingLimit(SendingLimitEvent::UniqueExceeded) => 630,
+            EventType::SendingLimit(SendingLimitEvent::VolumeSpike) => 631,
+            EventType::SendingLimit(SendingLimitEvent::CountryAnomaly) => 632,
+            EventType::SendingLimit(SendingLimitEvent::AccountLocked) => 633,
+            EventType::SendingLimit(SendingLimitEvent::AccountUnlocked) => 634,
+            EventType::SendingLimit(SendingLimitEvent::SendingRejected) => 635,
+            EventType::Dlp(DlpEvent::PolicyMatched) => 636,
+            EventType::Dlp(DlpEvent::MessageRejected) => 637,
+            EventType::Dlp(DlpEvent::MessageHeld) => 638,
+            EventType::Dlp(DlpEvent::MessageReleased) => 639,
+            EventType::Dlp(DlpEvent::TlsRequired) => 640,
+            EventType::Dlp(DlpEvent::ComplianceCopy) => 641,
+            EventType::Quarantine(QuarantineEvent::Held) => 642,
+            EventType::Quarantine(QuarantineEvent::Released) => 643,
+            EventType::Quarantine(QuarantineEvent::Deleted) => 644,
+            EventType::Quarantine(QuarantineEvent::DigestSent) => 645,
+            EventType::Quarantine(QuarantineEvent::Error) => 646,
+            EventType::Imap(ImapEvent::Notify) => 647,
+            EventType::Imap(ImapEvent::GetMetadata) => 648,
+            EventType::Imap(ImapEvent::SetMetadata) => 649,
+            EventType::Imap(ImapEvent::Compress) => 650,
+            EventType::Suppression(SuppressionEvent::BounceUnmatched) => 651,
             EventType::MtaSts(MtaStsEvent::Authorized) => 309,
             EventType::MtaSts(MtaStsEvent::InvalidPolicy) => 310,
             EventType::MtaSts(MtaStsEvent::NotAuthorized) => 311,
@@ -1233,6 +1295,86 @@ impl EventType {
             588 => Some(EventType::DeliveryHook(DeliveryHookEvent::ActionDiscard)),
             589 => Some(EventType::DeliveryHook(DeliveryHookEvent::ActionQuarantine)),
             590 => Some(EventType::DeliveryHook(DeliveryHookEvent::Error)),
+            591 => Some(EventType::DeliveryHook(
+                DeliveryHookEvent::CircuitBreakerOpen,
+            )),
+            592 => Some(EventType::DeliveryHook(
+                DeliveryHookEvent::CircuitBreakerHalfOpen,
+            )),
+            593 => Some(EventType::DeliveryHook(
+                DeliveryHookEvent::CircuitBreakerClosed,
+            )),
+            594 => Some(EventType::DeliveryHook(
+                DeliveryHookEvent::CircuitBreakerFallback,
+            )),
+            595 => Some(EventType::DeliveryHook(DeliveryHookEvent::ActionRedirect)),
+            596 => Some(EventType::MailingList(MailingListEvent::Posted)),
+            597 => Some(EventType::MailingList(MailingListEvent::Held)),
+            598 => Some(EventType::MailingList(MailingListEvent::Approved)),
+            599 => Some(EventType::MailingList(MailingListEvent::Rejected)),
+            600 => Some(EventType::MailingList(MailingListEvent::Discarded)),
+            601 => Some(EventType::MailingList(MailingListEvent::SubscribeRequested)),
+            602 => Some(EventType::MailingList(
+                MailingListEvent::UnsubscribeRequested,
+            )),
+            603 => Some(EventType::MailingList(MailingListEvent::Subscribed)),
+            604 => Some(EventType::MailingList(MailingListEvent::Unsubscribed)),
+            605 => Some(EventType::MailingList(MailingListEvent::ConfirmFailed)),
+            606 => Some(EventType::MailingList(MailingListEvent::BounceReceived)),
+            607 => Some(EventType::MailingList(MailingListEvent::Error)),
+            608 => Some(EventType::Suppression(SuppressionEvent::HardBounce)),
+            609 => Some(EventType::Suppression(SuppressionEvent::SoftBounce)),
+            610 => Some(EventType::Suppression(SuppressionEvent::Added)),
+            611 => Some(EventType::Suppression(SuppressionEvent::Removed)),
+            612 => Some(EventType::Suppression(SuppressionEvent::RcptRejected)),
+            613 => Some(EventType::Suppression(SuppressionEvent::RcptWarning)),
+            614 => Some(EventType::AdaptiveThrottle(AdaptiveThrottleEvent::Deferral)),
+            615 => Some(EventType::AdaptiveThrottle(
+                AdaptiveThrottleEvent::LimitDecreased,
+            )),
+            616 => Some(EventType::AdaptiveThrottle(
+                AdaptiveThrottleEvent::LimitIncreased,
+            )),
+            617 => Some(EventType::AdaptiveThrottle(
+                AdaptiveThrottleEvent::LimitRestored,
+            )),
+            618 => Some(EventType::AdaptiveThrottle(
+                AdaptiveThrottleEvent::Throttled,
+            )),
+            619 => Some(EventType::Warmup(WarmupEvent::Started)),
+            620 => Some(EventType::Warmup(WarmupEvent::LimitReached)),
+            621 => Some(EventType::Warmup(WarmupEvent::Spilled)),
+            622 => Some(EventType::Warmup(WarmupEvent::Deferred)),
+            623 => Some(EventType::Warmup(WarmupEvent::Reset)),
+            624 => Some(EventType::Complaint(ComplaintEvent::Received)),
+            625 => Some(EventType::Complaint(ComplaintEvent::Unmatched)),
+            626 => Some(EventType::Complaint(ComplaintEvent::AccountSuspended)),
+            627 => Some(EventType::Complaint(ComplaintEvent::AccountReleased)),
+            628 => Some(EventType::Complaint(ComplaintEvent::SendingRejected)),
+            629 => Some(EventType::SendingLimit(SendingLimitEvent::RateExceeded)),
+            630 => Some(EventType::SendingLimit(SendingLimitEvent::UniqueExceeded)),
+            631 => Some(EventType::SendingLimit(SendingLimitEvent::VolumeSpike)),
+            632 => Some(EventType::SendingLimit(SendingLimitEvent::CountryAnomaly)),
+            633 => Some(EventType::SendingLimit(SendingLimitEvent::AccountLocked)),
+            634 => Some(EventType::SendingLimit(SendingLimitEvent::AccountUnlocked)),
+            635 => Some(EventType::SendingLimit(SendingLimitEvent::SendingRejected)),
+            636 => Some(EventType::Dlp(DlpEvent::PolicyMatched)),
+            637 => Some(EventType::Dlp(DlpEvent::MessageRejected)),
+            638 => Some(EventType::Dlp(DlpEvent::MessageHeld)),
+            639 => Some(EventType::Dlp(DlpEvent::MessageReleased)),
+            640 => Some(EventType::Dlp(DlpEvent::TlsRequired)),
+            641 => Some(EventType::Dlp(DlpEvent::ComplianceCopy)),
+            642 => Some(EventType::Quarantine(QuarantineEvent::Held)),
+            643 => Some(EventType::Quarantine(QuarantineEvent::Released)),
+            644 => Some(EventType::Quarantine(QuarantineEvent::Deleted)),
+            645 => Some(EventType::Quarantine(QuarantineEvent::DigestSent)),
+            646 => Some(EventType::Quarantine(QuarantineEvent::Error)),
+            647 => Some(EventType::Imap(ImapEvent::Notify)),
+            648 => Some(EventType::Imap(ImapEvent::GetMetadata)),
+            649 => Some(EventType::Imap(ImapEvent::SetMetadata)),
+            650 => Some(EventType::Imap(ImapEvent::Compress)),
+            651 => Some(EventType::Suppression(SuppressionEvent::BounceUnmatched)),
+            652 => Some(EventType::Complaint(ComplaintEvent::Untrusted)),
             309 => Some(EventType::MtaSts(MtaStsEvent::Authorized)),
             310 => Some(EventType::MtaSts(MtaStsEvent::InvalidPolicy)),
             311 => Some(EventType::MtaSts(MtaStsEvent::NotAuthorized)),
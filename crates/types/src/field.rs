@@ -43,12 +43,14 @@ pub enum EmailField {
     Threading,
     DeletedAt,
     ThreadingId,
+    HookAudit,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum MailboxField {
     UidCounter,
+    Metadata,
     Archive,
 }
 
@@ -84,6 +86,7 @@ pub enum PrincipalField {
     DefaultAddressBookId,
     ActiveScriptId,
     PushSubscriptions,
+    MailboxMetadata,
 }
 
 impl From<ContactField> for u8 {
@@ -122,6 +125,7 @@ impl From<EmailField> for u8 {
             EmailField::Threading => 90,
             EmailField::DeletedAt => 91,
             EmailField::ThreadingId => 92,
+            EmailField::HookAudit => 93,
             EmailField::Archive => ARCHIVE_FIELD,
         }
     }
@@ -131,6 +135,7 @@ impl From<MailboxField> for u8 {
     fn from(value: MailboxField) -> Self {
         match value {
             MailboxField::UidCounter => 84,
+            MailboxField::Metadata => 85,
             MailboxField::Archive => ARCHIVE_FIELD,
         }
     }
@@ -164,6 +169,7 @@ impl From<PrincipalField> for u8 {
             PrincipalField::DefaultAddressBookId => 48,
             PrincipalField::ActiveScriptId => 49,
             PrincipalField::PushSubscriptions => 44,
+            PrincipalField::MailboxMetadata => 52,
             PrincipalField::Archive => ARCHIVE_FIELD,
         }
     }
@@ -13,7 +13,7 @@ use crate::{
 use ahash::AHashSet;
 use common::{Core, Inner, Server, config::storage::Storage};
 use directory::{
-    Permission, QueryBy, QueryParams, Type,
+    Permission, PrincipalData, QueryBy, QueryParams, Type,
     backend::{
         RcptType,
         internal::{
@@ -22,6 +22,7 @@ use directory::{
             manage::{self, ChangedPrincipals, ManageDirectory, UpdatePrincipal},
         },
     },
+    core::scram::ScramVerifier,
 };
 use http::management::stores::destroy_account_data;
 use mail_send::Credentials;
@@ -460,6 +461,18 @@ async fn internal_directory() {
             .await
             .unwrap()
             .unwrap();
+        // Changing the password stores a SCRAM verifier derived from it
+        let verifiers = principal
+            .data
+            .iter()
+            .filter_map(|data| match data {
+                PrincipalData::ScramVerifier(secret) => ScramVerifier::parse(secret),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(verifiers.len(), 1);
+        assert!(verifiers[0].verify_password("12345"));
+        assert!(!verifiers[0].verify_password("secret"));
         let principal = store.map_principal(principal, &[]).await.unwrap();
         assert_eq!(
             principal.into_test().into_sorted(),
@@ -15,6 +15,7 @@ use common::{Core, Server, config::smtp::session::AddressMapping};
 use directory::{
     Directories, Principal, PrincipalData, Type,
     backend::internal::{PrincipalField, PrincipalSet, manage::ManageDirectory},
+    core::scram::SCRAM_SHA256_PREFIX,
 };
 use mail_send::Credentials;
 use rustls::ServerConfig;
@@ -567,7 +568,10 @@ impl From<PrincipalSet> for TestPrincipal {
             name: value.take_str(PrincipalField::Name).unwrap_or_default(),
             secrets: value
                 .take_str_array(PrincipalField::Secrets)
-                .unwrap_or_default(),
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|secret| !secret.starts_with(SCRAM_SHA256_PREFIX))
+                .collect(),
             emails: value
                 .take_str_array(PrincipalField::Emails)
                 .unwrap_or_default(),
@@ -0,0 +1,118 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{io::Write, time::Duration};
+
+use flate2::{
+    Compression,
+    write::{DeflateDecoder, DeflateEncoder},
+};
+use imap_proto::ResponseType;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test() {
+    println!("Running COMPRESS tests...");
+
+    // Compression is only offered to authenticated clients
+    let mut imap = ImapConnection::connect(b"_z ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.send("COMPRESS DEFLATE").await;
+    imap.assert_read(Type::Tagged, ResponseType::No).await;
+    imap.authenticate("jdoe@example.com", "secret").await;
+    imap.send("CAPABILITY").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("COMPRESS=DEFLATE");
+
+    // Enable compression, the response is sent uncompressed
+    imap.send("COMPRESS DEFLATE").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let mut imap = DeflateConnection::new(imap);
+
+    // Commands are exchanged over the compressed stream
+    imap.send("C1 SELECT INBOX").await;
+    imap.assert_tagged("C1 OK").await;
+    imap.send("C2 LIST \"\" \"*\"").await;
+    imap.assert_tagged("C2 OK").await;
+
+    // Compression cannot be enabled twice
+    imap.send("C3 COMPRESS DEFLATE").await;
+    imap.assert_tagged("C3 NO [COMPRESSIONACTIVE]").await;
+
+    // TLS cannot be negotiated on top of compression
+    imap.send("C4 STARTTLS").await;
+    imap.assert_tagged("C4 NO").await;
+
+    // The session is still usable
+    imap.send("C5 NOOP").await;
+    imap.assert_tagged("C5 OK").await;
+    imap.send("C6 LOGOUT").await;
+    imap.assert_tagged("C6 OK").await;
+}
+
+struct DeflateConnection {
+    stream: TcpStream,
+    encoder: DeflateEncoder<Vec<u8>>,
+    decoder: DeflateDecoder<Vec<u8>>,
+}
+
+impl DeflateConnection {
+    fn new(imap: ImapConnection) -> Self {
+        DeflateConnection {
+            stream: imap.reader.into_inner().into_inner().unsplit(imap.writer),
+            encoder: DeflateEncoder::new(Vec::new(), Compression::default()),
+            decoder: DeflateDecoder::new(Vec::new()),
+        }
+    }
+
+    async fn send(&mut self, line: &str) {
+        self.encoder.write_all(line.as_bytes()).unwrap();
+        self.encoder.write_all(b"\r\n").unwrap();
+        self.encoder.flush().unwrap();
+        let bytes = std::mem::take(self.encoder.get_mut());
+        self.stream.write_all(&bytes).await.unwrap();
+    }
+
+    // Reads responses until the tagged one
+    async fn assert_tagged(&mut self, response: &str) {
+        let (tag, _) = response.split_once(' ').unwrap();
+        loop {
+            let line = self.read_line().await;
+            if line.starts_with(tag) {
+                assert!(
+                    line.starts_with(response),
+                    "Expected {response:?}, got {line:?}"
+                );
+                break;
+            }
+        }
+    }
+
+    async fn read_line(&mut self) -> String {
+        let mut buf = vec![0; 1024];
+        loop {
+            let decoded = self.decoder.get_mut();
+            if let Some(pos) = decoded.iter().position(|&ch| ch == b'\n') {
+                let line = decoded.drain(..=pos).collect::<Vec<_>>();
+                return String::from_utf8(line).unwrap();
+            }
+
+            let bytes_read =
+                tokio::time::timeout(Duration::from_millis(1500), self.stream.read(&mut buf))
+                    .await
+                    .expect("Timeout while waiting for server response")
+                    .unwrap();
+            assert_ne!(bytes_read, 0, "Connection closed");
+            self.decoder.write_all(&buf[..bytes_read]).unwrap();
+            self.decoder.flush().unwrap();
+        }
+    }
+}
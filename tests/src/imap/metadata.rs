@@ -0,0 +1,141 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{AssertResult, ImapConnection, Type};
+use imap_proto::ResponseType;
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    println!("Running METADATA tests...");
+
+    imap.send("CAPABILITY").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("METADATA");
+
+    // Nothing is returned for unset annotations
+    imap.send("GETMETADATA \"\" /private/vendor/vendor.dovecot/webmail-user")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("* METADATA", 0);
+
+    // Values containing line breaks are sent as literals
+    imap.send(concat!(
+        "SETMETADATA INBOX (/private/comment {12+}\r\n",
+        "My new\r\nnote /shared/Comment \"Shared note\")"
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .send("GETMETADATA INBOX (/private/comment /shared/comment)")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* METADATA \"INBOX\" (/private/comment {12}")
+        .assert_equals("My new")
+        .assert_equals("note /shared/comment \"Shared note\")");
+
+    // Entries over MAXSIZE are omitted and reported
+    imap.send("GETMETADATA (MAXSIZE 11 DEPTH infinity) INBOX (/shared/Comment /private/comment)")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* METADATA \"INBOX\" (/shared/comment \"Shared note\")")
+        .assert_contains("[METADATA LONGENTRIES 12]");
+
+    // Entries below the requested one are returned depending on DEPTH
+    imap.send(concat!(
+        "SETMETADATA INBOX (/shared/vendor/acme/color \"red\" ",
+        "/shared/vendor/acme/size/large \"yes\")"
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    for (depth, expected) in [
+        ("0", None),
+        (
+            "1",
+            Some("* METADATA \"INBOX\" (/shared/vendor/acme/color \"red\")"),
+        ),
+        (
+            "infinity",
+            Some(concat!(
+                "* METADATA \"INBOX\" (/shared/vendor/acme/color \"red\" ",
+                "/shared/vendor/acme/size/large \"yes\")"
+            )),
+        ),
+    ] {
+        imap.send(&format!(
+            "GETMETADATA (DEPTH {depth}) INBOX /shared/vendor/acme"
+        ))
+        .await;
+        let response = imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+        if let Some(expected) = expected {
+            response.assert_equals(expected);
+        } else {
+            response.assert_count("* METADATA", 0);
+        }
+    }
+
+    // Annotations can be requested when listing mailboxes
+    imap.send("LIST \"\" INBOX RETURN (METADATA (/shared/vendor/acme/color /private/none))")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* LIST")
+        .assert_equals("* METADATA \"INBOX\" (/shared/vendor/acme/color \"red\")");
+
+    // Server annotations are stored separately from mailbox annotations
+    imap.send("SETMETADATA \"\" (/private/vendor/vendor.dovecot/webmail-user \"john\")")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("GETMETADATA \"\" (/private/vendor/vendor.dovecot/webmail-user /private/comment)")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* METADATA \"\" (/private/vendor/vendor.dovecot/webmail-user \"john\")");
+
+    // Oversized values and too many annotations are rejected
+    imap.send(&format!(
+        "SETMETADATA INBOX (/private/large \"{}\")",
+        "a".repeat(101)
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[METADATA MAXSIZE 100]");
+    imap.send("SETMETADATA INBOX (/private/a \"1\" /private/b \"2\" /private/c \"3\")")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[METADATA TOOMANY]");
+
+    // Invalid entry names are rejected
+    for entry in ["/comment", "/shared/comment/", "/shared/*"] {
+        imap.send(&format!("GETMETADATA INBOX {entry}")).await;
+        imap.assert_read(Type::Tagged, ResponseType::Bad).await;
+    }
+    imap.send("GETMETADATA Gruyere /shared/comment").await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[NONEXISTENT]");
+
+    // Setting an annotation to NIL removes it
+    imap.send(concat!(
+        "SETMETADATA INBOX (/private/comment NIL /shared/comment NIL ",
+        "/shared/vendor/acme/color NIL /shared/vendor/acme/size/large NIL)"
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("SETMETADATA \"\" (/private/vendor/vendor.dovecot/webmail-user NIL)")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("GETMETADATA (DEPTH infinity) INBOX (/private /shared)")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("* METADATA", 0);
+}
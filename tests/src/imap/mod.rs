@@ -9,16 +9,21 @@ pub mod antispam;
 pub mod append;
 pub mod basic;
 pub mod body_structure;
+pub mod compress;
 pub mod condstore;
 pub mod copy_move;
 pub mod fetch;
 pub mod idle;
 pub mod mailbox;
 pub mod managesieve;
+pub mod metadata;
+pub mod notify;
 pub mod pop;
+pub mod scram;
 pub mod search;
 pub mod store;
 pub mod thread;
+pub mod unix;
 
 use crate::{
     AssertConfig, add_test_certs,
@@ -100,8 +105,10 @@ pub async fn imap_tests() {
     copy_move::test(&mut imap, &mut imap_check).await;
     thread::test(&mut imap, &mut imap_check, &handle).await;
     idle::test(&mut imap, &mut imap_check, false).await;
+    notify::test(&mut imap, &mut imap_check).await;
     condstore::test(&mut imap, &mut imap_check).await;
     acl::test(&mut imap, &mut imap_check).await;
+    metadata::test(&mut imap, &mut imap_check).await;
 
     // Logout
     for imap in [&mut imap, &mut imap_check] {
@@ -121,6 +128,15 @@ pub async fn imap_tests() {
     // Run POP3 tests
     pop::test().await;
 
+    // Run COMPRESS tests
+    compress::test().await;
+
+    // Run SCRAM tests
+    scram::test(&handle).await;
+
+    // Run Unix socket tests
+    unix::test(&handle).await;
+
     // Print elapsed time
     let elapsed = start_time.elapsed();
     println!(
@@ -285,6 +301,22 @@ async fn init_imap_tests(delete_if_exists: bool) -> IMAPTest {
             &["spamtrap@example.com"],
         )
         .await;
+    store
+        .create_test_user(
+            "scram@example.com",
+            "secret",
+            "Scram User",
+            &["scram@example.com"],
+        )
+        .await;
+    store
+        .create_test_user(
+            "scram.hashed@example.com",
+            "$2y$05$bvIG6Nmid91Mu9RcmmWZfO5HJIMCT8riNW0hEp8f6/FuA2/mHZFpe",
+            "Scram Hashed User",
+            &["scram.hashed@example.com"],
+        )
+        .await;
     store
         .create_test_group(
             "support@example.com",
@@ -641,12 +673,23 @@ protocol = "pop3"
 max-connections = 81920
 tls.implicit = true
 
+[server.listener.submissions]
+bind = ["127.0.0.1:4465"]
+protocol = "smtp"
+max-connections = 81920
+tls.implicit = true
+
 [server.listener.lmtp-debug]
 bind = ['127.0.0.1:11201']
 greeting = 'Test LMTP instance'
 protocol = 'lmtp'
 tls.implicit = false
 
+[server.listener.imap-unix]
+bind = ['unix:{TMP}/imap.sock']
+protocol = 'imap'
+unix.mode = '600'
+
 [server.socket]
 reuse-addr = true
 
@@ -658,6 +701,10 @@ certificate = "default"
 [session.ehlo]
 reject-non-fqdn = false
 
+[session.auth]
+mechanisms = "[plain, scram_sha256_plus, scram_sha256]"
+errors.wait = "1ms"
+
 [session.rcpt]
 relay = [ { if = "!is_empty(authenticated_as)", then = true }, 
           { else = false } ]
@@ -714,6 +761,10 @@ private-key = "%{file:{PK}}%"
 [imap.protocol]
 uidplus = true
 
+[imap.metadata]
+max-size = 100
+max-entries = 3
+
 [jmap.protocol]
 set.max-objects = 100000
 
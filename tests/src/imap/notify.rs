@@ -0,0 +1,141 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{AssertResult, ImapConnection, Type};
+use imap_proto::ResponseType;
+use std::time::Duration;
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    println!("Running NOTIFY tests...");
+
+    // Request delayed notifications for the selected mailbox
+    imap_check.send("CREATE Gorgonzola").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("SELECT Gorgonzola").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .send("NOTIFY SET (SELECTED-DELAYED (MessageNew MessageExpunge FlagChange))")
+        .await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // New messages are reported right away
+    let message = "From: test@domain.com\nSubject: Test\n\nTest message\n";
+    imap.send(&format!("APPEND Gorgonzola {{{}}}", message.len()))
+        .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(message).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXISTS");
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 FETCH (FLAGS () UID 1)");
+
+    // Flag changes are reported right away
+    imap.send("SELECT Gorgonzola").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("STORE 1 +FLAGS (\\Deleted)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 FETCH (FLAGS (\\Deleted) UID 1)");
+
+    // Expunges are deferred until the next command
+    imap.send("EXPUNGE").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXPUNGE");
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), imap_check.read(Type::Status))
+            .await
+            .is_err(),
+        "Expunge was not deferred"
+    );
+    imap_check.send("NOOP").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXPUNGE")
+        .assert_contains("* 0 EXISTS");
+
+    // Request the status of personal mailboxes along with their changes
+    imap_check
+        .send(concat!(
+            "NOTIFY SET STATUS (SELECTED (MessageNew MessageExpunge)) ",
+            "(PERSONAL (MessageNew MessageExpunge MailboxName SubscriptionChange))"
+        ))
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* STATUS \"INBOX\"");
+
+    // Created mailboxes are reported
+    imap.send("CREATE Mascarpone").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* LIST ()")
+        .assert_contains("\"Mascarpone\"");
+
+    // Messages appended to other mailboxes are reported with their status
+    imap.send(&format!("APPEND Mascarpone {{{}}}", message.len()))
+        .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(message).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* STATUS \"Mascarpone\" (MESSAGES 1 ");
+
+    // Renamed mailboxes are reported as deleted under their old name
+    imap.send("RENAME Mascarpone Ricotta").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* LIST (\\NonExistent)")
+        .assert_contains("\"Mascarpone\"");
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* LIST ()")
+        .assert_contains("\"Ricotta\"");
+
+    // Subscriptions are reported
+    imap.send("SUBSCRIBE Ricotta").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* LIST (\\Subscribed)")
+        .assert_contains("\"Ricotta\"");
+
+    // Deleted mailboxes are reported as non-existent
+    imap.send("DELETE Ricotta").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* LIST (\\NonExistent)")
+        .assert_contains("\"Ricotta\"");
+
+    // Cleanup
+    imap_check.send("NOTIFY NONE").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    for imap in [&mut *imap, &mut *imap_check] {
+        imap.send("UNSELECT").await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+    imap.send("DELETE Gorgonzola").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+}
@@ -48,7 +48,7 @@ pub async fn test() {
     pop3.send("CAPA").await;
     pop3.assert_read(ResponseType::Multiline)
         .await
-        .assert_contains("SASL PLAIN")
+        .assert_contains("SASL SCRAM-SHA-256-PLUS SCRAM-SHA-256 PLAIN")
         .assert_contains("IMPLEMENTATION");
 
     // Noop
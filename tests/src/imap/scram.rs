@@ -0,0 +1,433 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{IMAPTest, ImapConnection, Type};
+use ahash::AHashMap;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use directory::core::scram::hmac_sha256;
+use imap_proto::ResponseType;
+use mail_send::smtp::tls::build_tls_connector;
+use ring::digest;
+use rustls_pki_types::ServerName;
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, ReadHalf, WriteHalf},
+    net::TcpStream,
+};
+use tokio_rustls::client::TlsStream;
+
+const PROTOCOLS: [Protocol; 4] = [
+    Protocol::Imap,
+    Protocol::Smtp,
+    Protocol::Pop3,
+    Protocol::ManageSieve,
+];
+
+pub async fn test(handle: &IMAPTest) {
+    println!("Running SCRAM tests...");
+
+    // SCRAM-SHA-256-PLUS is only offered over TLS
+    for protocol in PROTOCOLS {
+        let capabilities = SaslConnection::connect(protocol).await.capabilities;
+        assert!(
+            capabilities.contains("SCRAM-SHA-256-PLUS") && capabilities.contains("SCRAM-SHA-256"),
+            "{protocol:?}: {capabilities}"
+        );
+    }
+    let mut imap = ImapConnection::connect(b"_s ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.send("CAPABILITY").await;
+    let capabilities = imap
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .join("\n");
+    assert!(capabilities.contains("AUTH=SCRAM-SHA-256"));
+    assert!(!capabilities.contains("AUTH=SCRAM-SHA-256-PLUS"));
+
+    // Accounts with a hashed password have no SCRAM verifier until they log in
+    assert!(
+        !SaslConnection::connect(Protocol::Imap)
+            .await
+            .scram("scram.hashed@example.com", "password", Binding::None)
+            .await
+    );
+
+    // A verifier is stored after the first login with the account password
+    let mut core = handle.server.core.as_ref().clone();
+    core.jmap.scram_upgrade = true;
+    handle.server.inner.shared_core.store(Arc::new(core));
+    let mut imap = ImapConnection::connect(b"_s ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.authenticate("scram.hashed@example.com", "password")
+        .await;
+    imap.send("LOGOUT").await;
+    imap.assert_read(Type::Untagged, ResponseType::Bye).await;
+    assert!(
+        SaslConnection::connect(Protocol::Imap)
+            .await
+            .scram("scram.hashed@example.com", "password", Binding::None)
+            .await
+    );
+
+    // Authenticate using all channel binding types over every protocol
+    for protocol in PROTOCOLS {
+        for binding in [
+            Binding::None,
+            Binding::TlsExporter,
+            Binding::TlsServerEndPoint,
+        ] {
+            assert!(
+                SaslConnection::connect(protocol)
+                    .await
+                    .scram("scram@example.com", "secret", binding)
+                    .await,
+                "{protocol:?} {binding:?}"
+            );
+        }
+
+        // Wrong passwords, channel binding data and downgrades are rejected
+        for (password, binding) in [
+            ("wrong", Binding::None),
+            ("secret", Binding::Invalid),
+            ("secret", Binding::Supported),
+        ] {
+            assert!(
+                !SaslConnection::connect(protocol)
+                    .await
+                    .scram("scram@example.com", password, binding)
+                    .await,
+                "{protocol:?} {binding:?}"
+            );
+        }
+    }
+
+    // SCRAM over a plain text connection
+    let mut imap = ImapConnection::connect(b"_s ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    let client = ScramClient::new("scram@example.com", "secret", Binding::None, &[]);
+    imap.send(&format!(
+        "AUTHENTICATE SCRAM-SHA-256 {}",
+        STANDARD.encode(client.client_first())
+    ))
+    .await;
+    let server_first = imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    let (client_final, server_signature) = client.client_final(&decode_challenge(
+        server_first.last().unwrap().strip_prefix("+ ").unwrap(),
+    ));
+    imap.send_untagged(&STANDARD.encode(client_final)).await;
+    let server_final = imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    assert_eq!(
+        decode_challenge(server_final.last().unwrap().strip_prefix("+ ").unwrap()),
+        server_signature
+    );
+    imap.send_untagged("").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("LOGOUT").await;
+    imap.assert_read(Type::Untagged, ResponseType::Bye).await;
+
+    // The certificate presented during the handshake is used for tls-server-end-point,
+    // even if the certificates are replaced before authenticating
+    let certificates = handle.server.inner.data.tls_certificates.load_full();
+    let self_signed = handle
+        .server
+        .inner
+        .data
+        .tls_self_signed_cert
+        .clone()
+        .unwrap();
+    for protocol in PROTOCOLS {
+        let mut connection = SaslConnection::connect(protocol).await;
+        handle
+            .server
+            .inner
+            .data
+            .tls_certificates
+            .store(Arc::new(AHashMap::from_iter([(
+                "*".to_string(),
+                self_signed.clone(),
+            )])));
+        assert_ne!(
+            SaslConnection::connect(protocol).await.end_point,
+            connection.end_point
+        );
+        assert!(
+            connection
+                .scram("scram@example.com", "secret", Binding::TlsServerEndPoint)
+                .await,
+            "{protocol:?}"
+        );
+        handle
+            .server
+            .inner
+            .data
+            .tls_certificates
+            .store(certificates.clone());
+    }
+
+    handle
+        .server
+        .inner
+        .shared_core
+        .store(handle.server.core.clone());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Imap,
+    Smtp,
+    Pop3,
+    ManageSieve,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Binding {
+    None,
+    Supported,
+    TlsExporter,
+    TlsServerEndPoint,
+    Invalid,
+}
+
+struct SaslConnection {
+    protocol: Protocol,
+    reader: Lines<BufReader<ReadHalf<TlsStream<TcpStream>>>>,
+    writer: WriteHalf<TlsStream<TcpStream>>,
+    capabilities: String,
+    exporter: Vec<u8>,
+    end_point: Vec<u8>,
+}
+
+impl SaslConnection {
+    async fn connect(protocol: Protocol) -> Self {
+        let stream = build_tls_connector(true)
+            .connect(
+                ServerName::try_from("imap.example.org").unwrap().to_owned(),
+                TcpStream::connect(match protocol {
+                    Protocol::Imap => "127.0.0.1:9992",
+                    Protocol::Smtp => "127.0.0.1:4465",
+                    Protocol::Pop3 => "127.0.0.1:4110",
+                    Protocol::ManageSieve => "127.0.0.1:4190",
+                })
+                .await
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Channel binding data as seen by the client
+        let (_, conn) = stream.get_ref();
+        let exporter = conn
+            .export_keying_material([0u8; 32], b"EXPORTER-Channel-Binding", None)
+            .unwrap()
+            .to_vec();
+        let end_point = digest::digest(
+            &digest::SHA256,
+            conn.peer_certificates().unwrap()[0].as_ref(),
+        )
+        .as_ref()
+        .to_vec();
+
+        let (reader, writer) = tokio::io::split(stream);
+        let mut connection = SaslConnection {
+            protocol,
+            reader: BufReader::new(reader).lines(),
+            writer,
+            capabilities: String::new(),
+            exporter,
+            end_point,
+        };
+
+        connection.capabilities = match protocol {
+            Protocol::Imap => {
+                connection.read_line().await;
+                connection.send("C CAPABILITY").await;
+                connection.read_until(|line| line.starts_with("C ")).await
+            }
+            Protocol::Smtp => {
+                connection.read_line().await;
+                connection.send("EHLO mx.example.org").await;
+                connection.read_until(|line| line.starts_with("250 ")).await
+            }
+            Protocol::Pop3 => {
+                connection.read_line().await;
+                connection.send("CAPA").await;
+                connection.read_until(|line| line == ".").await
+            }
+            Protocol::ManageSieve => connection.read_until(|line| line.starts_with("OK")).await,
+        };
+
+        connection
+    }
+
+    /// Authenticates using SCRAM-SHA-256 or SCRAM-SHA-256-PLUS, returns whether the
+    /// server accepted the credentials
+    async fn scram(&mut self, username: &str, password: &str, binding: Binding) -> bool {
+        let binding_data = match binding {
+            Binding::TlsExporter => self.exporter.clone(),
+            Binding::TlsServerEndPoint => self.end_point.clone(),
+            Binding::Invalid => vec![0u8; 32],
+            Binding::None | Binding::Supported => vec![],
+        };
+        let client = ScramClient::new(username, password, binding, &binding_data);
+        let mechanism = if client.is_plus() {
+            "SCRAM-SHA-256-PLUS"
+        } else {
+            "SCRAM-SHA-256"
+        };
+        let client_first = STANDARD.encode(client.client_first());
+        self.send(&match self.protocol {
+            Protocol::Imap => format!("A AUTHENTICATE {mechanism} {client_first}"),
+            Protocol::Smtp | Protocol::Pop3 => format!("AUTH {mechanism} {client_first}"),
+            Protocol::ManageSieve => format!("AUTHENTICATE \"{mechanism}\" \"{client_first}\""),
+        })
+        .await;
+
+        let Some(server_first) = self.read_challenge().await else {
+            return false;
+        };
+        let (client_final, server_signature) = client.client_final(&server_first);
+        self.send_response(&STANDARD.encode(client_final)).await;
+        let Some(server_final) = self.read_challenge().await else {
+            return false;
+        };
+        assert_eq!(server_final, server_signature);
+        self.send_response("").await;
+
+        let line = self.read_line().await;
+        match self.protocol {
+            Protocol::Imap => line.starts_with("A OK"),
+            Protocol::Smtp => line.starts_with("235 "),
+            Protocol::Pop3 => line.starts_with("+OK"),
+            Protocol::ManageSieve => line.starts_with("OK"),
+        }
+    }
+
+    async fn read_challenge(&mut self) -> Option<String> {
+        let line = self.read_line().await;
+        match self.protocol {
+            Protocol::Imap | Protocol::Pop3 => line.strip_prefix("+ "),
+            Protocol::Smtp => line.strip_prefix("334 "),
+            Protocol::ManageSieve => line
+                .strip_prefix('"')
+                .and_then(|line| line.strip_suffix('"')),
+        }
+        .map(decode_challenge)
+    }
+
+    async fn send_response(&mut self, response: &str) {
+        if self.protocol == Protocol::ManageSieve {
+            self.send(&format!("\"{response}\"")).await;
+        } else {
+            self.send(response).await;
+        }
+    }
+
+    async fn read_until(&mut self, is_last: impl Fn(&str) -> bool) -> String {
+        let mut lines = String::new();
+        loop {
+            let line = self.read_line().await;
+            lines.push_str(&line);
+            lines.push('\n');
+            if is_last(&line) {
+                return lines;
+            }
+        }
+    }
+
+    async fn read_line(&mut self) -> String {
+        self.reader.next_line().await.unwrap().unwrap()
+    }
+
+    async fn send(&mut self, text: &str) {
+        self.writer.write_all(text.as_bytes()).await.unwrap();
+        self.writer.write_all(b"\r\n").await.unwrap();
+        self.writer.flush().await.unwrap();
+    }
+}
+
+struct ScramClient {
+    password: String,
+    gs2_header: String,
+    client_first_bare: String,
+    nonce: String,
+    binding_data: Vec<u8>,
+}
+
+impl ScramClient {
+    fn new(username: &str, password: &str, binding: Binding, binding_data: &[u8]) -> Self {
+        let nonce = STANDARD.encode(::store::rand::random::<[u8; 18]>());
+        ScramClient {
+            password: password.to_string(),
+            gs2_header: match binding {
+                Binding::None => "n,,",
+                Binding::Supported => "y,,",
+                Binding::TlsExporter => "p=tls-exporter,,",
+                Binding::TlsServerEndPoint | Binding::Invalid => "p=tls-server-end-point,,",
+            }
+            .to_string(),
+            client_first_bare: format!("n={},r={nonce}", username.replace(',', "=2C")),
+            nonce,
+            binding_data: binding_data.to_vec(),
+        }
+    }
+
+    fn is_plus(&self) -> bool {
+        self.gs2_header.starts_with("p=")
+    }
+
+    fn client_first(&self) -> String {
+        format!("{}{}", self.gs2_header, self.client_first_bare)
+    }
+
+    /// Returns the client-final message and the expected server-final message
+    fn client_final(&self, server_first: &str) -> (String, String) {
+        let mut nonce = "";
+        let mut salt = vec![];
+        let mut iterations = 0;
+        for attribute in server_first.split(',') {
+            match attribute.split_once('=').unwrap() {
+                ("r", value) => nonce = value,
+                ("s", value) => salt = STANDARD.decode(value).unwrap(),
+                ("i", value) => iterations = value.parse().unwrap(),
+                _ => {}
+            }
+        }
+        assert!(nonce.starts_with(&self.nonce), "{server_first}");
+
+        let mut cbind_input = self.gs2_header.as_bytes().to_vec();
+        cbind_input.extend_from_slice(&self.binding_data);
+        let without_proof = format!("c={},r={nonce}", STANDARD.encode(cbind_input));
+        let auth_message = format!("{},{server_first},{without_proof}", self.client_first_bare);
+
+        let mut salted_password = [0u8; 32];
+        ring::pbkdf2::derive(
+            ring::pbkdf2::PBKDF2_HMAC_SHA256,
+            std::num::NonZeroU32::new(iterations).unwrap(),
+            &salt,
+            self.password.as_bytes(),
+            &mut salted_password,
+        );
+        let client_key = hmac_sha256(&salted_password, b"Client Key");
+        let stored_key = digest::digest(&digest::SHA256, &client_key);
+        let client_signature = hmac_sha256(stored_key.as_ref(), auth_message.as_bytes());
+        let proof = client_key
+            .iter()
+            .zip(client_signature)
+            .map(|(key, signature)| key ^ signature)
+            .collect::<Vec<_>>();
+        let server_key = hmac_sha256(&salted_password, b"Server Key");
+        let server_signature = hmac_sha256(&server_key, auth_message.as_bytes());
+
+        (
+            format!("{without_proof},p={}", STANDARD.encode(proof)),
+            format!("v={}", STANDARD.encode(server_signature)),
+        )
+    }
+}
+
+fn decode_challenge(challenge: &str) -> String {
+    String::from_utf8(STANDARD.decode(challenge).unwrap()).unwrap()
+}
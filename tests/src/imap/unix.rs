@@ -0,0 +1,144 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    os::unix::fs::PermissionsExt,
+    sync::Arc,
+    time::Duration,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use common::listener::blocked::Security;
+use imap_proto::ResponseType;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        UnixStream,
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+    },
+};
+use utils::config::Config;
+
+use super::{IMAPTest, ImapConnection, Type};
+
+pub async fn test(handle: &IMAPTest) {
+    println!("Running Unix socket tests...");
+
+    // Sockets are created with the configured mode
+    let socket = handle.temp_dir.path.join("imap.sock");
+    assert_eq!(
+        std::fs::metadata(&socket).unwrap().permissions().mode() & 0o7777,
+        0o600
+    );
+
+    // Allow a single authentication failure per client
+    let old_core = handle.server.inner.shared_core.load_full();
+    let mut core = old_core.as_ref().clone();
+    core.network.security =
+        Security::parse(&mut Config::new("[server.auto-ban.auth]\nrate = \"1/1d\"").unwrap());
+    handle.server.inner.shared_core.store(Arc::new(core));
+
+    // Unix peers are rate limited by uid, exceeding the limit drops the connection
+    let mut client = UnixConnection::connect(&socket).await;
+    client.read_tagged("* OK").await;
+    client
+        .authenticate("A1", "nobody@example.com", "wrong")
+        .await;
+    client.read_tagged("A1 NO").await;
+    client
+        .authenticate("A2", "nobody@example.com", "wrong")
+        .await;
+    client.assert_disconnect().await;
+
+    // Unix peers share the unspecified address, which is never banned
+    let unspecified = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+    assert!(
+        !handle
+            .server
+            .inner
+            .data
+            .blocked_ips
+            .read()
+            .contains(&unspecified)
+    );
+    assert!(!handle.server.is_ip_blocked(&unspecified));
+    let mut client = UnixConnection::connect(&socket).await;
+    client.read_tagged("* OK").await;
+    client
+        .authenticate("A1", "jdoe@example.com", "secret")
+        .await;
+    client.read_tagged("A1 OK").await;
+    client.send("A2 LOGOUT").await;
+    client.read_tagged("A2 OK").await;
+
+    // Loopback clients are not affected by the limits of Unix peers
+    let mut imap = ImapConnection::connect(b"_u ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.authenticate("jdoe@example.com", "secret").await;
+    imap.send("LOGOUT").await;
+    imap.assert_read(Type::Untagged, ResponseType::Bye).await;
+
+    // Restore the default limits
+    handle.server.inner.shared_core.store(old_core);
+}
+
+struct UnixConnection {
+    reader: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl UnixConnection {
+    async fn connect(path: &std::path::Path) -> Self {
+        let (reader, writer) = UnixStream::connect(path).await.unwrap().into_split();
+        UnixConnection {
+            reader: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+
+    async fn authenticate(&mut self, tag: &str, user: &str, pass: &str) {
+        self.send(&format!(
+            "{tag} AUTHENTICATE PLAIN {}",
+            STANDARD.encode(format!("\0{user}\0{pass}"))
+        ))
+        .await;
+    }
+
+    async fn send(&mut self, line: &str) {
+        self.writer
+            .write_all(format!("{line}\r\n").as_bytes())
+            .await
+            .unwrap();
+    }
+
+    async fn read_tagged(&mut self, response: &str) {
+        let (tag, _) = response.split_once(' ').unwrap();
+        loop {
+            let line = self.read_line().await.expect("Connection closed");
+            if line.starts_with(tag) {
+                assert!(
+                    line.starts_with(response),
+                    "Expected {response:?}, got {line:?}"
+                );
+                break;
+            }
+        }
+    }
+
+    async fn assert_disconnect(&mut self) {
+        if let Some(line) = self.read_line().await {
+            panic!("Expected connection to be closed, but got {line:?}");
+        }
+    }
+
+    async fn read_line(&mut self) -> Option<String> {
+        tokio::time::timeout(Duration::from_millis(1500), self.reader.next_line())
+            .await
+            .expect("Timeout while waiting for server response")
+            .unwrap()
+    }
+}
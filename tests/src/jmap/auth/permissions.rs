@@ -628,7 +628,8 @@ pub async fn test(params: &JMAPTest) {
                 sender_authenticated: true,
                 recipients: vec![IngestRecipient {
                     address: "john@foobar.org".to_string(),
-                    is_spam: false
+                    is_spam: false,
+                    is_quarantined: false
                 }],
                 message_blob: message_blob.clone(),
                 message_size: TEST_MESSAGE.len() as u64,
@@ -670,7 +671,8 @@ pub async fn test(params: &JMAPTest) {
                 sender_authenticated: true,
                 recipients: vec![IngestRecipient {
                     address: "john@foobar.org".to_string(),
-                    is_spam: false
+                    is_spam: false,
+                    is_quarantined: false
                 }],
                 message_blob: message_blob.clone(),
                 message_size: TEST_MESSAGE.len() as u64,
@@ -700,7 +702,8 @@ pub async fn test(params: &JMAPTest) {
                 sender_authenticated: true,
                 recipients: vec![IngestRecipient {
                     address: "john@foobar.org".to_string(),
-                    is_spam: false
+                    is_spam: false,
+                    is_quarantined: false
                 }],
                 message_blob,
                 message_size: TEST_MESSAGE.len() as u64,
@@ -465,7 +465,7 @@ async fn assert_message_headers_contains(
     );
 }
 
-async fn message_headers(server: &Server, account_id: u32, document_id: u32) -> String {
+pub async fn message_headers(server: &Server, account_id: u32, document_id: u32) -> String {
     std::str::from_utf8(
         message_metadata(server, account_id, document_id)
             .await
@@ -0,0 +1,1081 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    AssertConfig,
+    imap::{AssertResult, ImapConnection, Type},
+    jmap::{JMAPTest, ManagementApi, wait_for_index},
+    store::cleanup::store_blob_expire_all,
+};
+use ahash::AHashMap;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use common::{Server, config::smtp::session::SessionConfig, core::BuildServer};
+use email::{
+    cache::{MessageCacheFetch, email::MessageCacheAccess, mailbox::MailboxCacheAccess},
+    mailbox::INBOX_ID,
+    message::{
+        delivery::{
+            IngestMessage, IngestRecipient, LocalDeliveryResult, LocalDeliveryStatus, MailDelivery,
+        },
+        quarantine::{EmailQuarantine, QuarantineReason},
+    },
+};
+use http_proto::request::fetch_body;
+use hyper::{HeaderMap, body, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use imap_proto::ResponseType;
+use ring::hmac;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use store::{parking_lot::Mutex, write::now};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, UnixListener},
+    sync::watch,
+};
+use types::{id::Id, keyword::Keyword};
+use utils::config::Config;
+
+pub struct MockHookServer {
+    tx: watch::Sender<bool>,
+    requests: Mutex<Vec<HookRequest>>,
+    responses: Mutex<AHashMap<String, (u16, String)>>,
+    delays: Mutex<AHashMap<String, Duration>>,
+}
+
+pub struct HookRequest {
+    pub hook: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    pub request: serde_json::Value,
+}
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running delivery hook tests...");
+
+    let hooks = spawn_mock_hook_server();
+    let john = params.account("jdoe@example.com");
+    let account_id = john.id().document_id();
+
+    // Sequential hooks run by order and later hooks override the mailboxes chosen before
+    let server = set_hooks(
+        params,
+        r#"
+[session.delivery_hook]
+mode = "sequential"
+
+[session.delivery_hook.zeta]
+url = "http://127.0.0.1:8822/zeta"
+enable = true
+order = 1
+
+[session.delivery_hook.alpha]
+url = "http://127.0.0.1:8822/alpha"
+enable = true
+order = 2
+
+[session.delivery_hook.omega]
+url = "http://127.0.0.1:8822/omega"
+enable = true
+order = 3
+"#,
+    );
+    hooks.reply(
+        "zeta",
+        r#"{"action":"accept","flags":["$classified"],"modifications":[
+            {"type":"fileInto","folder":"Work","create":true},
+            {"type":"addHeader","name":"X-Classified","value":"work"}]}"#,
+    );
+    hooks.reply(
+        "alpha",
+        r#"{"action":"accept","stop":true,"modifications":[
+            {"type":"fileInto","folder":"Archive","create":true}]}"#,
+    );
+    assert_eq!(
+        deliver(&server, account_id, "Sequential pipeline")
+            .await
+            .status,
+        vec![LocalDeliveryStatus::Success]
+    );
+    let requests = hooks.take_requests();
+    assert_eq!(
+        requests.iter().map(|r| r.hook.as_str()).collect::<Vec<_>>(),
+        ["zeta", "alpha"]
+    );
+    let work_id = mailbox_id(&server, account_id, "Work").await.unwrap();
+    let pipeline = &requests[1].request["pipeline"];
+    assert_eq!(pipeline["position"], 2);
+    assert_eq!(pipeline["previous_hooks"], serde_json::json!(["zeta"]));
+    assert_eq!(
+        pipeline["mailbox_ids"],
+        serde_json::json!([Id::from(work_id).to_string()])
+    );
+    assert_eq!(pipeline["flags"], serde_json::json!(["$classified"]));
+    assert!(
+        requests[1].request["message"]["contents"]
+            .as_str()
+            .unwrap()
+            .contains("X-Classified: work")
+    );
+    let archive_id = mailbox_id(&server, account_id, "Archive").await.unwrap();
+    assert_eq!(mailbox_count(&server, account_id, archive_id).await, 1);
+    assert_eq!(mailbox_count(&server, account_id, work_id).await, 0);
+
+    // Hooks only run for the sources they are configured for
+    let server = set_hooks(
+        params,
+        r#"
+[session.delivery_hook.default]
+url = "http://127.0.0.1:8822/default"
+enable = true
+
+[session.delivery_hook.import]
+url = "http://127.0.0.1:8822/import"
+enable = true
+sources = ["imap", "jmap"]
+"#,
+    );
+    hooks.reply(
+        "import",
+        r#"{"action":"accept","flags":["$imported"],"modifications":[
+            {"type":"addHeader","name":"X-Imported","value":"yes"}]}"#,
+    );
+    let mut imap = ImapConnection::connect(b"_x ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.send("LOGIN \"jdoe@example.com\" \"12345\"").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("APPEND INBOX {33}").await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged("Subject: IMAP append\r\n\r\nImported.")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let requests = hooks.take_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].hook, "import");
+    assert_eq!(requests[0].request["source"], "imap");
+    assert!(requests[0].request.get("envelope").is_none());
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("FETCH * (FLAGS BODY[HEADER.FIELDS (X-Imported)])")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("$imported")
+        .assert_contains("X-Imported: yes");
+
+    hooks.reply(
+        "import",
+        r#"{"action":"reject","reason":"Imports are not allowed."}"#,
+    );
+    imap.send("APPEND INBOX {33}").await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged("Subject: IMAP append\r\n\r\nRejected.")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::No).await;
+    hooks.take_requests();
+
+    let inbox_id = Id::from(INBOX_ID).to_string();
+    assert!(
+        john.client()
+            .email_import(
+                b"Subject: JMAP import\r\n\r\nRejected.".to_vec(),
+                [&inbox_id],
+                None::<Vec<&str>>,
+                None,
+            )
+            .await
+            .is_err()
+    );
+    hooks.reply("import", r#"{"action":"accept"}"#);
+    john.client()
+        .email_import(
+            b"Subject: JMAP import\r\n\r\nImported.".to_vec(),
+            [&inbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap();
+    let requests = hooks.take_requests();
+    assert_eq!(
+        requests
+            .iter()
+            .map(|r| (r.hook.as_str(), r.request["source"].as_str().unwrap()))
+            .collect::<Vec<_>>(),
+        [("import", "jmap"), ("import", "jmap")]
+    );
+    deliver(&server, account_id, "SMTP delivery").await;
+    let requests = hooks.take_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].hook, "default");
+    assert_eq!(requests[0].request["source"], "smtp");
+
+    // Requests include the context the hook opted in to
+    let server = set_hooks(
+        params,
+        r#"
+[session.delivery_hook.context]
+url = "http://127.0.0.1:8822/context"
+enable = true
+include = ["spam", "auth-results", "mailboxes", "sieve-script"]
+"#,
+    );
+    deliver_raw(
+        &server,
+        account_id,
+        concat!(
+            "X-Spam-Result: DMARC_POLICY_ALLOW (-0.50),\r\n\tBAYES_SPAM (5.10)\r\n",
+            "X-Spam-Score: spam, score=4.60\r\n",
+            "Authentication-Results: mx.example.org;\r\n",
+            "\tdkim=pass header.d=example.org header.s=sel header.b=YWJj;\r\n",
+            "\tspf=pass (mx.example.org: domain of bill@example.org designates ",
+            "192.0.2.1 as permitted sender) smtp.mailfrom=bill@example.org;\r\n",
+            "\tdmarc=pass header.from=example.org policy.dmarc=none\r\n",
+            "Authentication-Results: forged.example; dkim=fail\r\n",
+            "From: bill@example.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Context\r\n",
+            "\r\n",
+            "Hello."
+        ),
+    )
+    .await;
+    let requests = hooks.take_requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0].request;
+    assert_eq!(
+        request["spam"],
+        serde_json::json!({
+            "is_spam": true,
+            "score": 4.6,
+            "tags": [
+                {"name": "DMARC_POLICY_ALLOW", "score": -0.5},
+                {"name": "BAYES_SPAM", "score": 5.1}
+            ]
+        })
+    );
+    assert_eq!(
+        request["auth_results"],
+        serde_json::json!({
+            "dkim": [{
+                "result": "pass",
+                "properties": [["header.d", "example.org"], ["header.s", "sel"], ["header.b", "YWJj"]]
+            }],
+            "spf": {
+                "result": "pass",
+                "properties": [["smtp.mailfrom", "bill@example.org"]]
+            },
+            "dmarc": {
+                "result": "pass",
+                "properties": [["header.from", "example.org"], ["policy.dmarc", "none"]]
+            }
+        })
+    );
+    assert!(
+        request["mailboxes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|mailbox| mailbox["role"] == "inbox")
+    );
+    assert!(request.get("sieve_script").is_none());
+
+    // Redirected copies carry an X-Loop header and are not redirected twice
+    let server = set_hooks(
+        params,
+        r#"
+[session.delivery_hook.rewrite]
+url = "http://127.0.0.1:8822/rewrite"
+enable = true
+"#,
+    );
+    hooks.reply(
+        "rewrite",
+        r#"{"action":"accept","modifications":[
+            {"type":"removeHeader","name":"X-Spam"},
+            {"type":"prependBodyBanner","text":"[EXT]"},
+            {"type":"redirect","address":"jdoe@example.com","keep":false}]}"#,
+    );
+    let inbox_count = mailbox_count(&server, account_id, INBOX_ID).await;
+    let result = deliver_raw(
+        &server,
+        account_id,
+        "From: bill@example.com\r\nTo: jdoe@example.com\r\nX-Spam: yes\r\nSubject: Redirect\r\n\r\nHello.",
+    )
+    .await;
+    assert_eq!(result.status, vec![LocalDeliveryStatus::Success]);
+    assert_eq!(result.autogenerated.len(), 1);
+    let redirect = &result.autogenerated[0];
+    assert_eq!(redirect.recipients, ["jdoe@example.com"]);
+    let redirected = std::str::from_utf8(&redirect.message).unwrap();
+    assert!(redirected.starts_with("X-Loop: jdoe@example.com\r\n"));
+    assert!(!redirected.contains("X-Spam"));
+    assert!(redirected.contains("[EXT]\r\n\r\nHello."));
+    assert_eq!(
+        mailbox_count(&server, account_id, INBOX_ID).await,
+        inbox_count
+    );
+    let result = deliver_raw(&server, account_id, redirected).await;
+    assert_eq!(result.status, vec![LocalDeliveryStatus::Success]);
+    assert!(result.autogenerated.is_empty());
+    assert_eq!(
+        mailbox_count(&server, account_id, INBOX_ID).await,
+        inbox_count + 1
+    );
+    hooks.take_requests();
+
+    // Replaced MIME parts keep their other headers and expiring messages are deleted when due
+    hooks.reply(
+        "rewrite",
+        r#"{"action":"accept","modifications":[
+            {"type":"replaceMimePart","part_id":2,"contents":"Attachment removed."},
+            {"type":"setExpiry","seconds":2}]}"#,
+    );
+    let started = Instant::now();
+    let result = deliver_raw(
+        &server,
+        account_id,
+        concat!(
+            "From: bill@example.com\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Replace part\r\n",
+            "MIME-Version: 1.0\r\n",
+            "Content-Type: multipart/mixed; boundary=\"b1\"\r\n",
+            "\r\n",
+            "--b1\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Hello.\r\n",
+            "--b1\r\n",
+            "Content-Type: application/octet-stream\r\n",
+            "Content-Disposition: attachment; filename=\"payload.bin\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "SGVsbG8=\r\n",
+            "--b1--\r\n"
+        ),
+    )
+    .await;
+    assert_eq!(result.status, vec![LocalDeliveryStatus::Success]);
+    assert_eq!(
+        mailbox_count(&server, account_id, INBOX_ID).await,
+        inbox_count + 2
+    );
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("FETCH * (BODY[1] BODY[2.MIME] BODY[2])").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Hello.")
+        .assert_contains("Content-Disposition: attachment; filename=\"payload.bin\"")
+        .assert_contains("Content-Type: text/plain; charset=\"utf-8\"")
+        .assert_contains("Attachment removed.")
+        .assert_count("SGVsbG8=", 0);
+    wait_for_hook_tasks(&server).await;
+    assert!(
+        started.elapsed() >= Duration::from_secs(2),
+        "Message expired after {:?}",
+        started.elapsed()
+    );
+    assert_eq!(
+        mailbox_count(&server, account_id, INBOX_ID).await,
+        inbox_count + 1
+    );
+
+    // Modifications producing a message that cannot be parsed are rolled back
+    hooks.reply(
+        "rewrite",
+        r#"{"action":"accept","flags":["$rolledback"],"modifications":[
+            {"type":"removeHeader","name":"Subject"}]}"#,
+    );
+    let result = deliver_raw(&server, account_id, "Subject: Rollback\r\n").await;
+    assert_eq!(result.status, vec![LocalDeliveryStatus::Success]);
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("FETCH * (FLAGS BODY[HEADER.FIELDS (SUBJECT)])")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("$rolledback")
+        .assert_contains("Subject: Rollback");
+    hooks.take_requests();
+
+    // Circuit breaker opens after consecutive failures and survives a reload
+    let config = r#"
+[session.delivery_hook.flaky]
+url = "http://127.0.0.1:8822/flaky"
+enable = true
+timeout = "2s"
+options.tempfail-on-error = false
+circuit-breaker.threshold = 2
+circuit-breaker.cooldown = "2s"
+circuit-breaker.fallback = "accept"
+"#;
+    let server = set_hooks(params, config);
+    hooks.reply_with_status("flaky", 500, "Internal Server Error");
+    for num in 0..2 {
+        assert_eq!(
+            deliver(&server, account_id, &format!("Failure {num}"))
+                .await
+                .status,
+            vec![LocalDeliveryStatus::Success]
+        );
+    }
+    assert_eq!(hooks.take_requests().len(), 2);
+    let server = set_hooks(params, config);
+    assert_eq!(
+        deliver(&server, account_id, "Circuit open").await.status,
+        vec![LocalDeliveryStatus::Success]
+    );
+    assert_eq!(hooks.take_requests().len(), 0);
+
+    // A dropped probe blocks other probes until the hook timeout expires
+    tokio::time::sleep(Duration::from_millis(3100)).await;
+    hooks.reply("flaky", r#"{"action":"accept"}"#);
+    hooks.delay("flaky", Duration::from_millis(500));
+    assert!(
+        tokio::time::timeout(
+            Duration::from_millis(100),
+            deliver(&server, account_id, "Dropped probe")
+        )
+        .await
+        .is_err()
+    );
+    assert_eq!(
+        deliver(&server, account_id, "Probe in flight").await.status,
+        vec![LocalDeliveryStatus::Success]
+    );
+    assert_eq!(hooks.take_requests().len(), 1);
+    tokio::time::sleep(Duration::from_millis(3100)).await;
+    hooks.delay("flaky", Duration::ZERO);
+    for num in 0..2 {
+        assert_eq!(
+            deliver(&server, account_id, &format!("Closed {num}"))
+                .await
+                .status,
+            vec![LocalDeliveryStatus::Success]
+        );
+    }
+    assert_eq!(hooks.take_requests().len(), 2);
+
+    // Hooks reached over a Unix socket or PubSub do not need an URL
+    let server = set_hooks(
+        params,
+        &format!(
+            r#"
+[session.delivery_hook.sidecar]
+transport = "unix"
+socket = "{MOCK_HOOK_SOCKET}"
+enable = true
+"#
+        ),
+    );
+    hooks.reply(
+        "",
+        r#"{"action":"accept","modifications":[
+            {"type":"fileInto","folder":"Sidecar","create":true}]}"#,
+    );
+    assert_eq!(
+        deliver(&server, account_id, "Unix socket").await.status,
+        vec![LocalDeliveryStatus::Success]
+    );
+    let requests = hooks.take_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].headers["host"], "localhost");
+    assert_eq!(requests[0].request["source"], "smtp");
+    let sidecar_id = mailbox_id(&server, account_id, "Sidecar").await.unwrap();
+    assert_eq!(mailbox_count(&server, account_id, sidecar_id).await, 1);
+
+    // PubSub hooks fail temporarily when no PubSub store is configured
+    let server = set_hooks(
+        params,
+        r#"
+[session.delivery_hook.queue]
+transport = "pubsub"
+topic = "hooks.classifier"
+enable = true
+"#,
+    );
+    assert_eq!(server.core.smtp.session.delivery_hooks.len(), 1);
+    let status = deliver(&server, account_id, "PubSub").await.status;
+    assert!(
+        matches!(
+            status[..],
+            [LocalDeliveryStatus::PermanentFailure {
+                code: [4, 5, 1],
+                ..
+            }]
+        ),
+        "{status:?}"
+    );
+
+    // Request signing is refused over PubSub and unknown transports are rejected
+    let mut config = Config::new(
+        r#"
+[session.delivery_hook.signed-queue]
+transport = "pubsub"
+signature.key = "secret"
+
+[session.delivery_hook.rpc]
+url = "http://127.0.0.1:8822/rpc"
+transport = "grpc"
+"#,
+    )
+    .unwrap();
+    assert!(SessionConfig::parse(&mut config).delivery_hooks.is_empty());
+    for key in [
+        "session.delivery_hook.signed-queue.signature.key",
+        "session.delivery_hook.rpc.transport",
+    ] {
+        assert!(
+            config.errors.contains_key(key),
+            "Missing error for {key}: {:?}",
+            config.errors
+        );
+    }
+
+    // Signed requests carry an HMAC over the timestamp, nonce and body
+    let server = set_hooks(
+        params,
+        r#"
+[session.delivery_hook.signed]
+url = "http://127.0.0.1:8822/signed"
+enable = true
+signature.key = "secret"
+"#,
+    );
+    for num in 0..2 {
+        deliver(&server, account_id, &format!("Signed {num}")).await;
+    }
+    let requests = hooks.take_requests();
+    assert_eq!(requests.len(), 2);
+    let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+    let mut nonces = Vec::new();
+    for request in &requests {
+        let header = |name: &str| request.headers[name].to_str().unwrap().to_string();
+        let timestamp = header("X-Signature-Timestamp");
+        let nonce = header("X-Signature-Nonce");
+        assert!(timestamp.parse::<u64>().unwrap().abs_diff(now()) < 60);
+        assert_eq!(nonce.len(), 32);
+        let mut signed = format!("{timestamp}.{nonce}.").into_bytes();
+        signed.extend_from_slice(&request.body);
+        let tag = STANDARD.decode(header("X-Signature")).unwrap();
+        hmac::verify(&key, &signed, &tag).expect("Invalid signature");
+        assert!(!nonces.contains(&nonce));
+        nonces.push(nonce);
+    }
+
+    // Hooks whose client certificate can't be loaded are not enabled
+    let mut config = Config::new(
+        r#"
+[session.delivery_hook.invalid-cert]
+url = "https://127.0.0.1:8822/invalid-cert"
+tls.certificate = "not a certificate"
+tls.private-key = "not a key"
+
+[session.delivery_hook.missing-key]
+url = "https://127.0.0.1:8822/missing-key"
+tls.certificate = "not a certificate"
+
+[session.hook.invalid-cert]
+url = "https://127.0.0.1:8822/invalid-cert"
+tls.certificate = "not a certificate"
+tls.private-key = "not a key"
+"#,
+    )
+    .unwrap();
+    let session = SessionConfig::parse(&mut config);
+    assert!(session.delivery_hooks.is_empty());
+    assert!(session.hooks.is_empty());
+    for key in [
+        "session.delivery_hook.invalid-cert.tls.certificate",
+        "session.delivery_hook.missing-key.tls.private-key",
+        "session.hook.invalid-cert.tls.certificate",
+    ] {
+        assert!(
+            config.errors.contains_key(key),
+            "Missing error for {key}: {:?}",
+            config.errors
+        );
+    }
+
+    // Asynchronous hooks run concurrently after delivery
+    let server = set_hooks(
+        params,
+        r#"
+[session.delivery_hook.background]
+url = "http://127.0.0.1:8822/background"
+enable = true
+async = true
+options.tempfail-on-error = true
+"#,
+    );
+    hooks.reply(
+        "background",
+        r#"{"action":"accept","flags":["$checked"],"modifications":[
+            {"type":"fileInto","folder":"Checked","create":true}]}"#,
+    );
+    hooks.delay("background", Duration::from_millis(500));
+    let inbox_count = mailbox_count(&server, account_id, INBOX_ID).await;
+    let started = Instant::now();
+    for num in 0..3 {
+        assert_eq!(
+            deliver(&server, account_id, &format!("Background {num}"))
+                .await
+                .status,
+            vec![LocalDeliveryStatus::Success]
+        );
+    }
+    wait_for_hook_tasks(&server).await;
+    assert!(
+        started.elapsed() < Duration::from_millis(1400),
+        "Asynchronous hooks took {:?}",
+        started.elapsed()
+    );
+    assert_eq!(hooks.take_requests().len(), 3);
+    let checked_id = mailbox_id(&server, account_id, "Checked").await.unwrap();
+    assert_eq!(mailbox_count(&server, account_id, checked_id).await, 3);
+    assert_eq!(
+        mailbox_count(&server, account_id, INBOX_ID).await,
+        inbox_count + 3
+    );
+
+    // Rejections by asynchronous hooks are final and not retried
+    hooks.delay("background", Duration::ZERO);
+    hooks.reply("background", r#"{"action":"reject"}"#);
+    assert_eq!(
+        deliver(&server, account_id, "Background reject")
+            .await
+            .status,
+        vec![LocalDeliveryStatus::Success]
+    );
+    wait_for_hook_tasks(&server).await;
+    assert_eq!(hooks.take_requests().len(), 1);
+    assert_eq!(
+        mailbox_count(&server, account_id, INBOX_ID).await,
+        inbox_count + 4
+    );
+
+    // Messages quarantined by asynchronous hooks are moved out of the mailboxes
+    let mut core = server.core.as_ref().clone();
+    core.smtp.quarantine.enable = true;
+    params.server.inner.shared_core.store(Arc::new(core));
+    let server = params.server.inner.build_server();
+    hooks.reply("background", r#"{"action":"quarantine"}"#);
+    assert_eq!(
+        deliver(&server, account_id, "Background quarantine")
+            .await
+            .status,
+        vec![LocalDeliveryStatus::Success]
+    );
+    wait_for_hook_tasks(&server).await;
+    assert_eq!(hooks.take_requests().len(), 1);
+    assert_eq!(
+        mailbox_count(&server, account_id, INBOX_ID).await,
+        inbox_count + 4
+    );
+    let quarantined = server.quarantine_list(Some(account_id)).await.unwrap();
+    assert_eq!(quarantined.len(), 1);
+    assert_eq!(quarantined[0].reason, QuarantineReason::Hook);
+    assert_eq!(
+        quarantined[0].subject.as_deref(),
+        Some("Background quarantine")
+    );
+    assert!(
+        server
+            .quarantine_delete(account_id, quarantined[0].id)
+            .await
+            .unwrap()
+    );
+
+    // Quarantined messages are not redirected
+    set_hooks(
+        params,
+        r#"
+[session.delivery_hook.rewrite]
+url = "http://127.0.0.1:8822/rewrite"
+enable = true
+"#,
+    );
+    let mut core = params.server.inner.shared_core.load_full().as_ref().clone();
+    core.smtp.quarantine.enable = true;
+    params.server.inner.shared_core.store(Arc::new(core));
+    let server = params.server.inner.build_server();
+    hooks.reply(
+        "rewrite",
+        r#"{"action":"quarantine","modifications":[
+            {"type":"redirect","address":"jdoe@example.com","keep":false}]}"#,
+    );
+    let result = deliver(&server, account_id, "Quarantined redirect").await;
+    assert_eq!(result.status, vec![LocalDeliveryStatus::Success]);
+    assert!(result.autogenerated.is_empty());
+    assert_eq!(hooks.take_requests().len(), 1);
+    let quarantined = server.quarantine_list(Some(account_id)).await.unwrap();
+    assert_eq!(quarantined.len(), 1);
+    assert_eq!(
+        quarantined[0].subject.as_deref(),
+        Some("Quarantined redirect")
+    );
+    assert!(
+        server
+            .quarantine_delete(account_id, quarantined[0].id)
+            .await
+            .unwrap()
+    );
+
+    // The responses of the hooks are stored and exposed through the management API
+    let server = set_hooks(
+        params,
+        r#"
+[session.delivery_hook.audited]
+url = "http://127.0.0.1:8822/audited"
+enable = true
+
+[session.delivery_hook.audited-async]
+url = "http://127.0.0.1:8822/audited-async"
+enable = true
+async = true
+"#,
+    );
+    hooks.reply(
+        "audited",
+        r#"{"action":"accept","flags":["$audited"],"modifications":[
+            {"type":"fileInto","folder":"Audited","create":true}]}"#,
+    );
+    hooks.reply(
+        "audited-async",
+        r#"{"action":"accept","flags":["$indexed"]}"#,
+    );
+    deliver(&server, account_id, "Audited").await;
+    wait_for_hook_tasks(&server).await;
+    let audited_id = mailbox_id(&server, account_id, "Audited").await.unwrap();
+    let document_id = server
+        .get_cached_messages(account_id)
+        .await
+        .unwrap()
+        .in_mailbox(audited_id)
+        .next()
+        .unwrap()
+        .document_id;
+    let email_id = Id::from(document_id).to_string();
+    let audit = ManagementApi::new(8899, "admin", "secret")
+        .get::<serde_json::Value>(&format!("/api/delivery-hooks/jdoe@example.com/{email_id}"))
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(audit["id"], email_id.as_str());
+    assert!(
+        audit["mailboxIds"]
+            .as_array()
+            .unwrap()
+            .contains(&Id::from(audited_id).to_string().into())
+    );
+    let runs = audit["runs"].as_array().unwrap();
+    assert_eq!(runs[0]["source"], "smtp");
+    assert_eq!(runs[0]["isAsync"], false);
+    assert_eq!(runs[0]["hooks"].as_array().unwrap().len(), 1);
+    let hook = &runs[0]["hooks"][0];
+    assert_eq!(hook["hookId"], "audited");
+    assert_eq!(hook["action"], "accept");
+    assert_eq!(
+        hook["modifications"],
+        serde_json::json!(["fileInto Audited"])
+    );
+    assert_eq!(hook["flags"], serde_json::json!(["$audited"]));
+    assert_eq!(
+        hook["mailboxIds"],
+        serde_json::json!([Id::from(audited_id).to_string()])
+    );
+    // Asynchronous runs are appended to the trail recorded at delivery time
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[1]["isAsync"], true);
+    assert_eq!(runs[1]["hooks"][0]["hookId"], "audited-async");
+    assert_eq!(
+        runs[1]["hooks"][0]["flags"],
+        serde_json::json!(["$indexed"])
+    );
+    assert!(
+        hooks
+            .take_requests()
+            .iter()
+            .all(|request| request.request.get("dry_run").is_none())
+    );
+    assert_eq!(
+        ManagementApi::new(8899, "admin", "secret")
+            .get::<serde_json::Value>("/api/delivery-hooks/jdoe@example.com/zzzzzz")
+            .await
+            .unwrap()
+            .unwrap_error()
+            .0,
+        "notFound"
+    );
+
+    // Dry runs call the hooks without modifying the account, asynchronous hooks
+    // are skipped unless requested explicitly
+    hooks.reply(
+        "audited",
+        r#"{"action":"accept","flags":["$dry"],"modifications":[
+            {"type":"fileInto","folder":"Dry Run","create":true},
+            {"type":"addHeader","name":"X-Dry","value":"yes"}]}"#,
+    );
+    let total_messages = server
+        .get_cached_messages(account_id)
+        .await
+        .unwrap()
+        .emails
+        .items
+        .len();
+    let dry_run = ManagementApi::new(8899, "admin", "secret")
+        .post::<serde_json::Value>(
+            "/api/delivery-hooks/jdoe@example.com/test",
+            &serde_json::json!({
+                "emailId": email_id,
+                "source": "smtp",
+            }),
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(dry_run["action"], "accept");
+    assert_eq!(dry_run["flags"], serde_json::json!(["$dry"]));
+    assert_eq!(dry_run["mailboxIds"], serde_json::json!([]));
+    assert_eq!(dry_run["wouldCreate"], serde_json::json!(["Dry Run"]));
+    assert_eq!(
+        dry_run["addedHeaders"],
+        serde_json::json!([["X-Dry", "yes"]])
+    );
+    assert_eq!(dry_run["bodyChanged"], false);
+    assert_eq!(
+        dry_run["skippedHooks"],
+        serde_json::json!(["audited-async"])
+    );
+    assert_eq!(dry_run["hooks"].as_array().unwrap().len(), 1);
+    assert_eq!(dry_run["hooks"][0]["hookId"], "audited");
+    let requests = hooks.take_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].hook, "audited");
+    assert_eq!(requests[0].request["dry_run"], true);
+    let dry_run = ManagementApi::new(8899, "admin", "secret")
+        .post::<serde_json::Value>(
+            "/api/delivery-hooks/jdoe@example.com/test",
+            &serde_json::json!({
+                "emailId": email_id,
+                "source": "smtp",
+                "hooks": ["audited-async"],
+            }),
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(dry_run["flags"], serde_json::json!(["$indexed"]));
+    assert_eq!(dry_run["skippedHooks"], serde_json::json!([]));
+    let requests = hooks.take_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].hook, "audited-async");
+    assert_eq!(requests[0].request["dry_run"], true);
+    wait_for_hook_tasks(&server).await;
+    assert!(hooks.take_requests().is_empty());
+    assert!(mailbox_id(&server, account_id, "Dry Run").await.is_none());
+    let cache = server.get_cached_messages(account_id).await.unwrap();
+    assert_eq!(cache.emails.items.len(), total_messages);
+    assert_eq!(
+        cache
+            .with_keyword(&Keyword::from("$dry".to_string()))
+            .count(),
+        0
+    );
+
+    // Remove test data
+    hooks.tx.send(false).ok();
+    params
+        .server
+        .inner
+        .shared_core
+        .store(params.server.core.clone());
+    params.destroy_all_mailboxes(john).await;
+    store_blob_expire_all(params.server.store()).await;
+    params.assert_is_empty().await;
+}
+
+async fn wait_for_hook_tasks(server: &Server) {
+    tokio::time::timeout(Duration::from_secs(10), wait_for_index(server))
+        .await
+        .expect("Asynchronous hook tasks were not completed");
+}
+
+fn set_hooks(params: &JMAPTest, config: &str) -> Server {
+    let mut config = Config::new(config).unwrap();
+    let session = SessionConfig::parse(&mut config);
+    config.assert_no_errors();
+    let mut core = params.server.core.as_ref().clone();
+    core.smtp.session.delivery_hooks = session.delivery_hooks;
+    core.smtp.session.delivery_hooks_mode = session.delivery_hooks_mode;
+    params.server.inner.shared_core.store(Arc::new(core));
+    params.server.inner.build_server()
+}
+
+async fn deliver(server: &Server, account_id: u32, subject: &str) -> LocalDeliveryResult {
+    deliver_raw(
+        server,
+        account_id,
+        &format!(
+            "From: bill@example.com\r\nTo: jdoe@example.com\r\nSubject: {subject}\r\n\r\nHello."
+        ),
+    )
+    .await
+}
+
+async fn deliver_raw(server: &Server, account_id: u32, message: &str) -> LocalDeliveryResult {
+    let (message_blob, _) = server
+        .put_temporary_blob(account_id, message.as_bytes(), 60)
+        .await
+        .unwrap();
+    server
+        .deliver_message(IngestMessage {
+            sender_address: "bill@example.com".to_string(),
+            sender_authenticated: false,
+            recipients: vec![IngestRecipient {
+                address: "jdoe@example.com".to_string(),
+                is_spam: false,
+                is_quarantined: false,
+            }],
+            message_blob,
+            message_size: message.len() as u64,
+            session_id: 0,
+        })
+        .await
+}
+
+async fn mailbox_id(server: &Server, account_id: u32, path: &str) -> Option<u32> {
+    server
+        .get_cached_messages(account_id)
+        .await
+        .unwrap()
+        .mailbox_by_path(path)
+        .map(|mailbox| mailbox.document_id)
+}
+
+async fn mailbox_count(server: &Server, account_id: u32, mailbox_id: u32) -> usize {
+    server
+        .get_cached_messages(account_id)
+        .await
+        .unwrap()
+        .in_mailbox(mailbox_id)
+        .count()
+}
+
+impl MockHookServer {
+    pub fn reply(&self, hook: &str, response: &str) {
+        self.reply_with_status(hook, 200, response);
+    }
+
+    pub fn reply_with_status(&self, hook: &str, status: u16, response: &str) {
+        self.responses
+            .lock()
+            .insert(hook.to_string(), (status, response.to_string()));
+    }
+
+    pub fn delay(&self, hook: &str, delay: Duration) {
+        self.delays.lock().insert(hook.to_string(), delay);
+    }
+
+    pub fn take_requests(&self) -> Vec<HookRequest> {
+        std::mem::take(&mut *self.requests.lock())
+    }
+}
+
+pub const MOCK_HOOK_SOCKET: &str = "/tmp/stalwart-test-delivery-hook.sock";
+
+pub fn spawn_mock_hook_server() -> Arc<MockHookServer> {
+    let (tx, mut rx) = watch::channel(true);
+    let hooks = Arc::new(MockHookServer {
+        tx,
+        requests: Default::default(),
+        responses: Default::default(),
+        delays: Default::default(),
+    });
+    let hooks_ = hooks.clone();
+
+    tokio::spawn(async move {
+        let listener = TcpListener::bind("127.0.0.1:8822")
+            .await
+            .unwrap_or_else(|e| {
+                panic!("Failed to bind mock delivery hook server to 127.0.0.1:8822: {e}");
+            });
+        let _ = std::fs::remove_file(MOCK_HOOK_SOCKET);
+        let unix_listener = UnixListener::bind(MOCK_HOOK_SOCKET).unwrap_or_else(|e| {
+            panic!("Failed to bind mock delivery hook server to {MOCK_HOOK_SOCKET}: {e}");
+        });
+
+        loop {
+            tokio::select! {
+                stream = listener.accept() => {
+                    let (stream, _) = stream.unwrap();
+                    tokio::spawn(serve_hook_connection(hooks_.clone(), stream));
+                },
+                stream = unix_listener.accept() => {
+                    let (stream, _) = stream.unwrap();
+                    tokio::spawn(serve_hook_connection(hooks_.clone(), stream));
+                },
+                _ = rx.changed() => {
+                    break;
+                }
+            };
+        }
+
+        let _ = std::fs::remove_file(MOCK_HOOK_SOCKET);
+    });
+
+    hooks
+}
+
+async fn serve_hook_connection(
+    hooks: Arc<MockHookServer>,
+    stream: impl AsyncRead + AsyncWrite + Unpin + Send + 'static,
+) {
+    let _ = http1::Builder::new()
+        .keep_alive(false)
+        .serve_connection(
+            TokioIo::new(stream),
+            service_fn(|mut req: hyper::Request<body::Incoming>| {
+                let hooks = hooks.clone();
+
+                async move {
+                    let hook = req.uri().path().trim_start_matches('/').to_string();
+                    let headers = req.headers().clone();
+                    let body = fetch_body(&mut req, usize::MAX, 0).await.unwrap();
+                    let request = serde_json::from_slice(&body).unwrap();
+                    let (status, response) = hooks
+                        .responses
+                        .lock()
+                        .get(&hook)
+                        .cloned()
+                        .unwrap_or_else(|| (200, r#"{"action":"accept"}"#.to_string()));
+                    let delay = hooks.delays.lock().get(&hook).copied();
+                    hooks.requests.lock().push(HookRequest {
+                        hook,
+                        headers,
+                        body,
+                        request,
+                    });
+                    if let Some(delay) = delay {
+                        tokio::time::sleep(delay).await;
+                    }
+
+                    Ok::<_, hyper::Error>(
+                        hyper::Response::builder()
+                            .status(status)
+                            .header("Content-Type", "application/json")
+                            .body(http_body_util::Full::new(body::Bytes::from(response)))
+                            .unwrap(),
+                    )
+                }
+            }),
+        )
+        .await;
+}
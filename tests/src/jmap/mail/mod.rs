@@ -10,9 +10,11 @@ pub mod changes;
 pub mod copy;
 pub mod crypto;
 pub mod delivery;
+pub mod delivery_hooks;
 pub mod get;
 pub mod mailbox;
 pub mod parse;
+pub mod quarantine;
 pub mod query;
 pub mod query_changes;
 pub mod search_snippet;
@@ -0,0 +1,165 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    jmap::{JMAPTest, mail::delivery::message_headers},
+    store::cleanup::store_blob_expire_all,
+};
+use common::{Server, core::BuildServer};
+use email::{
+    cache::{MessageCacheFetch, email::MessageCacheAccess},
+    mailbox::INBOX_ID,
+    message::{
+        delivery::{IngestMessage, IngestRecipient, LocalDeliveryStatus, MailDelivery},
+        quarantine::{EmailQuarantine, QuarantineReason},
+    },
+};
+use std::sync::Arc;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running quarantine tests...");
+
+    let john = params.account("jdoe@example.com");
+    let account_id = john.id().document_id();
+    let message = concat!(
+        "From: bill@example.com\r\n",
+        "To: jdoe@example.com\r\n",
+        "Subject: Nothing to see here\r\n",
+        "X-Quarantine: true\r\n",
+        "\r\n",
+        "Please deliver me."
+    );
+
+    // Without a quarantine, quarantine headers are delivered as any other header
+    assert_eq!(
+        deliver(&params.server, account_id, message, false).await,
+        vec![LocalDeliveryStatus::Success]
+    );
+    let cache = params.server.get_cached_messages(account_id).await.unwrap();
+    let unfiltered_id = cache.in_mailbox(INBOX_ID).next().unwrap().document_id;
+    let headers = message_headers(&params.server, account_id, unfiltered_id).await;
+    assert!(
+        headers.contains("X-Quarantine"),
+        "Missing quarantine header in {headers:?}"
+    );
+
+    // Enable quarantine
+    let old_core = params.server.core.clone();
+    let mut new_core = old_core.as_ref().clone();
+    new_core.smtp.quarantine.enable = true;
+    params.server.inner.shared_core.store(Arc::new(new_core));
+    let server = params.server.inner.build_server();
+
+    // Quarantine headers added by the sender are removed and ignored
+    assert_eq!(
+        deliver(&server, account_id, message, false).await,
+        vec![LocalDeliveryStatus::Success]
+    );
+    assert_eq!(
+        server.quarantine_list(Some(account_id)).await.unwrap(),
+        vec![]
+    );
+    let cache = server.get_cached_messages(account_id).await.unwrap();
+    let document_id = cache
+        .in_mailbox(INBOX_ID)
+        .map(|item| item.document_id)
+        .find(|document_id| *document_id != unfiltered_id)
+        .unwrap();
+    assert_eq!(cache.in_mailbox(INBOX_ID).count(), 2);
+    let headers = message_headers(&server, account_id, document_id).await;
+    assert!(
+        !headers.contains("X-Quarantine"),
+        "Unexpected quarantine header in {headers:?}"
+    );
+
+    // Messages quarantined before queueing are held outside of the mailboxes
+    assert_eq!(
+        deliver(
+            &server,
+            account_id,
+            concat!(
+                "From: bill@example.com\r\n",
+                "To: jdoe@example.com\r\n",
+                "Subject: Suspicious attachment\r\n",
+                "\r\n",
+                "Please open the attachment."
+            ),
+            true,
+        )
+        .await,
+        vec![LocalDeliveryStatus::Success]
+    );
+    let quarantined = server.quarantine_list(Some(account_id)).await.unwrap();
+    assert_eq!(quarantined.len(), 1);
+    assert_eq!(quarantined[0].reason, QuarantineReason::Hook);
+    assert_eq!(
+        quarantined[0].subject.as_deref(),
+        Some("Suspicious attachment")
+    );
+    assert_eq!(
+        server
+            .get_cached_messages(account_id)
+            .await
+            .unwrap()
+            .in_mailbox(INBOX_ID)
+            .count(),
+        2
+    );
+
+    // Released messages are delivered to the Inbox
+    assert!(
+        server
+            .quarantine_release(account_id, quarantined[0].id)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        server.quarantine_list(Some(account_id)).await.unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        server
+            .get_cached_messages(account_id)
+            .await
+            .unwrap()
+            .in_mailbox(INBOX_ID)
+            .count(),
+        3
+    );
+
+    // Remove test data
+    params.destroy_all_mailboxes(john).await;
+    store_blob_expire_all(params.server.store()).await;
+    params.server.inner.shared_core.store(old_core);
+    params.assert_is_empty().await;
+}
+
+async fn deliver(
+    server: &Server,
+    account_id: u32,
+    message: &str,
+    is_quarantined: bool,
+) -> Vec<LocalDeliveryStatus> {
+    let (message_blob, _) = server
+        .put_temporary_blob(account_id, message.as_bytes(), 60)
+        .await
+        .unwrap();
+    server
+        .deliver_message(IngestMessage {
+            sender_address: "bill@example.com".to_string(),
+            sender_authenticated: false,
+            recipients: vec![IngestRecipient {
+                address: "jdoe@example.com".to_string(),
+                is_spam: false,
+                is_quarantined,
+            }],
+            message_blob,
+            message_size: message.len() as u64,
+            session_id: 0,
+        })
+        .await
+        .status
+}
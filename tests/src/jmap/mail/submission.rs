@@ -5,10 +5,16 @@
  */
 
 use crate::{
-    jmap::{JMAPTest, mail::set::assert_email_properties},
+    jmap::{JMAPTest, JmapUtils, mail::set::assert_email_properties},
     smtp::DnsCache,
 };
 use ahash::AHashMap;
+use common::{config::smtp::queue::QueueName, expr::if_block::IfBlock};
+use directory::backend::internal::{
+    PrincipalField, PrincipalUpdate, PrincipalValue,
+    manage::{ManageDirectory, UpdatePrincipal},
+};
+use email::submission::EmailSubmission;
 use jmap_client::{
     Error,
     core::set::{SetError, SetErrorType, SetObject},
@@ -16,17 +22,24 @@ use jmap_client::{
     mailbox::Role,
 };
 use mail_parser::DateTime;
+use serde_json::json;
+use smtp::queue::spool::SmtpSpool;
 use std::{
+    str::FromStr,
     sync::Arc,
     time::{Duration, Instant},
 };
-use store::parking_lot::Mutex;
+use store::{
+    ValueKey,
+    parking_lot::Mutex,
+    write::{AlignedBytes, Archive, now},
+};
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
     net::TcpListener,
     sync::mpsc,
 };
-use types::id::Id;
+use types::{collection::Collection, id::Id};
 
 #[derive(Default, Debug, PartialEq, Eq)]
 pub struct MockMessage {
@@ -302,10 +315,7 @@ pub async fn test(params: &mut JMAPTest) {
         .await
         .unwrap()
         .unwrap();
-    assert_eq!(
-        email_submission.undo_status().unwrap(),
-        &UndoStatus::Pending
-    );
+    assert_eq!(email_submission.undo_status().unwrap(), &UndoStatus::Final);
     assert_eq!(
         email_submission.delivery_status().unwrap(),
         &AHashMap::from_iter([
@@ -344,46 +354,52 @@ pub async fn test(params: &mut JMAPTest) {
         ])
     );
 
-    // Cancel submission
-    client
-        .email_submission_change_status(&email_submission_id, UndoStatus::Canceled)
-        .await
-        .unwrap();
+    // Submissions can no longer be canceled once sendAt has passed
+    assert!(matches!(
+        client
+            .email_submission_change_status(&email_submission_id, UndoStatus::Canceled)
+            .await,
+        Err(Error::Set(SetError {
+            type_: SetErrorType::CannotUnsend,
+            ..
+        }))
+    ));
     let email_submission = client
         .email_submission_get(&email_submission_id, None)
         .await
         .unwrap()
         .unwrap();
+    assert_eq!(email_submission.undo_status().unwrap(), &UndoStatus::Final);
     assert_eq!(
-        email_submission.undo_status().unwrap(),
-        &UndoStatus::Canceled
-    );
-    assert_eq!(
-        email_submission.delivery_status().unwrap(),
-        &AHashMap::from_iter([
-            (
-                "nonexistant@example.com".to_string(),
-                DeliveryStatus::new(
-                    "550 5.1.2 Mailbox does not exist.",
-                    Delivered::No,
-                    Displayed::Unknown
-                )
-            ),
-            (
-                "delay@other_domain.com".to_string(),
-                DeliveryStatus::new("250 2.1.5 Queued", Delivered::Unknown, Displayed::Unknown)
-            ),
-            (
-                "fail@test.com".to_string(),
-                DeliveryStatus::new("250 2.1.5 Queued", Delivered::Unknown, Displayed::Unknown)
-            ),
-            (
-                "tim@foobar.com".to_string(),
-                DeliveryStatus::new("250 2.1.5 Queued", Delivered::Unknown, Displayed::Unknown)
-            ),
-        ])
+        email_submission
+            .delivery_status_email("delay@other_domain.com")
+            .unwrap()
+            .delivered(),
+        &Delivered::Queued
     );
 
+    // Remove the deferred recipient from the queue
+    let queue_id = server
+        .store()
+        .get_value::<Archive<AlignedBytes>>(ValueKey::archive(
+            account.id().document_id(),
+            Collection::EmailSubmission,
+            Id::from_str(&email_submission_id).unwrap().document_id(),
+        ))
+        .await
+        .unwrap()
+        .unwrap()
+        .deserialize::<EmailSubmission>()
+        .unwrap()
+        .queue_id
+        .unwrap();
+    server
+        .read_message(queue_id, QueueName::default())
+        .await
+        .unwrap()
+        .remove(&server, None)
+        .await;
+
     // Confirm that the sendAt property is updated when using FUTURERELEASE
     let hold_until = DateTime::parse_rfc3339("2079-11-20T05:00:00Z")
         .unwrap()
@@ -417,6 +433,201 @@ pub async fn test(params: &mut JMAPTest) {
         ),])
     );
 
+    // Messages can be held until sendAt
+    let response = account
+        .jmap_create(
+            "EmailSubmission",
+            [json!({
+                "emailId": &email_id,
+                "identityId": &identity_id,
+                "sendAt": "2079-11-20T05:00:00Z",
+                "envelope": {
+                    "mailFrom": {"email": "jdoe@example.com"},
+                    "rcptTo": [{"email": "jane_smith@remote.org"}]
+                }
+            })],
+            Vec::<(&str, &str)>::new(),
+        )
+        .await;
+    let send_at_submission_id = response.created(0).id().to_string();
+    let email_submission = client
+        .email_submission_get(&send_at_submission_id, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(email_submission.send_at().unwrap(), hold_until);
+    assert_eq!(
+        email_submission.undo_status().unwrap(),
+        &UndoStatus::Pending
+    );
+    let pending_ids = client
+        .email_submission_query(
+            Filter::undo_status(UndoStatus::Pending).into(),
+            None::<Vec<_>>,
+        )
+        .await
+        .unwrap()
+        .take_ids();
+    assert!(pending_ids.contains(&email_submission_id));
+    assert!(pending_ids.contains(&send_at_submission_id));
+    expect_nothing(&mut smtp_rx).await;
+
+    // sendAt cannot be combined with FUTURERELEASE
+    let response = account
+        .jmap_create(
+            "EmailSubmission",
+            [json!({
+                "emailId": &email_id,
+                "identityId": &identity_id,
+                "sendAt": "2079-11-20T05:00:00Z",
+                "envelope": {
+                    "mailFrom": {
+                        "email": "jdoe@example.com",
+                        "parameters": {"HOLDFOR": "3600"}
+                    },
+                    "rcptTo": [{"email": "jane_smith@remote.org"}]
+                }
+            })],
+            Vec::<(&str, &str)>::new(),
+        )
+        .await;
+    assert_eq!(
+        response.not_created(0).get("type").and_then(|v| v.as_str()),
+        Some("invalidProperties")
+    );
+    assert_eq!(
+        response.not_created(0).get("properties"),
+        Some(&json!(["sendAt"]))
+    );
+
+    // Submissions are held for the undo-send delay
+    let old_core = params.server.core.clone();
+    let mut new_core = old_core.as_ref().clone();
+    new_core.smtp.session.extensions.undo_delay =
+        IfBlock::new::<()>("session.extensions.undo-delay", [], "1h");
+    params.server.inner.shared_core.store(Arc::new(new_core));
+    let before_submission = now();
+    let undo_submission_id = client
+        .email_submission_create_envelope(
+            &email_id,
+            &identity_id,
+            "jdoe@example.com",
+            ["jane_smith@remote.org"],
+        )
+        .await
+        .unwrap()
+        .take_id();
+    params.server.inner.shared_core.store(old_core);
+    let email_submission = client
+        .email_submission_get(&undo_submission_id, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        (before_submission + 3600..=now() + 3600)
+            .contains(&(email_submission.send_at().unwrap() as u64))
+    );
+    assert_eq!(
+        email_submission.undo_status().unwrap(),
+        &UndoStatus::Pending
+    );
+    expect_nothing(&mut smtp_rx).await;
+
+    // Held submissions can be canceled before they are released
+    client
+        .email_submission_change_status(&undo_submission_id, UndoStatus::Canceled)
+        .await
+        .unwrap();
+    let email_submission = client
+        .email_submission_get(&undo_submission_id, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        email_submission.undo_status().unwrap(),
+        &UndoStatus::Canceled
+    );
+    assert!(
+        !client
+            .email_submission_query(
+                Filter::undo_status(UndoStatus::Pending).into(),
+                None::<Vec<_>>
+            )
+            .await
+            .unwrap()
+            .take_ids()
+            .contains(&undo_submission_id)
+    );
+    expect_nothing(&mut smtp_rx).await;
+
+    // Account-level undo-send delays take precedence over the server default
+    let account_id = account.id().document_id();
+    for delay in [
+        PrincipalValue::Integer(120),
+        PrincipalValue::String("".into()),
+    ] {
+        let is_set = matches!(delay, PrincipalValue::Integer(_));
+        server
+            .invalidate_principal_caches(
+                server
+                    .core
+                    .storage
+                    .data
+                    .update_principal(UpdatePrincipal::by_id(account_id).with_updates(vec![
+                        PrincipalUpdate::set(PrincipalField::UndoSendDelay, delay),
+                    ]))
+                    .await
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(
+            account.jmap_session_object().await.pointer(&format!(
+                "/accounts/{}/accountCapabilities/urn:ietf:params:jmap:submission/undoSendDelay",
+                account.id_string()
+            )),
+            is_set.then_some(&json!(120))
+        );
+        if !is_set {
+            break;
+        }
+
+        let old_core = params.server.core.clone();
+        let mut new_core = old_core.as_ref().clone();
+        new_core.smtp.session.extensions.undo_delay =
+            IfBlock::new::<()>("session.extensions.undo-delay", [], "1h");
+        params.server.inner.shared_core.store(Arc::new(new_core));
+        let before_submission = now();
+        let account_submission_id = client
+            .email_submission_create_envelope(
+                &email_id,
+                &identity_id,
+                "jdoe@example.com",
+                ["jane_smith@remote.org"],
+            )
+            .await
+            .unwrap()
+            .take_id();
+        params.server.inner.shared_core.store(old_core);
+        let email_submission = client
+            .email_submission_get(&account_submission_id, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            (before_submission + 120..=now() + 120)
+                .contains(&(email_submission.send_at().unwrap() as u64))
+        );
+        assert_eq!(
+            email_submission.undo_status().unwrap(),
+            &UndoStatus::Pending
+        );
+        client
+            .email_submission_change_status(&account_submission_id, UndoStatus::Canceled)
+            .await
+            .unwrap();
+        expect_nothing(&mut smtp_rx).await;
+    }
+
     // Verify onSuccessUpdateEmail action
     let mut request = client.build();
     let set_request = request.set_email_submission();
@@ -90,6 +90,8 @@ async fn jmap_tests() {
     mail::thread_merge::test(&mut params).await;
     mail::mailbox::test(&mut params).await;
     mail::delivery::test(&mut params).await;
+    mail::delivery_hooks::test(&mut params).await;
+    mail::quarantine::test(&mut params).await;
     mail::acl::test(&mut params).await;
     mail::sieve_script::test(&mut params).await;
     mail::vacation_response::test(&mut params).await;
@@ -319,6 +319,7 @@ fn parse_servers() {
                 nodelay: true,
             }],
             max_connections: 8192,
+            unix_listeners: vec![],
             proxy_networks: vec![],
             span_id_gen: id_generator.clone(),
         },
@@ -344,6 +345,7 @@ fn parse_servers() {
                 },
             ],
             max_connections: 1024,
+            unix_listeners: vec![],
             proxy_networks: vec![],
             span_id_gen: id_generator.clone(),
         },
@@ -359,6 +361,7 @@ fn parse_servers() {
                 nodelay: true,
             }],
             max_connections: 8192,
+            unix_listeners: vec![],
             proxy_networks: vec![],
             span_id_gen: id_generator.clone(),
         },
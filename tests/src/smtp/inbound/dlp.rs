@@ -0,0 +1,363 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::{server::ServerProtocol, smtp::queue::QueueExpiry};
+use mail_auth::MX;
+use smtp::{
+    inbound::dlp::{HeldMessages, find_credit_cards, find_ibans},
+    queue::{HELD_FOR_APPROVAL, Status},
+};
+use smtp_proto::{MAIL_REQUIRETLS, RCPT_NOTIFY_NEVER};
+use store::write::now;
+
+use crate::smtp::{
+    DnsCache, TestSMTP,
+    inbound::{TestMessage, TestQueueEvent},
+    session::{TestSession, VerifyResponse},
+};
+
+const LOCAL: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@foobar.org"
+
+[session.auth]
+mechanisms = "[plain]"
+directory = "'local'"
+
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+
+[session.dlp.policy.pci]
+detect = "credit-card"
+action = "reject"
+reject.message = "550 5.7.1 Card numbers are not allowed."
+
+[session.dlp.policy.finance]
+detect = "iban"
+action = "hold"
+
+[session.dlp.policy.confidential]
+detect = "keywords"
+lookup.keywords = "dlp-keywords"
+min-matches = 2
+action = "bcc"
+bcc = "Compliance@foobar.org"
+
+[session.dlp.policy.executables]
+detect = "attachments"
+lookup.attachments = "dlp-attachments"
+action = "require-tls"
+
+[session.dlp.hold]
+expire = "1d"
+
+[lookup]
+"dlp-keywords" = {"confidential", "merger"}
+"dlp-attachments" = {"exe", "application/x-msdownload"}
+
+[spam-filter]
+enable = false
+"#;
+
+const REMOTE: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+
+[spam-filter]
+enable = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn dlp_hold() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_dlp_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+
+    // Add mock DNS entries
+    let mut local = TestSMTP::new("smtp_dlp_local", LOCAL).await;
+    let core = local.build_smtp();
+    core.mx_add(
+        "remote.org",
+        vec![MX {
+            exchanges: vec!["mx.remote.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.remote.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "235 2.7.0")
+        .await;
+
+    // Messages matching a reject policy are refused
+    session
+        .send_message(
+            "john@foobar.org",
+            &["bill@remote.org"],
+            "From: john@foobar.org\r\nTo: bill@remote.org\r\nSubject: Card\r\n\r\nCard: 4111 1111 1111 1111, exp 12/29",
+            "550 5.7.1 Card numbers are not allowed.",
+        )
+        .await;
+    local.queue_receiver.assert_no_events();
+
+    // Messages matching a hold policy are queued but not delivered
+    session
+        .send_message(
+            "john@foobar.org",
+            &["bill@remote.org"],
+            "From: john@foobar.org\r\nTo: bill@remote.org\r\nSubject: Invoice\r\n\r\nPlease pay to DE89 3704 0044 0532 0130 00 today",
+            "250",
+        )
+        .await;
+    let mut message = local.queue_receiver.expect_message().await;
+    let queue_id = message.queue_id;
+    let created = message.message.created;
+    let release_due = created + 86400;
+    assert_ne!(message.message.flags & HELD_FOR_APPROVAL, 0);
+    assert_eq!(
+        local.queue_receiver.message_due(queue_id).await,
+        release_due
+    );
+
+    // Held messages are not delivered when retried before being approved
+    let due = local.queue_receiver.message_due(queue_id).await;
+    for rcpt in message.message.recipients.iter_mut() {
+        rcpt.retry.due = now();
+        rcpt.expires = QueueExpiry::Attempts(10);
+    }
+    message.save_changes(&local.server, due.into()).await;
+    local
+        .queue_receiver
+        .delivery_attempt(queue_id)
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_refresh();
+    remote.queue_receiver.assert_no_events();
+    assert_eq!(
+        local.queue_receiver.message_due(queue_id).await,
+        release_due
+    );
+    let rcpt = &local
+        .queue_receiver
+        .last_queued_message()
+        .await
+        .message
+        .recipients[0];
+    assert!(matches!(rcpt.status, Status::Scheduled));
+    assert_eq!(rcpt.expiration_time(created), Some(release_due));
+
+    // Released messages are delivered
+    assert_eq!(local.server.release_held(queue_id).await, Some(true));
+    assert_eq!(local.server.release_held(queue_id).await, Some(false));
+    local
+        .queue_receiver
+        .delivery_attempt(queue_id)
+        .await
+        .try_deliver(core.clone());
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("DE89 3704 0044 0532 0130 00");
+    local.queue_receiver.read_event().await.assert_done();
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn dlp_actions() {
+    // Enable logging
+    crate::enable_logging();
+
+    let mut local = TestSMTP::new("smtp_dlp_actions", LOCAL).await;
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "235 2.7.0")
+        .await;
+
+    // Keyword policies trigger once the minimum number of matches is reached
+    session
+        .send_message(
+            "john@foobar.org",
+            &["bill@remote.org"],
+            "From: john@foobar.org\r\nTo: bill@remote.org\r\nSubject: Merger\r\n\r\nSee you at the meeting",
+            "250",
+        )
+        .await;
+    let message = local.queue_receiver.expect_message().await;
+    assert_eq!(
+        message
+            .message
+            .recipients
+            .iter()
+            .map(|rcpt| rcpt.address())
+            .collect::<Vec<_>>(),
+        vec!["bill@remote.org"]
+    );
+    assert_eq!(message.message.flags & MAIL_REQUIRETLS, 0);
+
+    // Matching messages are copied to the compliance address, once
+    for rcpts in [
+        &["bill@remote.org"][..],
+        &["bill@remote.org", "compliance@foobar.org"][..],
+    ] {
+        session
+            .send_message(
+                "john@foobar.org",
+                rcpts,
+                "From: john@foobar.org\r\nTo: bill@remote.org\r\nSubject: Merger\r\n\r\nThis is CONFIDENTIAL",
+                "250",
+            )
+            .await;
+        let message = local.queue_receiver.expect_message().await;
+        assert_eq!(
+            message
+                .message
+                .recipients
+                .iter()
+                .map(|rcpt| rcpt.address())
+                .collect::<Vec<_>>(),
+            vec!["bill@remote.org", "compliance@foobar.org"]
+        );
+        if rcpts.len() == 1 {
+            assert_eq!(message.message.recipients[1].flags, RCPT_NOTIFY_NEVER);
+        }
+    }
+
+    // Attachment policies match file extensions and content types
+    for (name, content_type) in [
+        ("setup.EXE", "application/octet-stream"),
+        ("update.bin", "application/x-msdownload"),
+        ("report.pdf", "application/pdf"),
+    ] {
+        session
+            .send_message(
+                "john@foobar.org",
+                &["bill@remote.org"],
+                &format!(
+                    concat!(
+                        "From: john@foobar.org\r\nTo: bill@remote.org\r\n",
+                        "Subject: Files\r\nMIME-Version: 1.0\r\n",
+                        "Content-Type: multipart/mixed; boundary=\"b\"\r\n\r\n",
+                        "--b\r\nContent-Type: text/plain\r\n\r\nAttached\r\n",
+                        "--b\r\nContent-Type: {}\r\n",
+                        "Content-Disposition: attachment; filename=\"{}\"\r\n",
+                        "Content-Transfer-Encoding: base64\r\n\r\nAAAA\r\n--b--\r\n"
+                    ),
+                    content_type, name
+                ),
+                "250",
+            )
+            .await;
+        let message = local.queue_receiver.expect_message().await;
+        assert_eq!(
+            message.message.flags & MAIL_REQUIRETLS != 0,
+            name != "report.pdf",
+            "{name}"
+        );
+        assert_eq!(message.message.recipients.len(), 1);
+    }
+    local.queue_receiver.assert_no_events();
+}
+
+#[test]
+fn dlp_detect_credit_cards() {
+    for (text, expected) in [
+        // Valid numbers, grouped or compact
+        ("Card: 4111 1111 1111 1111, exp 12/29", 1),
+        ("4111-1111-1111-1111 and 5500 0000 0000 0004", 2),
+        ("4111111111111111 4111-1111-1111-1111", 1),
+        ("Amex 3782 822463 10005.", 1),
+        ("Visa 4222222222222 (13 digits)", 1),
+        // Invalid checksums
+        ("Invoice 4111 1111 1111 1112", 0),
+        ("5500-0000-0000-0005", 0),
+        // Only single spaces or dashes separate groups
+        ("4111  1111 1111 1111", 0),
+        ("4111.1111.1111.1111", 0),
+        ("4111 -1111-1111-1111", 0),
+        // Too many or too few digits
+        ("Tracking 41111111111111111111", 0),
+        ("Ref 4111111111111111111111111", 0),
+        ("Order 12345678", 0),
+        // Repeated digits
+        ("Call 0000 0000 0000 0000", 0),
+        ("0000000000000", 0),
+        // Trailing groups are not part of the number
+        ("4111 1111 1111 1111 2029", 1),
+    ] {
+        assert_eq!(find_credit_cards(text), expected, "{text}");
+    }
+}
+
+#[test]
+fn dlp_detect_ibans() {
+    for (text, expected) in [
+        // Valid IBANs, grouped or compact
+        ("Please pay to DE89 3704 0044 0532 0130 00 today", 1),
+        ("IBAN: GB82WEST12345698765432", 1),
+        (
+            "GB82 WEST 1234 5698 7654 32 AND FR14 2004 1010 0505 0001 3M02 606",
+            2,
+        ),
+        ("DE89370400440532013000 DE89 3704 0044 0532 0130 00", 1),
+        // Invalid checksums
+        ("DE89 3704 0044 0532 0130 01", 0),
+        ("GB83WEST12345698765432", 0),
+        // Only single spaces separate groups
+        ("DE89-3704-0044-0532-0130-00", 0),
+        ("DE89  3704 0044 0532 0130 00", 0),
+        ("de89 3704 0044 0532 0130 00", 0),
+        // IBANs must not be part of a longer word
+        ("XDE89370400440532013000", 0),
+        ("SKU AB12 3456", 0),
+        // IBANs followed by extra groups
+        ("DE89 3704 0044 0532 0130 00 1234 ref", 1),
+        ("DE89 3704 0044 0532 0130 00 GB82 WEST 1234 5698 7654 32", 2),
+        ("GB82WEST12345698765432 0000 0000 0000 0000 0000", 1),
+    ] {
+        assert_eq!(find_ibans(text), expected, "{text}");
+    }
+}
@@ -0,0 +1,426 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    smtp::{
+        DnsCache, QueueReceiver, TestSMTP,
+        inbound::{TestMessage, TestQueueEvent},
+        session::{DummyIo, TestSession},
+    },
+};
+use common::Server;
+use mail_auth::{common::parse::TxtRecordParser, dmarc::Dmarc, spf::Spf};
+use mail_parser::MessageParser;
+use smtp::{
+    core::Session,
+    lists::{MailingList, moderation::ListModeration},
+};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "internal"
+
+[directory."internal"]
+type = "internal"
+store = "{STORE}"
+
+[session.rcpt]
+directory = "'internal'"
+
+[session.auth]
+mechanisms = "[plain]"
+directory = "'internal'"
+
+[session.data.limits]
+messages = 100
+
+[auth.iprev]
+verify = "disable"
+
+[auth.spf.verify]
+ehlo = "disable"
+mail-from = "relaxed"
+
+[auth.dkim]
+verify = "relaxed"
+
+[auth.arc]
+verify = "disable"
+
+[auth.dmarc]
+verify = "relaxed"
+
+[mailing-list]
+enable = true
+url = "https://mx.example.org/list"
+
+[mailing-list.default]
+posting = "members"
+non-member = "moderate"
+moderators = ["owner@example.org"]
+subject-prefix = "[devel]"
+
+[session.dlp.policy.pci]
+detect = "credit-card"
+action = "reject"
+
+[session.dlp.policy.finance]
+detect = "iban"
+action = "hold"
+"#;
+
+#[tokio::test]
+async fn mailing_lists() {
+    // Enable logging
+    crate::enable_logging();
+
+    let mut local = TestSMTP::new("smtp_mailing_lists", CONFIG).await;
+    let server = local.server.clone();
+    let store = server.store().clone();
+    store
+        .create_test_user("jane", "secret", "Jane Doe", &["jane@example.org"])
+        .await;
+    store
+        .create_test_user("john", "secret", "John Doe", &["john@example.org"])
+        .await;
+    let list_id = store
+        .create_test_list("devel@example.org", "Developers", &["jane", "john"])
+        .await;
+
+    // SPF and DMARC records of the external subscriber
+    server.txt_add(
+        "example.net",
+        Spf::parse(b"v=spf1 ip4:10.0.0.1 -all").unwrap(),
+        Instant::now() + Duration::from_secs(100),
+    );
+    server.txt_add(
+        "_dmarc.example.net",
+        Dmarc::parse(b"v=DMARC1; p=reject").unwrap(),
+        Instant::now() + Duration::from_secs(100),
+    );
+
+    // Subscription requests are confirmed by replying to the confirmation message
+    let mut session = new_session(&local, "10.0.0.1").await;
+    session
+        .send_message(
+            "bill@example.net",
+            &["devel+subscribe@example.org"],
+            "From: bill@example.net\r\nTo: devel+subscribe@example.org\r\nSubject: subscribe\r\n\r\nsubscribe",
+            "250",
+        )
+        .await;
+    let message = local.queue_receiver.consume_message(&server).await;
+    assert_eq!(
+        message.message.return_path.as_ref(),
+        "devel+bounces@example.org"
+    );
+    assert_eq!(message.message.recipients[0].address(), "bill@example.net");
+    let confirmation = message.read_message(&local.queue_receiver).await;
+    let token = confirmation_token(&confirmation);
+    assert!(confirmation.contains("https://mx.example.org/list/confirm?t="));
+    assert!(
+        !list_members(&server, list_id)
+            .await
+            .contains(&"bill@example.net".to_string())
+    );
+
+    session
+        .send_message(
+            "bill@example.net",
+            &["devel+confirm@example.org"],
+            &format!(
+                "From: bill@example.net\r\nTo: devel+confirm@example.org\r\nSubject: Re: Confirm: {token}\r\n\r\nconfirm"
+            ),
+            "250",
+        )
+        .await;
+    assert_eq!(
+        list_members(&server, list_id).await,
+        vec!["bill@example.net", "jane@example.org", "john@example.org"]
+    );
+    local.queue_receiver.assert_no_events();
+
+    // Posts from authenticated members are sent to each member with the list headers
+    let mut session = new_session(&local, "10.0.0.3").await;
+    session.stream.tls = true;
+    session
+        .cmd("AUTH PLAIN AGphbmUAc2VjcmV0", "235 2.7.0")
+        .await;
+    session
+        .send_message(
+            "jane@example.org",
+            &["devel@example.org"],
+            concat!(
+                "From: Jane Doe <jane@example.org>\r\n",
+                "To: devel@example.org\r\n",
+                "Subject: Hello world\r\n",
+                "List-Id: <other.example.net>\r\n",
+                "Precedence: bulk\r\n",
+                "\r\n",
+                "Test message\r\n"
+            ),
+            "250",
+        )
+        .await;
+    for message in expect_post(&mut local.queue_receiver, &server).await {
+        assert_contains(
+            &message,
+            &[
+                "From: Jane Doe <jane@example.org>\r\n",
+                "Subject: [devel] Hello world\r\n",
+                "List-Id: \"Developers\" <devel.example.org>\r\n",
+                "List-Post: <mailto:devel@example.org>\r\n",
+                "List-Subscribe: <mailto:devel+subscribe@example.org>\r\n",
+                "List-Owner: <mailto:devel+owner@example.org>\r\n",
+                "List-Unsubscribe: <https://mx.example.org/list/unsubscribe?t=",
+                ">,\r\n\t<mailto:devel+unsubscribe@example.org>\r\n",
+                "List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n",
+                "Precedence: list\r\n",
+                "X-Loop: devel@example.org\r\n",
+            ],
+        );
+        assert!(!message.contains("other.example.net"), "{message}");
+        assert!(!message.contains("Precedence: bulk"), "{message}");
+        assert!(!message.contains("Reply-To:"), "{message}");
+    }
+
+    // Posts that were already distributed by the list are discarded
+    session
+        .send_message(
+            "jane@example.org",
+            &["devel@example.org"],
+            "From: jane@example.org\r\nX-Loop: devel@example.org\r\nSubject: Loop\r\n\r\nLoop",
+            "250",
+        )
+        .await;
+    local.queue_receiver.assert_no_events();
+
+    // Content policies are applied before posts are distributed
+    session
+        .send_message(
+            "jane@example.org",
+            &["devel@example.org"],
+            "From: jane@example.org\r\nSubject: Card\r\n\r\nCard: 4111 1111 1111 1111",
+            "550 5.7.1",
+        )
+        .await;
+    local.queue_receiver.assert_no_events();
+
+    // Posts held by a content policy wait for a moderator
+    session
+        .send_message(
+            "jane@example.org",
+            &["devel@example.org"],
+            "From: jane@example.org\r\nSubject: Invoice\r\n\r\nPay to DE89 3704 0044 0532 0130 00",
+            "250",
+        )
+        .await;
+    let notification = local.queue_receiver.consume_message(&server).await;
+    assert_eq!(
+        notification.message.recipients[0].address(),
+        "owner@example.org"
+    );
+    assert_contains(
+        &notification.read_message(&local.queue_receiver).await,
+        &[
+            "Subject: Post to devel@example.org awaiting moderation",
+            "Subject: Invoice",
+        ],
+    );
+    local.queue_receiver.assert_no_events();
+    let held = server.list_held_posts(list_id).await.unwrap();
+    assert_eq!(held.len(), 1);
+    let list = server.list_by_id(list_id).await.unwrap().unwrap();
+    assert!(
+        server
+            .list_moderate(&list, held[0].id, false)
+            .await
+            .unwrap()
+    );
+
+    // Unauthenticated members need a DMARC aligned From header,
+    // the From header is rewritten to the list address
+    let mut session = new_session(&local, "10.0.0.1").await;
+    session
+        .send_message(
+            "bill@example.net",
+            &["devel@example.org"],
+            "From: Bill <bill@example.net>\r\nTo: devel@example.org\r\nSubject: Aligned\r\n\r\nAligned",
+            "250",
+        )
+        .await;
+    for message in expect_post(&mut local.queue_receiver, &server).await {
+        assert_contains(
+            &message,
+            &[
+                "From: \"Bill via Developers\" <devel@example.org>\r\n",
+                "X-Original-From: Bill <bill@example.net>\r\n",
+                "Reply-To: \"Bill\" <bill@example.net>\r\n",
+                "Subject: [devel] Aligned\r\n",
+            ],
+        );
+    }
+
+    // Posts using a member's address without authentication or DMARC
+    // alignment are held for moderation
+    for (remote_ip, sender, subject) in [
+        ("10.0.0.2", "bill@example.net", "Spoofed"),
+        ("10.0.0.2", "jane@example.org", "Unauthenticated"),
+    ] {
+        let mut session = new_session(&local, remote_ip).await;
+        session
+            .send_message(
+                sender,
+                &["devel@example.org"],
+                &format!("From: {sender}\r\nTo: devel@example.org\r\nSubject: {subject}\r\n\r\nHi"),
+                "250",
+            )
+            .await;
+        let notification = local.queue_receiver.consume_message(&server).await;
+        assert_eq!(
+            notification.message.recipients[0].address(),
+            "owner@example.org"
+        );
+        assert_contains(
+            &notification.read_message(&local.queue_receiver).await,
+            &[
+                "Subject: Post to devel@example.org awaiting moderation",
+                &format!("Subject: {subject}"),
+            ],
+        );
+    }
+    local.queue_receiver.assert_no_events();
+    let held = server.list_held_posts(list_id).await.unwrap();
+    assert_eq!(
+        held.iter()
+            .map(|post| post.subject.as_deref().unwrap_or_default())
+            .collect::<Vec<_>>(),
+        vec!["Spoofed", "Unauthenticated"]
+    );
+
+    // Approved posts are distributed, rejected posts are removed
+    let list = server.list_by_id(list_id).await.unwrap().unwrap();
+    assert!(server.list_moderate(&list, held[1].id, true).await.unwrap());
+    for message in expect_post(&mut local.queue_receiver, &server).await {
+        assert_contains(
+            &message,
+            &[
+                "Subject: [devel] Unauthenticated\r\n",
+                "From: jane@example.org\r\n",
+            ],
+        );
+    }
+    assert!(
+        server
+            .list_moderate(&list, held[0].id, false)
+            .await
+            .unwrap()
+    );
+    local.queue_receiver.assert_no_events();
+    assert_eq!(server.list_held_posts(list_id).await.unwrap(), vec![]);
+    assert!(!server.list_moderate(&list, held[0].id, true).await.unwrap());
+
+    // Unsubscribe requests are confirmed as well
+    let mut session = new_session(&local, "10.0.0.1").await;
+    session
+        .send_message(
+            "bill@example.net",
+            &["devel+unsubscribe@example.org"],
+            "From: bill@example.net\r\nTo: devel+unsubscribe@example.org\r\nSubject: unsubscribe\r\n\r\nunsubscribe",
+            "250",
+        )
+        .await;
+    let confirmation = local
+        .queue_receiver
+        .consume_message(&server)
+        .await
+        .read_message(&local.queue_receiver)
+        .await;
+    assert!(confirmation.contains("https://mx.example.org/list/unsubscribe?t="));
+    let token = confirmation_token(&confirmation);
+    session
+        .send_message(
+            "bill@example.net",
+            &["devel+confirm@example.org"],
+            &format!(
+                "From: bill@example.net\r\nTo: devel+confirm@example.org\r\nSubject: Re: Confirm: {token}\r\n\r\nconfirm"
+            ),
+            "250",
+        )
+        .await;
+    assert_eq!(
+        list_members(&server, list_id).await,
+        vec!["jane@example.org", "john@example.org"]
+    );
+}
+
+async fn new_session(local: &TestSMTP, remote_ip: &str) -> Session<DummyIo> {
+    let mut session = local.new_session();
+    session.data.remote_ip_str = remote_ip.into();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.example.net").await;
+    session
+}
+
+fn assert_contains(message: &str, expected: &[&str]) {
+    for expected in expected {
+        assert!(
+            message.contains(expected),
+            "{expected:?} not found in {message}"
+        );
+    }
+}
+
+fn confirmation_token(message: &str) -> String {
+    MessageParser::new()
+        .parse(message.as_bytes())
+        .and_then(|message| {
+            message
+                .subject()
+                .and_then(|subject| subject.strip_prefix("Confirm: "))
+                .map(|token| token.to_string())
+        })
+        .expect("Missing confirmation token")
+}
+
+async fn list_members(server: &Server, list_id: u32) -> Vec<String> {
+    server.list_by_id(list_id).await.unwrap().unwrap().members
+}
+
+// Each member receives its own copy of the post
+async fn expect_post(queue_receiver: &mut QueueReceiver, server: &Server) -> Vec<String> {
+    for _ in 0..3 {
+        queue_receiver.read_event().await.assert_refresh();
+    }
+    queue_receiver.assert_no_events();
+
+    let mut queued = queue_receiver.read_queued_messages().await;
+    queued.sort_by(|a, b| {
+        a.message.recipients[0]
+            .address()
+            .cmp(b.message.recipients[0].address())
+    });
+    let mut messages = Vec::new();
+    for (member, message) in ["bill@example.net", "jane@example.org", "john@example.org"]
+        .into_iter()
+        .zip(queued.iter())
+    {
+        assert_eq!(
+            message.message.return_path.as_ref(),
+            "devel+bounces@example.org"
+        );
+        assert_eq!(message.message.recipients.len(), 1);
+        assert_eq!(message.message.recipients[0].address(), member);
+        messages.push(message.read_message(queue_receiver).await);
+    }
+    assert_eq!(queued.len(), 3);
+    queue_receiver.clear_queue(server).await;
+    messages
+}
@@ -27,15 +27,20 @@ pub mod asn;
 pub mod auth;
 pub mod basic;
 pub mod data;
+pub mod dlp;
 pub mod dmarc;
 pub mod ehlo;
 pub mod limits;
+pub mod lists;
 pub mod mail;
 pub mod milter;
+pub mod quarantine;
 pub mod rcpt;
 pub mod rewrite;
 pub mod scripts;
+pub mod sending_limits;
 pub mod sign;
+pub mod suppression;
 pub mod throttle;
 pub mod vrfy;
 
@@ -0,0 +1,118 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::smtp::{TestSMTP, inbound::TestMessage, session::TestSession};
+use smtp::queue::RCPT_QUARANTINE;
+
+const CONFIG: &str = r#"
+[spam-filter]
+enable = false
+
+[session.rcpt]
+relay = true
+
+[session.data]
+script = "'quarantine'"
+
+[session.data.limits]
+messages = 100
+
+[sieve.trusted.scripts."quarantine"]
+contents = '''
+require ["variables"];
+
+if header :contains "Subject" "quarantine" {
+    eval "add_header('X-Quarantine', 'true')";
+}
+'''
+"#;
+
+#[tokio::test]
+async fn quarantine_verdict() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Without a quarantine, quarantine headers are delivered as any other header
+    let mut local = TestSMTP::new("smtp_quarantine_disabled", CONFIG).await;
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+    session
+        .send_message(
+            "bill@doe.org",
+            &["jane@foobar.org"],
+            "From: bill@doe.org\r\nSubject: Please quarantine me\r\n\r\nHello",
+            "250",
+        )
+        .await;
+    let message = local.queue_receiver.expect_message().await;
+    assert!(
+        message
+            .message
+            .recipients
+            .iter()
+            .all(|rcpt| rcpt.flags & RCPT_QUARANTINE == 0)
+    );
+    assert!(
+        message
+            .read_message(&local.queue_receiver)
+            .await
+            .contains("X-Quarantine: true")
+    );
+
+    let mut local = TestSMTP::new(
+        "smtp_quarantine_verdict",
+        format!("{CONFIG}\n[quarantine]\nenable = true\n"),
+    )
+    .await;
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+
+    // Quarantine headers added by the sender are not a verdict
+    session
+        .send_message(
+            "bill@doe.org",
+            &["jane@foobar.org"],
+            "From: bill@doe.org\r\nSubject: Hello\r\nX-Quarantine: true\r\n\r\nHello",
+            "250",
+        )
+        .await;
+    let message = local.queue_receiver.expect_message().await;
+    assert!(
+        message
+            .message
+            .recipients
+            .iter()
+            .all(|rcpt| rcpt.flags & RCPT_QUARANTINE == 0)
+    );
+
+    // Sieve verdicts are kept in the envelope
+    session
+        .send_message(
+            "bill@doe.org",
+            &["jane@foobar.org", "john@foobar.org"],
+            "From: bill@doe.org\r\nSubject: Please quarantine me\r\n\r\nHello",
+            "250",
+        )
+        .await;
+    let message = local.queue_receiver.expect_message().await;
+    assert!(
+        message
+            .message
+            .recipients
+            .iter()
+            .all(|rcpt| rcpt.flags & RCPT_QUARANTINE != 0)
+    );
+    assert!(
+        !message
+            .read_message(&local.queue_receiver)
+            .await
+            .contains("X-Quarantine")
+    );
+}
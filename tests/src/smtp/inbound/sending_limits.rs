@@ -0,0 +1,169 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use crate::smtp::{TestSMTP, session::TestSession};
+use common::auth::oauth::GrantType;
+use smtp::core::sending_limits::SendingLimits;
+
+const DIRECTORY: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@foobar.org"
+
+[session.auth]
+mechanisms = "[plain]"
+directory = "'local'"
+
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+"#;
+
+const CAPS: &str = r#"
+[session.sending-limits]
+enable = true
+recipients.hourly = 5
+recipients.unique = 3
+
+[session.sending-limits.anomaly]
+spike.factor = false
+"#;
+
+const LOCKOUT: &str = r#"
+[session.sending-limits]
+enable = true
+
+[session.sending-limits.anomaly]
+spike.factor = 2
+spike.min-recipients = 6
+"#;
+
+#[tokio::test]
+async fn sending_limits_caps() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new(
+        "smtp_sending_limits_caps",
+        format!("{DIRECTORY}{CAPS}").as_str(),
+    )
+    .await;
+    let server = local.server.clone();
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "235 2.7.0")
+        .await;
+    let account_id = session.data.authenticated_as.as_ref().unwrap().primary_id();
+
+    // Distinct recipients are limited per day
+    session.mail_from("john@foobar.org", "250").await;
+    for rcpt in ["jane@remote.org", "bill@remote.org", "mike@remote.org"] {
+        session.rcpt_to(rcpt, "250").await;
+    }
+    session.rcpt_to("tom@remote.org", "451 4.7.1").await;
+    session.rset().await;
+
+    // Recipients are limited per hour
+    session.mail_from("john@foobar.org", "250").await;
+    session.rcpt_to("jane@remote.org", "250").await;
+    session.rcpt_to("bill@remote.org", "250").await;
+    session.rcpt_to("mike@remote.org", "451 4.7.1").await;
+    session.rset().await;
+
+    let status = server.sending_status(account_id).await.unwrap();
+    assert_eq!(status.recipients_hour, 5);
+    assert_eq!(status.recipients_day, 5);
+    assert_eq!(status.unique_recipients_day, 3);
+    assert_eq!(status.lockout, None);
+}
+
+#[tokio::test]
+async fn sending_limits_lockout() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new(
+        "smtp_sending_limits_lockout",
+        format!("{DIRECTORY}{LOCKOUT}").as_str(),
+    )
+    .await;
+    let server = local.server.clone();
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "235 2.7.0")
+        .await;
+    let account_id = session.data.authenticated_as.as_ref().unwrap().primary_id();
+
+    // Issue an OAuth token before the lockout
+    let token = server
+        .encode_access_token(GrantType::AccessToken, account_id, "test", 3600)
+        .await
+        .unwrap();
+    server
+        .validate_access_token(GrantType::AccessToken.into(), &token)
+        .await
+        .unwrap();
+
+    // Sudden spikes in volume lock the account out
+    session.mail_from("john@foobar.org", "250").await;
+    for num in 1..=5 {
+        session
+            .rcpt_to(&format!("user{num}@remote.org"), "250")
+            .await;
+    }
+    session.rcpt_to("user6@remote.org", "550 5.7.1").await;
+    session.rset().await;
+    let status = server.sending_status(account_id).await.unwrap();
+    assert_eq!(
+        status.lockout.map(|lockout| lockout.reason),
+        Some("sending-limit.volume-spike".to_string())
+    );
+
+    // Locked out accounts cannot send and their tokens are revoked
+    session.mail_from("john@foobar.org", "550 5.7.1").await;
+    assert!(
+        server
+            .validate_access_token(GrantType::AccessToken.into(), &token)
+            .await
+            .is_err()
+    );
+
+    // Tokens issued after the lockout are valid
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let token = server
+        .encode_access_token(GrantType::AccessToken, account_id, "test", 3600)
+        .await
+        .unwrap();
+    server
+        .validate_access_token(GrantType::AccessToken.into(), &token)
+        .await
+        .unwrap();
+
+    // Unlocked accounts can send again
+    assert!(server.sending_unlock(account_id).await.unwrap());
+    assert!(!server.sending_unlock(account_id).await.unwrap());
+    session.mail_from("john@foobar.org", "250").await;
+}
@@ -0,0 +1,308 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use crate::smtp::{TestSMTP, session::TestSession};
+use smtp::queue::suppression::{SuppressionList, SuppressionReason};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@foobar.org"
+
+[[directory."local".principals]]
+name = "jane"
+description = "Jane Doe"
+secret = "p4ssw0rd"
+email = "jane@foobar.org"
+
+[session.auth]
+mechanisms = "[plain]"
+directory = "'local'"
+
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+
+[queue.suppression]
+enable = true
+"#;
+
+const DSN: &str = r#"From: MAILER-DAEMON@remote.org
+To: {RCPT}
+Subject: Undelivered Mail Returned to Sender
+MIME-Version: 1.0
+Content-Type: multipart/report; report-type=delivery-status; boundary="dsn"
+
+--dsn
+Content-Type: text/plain
+
+Your message could not be delivered.
+
+--dsn
+Content-Type: message/delivery-status
+
+Reporting-MTA: dns; mx.remote.org
+
+Final-Recipient: rfc822; bill@remote.org
+Action: failed
+Status: 5.1.1
+Diagnostic-Code: smtp; 550 5.1.1 User unknown
+
+Final-Recipient: rfc822; mike@remote.org
+Action: failed
+Status: 5.1.1
+Diagnostic-Code: smtp; 550 5.1.1 User unknown
+
+--dsn
+Content-Type: text/rfc822-headers
+
+From: john@foobar.org
+To: bill@remote.org
+Subject: Hello
+Message-ID: <{MESSAGE_ID}>
+
+--dsn--
+"#;
+
+#[tokio::test]
+async fn suppression_bounces() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_suppression_bounces", CONFIG).await;
+    let server = local.server.clone();
+
+    // Send a message as an authenticated user
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "235 2.7.0")
+        .await;
+    session
+        .send_message(
+            "john@foobar.org",
+            &["bill@remote.org"],
+            "From: john@foobar.org\r\nTo: bill@remote.org\r\nSubject: Hello\r\nMessage-ID: <genuine@foobar.org>\r\n\r\nHello",
+            "250",
+        )
+        .await;
+
+    // Notifications are received from a remote host
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.2".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.remote.org").await;
+
+    // Forged notifications about messages that were never sent are ignored
+    for (rcpt, message_id) in [
+        ("john@foobar.org", "forged@foobar.org"),
+        ("jane@foobar.org", "genuine@foobar.org"),
+    ] {
+        session
+            .send_message(
+                "<>",
+                &[rcpt],
+                &DSN.replace("{RCPT}", rcpt)
+                    .replace("{MESSAGE_ID}", message_id),
+                "250",
+            )
+            .await;
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(server.suppression_list(None).await.unwrap(), vec![]);
+
+    // Genuine notifications only suppress the recipients of the original message
+    session
+        .send_message(
+            "<>",
+            &["john@foobar.org"],
+            &DSN.replace("{RCPT}", "john@foobar.org")
+                .replace("{MESSAGE_ID}", "genuine@foobar.org"),
+            "250",
+        )
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let entry = server
+        .suppression_get(None, "bill@remote.org")
+        .await
+        .unwrap()
+        .expect("Recipient was not suppressed");
+    assert_eq!(entry.status.as_deref(), Some("5.1.1"));
+    assert_eq!(
+        server
+            .suppression_get(None, "mike@remote.org")
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+const DSN_FIELDS: &str = r#"From: MAILER-DAEMON@remote.org
+To: john@foobar.org
+Subject: Delivery Status Notification (Failure)
+MIME-Version: 1.0
+Content-Type: multipart/report; report-type=delivery-status;
+	boundary="dsn"
+
+--dsn
+Content-Type: text/plain
+
+Your message could not be delivered.
+
+--dsn
+Content-Type: message/delivery-status
+
+Reporting-MTA: dns; mx.remote.org
+Arrival-Date: Mon, 1 Jan 2024 00:00:00 +0000
+
+Final-Recipient: rfc822; <Jane@Remote.org>
+Action: failed
+Status: 5.1.1
+Diagnostic-Code: smtp; 550 5.1.1 <jane@remote.org>:
+ Recipient address rejected: User unknown
+
+Original-Recipient: rfc822;bill@remote.org
+Final-Recipient: rfc822;william@remote.org
+Action: delayed
+Status: 4.2.2
+
+Final-Recipient: rfc822;tom@remote.org
+Action: Failed
+Status: 5.2.1
+
+Final-Recipient: rfc822;sam@remote.org
+Action: failed
+Status: 5.7.1
+
+Final-Recipient: rfc822;mike@remote.org
+Action: delivered
+Status: 2.0.0
+
+--dsn
+Content-Type: text/rfc822-headers
+
+From: john@foobar.org
+Subject: Hello
+Message-ID: <fields@foobar.org>
+
+--dsn--
+"#;
+
+#[tokio::test]
+async fn suppression_dsn_fields() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new(
+        "smtp_suppression_dsn_fields",
+        CONFIG.to_string() + "\n[queue.suppression.soft-bounce]\nlimit = \"1/1d\"\n",
+    )
+    .await;
+    let server = local.server.clone();
+
+    // Send a message as an authenticated user
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "235 2.7.0")
+        .await;
+    session
+        .send_message(
+            "john@foobar.org",
+            &[
+                "jane@remote.org",
+                "bill@remote.org",
+                "tom@remote.org",
+                "sam@remote.org",
+                "mike@remote.org",
+            ],
+            "From: john@foobar.org\r\nTo: jane@remote.org\r\nSubject: Hello\r\nMessage-ID: <fields@foobar.org>\r\n\r\nHello",
+            "250",
+        )
+        .await;
+
+    // Permanent failures are suppressed on the first notification, transient
+    // failures once the soft bounce limit is exceeded
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.2".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.remote.org").await;
+    session
+        .send_message("<>", &["john@foobar.org"], DSN_FIELDS, "250")
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let entries = server.suppression_list(None).await.unwrap();
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| (
+                entry.address.as_str(),
+                entry.reason,
+                entry.status.as_deref()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "jane@remote.org",
+                SuppressionReason::HardBounce,
+                Some("5.1.1")
+            ),
+            (
+                "tom@remote.org",
+                SuppressionReason::HardBounce,
+                Some("5.2.1")
+            ),
+        ]
+    );
+    let jane = &entries[0];
+    assert_eq!(
+        jane.diagnostic.as_deref(),
+        Some("550 5.1.1 <jane@remote.org>: Recipient address rejected: User unknown")
+    );
+    assert!(jane.expires.is_some_and(|expires| expires > jane.created));
+
+    session
+        .send_message("<>", &["john@foobar.org"], DSN_FIELDS, "250")
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(
+        server
+            .suppression_list(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.address, entry.reason))
+            .collect::<Vec<_>>(),
+        vec![
+            ("bill@remote.org".to_string(), SuppressionReason::SoftBounce),
+            ("jane@remote.org".to_string(), SuppressionReason::HardBounce),
+            ("sam@remote.org".to_string(), SuppressionReason::SoftBounce),
+            ("tom@remote.org".to_string(), SuppressionReason::HardBounce),
+        ]
+    );
+    for address in ["william@remote.org", "mike@remote.org"] {
+        assert_eq!(server.suppression_get(None, address).await.unwrap(), None);
+    }
+}
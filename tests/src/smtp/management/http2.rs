@@ -0,0 +1,219 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{sync::Arc, time::Duration};
+
+use common::{config::server::ServerProtocol, core::BuildServer};
+use futures::{SinkExt, StreamExt};
+use http_body_util::Empty;
+use hyper::{Method, Request, StatusCode, Version, body::Bytes, ext::Protocol};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use reqwest::header::AUTHORIZATION;
+use rustls_pki_types::ServerName;
+use services::state_manager::manager::spawn_push_router;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_tungstenite::{
+    WebSocketStream,
+    tungstenite::{Message, protocol::Role},
+};
+
+use crate::smtp::TestSMTP;
+
+const LOCAL: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@foobar.org"
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn manage_http2() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start local management interface with a push manager for WebSocket sessions
+    let mut local = TestSMTP::new("smtp_manage_http2", LOCAL).await;
+    let (inner, mut rxs) = local.inner_with_rxs();
+    inner
+        .data
+        .tls_certificates
+        .store(local.server.inner.data.tls_certificates.load_full());
+    spawn_push_router(inner.clone(), rxs.push_rx.take().unwrap());
+    local.server = inner.build_server();
+    let _rx = local.start(&[ServerProtocol::Http]).await;
+
+    // HTTP/2 is negotiated using ALPN on TLS listeners
+    for (client, expected_version) in [
+        (reqwest::Client::builder(), Version::HTTP_2),
+        (reqwest::Client::builder().http1_only(), Version::HTTP_11),
+    ] {
+        let response = client
+            .timeout(Duration::from_millis(500))
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap()
+            .get("https://127.0.0.1:9980/healthz/live")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.version(), expected_version);
+    }
+
+    // HTTP/2 with prior knowledge is rejected on plain text listeners unless h2c is enabled
+    let h2c = reqwest::Client::builder()
+        .timeout(Duration::from_millis(500))
+        .http2_prior_knowledge()
+        .build()
+        .unwrap();
+    assert!(
+        h2c.get("http://127.0.0.1:9981/healthz/live")
+            .send()
+            .await
+            .is_err()
+    );
+    let response = reqwest::get("http://127.0.0.1:9981/healthz/live")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.version(), Version::HTTP_11);
+
+    let old_core = local.server.inner.shared_core.load_full();
+    let mut new_core = old_core.as_ref().clone();
+    new_core.jmap.http2_h2c = true;
+    local.server.inner.shared_core.store(Arc::new(new_core));
+    let response = h2c
+        .get("http://127.0.0.1:9981/healthz/live")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.version(), Version::HTTP_2);
+    let response = reqwest::get("http://127.0.0.1:9981/healthz/live")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.version(), Version::HTTP_11);
+
+    // Disabling HTTP/2 applies to connections that negotiated it using ALPN or h2c
+    let mut new_core = old_core.as_ref().clone();
+    new_core.jmap.http2_enable = false;
+    new_core.jmap.http2_h2c = true;
+    local.server.inner.shared_core.store(Arc::new(new_core));
+    for (client, url, is_ok) in [
+        (
+            reqwest::Client::builder().http2_prior_knowledge(),
+            "http://127.0.0.1:9981/healthz/live",
+            false,
+        ),
+        (
+            reqwest::Client::builder().http2_prior_knowledge(),
+            "https://127.0.0.1:9980/healthz/live",
+            false,
+        ),
+        (
+            reqwest::Client::builder().http1_only(),
+            "https://127.0.0.1:9980/healthz/live",
+            true,
+        ),
+    ] {
+        let response = client
+            .timeout(Duration::from_millis(500))
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap()
+            .get(url)
+            .send()
+            .await;
+        assert_eq!(response.is_ok(), is_ok, "{url}: {response:?}");
+    }
+    local.server.inner.shared_core.store(old_core);
+
+    // WebSockets are bootstrapped over HTTP/2 using extended CONNECT (RFC 8441)
+    let mut tls_config = utils::rustls_client_config(true);
+    tls_config.alpn_protocols = vec![b"h2".to_vec()];
+    let stream = TlsConnector::from(Arc::new(tls_config))
+        .connect(
+            ServerName::try_from("127.0.0.1").unwrap().to_owned(),
+            TcpStream::connect("127.0.0.1:9980").await.unwrap(),
+        )
+        .await
+        .unwrap();
+    let (mut sender, conn) =
+        hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+            .await
+            .unwrap();
+    tokio::spawn(conn);
+
+    // Wait for the server settings enabling extended CONNECT
+    let response = sender
+        .send_request(
+            Request::get("https://127.0.0.1:9980/healthz/live")
+                .body(Empty::<Bytes>::new())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut request = Request::builder()
+        .method(Method::CONNECT)
+        .uri("https://127.0.0.1:9980/jmap/ws")
+        .header(AUTHORIZATION, "Basic am9objpzZWNyZXQ=")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Protocol", "jmap")
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(Protocol::from_static("websocket"));
+    let response = sender.send_request(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("Sec-WebSocket-Protocol")
+            .and_then(|h| h.to_str().ok()),
+        Some("jmap")
+    );
+
+    let mut ws_stream = WebSocketStream::from_raw_socket(
+        TokioIo::new(hyper::upgrade::on(response).await.unwrap()),
+        Role::Client,
+        None,
+    )
+    .await;
+    ws_stream
+        .send(Message::text(
+            r#"{"@type":"Request","id":"ws1","using":["urn:ietf:params:jmap:core"],"methodCalls":[["Core/echo",{"hello":"world"},"c0"]]}"#,
+        ))
+        .await
+        .unwrap();
+    let response = tokio::time::timeout(Duration::from_secs(1), ws_stream.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+        .into_text()
+        .unwrap();
+    let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+    assert_eq!(response["@type"], "Response", "{response}");
+    assert_eq!(response["requestId"], "ws1", "{response}");
+    assert_eq!(
+        response["methodResponses"][0],
+        serde_json::json!(["Core/echo", {"hello": "world"}, "c0"]),
+        "{response}"
+    );
+}
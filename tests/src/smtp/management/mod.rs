@@ -4,5 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod http2;
 pub mod queue;
 pub mod report;
+pub mod suppression;
+pub mod unix;
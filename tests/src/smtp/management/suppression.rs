@@ -0,0 +1,157 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use common::config::server::ServerProtocol;
+use directory::{
+    Type,
+    backend::internal::{PrincipalField, PrincipalSet, manage::ManageDirectory},
+};
+use serde_json::json;
+use smtp::queue::suppression::{Suppression, SuppressionList, SuppressionReason};
+
+use crate::{
+    jmap::{ManagementApi, server::enterprise::EnterpriseCore},
+    smtp::{TestSMTP, management::queue::List},
+};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "admin"
+type = "admin"
+description = "Superuser"
+secret = "secret"
+class = "admin"
+
+[queue.suppression]
+enable = true
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn manage_suppressions() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Tenants require the enterprise edition
+    let local = TestSMTP::new("smtp_manage_suppressions", CONFIG).await;
+    let server = local.build_smtp();
+    server
+        .inner
+        .shared_core
+        .store(Arc::new(server.core.as_ref().clone().enable_enterprise()));
+    let tenant_id = server
+        .store()
+        .create_principal(
+            PrincipalSet::new(0, Type::Tenant).with_field(PrincipalField::Name, "acme"),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .id;
+    let _rx = local.start(&[ServerProtocol::Http]).await;
+    let api = ManagementApi::default();
+
+    // System administrators manage the suppression list of a tenant with the
+    // tenant parameter
+    api.post::<()>(
+        "/api/queue/suppressions?tenant=acme",
+        &json!({"address": "Bill@Remote.org"}),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    api.post::<()>(
+        "/api/queue/suppressions",
+        &json!({"address": "jane@remote.org", "expires": "30d"}),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+
+    let entries = api
+        .get::<List<Suppression>>("/api/queue/suppressions?tenant=acme")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(entries.total, 1);
+    assert_eq!(entries.items[0].address, "bill@remote.org");
+    assert_eq!(entries.items[0].reason, SuppressionReason::Manual);
+    assert_eq!(entries.items[0].expires, None);
+    let entries = api
+        .get::<List<Suppression>>("/api/queue/suppressions")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(entries.total, 1);
+    assert_eq!(entries.items[0].address, "jane@remote.org");
+    assert!(
+        entries.items[0]
+            .expires
+            .is_some_and(|expires| expires == entries.items[0].created + 30 * 86400)
+    );
+
+    assert_eq!(
+        server
+            .suppression_get(Some(tenant_id), "bill@remote.org")
+            .await
+            .unwrap()
+            .map(|entry| entry.address),
+        Some("bill@remote.org".to_string())
+    );
+    assert_eq!(
+        server
+            .suppression_get(None, "bill@remote.org")
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        api.get::<Suppression>("/api/queue/suppressions/bill@remote.org?tenant=acme")
+            .await
+            .unwrap()
+            .unwrap_data()
+            .reason,
+        SuppressionReason::Manual
+    );
+    assert_eq!(
+        api.get::<Suppression>("/api/queue/suppressions/bill@remote.org")
+            .await
+            .unwrap()
+            .unwrap_error()
+            .0,
+        "notFound"
+    );
+
+    // Unknown tenants are not silently mapped to the system list
+    assert_eq!(
+        api.get::<List<Suppression>>("/api/queue/suppressions?tenant=unknown")
+            .await
+            .unwrap()
+            .unwrap_error()
+            .0,
+        "notFound"
+    );
+
+    // Remove entries
+    api.delete::<()>("/api/queue/suppressions/bill@remote.org?tenant=acme")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(
+        server.suppression_list(Some(tenant_id)).await.unwrap(),
+        vec![]
+    );
+    assert_eq!(server.suppression_list(None).await.unwrap().len(), 1);
+}
@@ -0,0 +1,321 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::Path,
+};
+
+use common::config::server::{Listeners, ServerProtocol};
+use http::HttpSessionManager;
+use smtp::core::SmtpSessionManager;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{
+        UnixStream,
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    sync::watch,
+};
+use utils::config::Config;
+
+use crate::{
+    AssertConfig,
+    smtp::{TestSMTP, inbound::TestMessage},
+};
+
+const HTTP_LISTENERS: &str = r#"
+[server.listener.unix-denied]
+bind = ['unix:{TMP}/denied.sock']
+protocol = 'http'
+
+[server.listener.unix-allowed]
+bind = ['unix:{TMP}/allowed.sock']
+protocol = 'http'
+"#;
+
+const HTTP_CONFIG: &str = r#"
+[http]
+allowed-endpoint = [ { if = "listener == 'unix-denied' && peer_uid == {UID}", then = 403 },
+                     { else = 200 } ]
+"#;
+
+const SESSION_LISTENERS: &str = r#"
+[server.listener.unix-smtp]
+bind = ['unix:{TMP}/smtp.sock']
+protocol = 'smtp'
+unix.mode = '640'
+unix.owner = '{UID}'
+unix.group = '{GID}'
+
+[server.listener.unix-lmtp]
+bind = ['unix:{TMP}/lmtp.sock']
+protocol = 'lmtp'
+
+[server.listener.unix-invalid]
+bind = ['unix:{TMP}/invalid.sock']
+protocol = 'lmtp'
+unix.mode = '999'
+"#;
+
+const SESSION_CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@foobar.org"
+
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+
+[server.blocked-ip]
+"0.0.0.0/8" = ""
+
+[auth.iprev]
+verify = "disable"
+
+[auth.spf.verify]
+ehlo = "disable"
+mail-from = "disable"
+
+[auth.dmarc]
+verify = "disable"
+
+[auth.dkim]
+verify = "disable"
+
+[auth.arc]
+verify = "disable"
+
+[spam-filter]
+enable = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn unix_peer_access() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Deny the uid this process is running as
+    let uid = std::fs::metadata("/proc/self").unwrap().uid();
+    let local = TestSMTP::new(
+        "smtp_unix_peer_access",
+        HTTP_CONFIG.replace("{UID}", &uid.to_string()),
+    )
+    .await;
+    let temp_dir = local.temp_dir.as_ref().unwrap();
+
+    // Spawn Unix socket listeners
+    let mut config = Config::new(temp_dir.update_config(HTTP_LISTENERS)).unwrap();
+    let _rx = spawn_listeners(&local, &mut config);
+    config.assert_no_errors();
+
+    // Unix peers must not be treated as loopback clients, a denied uid is rejected
+    assert_eq!(
+        http_get(
+            &temp_dir.temp_dir.join("denied.sock"),
+            "/api/queue/messages"
+        )
+        .await,
+        403
+    );
+
+    // Other peers reach the endpoint and are asked to authenticate
+    assert_eq!(
+        http_get(
+            &temp_dir.temp_dir.join("allowed.sock"),
+            "/api/queue/messages"
+        )
+        .await,
+        401
+    );
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn unix_session_listeners() {
+    // Enable logging
+    crate::enable_logging();
+
+    let mut local = TestSMTP::new("smtp_unix_session_listeners", SESSION_CONFIG).await;
+    let temp_dir = local.temp_dir.as_ref().unwrap().temp_dir.clone();
+    let metadata = std::fs::metadata(&temp_dir).unwrap();
+
+    // Spawn Unix socket listeners, invalid file modes are reported
+    let mut config = Config::new(
+        local
+            .temp_dir
+            .as_ref()
+            .unwrap()
+            .update_config(SESSION_LISTENERS)
+            .replace("{UID}", &metadata.uid().to_string())
+            .replace("{GID}", &metadata.gid().to_string()),
+    )
+    .unwrap();
+    let _rx = spawn_listeners(&local, &mut config);
+    assert!(
+        config
+            .errors
+            .contains_key("server.listener.unix-invalid.unix.mode")
+    );
+
+    // Sockets are created with the configured mode and owner
+    let socket = std::fs::metadata(temp_dir.join("smtp.sock")).unwrap();
+    assert_eq!(socket.permissions().mode() & 0o7777, 0o640);
+    assert_eq!(socket.uid(), metadata.uid());
+    assert_eq!(socket.gid(), metadata.gid());
+
+    // Messages are accepted over SMTP, Unix peers are exempt from blocked networks
+    let mut client = UnixClient::connect(&temp_dir.join("smtp.sock")).await;
+    client.expect("220").await;
+    client.send("EHLO mx.foobar.org").await;
+    client.expect("250").await;
+    client.send("MAIL FROM:<bill@foobar.net>").await;
+    client.expect("250").await;
+    client.send("RCPT TO:<jane@foobar.net>").await;
+    client.expect("250").await;
+    client.send("DATA").await;
+    client.expect("354").await;
+    client
+        .send("From: bill@foobar.net\r\nSubject: SMTP\r\n\r\nHello\r\n.")
+        .await;
+    client.expect("250").await;
+    client.send("QUIT").await;
+    client.expect("221").await;
+    let message = local.queue_receiver.expect_message().await;
+    assert_eq!(message.message.recipients[0].address(), "jane@foobar.net");
+    assert!(
+        message
+            .read_message(&local.queue_receiver)
+            .await
+            .contains("Subject: SMTP")
+    );
+
+    // Messages are accepted over LMTP
+    let mut client = UnixClient::connect(&temp_dir.join("lmtp.sock")).await;
+    client.expect("220").await;
+    client.send("LHLO mx.foobar.org").await;
+    client.expect("250").await;
+    client.send("MAIL FROM:<bill@foobar.net>").await;
+    client.expect("250").await;
+    client.send("RCPT TO:<jane@foobar.net>").await;
+    client.expect("250").await;
+    client.send("DATA").await;
+    client.expect("354").await;
+    client
+        .send("From: bill@foobar.net\r\nSubject: LMTP\r\n\r\nHello\r\n.")
+        .await;
+    client.expect("250").await;
+    client.send("QUIT").await;
+    client.expect("221").await;
+    let message = local.queue_receiver.expect_message().await;
+    assert_eq!(message.message.recipients[0].address(), "jane@foobar.net");
+    assert!(
+        message
+            .read_message(&local.queue_receiver)
+            .await
+            .contains("Subject: LMTP")
+    );
+}
+
+fn spawn_listeners(local: &TestSMTP, config: &mut Config) -> watch::Sender<bool> {
+    let mut servers = Listeners::parse(config);
+    servers.parse_tcp_acceptors(config, local.server.inner.clone());
+    servers.bind_and_drop_priv(config);
+    servers
+        .spawn(|server, acceptor, shutdown_rx| {
+            let inner = local.server.inner.clone();
+            match &server.protocol {
+                ServerProtocol::Smtp | ServerProtocol::Lmtp => server.spawn(
+                    SmtpSessionManager::new(inner.clone()),
+                    inner,
+                    acceptor,
+                    shutdown_rx,
+                ),
+                ServerProtocol::Http => server.spawn(
+                    HttpSessionManager::new(inner.clone()),
+                    inner,
+                    acceptor,
+                    shutdown_rx,
+                ),
+                ServerProtocol::Imap | ServerProtocol::Pop3 | ServerProtocol::ManageSieve => {
+                    unreachable!()
+                }
+            };
+        })
+        .0
+}
+
+struct UnixClient {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+}
+
+impl UnixClient {
+    async fn connect(path: &Path) -> Self {
+        let (reader, writer) = UnixStream::connect(path).await.unwrap().into_split();
+        UnixClient {
+            reader: BufReader::new(reader),
+            writer,
+        }
+    }
+
+    async fn send(&mut self, line: &str) {
+        self.writer
+            .write_all(format!("{line}\r\n").as_bytes())
+            .await
+            .unwrap();
+    }
+
+    // Reads a reply, the last line of multi-line SMTP replies has no dash after the code
+    async fn expect(&mut self, code: &str) {
+        loop {
+            let line = self.read_line().await;
+            if line.as_bytes().get(3) != Some(&b'-') {
+                assert!(line.starts_with(code), "Expected {code:?}, got {line:?}");
+                break;
+            }
+        }
+    }
+
+    async fn read_line(&mut self) -> String {
+        let mut line = String::new();
+        assert_ne!(
+            self.reader.read_line(&mut line).await.unwrap(),
+            0,
+            "Connection closed"
+        );
+        line
+    }
+}
+
+async fn http_get(path: &Path, uri: &str) -> u16 {
+    let mut stream = UnixStream::connect(path).await.unwrap();
+    stream
+        .write_all(
+            format!("GET {uri} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .as_bytes(),
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .unwrap_or_else(|| panic!("Invalid HTTP response: {response:?}"))
+}
@@ -124,6 +124,10 @@ bind = ['127.0.0.1:9980']
 protocol = 'http'
 tls.implicit = true
 
+[server.listener.http-debug]
+bind = ['127.0.0.1:9981']
+protocol = 'http'
+
 [server.socket]
 reuse-addr = true
 
@@ -0,0 +1,200 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant, SystemTime};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+use smtp::outbound::adaptive::throttle_destination;
+use store::write::now;
+
+use crate::smtp::{DnsCache, TestSMTP, inbound::TestQueueEvent, session::TestSession};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+
+[queue.adaptive]
+enable = true
+concurrency.min = 1
+concurrency.max = 4
+rate.min = "100/1s"
+rate.max = "100/1s"
+cooldown = "10ms"
+recovery = "500ms"
+retry = "1s"
+
+[spam-filter]
+enable = false
+"#;
+
+const REMOTE: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+
+[spam-filter]
+enable = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn adaptive_throttling() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_adaptive_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+
+    // Add mock DNS entries
+    let mut local = TestSMTP::new("smtp_adaptive_local", LOCAL).await;
+    let core = local.build_smtp();
+    core.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    // MX hosts of the same provider share their limits
+    assert_eq!(throttle_destination("mx.foobar.org"), "foobar.org");
+    assert_eq!(
+        throttle_destination("mx1.mail.foobar.co.uk."),
+        "foobar.co.uk"
+    );
+    assert_eq!(throttle_destination("localhost"), "localhost");
+
+    // Only temporary failures that indicate throttling count as deferrals
+    let adaptive = &core.core.smtp.adaptive;
+    assert!(adaptive.is_deferral(421, [4, 3, 2], "Service not available"));
+    assert!(adaptive.is_deferral(452, [4, 4, 5], "Mailbox full"));
+    assert!(adaptive.is_deferral(450, [4, 7, 1], "Policy"));
+    assert!(adaptive.is_deferral(451, [4, 5, 3], "Try again LATER"));
+    assert!(!adaptive.is_deferral(451, [4, 3, 0], "Temporary lookup failure"));
+    assert!(!adaptive.is_deferral(550, [5, 7, 1], "Too many recipients"));
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Three other deliveries to the destination are in progress
+    let throttle = &core.inner.data.queue_throttle;
+    let permits = (0..3)
+        .map(|_| throttle.acquire(adaptive, "foobar.org", now_ms()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(throttle.limited_count(), 0);
+
+    // A 421 deferral halves the concurrency of the destination
+    session
+        .send_message(
+            "john@test.org",
+            &["throttle@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_refresh();
+    remote.queue_receiver.assert_no_events();
+    local.queue_receiver.clear_queue(&core).await;
+    assert_eq!(limits(&core), (Some(2), 1));
+
+    // Deliveries over the concurrency limit are rescheduled without connecting
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_refresh();
+    remote.queue_receiver.assert_no_events();
+    assert!(local.queue_receiver.last_queued_due().await <= now() + 1);
+    local.queue_receiver.clear_queue(&core).await;
+
+    // Further deferrals after the cooldown lower the concurrency to the minimum
+    drop(permits);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    for expected_deferrals in [2, 3] {
+        session
+            .send_message(
+                "john@test.org",
+                &["throttle@foobar.org"],
+                "test:no_dkim",
+                "250",
+            )
+            .await;
+        local
+            .queue_receiver
+            .expect_message_then_deliver()
+            .await
+            .try_deliver(core.clone());
+        local.queue_receiver.read_event().await.assert_refresh();
+        local.queue_receiver.clear_queue(&core).await;
+        assert_eq!(limits(&core), (Some(1), expected_deferrals));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    remote.queue_receiver.assert_no_events();
+
+    // Successful deliveries raise the concurrency one step per recovery period
+    // until the limits are lifted
+    for expected_concurrency in [Some(2), Some(3), None] {
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        session
+            .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+            .await;
+        local
+            .queue_receiver
+            .expect_message_then_deliver()
+            .await
+            .try_deliver(core.clone());
+        remote.queue_receiver.expect_message().await;
+        local.queue_receiver.read_event().await.assert_done();
+        assert_eq!(
+            throttle
+                .limited()
+                .first()
+                .and_then(|(_, limits)| limits.concurrency),
+            expected_concurrency
+        );
+    }
+    assert_eq!(throttle.limited_count(), 0);
+}
+
+fn limits(core: &common::Server) -> (Option<u32>, u64) {
+    let limited = core.inner.data.queue_throttle.limited();
+    assert_eq!(limited.len(), 1);
+    assert_eq!(limited[0].0.name, "foobar.org");
+    (limited[0].1.concurrency, limited[0].1.deferrals)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::UNIX_EPOCH
+        .elapsed()
+        .map_or(0, |time| time.as_millis() as u64)
+}
@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod adaptive;
 pub mod dane;
 pub mod extensions;
 pub mod fallback_relay;
@@ -13,3 +14,4 @@ pub mod mta_sts;
 pub mod smtp;
 pub mod throttle;
 pub mod tls;
+pub mod warmup;
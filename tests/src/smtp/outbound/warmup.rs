@@ -0,0 +1,122 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+use smtp::outbound::warmup::IpWarmup;
+use store::write::now;
+
+use crate::smtp::{DnsCache, TestSMTP, inbound::TestQueueEvent, session::TestSession};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+
+[queue.connection.default]
+ehlo-hostname = "mx.test.org"
+source-ips = ["127.0.0.1"]
+
+[queue.source-ip."127.0.0.1".warmup]
+schedule = [2, 10]
+overflow = "defer"
+
+[spam-filter]
+enable = false
+"#;
+
+const REMOTE: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+
+[spam-filter]
+enable = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn warmup_throttling() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_warmup_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+
+    // Add mock DNS entries
+    let mut local = TestSMTP::new("smtp_warmup_local", LOCAL).await;
+    let core = local.build_smtp();
+    core.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Messages are delivered until the first daily limit is reached
+    for _ in 0..2 {
+        session
+            .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+            .await;
+        local
+            .queue_receiver
+            .expect_message_then_deliver()
+            .await
+            .try_deliver(core.clone());
+        remote.queue_receiver.expect_message().await;
+        local.queue_receiver.read_event().await.assert_done();
+    }
+    let (ip, schedule) = core.warmup_schedules()[0];
+    let status = core.warmup_status(ip, schedule).await.unwrap();
+    assert_eq!(status.day, 0);
+    assert_eq!(status.daily_limit, Some(2));
+    assert_eq!(status.sent_today, 2);
+    assert!(!status.is_warm);
+
+    // Further messages are deferred until the next day
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_refresh();
+    remote.queue_receiver.assert_no_events();
+    assert_eq!(
+        local.queue_receiver.last_queued_due().await,
+        (now() / 86400 + 1) * 86400
+    );
+
+    // Resetting the warm-up starts the schedule over
+    assert!(core.warmup_reset(ip).await.unwrap());
+    let status = core.warmup_status(ip, schedule).await.unwrap();
+    assert_eq!(status.started, None);
+    assert!(!core.warmup_reset(ip).await.unwrap());
+}
@@ -0,0 +1,169 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use crate::smtp::{TestSMTP, session::TestSession};
+use smtp::reporting::complaints::Complaints;
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@foobar.org"
+
+[session.auth]
+mechanisms = "[plain]"
+directory = "'local'"
+
+[session.rcpt]
+relay = true
+
+[session.data.limits]
+messages = 100
+
+[report.analysis]
+addresses = ["feedback@foobar.org"]
+forward = false
+
+[report.analysis.complaints]
+enable = true
+sources = ["10.0.1.0/24"]
+suspend.enable = true
+suspend.rate = 0.5
+suspend.min-messages = 2
+"#;
+
+const ARF: &str = r#"From: feedback@fbl.remote.org
+To: feedback@foobar.org
+Subject: Abuse report
+MIME-Version: 1.0
+Content-Type: multipart/report; report-type=feedback-report; boundary="arf"
+
+--arf
+Content-Type: text/plain
+
+This is an abuse report.
+
+--arf
+Content-Type: message/feedback-report
+
+Feedback-Type: abuse
+User-Agent: FBL/1.0
+Version: 1
+Incidents: 1000
+
+--arf
+Content-Type: message/rfc822
+
+From: john@foobar.org
+To: bill@remote.org
+Subject: Hello
+Message-ID: <{MESSAGE_ID}>
+
+Hello
+
+--arf--
+"#;
+
+#[tokio::test]
+async fn report_complaints() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_report_complaints", CONFIG).await;
+    let server = local.server.clone();
+
+    // Send two messages as an authenticated user
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "235 2.7.0")
+        .await;
+    let account_id = session.data.authenticated_as.as_ref().unwrap().primary_id();
+    for num in 1..=2 {
+        session
+            .send_message(
+                "john@foobar.org",
+                &["bill@remote.org"],
+                &format!(
+                    "From: john@foobar.org\r\nTo: bill@remote.org\r\nSubject: Hello\r\nMessage-ID: <msg{num}@foobar.org>\r\n\r\nHello"
+                ),
+                "250",
+            )
+            .await;
+    }
+    let stats = server.complaint_stats_account(account_id).await.unwrap();
+    assert_eq!(stats.sent, 2);
+    assert_eq!(stats.complaints, 0);
+
+    // Reports from untrusted sources that are not DKIM authenticated are ignored
+    let mut untrusted = local.new_session();
+    untrusted.data.remote_ip_str = "10.0.0.2".into();
+    untrusted.data.remote_ip = untrusted.data.remote_ip_str.parse().unwrap();
+    untrusted.eval_session_params().await;
+    untrusted.ehlo("mx.remote.org").await;
+    untrusted
+        .send_message(
+            "feedback@fbl.remote.org",
+            &["feedback@foobar.org"],
+            &ARF.replace("{MESSAGE_ID}", "msg1@foobar.org"),
+            "250",
+        )
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let stats = server.complaint_stats_account(account_id).await.unwrap();
+    assert_eq!(stats.complaints, 0);
+    assert_eq!(stats.suspended, Some(false));
+
+    // Reports from feedback loop sources count as a single complaint
+    let mut trusted = local.new_session();
+    trusted.data.remote_ip_str = "10.0.1.1".into();
+    trusted.data.remote_ip = trusted.data.remote_ip_str.parse().unwrap();
+    trusted.eval_session_params().await;
+    trusted.ehlo("fbl.remote.org").await;
+    trusted
+        .send_message(
+            "feedback@fbl.remote.org",
+            &["feedback@foobar.org"],
+            &ARF.replace("{MESSAGE_ID}", "msg1@foobar.org"),
+            "250",
+        )
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let stats = server.complaint_stats_account(account_id).await.unwrap();
+    assert_eq!(stats.complaints, 1);
+    assert_eq!(stats.suspended, Some(false));
+
+    // Accounts going over the complaint rate are suspended
+    trusted
+        .send_message(
+            "feedback@fbl.remote.org",
+            &["feedback@foobar.org"],
+            &ARF.replace("{MESSAGE_ID}", "msg2@foobar.org"),
+            "250",
+        )
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let stats = server.complaint_stats_account(account_id).await.unwrap();
+    assert_eq!(stats.complaints, 2);
+    assert_eq!(stats.suspended, Some(true));
+    session.mail_from("john@foobar.org", "550 5.7.1").await;
+
+    // Released accounts can send again
+    assert!(server.sending_release(account_id).await.unwrap());
+    session.mail_from("john@foobar.org", "250").await;
+}
@@ -5,6 +5,7 @@
  */
 
 pub mod analyze;
+pub mod complaints;
 pub mod dmarc;
 pub mod scheduler;
 pub mod tls;